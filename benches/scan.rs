@@ -0,0 +1,82 @@
+//! Benchmark the scan (disk usage and code stats) against a synthetic tree, see
+//! the hidden command `roxide debug bench-scan`.
+//!
+//! The shape of the tree can be changed by env `ROXIDE_BENCH_REPOS`,
+//! `ROXIDE_BENCH_DEPTH`, `ROXIDE_BENCH_WIDTH`, `ROXIDE_BENCH_FILES` and
+//! `ROXIDE_BENCH_FILE_SIZE`. Since roxide has no library target, the binary is
+//! run for each sample; the process startup is the same for all the cases, so
+//! comparing `direct` with `batch` still shows the overhead of the dispatcher.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const TREE_ARGS: [(&str, &str, &str); 5] = [
+    ("--repos", "ROXIDE_BENCH_REPOS", "16"),
+    ("--depth", "ROXIDE_BENCH_DEPTH", "2"),
+    ("--width", "ROXIDE_BENCH_WIDTH", "4"),
+    ("--files", "ROXIDE_BENCH_FILES", "8"),
+    ("--file-size", "ROXIDE_BENCH_FILE_SIZE", "4K"),
+];
+
+struct Bench {
+    root: PathBuf,
+    tree: String,
+}
+
+impl Bench {
+    fn setup() -> Bench {
+        let root = env::temp_dir().join("roxide-bench");
+        // An empty config dir, so that the user's config does not affect the
+        // results.
+        fs::create_dir_all(root.join("config")).unwrap();
+        let tree = format!("{}", root.join("tree").display());
+
+        let mut args = vec!["--generate", "-n", "0"];
+        let values: Vec<String> = TREE_ARGS
+            .iter()
+            .map(|(_, env, default)| env::var(env).unwrap_or(default.to_string()))
+            .collect();
+        for ((flag, _, _), value) in TREE_ARGS.iter().zip(values.iter()) {
+            args.push(flag);
+            args.push(value);
+        }
+        let bench = Bench { root, tree };
+        bench.run(&args);
+        bench
+    }
+
+    fn run(&self, args: &[&str]) {
+        let status = Command::new(env!("CARGO_BIN_EXE_roxide"))
+            .args(["debug", "bench-scan", &self.tree])
+            .args(args)
+            .env("ROXIDE_CONFIG", self.root.join("config"))
+            .env("ROXIDE_QUIET", "true")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "roxide debug bench-scan {args:?} failed");
+    }
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let bench = Bench::setup();
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+    for mode in ["usage", "stats"] {
+        for dispatch in ["direct", "batch"] {
+            let args = ["-n", "1", "-m", mode, "-d", dispatch];
+            group.bench_function(format!("{mode}/{dispatch}"), |b| {
+                b.iter(|| bench.run(&args))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_scan);
+criterion_main!(benches);
@@ -18,7 +18,7 @@ _roxide_home() {
 _roxide_base() {
 	action=$1
 	case "${action}" in
-		home | copy)
+		home | copy | recent | jump)
 			_roxide_home "$@"
 			;;
 
@@ -28,3 +28,21 @@ _roxide_base() {
 	esac
 	return $?
 }
+
+# Record the visited directories for `roxide jump`, and report them to
+# `roxide watch`, the visits file only exists when the watcher is running.
+_roxide_visit() {
+	if [ "$PWD" != "$_roxide_last_pwd" ]; then
+		_roxide_last_pwd="$PWD"
+		(roxide jump --add "$PWD" >/dev/null 2>&1 &)
+		if [ -f _roxide_visits_file ]; then
+			echo "$PWD" >> _roxide_visits_file
+		fi
+	fi
+}
+
+if [ -n "$ZSH_VERSION" ]; then
+	precmd_functions+=(_roxide_visit)
+else
+	PROMPT_COMMAND="_roxide_visit${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
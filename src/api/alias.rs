@@ -28,6 +28,15 @@ impl Provider for Alias {
         Ok(names)
     }
 
+    fn list_owners(&self) -> Result<Vec<String>> {
+        let owners = self.upstream.list_owners()?;
+        let owners = owners
+            .into_iter()
+            .map(|owner| self.raw_owner(owner))
+            .collect();
+        Ok(owners)
+    }
+
     fn get_repo(&self, raw_owner: &str, raw_name: &str) -> Result<ApiRepo> {
         let owner = self.alias_owner(raw_owner);
         let name = self.alias_repo(owner, raw_name);
@@ -47,8 +56,7 @@ impl Provider for Alias {
     fn create_merge(
         &mut self,
         mut merge: MergeOptions,
-        title: String,
-        body: String,
+        create: CreateMergeOptions,
     ) -> Result<String> {
         let owner = self.alias_owner(&merge.owner);
         let name = self.alias_repo(owner, &merge.name);
@@ -56,13 +64,59 @@ impl Provider for Alias {
         merge.owner = owner.to_string();
         merge.name = name.to_string();
 
-        self.upstream.create_merge(merge, title, body)
+        self.upstream.create_merge(merge, create)
+    }
+
+    fn accept_merge(&mut self, mut merge: MergeOptions, method: MergeMethod) -> Result<String> {
+        let owner = self.alias_owner(&merge.owner);
+        let name = self.alias_repo(owner, &merge.name);
+
+        merge.owner = owner.to_string();
+        merge.name = name.to_string();
+
+        self.upstream.accept_merge(merge, method)
+    }
+
+    fn approve_merge(&mut self, mut merge: MergeOptions) -> Result<String> {
+        let owner = self.alias_owner(&merge.owner);
+        let name = self.alias_repo(owner, &merge.name);
+
+        merge.owner = owner.to_string();
+        merge.name = name.to_string();
+
+        self.upstream.approve_merge(merge)
+    }
+
+    fn list_notifications(&self, all: bool) -> Result<Vec<Notification>> {
+        self.upstream.list_notifications(all)
+    }
+
+    fn mark_notification_read(&mut self, id: &str) -> Result<()> {
+        self.upstream.mark_notification_read(id)
     }
 
     fn search_repos(&self, query: &str) -> Result<Vec<String>> {
         self.upstream.search_repos(query)
     }
 
+    fn rename_repo(&mut self, raw_owner: &str, raw_name: &str, new_name: &str) -> Result<()> {
+        let owner = self.alias_owner(raw_owner).to_string();
+        let name = self.alias_repo(&owner, raw_name).to_string();
+        self.upstream.rename_repo(&owner, &name, new_name)
+    }
+
+    fn create_release(
+        &mut self,
+        raw_owner: &str,
+        raw_name: &str,
+        tag: &str,
+        body: String,
+    ) -> Result<String> {
+        let owner = self.alias_owner(raw_owner).to_string();
+        let name = self.alias_repo(&owner, raw_name).to_string();
+        self.upstream.create_release(&owner, &name, tag, body)
+    }
+
     fn get_action(&self, opts: &ActionOptions) -> Result<Option<Action>> {
         self.upstream.get_action(opts)
     }
@@ -74,6 +128,12 @@ impl Provider for Alias {
     fn get_job(&self, owner: &str, name: &str, id: u64) -> Result<ActionJob> {
         self.upstream.get_job(owner, name, id)
     }
+
+    fn get_issue(&self, raw_owner: &str, raw_name: &str, id: u64) -> Result<ApiIssue> {
+        let owner = self.alias_owner(raw_owner);
+        let name = self.alias_repo(owner, raw_name);
+        self.upstream.get_issue(owner, name, id)
+    }
 }
 
 impl Alias {
@@ -108,6 +168,14 @@ impl Alias {
         }
     }
 
+    fn raw_owner(&self, owner: String) -> String {
+        self.owner_map
+            .iter()
+            .find(|(_, name)| **name == owner)
+            .map(|(raw, _)| raw.clone())
+            .unwrap_or(owner)
+    }
+
     fn alias_repo<'a>(&'a self, owner: &str, name: &'a str) -> &'a str {
         if let Some(map) = self.repo_map.get(owner) {
             if let Some(name) = map.get(name) {
@@ -174,6 +242,10 @@ mod tests {
             .collect();
         assert_eq!(result, expect);
 
+        let owners = alias.list_owners().unwrap();
+        assert!(owners.contains(&String::from("test-alias")));
+        assert!(!owners.contains(&String::from("fioncat")));
+
         alias.get_repo("test-alias", "vim").unwrap();
         alias.get_repo("test-alias", "ro").unwrap();
         alias.get_repo("kubernetes", "k8s").unwrap();
@@ -186,7 +258,7 @@ mod tests {
             target: "main".to_string(),
         };
         let result = alias
-            .create_merge(merge.clone(), String::new(), String::new())
+            .create_merge(merge.clone(), CreateMergeOptions::default())
             .unwrap();
         assert_eq!(result, "fioncat/roxide");
 
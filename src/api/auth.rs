@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use console::style;
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::api::build_common_client;
+use crate::config::{Config, OAuthConfig, RemoteConfig};
+use crate::errors::ErrorKind;
+use crate::filelock::FileLock;
+use crate::{debug, secret, timings, utils};
+
+/// The token saved by the `login` command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginToken {
+    pub token: String,
+
+    /// Only the expiring tokens (of GitHub App) have this.
+    pub refresh_token: Option<String>,
+
+    /// The unix time when the token expires, [`None`] means never.
+    pub expires_at: Option<u64>,
+    pub refresh_expires_at: Option<u64>,
+}
+
+impl LoginToken {
+    /// Treat the token as expired a little earlier, so that it won't expire
+    /// during the requests.
+    const EXPIRE_MARGIN: u64 = 60;
+
+    pub fn new(token: String) -> LoginToken {
+        LoginToken {
+            token,
+            refresh_token: None,
+            expires_at: None,
+            refresh_expires_at: None,
+        }
+    }
+
+    fn is_expired(expires_at: Option<u64>, now: u64) -> bool {
+        matches!(expires_at, Some(expires_at) if now + Self::EXPIRE_MARGIN >= expires_at)
+    }
+}
+
+/// The tokens saved by the `login` command, stored in `{metadir}/tokens` with
+/// the mode `0600`. The file is encrypted with the secret password (see
+/// [`secret::read_secret`]), which is inputted once per process. The tokens are
+/// only used when the remote has no `token` in config.
+pub struct TokenStore {
+    path: PathBuf,
+}
+
+impl TokenStore {
+    pub fn new(cfg: &Config) -> TokenStore {
+        TokenStore {
+            path: cfg.get_meta_dir().join("tokens"),
+        }
+    }
+
+    pub fn get(&self, cfg: &Config, remote: &str) -> Result<Option<LoginToken>> {
+        let (mut tokens, encrypted) = self.load()?;
+        if !encrypted && !tokens.is_empty() {
+            // The tokens file written by the old versions is plain, encrypt it
+            // rather than keeping reading it.
+            let _lock = FileLock::acquire(cfg, "tokens")?;
+            self.write(&tokens)?;
+        }
+        Ok(tokens.remove(remote))
+    }
+
+    pub fn save(&self, cfg: &Config, remote: &str, token: &LoginToken) -> Result<()> {
+        let _lock = FileLock::acquire(cfg, "tokens")?;
+        let (mut tokens, _) = self.load()?;
+        tokens.insert(remote.to_string(), token.clone());
+        self.write(&tokens)
+    }
+
+    /// Remove the token, return `false` if the remote is not logged in.
+    pub fn remove(&self, cfg: &Config, remote: &str) -> Result<bool> {
+        let _lock = FileLock::acquire(cfg, "tokens")?;
+        let (mut tokens, _) = self.load()?;
+        if tokens.remove(remote).is_none() {
+            return Ok(false);
+        }
+        self.write(&tokens)?;
+        Ok(true)
+    }
+
+    /// Load the tokens, also return whether the file is encrypted.
+    fn load(&self) -> Result<(HashMap<String, LoginToken>, bool)> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((HashMap::new(), true)),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("read tokens file '{}'", self.path.display()))
+            }
+        };
+        if !secret::is_secret_data(&data) {
+            let tokens = bincode::deserialize(&data).context("decode tokens")?;
+            return Ok((tokens, false));
+        }
+        let data = secret::decrypt_data(&data)
+            .with_context(|| format!("decrypt tokens file '{}'", self.path.display()))?;
+        let tokens = bincode::deserialize(&data).context("decode tokens")?;
+        Ok((tokens, true))
+    }
+
+    /// Write the tokens encrypted, there is no plain fallback: if the password
+    /// cannot be inputted, the write fails.
+    fn write(&self, tokens: &HashMap<String, LoginToken>) -> Result<()> {
+        let data = bincode::serialize(tokens).context("encode tokens")?;
+        let data = secret::encrypt_data(&data).context("encrypt tokens")?;
+        utils::write_private_file(&self.path, &data)
+    }
+}
+
+/// Return the token to call the remote api: the `token` in config first, then
+/// the one saved by the `login` command. The expired token is refreshed if
+/// possible.
+pub fn resolve_token(cfg: &Config, remote_cfg: &RemoteConfig) -> Result<Option<String>> {
+    if remote_cfg.token.is_some() {
+        return Ok(remote_cfg.token.clone());
+    }
+    let remote = remote_cfg.get_name();
+    let store = TokenStore::new(cfg);
+    let token = match store.get(cfg, remote)? {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+    let now = cfg.now();
+    if !LoginToken::is_expired(token.expires_at, now) {
+        return Ok(Some(token.token));
+    }
+
+    let (refresh_token, oauth) = match (token.refresh_token.as_ref(), remote_cfg.oauth.as_ref()) {
+        (Some(refresh_token), Some(oauth))
+            if !LoginToken::is_expired(token.refresh_expires_at, now) =>
+        {
+            (refresh_token, oauth)
+        }
+        _ => {
+            return Err(ErrorKind::AuthFailed.error(format!(
+                "the login token of remote '{remote}' is expired, please login again"
+            )))
+        }
+    };
+    debug!("Refresh the login token of remote '{}'", remote);
+    let client = build_common_client(remote_cfg)?;
+    let mut params = vec![
+        ("client_id", oauth.client_id.as_str()),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.as_str()),
+    ];
+    if let Some(secret) = oauth.client_secret.as_ref() {
+        params.push(("client_secret", secret.as_str()));
+    }
+    let url = github_oauth_url(remote_cfg, GITHUB_ACCESS_TOKEN_PATH);
+    let result: AccessTokenResult = post_form(&client, &url, &params)?;
+    if let Some(err) = result.error {
+        return Err(ErrorKind::AuthFailed.error(format!(
+            "refresh the login token of remote '{remote}': {}, please login again",
+            result.error_description.unwrap_or(err)
+        )));
+    }
+    let token = result.into_token(now)?;
+    store.save(cfg, remote, &token)?;
+    Ok(Some(token.token))
+}
+
+const GITHUB_DEVICE_CODE_PATH: &str = "login/device/code";
+const GITHUB_ACCESS_TOKEN_PATH: &str = "login/oauth/access_token";
+
+/// Build the GitHub oauth url with the `api_domain` of the remote, so that the
+/// GitHub Enterprise can be logged in too.
+fn github_oauth_url(remote_cfg: &RemoteConfig, path: &str) -> String {
+    let domain = match remote_cfg.api_domain.as_deref() {
+        Some(domain) if !domain.is_empty() => domain,
+        _ => "github.com",
+    };
+    format!("https://{domain}/{path}")
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessTokenResult {
+    access_token: Option<String>,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+    refresh_token_expires_in: Option<u64>,
+
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+impl AccessTokenResult {
+    fn into_token(self, now: u64) -> Result<LoginToken> {
+        let token = match self.access_token {
+            Some(token) => token,
+            None => bail!("GitHub oauth response has no access token"),
+        };
+        Ok(LoginToken {
+            token,
+            refresh_token: self.refresh_token,
+            expires_at: self.expires_in.map(|secs| now + secs),
+            refresh_expires_at: self.refresh_token_expires_in.map(|secs| now + secs),
+        })
+    }
+}
+
+/// Login GitHub with the OAuth device flow: show the user code, wait for the
+/// user to enter it in the browser, then return the token.
+///
+/// See: https://docs.github.com/en/apps/oauth-apps/building-oauth-apps/authorizing-oauth-apps#device-flow
+pub fn github_device_login(cfg: &Config, remote_cfg: &RemoteConfig) -> Result<LoginToken> {
+    let oauth: &OAuthConfig = match remote_cfg.oauth.as_ref() {
+        Some(oauth) => oauth,
+        None => bail!(
+            "missing oauth config for remote '{}', it is required to login GitHub",
+            remote_cfg.get_name()
+        ),
+    };
+    let client = build_common_client(remote_cfg)?;
+    let params = [
+        ("client_id", oauth.client_id.as_str()),
+        ("scope", oauth.scopes.as_str()),
+    ];
+    let url = github_oauth_url(remote_cfg, GITHUB_DEVICE_CODE_PATH);
+    let code: DeviceCode = post_form(&client, &url, &params)?;
+
+    eprintln!(
+        "Please open {} and enter the code: {}",
+        style(&code.verification_uri).cyan(),
+        style(&code.user_code).bold().yellow()
+    );
+    // The browser is not available in some environments (such as ssh), the
+    // user can open the url manually.
+    let _ = utils::open_url(&code.verification_uri);
+
+    let params = [
+        ("client_id", oauth.client_id.as_str()),
+        ("device_code", code.device_code.as_str()),
+        ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+    ];
+    let url = github_oauth_url(remote_cfg, GITHUB_ACCESS_TOKEN_PATH);
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = code.interval.max(1);
+    loop {
+        thread::sleep(Duration::from_secs(interval));
+        if Instant::now() >= deadline {
+            return Err(
+                ErrorKind::AuthFailed.error("the device code is expired, please login again")
+            );
+        }
+        let result: AccessTokenResult = post_form(&client, &url, &params)?;
+        match result.error.as_deref() {
+            None => return result.into_token(cfg.now()),
+            Some("authorization_pending") => continue,
+            // GitHub requires to increase the interval by 5 seconds.
+            Some("slow_down") => interval += 5,
+            Some("access_denied") => {
+                return Err(ErrorKind::UserAborted.error("the authorization is denied"))
+            }
+            Some(err) => {
+                let msg = result.error_description.as_deref().unwrap_or(err);
+                return Err(ErrorKind::AuthFailed.error(format!("GitHub oauth error: {msg}")));
+            }
+        }
+    }
+}
+
+fn post_form<T>(client: &Client, url: &str, params: &[(&str, &str)]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    debug!("GitHub oauth request: POST {}", url);
+    let span = timings::span("api");
+    let resp = match client
+        .post(url)
+        .header("Accept", "application/json")
+        .header("User-Agent", "roxide-client")
+        .form(params)
+        .send()
+    {
+        Ok(resp) => resp,
+        Err(err) if err.is_connect() || err.is_timeout() => {
+            return Err(ErrorKind::Network.error(format!("GitHub oauth request: {err}")));
+        }
+        Err(err) => return Err(err).context("GitHub oauth request"),
+    };
+    drop(span);
+    debug!("GitHub oauth response: POST {url}: {}", resp.status());
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().unwrap_or_default();
+        bail!("GitHub oauth error: {status}: {body}");
+    }
+    let data = resp.bytes().context("read GitHub oauth response body")?;
+    serde_json::from_slice(&data).context("decode GitHub oauth response data")
+}
+
+#[cfg(test)]
+mod auth_tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use crate::api::auth::*;
+    use crate::config::config_tests;
+
+    #[test]
+    fn test_token_store() {
+        let cfg = config_tests::load_test_config("auth_token_store");
+        let store = TokenStore::new(&cfg);
+        let _ = fs::remove_file(&store.path);
+        secret::set_password("test-password");
+
+        assert_eq!(store.get(&cfg, "github").unwrap(), None);
+        let token = LoginToken::new(String::from("test-token"));
+        store.save(&cfg, "github", &token).unwrap();
+        assert_eq!(store.get(&cfg, "github").unwrap(), Some(token));
+        let mode = fs::metadata(&store.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let data = fs::read(&store.path).unwrap();
+        assert!(secret::is_secret_data(&data));
+        assert!(!String::from_utf8_lossy(&data).contains("test-token"));
+
+        // The plain tokens file of the old versions is encrypted once read.
+        let mut tokens = HashMap::new();
+        tokens.insert(String::from("gitlab"), LoginToken::new(String::from("old")));
+        utils::write_private_file(&store.path, &bincode::serialize(&tokens).unwrap()).unwrap();
+        let token = store.get(&cfg, "gitlab").unwrap().unwrap();
+        assert_eq!(token.token, "old");
+        assert!(secret::is_secret_data(&fs::read(&store.path).unwrap()));
+        assert_eq!(store.get(&cfg, "gitlab").unwrap(), Some(token));
+        store.remove(&cfg, "gitlab").unwrap();
+        let token = LoginToken::new(String::from("test-token"));
+        store.save(&cfg, "github", &token).unwrap();
+
+        // The token in config takes precedence.
+        let mut remote_cfg = cfg.get_remote("github").unwrap().into_owned();
+        remote_cfg.token = None;
+        let resolved = resolve_token(&cfg, &remote_cfg).unwrap();
+        assert_eq!(resolved.as_deref(), Some("test-token"));
+        remote_cfg.token = Some(String::from("config-token"));
+        let resolved = resolve_token(&cfg, &remote_cfg).unwrap();
+        assert_eq!(resolved.as_deref(), Some("config-token"));
+
+        // Expired, and cannot be refreshed.
+        let mut token = LoginToken::new(String::from("expired-token"));
+        token.expires_at = Some(cfg.now());
+        store.save(&cfg, "github", &token).unwrap();
+        remote_cfg.token = None;
+        assert!(resolve_token(&cfg, &remote_cfg).is_err());
+
+        assert!(store.remove(&cfg, "github").unwrap());
+        assert!(!store.remove(&cfg, "github").unwrap());
+    }
+
+    #[test]
+    fn test_github_oauth_url() {
+        let cfg = config_tests::load_test_config("auth_oauth_url");
+        let mut remote_cfg = cfg.get_remote("github").unwrap().into_owned();
+        assert_eq!(
+            github_oauth_url(&remote_cfg, GITHUB_DEVICE_CODE_PATH),
+            "https://github.com/login/device/code"
+        );
+        remote_cfg.api_domain = Some(String::from("github.example.com"));
+        assert_eq!(
+            github_oauth_url(&remote_cfg, GITHUB_ACCESS_TOKEN_PATH),
+            "https://github.example.com/login/oauth/access_token"
+        );
+        assert_eq!(resolve_token(&cfg, &remote_cfg).unwrap(), None);
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::io::ErrorKind;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{bail, Context, Result};
 use bincode::Options;
@@ -11,8 +11,10 @@ use serde::Serialize;
 use crate::api::*;
 use crate::config::{Config, RemoteConfig};
 use crate::filelock::FileLock;
-use crate::utils;
+use crate::{debug, utils};
 
+/// The cache of the remote api, stored in `{metadir}/cache/{remote}`, one file
+/// per request.
 pub struct Cache {
     dir: PathBuf,
 
@@ -44,6 +46,18 @@ impl Provider for Cache {
         Ok(repos)
     }
 
+    fn list_owners(&self) -> Result<Vec<String>> {
+        let path = Self::list_owners_file(&self.dir);
+        if !self.force {
+            if let Some(owners) = self.read(&path)? {
+                return Ok(owners);
+            }
+        }
+        let owners = self.upstream.list_owners()?;
+        self.write(&owners, &path)?;
+        Ok(owners)
+    }
+
     fn get_repo(&self, owner: &str, name: &str) -> Result<ApiRepo> {
         let path = self.get_repo_path(owner, name);
         if !self.force {
@@ -60,8 +74,24 @@ impl Provider for Cache {
         self.upstream.get_merge(merge)
     }
 
-    fn create_merge(&mut self, merge: MergeOptions, title: String, body: String) -> Result<String> {
-        self.upstream.create_merge(merge, title, body)
+    fn create_merge(&mut self, merge: MergeOptions, create: CreateMergeOptions) -> Result<String> {
+        self.upstream.create_merge(merge, create)
+    }
+
+    fn accept_merge(&mut self, merge: MergeOptions, method: MergeMethod) -> Result<String> {
+        self.upstream.accept_merge(merge, method)
+    }
+
+    fn approve_merge(&mut self, merge: MergeOptions) -> Result<String> {
+        self.upstream.approve_merge(merge)
+    }
+
+    fn list_notifications(&self, all: bool) -> Result<Vec<Notification>> {
+        self.upstream.list_notifications(all)
+    }
+
+    fn mark_notification_read(&mut self, id: &str) -> Result<()> {
+        self.upstream.mark_notification_read(id)
     }
 
     fn search_repos(&self, query: &str) -> Result<Vec<String>> {
@@ -76,6 +106,22 @@ impl Provider for Cache {
         Ok(repos)
     }
 
+    fn rename_repo(&mut self, owner: &str, name: &str, new_name: &str) -> Result<()> {
+        self.upstream.rename_repo(owner, name, new_name)?;
+        self.remove(&self.list_repos_path(owner))?;
+        self.remove(&self.get_repo_path(owner, name))
+    }
+
+    fn create_release(
+        &mut self,
+        owner: &str,
+        name: &str,
+        tag: &str,
+        body: String,
+    ) -> Result<String> {
+        self.upstream.create_release(owner, name, tag, body)
+    }
+
     fn get_action(&self, opts: &ActionOptions) -> Result<Option<Action>> {
         self.upstream.get_action(opts)
     }
@@ -87,9 +133,15 @@ impl Provider for Cache {
     fn get_job(&self, owner: &str, name: &str, id: u64) -> Result<ActionJob> {
         self.upstream.get_job(owner, name, id)
     }
+
+    fn get_issue(&self, owner: &str, name: &str, id: u64) -> Result<ApiIssue> {
+        self.upstream.get_issue(owner, name, id)
+    }
 }
 
 impl Cache {
+    const CLEANUP_INTERVAL: u64 = 3600;
+
     pub fn new(
         cfg: &Config,
         remote_cfg: &RemoteConfig,
@@ -97,8 +149,11 @@ impl Cache {
         force: bool,
     ) -> Result<Cache> {
         let lock = FileLock::acquire(cfg, "cache")?;
+        if let Err(err) = Self::cleanup(cfg) {
+            debug!("Cleanup api cache failed: {:#}", err);
+        }
         let expire = Duration::from_secs(remote_cfg.cache_hours as u64 * 3600);
-        let dir = cfg.get_meta_dir().join("cache").join(remote_cfg.get_name());
+        let dir = Self::get_dir(cfg, remote_cfg);
         Ok(Cache {
             dir,
             expire,
@@ -109,9 +164,135 @@ impl Cache {
         })
     }
 
+    /// Cleanup the api cache at most once per [`Cache::CLEANUP_INTERVAL`]:
+    /// remove the expired (or incompatible) files and the caches of the remotes
+    /// no longer cached, then evict the least recently used files if the total
+    /// size exceeds `api_cache.max_size`. The caller should hold the cache lock.
+    fn cleanup(cfg: &Config) -> Result<()> {
+        let root = cfg.get_meta_dir().join("cache");
+        let stamp_path = root.join(".cleanup");
+        let now = cfg.now();
+        if let Ok(data) = fs::read_to_string(&stamp_path) {
+            if let Ok(last) = data.trim().parse::<u64>() {
+                if now < last + Self::CLEANUP_INTERVAL {
+                    return Ok(());
+                }
+            }
+        }
+
+        let dirs = match fs::read_dir(&root) {
+            Ok(dirs) => dirs,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read cache dir {}", root.display()))
+            }
+        };
+        let mut files = Vec::new();
+        for dir in dirs {
+            let dir = dir.context("read cache dir entry")?;
+            if !dir.file_type().context("get cache file type")?.is_dir() {
+                continue;
+            }
+            let path = dir.path();
+            let name = dir.file_name();
+            // The remotes hidden by the profile may share the metadir, their
+            // caches are not unused.
+            let name = name.to_string_lossy();
+            let remote_cfg = cfg
+                .remotes
+                .get(name.as_ref())
+                .or_else(|| cfg.hidden_remotes.get(name.as_ref()));
+            let expire = match remote_cfg {
+                Some(remote_cfg) if remote_cfg.cache_hours > 0 => {
+                    remote_cfg.cache_hours as u64 * 3600
+                }
+                _ => {
+                    debug!("Remove unused cache dir {}", path.display());
+                    fs::remove_dir_all(&path)
+                        .with_context(|| format!("remove cache dir {}", path.display()))?;
+                    continue;
+                }
+            };
+            Self::scan_dir(&path, now, expire, &mut files)?;
+        }
+
+        let quota = cfg.api_cache.max_size * 1024 * 1024;
+        for path in Self::select_evictions(files, quota) {
+            debug!("Evict cache file {}", path.display());
+            fs::remove_file(&path)
+                .with_context(|| format!("remove cache file {}", path.display()))?;
+        }
+
+        utils::write_file(&stamp_path, format!("{now}").as_bytes())
+    }
+
+    /// Remove the expired files in the remote cache dir, push the others to
+    /// `files` as `(path, size, last_used)`.
+    fn scan_dir(
+        dir: &Path,
+        now: u64,
+        expire: u64,
+        files: &mut Vec<(PathBuf, u64, SystemTime)>,
+    ) -> Result<()> {
+        let decoder = &mut bincode::options().with_fixint_encoding();
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("read cache dir {}", dir.display()))?;
+        for entry in entries {
+            let path = entry.context("read cache dir entry")?.path();
+            let data =
+                fs::read(&path).with_context(|| format!("read cache file {}", path.display()))?;
+            let update_time: u64 = data
+                .get(..8)
+                .and_then(|data| decoder.deserialize(data).ok())
+                .unwrap_or(0);
+            if now >= update_time + expire {
+                debug!("Remove expired cache file {}", path.display());
+                fs::remove_file(&path)
+                    .with_context(|| format!("remove cache file {}", path.display()))?;
+                continue;
+            }
+            let meta = fs::metadata(&path)
+                .with_context(|| format!("get metadata of cache file {}", path.display()))?;
+            let last_used = meta.modified().unwrap_or(UNIX_EPOCH);
+            files.push((path, data.len() as u64, last_used));
+        }
+        Ok(())
+    }
+
+    /// Select the least recently used files to remove, to make the total size
+    /// not exceed the quota. 0 quota means unlimited.
+    fn select_evictions(mut files: Vec<(PathBuf, u64, SystemTime)>, quota: u64) -> Vec<PathBuf> {
+        if quota == 0 {
+            return Vec::new();
+        }
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        files.sort_unstable_by_key(|(_, _, last_used)| *last_used);
+        let mut evictions = Vec::new();
+        for (path, size, _) in files {
+            if total <= quota {
+                break;
+            }
+            total -= size;
+            evictions.push(path);
+        }
+        evictions
+    }
+
+    fn get_dir(cfg: &Config, remote_cfg: &RemoteConfig) -> PathBuf {
+        cfg.get_meta_dir().join("cache").join(remote_cfg.get_name())
+    }
+
     fn list_repos_path(&self, owner: &str) -> PathBuf {
+        Self::list_repos_file(&self.dir, owner)
+    }
+
+    fn list_repos_file(dir: &Path, owner: &str) -> PathBuf {
         let owner = owner.replace('/', ".");
-        self.dir.join(format!("list.{owner}"))
+        dir.join(format!("list.{owner}"))
+    }
+
+    fn list_owners_file(dir: &Path) -> PathBuf {
+        dir.join("owners")
     }
 
     fn get_repo_path(&self, owner: &str, name: &str) -> PathBuf {
@@ -137,6 +318,37 @@ impl Cache {
             }
         };
 
+        // The cache written by the old version might be incompatible, treat it
+        // as expired.
+        let (update_time, cache) = match Self::decode(path, &data) {
+            Ok((update_time, cache)) => (update_time, Some(cache)),
+            Err(err) => {
+                debug!("Drop incompatible cache {}: {err:#}", path.display());
+                (0, None)
+            }
+        };
+        let expire_duration = Duration::from_secs(update_time) + self.expire;
+        match cache {
+            Some(cache) if self.now < expire_duration.as_secs() => {
+                // The modified time is used as the last used time by eviction.
+                if let Ok(file) = fs::File::options().write(true).open(path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Ok(Some(cache))
+            }
+            _ => {
+                fs::remove_file(path)
+                    .with_context(|| format!("remove cache file {}", path.display()))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Decode the cache data, return the update time and the value.
+    fn decode<T>(path: &Path, data: &[u8]) -> Result<(u64, T)>
+    where
+        T: DeserializeOwned,
+    {
         let decoder = &mut bincode::options().with_fixint_encoding();
         let mut update_time: u64 = 0;
         let update_time_size = decoder.serialized_size(&update_time).unwrap() as usize;
@@ -148,17 +360,19 @@ impl Cache {
         update_time = decoder
             .deserialize(update_time_data)
             .context("decode cache last_updated data")?;
-        let expire_duration = Duration::from_secs(update_time) + self.expire;
-        if self.now >= expire_duration.as_secs() {
-            fs::remove_file(path)
-                .with_context(|| format!("remove cache file {}", path.display()))?;
-            return Ok(None);
-        }
 
         let cache = decoder
             .deserialize::<T>(cache_data)
             .context("decode cache data")?;
-        Ok(Some(cache))
+        Ok((update_time, cache))
+    }
+
+    fn remove(&self, path: &PathBuf) -> Result<()> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("remove cache file {}", path.display())),
+        }
     }
 
     fn write<T>(&self, value: &T, path: &PathBuf) -> Result<()>
@@ -177,6 +391,39 @@ impl Cache {
     }
 }
 
+/// Read the cached repositories of the owner for completion. This never calls the
+/// remote api or acquires the cache lock, and the expired cache is still used
+/// (the completion candidates are allowed to be stale). Return [`None`] if the
+/// owner has not been listed before.
+pub fn list_cached_repos(
+    cfg: &Config,
+    remote_cfg: &RemoteConfig,
+    owner: &str,
+) -> Result<Option<Vec<String>>> {
+    let path = Cache::list_repos_file(&Cache::get_dir(cfg, remote_cfg), owner);
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("read cache file {}", path.display())),
+    };
+    let (_, repos) = Cache::decode(&path, &data)?;
+    Ok(Some(repos))
+}
+
+/// Read the cached owners of the remote for completion, the same as
+/// [`list_cached_repos`]. Return [`None`] if the owners have not been listed
+/// before.
+pub fn list_cached_owners(cfg: &Config, remote_cfg: &RemoteConfig) -> Result<Option<Vec<String>>> {
+    let path = Cache::list_owners_file(&Cache::get_dir(cfg, remote_cfg));
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("read cache file {}", path.display())),
+    };
+    let (_, owners) = Cache::decode(&path, &data)?;
+    Ok(Some(owners))
+}
+
 #[cfg(test)]
 mod cache_tests {
     use crate::api::api_tests::StaticProvider;
@@ -193,12 +440,22 @@ mod cache_tests {
         let mut cache = Cache::new(&cfg, &remote_cfg, upstream, true).unwrap();
 
         assert_eq!(cache.list_repos("fioncat").unwrap(), expect_repos);
+        let expect_owners = cache.list_owners().unwrap();
 
         let upstream = StaticProvider::build(vec![("fioncat", vec!["hello0", "hello1", "hello2"])]);
         cache.upstream = upstream;
         cache.force = false;
 
         assert_eq!(cache.list_repos("fioncat").unwrap(), expect_repos);
+
+        let cached = list_cached_repos(&cfg, &remote_cfg, "fioncat").unwrap();
+        assert_eq!(cached, Some(expect_repos));
+        let cached = list_cached_repos(&cfg, &remote_cfg, "unknown").unwrap();
+        assert_eq!(cached, None);
+
+        assert_eq!(cache.list_owners().unwrap(), expect_owners);
+        let cached = list_cached_owners(&cfg, &remote_cfg).unwrap();
+        assert_eq!(cached, Some(expect_owners));
     }
 
     #[test]
@@ -223,4 +480,96 @@ mod cache_tests {
 
         assert_eq!(cache.list_repos("kubernetes").unwrap(), expect_repos);
     }
+
+    #[test]
+    fn test_cache_incompatible() {
+        let cfg = config_tests::load_test_config("api_cache/incompatible");
+        let upstream = StaticProvider::mock();
+        let remote_cfg = cfg.get_remote("github").unwrap();
+
+        let cache = Cache::new(&cfg, &remote_cfg, upstream, false).unwrap();
+        let path = cache.get_repo_path("fioncat", "roxide");
+        // The cache written by the old version, without the new fields.
+        cache.write(&String::from("main"), &path).unwrap();
+
+        let repo = cache.get_repo("fioncat", "roxide").unwrap();
+        assert_eq!(repo.visibility, "public");
+        let cached: ApiRepo = cache.read(&path).unwrap().unwrap();
+        assert_eq!(cached, repo);
+    }
+
+    #[test]
+    fn test_cache_cleanup() {
+        let mut cfg = config_tests::load_test_config("api_cache/cleanup");
+        let upstream = StaticProvider::mock();
+        let remote_cfg = cfg.get_remote("github").unwrap().into_owned();
+        let root = cfg.get_meta_dir().join("cache");
+        let _ = fs::remove_dir_all(&root);
+
+        let mut cache = Cache::new(&cfg, &remote_cfg, upstream, true).unwrap();
+        cache.list_repos("fioncat").unwrap();
+        cache.list_repos("kubernetes").unwrap();
+        cache.now = 0;
+        let expired = cache.get_repo_path("fioncat", "roxide");
+        cache.write(&String::from("main"), &expired).unwrap();
+        let unused = root.join("unknown").join("owners");
+        utils::write_file(&unused, b"data").unwrap();
+        drop(cache);
+
+        cfg.api_cache.max_size = 0;
+        // The remote hidden by the profile should not be treated as unused.
+        let github = cfg.remotes.remove("github").unwrap();
+        cfg.hidden_remotes.insert(String::from("github"), github);
+        Cache::cleanup(&cfg).unwrap();
+        assert!(!expired.exists());
+        assert!(!unused.exists());
+        let dir = Cache::get_dir(&cfg, &remote_cfg);
+        assert!(Cache::list_repos_file(&dir, "fioncat").exists());
+        assert!(Cache::list_repos_file(&dir, "kubernetes").exists());
+
+        // Throttled.
+        utils::write_file(&unused, b"data").unwrap();
+        Cache::cleanup(&cfg).unwrap();
+        assert!(unused.exists());
+    }
+
+    #[test]
+    fn test_cache_evictions() {
+        let now = SystemTime::now();
+        let files = vec![
+            (PathBuf::from("a"), 30, now),
+            (PathBuf::from("b"), 30, now - Duration::from_secs(20)),
+            (PathBuf::from("c"), 30, now - Duration::from_secs(10)),
+        ];
+        assert!(Cache::select_evictions(files.clone(), 0).is_empty());
+        assert!(Cache::select_evictions(files.clone(), 90).is_empty());
+        assert_eq!(
+            Cache::select_evictions(files.clone(), 60),
+            vec![PathBuf::from("b")]
+        );
+        assert_eq!(
+            Cache::select_evictions(files, 40),
+            vec![PathBuf::from("b"), PathBuf::from("c")]
+        );
+    }
+
+    #[test]
+    fn test_cache_rename() {
+        let cfg = config_tests::load_test_config("api_cache/rename");
+        let upstream = StaticProvider::mock();
+        let remote_cfg = cfg.get_remote("github").unwrap();
+
+        let mut cache = Cache::new(&cfg, &remote_cfg, upstream, true).unwrap();
+        assert!(cache
+            .list_repos("fioncat")
+            .unwrap()
+            .contains(&String::from("roxide")));
+
+        cache.force = false;
+        cache.rename_repo("fioncat", "roxide", "rox").unwrap();
+
+        let repos = cache.list_repos("fioncat").unwrap();
+        assert!(repos.contains(&String::from("rox")));
+        assert!(!repos.contains(&String::from("roxide")));
+    }
 }
@@ -3,11 +3,13 @@ use std::time::Duration;
 use anyhow::{bail, Context, Result};
 use reqwest::blocking::{Client, Request, Response};
 use reqwest::{Method, Url};
-use serde::de::DeserializeOwned;
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde::{Deserialize, Serialize};
 
 use crate::api::*;
 use crate::config::RemoteConfig;
+use crate::errors::ErrorKind;
+use crate::{debug, timings};
 
 #[derive(Debug, Deserialize)]
 struct Repo {
@@ -18,6 +20,15 @@ struct Repo {
     pub source: Option<Source>,
 
     pub default_branch: String,
+
+    pub description: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub private: bool,
+    pub visibility: Option<String>,
+    #[serde(default)]
+    pub stargazers_count: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,9 +53,31 @@ struct Error {
     pub message: String,
 }
 
+#[derive(Debug, Serialize)]
+struct GraphQLBody<'a> {
+    query: &'a str,
+    variables: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQLResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQLError {
+    message: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct Release {
     pub tag_name: String,
+    #[serde(default)]
+    pub html_url: String,
 }
 
 impl Repo {
@@ -55,6 +88,11 @@ impl Repo {
             full_name: _,
             source,
             default_branch,
+            description,
+            archived,
+            private,
+            visibility,
+            stargazers_count,
         } = self;
         let upstream = match source {
             Some(source) => Some(ApiUpstream {
@@ -64,10 +102,17 @@ impl Repo {
             }),
             None => None,
         };
+        // The `visibility` field is missing in some old GitHub Enterprise.
+        let visibility =
+            visibility.unwrap_or_else(|| String::from(if private { "private" } else { "public" }));
         ApiRepo {
             default_branch,
             upstream,
             web_url: html_url,
+            description,
+            archived,
+            visibility,
+            stars: stargazers_count,
         }
     }
 }
@@ -89,6 +134,18 @@ struct PullRequestBody {
 
     title: String,
     body: String,
+
+    draft: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct RequestReviewersBody {
+    reviewers: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AddLabelsBody {
+    labels: Vec<String>,
 }
 
 impl From<MergeOptions> for PullRequestOptions {
@@ -121,9 +178,94 @@ impl From<MergeOptions> for PullRequestOptions {
 
 #[derive(Debug, Deserialize)]
 struct PullRequest {
+    number: u64,
+    html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    number: u64,
+    title: String,
+    html_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MergePullRequestBody {
+    merge_method: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+struct MergePullRequestResult {
+    merged: bool,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ReviewBody {
+    event: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct RenameRepoBody<'a> {
+    name: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct ReleaseBody {
+    tag_name: String,
+    name: String,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationThread {
+    id: String,
+    unread: bool,
+    reason: String,
+    updated_at: String,
+    subject: NotificationSubject,
+    repository: NotificationRepo,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationSubject {
+    title: String,
+    url: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationRepo {
+    full_name: String,
     html_url: String,
 }
 
+impl NotificationThread {
+    fn api(self) -> Notification {
+        // The subject url is an api url, like
+        // `https://api.github.com/repos/{owner}/{name}/pulls/{id}`, convert it to the
+        // web url. Some subjects (like discussions) have no url, use the repo url.
+        let url = match self.subject.url {
+            Some(url) => url
+                .replace("https://api.github.com/repos/", "https://github.com/")
+                .replace("/pulls/", "/pull/")
+                .replace("/commits/", "/commit/"),
+            None => self.repository.html_url,
+        };
+        Notification {
+            id: self.id,
+            repo: self.repository.full_name,
+            kind: self.subject.kind,
+            title: self.subject.title,
+            reason: self.reason,
+            unread: self.unread,
+            url,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ListWorkflowRunResult {
     workflow_runs: Vec<WorkflowRun>,
@@ -255,6 +397,15 @@ impl Provider for GitHub {
         Ok(repos)
     }
 
+    fn list_owners(&self) -> Result<Vec<String>> {
+        let user = self.execute_get::<Owner>("user")?;
+        let path = format!("user/orgs?per_page={}", self.per_page);
+        let orgs = self.execute_get::<Vec<Owner>>(&path)?;
+        let mut owners = vec![user.login];
+        owners.extend(orgs.into_iter().map(|org| org.login));
+        Ok(owners)
+    }
+
     fn get_repo(&self, owner: &str, name: &str) -> Result<ApiRepo> {
         let path = format!("repos/{}/{}", owner, name);
         Ok(self.execute_get::<Repo>(&path)?.api())
@@ -262,32 +413,89 @@ impl Provider for GitHub {
 
     fn get_merge(&self, merge: MergeOptions) -> Result<Option<String>> {
         let opts: PullRequestOptions = merge.into();
-        let head = urlencoding::encode(&opts.head_search);
-        let base = urlencoding::encode(&opts.base);
-        let path = format!(
-            "repos/{}/{}/pulls?state=open&head={}&base={}",
-            opts.owner, opts.name, head, base
-        );
-        let mut prs = self.execute_get::<Vec<PullRequest>>(&path)?;
-        if prs.is_empty() {
-            return Ok(None);
-        }
-        Ok(Some(prs.remove(0).html_url))
+        Ok(self.get_pull_request(&opts)?.map(|pr| pr.html_url))
     }
 
-    fn create_merge(&mut self, merge: MergeOptions, title: String, body: String) -> Result<String> {
+    fn create_merge(&mut self, merge: MergeOptions, create: CreateMergeOptions) -> Result<String> {
         let opts: PullRequestOptions = merge.into();
         let path = format!("repos/{}/{}/pulls", opts.owner, opts.name);
         let body = PullRequestBody {
             head: opts.head,
             base: opts.base,
-            title,
-            body,
+            title: create.title,
+            body: create.body,
+            draft: create.draft,
         };
         let pr = self.execute_post::<PullRequestBody, PullRequest>(&path, body)?;
+
+        if !create.reviewers.is_empty() {
+            let path = format!(
+                "repos/{}/{}/pulls/{}/requested_reviewers",
+                opts.owner, opts.name, pr.number
+            );
+            let body = RequestReviewersBody {
+                reviewers: create.reviewers,
+            };
+            self.execute_post::<RequestReviewersBody, IgnoredAny>(&path, body)?;
+        }
+        if !create.labels.is_empty() {
+            // The labels of PullRequest are managed by the issues api.
+            let path = format!(
+                "repos/{}/{}/issues/{}/labels",
+                opts.owner, opts.name, pr.number
+            );
+            let body = AddLabelsBody {
+                labels: create.labels,
+            };
+            self.execute_post::<AddLabelsBody, IgnoredAny>(&path, body)?;
+        }
+        Ok(pr.html_url)
+    }
+
+    fn accept_merge(&mut self, merge: MergeOptions, method: MergeMethod) -> Result<String> {
+        let opts: PullRequestOptions = merge.into();
+        let pr = self.must_get_pull_request(&opts)?;
+        let path = format!(
+            "repos/{}/{}/pulls/{}/merge",
+            opts.owner, opts.name, pr.number
+        );
+        let body = MergePullRequestBody {
+            merge_method: method.as_str(),
+        };
+        let body = serde_json::to_vec(&body).context("encode GitHub request body")?;
+        let req = self.build_request(&path, Method::PUT, Some(body))?;
+        let result = self.execute::<MergePullRequestResult>(req)?;
+        if !result.merged {
+            bail!("GitHub refused to merge: {}", result.message);
+        }
         Ok(pr.html_url)
     }
 
+    fn approve_merge(&mut self, merge: MergeOptions) -> Result<String> {
+        let opts: PullRequestOptions = merge.into();
+        let pr = self.must_get_pull_request(&opts)?;
+        let path = format!(
+            "repos/{}/{}/pulls/{}/reviews",
+            opts.owner, opts.name, pr.number
+        );
+        let body = ReviewBody { event: "APPROVE" };
+        self.execute_post::<ReviewBody, IgnoredAny>(&path, body)?;
+        Ok(pr.html_url)
+    }
+
+    fn list_notifications(&self, all: bool) -> Result<Vec<Notification>> {
+        let path = format!("notifications?all={all}&per_page={}", self.per_page);
+        let threads = self.execute_get::<Vec<NotificationThread>>(&path)?;
+        Ok(threads.into_iter().map(|thread| thread.api()).collect())
+    }
+
+    fn mark_notification_read(&mut self, id: &str) -> Result<()> {
+        let path = format!("notifications/threads/{id}");
+        let req = self.build_request(&path, Method::PATCH, None)?;
+        self.execute_resp(req)?;
+        Ok(())
+    }
+
     fn search_repos(&self, query: &str) -> Result<Vec<String>> {
         let path = format!("search/repositories?q={}", query);
         let result = self.execute_get::<SearchRepoResult>(&path)?;
@@ -300,6 +508,32 @@ impl Provider for GitHub {
         Ok(repos)
     }
 
+    fn rename_repo(&mut self, owner: &str, name: &str, new_name: &str) -> Result<()> {
+        let path = format!("repos/{owner}/{name}");
+        let body = RenameRepoBody { name: new_name };
+        let body = serde_json::to_vec(&body).context("encode GitHub request body")?;
+        let req = self.build_request(&path, Method::PATCH, Some(body))?;
+        self.execute_resp(req)?;
+        Ok(())
+    }
+
+    fn create_release(
+        &mut self,
+        owner: &str,
+        name: &str,
+        tag: &str,
+        body: String,
+    ) -> Result<String> {
+        let path = format!("repos/{owner}/{name}/releases");
+        let body = ReleaseBody {
+            tag_name: tag.to_string(),
+            name: tag.to_string(),
+            body,
+        };
+        let release = self.execute_post::<ReleaseBody, Release>(&path, body)?;
+        Ok(release.html_url)
+    }
+
     fn get_action(&self, opts: &ActionOptions) -> Result<Option<Action>> {
         let target = match &opts.target {
             ActionTarget::Commit(commit) => format!("head_sha={commit}"),
@@ -400,20 +634,38 @@ impl Provider for GitHub {
             url: job.html_url,
         })
     }
+
+    fn get_issue(&self, owner: &str, name: &str, id: u64) -> Result<ApiIssue> {
+        let path = format!("repos/{owner}/{name}/issues/{id}");
+        let issue = self.execute_get::<Issue>(&path)?;
+        Ok(ApiIssue {
+            id: issue.number,
+            title: issue.title,
+            web_url: issue.html_url,
+        })
+    }
 }
 
 impl GitHub {
     const API_VERSION: &'static str = "2022-11-28";
 
-    pub fn build(remote_cfg: &RemoteConfig) -> Box<dyn Provider> {
-        let client = build_common_client(remote_cfg);
-        Box::new(GitHub {
+    pub fn build(remote_cfg: &RemoteConfig) -> Result<Box<dyn Provider>> {
+        Ok(Box::new(Self::new(remote_cfg)?))
+    }
+
+    pub(super) fn new(remote_cfg: &RemoteConfig) -> Result<GitHub> {
+        let client = build_common_client(remote_cfg)?;
+        Ok(GitHub {
             token: remote_cfg.token.clone(),
             per_page: remote_cfg.list_limit,
             client,
         })
     }
 
+    pub(super) fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
     pub fn new_empty() -> GitHub {
         let client = Client::builder()
             .timeout(Duration::from_secs_f32(20.0))
@@ -446,6 +698,28 @@ impl GitHub {
         self.execute(req)
     }
 
+    /// Execute a GraphQL query, the errors in the response are converted to
+    /// [`ErrorKind`] if possible. GitHub requires auth for GraphQL.
+    pub(super) fn execute_graphql<T>(&self, query: &str, variables: serde_json::Value) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let body = GraphQLBody { query, variables };
+        let resp = self.execute_post::<GraphQLBody, GraphQLResponse<T>>("graphql", body)?;
+        if let Some(err) = resp.errors.into_iter().next() {
+            let msg = format!("GitHub graphql error: {}", err.message);
+            return match err.kind.as_deref() {
+                Some("NOT_FOUND") => Err(ErrorKind::NotFound.error(msg)),
+                Some("FORBIDDEN") => Err(ErrorKind::AuthFailed.error(msg)),
+                _ => bail!(msg),
+            };
+        }
+        match resp.data {
+            Some(data) => Ok(data),
+            None => bail!("GitHub graphql response has no data"),
+        }
+    }
+
     fn execute_get_resp(&self, path: &str) -> Result<Response> {
         let req = self.build_request(path, Method::GET, None)?;
         self.execute_resp(req)
@@ -461,20 +735,36 @@ impl GitHub {
     }
 
     fn execute_resp(&self, req: Request) -> Result<Response> {
-        let resp = self.client.execute(req).context("GitHub http request")?;
+        let (method, url) = (req.method().clone(), req.url().clone());
+        debug!("GitHub request: {} {}", method, url);
+        let span = timings::span("api");
+        let resp = match self.client.execute(req) {
+            Ok(resp) => resp,
+            Err(err) if err.is_connect() || err.is_timeout() => {
+                return Err(ErrorKind::Network.error(format!("GitHub http request: {err}")));
+            }
+            Err(err) => return Err(err).context("GitHub http request"),
+        };
+        drop(span);
         let ok = resp.status().is_success();
+        debug!("GitHub response: {} {}: {}", method, url, resp.status());
         if ok {
             return Ok(resp);
         }
 
+        let kind = ErrorKind::from_http_status(resp.status().as_u16());
         let data = resp.bytes().context("read GitHub response body")?;
-        match serde_json::from_slice::<Error>(&data) {
-            Ok(err) => bail!("GitHub api error: {}", err.message),
-            Err(_err) => bail!(
+        let msg = match serde_json::from_slice::<Error>(&data) {
+            Ok(err) => format!("GitHub api error: {}", err.message),
+            Err(_err) => format!(
                 "unknown GitHub api error: {}",
                 String::from_utf8(data.to_vec())
                     .context("decode GitHub response to UTF-8 string")?
             ),
+        };
+        match kind {
+            Some(kind) => Err(kind.error(msg)),
+            None => bail!(msg),
         }
     }
 
@@ -496,6 +786,27 @@ impl GitHub {
         builder.build().context("build request")
     }
 
+    fn get_pull_request(&self, opts: &PullRequestOptions) -> Result<Option<PullRequest>> {
+        let head = urlencoding::encode(&opts.head_search);
+        let base = urlencoding::encode(&opts.base);
+        let path = format!(
+            "repos/{}/{}/pulls?state=open&head={}&base={}",
+            opts.owner, opts.name, head, base
+        );
+        let mut prs = self.execute_get::<Vec<PullRequest>>(&path)?;
+        if prs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(prs.remove(0)))
+    }
+
+    fn must_get_pull_request(&self, opts: &PullRequestOptions) -> Result<PullRequest> {
+        match self.get_pull_request(opts)? {
+            Some(pr) => Ok(pr),
+            None => bail!("could not find opened PullRequest for '{}'", opts.head),
+        }
+    }
+
     pub fn get_latest_tag(&self, owner: &str, name: &str) -> Result<String> {
         let path = format!("repos/{owner}/{name}/releases/latest");
         let release = self.execute_get::<Release>(&path)?;
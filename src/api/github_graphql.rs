@@ -0,0 +1,492 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::api::github::GitHub;
+use crate::api::*;
+use crate::config::RemoteConfig;
+use crate::errors::ErrorKind;
+
+const REPO_QUERY: &str = r#"
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    url
+    description
+    isArchived
+    visibility
+    stargazerCount
+    defaultBranchRef { name }
+    parent {
+      name
+      owner { login }
+      defaultBranchRef { name }
+    }
+  }
+}
+"#;
+
+const PULL_REQUEST_QUERY: &str = r#"
+query($owner: String!, $name: String!, $head: String!, $base: String!) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(headRefName: $head, baseRefName: $base, states: OPEN, first: 20) {
+      nodes {
+        url
+        headRepositoryOwner { login }
+      }
+    }
+  }
+}
+"#;
+
+const ACTION_QUERY: &str = r#"
+query($owner: String!, $name: String!, $expression: String!) {
+  repository(owner: $owner, name: $name) {
+    object(expression: $expression) {
+      ... on Commit {
+        oid
+        message
+        author { name email }
+        checkSuites(first: 50) {
+          nodes {
+            workflowRun {
+              url
+              workflow { name }
+            }
+            checkRuns(first: 100) {
+              nodes { databaseId name status conclusion detailsUrl }
+            }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+#[derive(Debug, Deserialize)]
+struct RepoData<T> {
+    repository: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Repo {
+    url: String,
+    description: Option<String>,
+    is_archived: bool,
+    visibility: String,
+    stargazer_count: u64,
+    default_branch_ref: Option<BranchRef>,
+    parent: Option<ParentRepo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParentRepo {
+    name: String,
+    owner: Owner,
+    default_branch_ref: Option<BranchRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BranchRef {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Owner {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestRepo {
+    pull_requests: Nodes<PullRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullRequest {
+    url: String,
+    head_repository_owner: Option<Owner>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionRepo {
+    object: Option<Commit>,
+}
+
+/// For the non-commit objects, the fragment has no field, so all the fields
+/// here are optional.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Commit {
+    oid: Option<String>,
+    message: Option<String>,
+    author: Option<CommitAuthor>,
+    check_suites: Option<Nodes<CheckSuite>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitAuthor {
+    name: Option<String>,
+    email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckSuite {
+    workflow_run: Option<WorkflowRun>,
+    check_runs: Option<Nodes<CheckRun>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRun {
+    url: String,
+    workflow: Workflow,
+}
+
+#[derive(Debug, Deserialize)]
+struct Workflow {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckRun {
+    database_id: Option<u64>,
+    name: String,
+    status: String,
+    conclusion: Option<String>,
+    details_url: Option<String>,
+}
+
+impl CheckRun {
+    fn convert_status(&self) -> ActionJobStatus {
+        match self.status.as_str() {
+            "COMPLETED" => {}
+            "IN_PROGRESS" => return ActionJobStatus::Running,
+            _ => return ActionJobStatus::Pending,
+        }
+        match self.conclusion.as_deref() {
+            Some("SUCCESS") => ActionJobStatus::Success,
+            Some("SKIPPED") | Some("NEUTRAL") => ActionJobStatus::Skipped,
+            Some("ACTION_REQUIRED") => ActionJobStatus::WaitingForConfirm,
+            Some("CANCELLED") => ActionJobStatus::Canceled,
+            _ => ActionJobStatus::Failed,
+        }
+    }
+}
+
+impl Commit {
+    /// Convert the commit to [`Action`], only the check suites triggered by the
+    /// workflows are included, the same as the REST api.
+    fn action(self) -> Option<Action> {
+        let id = self.oid?;
+        let author = self.author;
+        let commit = ActionCommit {
+            id,
+            message: self.message.unwrap_or_default(),
+            author_name: author
+                .as_ref()
+                .and_then(|author| author.name.clone())
+                .unwrap_or_default(),
+            author_email: author.and_then(|author| author.email).unwrap_or_default(),
+        };
+
+        let mut runs: Vec<ActionRun> = Vec::new();
+        for suite in self.check_suites.map(|suites| suites.nodes)? {
+            let workflow_run = match suite.workflow_run {
+                Some(run) => run,
+                None => continue,
+            };
+            let jobs: Vec<ActionJob> = suite
+                .check_runs
+                .map(|runs| runs.nodes)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|run| {
+                    let status = run.convert_status();
+                    Some(ActionJob {
+                        id: run.database_id?,
+                        name: run.name,
+                        status,
+                        url: run.details_url.unwrap_or_default(),
+                    })
+                })
+                .collect();
+            if jobs.is_empty() {
+                continue;
+            }
+            runs.push(ActionRun {
+                name: workflow_run.workflow.name,
+                url: Some(workflow_run.url),
+                jobs,
+            });
+        }
+        if runs.is_empty() {
+            return None;
+        }
+        runs.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        Some(Action {
+            url: None,
+            commit,
+            runs,
+        })
+    }
+}
+
+/// The GitHub provider using the GraphQL api, see `api_style` in remote config.
+/// The repository, PullRequest and action data are fetched in single queries,
+/// the rest are delegated to the REST api.
+pub struct GitHubGraphQL {
+    rest: GitHub,
+}
+
+impl Provider for GitHubGraphQL {
+    fn info(&self) -> Result<ProviderInfo> {
+        let mut info = self.rest.info()?;
+        info.name = String::from("GitHub GraphQL API");
+        Ok(info)
+    }
+
+    fn list_repos(&self, owner: &str) -> Result<Vec<String>> {
+        self.rest.list_repos(owner)
+    }
+
+    fn list_owners(&self) -> Result<Vec<String>> {
+        self.rest.list_owners()
+    }
+
+    fn get_repo(&self, owner: &str, name: &str) -> Result<ApiRepo> {
+        let variables = json!({ "owner": owner, "name": name });
+        let data: RepoData<Repo> = self.rest.execute_graphql(REPO_QUERY, variables)?;
+        let repo = match data.repository {
+            Some(repo) => repo,
+            None => {
+                let msg = format!("GitHub repo {owner}/{name} not found");
+                return Err(ErrorKind::NotFound.error(msg));
+            }
+        };
+        let upstream = repo.parent.map(|parent| ApiUpstream {
+            owner: parent.owner.login,
+            name: parent.name,
+            default_branch: Self::branch_name(parent.default_branch_ref),
+        });
+        Ok(ApiRepo {
+            default_branch: Self::branch_name(repo.default_branch_ref),
+            upstream,
+            web_url: repo.url,
+            description: repo.description,
+            archived: repo.is_archived,
+            visibility: repo.visibility.to_lowercase(),
+            stars: repo.stargazer_count,
+        })
+    }
+
+    fn get_merge(&self, merge: MergeOptions) -> Result<Option<String>> {
+        let (owner, name) = match merge.upstream.as_ref() {
+            Some(upstream) => (upstream.owner.as_str(), upstream.name.as_str()),
+            None => (merge.owner.as_str(), merge.name.as_str()),
+        };
+        let variables = json!({
+            "owner": owner,
+            "name": name,
+            "head": merge.source,
+            "base": merge.target,
+        });
+        let data: RepoData<PullRequestRepo> =
+            self.rest.execute_graphql(PULL_REQUEST_QUERY, variables)?;
+        let prs = match data.repository {
+            Some(repo) => repo.pull_requests.nodes,
+            None => return Ok(None),
+        };
+        // The branches with the same name in other forks are excluded.
+        Ok(prs
+            .into_iter()
+            .find(|pr| {
+                pr.head_repository_owner
+                    .as_ref()
+                    .is_some_and(|head_owner| head_owner.login == merge.owner)
+            })
+            .map(|pr| pr.url))
+    }
+
+    fn create_merge(&mut self, merge: MergeOptions, create: CreateMergeOptions) -> Result<String> {
+        self.rest.create_merge(merge, create)
+    }
+
+    fn accept_merge(&mut self, merge: MergeOptions, method: MergeMethod) -> Result<String> {
+        self.rest.accept_merge(merge, method)
+    }
+
+    fn approve_merge(&mut self, merge: MergeOptions) -> Result<String> {
+        self.rest.approve_merge(merge)
+    }
+
+    fn list_notifications(&self, all: bool) -> Result<Vec<Notification>> {
+        self.rest.list_notifications(all)
+    }
+
+    fn mark_notification_read(&mut self, id: &str) -> Result<()> {
+        self.rest.mark_notification_read(id)
+    }
+
+    fn search_repos(&self, query: &str) -> Result<Vec<String>> {
+        self.rest.search_repos(query)
+    }
+
+    fn rename_repo(&mut self, owner: &str, name: &str, new_name: &str) -> Result<()> {
+        self.rest.rename_repo(owner, name, new_name)
+    }
+
+    fn create_release(
+        &mut self,
+        owner: &str,
+        name: &str,
+        tag: &str,
+        body: String,
+    ) -> Result<String> {
+        self.rest.create_release(owner, name, tag, body)
+    }
+
+    fn get_action(&self, opts: &ActionOptions) -> Result<Option<Action>> {
+        // For branch, the action of its latest commit is used.
+        let expression = match &opts.target {
+            ActionTarget::Commit(commit) => commit,
+            ActionTarget::Branch(branch) => branch,
+        };
+        let variables = json!({
+            "owner": opts.owner,
+            "name": opts.name,
+            "expression": expression,
+        });
+        let data: RepoData<ActionRepo> = self.rest.execute_graphql(ACTION_QUERY, variables)?;
+        Ok(data
+            .repository
+            .and_then(|repo| repo.object)
+            .and_then(|commit| commit.action()))
+    }
+
+    fn logs_job(&self, owner: &str, name: &str, id: u64, dst: &mut dyn Write) -> Result<()> {
+        self.rest.logs_job(owner, name, id, dst)
+    }
+
+    fn get_job(&self, owner: &str, name: &str, id: u64) -> Result<ActionJob> {
+        self.rest.get_job(owner, name, id)
+    }
+
+    fn get_issue(&self, owner: &str, name: &str, id: u64) -> Result<ApiIssue> {
+        self.rest.get_issue(owner, name, id)
+    }
+}
+
+impl GitHubGraphQL {
+    /// Build the GraphQL provider. GitHub requires auth for the GraphQL api, so
+    /// without token, the REST provider is returned.
+    pub fn build(remote_cfg: &RemoteConfig) -> Result<Box<dyn Provider>> {
+        let rest = GitHub::new(remote_cfg)?;
+        if !rest.has_token() {
+            return Ok(Box::new(rest));
+        }
+        Ok(Box::new(GitHubGraphQL { rest }))
+    }
+
+    /// The empty repositories have no default branch, use `main` like GitHub.
+    fn branch_name(branch: Option<BranchRef>) -> String {
+        branch
+            .map(|branch| branch.name)
+            .unwrap_or_else(|| String::from("main"))
+    }
+}
+
+#[cfg(test)]
+mod github_graphql_tests {
+    use crate::api::github_graphql::*;
+
+    #[test]
+    fn test_parse_action() {
+        let data = r#"{
+          "repository": {
+            "object": {
+              "oid": "4e1243bd22c66e76c2ba9eddc1f91394e57f9f83",
+              "message": "Fix typo",
+              "author": { "name": "fioncat", "email": "fioncat@example.com" },
+              "checkSuites": {
+                "nodes": [
+                  {
+                    "workflowRun": {
+                      "url": "https://github.com/fioncat/roxide/actions/runs/2",
+                      "workflow": { "name": "Test" }
+                    },
+                    "checkRuns": {
+                      "nodes": [
+                        {
+                          "databaseId": 21,
+                          "name": "unit",
+                          "status": "COMPLETED",
+                          "conclusion": "FAILURE",
+                          "detailsUrl": "https://github.com/fioncat/roxide/actions/runs/2/job/21"
+                        },
+                        {
+                          "databaseId": 22,
+                          "name": "lint",
+                          "status": "IN_PROGRESS",
+                          "conclusion": null,
+                          "detailsUrl": "https://github.com/fioncat/roxide/actions/runs/2/job/22"
+                        }
+                      ]
+                    }
+                  },
+                  {
+                    "workflowRun": null,
+                    "checkRuns": { "nodes": [] }
+                  },
+                  {
+                    "workflowRun": {
+                      "url": "https://github.com/fioncat/roxide/actions/runs/1",
+                      "workflow": { "name": "Build" }
+                    },
+                    "checkRuns": {
+                      "nodes": [
+                        {
+                          "databaseId": 11,
+                          "name": "build",
+                          "status": "COMPLETED",
+                          "conclusion": "SUCCESS",
+                          "detailsUrl": "https://github.com/fioncat/roxide/actions/runs/1/job/11"
+                        }
+                      ]
+                    }
+                  }
+                ]
+              }
+            }
+          }
+        }"#;
+        let data: RepoData<ActionRepo> = serde_json::from_str(data).unwrap();
+        let action = data.repository.unwrap().object.unwrap().action().unwrap();
+
+        assert_eq!(action.commit.message, "Fix typo");
+        assert_eq!(action.commit.author_name, "fioncat");
+        let names: Vec<_> = action.runs.iter().map(|run| run.name.as_str()).collect();
+        assert_eq!(names, vec!["Build", "Test"]);
+        let statuses: Vec<_> = action.runs[1].jobs.iter().map(|job| job.status).collect();
+        assert_eq!(
+            statuses,
+            vec![ActionJobStatus::Failed, ActionJobStatus::Running]
+        );
+        assert_eq!(action.failed_jobs(), vec!["Test/unit"]);
+
+        // Not a commit.
+        let data: RepoData<ActionRepo> =
+            serde_json::from_str(r#"{"repository": {"object": {}}}"#).unwrap();
+        assert!(data.repository.unwrap().object.unwrap().action().is_none());
+    }
+}
@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::api::*;
 use crate::config::{defaults, RemoteConfig};
+use crate::errors::ErrorKind;
+use crate::{debug, timings};
 
 #[derive(Debug, Deserialize)]
 struct GitLabRepo {
@@ -18,6 +20,19 @@ struct GitLabRepo {
     pub default_branch: String,
 
     pub web_url: String,
+
+    pub description: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default = "defaults::empty_string")]
+    pub visibility: String,
+    #[serde(default)]
+    pub star_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabGroup {
+    pub full_path: String,
 }
 
 impl GitLabRepo {
@@ -26,15 +41,50 @@ impl GitLabRepo {
             default_branch: self.default_branch,
             upstream: None,
             web_url: self.web_url,
+            description: self.description.filter(|desc| !desc.is_empty()),
+            archived: self.archived,
+            visibility: self.visibility,
+            stars: self.star_count,
         }
     }
 }
 
 #[derive(Debug, Deserialize)]
 struct MergeRequest {
+    iid: u64,
     web_url: String,
 }
 
+#[derive(Debug, Serialize)]
+struct AcceptMergeRequest {
+    squash: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct RenameProject<'a> {
+    name: &'a str,
+    path: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateRelease {
+    tag_name: String,
+    name: String,
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    #[serde(rename = "_links")]
+    links: ReleaseLinks,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseLinks {
+    #[serde(rename = "self")]
+    url: String,
+}
+
 #[derive(Debug, Serialize)]
 struct CreateMergeRequest {
     id: String,
@@ -42,6 +92,69 @@ struct CreateMergeRequest {
     target_branch: String,
     title: String,
     description: String,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    reviewer_ids: Vec<u64>,
+    /// Comma-separated label names.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    labels: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    iid: u64,
+    title: String,
+    web_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Todo {
+    id: u64,
+    project: Option<TodoProject>,
+    action_name: String,
+    target_type: String,
+    target: Option<TodoTarget>,
+    target_url: String,
+    body: String,
+    state: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TodoProject {
+    path_with_namespace: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TodoTarget {
+    title: Option<String>,
+}
+
+impl Todo {
+    fn api(self) -> Notification {
+        let title = match self.target.and_then(|target| target.title) {
+            Some(title) => title,
+            None => self.body,
+        };
+        Notification {
+            id: format!("{}", self.id),
+            repo: self
+                .project
+                .map(|project| project.path_with_namespace)
+                .unwrap_or_default(),
+            kind: self.target_type,
+            title,
+            reason: self.action_name,
+            unread: self.state == "pending",
+            url: self.target_url,
+            updated_at: self.updated_at,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -157,6 +270,12 @@ impl Provider for GitLab {
         Ok(repos)
     }
 
+    fn list_owners(&self) -> Result<Vec<String>> {
+        let path = format!("groups?order_by=path&per_page={}", self.per_page);
+        let groups = self.execute_get::<Vec<GitLabGroup>>(&path)?;
+        Ok(groups.into_iter().map(|group| group.full_path).collect())
+    }
+
     fn get_repo(&self, owner: &str, name: &str) -> Result<ApiRepo> {
         let id = format!("{owner}/{name}");
         let id_encode = urlencoding::encode(&id);
@@ -165,26 +284,23 @@ impl Provider for GitLab {
     }
 
     fn get_merge(&self, merge: MergeOptions) -> Result<Option<String>> {
-        if merge.upstream.is_some() {
-            bail!("GitLab now does not support upstream");
-        }
-        let id = format!("{}/{}", merge.owner, merge.name);
-        let id_encode = urlencoding::encode(&id);
-        let path = format!(
-            "projects/{id_encode}/merge_requests?state=opened&source_branch={}&target_branch={}",
-            merge.source, merge.target
-        );
-        let mut mrs = self.execute_get::<Vec<MergeRequest>>(&path)?;
-        if mrs.is_empty() {
-            return Ok(None);
-        }
-        Ok(Some(mrs.remove(0).web_url))
+        Ok(self.get_merge_request(&merge)?.map(|mr| mr.web_url))
     }
 
-    fn create_merge(&mut self, merge: MergeOptions, title: String, body: String) -> Result<String> {
+    fn create_merge(&mut self, merge: MergeOptions, create: CreateMergeOptions) -> Result<String> {
         if merge.upstream.is_some() {
             bail!("GitLab now does not support upstream");
         }
+        let mut reviewer_ids = Vec::with_capacity(create.reviewers.len());
+        for username in create.reviewers.iter() {
+            reviewer_ids.push(self.get_user_id(username)?);
+        }
+        // GitLab marks the MergeRequest as draft by the title prefix.
+        let title = if create.draft {
+            format!("Draft: {}", create.title)
+        } else {
+            create.title
+        };
         let id = format!("{}/{}", merge.owner, merge.name);
         let id_encode = urlencoding::encode(&id);
         let path = format!("projects/{id_encode}/merge_requests");
@@ -193,12 +309,58 @@ impl Provider for GitLab {
             source_branch: merge.source,
             target_branch: merge.target,
             title,
-            description: body,
+            description: create.body,
+            reviewer_ids,
+            labels: create.labels.join(","),
         };
         let mr = self.execute_post::<CreateMergeRequest, MergeRequest>(&path, create)?;
         Ok(mr.web_url)
     }
 
+    fn accept_merge(&mut self, merge: MergeOptions, method: MergeMethod) -> Result<String> {
+        if let MergeMethod::Rebase = method {
+            bail!("GitLab does not support rebase method when merging, please configure it in the project settings");
+        }
+        let mr = self.must_get_merge_request(&merge)?;
+        let id = format!("{}/{}", merge.owner, merge.name);
+        let id_encode = urlencoding::encode(&id);
+        let path = format!("projects/{id_encode}/merge_requests/{}/merge", mr.iid);
+        let body = AcceptMergeRequest {
+            squash: matches!(method, MergeMethod::Squash),
+        };
+        let body = serde_json::to_vec(&body).context("encode GitLab request body")?;
+        let req = self.build_request(&path, Method::PUT, Some(body))?;
+        let mr = self.execute::<MergeRequest>(req)?;
+        Ok(mr.web_url)
+    }
+
+    fn approve_merge(&mut self, merge: MergeOptions) -> Result<String> {
+        let mr = self.must_get_merge_request(&merge)?;
+        let id = format!("{}/{}", merge.owner, merge.name);
+        let id_encode = urlencoding::encode(&id);
+        let path = format!("projects/{id_encode}/merge_requests/{}/approve", mr.iid);
+        let req = self.build_request(&path, Method::POST, None)?;
+        self.execute_resp(req)?;
+        Ok(mr.web_url)
+    }
+
+    fn list_notifications(&self, all: bool) -> Result<Vec<Notification>> {
+        let path = if all {
+            format!("todos?per_page={}", self.per_page)
+        } else {
+            format!("todos?state=pending&per_page={}", self.per_page)
+        };
+        let todos = self.execute_get::<Vec<Todo>>(&path)?;
+        Ok(todos.into_iter().map(|todo| todo.api()).collect())
+    }
+
+    fn mark_notification_read(&mut self, id: &str) -> Result<()> {
+        let path = format!("todos/{id}/mark_as_done");
+        let req = self.build_request(&path, Method::POST, None)?;
+        self.execute_resp(req)?;
+        Ok(())
+    }
+
     fn search_repos(&self, query: &str) -> Result<Vec<String>> {
         let path = format!("search?scope=projects&search={}", query);
         let gitlab_repos = self.execute_get::<Vec<GitLabRepo>>(&path)?;
@@ -209,6 +371,39 @@ impl Provider for GitLab {
         Ok(repos)
     }
 
+    fn rename_repo(&mut self, owner: &str, name: &str, new_name: &str) -> Result<()> {
+        let id = format!("{owner}/{name}");
+        let id_encode = urlencoding::encode(&id);
+        let path = format!("projects/{id_encode}");
+        let body = RenameProject {
+            name: new_name,
+            path: new_name,
+        };
+        let body = serde_json::to_vec(&body).context("encode GitLab request body")?;
+        let req = self.build_request(&path, Method::PUT, Some(body))?;
+        self.execute_resp(req)?;
+        Ok(())
+    }
+
+    fn create_release(
+        &mut self,
+        owner: &str,
+        name: &str,
+        tag: &str,
+        body: String,
+    ) -> Result<String> {
+        let id = format!("{owner}/{name}");
+        let id_encode = urlencoding::encode(&id);
+        let path = format!("projects/{id_encode}/releases");
+        let create = CreateRelease {
+            tag_name: tag.to_string(),
+            name: tag.to_string(),
+            description: body,
+        };
+        let release = self.execute_post::<CreateRelease, Release>(&path, create)?;
+        Ok(release.links.url)
+    }
+
     fn get_action(&self, opts: &ActionOptions) -> Result<Option<Action>> {
         let target = match &opts.target {
             ActionTarget::Commit(sha) => format!("sha={sha}"),
@@ -325,13 +520,25 @@ impl Provider for GitLab {
             url: job.web_url,
         })
     }
+
+    fn get_issue(&self, owner: &str, name: &str, id: u64) -> Result<ApiIssue> {
+        let project_id = format!("{owner}/{name}");
+        let id_encode = urlencoding::encode(&project_id);
+        let path = format!("projects/{id_encode}/issues/{id}");
+        let issue = self.execute_get::<Issue>(&path)?;
+        Ok(ApiIssue {
+            id: issue.iid,
+            title: issue.title,
+            web_url: issue.web_url,
+        })
+    }
 }
 
 impl GitLab {
     const API_VERSION: u8 = 4;
 
-    pub fn build(remote_cfg: &RemoteConfig) -> Box<dyn Provider> {
-        let client = build_common_client(remote_cfg);
+    pub fn build(remote_cfg: &RemoteConfig) -> Result<Box<dyn Provider>> {
+        let client = build_common_client(remote_cfg)?;
         let domain = match &remote_cfg.api_domain {
             Some(domain) => domain.clone(),
             None => String::from("gitlab.com"),
@@ -339,12 +546,47 @@ impl GitLab {
 
         let url = format!("https://{domain}/api/v{}", Self::API_VERSION);
 
-        Box::new(GitLab {
+        Ok(Box::new(GitLab {
             token: remote_cfg.token.clone(),
             client,
             url,
             per_page: remote_cfg.list_limit,
-        })
+        }))
+    }
+
+    fn get_merge_request(&self, merge: &MergeOptions) -> Result<Option<MergeRequest>> {
+        if merge.upstream.is_some() {
+            bail!("GitLab now does not support upstream");
+        }
+        let id = format!("{}/{}", merge.owner, merge.name);
+        let id_encode = urlencoding::encode(&id);
+        let path = format!(
+            "projects/{id_encode}/merge_requests?state=opened&source_branch={}&target_branch={}",
+            merge.source, merge.target
+        );
+        let mut mrs = self.execute_get::<Vec<MergeRequest>>(&path)?;
+        if mrs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(mrs.remove(0)))
+    }
+
+    fn get_user_id(&self, username: &str) -> Result<u64> {
+        let path = format!("users?username={}", urlencoding::encode(username));
+        let users = self.execute_get::<Vec<User>>(&path)?;
+        match users.first() {
+            Some(user) => Ok(user.id),
+            None => {
+                Err(ErrorKind::NotFound.error(format!("could not find GitLab user '{username}'")))
+            }
+        }
+    }
+
+    fn must_get_merge_request(&self, merge: &MergeOptions) -> Result<MergeRequest> {
+        match self.get_merge_request(merge)? {
+            Some(mr) => Ok(mr),
+            None => bail!("could not find opened MergeRequest for '{}'", merge.source),
+        }
     }
 
     fn execute_get<T>(&self, path: &str) -> Result<T>
@@ -380,20 +622,36 @@ impl GitLab {
     }
 
     fn execute_resp(&self, req: Request) -> Result<Response> {
-        let resp = self.client.execute(req).context("GitLab http request")?;
+        let (method, url) = (req.method().clone(), req.url().clone());
+        debug!("GitLab request: {} {}", method, url);
+        let span = timings::span("api");
+        let resp = match self.client.execute(req) {
+            Ok(resp) => resp,
+            Err(err) if err.is_connect() || err.is_timeout() => {
+                return Err(ErrorKind::Network.error(format!("GitLab http request: {err}")));
+            }
+            Err(err) => return Err(err).context("GitLab http request"),
+        };
+        drop(span);
         let ok = resp.status().is_success();
+        debug!("GitLab response: {} {}: {}", method, url, resp.status());
         if ok {
             return Ok(resp);
         }
 
+        let kind = ErrorKind::from_http_status(resp.status().as_u16());
         let data = resp.bytes().context("read GitLab response body")?;
-        match serde_json::from_slice::<GitLabError>(&data) {
-            Ok(err) => bail!("GitLab api error: {}", err.error),
-            Err(_err) => bail!(
+        let msg = match serde_json::from_slice::<GitLabError>(&data) {
+            Ok(err) => format!("GitLab api error: {}", err.error),
+            Err(_err) => format!(
                 "unknown GitLab api error: {}",
                 String::from_utf8(data.to_vec())
                     .context("decode GitLab response to UTF-8 string")?
             ),
+        };
+        match kind {
+            Some(kind) => Err(kind.error(msg)),
+            None => bail!(msg),
         }
     }
 
@@ -1,22 +1,32 @@
 mod alias;
+pub mod auth;
 mod cache;
 pub mod github;
+mod github_graphql;
 mod gitlab;
 
+use std::borrow::Cow;
 use std::fmt::Display;
+use std::fs;
 use std::io::Write;
 use std::time::Duration;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
 use console::style;
 use reqwest::blocking::Client;
+use reqwest::{Certificate, NoProxy, Proxy};
 use serde::{Deserialize, Serialize};
 
 use crate::api::alias::Alias;
 use crate::api::cache::Cache;
 use crate::api::github::GitHub;
+use crate::api::github_graphql::GitHubGraphQL;
 use crate::api::gitlab::GitLab;
-use crate::config::{Config, ProviderType, RemoteConfig};
+use crate::config::{ApiStyle, Config, ProviderType, RemoteConfig};
+use crate::warn;
+
+pub use crate::api::cache::{list_cached_owners, list_cached_repos};
 
 #[derive(Debug, Serialize)]
 pub struct ProviderInfo {
@@ -37,6 +47,14 @@ impl Display for ProviderInfo {
     }
 }
 
+/// Represents an issue obtained from a [`Provider`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiIssue {
+    pub id: u64,
+    pub title: String,
+    pub web_url: String,
+}
+
 /// Represents repository information obtained from a [`Provider`].
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct ApiRepo {
@@ -51,6 +69,32 @@ pub struct ApiRepo {
 
     /// The web access URL for this repository. Typically, open it in a web browser.
     pub web_url: String,
+
+    /// The description of this repository.
+    pub description: Option<String>,
+
+    /// The archived repository is read-only, the pushes and MergeRequests are
+    /// rejected by the remote.
+    pub archived: bool,
+
+    /// The visibility, `public`, `private` or `internal`.
+    pub visibility: String,
+
+    /// The star count.
+    pub stars: u64,
+}
+
+impl ApiRepo {
+    /// Warn if this repository is archived, since the operations that write to
+    /// the remote will be rejected.
+    pub fn warn_archived(&self, name: impl AsRef<str>) {
+        if self.archived {
+            warn!(
+                "Repo {} is archived on the remote, it is read-only",
+                name.as_ref()
+            );
+        }
+    }
 }
 
 /// Information about the fork source of the repository.
@@ -89,6 +133,21 @@ pub struct MergeOptions {
     pub target: String,
 }
 
+/// The content of the MergeRequest to create.
+#[derive(Debug, Clone, Default)]
+pub struct CreateMergeOptions {
+    pub title: String,
+    pub body: String,
+
+    /// Create as draft, which cannot be merged until it is marked as ready.
+    pub draft: bool,
+
+    /// The usernames to request review from.
+    pub reviewers: Vec<String>,
+    /// The labels to add, the nonexistent ones are created by the remote.
+    pub labels: Vec<String>,
+}
+
 impl Display for MergeOptions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}/{}", self.owner, self.name)
@@ -115,6 +174,56 @@ impl MergeOptions {
     }
 }
 
+/// A notification for the current user. In GitHub, this is the notification
+/// thread; In GitLab, this is the pending todo.
+#[derive(Debug)]
+pub struct Notification {
+    /// The unique ID, used to mark the notification as read.
+    pub id: String,
+
+    /// The repository full name, `{owner}/{name}`.
+    pub repo: String,
+
+    /// The type of the subject, like `PullRequest`, `Issue`, etc.
+    pub kind: String,
+
+    /// The subject title.
+    pub title: String,
+
+    /// Why the user received this notification, like `mention`, `review_requested`,
+    /// etc.
+    pub reason: String,
+
+    pub unread: bool,
+
+    /// The web url of the subject.
+    pub url: String,
+
+    /// The update time, in RFC3339 format.
+    pub updated_at: String,
+}
+
+/// The method to accept (merge) a MergeRequest.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum MergeMethod {
+    /// Create a merge commit.
+    Merge,
+    /// Squash all the commits into one.
+    Squash,
+    /// Rebase the commits onto the target branch.
+    Rebase,
+}
+
+impl MergeMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::Squash => "squash",
+            Self::Rebase => "rebase",
+        }
+    }
+}
+
 /// The options to fetch [`Action`] from remote.
 #[derive(Debug)]
 pub struct ActionOptions {
@@ -250,6 +359,24 @@ impl Display for ActionJobStatus {
     }
 }
 
+impl Action {
+    /// Return the names (`{run}/{job}`) of the jobs that failed or were canceled.
+    pub fn failed_jobs(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for run in self.runs.iter() {
+            for job in run.jobs.iter() {
+                if matches!(
+                    job.status,
+                    ActionJobStatus::Failed | ActionJobStatus::Canceled
+                ) {
+                    names.push(format!("{}/{}", run.name, job.name));
+                }
+            }
+        }
+        names
+    }
+}
+
 impl ActionJobStatus {
     pub fn is_completed(&self) -> bool {
         !matches!(
@@ -271,6 +398,11 @@ pub trait Provider {
     /// Retrieve all repositories under a given owner.
     fn list_repos(&self, owner: &str) -> Result<Vec<String>>;
 
+    /// Retrieve the owners accessible to the current user. For GitLab, these
+    /// are the groups and subgroups (full paths, like `group/subgroup`); For
+    /// GitHub, these are the user itself and its organizations.
+    fn list_owners(&self) -> Result<Vec<String>>;
+
     /// Retrieve information for a specific repository.
     fn get_repo(&self, owner: &str, name: &str) -> Result<ApiRepo>;
 
@@ -281,11 +413,37 @@ pub trait Provider {
     fn get_merge(&self, merge: MergeOptions) -> Result<Option<String>>;
 
     /// Create MergeRequest (PullRequest in GitHub), and return its URL.
-    fn create_merge(&mut self, merge: MergeOptions, title: String, body: String) -> Result<String>;
+    fn create_merge(&mut self, merge: MergeOptions, create: CreateMergeOptions) -> Result<String>;
+
+    /// Accept (merge) an opened MergeRequest with `method`, and return its URL.
+    fn accept_merge(&mut self, merge: MergeOptions, method: MergeMethod) -> Result<String>;
+
+    /// Approve an opened MergeRequest, and return its URL.
+    fn approve_merge(&mut self, merge: MergeOptions) -> Result<String>;
+
+    /// List notifications for the current user. If `all` is `false`, only the
+    /// unread ones are returned.
+    fn list_notifications(&self, all: bool) -> Result<Vec<Notification>>;
+
+    /// Mark the notification as read.
+    fn mark_notification_read(&mut self, id: &str) -> Result<()>;
 
     /// Search repositories using the specified `query`.
     fn search_repos(&self, query: &str) -> Result<Vec<String>>;
 
+    /// Rename a repository, the owner will not be changed.
+    fn rename_repo(&mut self, owner: &str, name: &str, new_name: &str) -> Result<()>;
+
+    /// Create a release for an existing tag, with `body` as its notes. Return the
+    /// url of the release.
+    fn create_release(
+        &mut self,
+        owner: &str,
+        name: &str,
+        tag: &str,
+        body: String,
+    ) -> Result<String>;
+
     /// Return the CI/CD action.
     fn get_action(&self, opts: &ActionOptions) -> Result<Option<Action>>;
 
@@ -294,14 +452,36 @@ pub trait Provider {
 
     /// Get the info of a specific CI/CD job based on its ID.
     fn get_job(&self, owner: &str, name: &str, id: u64) -> Result<ActionJob>;
+
+    /// Get the issue by its number (`iid` in GitLab).
+    fn get_issue(&self, owner: &str, name: &str, id: u64) -> Result<ApiIssue>;
 }
 
-/// Build common http client.
-fn build_common_client(remote_cfg: &RemoteConfig) -> Client {
-    Client::builder()
-        .timeout(Duration::from_secs(remote_cfg.api_timeout))
-        .build()
-        .unwrap()
+/// Build common http client, with the proxy and TLS settings of the remote.
+fn build_common_client(remote_cfg: &RemoteConfig) -> Result<Client> {
+    let mut builder = Client::builder().timeout(Duration::from_secs(remote_cfg.api_timeout));
+    if let Some(proxy) = remote_cfg.proxy.as_ref() {
+        let no_proxy = remote_cfg
+            .no_proxy
+            .as_deref()
+            .and_then(NoProxy::from_string);
+        let proxy = Proxy::all(proxy)
+            .with_context(|| format!("invalid proxy '{proxy}'"))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = remote_cfg.ca_bundle.as_ref() {
+        let data = fs::read(path).with_context(|| format!("read ca bundle '{path}'"))?;
+        let certs = Certificate::from_pem_bundle(&data)
+            .with_context(|| format!("parse ca bundle '{path}'"))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if remote_cfg.insecure_skip_verify {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().context("build http client")
 }
 
 /// Build a [`Provider`] object based on the configuration.
@@ -331,7 +511,7 @@ pub fn build_provider(
         );
     }
 
-    let mut provider = build_raw_provider(remote_cfg);
+    let mut provider = build_raw_provider(cfg, remote_cfg)?;
 
     if remote_cfg.cache_hours > 0 {
         let cache = Cache::new(cfg, remote_cfg, provider, force)?;
@@ -346,11 +526,24 @@ pub fn build_provider(
     Ok(provider)
 }
 
-pub fn build_raw_provider(remote_cfg: &RemoteConfig) -> Box<dyn Provider> {
-    match remote_cfg.provider.as_ref().unwrap() {
-        ProviderType::Github => GitHub::build(remote_cfg),
-        ProviderType::Gitlab => GitLab::build(remote_cfg),
-    }
+/// Build a [`Provider`] object without the cache and alias layers. The token is
+/// resolved by [`auth::resolve_token`].
+pub fn build_raw_provider(cfg: &Config, remote_cfg: &RemoteConfig) -> Result<Box<dyn Provider>> {
+    let token = auth::resolve_token(cfg, remote_cfg)?;
+    let remote_cfg = if token == remote_cfg.token {
+        Cow::Borrowed(remote_cfg)
+    } else {
+        let mut remote_cfg = remote_cfg.clone();
+        remote_cfg.token = token;
+        Cow::Owned(remote_cfg)
+    };
+    Ok(match remote_cfg.provider.as_ref().unwrap() {
+        ProviderType::Github => match remote_cfg.api_style {
+            ApiStyle::Rest => GitHub::build(&remote_cfg)?,
+            ApiStyle::Graphql => GitHubGraphQL::build(&remote_cfg)?,
+        },
+        ProviderType::Gitlab => GitLab::build(&remote_cfg)?,
+    })
 }
 
 #[cfg(test)]
@@ -410,6 +603,12 @@ pub mod api_tests {
             }
         }
 
+        fn list_owners(&self) -> Result<Vec<String>> {
+            let mut owners: Vec<String> = self.repos.keys().cloned().collect();
+            owners.sort_unstable();
+            Ok(owners)
+        }
+
         fn get_repo(&self, owner: &str, name: &str) -> Result<ApiRepo> {
             match self.repos.get(owner) {
                 Some(repos) => match repos.iter().find_map(|repo_name| {
@@ -418,6 +617,10 @@ pub mod api_tests {
                             default_branch: String::from("main"),
                             upstream: None,
                             web_url: String::new(),
+                            description: None,
+                            archived: false,
+                            visibility: String::from("public"),
+                            stars: 0,
                         })
                     } else {
                         None
@@ -438,17 +641,61 @@ pub mod api_tests {
             }
         }
 
-        fn create_merge(&mut self, merge: MergeOptions, _: String, _: String) -> Result<String> {
+        fn create_merge(&mut self, merge: MergeOptions, _: CreateMergeOptions) -> Result<String> {
             self.get_repo(&merge.owner, &merge.name)?;
             let merge = merge.to_string();
             self.merges.insert(merge.clone());
             Ok(merge)
         }
 
+        fn accept_merge(&mut self, merge: MergeOptions, _: MergeMethod) -> Result<String> {
+            if !self.merges.remove(merge.to_string().as_str()) {
+                bail!("could not find merge {merge}");
+            }
+            Ok(merge.to_string())
+        }
+
+        fn approve_merge(&mut self, merge: MergeOptions) -> Result<String> {
+            match self.get_merge(merge)? {
+                Some(merge) => Ok(merge),
+                None => bail!("could not find merge"),
+            }
+        }
+
+        fn list_notifications(&self, _all: bool) -> Result<Vec<Notification>> {
+            Ok(Vec::new())
+        }
+
+        fn mark_notification_read(&mut self, _id: &str) -> Result<()> {
+            Ok(())
+        }
+
         fn search_repos(&self, _query: &str) -> Result<Vec<String>> {
             Ok(Vec::new())
         }
 
+        fn rename_repo(&mut self, owner: &str, name: &str, new_name: &str) -> Result<()> {
+            let names = match self.repos.get_mut(owner) {
+                Some(names) => names,
+                None => bail!("could not find owner {owner}"),
+            };
+            match names.iter_mut().find(|repo| *repo == name) {
+                Some(repo) => *repo = new_name.to_string(),
+                None => bail!("could not find repo {owner}/{name}"),
+            }
+            Ok(())
+        }
+
+        fn create_release(
+            &mut self,
+            owner: &str,
+            name: &str,
+            tag: &str,
+            _body: String,
+        ) -> Result<String> {
+            Ok(format!("https://example.com/{owner}/{name}/releases/{tag}"))
+        }
+
         fn get_action(&self, _opts: &ActionOptions) -> Result<Option<Action>> {
             Ok(None)
         }
@@ -466,5 +713,29 @@ pub mod api_tests {
         fn get_job(&self, _owner: &str, _name: &str, _id: u64) -> Result<ActionJob> {
             todo!()
         }
+
+        fn get_issue(&self, owner: &str, name: &str, id: u64) -> Result<ApiIssue> {
+            Ok(ApiIssue {
+                id,
+                title: format!("Test issue {id}"),
+                web_url: format!("https://example.com/{owner}/{name}/issues/{id}"),
+            })
+        }
+    }
+
+    #[test]
+    fn test_build_common_client() {
+        let mut remote_cfg = crate::config::defaults::remote("test");
+        remote_cfg.proxy = Some(String::from("http://127.0.0.1:7890"));
+        remote_cfg.no_proxy = Some(String::from("localhost,.example.com"));
+        remote_cfg.insecure_skip_verify = true;
+        build_common_client(&remote_cfg).unwrap();
+
+        remote_cfg.proxy = Some(String::from("not a proxy"));
+        assert!(build_common_client(&remote_cfg).is_err());
+
+        remote_cfg.proxy = None;
+        remote_cfg.ca_bundle = Some(String::from("_test/not_exists.pem"));
+        assert!(build_common_client(&remote_cfg).is_err());
     }
 }
@@ -0,0 +1,186 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Error, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::errors::{self, SilentExit};
+use crate::filelock::FileLock;
+use crate::utils;
+
+/// The repositories changed (upserted or removed) in the database by the
+/// current command, see [`touch_repo`].
+static TOUCHED_REPOS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Mark the repository as affected by the current command, the database calls
+/// this when the changes are saved.
+pub fn touch_repo(name: String) {
+    if let Ok(mut repos) = TOUCHED_REPOS.lock() {
+        repos.insert(name);
+    }
+}
+
+/// A state-changing roxide invocation, saved in [`AuditHistory`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub id: u64,
+
+    pub time: u64,
+
+    /// The subcommand name.
+    pub command: String,
+    /// The full command line args, without the program name.
+    pub args: Vec<String>,
+    pub cwd: String,
+
+    /// The repositories changed in the database, `remote:owner/name`.
+    pub repos: Vec<String>,
+
+    pub elapsed_ms: u64,
+
+    pub exit_code: i32,
+    /// The failure reason, [`None`] means the command succeeded or was
+    /// canceled.
+    pub error: Option<String>,
+}
+
+/// The audit history of the state-changing commands, stored in
+/// `{metadir}/audit`. The records older than `audit.retention_days` are dropped
+/// when saving, and at most [`AuditHistory::LIMIT`] records are kept.
+pub struct AuditHistory {
+    path: PathBuf,
+}
+
+impl AuditHistory {
+    const LIMIT: usize = 5000;
+
+    pub fn new(cfg: &Config) -> AuditHistory {
+        AuditHistory {
+            path: cfg.get_meta_dir().join("audit"),
+        }
+    }
+
+    /// Record the command result, do nothing if the auditing is disabled.
+    pub fn record(
+        &self,
+        cfg: &Config,
+        command: &str,
+        elapsed: Duration,
+        result: &Result<()>,
+    ) -> Result<()> {
+        if cfg.audit.retention_days == 0 {
+            return Ok(());
+        }
+        let (exit_code, error) = match result {
+            Ok(()) => (0, None),
+            Err(err) => Self::parse_error(err),
+        };
+        let repos = match TOUCHED_REPOS.lock() {
+            Ok(mut repos) => std::mem::take(&mut *repos).into_iter().collect(),
+            Err(_) => Vec::new(),
+        };
+        let cwd = env::current_dir()
+            .map(|dir| format!("{}", dir.display()))
+            .unwrap_or_default();
+        let record = AuditRecord {
+            id: 0,
+            time: cfg.now(),
+            command: command.to_string(),
+            args: env::args().skip(1).collect(),
+            cwd,
+            repos,
+            elapsed_ms: elapsed.as_millis() as u64,
+            exit_code,
+            error,
+        };
+        self.save(cfg, record)
+    }
+
+    fn parse_error(err: &Error) -> (i32, Option<String>) {
+        let code = errors::exit_code(err, errors::CODE_COMMAND_FAILED);
+        match err.downcast_ref::<SilentExit>() {
+            Some(_) => (code, None),
+            None => (code, Some(format!("{err:#}"))),
+        }
+    }
+
+    fn save(&self, cfg: &Config, mut record: AuditRecord) -> Result<()> {
+        let _lock = FileLock::acquire(cfg, "audit")?;
+        let mut records = self.list()?;
+        record.id = records.last().map(|record| record.id + 1).unwrap_or(1);
+        records.push(record);
+
+        let since = cfg
+            .now()
+            .saturating_sub(cfg.audit.retention_days * utils::DAY);
+        records.retain(|record| record.time >= since);
+        if records.len() > Self::LIMIT {
+            records.drain(..records.len() - Self::LIMIT);
+        }
+
+        let data = bincode::serialize(&records).context("encode audit history")?;
+        utils::write_file(&self.path, &data)
+    }
+
+    /// List the saved records, in the order of running.
+    pub fn list(&self) -> Result<Vec<AuditRecord>> {
+        match fs::read(&self.path) {
+            Ok(data) => bincode::deserialize(&data).context("decode audit history"),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err)
+                .with_context(|| format!("read audit history file '{}'", self.path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use anyhow::bail;
+
+    use crate::audit::*;
+    use crate::config::config_tests;
+
+    #[test]
+    fn test_audit() {
+        let mut cfg = config_tests::load_test_config("audit");
+        let history = AuditHistory::new(&cfg);
+        let _ = fs::remove_file(&history.path);
+
+        touch_repo(String::from("github:fioncat/roxide"));
+        history
+            .record(&cfg, "remove", Duration::from_millis(10), &Ok(()))
+            .unwrap();
+        let failed: Result<()> = (|| bail!("remove failed"))();
+        history
+            .record(&cfg, "sync", Duration::from_millis(20), &failed)
+            .unwrap();
+        let canceled: Result<()> = Err(anyhow::anyhow!(SilentExit { code: 130 }));
+        history
+            .record(&cfg, "clean", Duration::from_millis(0), &canceled)
+            .unwrap();
+
+        let records = history.list().unwrap();
+        let ids: Vec<_> = records.iter().map(|record| record.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert!(records[0]
+            .repos
+            .contains(&String::from("github:fioncat/roxide")));
+        assert_eq!(records[1].exit_code, errors::CODE_COMMAND_FAILED);
+        assert_eq!(records[1].error.as_deref(), Some("remove failed"));
+        assert_eq!(records[2].exit_code, errors::CODE_SILENT_EXIT + 130);
+        assert_eq!(records[2].error, None);
+
+        // Disabled.
+        cfg.audit.retention_days = 0;
+        history
+            .record(&cfg, "remove", Duration::from_millis(10), &Ok(()))
+            .unwrap();
+        assert_eq!(history.list().unwrap().len(), 3);
+    }
+}
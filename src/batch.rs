@@ -1,12 +1,15 @@
-use std::sync::mpsc::{self, Receiver};
+use std::env;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use console::style;
 
-use crate::{term, utils};
+use crate::interrupt::{self, Interrupt};
+use crate::progress::MultiProgress;
+use crate::{progress, term, utils};
 
 /// `Task` is used to represent a concurrent task that needs to be executed.
 ///
@@ -32,6 +35,10 @@ enum Report<R> {
     /// Indicates that the task has been completed. `usize` is the current task
     /// index, and `Result<R>` represents the execution result of the task.
     Done(usize, Result<R>),
+
+    /// Indicates that the task is skipped because of Ctrl-C, `usize` is the task
+    /// index.
+    Cancelled(usize),
 }
 
 /// `Tracker` is used to track the execution status of batch tasks and output the
@@ -58,10 +65,23 @@ struct Tracker<R> {
 
     ok_count: usize,
     fail_count: usize,
+    cancel_count: usize,
 
     /// If tasks failed, display their error messages.
     show_fail: bool,
     fail_message: Option<Vec<(String, String)>>,
+
+    /// In quiet mode, the tracing line is not rendered, only the completed
+    /// tasks are printed. See: [`progress::is_quiet`].
+    quiet: bool,
+
+    /// The bars of the workers, rendered under the tracing line.
+    multi: Arc<MultiProgress>,
+    /// The number of lines rendered at the bottom, they are cleared before
+    /// printing anything else.
+    rendered: usize,
+
+    start: Instant,
 }
 
 impl<R> Tracker<R> {
@@ -73,6 +93,8 @@ impl<R> Tracker<R> {
     const SEP_SIZE: usize = Self::SEP.len();
     const OMIT_SIZE: usize = Self::OMIT.len();
 
+    const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
     /// Create a Tracker, call [`Tracker::wait`] later to start tracking.
     ///
     /// # Arguments
@@ -82,12 +104,14 @@ impl<R> Tracker<R> {
     /// * `total` - The expected number of tasks. Tracking will stop when the number
     ///   of completed tasks reaches this value.
     /// * `show_fail` - If `true`, show error messages for tasks after they fail.
-    fn new(desc: &str, total: usize, show_fail: bool) -> Tracker<R> {
+    /// * `multi` - The bars of the workers.
+    fn new(desc: &str, total: usize, show_fail: bool, multi: Arc<MultiProgress>) -> Tracker<R> {
         let desc_pure = String::from(desc);
         let desc = style(desc).cyan().bold().to_string();
         let desc_size = Self::get_size(&desc);
 
         let total_pad = total.to_string().chars().count();
+        let quiet = progress::is_quiet();
 
         Tracker {
             total,
@@ -100,8 +124,14 @@ impl<R> Tracker<R> {
             desc_head: " ".repeat(desc_size),
             ok_count: 0,
             fail_count: 0,
+            cancel_count: 0,
             show_fail,
             fail_message: None,
+            quiet,
+            multi,
+            // The empty line printed after the title.
+            rendered: if quiet { 0 } else { 1 },
+            start: Instant::now(),
         }
     }
 
@@ -112,30 +142,49 @@ impl<R> Tracker<R> {
     /// If `show_fail` passed to [`Tracker::new`] is `true`, error messages will be
     /// printed when tasks fail.
     fn wait(mut self, rx: Receiver<Report<R>>) -> Vec<Result<R>> {
-        let start = Instant::now();
+        self.start = Instant::now();
         while self.done.len() < self.total {
-            match rx.recv().unwrap() {
+            // The workers' progress is not sent as reports, redraw it at
+            // intervals when nothing else happens.
+            let report = match rx.recv_timeout(Self::REPORT_INTERVAL) {
+                Ok(report) => report,
+                Err(RecvTimeoutError::Timeout) => {
+                    if !self.quiet && self.multi.take_changed() {
+                        self.clear();
+                        self.draw();
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => panic!("batch workers are gone"),
+            };
+            match report {
                 Report::Running(idx, name) => self.trace_running(idx, name),
                 Report::Done(idx, result) => self.trace_done(idx, result),
+                Report::Cancelled(idx) => {
+                    self.cancel_count += 1;
+                    self.done.push((idx, Err(anyhow!("cancelled"))));
+                }
             }
         }
-        let end = Instant::now();
-        let elapsed_time = end - start;
+        let elapsed_time = self.start.elapsed();
 
-        let result = if self.fail_count > 0 {
+        let result = if self.cancel_count > 0 {
+            style("interrupted").yellow().to_string()
+        } else if self.fail_count > 0 {
             style("failed").red().to_string()
         } else {
             style("ok").green().to_string()
         };
 
-        term::cursor_up();
+        self.clear();
         eprintln!();
         eprintln!(
-            "{} result: {}. {} ok; {} failed; finished in {}",
+            "{} result: {}. {} ok; {} failed; {} cancelled; finished in {}",
             self.desc_pure,
             result,
             self.ok_count,
             self.fail_count,
+            self.cancel_count,
             utils::format_elapsed(elapsed_time),
         );
         if let Some(fail_message) = self.fail_message.as_ref() {
@@ -147,8 +196,7 @@ impl<R> Tracker<R> {
             eprintln!();
         }
 
-        self.done
-            .sort_unstable_by(|(idx1, _), (idx2, _)| idx1.cmp(idx2));
+        self.done.sort_unstable_by_key(|(idx1, _)| *idx1);
 
         let results: Vec<_> = self.done.into_iter().map(|(_, result)| result).collect();
 
@@ -158,9 +206,11 @@ impl<R> Tracker<R> {
     /// Print running task on terminal.
     fn trace_running(&mut self, idx: usize, name: String) {
         self.running.push((idx, name));
-        let line = self.render();
-        term::cursor_up();
-        eprintln!("{}", line);
+        if self.quiet {
+            return;
+        }
+        self.clear();
+        self.draw();
     }
 
     /// Print completed task on terminal.
@@ -177,7 +227,7 @@ impl<R> Tracker<R> {
             None => return,
         };
 
-        term::cursor_up();
+        self.clear();
         match result.as_ref() {
             Ok(_) => {
                 self.ok_count += 1;
@@ -196,13 +246,35 @@ impl<R> Tracker<R> {
             }
         }
         self.done.push((idx, result));
+        if self.quiet {
+            return;
+        }
+        self.draw();
+    }
+
+    /// Clear the lines rendered by [`Tracker::draw`].
+    fn clear(&mut self) {
+        for _ in 0..self.rendered {
+            term::cursor_up();
+        }
+        self.rendered = 0;
+    }
+
+    /// Print the tracing line and the bars of the workers at the bottom.
+    fn draw(&mut self) {
         let line = self.render();
         eprintln!("{}", line);
+        self.multi.take_changed();
+        let bars = self.multi.render();
+        for bar in bars.iter() {
+            eprintln!("{}", bar);
+        }
+        self.rendered = bars.len() + 1;
     }
 
     /// Render tracing line. The format is:
     ///
-    /// * `{desc} {progress_bar} ({current_num}/{total_num}) {running}...`
+    /// * `{desc} {progress_bar} ({current_num}/{total_num}, ETA {eta}) {running}...`
     ///
     /// The tracing line will adaptively change based on the current terminal width.
     /// If the terminal width is too small, some information at the end will be
@@ -236,7 +308,7 @@ impl<R> Tracker<R> {
 
         // Render tag.
         let tag = self.render_tag();
-        let tag_size = Self::get_size(&bar);
+        let tag_size = Self::get_size(&tag);
         if Self::get_size(&line) + tag_size > term_size {
             return line;
         }
@@ -258,11 +330,15 @@ impl<R> Tracker<R> {
         line
     }
 
-    /// The tracing tag, `({current}/{total})`.
+    /// The tracing tag, `({current}/{total}, ETA {eta})`, the ETA is omitted
+    /// before the first task is done.
     fn render_tag(&self) -> String {
         let pad = self.total_pad;
         let current = format!("{:pad$}", self.done.len(), pad = pad);
-        format!("({current}/{})", self.total)
+        match progress::render_eta(self.start, self.done.len() as u64, self.total as u64) {
+            Some(eta) => format!("({current}/{}, {eta})", self.total),
+            None => format!("({current}/{})", self.total),
+        }
     }
 
     /// The running items, `item0, item1, ...`.
@@ -310,7 +386,20 @@ where
     R: Send + 'static,
     T: Task<R> + Send + 'static,
 {
-    let results = run(desc, tasks, true);
+    must_run_with(desc, tasks, 0)
+}
+
+/// Similar to [`must_run`], but with the number of `workers`, `0` means the
+/// default, see [`get_worker_len`].
+pub fn must_run_with<T, R>(desc: &str, tasks: Vec<(String, T)>, workers: usize) -> Result<Vec<R>>
+where
+    R: Send + 'static,
+    T: Task<R> + Send + 'static,
+{
+    let results = run(desc, tasks, true, workers);
+    if interrupt::is_interrupted() {
+        bail!("{desc} is interrupted, the partial results are discarded");
+    }
     if !is_ok(&results) {
         bail!("{desc} failed");
     }
@@ -321,17 +410,26 @@ where
 /// tasks are completed or an error occurs. Return the execution results of these
 /// tasks.
 ///
-/// We will start working threads equal to the number of CPU cores on the current
-/// machine to execute tasks.
+/// By default, we will start working threads equal to the number of CPU cores on
+/// the current machine to execute tasks, see [`get_worker_len`].
 ///
 /// For how to define the execution function for tasks, see: [`Task`].
 ///
+/// When Ctrl-C is pressed, the running tasks are left to finish, and the pending
+/// ones are cancelled (with error results).
+///
 /// # Arguments
 ///
 /// * `desc` - A descriptive string for the task, which will be printed in the terminal.
 /// * `tasks` - The tasks list to execute.
 /// * `show_fail` - If `true`, show error messages for tasks after they fail.
-pub fn run<T, R>(desc: &str, tasks: Vec<(String, T)>, show_fail: bool) -> Vec<Result<R>>
+/// * `workers` - The number of workers, `0` means the default.
+pub fn run<T, R>(
+    desc: &str,
+    tasks: Vec<(String, T)>,
+    show_fail: bool,
+    workers: usize,
+) -> Vec<Result<R>>
 where
     R: Send + 'static,
     T: Task<R> + Send + 'static,
@@ -341,14 +439,16 @@ where
         return vec![];
     }
 
-    // Set the number of workers to the number of cpu cores to maximize the use of
-    // multicore cpu.
-    // Here num_cpus can guarantee that the number of cores returned is greater
-    // than 0.
-    let worker_len = num_cpus::get();
+    let worker_len = get_worker_len(workers, task_len);
     assert_ne!(worker_len, 0);
 
-    let (task_tx, task_rx) = mpsc::channel::<(usize, String, T)>();
+    let interrupt = Interrupt::catch();
+
+    // The task channel is bounded to the worker count, the producer is blocked
+    // when the workers are busy. Note that this only limits the running tasks,
+    // the tasks themselves are built by the caller up front (they are small,
+    // usually one per repository), their memory is not bounded here.
+    let (task_tx, task_rx) = mpsc::sync_channel::<(usize, String, T)>(worker_len);
     // By default, mpsc does not support multiple threads using the same consumer.
     // But our multiple workers need to preempt the task queue. Therefore, Arc+Mutex
     // is used here to allow the consumer of mpsc to be preempted by multiple threads
@@ -363,58 +463,100 @@ where
         .bold()
         .cyan()
         .underlined();
-    eprintln!("{}\n", title);
+    if progress::is_quiet() {
+        eprintln!("{}", title);
+    } else {
+        eprintln!("{}\n", title);
+    }
+    let multi = MultiProgress::new(worker_len);
     let mut handlers = Vec::with_capacity(worker_len);
-    for _ in 0..worker_len {
+    for slot in 0..worker_len {
         let task_shared_rx = Arc::clone(&task_shared_rx);
         let report_tx = report_tx.clone();
-        let handler = thread::spawn(move || loop {
-            // Try to preempt the shared mpsc consumer. If there are other threads
-            // occupying the consumer at this time, it will be blocked here.
-            let task_rx = match task_shared_rx.lock() {
-                Ok(rx) => rx,
-                Err(_) => return,
-            };
-
-            // Consume a task from shared task channel.
-            let recv = task_rx.recv();
-            // After acquiring a task, the consumer is released immediately so that
-            // other workers can preempt other tasks. Otherwise, while the worker is
-            // processing this task, other workers will be blocked when preempting
-            // the task, cause tasks cannot be processed asynchronously.
-            drop(task_rx);
-
-            if let Ok((idx, name, task)) = recv {
-                report_tx.send(Report::Running(idx, name)).unwrap();
-                // Running message reporting will be done by task itself.
-                let result = task.run();
-                report_tx.send(Report::Done(idx, result)).unwrap();
-            } else {
-                // task_rx is closed, it means that all tasks have been processed.
-                // The worker can exit now.
-                return;
+        let multi = Arc::clone(&multi);
+        let handler = thread::spawn(move || {
+            multi.attach(slot);
+            loop {
+                // Try to preempt the shared mpsc consumer. If there are other threads
+                // occupying the consumer at this time, it will be blocked here.
+                let task_rx = match task_shared_rx.lock() {
+                    Ok(rx) => rx,
+                    Err(_) => return,
+                };
+
+                // Consume a task from shared task channel.
+                let recv = task_rx.recv();
+                // After acquiring a task, the consumer is released immediately so that
+                // other workers can preempt other tasks. Otherwise, while the worker is
+                // processing this task, other workers will be blocked when preempting
+                // the task, cause tasks cannot be processed asynchronously.
+                drop(task_rx);
+
+                if let Ok((idx, name, task)) = recv {
+                    if interrupt::is_interrupted() {
+                        report_tx.send(Report::Cancelled(idx)).unwrap();
+                        continue;
+                    }
+                    multi.start(slot, &name);
+                    report_tx.send(Report::Running(idx, name)).unwrap();
+                    // Running message reporting will be done by task itself.
+                    let result = task.run();
+                    multi.end(slot);
+                    report_tx.send(Report::Done(idx, result)).unwrap();
+                } else {
+                    // task_rx is closed, it means that all tasks have been processed.
+                    // The worker can exit now.
+                    return;
+                }
             }
         });
         handlers.push(handler);
     }
 
-    // Produce all tasks to workers.
-    for (idx, (name, task)) in tasks.into_iter().enumerate() {
-        task_tx.send((idx, name, task)).unwrap();
-    }
-    drop(task_tx);
+    // Produce all tasks to workers in background, the main thread is used to
+    // track the reports.
+    let producer = thread::spawn(move || {
+        for (idx, (name, task)) in tasks.into_iter().enumerate() {
+            if task_tx.send((idx, name, task)).is_err() {
+                return;
+            }
+        }
+    });
 
-    let tracker = Tracker::new(desc, task_len, show_fail);
+    let tracker = Tracker::new(desc, task_len, show_fail, multi);
     let results = tracker.wait(report_rx);
 
     // Wait for all workers done.
+    producer.join().unwrap();
     for handler in handlers {
         handler.join().unwrap();
     }
+    drop(interrupt);
 
     results
 }
 
+/// Return the number of workers, which is also the limit of in-flight tasks.
+/// The `workers` (usually from config or flag) is used if it is not `0`. By
+/// default, it is the number of cpu cores to maximize the use of multicore cpu,
+/// use env `ROXIDE_BATCH_LIMIT` to lower it (such as for a huge workspace on a
+/// slow disk). It is never greater than the number of tasks.
+pub fn get_worker_len(workers: usize, task_len: usize) -> usize {
+    let limit = env::var("ROXIDE_BATCH_LIMIT").ok();
+    parse_worker_len(workers, limit.as_deref(), task_len)
+}
+
+fn parse_worker_len(workers: usize, limit: Option<&str>, task_len: usize) -> usize {
+    let limit = match limit.and_then(|limit| limit.parse::<usize>().ok()) {
+        _ if workers > 0 => workers,
+        Some(limit) if limit > 0 => limit,
+        // Here num_cpus can guarantee that the number of cores returned is
+        // greater than 0.
+        _ => num_cpus::get(),
+    };
+    limit.min(task_len)
+}
+
 /// Returns `true` if all tasks are ok.
 pub fn is_ok<R>(results: &Vec<Result<R>>) -> bool {
     for result in results {
@@ -451,7 +593,7 @@ mod batch_tests {
             tasks.push((format!("Task-{i}"), task));
         }
 
-        let results: Vec<usize> = run("Test", tasks, false)
+        let results: Vec<usize> = run("Test", tasks, false, 0)
             .into_iter()
             .map(|result| result.unwrap())
             .collect();
@@ -460,4 +602,16 @@ mod batch_tests {
             assert_eq!(i, result);
         }
     }
+
+    #[test]
+    fn test_parse_worker_len() {
+        let cpus = num_cpus::get();
+        assert_eq!(parse_worker_len(0, None, 1000), cpus);
+        assert_eq!(parse_worker_len(0, Some("2"), 1000), 2);
+        assert_eq!(parse_worker_len(0, Some("2"), 1), 1);
+        assert_eq!(parse_worker_len(0, Some("0"), 1000), cpus);
+        assert_eq!(parse_worker_len(0, Some("abc"), 1000), cpus);
+        assert_eq!(parse_worker_len(3, Some("2"), 1000), 3);
+        assert_eq!(parse_worker_len(3, None, 1000), 3);
+    }
 }
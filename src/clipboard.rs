@@ -0,0 +1,117 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as B64Engine;
+use base64::Engine;
+
+use crate::{info, utils};
+
+/// The clipboard commands to try, in order. Each item is the program, its
+/// args, and the env that must exist to use it (for Linux display servers).
+const CLIPBOARD_COMMANDS: [(&str, &[&str], Option<&str>); 4] = [
+    ("pbcopy", &[], None),
+    ("wl-copy", &[], Some("WAYLAND_DISPLAY")),
+    ("xclip", &["-selection", "clipboard"], Some("DISPLAY")),
+    ("xsel", &["--clipboard", "--input"], Some("DISPLAY")),
+];
+
+/// Copy the text to system clipboard.
+///
+/// In ssh session, or if no clipboard command (`pbcopy`, `wl-copy`, `xclip`,
+/// `xsel`) is available, the OSC52 escape sequence will be used, which asks
+/// the terminal emulator to set the clipboard. This requires the terminal to
+/// support OSC52.
+pub fn copy(text: &str) -> Result<()> {
+    let ssh = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+    if !ssh {
+        for (program, args, require_env) in CLIPBOARD_COMMANDS {
+            if let Some(require_env) = require_env {
+                if env::var_os(require_env).is_none() {
+                    continue;
+                }
+            }
+            if copy_with_command(program, args, text)? {
+                return Ok(());
+            }
+        }
+    }
+    copy_with_osc52(text)
+}
+
+/// Copy the url to clipboard if `copy` is `true`, else, open it in default
+/// browser.
+pub fn open_or_copy(url: impl AsRef<str>, copy: bool) -> Result<()> {
+    if !copy {
+        return utils::open_url(url);
+    }
+    self::copy(url.as_ref())?;
+    info!("Copied {} to clipboard", url.as_ref());
+    Ok(())
+}
+
+/// Return `false` if the command is not found.
+fn copy_with_command(program: &str, args: &[&str], text: &str) -> Result<bool> {
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => {
+            return Err(err).with_context(|| format!("launch clipboard command `{program}`"))
+        }
+    };
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin
+        .write_all(text.as_bytes())
+        .with_context(|| format!("write text to clipboard command `{program}`"))?;
+    drop(stdin);
+
+    let status = child
+        .wait()
+        .with_context(|| format!("wait clipboard command `{program}`"))?;
+    if !status.success() {
+        bail!("clipboard command `{program}` exited with bad code");
+    }
+    Ok(true)
+}
+
+fn copy_with_osc52(text: &str) -> Result<()> {
+    let seq = osc52_sequence(text, env::var_os("TMUX").is_some());
+    let mut stderr = io::stderr();
+    stderr
+        .write_all(seq.as_bytes())
+        .and_then(|_| stderr.flush())
+        .context("write osc52 sequence to terminal")
+}
+
+/// Build the OSC52 sequence, in tmux, the sequence must be wrapped with DCS
+/// passthrough, so that tmux can forward it to the outer terminal.
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let seq = format!("\x1b]52;c;{}\x07", B64Engine.encode(text));
+    if tmux {
+        format!("\x1bPtmux;\x1b{seq}\x1b\\")
+    } else {
+        seq
+    }
+}
+
+#[cfg(test)]
+mod clipboard_tests {
+    use crate::clipboard::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(osc52_sequence("hello", false), "\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(
+            osc52_sequence("hello", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGVsbG8=\x07\x1b\\"
+        );
+    }
+}
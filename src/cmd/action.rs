@@ -15,6 +15,7 @@ use crate::api::ActionJobStatus;
 use crate::api::ActionOptions;
 use crate::api::ActionTarget;
 use crate::api::Provider;
+use crate::clipboard;
 use crate::cmd::Run;
 use crate::config::Config;
 use crate::exec::{self, Cmd};
@@ -22,11 +23,14 @@ use crate::git::GitBranch;
 use crate::repo::database::Database;
 use crate::repo::Repo;
 use crate::term;
-use crate::utils;
 
 /// The remote action (CI/CD) operations.
 #[derive(Args)]
 pub struct ActionArgs {
+    /// Only select the jobs whose name contains this (only affect `-o` and `-l`
+    /// options).
+    pub name: Option<String>,
+
     /// Use the branch to get action rather than commit.
     #[clap(short, long)]
     pub branch: bool,
@@ -56,6 +60,16 @@ pub struct ActionArgs {
     /// will take a lot of your cpu and memory.
     #[clap(short, long)]
     pub rolling: bool,
+
+    /// Show logs in pager (`$PAGER`, default is `less -R`) rather than printing
+    /// them, will be ignored in rolling mode.
+    #[clap(short, long)]
+    pub pager: bool,
+
+    /// Copy the url to clipboard rather than opening it in browser (only affect
+    /// `-o` option).
+    #[clap(long)]
+    pub copy: bool,
 }
 
 impl Run for ActionArgs {
@@ -63,7 +77,7 @@ impl Run for ActionArgs {
         let db = Database::load(cfg)?;
         let repo = db.must_get_current()?;
 
-        let provider = api::build_raw_provider(&repo.remote_cfg);
+        let provider = api::build_raw_provider(cfg, &repo.remote_cfg)?;
         let opts = self.get_opts(repo)?;
         drop(db);
 
@@ -102,42 +116,22 @@ impl ActionArgs {
 
     fn watch(
         &self,
-        mut action: Option<Action>,
+        action: Option<Action>,
         provider: Box<dyn Provider>,
         opts: ActionOptions,
     ) -> Result<()> {
-        let retry_sleep = || {
-            thread::sleep(Duration::from_millis(500));
-        };
-
-        if action.is_none() {
-            eprintln!("Waiting for action to be created...");
-        }
-        while action.is_none() {
-            let current_action = provider.get_action(&opts)?;
-            if current_action.is_none() {
-                retry_sleep();
-                continue;
-            }
-
-            action = current_action;
-            term::cursor_up();
-        }
-        let action = action.unwrap();
-        eprintln!("{action}");
-
-        let mut watcher = ActionWatcher::new(action, provider, opts);
-        watcher.wait()
+        wait_action(action, provider.as_ref(), &opts)?;
+        Ok(())
     }
 
     fn open(&self, action: Action) -> Result<()> {
-        if self.job || self.fail {
+        if self.job || self.fail || self.name.is_some() {
             let job = self.select_job(action)?;
-            return utils::open_url(job.url);
+            return clipboard::open_or_copy(job.url, self.copy);
         }
 
         if let Some(url) = action.url.as_ref() {
-            return utils::open_url(url);
+            return clipboard::open_or_copy(url, self.copy);
         }
 
         let items: Vec<&str> = action.runs.iter().map(|run| run.name.as_str()).collect();
@@ -148,12 +142,18 @@ impl ActionArgs {
             bail!("url is missing for action run");
         }
 
-        utils::open_url(run.url.as_ref().unwrap())
+        clipboard::open_or_copy(run.url.as_ref().unwrap(), self.copy)
     }
 
     fn logs(&self, action: Action, provider: Box<dyn Provider>, opts: ActionOptions) -> Result<()> {
         let job = self.select_job(action)?;
 
+        if self.pager && (!self.rolling || job.status.is_completed()) {
+            let mut data: Vec<u8> = Vec::with_capacity(512);
+            provider.logs_job(&opts.owner, &opts.name, job.id, &mut data)?;
+            return term::page(&data);
+        }
+
         if !self.rolling || job.status.is_completed() {
             let mut stderr: Box<dyn Write> = Box::new(io::stderr());
 
@@ -190,6 +190,11 @@ impl ActionArgs {
                 if self.running && !matches!(job.status, ActionJobStatus::Running) {
                     continue;
                 }
+                if let Some(name) = self.name.as_ref() {
+                    if !job.name.contains(name.as_str()) {
+                        continue;
+                    }
+                }
 
                 let item = format!("{}/{}", run.name, job.name);
                 items.push(item);
@@ -203,6 +208,9 @@ impl ActionArgs {
             if self.fail {
                 bail!("no failed job for current action");
             }
+            if let Some(name) = self.name.as_ref() {
+                bail!("no job matches '{name}' for current action");
+            }
             bail!("no job for current action");
         }
         if jobs.len() == 1 {
@@ -215,7 +223,35 @@ impl ActionArgs {
     }
 }
 
-struct ActionWatcher {
+/// Wait for the action to be created and all its jobs to be completed, the jobs
+/// status will be displayed in the terminal. Return the completed action.
+pub fn wait_action(
+    mut action: Option<Action>,
+    provider: &dyn Provider,
+    opts: &ActionOptions,
+) -> Result<Action> {
+    if action.is_none() {
+        eprintln!("Waiting for action to be created...");
+    }
+    while action.is_none() {
+        let current_action = provider.get_action(opts)?;
+        if current_action.is_none() {
+            thread::sleep(Duration::from_millis(500));
+            continue;
+        }
+
+        action = current_action;
+        term::cursor_up();
+    }
+    let action = action.unwrap();
+    eprintln!("{action}");
+
+    let mut watcher = ActionWatcher::new(action, provider, opts);
+    watcher.wait()?;
+    Ok(watcher.action)
+}
+
+struct ActionWatcher<'a> {
     status_map: HashMap<u64, ActionJobStatus>,
     last_lines: usize,
 
@@ -223,13 +259,13 @@ struct ActionWatcher {
 
     action: Action,
 
-    provider: Box<dyn Provider>,
+    provider: &'a dyn Provider,
 
-    opts: ActionOptions,
+    opts: &'a ActionOptions,
 }
 
-impl ActionWatcher {
-    fn new(action: Action, provider: Box<dyn Provider>, opts: ActionOptions) -> Self {
+impl<'a> ActionWatcher<'a> {
+    fn new(action: Action, provider: &'a dyn Provider, opts: &'a ActionOptions) -> Self {
         ActionWatcher {
             status_map: HashMap::new(),
             last_lines: 0,
@@ -313,7 +349,7 @@ impl ActionWatcher {
 
     fn next(&mut self) -> Result<()> {
         Self::retry_sleep();
-        let current_action = self.provider.get_action(&self.opts)?;
+        let current_action = self.provider.get_action(self.opts)?;
         if current_action.is_none() {
             bail!("action was removed during watching");
         }
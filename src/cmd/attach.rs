@@ -1,18 +1,28 @@
-use anyhow::{bail, Result};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
 use clap::Args;
 
 use crate::cmd::{Completion, Run};
 use crate::config::Config;
+use crate::errors::ErrorKind;
 use crate::exec::Cmd;
-use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::git;
+use crate::repo::database::{self, Database, SelectOptions, Selector};
+use crate::repo::detect::labels::DetectLabels;
 use crate::repo::Repo;
-use crate::{confirm, info, utils};
+use crate::{confirm, info, term, utils, warn};
 
-/// Attach the current directory to a repository.
+/// Attach the current directory (or a directory outside the workspace) to a
+/// repository.
 #[derive(Args)]
 pub struct AttachArgs {
     /// Repository selection head.
-    pub head: String,
+    #[clap(required_unless_present = "orphans")]
+    pub head: Option<String>,
 
     /// Repository selection query.
     pub query: Option<String>,
@@ -24,6 +34,16 @@ pub struct AttachArgs {
     /// Append these labels to the database.
     #[clap(short, long)]
     pub labels: Option<String>,
+
+    /// Attach this directory rather than the current one. It can be outside
+    /// the workspace, the path will be stored in the database.
+    #[clap(short, long)]
+    pub path: Option<String>,
+
+    /// Scan the workspace for git repositories that are not in the database, and
+    /// attach them in bulk. The labels and git configs follow the owner's config.
+    #[clap(short, long)]
+    pub orphans: bool,
 }
 
 impl Run for AttachArgs {
@@ -31,57 +51,72 @@ impl Run for AttachArgs {
         let append_labels = utils::parse_labels(&self.labels);
 
         let mut db = Database::load(cfg)?;
+        if self.orphans {
+            return self.adopt_orphans(cfg, db, append_labels);
+        }
 
-        if let Some(found) = db.get_current() {
-            bail!(
+        let dir = self.get_dir(cfg)?;
+        if let Some(found) = db.get_by_path(&dir) {
+            return Err(ErrorKind::Conflict.error(format!(
                 "this path has already been bound to '{}', please detach it first",
                 found.name_with_remote()
-            );
+            )));
         }
 
-        let path = format!("{}", cfg.get_current_dir().display());
+        let path = format!("{}", dir.display());
         let opts = SelectOptions::default()
             .with_force_no_cache(self.force)
             .with_force_remote(true)
-            .with_repo_path(path);
+            .with_repo_path(path.clone());
 
-        let head = Some(self.head.clone());
-        let selector = Selector::from_args(&head, &self.query, opts);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
         let (mut repo, exists) = selector.one(&db)?;
 
         if exists {
-            bail!(
+            return Err(ErrorKind::Conflict.error(format!(
                 "the repo '{}' has already been bound to '{}', please detach it first",
                 repo.name_with_remote(),
                 repo.get_path(cfg).display()
-            );
+            )));
         }
 
         confirm!(
-            "Do you want to attach current directory to {}",
+            "Do you want to attach '{}' to {}",
+            dir.display(),
             repo.name_with_remote()
         );
 
-        if let Some(user) = &repo.remote_cfg.user {
-            Cmd::git(&["config", "user.name", user.as_str()])
-                .with_display(format!("Set user to '{}'", user))
-                .execute()?;
-        }
-        if let Some(email) = &repo.remote_cfg.email {
-            Cmd::git(&["config", "user.email", email.as_str()])
-                .with_display(format!("Set email to '{}'", email))
+        for (key, value) in repo.remote_cfg.git_configs(repo.owner.as_ref()) {
+            Cmd::git(&["-C", path.as_str(), "config", key, value.as_str()])
+                .with_display(format!("Set {key} to '{value}'"))
                 .execute()?;
         }
         if repo.remote_cfg.clone.is_some() {
             let url =
                 Repo::get_clone_url(repo.owner.as_ref(), repo.name.as_ref(), &repo.remote_cfg);
-            Cmd::git(&["remote", "set-url", "origin", url.as_str()])
-                .with_display(format!("Set remote origin url to '{}'", url))
-                .execute()?;
+            Cmd::git(&[
+                "-C",
+                path.as_str(),
+                "remote",
+                "set-url",
+                "origin",
+                url.as_str(),
+            ])
+            .with_display(format!("Set remote origin url to '{}'", url))
+            .execute()?;
+        }
+
+        if let Err(err) = git::setup_lfs(&dir) {
+            warn!("Setup Git LFS failed: {:#}", err);
         }
 
-        info!("Attach current directory to {}", repo.name_with_remote());
+        info!("Attach '{}' to {}", dir.display(), repo.name_with_remote());
         repo.append_labels(append_labels);
+        if cfg.detect.auto {
+            DetectLabels::new(cfg)
+                .update(&mut repo)
+                .context("auto detect labels for repo")?;
+        }
         db.upsert(repo.update());
 
         db.save()
@@ -89,6 +124,92 @@ impl Run for AttachArgs {
 }
 
 impl AttachArgs {
+    fn get_dir(&self, cfg: &Config) -> Result<PathBuf> {
+        let path = match self.path.as_ref() {
+            Some(path) => cfg.get_current_dir().join(path),
+            None => return Ok(cfg.get_current_dir().clone()),
+        };
+        let path = fs::canonicalize(&path)
+            .with_context(|| format!("canonicalize path '{}'", path.display()))?;
+        if !path.join(".git").exists() {
+            bail!("'{}' is not a git repository", path.display());
+        }
+        Ok(path)
+    }
+
+    fn adopt_orphans(
+        &self,
+        cfg: &Config,
+        mut db: Database,
+        append_labels: Option<HashSet<String>>,
+    ) -> Result<()> {
+        let root = cfg.get_workspace_dir().clone();
+        let repo_set: HashSet<PathBuf> = db
+            .list_all(&None)
+            .iter()
+            .map(|repo| repo.get_path(cfg))
+            .collect();
+
+        info!("Scan orphan repos under '{}'", root.display());
+        let mut orphans = Vec::new();
+        utils::walk_dir(root, |path, entry| {
+            if !entry.is_dir() || repo_set.contains(path) {
+                return Ok(false);
+            }
+            if !path.join(".git").exists() {
+                return Ok(true);
+            }
+            if let Some((remote, owner, name)) = database::parse_workspace_path(cfg, path) {
+                if cfg.get_remote(&remote).is_some() {
+                    orphans.push((remote, owner, name));
+                }
+            }
+            Ok(false)
+        })?;
+        if orphans.is_empty() {
+            eprintln!("No orphan repo to attach");
+            return Ok(());
+        }
+        orphans.sort_unstable();
+
+        let items: Vec<String> = orphans
+            .iter()
+            .map(|(remote, owner, name)| format!("{remote}:{owner}/{name}"))
+            .collect();
+        term::must_confirm_items(&items, "attach", "attachment", "Orphan", "Orphans")?;
+
+        let detect_labels = if cfg.detect.auto {
+            Some(DetectLabels::new(cfg))
+        } else {
+            None
+        };
+
+        for (remote, owner, name) in orphans {
+            let mut repo = Repo::new(
+                cfg,
+                Cow::Owned(remote),
+                Cow::Owned(owner),
+                Cow::Owned(name),
+                None,
+            )?;
+            let path = repo.get_path(cfg);
+            let path = format!("{}", path.display());
+            for (key, value) in repo.remote_cfg.git_configs(repo.owner.as_ref()) {
+                Cmd::git(&["-C", path.as_str(), "config", key, value.as_str()]).execute()?;
+            }
+            repo.append_labels(append_labels.clone());
+            if let Some(detect_labels) = detect_labels.as_ref() {
+                detect_labels
+                    .update(&mut repo)
+                    .context("auto detect labels for repo")?;
+            }
+            db.upsert(repo.update());
+        }
+        info!("Attach {}", utils::plural(&items, "orphan repo"));
+
+        db.save()
+    }
+
     pub fn completion() -> Completion {
         Completion {
             args: Completion::owner_args,
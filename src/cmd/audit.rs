@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::audit::{AuditHistory, AuditRecord};
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::table::{Table, TableArgs, TableCell, TableCellColor};
+use crate::{term, utils};
+
+/// Show the audit history of the state-changing commands.
+///
+/// Such as `remove`, `clean` and `sync`, the latest first. See `audit` in
+/// config.
+#[derive(Args)]
+pub struct AuditArgs {
+    /// Only show the records affecting the repositories that contain this, such
+    /// as `roxide` or `github:fioncat/roxide`.
+    pub repo: Option<String>,
+
+    /// Only show the records of this command.
+    #[clap(short, long)]
+    pub command: Option<String>,
+
+    /// Show at most these records.
+    #[clap(short = 'n', long, default_value = "30")]
+    pub limit: usize,
+
+    /// Show result as json format.
+    #[clap(short = 'J')]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
+}
+
+impl Run for AuditArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let history = AuditHistory::new(cfg);
+        let records: Vec<AuditRecord> = history
+            .list()?
+            .into_iter()
+            .rev()
+            .filter(|record| self.matches(record))
+            .take(self.limit)
+            .collect();
+        if self.json {
+            return term::show_json(&records);
+        }
+        if records.is_empty() {
+            eprintln!("No audit record");
+            return Ok(());
+        }
+
+        let mut table = Table::with_capacity(records.len() + 1);
+        table.add(vec![
+            String::from("ID"),
+            String::from("Time"),
+            String::from("Command"),
+            String::from("Repos"),
+            String::from("Status"),
+            String::from("Elapsed"),
+        ]);
+        for record in records {
+            let status = if record.exit_code == 0 {
+                TableCell::with_color(String::from("ok"), TableCellColor::Green)
+            } else if record.error.is_some() {
+                TableCell::with_color(String::from("failed"), TableCellColor::Red)
+            } else {
+                TableCell::with_color(String::from("canceled"), TableCellColor::Yellow)
+            };
+            table.add_color(vec![
+                TableCell::no_color(format!("{}", record.id)),
+                TableCell::no_color(utils::format_since(cfg, record.time))
+                    .with_sort_key(record.time),
+                TableCell::no_color(record.args.join(" ")),
+                TableCell::no_color(Self::format_repos(&record.repos)),
+                status,
+                TableCell::no_color(utils::format_elapsed(Duration::from_millis(
+                    record.elapsed_ms,
+                )))
+                .with_sort_key(record.elapsed_ms),
+            ]);
+        }
+        table.show_with(
+            &self.table,
+            &["id", "time", "command", "repos", "status", "elapsed"],
+        )
+    }
+}
+
+impl AuditArgs {
+    /// Show at most these repositories in the table, use `-J` to see all.
+    const MAX_SHOW_REPOS: usize = 2;
+
+    fn matches(&self, record: &AuditRecord) -> bool {
+        if let Some(command) = self.command.as_ref() {
+            if &record.command != command {
+                return false;
+            }
+        }
+        if let Some(repo) = self.repo.as_ref() {
+            return record.repos.iter().any(|name| name.contains(repo.as_str()));
+        }
+        true
+    }
+
+    fn format_repos(repos: &[String]) -> String {
+        if repos.len() <= Self::MAX_SHOW_REPOS {
+            return repos.join(", ");
+        }
+        format!(
+            "{} (+{})",
+            repos[..Self::MAX_SHOW_REPOS].join(", "),
+            repos.len() - Self::MAX_SHOW_REPOS
+        )
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |cfg, args| match args.len() {
+                0 | 1 => {
+                    let history = AuditHistory::new(cfg);
+                    let repos: BTreeSet<_> = history
+                        .list()?
+                        .into_iter()
+                        .flat_map(|record| record.repos)
+                        .collect();
+                    Ok(CompletionResult::from(
+                        repos.into_iter().collect::<Vec<_>>(),
+                    ))
+                }
+                _ => Ok(CompletionResult::empty()),
+            },
+            flags: Some(|cfg, flag, _to_complete| match flag {
+                'c' => {
+                    let history = AuditHistory::new(cfg);
+                    let commands: BTreeSet<_> = history
+                        .list()?
+                        .into_iter()
+                        .map(|record| record.command)
+                        .collect();
+                    Ok(Some(CompletionResult::from(
+                        commands.into_iter().collect::<Vec<_>>(),
+                    )))
+                }
+                _ => Ok(None),
+            }),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use console::style;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::exec::{Cmd, GitCmd};
+use crate::filelock::FileLock;
+use crate::repo::database::{self, Database};
+use crate::{confirm, info, s3, secret, utils};
+
+/// The metadata files to backup, the missing ones are ignored.
+const META_FILES: [&str; 3] = ["database", "deps", "mirrors"];
+
+/// Backup or restore the workspace metadata.
+///
+/// The metadata (and the git bundles of pinned repositories) is encrypted with
+/// a password, and uploaded to a local directory, a rsync destination or the
+/// s3 storage.
+#[derive(Args)]
+pub struct BackupArgs {
+    /// Where to upload the backup file, default is `backup.target` in config.
+    /// Use `s3://{prefix}` to upload to the s3 storage (see `s3` in config).
+    #[clap(short, long)]
+    pub target: Option<String>,
+
+    /// Also bundle the git data of the pinned repositories, default is
+    /// `backup.bundle` in config.
+    #[clap(short, long)]
+    pub bundle: bool,
+
+    /// Restore the workspace with this backup file in the target (or a local
+    /// path). The current database is kept as a backup, and the missing pinned
+    /// repositories are cloned from their bundles.
+    #[clap(short, long)]
+    pub restore: Option<String>,
+}
+
+impl Run for BackupArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let work_dir = cfg.get_meta_dir().join("backup_tmp");
+        if work_dir.exists() {
+            fs::remove_dir_all(&work_dir).context("remove backup tmp dir")?;
+        }
+        let result = match self.restore.as_ref() {
+            Some(name) => self.restore(cfg, name, &work_dir),
+            None => self.backup(cfg, &work_dir),
+        };
+        let _ = fs::remove_dir_all(&work_dir);
+        result
+    }
+}
+
+impl BackupArgs {
+    fn backup(&self, cfg: &Config, work_dir: &Path) -> Result<()> {
+        let target = self.get_target(cfg)?;
+        let stage = work_dir.join("stage");
+        fs::create_dir_all(stage.join("meta")).context("create backup stage dir")?;
+
+        let db = Database::load(cfg)?;
+        let mut count: usize = 0;
+        for name in META_FILES {
+            let path = cfg.get_meta_dir().join(name);
+            if !path.exists() {
+                continue;
+            }
+            fs::copy(&path, stage.join("meta").join(name))
+                .with_context(|| format!("copy '{}'", path.display()))?;
+            count += 1;
+        }
+        info!("Backup {} metadata files", count);
+
+        if self.bundle || cfg.backup.bundle {
+            let mut repos: Vec<_> = db
+                .list_all(&None)
+                .into_iter()
+                .filter(|repo| repo.pin_priority().is_some())
+                .collect();
+            repos.sort_unstable_by_key(|repo| repo.name_with_remote());
+            for repo in repos {
+                let path = repo.get_path(cfg);
+                if !path.is_dir() {
+                    continue;
+                }
+                let bundle = stage
+                    .join("bundles")
+                    .join(repo.remote.as_ref())
+                    .join(repo.owner.as_ref())
+                    .join(format!("{}.bundle", repo.name));
+                utils::ensure_dir(&bundle)?;
+                let path = format!("{}", path.display());
+                let bundle = format!("{}", bundle.display());
+                GitCmd::with_path(&path).exec(&["bundle", "create", &bundle, "--all"])?;
+                info!("Bundle {}", repo.name_with_remote());
+            }
+        }
+        drop(db);
+
+        let name = format!("roxide-backup-{}.secret", cfg.now());
+        let archive = format!("{}", work_dir.join("backup.tar.gz").display());
+        let stage = format!("{}", stage.display());
+        Cmd::with_args("tar", &["-czf", &archive, "-C", &stage, "."])
+            .with_display("Pack backup")
+            .execute()?;
+
+        let secret_path = work_dir.join(&name);
+        secret::handle(&archive, &Some(format!("{}", secret_path.display())), None)?;
+
+        if let Some(prefix) = target.strip_prefix("s3://") {
+            let key = Self::s3_key(prefix, &name);
+            s3::upload(cfg, &key, &secret_path)?;
+        } else if Self::is_remote(&target) {
+            let path = format!("{}", secret_path.display());
+            let dest = format!("{}/{name}", target.trim_end_matches('/'));
+            Cmd::with_args("rsync", &["-a", &path, &dest])
+                .with_display("Upload backup")
+                .execute()?;
+        } else {
+            let dir = PathBuf::from(&target);
+            fs::create_dir_all(&dir).with_context(|| format!("create dir '{target}'"))?;
+            fs::copy(&secret_path, dir.join(&name))
+                .with_context(|| format!("copy backup to '{target}'"))?;
+        }
+
+        info!("Backup to {} as {}", target, name);
+        Ok(())
+    }
+
+    fn restore(&self, cfg: &Config, name: &str, work_dir: &Path) -> Result<()> {
+        fs::create_dir_all(work_dir.join("stage")).context("create backup stage dir")?;
+        let secret_path = work_dir.join("backup.secret");
+        if Path::new(name).is_file() {
+            fs::copy(name, &secret_path).with_context(|| format!("copy backup '{name}'"))?;
+        } else {
+            let target = self.get_target(cfg)?;
+            let src = format!("{}/{name}", target.trim_end_matches('/'));
+            if let Some(prefix) = target.strip_prefix("s3://") {
+                let key = Self::s3_key(prefix, name);
+                s3::download(cfg, &key, &secret_path)?;
+            } else if Self::is_remote(&target) {
+                let path = format!("{}", secret_path.display());
+                Cmd::with_args("rsync", &["-a", &src, &path])
+                    .with_display("Download backup")
+                    .execute()?;
+            } else {
+                fs::copy(&src, &secret_path).with_context(|| format!("copy backup '{src}'"))?;
+            }
+        }
+
+        let archive = format!("{}", work_dir.join("backup.tar.gz").display());
+        secret::handle(&secret_path, &Some(archive.clone()), None)?;
+
+        let stage = work_dir.join("stage");
+        let stage_str = format!("{}", stage.display());
+        Cmd::with_args("tar", &["-xzf", &archive, "-C", &stage_str])
+            .with_display("Unpack backup")
+            .execute()?;
+
+        let meta_dir = stage.join("meta");
+        if !meta_dir.join("database").is_file() {
+            bail!("invalid backup, the database is missing");
+        }
+        confirm!("Continue to restore the workspace with backup {}", name);
+
+        // Hold the locks of the metadata files (the lock names are the same as
+        // the file names) while replacing them, so that the restore does not
+        // race with other roxide processes saving them.
+        let locks = META_FILES
+            .iter()
+            .map(|name| FileLock::acquire(cfg, name))
+            .collect::<Result<Vec<_>>>()?;
+        if cfg.get_meta_dir().join("database").exists() {
+            database::backup_replace(cfg, "backup")?;
+        }
+        for name in META_FILES {
+            let path = meta_dir.join(name);
+            if !path.exists() {
+                continue;
+            }
+            let data = fs::read(&path).with_context(|| format!("read '{}'", path.display()))?;
+            utils::write_file_atomic(&cfg.get_meta_dir().join(name), &data)?;
+        }
+        drop(locks);
+        info!("Restore metadata done");
+
+        let bundles_dir = stage.join("bundles");
+        if bundles_dir.is_dir() {
+            self.restore_bundles(cfg, &bundles_dir)?;
+        }
+
+        println!();
+        println!(
+            "Restore done, you should use the {} command to clone the other repositories.",
+            style("sync").cyan().bold()
+        );
+        Ok(())
+    }
+
+    /// Clone the missing pinned repositories from their bundles, the bundles
+    /// are stored in `{remote}/{owner}/{name}.bundle`.
+    fn restore_bundles(&self, cfg: &Config, dir: &Path) -> Result<()> {
+        let db = Database::load(cfg)?;
+        for repo in db.list_all(&None) {
+            let bundle = dir
+                .join(repo.remote.as_ref())
+                .join(repo.owner.as_ref())
+                .join(format!("{}.bundle", repo.name));
+            if !bundle.is_file() {
+                continue;
+            }
+            let path = repo.get_path(cfg);
+            if path.exists() {
+                continue;
+            }
+            let path = format!("{}", path.display());
+            let bundle = format!("{}", bundle.display());
+            Cmd::git(&["clone", &bundle, &path]).execute()?;
+            let url = repo.clone_url();
+            GitCmd::with_path(&path).exec(&["remote", "set-url", "origin", &url])?;
+            info!("Restore {} from bundle", repo.name_with_remote());
+        }
+        Ok(())
+    }
+
+    fn get_target(&self, cfg: &Config) -> Result<String> {
+        let target = self.target.as_ref().unwrap_or(&cfg.backup.target);
+        if target.is_empty() {
+            bail!("no backup target, please specify it by `--target` or `backup.target` in config");
+        }
+        utils::expandenv(target).context("expand env for backup target")
+    }
+
+    fn s3_key(prefix: &str, name: &str) -> String {
+        let prefix = prefix.trim_matches('/');
+        if prefix.is_empty() {
+            return name.to_string();
+        }
+        format!("{prefix}/{name}")
+    }
+
+    /// The rsync destination is like `user@host:path`, a local path never
+    /// contains ':' before its first '/'.
+    fn is_remote(target: &str) -> bool {
+        match target.find(':') {
+            Some(idx) => !target[..idx].contains('/'),
+            None => false,
+        }
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_, _| Ok(CompletionResult::empty()),
+            flags: Some(|_cfg, flag, _to_complete| match flag {
+                't' | 'r' => Ok(Some(CompletionResult::files())),
+                _ => Ok(None),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod backup_tests {
+    use crate::cmd::backup::*;
+
+    #[test]
+    fn test_is_remote() {
+        let cases = [
+            ("user@host:backups/roxide", true),
+            ("host:/data", true),
+            ("/data/backups", false),
+            ("./a:b", false),
+            ("backups", false),
+        ];
+        for (target, expect) in cases {
+            assert_eq!(BackupArgs::is_remote(target), expect, "{target}");
+        }
+    }
+
+    #[test]
+    fn test_s3_key() {
+        assert_eq!(BackupArgs::s3_key("", "a.secret"), "a.secret");
+        assert_eq!(
+            BackupArgs::s3_key("/backups/", "a.secret"),
+            "backups/a.secret"
+        );
+    }
+}
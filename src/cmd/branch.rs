@@ -1,13 +1,20 @@
+use std::path::PathBuf;
+
 use anyhow::{bail, Context, Result};
 use clap::Args;
 use console::style;
+use glob::Pattern as GlobPattern;
 
+use crate::api;
+use crate::batch::{self, Task};
 use crate::cmd::{Completion, Run};
 use crate::config::Config;
+use crate::errors::ErrorKind;
 use crate::exec::{self, Cmd};
 use crate::git::{self, BranchStatus, GitBranch};
+use crate::repo::database::{Database, SelectOptions, Selector};
 use crate::table::{Table, TableCell, TableCellColor};
-use crate::term;
+use crate::{info, term, utils};
 
 /// Git branch operations
 #[derive(Args)]
@@ -42,6 +49,44 @@ pub struct BranchArgs {
     /// List branch
     #[clap(short, long)]
     pub list: bool,
+
+    /// Delete local branches that have been merged into the default branch
+    #[clap(short = 'M', long)]
+    pub merged: bool,
+
+    /// Delete local branches whose upstream has been deleted
+    #[clap(short = 'G', long)]
+    pub gone: bool,
+
+    /// Use editor to filter branches before deleting, use with `--merged` or
+    /// `--gone`
+    #[clap(short, long)]
+    pub edit: bool,
+
+    /// Create and switch to a branch for the issue, the name is generated from
+    /// the issue title with `git.issue_branch` in config if not provided. The
+    /// `merge` command will reference the issue.
+    #[clap(short = 'I', long)]
+    pub issue: Option<u64>,
+
+    /// Report the local repositories that have the branches matching `name`
+    /// (a glob pattern, such as `release/1.2*`), with their last commit dates.
+    /// With `--all`, the remote-tracking branches of `origin` are matched too,
+    /// they are not fetched.
+    #[clap(short = 'A', long)]
+    pub all_repos: bool,
+
+    /// Repository selection head, use with `--all-repos`.
+    #[clap(long)]
+    pub head: Option<String>,
+
+    /// Repository selection query, use with `--all-repos`.
+    #[clap(long)]
+    pub query: Option<String>,
+
+    /// Use the labels to filter repository, use with `--all-repos`.
+    #[clap(long)]
+    pub labels: Option<String>,
 }
 
 enum SyncBranchTask<'a> {
@@ -50,7 +95,16 @@ enum SyncBranchTask<'a> {
 }
 
 impl Run for BranchArgs {
-    fn run(&self, _cfg: &Config) -> Result<()> {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        if self.all_repos {
+            return self.report(cfg);
+        }
+        if self.merged || self.gone {
+            return self.cleanup(cfg);
+        }
+        if let Some(id) = self.issue {
+            return self.create_for_issue(cfg, id);
+        }
         if self.sync {
             git::ensure_no_uncommitted()?;
             self.fetch(false)?;
@@ -97,6 +151,58 @@ impl Run for BranchArgs {
 }
 
 impl BranchArgs {
+    /// The max length of the slug generated from the issue title.
+    const SLUG_MAX_LEN: usize = 40;
+
+    fn create_for_issue(&self, cfg: &Config, id: u64) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let repo = db.must_get_current()?;
+        let provider = api::build_provider(cfg, &repo.remote_cfg, false)?;
+        info!("Get issue #{} from remote API", id);
+        let issue = provider.get_issue(repo.owner.as_ref(), repo.name.as_ref(), id)?;
+
+        let name = match self.name.as_ref() {
+            Some(name) => name.clone(),
+            None => Self::issue_branch_name(&cfg.git.issue_branch, id, &issue.title),
+        };
+        Cmd::git(&["checkout", "-b", name.as_str()])
+            .with_display_cmd()
+            .execute()?;
+        GitBranch::set_issue(&name, id)?;
+        if self.push {
+            Cmd::git(&["push", "--set-upstream", "origin", name.as_str()])
+                .with_display_cmd()
+                .execute()?;
+        }
+        info!("Created branch {} for issue #{}: {}", name, id, issue.title);
+        Ok(())
+    }
+
+    fn issue_branch_name(pattern: &str, id: u64, title: &str) -> String {
+        let mut slug = String::with_capacity(title.len());
+        for ch in title.chars() {
+            if ch.is_ascii_alphanumeric() {
+                slug.push(ch.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        if slug.len() > Self::SLUG_MAX_LEN {
+            slug.truncate(Self::SLUG_MAX_LEN);
+            // Avoid cutting off a word.
+            if let Some(idx) = slug.rfind('-') {
+                slug.truncate(idx);
+            }
+        }
+        let slug = slug.trim_matches('-');
+
+        let name = pattern
+            .replace("{id}", &format!("{id}"))
+            .replace("{slug}", slug);
+        // The slug may be empty, such as the non-ascii title.
+        name.trim_matches(|ch| ch == '-' || ch == '/').to_string()
+    }
+
     fn show(&self, branches: &Vec<GitBranch>) -> Result<()> {
         if branches.is_empty() {
             eprintln!("No branch to list");
@@ -166,6 +272,80 @@ impl BranchArgs {
         Ok(())
     }
 
+    fn report(&self, cfg: &Config) -> Result<()> {
+        let pattern = match self.name.as_ref() {
+            Some(name) => {
+                GlobPattern::new(name).with_context(|| format!("parse branch pattern '{name}'"))?
+            }
+            None => bail!("the branch pattern is required for `--all-repos`"),
+        };
+
+        let db = Database::load(cfg)?;
+        let labels = utils::parse_labels(&self.labels);
+        let opts = SelectOptions::default().with_filter_labels(labels);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let (repos, level) = selector.many_local(&db)?;
+        if repos.is_empty() {
+            eprintln!("No repo to check");
+            return Ok(());
+        }
+
+        let tasks: Vec<_> = repos
+            .iter()
+            .map(|repo| {
+                let name = repo.to_string(&level);
+                let task = BranchTask {
+                    path: repo.get_path(cfg),
+                    pattern: pattern.clone(),
+                    remote: self.all,
+                };
+                (name, task)
+            })
+            .collect();
+        let names: Vec<_> = tasks.iter().map(|(name, _)| name.clone()).collect();
+        let results = batch::must_run_with("Branch", tasks, cfg.git.workers(None))?;
+
+        let mut rows = Vec::new();
+        let mut missing = 0;
+        for (name, refs) in names.into_iter().zip(results) {
+            if refs.is_empty() {
+                missing += 1;
+                continue;
+            }
+            for branch in refs {
+                rows.push((name.clone(), branch));
+            }
+        }
+        eprintln!();
+        if rows.is_empty() {
+            eprintln!("No repo has branch matching '{}'", pattern);
+            return Ok(());
+        }
+        // The most recently updated branches first.
+        rows.sort_by_key(|(_, branch)| std::cmp::Reverse(branch.time));
+
+        let mut table = Table::with_capacity(1 + rows.len());
+        table.add(vec![
+            String::from("Repo"),
+            String::from("Branch"),
+            String::from("Last Commit"),
+            String::from("Since"),
+        ]);
+        for (name, branch) in rows {
+            table.add(vec![
+                name,
+                branch.name,
+                utils::format_time(branch.time)?,
+                utils::format_since(cfg, branch.time),
+            ]);
+        }
+        table.show();
+        if missing > 0 {
+            eprintln!("{missing} repo(s) have no matching branch");
+        }
+        Ok(())
+    }
+
     fn sync(&self, branches: &Vec<GitBranch>) -> Result<()> {
         let default = GitBranch::default().context("Get default branch")?;
 
@@ -251,6 +431,74 @@ impl BranchArgs {
         Ok(())
     }
 
+    fn cleanup(&self, cfg: &Config) -> Result<()> {
+        if self.gone {
+            self.fetch(false)?;
+        }
+        let branches = GitBranch::list().context("list branch")?;
+        let default = GitBranch::default().context("get default branch")?;
+
+        let merged: Vec<String> = if self.merged {
+            Cmd::git(&["branch", "--merged", &default, "--format=%(refname:short)"])
+                .with_display(format!("List branches merged into {default}"))
+                .lines()?
+        } else {
+            vec![]
+        };
+
+        let mut targets: Vec<(&str, &str)> = Vec::new();
+        for branch in branches.iter() {
+            // The default and current branches are always protected.
+            if branch.current || branch.name == default {
+                continue;
+            }
+            if self.gone && matches!(branch.status, BranchStatus::Gone) {
+                targets.push((branch.name.as_str(), "gone"));
+            } else if merged.contains(&branch.name) {
+                targets.push((branch.name.as_str(), "merged"));
+            }
+        }
+        if targets.is_empty() {
+            eprintln!("No branch to delete");
+            return Ok(());
+        }
+
+        if self.edit {
+            let items = targets.iter().map(|(name, _)| name.to_string()).collect();
+            let items = term::edit_items(cfg, items)?;
+            targets.retain(|(name, _)| items.iter().any(|item| item == name));
+            if targets.is_empty() {
+                eprintln!("No branch to delete");
+                return Ok(());
+            }
+        }
+
+        let mut table = Table::with_capacity(targets.len() + 1);
+        table.add(vec![String::from("Name"), String::from("Reason")]);
+        for (name, reason) in targets.iter() {
+            let color = if *reason == "gone" {
+                TableCellColor::Red
+            } else {
+                TableCellColor::Green
+            };
+            table.add_color(vec![
+                TableCell::no_color(name.to_string()),
+                TableCell::with_color(reason.to_string(), color),
+            ]);
+        }
+        table.show();
+        eprintln!();
+
+        let items: Vec<String> = targets.iter().map(|(name, _)| name.to_string()).collect();
+        term::must_confirm_items(&items, "delete", "deletion", "Branch", "Branches")?;
+        for (name, _) in targets {
+            Cmd::git(&["branch", "-D", name])
+                .with_display_cmd()
+                .execute()?;
+        }
+        Ok(())
+    }
+
     fn fetch(&self, mute: bool) -> Result<()> {
         let mut cmd = Cmd::git(&["fetch", "origin", "--prune"]);
         if !mute {
@@ -295,7 +543,8 @@ impl BranchArgs {
         match &self.name {
             Some(name) => match branches.iter().find(|b| b.name.eq(name)) {
                 Some(b) => Ok(b),
-                None => bail!("could not find branch '{}'", style(&name).yellow()),
+                None => Err(ErrorKind::NotFound
+                    .error(format!("could not find branch '{}'", style(&name).yellow()))),
             },
             None => Self::must_get_current_branch(branches),
         }
@@ -361,3 +610,125 @@ impl BranchArgs {
         }
     }
 }
+
+struct BranchTask {
+    path: PathBuf,
+    pattern: GlobPattern,
+    remote: bool,
+}
+
+struct BranchRef {
+    name: String,
+    /// The commit time of the branch head.
+    time: u64,
+}
+
+impl Task<Vec<BranchRef>> for BranchTask {
+    fn run(&self) -> Result<Vec<BranchRef>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let path = format!("{}", self.path.display());
+        let mut args = vec![
+            "-C",
+            path.as_str(),
+            "for-each-ref",
+            "--format=%(committerdate:unix) %(refname)",
+            "refs/heads",
+        ];
+        if self.remote {
+            args.push("refs/remotes/origin");
+        }
+        let lines = Cmd::git(&args).lines()?;
+        Ok(Self::parse_refs(&self.pattern, lines))
+    }
+}
+
+impl BranchTask {
+    /// Parse the output of `git for-each-ref`, the remote-tracking branch is
+    /// merged with the local branch of the same name, the newer one is kept.
+    fn parse_refs(pattern: &GlobPattern, lines: Vec<String>) -> Vec<BranchRef> {
+        let mut refs: Vec<BranchRef> = Vec::new();
+        for line in lines {
+            let (time, refname) = match line.trim().split_once(' ') {
+                Some((time, refname)) => (time, refname),
+                None => continue,
+            };
+            let time: u64 = match time.parse() {
+                Ok(time) => time,
+                Err(_) => continue,
+            };
+            let name = match refname.strip_prefix("refs/heads/") {
+                Some(name) => name,
+                None => match refname.strip_prefix("refs/remotes/origin/") {
+                    Some("HEAD") | None => continue,
+                    Some(name) => name,
+                },
+            };
+            if !pattern.matches(name) {
+                continue;
+            }
+            match refs.iter_mut().find(|branch| branch.name == name) {
+                Some(branch) => branch.time = branch.time.max(time),
+                None => refs.push(BranchRef {
+                    name: name.to_string(),
+                    time,
+                }),
+            }
+        }
+        refs
+    }
+}
+
+#[cfg(test)]
+mod branch_tests {
+    use crate::cmd::branch::*;
+
+    #[test]
+    fn test_issue_branch_name() {
+        let cases = [
+            (
+                "{id}-{slug}",
+                12,
+                "Fix the crash on `sync`!",
+                "12-fix-the-crash-on-sync",
+            ),
+            ("issue/{id}", 3, "Anything", "issue/3"),
+            ("issue/{id}-{slug}", 5, "修复问题", "issue/5"),
+            (
+                "{id}-{slug}",
+                7,
+                "Support the very long issue title that should be truncated",
+                "7-support-the-very-long-issue-title-that",
+            ),
+        ];
+        for (pattern, id, title, expect) in cases {
+            assert_eq!(BranchArgs::issue_branch_name(pattern, id, title), expect);
+        }
+    }
+    #[test]
+    fn test_parse_refs() {
+        let lines = vec![
+            "100 refs/heads/main",
+            "200 refs/heads/release/1.2",
+            "300 refs/remotes/origin/release/1.2",
+            "150 refs/remotes/origin/release/1.2.1",
+            "300 refs/remotes/origin/HEAD",
+            "invalid",
+        ];
+        let lines = lines.into_iter().map(String::from).collect();
+        let pattern = GlobPattern::new("release/1.2*").unwrap();
+        let refs = BranchTask::parse_refs(&pattern, lines);
+        let refs: Vec<_> = refs
+            .into_iter()
+            .map(|branch| (branch.name, branch.time))
+            .collect();
+        assert_eq!(
+            refs,
+            vec![
+                (String::from("release/1.2"), 300),
+                (String::from("release/1.2.1"), 150),
+            ]
+        );
+    }
+}
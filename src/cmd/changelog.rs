@@ -0,0 +1,203 @@
+use anyhow::Result;
+use clap::Args;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::exec::Cmd;
+use crate::git::{ConventionalCommit, GitTag};
+use crate::repo::database::Database;
+use crate::{api, confirm, info};
+
+/// Generate the changelog between two refs, grouped by the conventional-commit
+/// type, and render it as Markdown.
+#[derive(Args)]
+pub struct ChangelogArgs {
+    /// The commit range, in the format `{from}..{to}`. If `..{to}` is omitted,
+    /// use `HEAD`. Default is from the latest tag to `HEAD`.
+    pub range: Option<String>,
+
+    /// Create a release for this tag in the remote, with the changelog as its
+    /// notes.
+    #[clap(short, long)]
+    pub release: Option<String>,
+
+    /// When calling the remote API, ignore caches that are not expired.
+    #[clap(short, long)]
+    pub force: bool,
+}
+
+/// The sections of the changelog, in the order they are rendered. Commits
+/// with other types (or not in conventional-commit format) go to "Other".
+const SECTIONS: [(&str, &str); 9] = [
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "CI"),
+    ("chore", "Chores"),
+];
+
+impl Run for ChangelogArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let range = self.get_range()?;
+        let lines = Cmd::git(&["log", "--no-merges", "--format=%h %s", range.as_str()])
+            .with_display(format!("Get commits in {range}"))
+            .lines()?;
+        let commits: Vec<_> = lines
+            .iter()
+            .filter_map(|line| line.trim().split_once(' '))
+            .collect();
+        if commits.is_empty() {
+            eprintln!("No commit in {range}");
+            return Ok(());
+        }
+
+        let changelog = Self::render(&commits);
+        let tag = match self.release.as_ref() {
+            Some(tag) => tag,
+            None => {
+                print!("{changelog}");
+                return Ok(());
+            }
+        };
+
+        let db = Database::load(cfg)?;
+        let repo = db.must_get_current()?;
+        eprint!("{changelog}");
+        eprintln!();
+        confirm!(
+            "Do you want to create release {tag} for {}",
+            repo.name_with_remote()
+        );
+
+        let mut provider = api::build_provider(cfg, &repo.remote_cfg, self.force)?;
+        info!("Create release {}", tag);
+        let url = provider.create_release(&repo.owner, &repo.name, tag, changelog)?;
+        println!("{url}");
+        Ok(())
+    }
+}
+
+impl ChangelogArgs {
+    fn get_range(&self) -> Result<String> {
+        if let Some(range) = self.range.as_ref() {
+            if range.contains("..") {
+                return Ok(range.clone());
+            }
+            return Ok(format!("{range}..HEAD"));
+        }
+        let tags = GitTag::list()?;
+        if tags.is_empty() {
+            // Without any tag, all the commits will be included.
+            return Ok(String::from("HEAD"));
+        }
+        let tag = Cmd::git(&["describe", "--tags", "--abbrev=0"])
+            .with_display("Get latest tag")
+            .read()?;
+        Ok(format!("{tag}..HEAD"))
+    }
+
+    /// Render the changelog as Markdown, `commits` is a list of
+    /// `(short_id, header)`.
+    fn render(commits: &[(&str, &str)]) -> String {
+        let mut breaking = Vec::new();
+        let mut sections: Vec<Vec<String>> = vec![Vec::new(); SECTIONS.len()];
+        let mut others = Vec::new();
+
+        for (id, header) in commits {
+            let commit = match ConventionalCommit::parse(header) {
+                Some(commit) => commit,
+                None => {
+                    others.push(format!("- {header} ({id})"));
+                    continue;
+                }
+            };
+            let item = match commit.scope.as_ref() {
+                Some(scope) => format!("- **{scope}:** {} ({id})", commit.summary),
+                None => format!("- {} ({id})", commit.summary),
+            };
+            if commit.breaking {
+                breaking.push(item);
+                continue;
+            }
+            match SECTIONS.iter().position(|(kind, _)| *kind == commit.kind) {
+                Some(idx) => sections[idx].push(item),
+                None => others.push(item),
+            }
+        }
+
+        let mut groups = vec![("Breaking Changes", breaking)];
+        for ((_, title), items) in SECTIONS.iter().zip(sections) {
+            groups.push((title, items));
+        }
+        groups.push(("Other", others));
+
+        let mut changelog = String::new();
+        for (title, items) in groups {
+            if items.is_empty() {
+                continue;
+            }
+            if !changelog.is_empty() {
+                changelog.push('\n');
+            }
+            changelog.push_str(&format!("## {title}\n\n"));
+            for item in items {
+                changelog.push_str(&item);
+                changelog.push('\n');
+            }
+        }
+        changelog
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_cfg, args| match args.len() {
+                0 | 1 => {
+                    let tags = GitTag::list()?;
+                    let items: Vec<_> = tags.into_iter().map(|tag| tag.to_string()).collect();
+                    Ok(CompletionResult::from(items))
+                }
+                _ => Ok(CompletionResult::empty()),
+            },
+            flags: Some(|_cfg, flag, _to_complete| match flag {
+                'r' => {
+                    let tags = GitTag::list()?;
+                    let items: Vec<_> = tags.into_iter().map(|tag| tag.to_string()).collect();
+                    Ok(Some(CompletionResult::from(items)))
+                }
+                _ => Ok(None),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod changelog_tests {
+    use crate::cmd::changelog::*;
+
+    #[test]
+    fn test_render() {
+        let commits = vec![
+            ("a1", "feat(api): add release api"),
+            ("b2", "fix: handle empty range"),
+            ("c3", "feat!: drop old config"),
+            ("d4", "Update README"),
+            ("e5", "style: format code"),
+            ("f6", "feat: add changelog"),
+        ];
+        let expect = "## Breaking Changes\n\n\
+                      - drop old config (c3)\n\n\
+                      ## Features\n\n\
+                      - **api:** add release api (a1)\n\
+                      - add changelog (f6)\n\n\
+                      ## Bug Fixes\n\n\
+                      - handle empty range (b2)\n\n\
+                      ## Other\n\n\
+                      - Update README (d4)\n\
+                      - format code (e5)\n";
+        assert_eq!(ChangelogArgs::render(&commits), expect);
+    }
+}
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::PathBuf;
@@ -9,20 +10,62 @@ use anyhow::{bail, Context, Result};
 use clap::Args;
 use console::style;
 use semver::VersionReq;
+use serde::Deserialize;
+use serde_json::Value;
 
 use crate::api::{self, Provider};
-use crate::cmd::Run;
+use crate::batch::{self, Task};
+use crate::cmd::{Completion, Run};
 use crate::config::{Config, RemoteConfig};
-use crate::repo::database::Database;
+use crate::exec::{Cmd, CmdResult};
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::detect;
 use crate::repo::Repo;
+use crate::table::{Table, TableCell, TableCellColor};
 use crate::{confirm, term, utils};
 
 /// Check system environment.
 #[derive(Args)]
-pub struct CheckArgs {}
+pub struct CheckArgs {
+    /// Repository selection head, only used with `--fsck` and `--audit`.
+    pub head: Option<String>,
+
+    /// Repository selection query, only used with `--fsck` and `--audit`.
+    pub query: Option<String>,
+
+    /// Verify the integrity of the repositories with `git fsck`, to detect the
+    /// corrupt objects and broken refs, such as after disk errors or
+    /// interrupted syncs.
+    #[clap(short, long)]
+    pub fsck: bool,
+
+    /// Audit the dependencies of the repositories for the security advisories.
+    /// The project type is detected from the root files, and the matching tool
+    /// is run: `cargo audit` for cargo, `npm audit` for nodejs and
+    /// `govulncheck` for gomod. The tools should be installed.
+    #[clap(short, long)]
+    pub audit: bool,
+
+    /// Use the labels to filter repository, only used with `--fsck` and
+    /// `--audit`.
+    #[clap(short, long)]
+    pub labels: Option<String>,
+
+    /// The number of repositories to check concurrently, default is
+    /// `git.workers` in config.
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+}
 
 impl Run for CheckArgs {
     fn run(&self, cfg: &Config) -> Result<()> {
+        if self.fsck {
+            return self.fsck(cfg);
+        }
+        if self.audit {
+            return self.audit(cfg);
+        }
+
         let mut db = Database::load(cfg)?;
 
         let mut checks: Vec<Box<dyn Check>> = vec![
@@ -76,7 +119,10 @@ impl Run for CheckArgs {
             eprintln!();
             confirm!("Do you want to remove failed repos");
 
-            for repo in to_remove {
+            for (repo, _) in to_remove.iter().filter(|(_, protected)| *protected) {
+                term::must_confirm_protected(cfg, &repo.name)?;
+            }
+            for (repo, _) in to_remove {
                 let path = repo.get_path(cfg);
                 utils::remove_dir_recursively(path, true)?;
                 db.remove(repo);
@@ -90,11 +136,187 @@ impl Run for CheckArgs {
 }
 
 impl CheckArgs {
+    fn fsck(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let opts = SelectOptions::default().with_filter_labels(utils::parse_labels(&self.labels));
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let (repos, level) = selector.many_local(&db)?;
+
+        let mut tasks = Vec::with_capacity(repos.len());
+        for repo in repos {
+            let path = repo.get_path(cfg);
+            // The repositories not cloned yet have nothing to check.
+            if !path.is_dir() {
+                continue;
+            }
+            tasks.push((repo.to_string(&level), FsckTask { path }));
+        }
+        if tasks.is_empty() {
+            eprintln!("No repo to check");
+            return Ok(());
+        }
+
+        let names: Vec<String> = tasks.iter().map(|(name, _)| name.clone()).collect();
+        let total = names.len();
+        let results =
+            batch::must_run_with::<_, Vec<String>>("Fsck", tasks, cfg.git.workers(self.jobs))?;
+        let corrupted: Vec<_> = names
+            .into_iter()
+            .zip(results)
+            .filter(|(_, problems)| !problems.is_empty())
+            .collect();
+        if corrupted.is_empty() {
+            eprintln!();
+            eprintln!("No problem found in {total} repo(s)");
+            return Ok(());
+        }
+
+        eprintln!();
+        for (name, problems) in corrupted.iter() {
+            eprintln!("{}:", style(name).bold());
+            for problem in problems {
+                eprintln!("  {}", style(problem).yellow());
+            }
+        }
+        eprintln!();
+        bail!(
+            "found {} corrupted in {total} repo(s) checked, consider recloning them",
+            utils::plural(&corrupted, "repo")
+        );
+    }
+
+    fn audit(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let opts = SelectOptions::default().with_filter_labels(utils::parse_labels(&self.labels));
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let (repos, level) = selector.many_local(&db)?;
+
+        let mut installed: Vec<(&str, bool)> = Vec::new();
+        let mut missing: Vec<(String, &str)> = Vec::new();
+        let mut tasks = Vec::new();
+        for repo in repos {
+            let path = repo.get_path(cfg);
+            if !path.is_dir() {
+                continue;
+            }
+            let name = repo.to_string(&level);
+            for module in detect::detect_root_modules(&path) {
+                let tool = match AUDIT_TOOLS.iter().find(|tool| tool.module == module) {
+                    Some(tool) => tool,
+                    None => continue,
+                };
+                let is_installed = match installed.iter().find(|(name, _)| *name == tool.name) {
+                    Some((_, is_installed)) => *is_installed,
+                    None => {
+                        let is_installed = tool.is_installed();
+                        installed.push((tool.name, is_installed));
+                        is_installed
+                    }
+                };
+                if !is_installed {
+                    missing.push((name.clone(), tool.name));
+                    continue;
+                }
+                let task = AuditTask {
+                    path: path.clone(),
+                    tool,
+                };
+                tasks.push((format!("{name} ({})", tool.name), (name.clone(), task)));
+            }
+        }
+        for (tool, is_installed) in installed {
+            if !is_installed {
+                eprintln!(
+                    "Tool {} is not installed, skip {}",
+                    style(tool).yellow(),
+                    utils::plural(
+                        &missing
+                            .iter()
+                            .filter(|(_, name)| *name == tool)
+                            .collect::<Vec<_>>(),
+                        "repo"
+                    )
+                );
+            }
+        }
+        if tasks.is_empty() {
+            eprintln!("No repo to audit");
+            return Ok(());
+        }
+
+        let names: Vec<_> = tasks
+            .iter()
+            .map(|(_, (name, task))| (name.clone(), task.tool.name))
+            .collect();
+        let tasks: Vec<_> = tasks
+            .into_iter()
+            .map(|(desc, (_, task))| (desc, task))
+            .collect();
+        let results =
+            batch::must_run_with::<_, AuditResult>("Audit", tasks, cfg.git.workers(self.jobs))?;
+        eprintln!();
+
+        let mut table = Table::with_capacity(1 + results.len());
+        table.add(vec![
+            String::from("Repo"),
+            String::from("Tool"),
+            String::from("Advisory"),
+            String::from("Package"),
+            String::from("Severity"),
+            String::from("Title"),
+        ]);
+        let mut vulnerable = 0;
+        let mut findings_count = 0;
+        let mut errors = Vec::new();
+        for ((name, tool), result) in names.into_iter().zip(results) {
+            match result {
+                AuditResult::Failed(err) => errors.push((name, tool, err)),
+                AuditResult::Findings(findings) => {
+                    if !findings.is_empty() {
+                        vulnerable += 1;
+                    }
+                    findings_count += findings.len();
+                    for finding in findings {
+                        let color = match finding.severity.to_lowercase().as_str() {
+                            "critical" | "high" => TableCellColor::Red,
+                            _ => TableCellColor::Yellow,
+                        };
+                        table.add_color(vec![
+                            TableCell::no_color(name.clone()),
+                            TableCell::no_color(String::from(tool)),
+                            TableCell::no_color(finding.id),
+                            TableCell::no_color(finding.package),
+                            TableCell::with_color(finding.severity, color),
+                            TableCell::no_color(finding.title),
+                        ]);
+                    }
+                }
+            }
+        }
+
+        for (name, tool, err) in errors.iter() {
+            eprintln!("{} {}: {}", style(name).bold(), tool, style(err).yellow());
+        }
+        if findings_count == 0 {
+            if !errors.is_empty() {
+                eprintln!();
+                bail!("{} failed to audit", utils::plural(&errors, "repo"));
+            }
+            eprintln!("No vulnerability found");
+            return Ok(());
+        }
+        if !errors.is_empty() {
+            eprintln!();
+        }
+        table.show();
+        bail!("found {findings_count} vulnerability(s) in {vulnerable} repo(s)");
+    }
+
     fn run_checks(
         checks: Vec<Box<dyn Check>>,
         cfg: &Config,
         db: &Database,
-        to_remove: &mut Vec<Repo>,
+        to_remove: &mut Vec<(Repo, bool)>,
         is_sub: bool,
         ok_count: &mut usize,
         fail_count: &mut usize,
@@ -126,18 +348,300 @@ impl CheckArgs {
                     let msg = err.to_string();
                     eprintln!("{} {:#}", style("✘").red().bold(), style(msg).yellow());
                     if let Some(repo) = check.get_repo() {
-                        to_remove.push(repo.update());
+                        let protected = repo.is_protected();
+                        to_remove.push((repo.update(), protected));
                     }
                 }
             };
         }
     }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: Some(Completion::labels),
+        }
+    }
+}
+
+struct FsckTask {
+    path: PathBuf,
+}
+
+/// Return the problems reported by `git fsck`, empty means the repository is
+/// healthy.
+impl Task<Vec<String>> for FsckTask {
+    fn run(&self) -> Result<Vec<String>> {
+        let path = format!("{}", self.path.display());
+        let result = Cmd::git(&[
+            "-C",
+            &path,
+            "fsck",
+            "--full",
+            "--no-progress",
+            "--no-dangling",
+        ])
+        .execute_unchecked()?;
+        let mut problems = parse_fsck_output(&result.stdout);
+        problems.extend(parse_fsck_output(&result.stderr));
+        if problems.is_empty() && result.code != Some(0) {
+            problems.push(format!("git fsck exited with code {:?}", result.code));
+        }
+        Ok(problems)
+    }
+}
+
+/// The dependency audit tool for a module type, see [`detect::detect_root_modules`].
+struct AuditTool {
+    module: &'static str,
+    name: &'static str,
+    program: &'static str,
+    args: &'static [&'static str],
+    /// The args to check if the tool is installed, for cargo, the missing
+    /// subcommand fails.
+    version_args: &'static [&'static str],
+    /// Parse the json output of the tool. The tools exit with non-zero code when
+    /// vulnerabilities are found, so the code is not checked.
+    parse: fn(&str) -> Result<Vec<AuditFinding>>,
+}
+
+const AUDIT_TOOLS: [AuditTool; 3] = [
+    AuditTool {
+        module: "cargo",
+        name: "cargo-audit",
+        program: "cargo",
+        args: &["audit", "--json"],
+        version_args: &["audit", "--version"],
+        parse: parse_cargo_audit,
+    },
+    AuditTool {
+        module: "nodejs",
+        name: "npm-audit",
+        program: "npm",
+        args: &["audit", "--json"],
+        version_args: &["--version"],
+        parse: parse_npm_audit,
+    },
+    AuditTool {
+        module: "gomod",
+        name: "govulncheck",
+        program: "govulncheck",
+        args: &["-json", "./..."],
+        version_args: &["-version"],
+        parse: parse_govulncheck,
+    },
+];
+
+impl AuditTool {
+    fn is_installed(&self) -> bool {
+        matches!(
+            Cmd::with_args(self.program, self.version_args).execute_unchecked(),
+            Ok(CmdResult { code: Some(0), .. })
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct AuditFinding {
+    id: String,
+    package: String,
+    severity: String,
+    title: String,
+}
+
+enum AuditResult {
+    Findings(Vec<AuditFinding>),
+    /// The tool failed, such as the missing lock file.
+    Failed(String),
+}
+
+struct AuditTask {
+    path: PathBuf,
+    tool: &'static AuditTool,
+}
+
+impl Task<AuditResult> for AuditTask {
+    fn run(&self) -> Result<AuditResult> {
+        let result = Cmd::with_args(self.tool.program, self.tool.args)
+            .with_path(&self.path)
+            .execute_unchecked()?;
+        if result.stdout.trim().is_empty() {
+            let msg = result.stderr.trim().lines().last().unwrap_or_default();
+            return Ok(AuditResult::Failed(format!(
+                "exited with code {:?}: {msg}",
+                result.code
+            )));
+        }
+        match (self.tool.parse)(&result.stdout) {
+            Ok(findings) => Ok(AuditResult::Findings(findings)),
+            Err(err) => Ok(AuditResult::Failed(format!("{err:#}"))),
+        }
+    }
+}
+
+fn parse_cargo_audit(output: &str) -> Result<Vec<AuditFinding>> {
+    #[derive(Deserialize)]
+    struct Report {
+        vulnerabilities: Vulnerabilities,
+    }
+    #[derive(Deserialize)]
+    struct Vulnerabilities {
+        list: Vec<Vulnerability>,
+    }
+    #[derive(Deserialize)]
+    struct Vulnerability {
+        advisory: Advisory,
+        package: Package,
+    }
+    #[derive(Deserialize)]
+    struct Advisory {
+        id: String,
+        title: String,
+        #[serde(default)]
+        informational: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Package {
+        name: String,
+        version: String,
+    }
+
+    let report: Report = serde_json::from_str(output).context("parse cargo audit output")?;
+    Ok(report
+        .vulnerabilities
+        .list
+        .into_iter()
+        .map(|vuln| AuditFinding {
+            id: vuln.advisory.id,
+            package: format!("{}@{}", vuln.package.name, vuln.package.version),
+            // The RustSec advisories only have the cvss vector, not the level.
+            severity: vuln
+                .advisory
+                .informational
+                .unwrap_or_else(|| String::from("vulnerability")),
+            title: vuln.advisory.title,
+        })
+        .collect())
+}
+
+fn parse_npm_audit(output: &str) -> Result<Vec<AuditFinding>> {
+    #[derive(Deserialize)]
+    struct Report {
+        #[serde(default)]
+        vulnerabilities: BTreeMap<String, Vulnerability>,
+        /// Such as the missing lock file.
+        error: Option<NpmError>,
+    }
+    #[derive(Deserialize)]
+    struct NpmError {
+        summary: String,
+    }
+    #[derive(Deserialize)]
+    struct Vulnerability {
+        severity: String,
+        via: Vec<Value>,
+    }
+
+    let report: Report = serde_json::from_str(output).context("parse npm audit output")?;
+    if let Some(err) = report.error {
+        bail!("npm audit failed: {}", err.summary);
+    }
+    let mut findings = Vec::new();
+    for (name, vuln) in report.vulnerabilities {
+        // The `via` is the advisory object, or the name of the vulnerable
+        // dependency for the indirect ones.
+        let advisory = vuln.via.iter().find_map(|via| via.as_object());
+        let (id, title) = match advisory {
+            Some(advisory) => {
+                let url = advisory.get("url").and_then(Value::as_str).unwrap_or("");
+                let id = url.rsplit('/').next().unwrap_or(url).to_string();
+                let title = advisory.get("title").and_then(Value::as_str).unwrap_or("");
+                (id, title.to_string())
+            }
+            None => {
+                let deps: Vec<_> = vuln.via.iter().filter_map(Value::as_str).collect();
+                (String::new(), format!("via {}", deps.join(", ")))
+            }
+        };
+        findings.push(AuditFinding {
+            id,
+            package: name,
+            severity: vuln.severity,
+            title,
+        });
+    }
+    Ok(findings)
+}
+
+/// The output of `govulncheck -json` is a stream of json objects, the
+/// vulnerabilities whose functions are called are reported, like the default
+/// text output.
+fn parse_govulncheck(output: &str) -> Result<Vec<AuditFinding>> {
+    let mut summaries: BTreeMap<String, String> = BTreeMap::new();
+    let mut called: BTreeMap<String, String> = BTreeMap::new();
+    for message in serde_json::Deserializer::from_str(output).into_iter::<Value>() {
+        let message = message.context("parse govulncheck output")?;
+        if let Some(osv) = message.get("osv") {
+            let id = osv.get("id").and_then(Value::as_str).unwrap_or_default();
+            let summary = osv
+                .get("summary")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            summaries.insert(id.to_string(), summary.to_string());
+        }
+        if let Some(finding) = message.get("finding") {
+            let trace = finding.get("trace").and_then(|trace| trace.get(0));
+            let trace = match trace {
+                Some(trace) if trace.get("function").is_some() => trace,
+                _ => continue,
+            };
+            let id = finding
+                .get("osv")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let module = trace
+                .get("module")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let version = trace.get("version").and_then(Value::as_str);
+            let package = match version {
+                Some(version) => format!("{module}@{version}"),
+                None => module.to_string(),
+            };
+            called.insert(id.to_string(), package);
+        }
+    }
+    Ok(called
+        .into_iter()
+        .map(|(id, package)| AuditFinding {
+            title: summaries.get(&id).cloned().unwrap_or_default(),
+            id,
+            package,
+            severity: String::from("called"),
+        })
+        .collect())
+}
+
+/// The dangling objects (such as the dropped stashes) and the progress lines
+/// are harmless, ignore them.
+fn parse_fsck_output(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with("dangling ")
+                && !line.starts_with("Checking ")
+                && !line.starts_with("notice: ")
+        })
+        .map(|line| line.to_string())
+        .collect()
 }
 
 trait Check {
     fn name(&self) -> Cow<'static, str>;
     fn check(&self, cfg: &Config, db: &Database) -> Result<CheckResult>;
-    fn get_repo(&self) -> Option<Repo>;
+    fn get_repo(&self) -> Option<Repo<'_>>;
 }
 
 struct CheckResult {
@@ -174,7 +678,7 @@ impl Check for CheckGit {
         })
     }
 
-    fn get_repo(&self) -> Option<Repo> {
+    fn get_repo(&self) -> Option<Repo<'_>> {
         None
     }
 }
@@ -208,7 +712,7 @@ impl Check for CheckFzf {
         })
     }
 
-    fn get_repo(&self) -> Option<Repo> {
+    fn get_repo(&self) -> Option<Repo<'_>> {
         None
     }
 }
@@ -259,7 +763,7 @@ impl Check for CheckDir {
         })
     }
 
-    fn get_repo(&self) -> Option<Repo> {
+    fn get_repo(&self) -> Option<Repo<'_>> {
         None
     }
 }
@@ -288,7 +792,7 @@ impl Check for CheckShell {
         }
     }
 
-    fn get_repo(&self) -> Option<Repo> {
+    fn get_repo(&self) -> Option<Repo<'_>> {
         None
     }
 }
@@ -341,7 +845,7 @@ impl Check for CheckRemoteApi {
         })
     }
 
-    fn get_repo(&self) -> Option<Repo> {
+    fn get_repo(&self) -> Option<Repo<'_>> {
         None
     }
 }
@@ -357,14 +861,86 @@ impl Check for CheckRepoApi<'_> {
     }
 
     fn check(&self, _cfg: &Config, _db: &Database) -> Result<CheckResult> {
-        self.provider.get_repo(&self.repo.owner, &self.repo.name)?;
+        let api_repo = self.provider.get_repo(&self.repo.owner, &self.repo.name)?;
         Ok(CheckResult {
-            hint: None,
+            hint: api_repo.archived.then(|| String::from("archived")),
             subs: None,
         })
     }
 
-    fn get_repo(&self) -> Option<Repo> {
+    fn get_repo(&self) -> Option<Repo<'_>> {
         Some(self.repo.clone())
     }
 }
+
+#[cfg(test)]
+mod check_tests {
+    use crate::cmd::check::*;
+
+    #[test]
+    fn test_parse_fsck_output() {
+        let output = "Checking object directories\n\
+                      dangling commit 2d8e6f1a\n\
+                      missing blob 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+                      error: refs/heads/dev: invalid sha1 pointer 0000000000000000000000000000000000000000\n\
+                      notice: HEAD points to an unborn branch (main)\n";
+        assert_eq!(
+            parse_fsck_output(output),
+            vec![
+                "missing blob 4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+                "error: refs/heads/dev: invalid sha1 pointer 0000000000000000000000000000000000000000",
+            ]
+        );
+    }
+    #[test]
+    fn test_parse_audit_output() {
+        let cargo = r#"{"vulnerabilities":{"found":true,"count":1,"list":[
+            {"advisory":{"id":"RUSTSEC-2020-0071","package":"time","title":"Potential segfault"},
+             "package":{"name":"time","version":"0.1.45"}}]}}"#;
+        assert_eq!(
+            parse_cargo_audit(cargo).unwrap(),
+            vec![AuditFinding {
+                id: String::from("RUSTSEC-2020-0071"),
+                package: String::from("time@0.1.45"),
+                severity: String::from("vulnerability"),
+                title: String::from("Potential segfault"),
+            }]
+        );
+
+        let npm = r#"{"vulnerabilities":{
+            "minimist":{"name":"minimist","severity":"critical","via":[
+                {"title":"Prototype Pollution","url":"https://github.com/advisories/GHSA-xvch-5gv4-984h"}]},
+            "mkdirp":{"name":"mkdirp","severity":"critical","via":["minimist"]}}}"#;
+        assert_eq!(
+            parse_npm_audit(npm).unwrap(),
+            vec![
+                AuditFinding {
+                    id: String::from("GHSA-xvch-5gv4-984h"),
+                    package: String::from("minimist"),
+                    severity: String::from("critical"),
+                    title: String::from("Prototype Pollution"),
+                },
+                AuditFinding {
+                    id: String::new(),
+                    package: String::from("mkdirp"),
+                    severity: String::from("critical"),
+                    title: String::from("via minimist"),
+                },
+            ]
+        );
+
+        let go = r#"{"config":{"scanner_name":"govulncheck"}}
+            {"osv":{"id":"GO-2023-1571","summary":"Denial of service in net/http"}}
+            {"finding":{"osv":"GO-2023-1571","trace":[{"module":"golang.org/x/net","version":"v0.1.0"}]}}
+            {"finding":{"osv":"GO-2023-1571","trace":[{"module":"golang.org/x/net","version":"v0.1.0","function":"Get"}]}}"#;
+        assert_eq!(
+            parse_govulncheck(go).unwrap(),
+            vec![AuditFinding {
+                id: String::from("GO-2023-1571"),
+                package: String::from("golang.org/x/net@v0.1.0"),
+                severity: String::from("called"),
+                title: String::from("Denial of service in net/http"),
+            }]
+        );
+    }
+}
@@ -36,9 +36,9 @@ impl CleanArgs {
         let mut files: Vec<PathBuf> = Vec::new();
 
         info!("Scan orphan under '{}'", root.display());
-        utils::walk_dir(root.clone(), |path, meta| {
+        utils::walk_dir(root.clone(), |path, entry| {
             let path = path.clone();
-            if !meta.is_dir() {
+            if !entry.is_dir() {
                 let rel_path = path.strip_prefix(&root).unwrap();
                 items.push(format!("{}", rel_path.display()));
                 files.push(path);
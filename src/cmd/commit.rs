@@ -0,0 +1,147 @@
+use anyhow::Result;
+use clap::Args;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::{CommitConfig, Config};
+use crate::git::{self, ConventionalCommit};
+use crate::repo::database::Database;
+use crate::{exec, term};
+
+/// Commit the changes with a conventional-commit message, the missing parts
+/// will be asked interactively.
+#[derive(Args)]
+pub struct CommitArgs {
+    /// The commit summary.
+    pub summary: Option<String>,
+
+    /// The commit type, such as `feat` or `fix`. If not provided, select it
+    /// with fzf.
+    #[clap(short = 't', long = "type")]
+    pub kind: Option<String>,
+
+    /// The commit scope. If not provided, ask for it (can be empty).
+    #[clap(short, long)]
+    pub scope: Option<String>,
+
+    /// Edit the commit body in the editor.
+    #[clap(short, long)]
+    pub body: bool,
+
+    /// Mark this commit as a breaking change, the description will be added
+    /// to the `BREAKING CHANGE` footer.
+    #[clap(short = 'B', long)]
+    pub breaking: bool,
+
+    /// Also stage the modified and deleted files, see `git commit -a`.
+    #[clap(short, long)]
+    pub all: bool,
+}
+
+impl Run for CommitArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let commit_cfg = Self::get_config(cfg)?;
+
+        let kind = match self.kind.as_ref() {
+            Some(kind) => kind.clone(),
+            None => {
+                let idx = exec::fzf_search(&commit_cfg.types)?;
+                commit_cfg.types[idx].clone()
+            }
+        };
+        let scope = match self.scope.as_ref() {
+            Some(scope) => scope.clone(),
+            None => term::input("Scope", commit_cfg.require_scope, None)?,
+        };
+        let summary = match self.summary.as_ref() {
+            Some(summary) => summary.clone(),
+            None => term::input("Summary", true, None)?,
+        };
+
+        let commit = ConventionalCommit {
+            kind,
+            scope: if scope.is_empty() { None } else { Some(scope) },
+            breaking: self.breaking,
+            summary,
+        };
+        commit_cfg.validate(&commit)?;
+
+        let body = if self.body || commit_cfg.template.is_some() {
+            let template = commit_cfg.template.clone().unwrap_or_default();
+            term::edit_content(cfg, template.as_str(), "commit_body", false)?
+        } else {
+            String::new()
+        };
+        let breaking = if self.breaking {
+            term::input("Breaking change", false, None)?
+        } else {
+            String::new()
+        };
+
+        let message = Self::build_message(&commit, &body, &breaking);
+        git::commit(&message, self.all)
+    }
+}
+
+impl CommitArgs {
+    fn get_config(cfg: &Config) -> Result<CommitConfig> {
+        let db = Database::load_readonly(cfg)?;
+        Ok(match db.get_current() {
+            Some(repo) => repo.remote_cfg.commit_config(&repo.owner),
+            None => CommitConfig::default(),
+        })
+    }
+
+    fn build_message(commit: &ConventionalCommit, body: &str, breaking: &str) -> String {
+        let mut message = commit.to_string();
+        let body = body.trim();
+        if !body.is_empty() {
+            message.push_str("\n\n");
+            message.push_str(body);
+        }
+        let breaking = breaking.trim();
+        if !breaking.is_empty() {
+            message.push_str("\n\nBREAKING CHANGE: ");
+            message.push_str(breaking);
+        }
+        message
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_cfg, _args| Ok(CompletionResult::empty()),
+            flags: Some(|cfg, flag, _to_complete| match flag {
+                't' => Ok(Some(CompletionResult::from(Self::get_config(cfg)?.types))),
+                's' => Ok(Some(CompletionResult::from(Self::get_config(cfg)?.scopes))),
+                _ => Ok(None),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod commit_tests {
+    use crate::cmd::commit::*;
+
+    #[test]
+    fn test_build_message() {
+        let commit = ConventionalCommit {
+            kind: String::from("feat"),
+            scope: Some(String::from("api")),
+            breaking: true,
+            summary: String::from("drop the v1 endpoint"),
+        };
+        let message = CommitArgs::build_message(&commit, "\nThe v1 is deprecated.\n", "use v2");
+        assert_eq!(
+            message,
+            "feat(api)!: drop the v1 endpoint\n\nThe v1 is deprecated.\n\nBREAKING CHANGE: use v2"
+        );
+
+        let commit = ConventionalCommit {
+            kind: String::from("fix"),
+            scope: None,
+            breaking: false,
+            summary: String::from("typo"),
+        };
+        assert_eq!(CommitArgs::build_message(&commit, "", ""), "fix: typo");
+    }
+}
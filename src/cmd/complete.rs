@@ -18,7 +18,12 @@ pub struct CompleteArgs {
 impl Run for CompleteArgs {
     fn run(&self, cfg: &Config) -> Result<()> {
         let cmds = Commands::VARIANTS;
-        let mut cmds: Vec<_> = cmds.iter().map(|key| key.to_string()).collect();
+        // The hidden commands are not completed.
+        let mut cmds: Vec<_> = cmds
+            .iter()
+            .filter(|key| **key != "debug")
+            .map(|key| key.to_string())
+            .collect();
         cmds.sort();
 
         let comps = Commands::get_completions();
@@ -104,7 +109,7 @@ struct ArgsIter<'a> {
 }
 
 impl ArgsIter<'_> {
-    fn new(args: &Vec<String>) -> ArgsIter {
+    fn new(args: &Vec<String>) -> ArgsIter<'_> {
         ArgsIter { args, idx: 0 }
     }
 
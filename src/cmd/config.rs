@@ -1,17 +1,20 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use clap::{Args, ValueEnum};
 use serde::Serialize;
 use strum::VariantNames;
+use toml_edit::{value, DocumentMut, Item, Table};
 
 use crate::cmd::{Completion, CompletionResult, Run};
-use crate::config::{Config, RemoteConfig, WorkflowConfig};
-use crate::{exec, term, utils};
+use crate::config::{defaults, CloneProtocol, Config, RemoteConfig, WorkflowConfig};
+use crate::errors::ErrorKind;
+use crate::{api, error, exec, info, term, utils, warn};
 
-/// Edit config file in terminal.
+/// Edit config file in terminal, the config will be re-validated after editing.
 #[derive(Args)]
 pub struct ConfigArgs {
     /// The config type.
@@ -23,6 +26,36 @@ pub struct ConfigArgs {
     /// Show the config as json.
     #[clap(short, long)]
     pub show: bool,
+
+    /// Set the clone protocol of the remote, instead of editing the config
+    /// file. Only available for remotes config.
+    #[clap(short, long)]
+    pub protocol: Option<CloneProtocol>,
+
+    /// Set the clone protocol for this owner, uses with `--protocol`.
+    #[clap(short, long)]
+    pub owner: Option<String>,
+
+    /// Check the config without touching anything, including the
+    /// cross-references between remotes and workflows.
+    #[clap(short, long)]
+    pub check: bool,
+
+    /// Print the fully-commented default config of the config type, useful to
+    /// discover the available options.
+    #[clap(short = 'D', long)]
+    pub default: bool,
+
+    /// Print the effective config (after env expansion and applying the
+    /// defaults) as toml. The credentials, such as the remote token, are
+    /// redacted.
+    #[clap(short, long)]
+    pub merged: bool,
+
+    /// Add a new remote config interactively, the name is the `name` argument.
+    /// The new remote will be checked by calling its remote API.
+    #[clap(short, long)]
+    pub add_remote: bool,
 }
 
 #[derive(Clone, ValueEnum, VariantNames)]
@@ -52,30 +85,236 @@ impl Run for ConfigArgs {
             return term::show_json(display);
         }
 
+        if self.check {
+            return Self::check(cfg);
+        }
+        if self.default {
+            let content = match self.config_type {
+                Some(ConfigType::Remotes) => defaults::REMOTE_TOML,
+                Some(ConfigType::Workflows) => defaults::WORKFLOW_TOML,
+                None => defaults::CONFIG_TOML,
+            };
+            print!("{content}");
+            return Ok(());
+        }
+
         let root = Config::get_path()?;
 
-        let path = if self.config_type.is_none() {
-            root.join("config.toml")
-        } else {
-            let cfg_type = match self.config_type.as_ref().unwrap() {
-                ConfigType::Remotes => "remotes",
-                ConfigType::Workflows => "workflows",
-            };
-            let dir = root.join(cfg_type);
+        if self.add_remote {
+            return self.add_remote(cfg, &root);
+        }
+        if self.merged {
+            return self.show_merged(cfg, &root);
+        }
+
+        if let Some(protocol) = self.protocol {
+            return self.set_protocol(&root, protocol);
+        }
+
+        let path = if let Some(config_type) = self.config_type.as_ref() {
+            let dir = root.join(config_type.dir_name());
             let name = match self.name.as_ref() {
                 Some(name) => Cow::Borrowed(name),
                 None => Cow::Owned(self.select_config_name(&dir)?),
             };
             dir.join(format!("{name}.toml"))
+        } else {
+            root.join("config.toml")
         };
 
         utils::ensure_dir(&path)?;
         let editor = term::get_editor()?;
-        term::edit_file(editor, &path)
+        loop {
+            term::edit_file(editor.as_str(), &path)?;
+            let err = match Config::load(cfg.profile.as_deref()) {
+                Ok(cfg) => return Self::check(&cfg),
+                Err(err) => err,
+            };
+            error!("Invalid config: {:#}", err);
+            if !term::confirm("Do you want to edit again")? {
+                bail!("the config '{}' is invalid", path.display());
+            }
+        }
     }
 }
 
 impl ConfigArgs {
+    fn add_remote(&self, cfg: &Config, root: &Path) -> Result<()> {
+        let name = match self.name.as_ref() {
+            Some(name) => name.clone(),
+            None => term::input("Remote name", true, None)?,
+        };
+        // The name is joined into the config path.
+        if name.is_empty() || name.contains('/') || name.contains("..") {
+            bail!("invalid remote name '{name}'");
+        }
+        let path = root.join("remotes").join(format!("{name}.toml"));
+        if cfg.remotes.contains_key(&name) || path.exists() {
+            return Err(ErrorKind::Conflict.error(format!("the remote '{name}' already exists")));
+        }
+
+        let providers = ["github", "gitlab", "none"];
+        let provider = match exec::fzf_search(&providers)? {
+            2 => None,
+            idx => Some(providers[idx]),
+        };
+        let default_clone = match provider {
+            Some("github") => "github.com",
+            Some("gitlab") => "gitlab.com",
+            _ => "",
+        };
+
+        let mut doc = DocumentMut::new();
+        let clone = term::input(
+            "Clone domain (empty for local remote)",
+            false,
+            Some(default_clone),
+        )?;
+        if !clone.is_empty() {
+            doc.insert("clone", value(clone.as_str()));
+        }
+        if let Some(provider) = provider {
+            doc.insert("provider", value(provider));
+            if provider == "gitlab" && clone != "gitlab.com" {
+                let domain = term::input("API domain", true, Some(clone.as_str()))?;
+                doc.insert("api_domain", value(domain));
+            }
+            if term::confirm("Set the API token (env like ${TOKEN} is supported)")? {
+                let token = term::input_secret("Token")?;
+                doc.insert("token", value(token));
+            }
+            let hours = term::input("Cache hours (0 to disable cache)", true, Some("24"))?;
+            let hours: u32 = hours
+                .parse()
+                .with_context(|| format!("invalid cache hours '{hours}'"))?;
+            doc.insert("cache_hours", value(hours as i64));
+        }
+
+        // The literal token is a secret, only the owner can read it.
+        if doc.contains_key("token") {
+            utils::write_private_file(&path, doc.to_string().as_bytes())?;
+        } else {
+            utils::write_file(&path, doc.to_string().as_bytes())?;
+        }
+        info!("Write remote config to '{}'", path.display());
+
+        let new_cfg = Config::load(cfg.profile.as_deref()).context("load the new config")?;
+        match new_cfg.get_remote(&name) {
+            Some(remote_cfg) if remote_cfg.provider.is_some() => {
+                let provider = api::build_raw_provider(&new_cfg, &remote_cfg)?;
+                let info = provider.info()?;
+                info!("Remote API: {}", info);
+                if !info.ping {
+                    warn!("Call remote API failed, please check the config");
+                }
+            }
+            Some(_) => {}
+            None => warn!("The remote is not available in the current profile"),
+        }
+
+        eprintln!();
+        eprintln!("Next steps:");
+        eprintln!("  * Edit more options: `rox config remotes {name}`");
+        eprintln!("  * Clone a repo:      `rox home {name} {{owner}}/{{name}}`");
+        if provider.is_some() {
+            eprintln!("  * Import an owner:   `rox import {name} {{owner}}`");
+        }
+        Ok(())
+    }
+
+    fn show_merged(&self, cfg: &Config, root: &Path) -> Result<()> {
+        let content = match self.config_type.as_ref() {
+            Some(config_type) => {
+                let dir = root.join(config_type.dir_name());
+                let name = match self.name.as_ref() {
+                    Some(name) => Cow::Borrowed(name),
+                    None => Cow::Owned(self.select_config_name(&dir)?),
+                };
+                match config_type {
+                    ConfigType::Remotes => {
+                        let mut remote_cfg = cfg.must_get_remote(name.as_str())?.into_owned();
+                        remote_cfg.redact();
+                        toml::to_string_pretty(&remote_cfg)
+                    }
+                    ConfigType::Workflows => {
+                        toml::to_string_pretty(cfg.get_workflow(name.as_str())?.as_ref())
+                    }
+                }
+            }
+            None => {
+                let mut cfg = cfg.clone();
+                cfg.redact();
+                toml::to_string_pretty(&cfg)
+            }
+        }
+        .context("encode config to toml")?;
+        print!("{content}");
+        Ok(())
+    }
+
+    fn check(cfg: &Config) -> Result<()> {
+        let problems = cfg.check();
+        if problems.is_empty() {
+            info!("Config is valid");
+            return Ok(());
+        }
+        for problem in problems.iter() {
+            error!("{}", problem);
+        }
+        bail!("found {} in config", utils::plural(&problems, "problem"))
+    }
+
+    fn set_protocol(&self, root: &Path, protocol: CloneProtocol) -> Result<()> {
+        if !matches!(self.config_type, Some(ConfigType::Remotes)) {
+            bail!("the clone protocol can only be set for remotes config");
+        }
+        let dir = root.join("remotes");
+        let name = match self.name.as_ref() {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(self.select_config_name(&dir)?),
+        };
+        let path = dir.join(format!("{name}.toml"));
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("read remote config file '{}'", path.display()))?;
+        let mut doc: DocumentMut = content
+            .parse()
+            .with_context(|| format!("parse remote config file '{}'", path.display()))?;
+
+        let table = match self.owner.as_ref() {
+            Some(owner) => {
+                let owners = doc
+                    .entry("owners")
+                    .or_insert_with(|| Item::Table(Table::new()))
+                    .as_table_mut()
+                    .context("the 'owners' in remote config is not a table")?;
+                owners.set_implicit(true);
+                owners
+                    .entry(owner)
+                    .or_insert_with(|| Item::Table(Table::new()))
+                    .as_table_mut()
+                    .with_context(|| {
+                        format!("the owner '{owner}' in remote config is not a table")
+                    })?
+            }
+            None => doc.as_table_mut(),
+        };
+        table.insert("clone_protocol", value(protocol.as_str()));
+
+        utils::write_file(&path, doc.to_string().as_bytes())?;
+        match self.owner.as_ref() {
+            Some(owner) => info!(
+                "Set clone protocol of '{}:{}' to {}",
+                name,
+                owner,
+                protocol.as_str()
+            ),
+            None => info!("Set clone protocol of '{}' to {}", name, protocol.as_str()),
+        }
+        info!("Use `sync --fix-origin` to update the origin of existing repos");
+        Ok(())
+    }
+
     fn select_config_name(&self, dir: &Path) -> Result<String> {
         let mut names = self.config_type.as_ref().unwrap().list_names(dir)?;
 
@@ -115,12 +354,22 @@ impl ConfigArgs {
                 }
                 _ => Ok(CompletionResult::empty()),
             },
-            flags: None,
+            flags: Some(|cfg, flag, _to_complete| match flag {
+                'o' => Completion::owners_flag(cfg),
+                _ => Ok(None),
+            }),
         }
     }
 }
 
 impl ConfigType {
+    fn dir_name(&self) -> &'static str {
+        match self {
+            ConfigType::Remotes => "remotes",
+            ConfigType::Workflows => "workflows",
+        }
+    }
+
     fn list_names(&self, dir: &Path) -> Result<Vec<String>> {
         let mut names: Vec<String> = match self {
             ConfigType::Remotes => Config::load_remotes(dir)?.into_keys().collect(),
@@ -0,0 +1,433 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use sha2::{Digest, Sha256};
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::local::LocalConfig;
+use crate::config::{defaults, Config};
+use crate::exec::Cmd;
+use crate::info;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::Repo;
+
+/// Dev container of repository.
+#[derive(Args)]
+pub struct ContainerArgs {
+    #[clap(subcommand)]
+    pub command: ContainerCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ContainerCommand {
+    /// Build and start the container in background. The environment is
+    /// detected from the devcontainer config, the compose file or the
+    /// Dockerfile in the repository, and can be set by `container` in the local
+    /// config `.roxide.toml`.
+    Up(ContainerUpArgs),
+    /// Open a shell in the container, it is started first if not running.
+    Enter(ContainerEnterArgs),
+}
+
+#[derive(Args)]
+pub struct ContainerTarget {
+    /// Repository selection head, default is the current repository.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Use search instead of fuzzy matching.
+    #[clap(short, long)]
+    pub search: bool,
+}
+
+#[derive(Args)]
+pub struct ContainerUpArgs {
+    #[clap(flatten)]
+    pub target: ContainerTarget,
+
+    /// Rebuild the image and recreate the container even if it exists.
+    #[clap(short, long)]
+    pub rebuild: bool,
+}
+
+#[derive(Args)]
+pub struct ContainerEnterArgs {
+    #[clap(flatten)]
+    pub target: ContainerTarget,
+
+    /// The shell to run in the container, default is `container.shell` in
+    /// local config, or `docker.shell` in config.
+    #[clap(short = 'S', long)]
+    pub shell: Option<String>,
+}
+
+impl Run for ContainerArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        // Bringing the container up may take minutes (building the image), do
+        // not hold the database lock across it.
+        let db = Database::load_readonly(cfg)?;
+        let target = match &self.command {
+            ContainerCommand::Up(args) => &args.target,
+            ContainerCommand::Enter(args) => &args.target,
+        };
+        let repo = if target.head.is_none() {
+            db.must_get_current()?
+        } else {
+            let opts = SelectOptions::default()
+                .with_force_search(target.search)
+                .with_force_local(true);
+            Selector::from_args(&target.head, &target.query, opts).must_one(&db)?
+        };
+        let path = repo.get_path(cfg);
+        if !path.is_dir() {
+            bail!(
+                "repo '{}' does not exist in local, please use `home` to create it first",
+                repo.name_with_remote()
+            );
+        }
+        let container = Container::load(cfg, &repo, path)?;
+
+        match &self.command {
+            ContainerCommand::Up(args) => {
+                container.up(args.rebuild)?;
+                info!("Container for {} is up", repo.name_with_remote());
+                Ok(())
+            }
+            ContainerCommand::Enter(args) => {
+                container.up(false)?;
+
+                let mut db = Database::load(cfg)?;
+                if let Some(mut repo) = db.get(&repo.remote, &repo.owner, &repo.name) {
+                    repo.accessed += 1;
+                    repo.last_accessed = cfg.now();
+                    db.upsert(repo.update());
+                }
+                db.save()?;
+                container.enter(args.shell.as_deref())
+            }
+        }
+    }
+}
+
+impl ContainerArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: |cfg, args| match args.len() {
+                0 | 1 => Ok(CompletionResult::from(vec![
+                    String::from("up"),
+                    String::from("enter"),
+                ])),
+                _ => Completion::repo_args(cfg, &args[1..]),
+            },
+            flags: None,
+        }
+    }
+}
+
+/// The kind of the dev environment, detected from the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContainerKind {
+    /// Managed by the `devcontainer` cli.
+    DevContainer,
+    /// Managed by `docker compose`.
+    Compose,
+    /// Built by `docker build` and run as a long-running container.
+    Dockerfile,
+}
+
+struct Container<'a> {
+    cfg: &'a Config,
+
+    kind: ContainerKind,
+    file: PathBuf,
+    path: PathBuf,
+
+    /// The name of the image, container and compose project.
+    name: String,
+
+    service: Option<String>,
+    shell: String,
+    work_dir: String,
+}
+
+impl Container<'_> {
+    /// The files to detect, in order.
+    const DETECT_FILES: [&'static str; 7] = [
+        ".devcontainer/devcontainer.json",
+        ".devcontainer.json",
+        "compose.yaml",
+        "compose.yml",
+        "docker-compose.yaml",
+        "docker-compose.yml",
+        "Dockerfile",
+    ];
+
+    fn load<'a>(cfg: &'a Config, repo: &Repo, path: PathBuf) -> Result<Container<'a>> {
+        let local = LocalConfig::load(&path)?.container;
+        let file = match local.file.as_ref() {
+            Some(file) => {
+                let file = path.join(file);
+                if !file.is_file() {
+                    bail!(
+                        "the container file '{}' in local config does not exist",
+                        file.display()
+                    );
+                }
+                file
+            }
+            None => match Self::DETECT_FILES
+                .iter()
+                .map(|file| path.join(file))
+                .find(|file| file.is_file())
+            {
+                Some(file) => file,
+                None => bail!(
+                    "no devcontainer config, compose file or Dockerfile in repo '{}', please set `container.file` in {}",
+                    repo.name_with_remote(),
+                    LocalConfig::FILE_NAME
+                ),
+            },
+        };
+
+        Ok(Container {
+            cfg,
+            kind: Self::detect_kind(&file),
+            file,
+            path,
+            name: Self::container_name(repo),
+            service: local.service,
+            shell: local.shell.unwrap_or_else(|| cfg.docker.shell.clone()),
+            work_dir: local.work_dir.unwrap_or_else(defaults::step_work_dir),
+        })
+    }
+
+    fn detect_kind(file: &Path) -> ContainerKind {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with("devcontainer.json") {
+            return ContainerKind::DevContainer;
+        }
+        if name.contains("compose") && (name.ends_with(".yaml") || name.ends_with(".yml")) {
+            return ContainerKind::Compose;
+        }
+        ContainerKind::Dockerfile
+    }
+
+    /// The name is `roxide-{owner}-{name}-{hash}`, in lowercase, the characters
+    /// not allowed by docker (the image names and compose projects are the
+    /// strictest) are replaced with "-". Different repos may be the same after
+    /// replacing (such as `a/b` + `c` and `a` + `b-c`), so the short hash of
+    /// the full repo name is appended to keep it unique.
+    fn container_name(repo: &Repo) -> String {
+        let name: String = format!("roxide-{}-{}", repo.owner, repo.name)
+            .to_lowercase()
+            .chars()
+            .map(|ch| {
+                if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                    ch
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        let hash = Sha256::digest(repo.name_with_remote().as_bytes());
+        let hash: String = hash[..4].iter().map(|b| format!("{b:02x}")).collect();
+        format!("{name}-{hash}")
+    }
+
+    fn up(&self, rebuild: bool) -> Result<()> {
+        match self.kind {
+            ContainerKind::DevContainer => {
+                let mut args = self.devcontainer_args("up");
+                if rebuild {
+                    args.push(String::from("--remove-existing-container"));
+                }
+                Self::cmd("devcontainer", &[], &args).execute()
+            }
+            ContainerKind::Compose => {
+                let mut args = self.compose_args();
+                args.extend([String::from("up"), String::from("-d")]);
+                if rebuild {
+                    args.extend([String::from("--build"), String::from("--force-recreate")]);
+                }
+                self.docker_cmd(&args).execute()
+            }
+            ContainerKind::Dockerfile => self.up_dockerfile(rebuild),
+        }
+    }
+
+    fn up_dockerfile(&self, rebuild: bool) -> Result<()> {
+        let state = self
+            .docker_cmd(&["inspect", "-f", "{{.State.Running}}", self.name.as_str()])
+            .execute_unchecked()?;
+        let exists = state.code == Some(0);
+        if exists && !rebuild {
+            if state.stdout.trim() != "true" {
+                self.docker_cmd(&["start", self.name.as_str()]).execute()?;
+            }
+            return Ok(());
+        }
+        if exists {
+            self.docker_cmd(&["rm", "-f", self.name.as_str()])
+                .execute()?;
+        }
+
+        let file = format!("{}", self.file.display());
+        let path = format!("{}", self.path.display());
+        self.docker_cmd(&["build", "-t", &self.name, "-f", &file, &path])
+            .execute()?;
+        let volume = format!("{path}:{}", self.work_dir);
+        // Keep the container running, so that it can be entered many times.
+        self.docker_cmd(&[
+            "run",
+            "-d",
+            "--name",
+            &self.name,
+            "-v",
+            &volume,
+            "-w",
+            &self.work_dir,
+            "--entrypoint",
+            "sleep",
+            &self.name,
+            "infinity",
+        ])
+        .execute()
+    }
+
+    fn enter(&self, shell: Option<&str>) -> Result<()> {
+        let shell = shell.unwrap_or(&self.shell);
+        let (program, args) = match self.kind {
+            ContainerKind::DevContainer => {
+                let mut args = self.devcontainer_args("exec");
+                args.push(shell.to_string());
+                (String::from("devcontainer"), args)
+            }
+            ContainerKind::Compose => {
+                let service = match self.service.as_ref() {
+                    Some(service) => service.clone(),
+                    None => self.first_service()?,
+                };
+                let mut args = self.cfg.docker.args.clone();
+                args.extend(self.compose_args());
+                args.extend([String::from("exec"), service, shell.to_string()]);
+                (self.cfg.docker.name.clone(), args)
+            }
+            ContainerKind::Dockerfile => {
+                let mut args = self.cfg.docker.args.clone();
+                args.extend(
+                    ["exec", "-it", "-w", &self.work_dir, &self.name, shell]
+                        .into_iter()
+                        .map(String::from),
+                );
+                (self.cfg.docker.name.clone(), args)
+            }
+        };
+
+        let mut cmd = Command::new(&program);
+        cmd.args(&args);
+        cmd.stdout(Stdio::inherit());
+        cmd.stderr(Stdio::inherit());
+        cmd.stdin(Stdio::inherit());
+        let status = cmd
+            .status()
+            .with_context(|| format!("launch '{program}'"))?;
+        if !status.success() {
+            bail!("enter container exited with {status}");
+        }
+        Ok(())
+    }
+
+    fn first_service(&self) -> Result<String> {
+        let mut args = self.compose_args();
+        args.extend([String::from("config"), String::from("--services")]);
+        let services = self.docker_cmd(&args).lines()?;
+        match services
+            .into_iter()
+            .find(|service| !service.trim().is_empty())
+        {
+            Some(service) => Ok(service.trim().to_string()),
+            None => bail!("no service in compose file '{}'", self.file.display()),
+        }
+    }
+
+    fn devcontainer_args(&self, action: &str) -> Vec<String> {
+        vec![
+            action.to_string(),
+            String::from("--workspace-folder"),
+            format!("{}", self.path.display()),
+            String::from("--config"),
+            format!("{}", self.file.display()),
+        ]
+    }
+
+    fn compose_args(&self) -> Vec<String> {
+        vec![
+            String::from("compose"),
+            String::from("-f"),
+            format!("{}", self.file.display()),
+            String::from("-p"),
+            self.name.clone(),
+        ]
+    }
+
+    fn docker_cmd<S: AsRef<str>>(&self, args: &[S]) -> Cmd {
+        Self::cmd(&self.cfg.docker.name, &self.cfg.docker.args, args)
+    }
+
+    fn cmd<S: AsRef<str>>(program: &str, prefix: &[String], args: &[S]) -> Cmd {
+        let mut cmd_args: Vec<&str> = prefix.iter().map(|arg| arg.as_str()).collect();
+        cmd_args.extend(args.iter().map(|arg| arg.as_ref()));
+        Cmd::with_args(program, &cmd_args).with_display_cmd()
+    }
+}
+
+#[cfg(test)]
+mod container_tests {
+    use std::borrow::Cow;
+
+    use crate::cmd::container::*;
+    use crate::config::config_tests;
+
+    #[test]
+    fn test_detect_kind() {
+        let cases = [
+            (
+                ".devcontainer/devcontainer.json",
+                ContainerKind::DevContainer,
+            ),
+            ("docker-compose.yml", ContainerKind::Compose),
+            ("deploy/compose.dev.yaml", ContainerKind::Compose),
+            ("Dockerfile", ContainerKind::Dockerfile),
+            ("docker/dev.Dockerfile", ContainerKind::Dockerfile),
+        ];
+        for (file, expect) in cases {
+            assert_eq!(Container::detect_kind(Path::new(file)), expect);
+        }
+    }
+
+    #[test]
+    fn test_container_name() {
+        let cfg = config_tests::load_test_config("container/name");
+        let new_repo = |owner: &'static str, name: &'static str| {
+            Repo::new(
+                &cfg,
+                Cow::Borrowed("github"),
+                Cow::Borrowed(owner),
+                Cow::Borrowed(name),
+                None,
+            )
+            .unwrap()
+        };
+        let name = Container::container_name(&new_repo("a/b", "c"));
+        assert!(name.starts_with("roxide-a-b-c-"), "{name}");
+        assert_ne!(name, Container::container_name(&new_repo("a", "b-c")));
+        assert_eq!(name, Container::container_name(&new_repo("a/b", "c")));
+    }
+}
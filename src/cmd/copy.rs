@@ -82,21 +82,15 @@ impl Run for CopyArgs {
         let git_remote = GitRemote::new();
         let clone_url = git_remote.get_url()?;
 
-        let user = match Cmd::git(&["config", "user.name"])
+        let user = Cmd::git(&["config", "user.name"])
             .with_display("Get current user")
             .read()
-        {
-            Ok(user) => Some(user),
-            Err(_) => None,
-        };
+            .ok();
 
-        let email = match Cmd::git(&["config", "user.email"])
+        let email = Cmd::git(&["config", "user.email"])
             .with_display("Get current email")
             .read()
-        {
-            Ok(email) => Some(email),
-            Err(_) => None,
-        };
+            .ok();
 
         let path = repo.get_path(cfg);
         match fs::read_dir(&path) {
@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::{Config, DaemonJob};
+use crate::exec::Cmd;
+use crate::filelock::FileLock;
+use crate::interrupt::{self, Interrupt};
+use crate::schedule::Schedule;
+use crate::table::{Table, TableCell, TableCellColor};
+use crate::{error, info, term, utils};
+
+/// Run the scheduled jobs in background.
+#[derive(Args)]
+pub struct DaemonArgs {
+    #[clap(subcommand)]
+    pub command: DaemonCommand,
+}
+
+#[derive(Subcommand)]
+pub enum DaemonCommand {
+    /// Start the daemon, only one daemon can be running at the same time.
+    ///
+    /// The scheduled jobs (see `daemon.jobs` in config) are run in the
+    /// foreground, run it with systemd, tmux, etc. as a daemon. The output of
+    /// the jobs is written to `{metadir}/daemon_logs/{job}.log`.
+    Start,
+    /// Show whether the daemon is running, and the last results of the jobs.
+    Status,
+    /// Run the job immediately in the foreground.
+    RunNow(DaemonRunNowArgs),
+    /// Install the daemon as a systemd user service (launchd agent on macOS),
+    /// so that it is started after login.
+    Install(DaemonInstallArgs),
+}
+
+#[derive(Args)]
+pub struct DaemonRunNowArgs {
+    /// The job name.
+    pub job: String,
+}
+
+#[derive(Args)]
+pub struct DaemonInstallArgs {
+    /// Only print the service file, skip installing.
+    #[clap(short, long)]
+    pub dry_run: bool,
+}
+
+impl Run for DaemonArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        match &self.command {
+            DaemonCommand::Start => Self::start(cfg),
+            DaemonCommand::Status => Self::status(cfg),
+            DaemonCommand::Install(args) => Self::install(args.dry_run),
+            DaemonCommand::RunNow(args) => {
+                let job = match cfg.daemon.jobs.iter().find(|job| job.name == args.job) {
+                    Some(job) => job,
+                    None => bail!("could not find daemon job '{}'", args.job),
+                };
+                let record = run_job(job, None);
+                JobRecords::new(cfg).save(cfg, &job.name, &record)?;
+                match record.error {
+                    Some(err) => bail!("job '{}' failed: {}", job.name, err),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl DaemonArgs {
+    fn start(cfg: &Config) -> Result<()> {
+        let mut jobs = Vec::with_capacity(cfg.daemon.jobs.len());
+        for job in cfg.daemon.jobs.iter() {
+            let schedule = Schedule::parse(&job.schedule)
+                .with_context(|| format!("parse schedule for daemon job '{}'", job.name))?;
+            jobs.push((job, schedule));
+        }
+        if jobs.is_empty() {
+            bail!("no daemon job, please add `daemon.jobs` in config");
+        }
+
+        let _lock = FileLock::acquire(cfg, "daemon")?;
+        let pid_path = pid_path(cfg);
+        utils::write_file(&pid_path, format!("{}", std::process::id()).as_bytes())?;
+        let logs_dir = cfg.get_meta_dir().join("daemon_logs");
+        let records = JobRecords::new(cfg);
+        info!(
+            "Start daemon with {} jobs, press Ctrl-C to stop",
+            jobs.len()
+        );
+
+        let interrupt = Interrupt::catch();
+        let mut last_minute = Local::now().timestamp() / 60;
+        while !interrupt::is_interrupted() {
+            thread::sleep(Duration::from_secs(1));
+            let now = Local::now();
+            let minute = now.timestamp() / 60;
+            if minute == last_minute {
+                continue;
+            }
+            last_minute = minute;
+
+            // The jobs run one by one, the minutes passed while running are not
+            // caught up.
+            for (job, schedule) in jobs.iter() {
+                if !schedule.matches(&now) || interrupt::is_interrupted() {
+                    continue;
+                }
+                info!("Run job {}", job.name);
+                let log_path = logs_dir.join(format!("{}.log", job.name));
+                let record = run_job(job, Some(&log_path));
+                match record.error.as_ref() {
+                    Some(err) => error!("Job {} failed: {}", job.name, err),
+                    None => info!("Job {} done", job.name),
+                }
+                // The daemon should keep running, only report the errors.
+                if let Err(err) = records.save(cfg, &job.name, &record) {
+                    error!("Save job record: {:#}", err);
+                }
+            }
+        }
+        drop(interrupt);
+
+        fs::remove_file(&pid_path).context("remove daemon pid file")?;
+        info!("Stop daemon");
+        Ok(())
+    }
+
+    fn status(cfg: &Config) -> Result<()> {
+        match running_pid(cfg)? {
+            Some(pid) => eprintln!("Daemon is running, pid {pid}"),
+            None => eprintln!("Daemon is not running"),
+        }
+        if cfg.daemon.jobs.is_empty() {
+            eprintln!("No daemon job");
+            return Ok(());
+        }
+        eprintln!();
+
+        let records = JobRecords::new(cfg).load()?;
+        let now = Local::now();
+        let mut table = Table::with_capacity(1 + cfg.daemon.jobs.len());
+        table.add(vec![
+            String::from("Job"),
+            String::from("Schedule"),
+            String::from("Next"),
+            String::from("Last"),
+            String::from("Status"),
+            String::from("Elapsed"),
+        ]);
+        for job in cfg.daemon.jobs.iter() {
+            let next = match Schedule::parse(&job.schedule) {
+                Ok(schedule) => match schedule.next_after(&now) {
+                    Some(next) => next.format("%Y-%m-%d %H:%M").to_string(),
+                    None => String::from("never"),
+                },
+                Err(_) => String::from("invalid"),
+            };
+            let (last, status, elapsed) = match records.get(&job.name) {
+                Some(record) => (
+                    utils::format_since(cfg, record.time),
+                    match record.error {
+                        Some(_) => {
+                            TableCell::with_color(String::from("failed"), TableCellColor::Red)
+                        }
+                        None => TableCell::with_color(String::from("ok"), TableCellColor::Green),
+                    },
+                    utils::format_elapsed(Duration::from_millis(record.elapsed_ms)),
+                ),
+                None => (
+                    String::from("never"),
+                    TableCell::no_color(String::new()),
+                    String::new(),
+                ),
+            };
+            table.add_color(vec![
+                TableCell::no_color(job.name.clone()),
+                TableCell::no_color(job.schedule.clone()),
+                TableCell::no_color(next),
+                TableCell::no_color(last),
+                status,
+                TableCell::no_color(elapsed),
+            ]);
+        }
+        table.show();
+        Ok(())
+    }
+
+    fn install(dry_run: bool) -> Result<()> {
+        let service = Service::build()?;
+        if dry_run {
+            eprintln!("Service file: {}", service.path.display());
+            print!("{}", service.content);
+            return Ok(());
+        }
+
+        if let Ok(content) = fs::read_to_string(&service.path) {
+            if content == service.content {
+                info!("The service is already installed");
+                return Ok(());
+            }
+            term::must_confirm(format!(
+                "The service file '{}' exists, do you want to overwrite it",
+                service.path.display()
+            ))?;
+        }
+        utils::write_file(&service.path, service.content.as_bytes())?;
+        info!("Write service file '{}'", service.path.display());
+
+        for cmd in service.enable_cmds.iter() {
+            let args: Vec<&str> = cmd[1..].iter().map(|arg| arg.as_str()).collect();
+            Cmd::with_args(&cmd[0], &args)
+                .with_display_cmd()
+                .execute()?;
+        }
+        info!("Install daemon service done");
+        Ok(())
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |cfg, args| match args.len() {
+                0 | 1 => Ok(CompletionResult::from(vec![
+                    String::from("start"),
+                    String::from("status"),
+                    String::from("run-now"),
+                    String::from("install"),
+                ])),
+                2 if args[0] == "run-now" => Ok(CompletionResult::from(
+                    cfg.daemon
+                        .jobs
+                        .iter()
+                        .map(|job| job.name.clone())
+                        .collect::<Vec<_>>(),
+                )),
+                _ => Ok(CompletionResult::empty()),
+            },
+            flags: None,
+        }
+    }
+}
+
+/// The service to run `daemon start` after login.
+struct Service {
+    path: PathBuf,
+    content: String,
+
+    /// The commands to load and start the service.
+    enable_cmds: Vec<Vec<String>>,
+}
+
+impl Service {
+    const SYSTEMD_NAME: &'static str = "roxide-daemon.service";
+    const LAUNCHD_LABEL: &'static str = "com.github.fioncat.roxide.daemon";
+
+    /// The env passed to the service. The service manager starts the service
+    /// with a minimal env, so the `PATH` (to find git, docker, etc.) and the
+    /// roxide config are taken from the current shell.
+    const ENV_NAMES: [&'static str; 3] = ["PATH", "ROXIDE_CONFIG", "ROXIDE_PROFILE"];
+
+    fn build() -> Result<Service> {
+        let exe = std::env::current_exe().context("get roxide executable path")?;
+        let exe = format!("{}", exe.display());
+        let env: Vec<(&str, String)> = Self::ENV_NAMES
+            .iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (*name, value)))
+            .collect();
+        let home = utils::get_home_dir()?;
+
+        if cfg!(target_os = "macos") {
+            let path = home
+                .join("Library")
+                .join("LaunchAgents")
+                .join(format!("{}.plist", Self::LAUNCHD_LABEL));
+            let path_str = format!("{}", path.display());
+            let log = format!("{}", home.join("Library/Logs/roxide-daemon.log").display());
+            return Ok(Service {
+                content: Self::launchd_plist(&exe, &env, &log),
+                enable_cmds: vec![
+                    vec![
+                        String::from("launchctl"),
+                        String::from("unload"),
+                        path_str.clone(),
+                    ],
+                    vec![
+                        String::from("launchctl"),
+                        String::from("load"),
+                        String::from("-w"),
+                        path_str,
+                    ],
+                ],
+                path,
+            });
+        }
+
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => home.join(".config"),
+        };
+        Ok(Service {
+            path: config_dir
+                .join("systemd")
+                .join("user")
+                .join(Self::SYSTEMD_NAME),
+            content: Self::systemd_unit(&exe, &env),
+            enable_cmds: vec![
+                vec![
+                    String::from("systemctl"),
+                    String::from("--user"),
+                    String::from("daemon-reload"),
+                ],
+                vec![
+                    String::from("systemctl"),
+                    String::from("--user"),
+                    String::from("enable"),
+                    String::from("--now"),
+                    String::from(Self::SYSTEMD_NAME),
+                ],
+            ],
+        })
+    }
+
+    fn systemd_unit(exe: &str, env: &[(&str, String)]) -> String {
+        // The `%` is the specifier prefix in systemd.
+        let quote = |s: &str| {
+            let s = s
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('%', "%%");
+            format!("\"{s}\"")
+        };
+        let mut lines = vec![
+            String::from("[Unit]"),
+            String::from("Description=roxide daemon, run the scheduled jobs"),
+            String::new(),
+            String::from("[Service]"),
+            format!("ExecStart={} daemon start", quote(exe)),
+        ];
+        for (name, value) in env.iter() {
+            lines.push(format!("Environment={}", quote(&format!("{name}={value}"))));
+        }
+        lines.extend([
+            String::from("Restart=on-failure"),
+            String::from("RestartSec=10"),
+            String::new(),
+            String::from("[Install]"),
+            String::from("WantedBy=default.target"),
+        ]);
+        let mut content = lines.join("\n");
+        content.push('\n');
+        content
+    }
+
+    fn launchd_plist(exe: &str, env: &[(&str, String)], log: &str) -> String {
+        let escape = |s: &str| {
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        };
+        let string = |s: &str| format!("<string>{}</string>", escape(s));
+        let mut lines = vec![
+            String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+            String::from(
+                r#"<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#,
+            ),
+            String::from(r#"<plist version="1.0">"#),
+            String::from("<dict>"),
+            String::from("  <key>Label</key>"),
+            format!("  {}", string(Self::LAUNCHD_LABEL)),
+            String::from("  <key>ProgramArguments</key>"),
+            String::from("  <array>"),
+            format!("    {}", string(exe)),
+            format!("    {}", string("daemon")),
+            format!("    {}", string("start")),
+            String::from("  </array>"),
+        ];
+        if !env.is_empty() {
+            lines.push(String::from("  <key>EnvironmentVariables</key>"));
+            lines.push(String::from("  <dict>"));
+            for (name, value) in env.iter() {
+                lines.push(format!("    <key>{}</key>", escape(name)));
+                lines.push(format!("    {}", string(value)));
+            }
+            lines.push(String::from("  </dict>"));
+        }
+        lines.extend([
+            String::from("  <key>RunAtLoad</key>"),
+            String::from("  <true/>"),
+            String::from("  <key>KeepAlive</key>"),
+            String::from("  <true/>"),
+            String::from("  <key>StandardOutPath</key>"),
+            format!("  {}", string(log)),
+            String::from("  <key>StandardErrorPath</key>"),
+            format!("  {}", string(log)),
+            String::from("</dict>"),
+            String::from("</plist>"),
+        ]);
+        let mut content = lines.join("\n");
+        content.push('\n');
+        content
+    }
+}
+
+fn pid_path(cfg: &Config) -> PathBuf {
+    cfg.get_meta_dir().join("daemon.pid")
+}
+
+/// Return the pid of the running daemon. The pid file is left when the daemon
+/// is killed, so check whether the process is still alive.
+fn running_pid(cfg: &Config) -> Result<Option<i32>> {
+    let path = pid_path(cfg);
+    let pid = match fs::read_to_string(&path) {
+        Ok(pid) => pid,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("read pid file '{}'", path.display())),
+    };
+    let pid: i32 = match pid.trim().parse() {
+        Ok(pid) => pid,
+        Err(_) => return Ok(None),
+    };
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    Ok(if alive { Some(pid) } else { None })
+}
+
+/// Run the job as a roxide sub process. The output is written to the log file,
+/// or shown in terminal if `log_path` is [`None`].
+fn run_job(job: &DaemonJob, log_path: Option<&Path>) -> JobRecord {
+    let start = Instant::now();
+    let time = Local::now().timestamp() as u64;
+    let error = match run_job_process(job, log_path) {
+        Ok(()) => None,
+        Err(err) => Some(format!("{err:#}")),
+    };
+    JobRecord {
+        time,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+fn run_job_process(job: &DaemonJob, log_path: Option<&Path>) -> Result<()> {
+    let exe = std::env::current_exe().context("get roxide executable path")?;
+    let mut cmd = Command::new(exe);
+    cmd.args(&job.args);
+    // There is no one to confirm in daemon, and the output is not a terminal.
+    cmd.env("ROXIDE_NOCONFIRM", "true");
+    cmd.env("ROXIDE_DAEMON_JOB", "true");
+    if let Some(log_path) = log_path {
+        utils::ensure_dir(log_path)?;
+        let log = File::create(log_path)
+            .with_context(|| format!("create log file '{}'", log_path.display()))?;
+        let log_err = log.try_clone().context("clone log file")?;
+        cmd.stdin(Stdio::null());
+        cmd.stdout(log);
+        cmd.stderr(log_err);
+    }
+
+    let status = cmd.status().context("launch roxide")?;
+    if !status.success() {
+        bail!("roxide {} exited with {status}", job.args.join(" "));
+    }
+    Ok(())
+}
+
+/// The last run result of a job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JobRecord {
+    time: u64,
+    elapsed_ms: u64,
+
+    /// The failure reason, [`None`] means the job succeeded.
+    error: Option<String>,
+}
+
+/// The last results of the jobs, stored in `{metadir}/daemon_jobs`.
+struct JobRecords {
+    path: PathBuf,
+}
+
+impl JobRecords {
+    fn new(cfg: &Config) -> JobRecords {
+        JobRecords {
+            path: cfg.get_meta_dir().join("daemon_jobs"),
+        }
+    }
+
+    fn load(&self) -> Result<HashMap<String, JobRecord>> {
+        match fs::read(&self.path) {
+            Ok(data) => bincode::deserialize(&data).context("decode daemon job records"),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err)
+                .with_context(|| format!("read daemon job records '{}'", self.path.display())),
+        }
+    }
+
+    fn save(&self, cfg: &Config, name: &str, record: &JobRecord) -> Result<()> {
+        let _lock = FileLock::acquire(cfg, "daemon_jobs")?;
+        let mut records = self.load()?;
+        records.insert(name.to_string(), record.clone());
+        let data = bincode::serialize(&records).context("encode daemon job records")?;
+        utils::write_file(&self.path, &data)
+    }
+}
+
+#[cfg(test)]
+mod daemon_tests {
+    use crate::cmd::daemon::*;
+
+    #[test]
+    fn test_systemd_unit() {
+        let env = vec![("PATH", String::from("/usr/bin:/bin"))];
+        let expect = r#"[Unit]
+Description=roxide daemon, run the scheduled jobs
+
+[Service]
+ExecStart="/opt/my bin/roxide" daemon start
+Environment="PATH=/usr/bin:/bin"
+Restart=on-failure
+RestartSec=10
+
+[Install]
+WantedBy=default.target
+"#;
+        assert_eq!(Service::systemd_unit("/opt/my bin/roxide", &env), expect);
+    }
+}
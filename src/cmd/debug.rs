@@ -0,0 +1,343 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+
+use crate::batch::{self, Task};
+use crate::cmd::Run;
+use crate::config::Config;
+use crate::exec::Cmd;
+use crate::repo::detect::stats::DetectStats;
+use crate::table::Table;
+use crate::{info, utils};
+
+/// The tools for developing roxide, please don't use directly.
+#[derive(Args)]
+pub struct DebugArgs {
+    #[clap(subcommand)]
+    pub command: DebugCommand,
+}
+
+#[derive(Subcommand)]
+pub enum DebugCommand {
+    BenchScan(BenchScanArgs),
+}
+
+impl Run for DebugArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        match &self.command {
+            DebugCommand::BenchScan(args) => args.run(cfg),
+        }
+    }
+}
+
+/// Measure the scan (disk usage and code stats, used by `stats`) against a
+/// synthetic tree, both scanning the repos directly in one thread and
+/// dispatching them to the batch workers. Comparing them shows the overhead of
+/// the dispatcher; the `benches/scan.rs` runs this under criterion.
+#[derive(Args)]
+pub struct BenchScanArgs {
+    /// The synthetic tree dir, it is generated if not exists. Default is
+    /// `{metadir}/bench_scan`.
+    pub dir: Option<String>,
+
+    /// Regenerate the tree even if it exists.
+    #[clap(short, long)]
+    pub generate: bool,
+
+    /// The number of repos in the tree, each repo is a batch task.
+    #[clap(long, default_value = "16")]
+    pub repos: usize,
+
+    /// The depth of the dirs in each repo.
+    #[clap(long, default_value = "2")]
+    pub depth: usize,
+
+    /// The number of sub dirs in each dir.
+    #[clap(long, default_value = "4")]
+    pub width: usize,
+
+    /// The number of files in each dir.
+    #[clap(long, default_value = "8")]
+    pub files: usize,
+
+    /// The size of each file, such as "512", "4K".
+    #[clap(long, default_value = "4K")]
+    pub file_size: String,
+
+    /// What to scan.
+    #[clap(short, long, default_value = "all")]
+    pub mode: ScanMode,
+
+    /// How to dispatch the repos.
+    #[clap(short, long, default_value = "all")]
+    pub dispatch: ScanDispatch,
+
+    /// The number of times to scan, `0` means only generating the tree.
+    #[clap(short = 'n', long, default_value = "3")]
+    pub iterations: usize,
+
+    /// The number of batch workers, default is `scan.workers` in config.
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ScanMode {
+    Usage,
+    Stats,
+    All,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ScanDispatch {
+    Direct,
+    Batch,
+    All,
+}
+
+impl Run for BenchScanArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let dir = match self.dir.as_ref() {
+            Some(dir) => PathBuf::from(dir),
+            None => cfg.get_meta_dir().join("bench_scan"),
+        };
+        if self.generate || !dir.exists() {
+            let spec = TreeSpec {
+                repos: self.repos,
+                depth: self.depth,
+                width: self.width,
+                files: self.files,
+                file_size: utils::parse_bytes(&self.file_size)?,
+            };
+            spec.generate(&dir)?;
+            info!("Generate synthetic tree {}", dir.display());
+        }
+        if self.iterations == 0 {
+            return Ok(());
+        }
+
+        let repos = TreeSpec::list_repos(&dir)?;
+        let workers = batch::get_worker_len(cfg.scan.workers(self.jobs), repos.len());
+        let detect_stats = Arc::new(DetectStats::new(cfg));
+
+        let mut cases = Vec::new();
+        for mode in [ScanMode::Usage, ScanMode::Stats] {
+            if self.mode != ScanMode::All && self.mode != mode {
+                continue;
+            }
+            for dispatch in [ScanDispatch::Direct, ScanDispatch::Batch] {
+                if self.dispatch != ScanDispatch::All && self.dispatch != dispatch {
+                    continue;
+                }
+                cases.push((mode, dispatch));
+            }
+        }
+
+        let mut table = Table::with_capacity(cases.len() + 1);
+        table.add(vec![
+            String::from("Mode"),
+            String::from("Dispatch"),
+            String::from("Min"),
+            String::from("Mean"),
+        ]);
+        cfg.scan.prepare()?;
+        for (mode, dispatch) in cases {
+            let mut elapsed = Vec::with_capacity(self.iterations);
+            for _ in 0..self.iterations {
+                let tasks: Vec<_> = repos
+                    .iter()
+                    .map(|path| {
+                        let task = ScanTask {
+                            mode,
+                            detect_stats: Arc::clone(&detect_stats),
+                            path: path.clone(),
+                        };
+                        (format!("{}", path.display()), task)
+                    })
+                    .collect();
+                let start = Instant::now();
+                match dispatch {
+                    ScanDispatch::Batch => {
+                        batch::must_run_with("Scan", tasks, workers)?;
+                    }
+                    _ => {
+                        for (_, task) in tasks {
+                            task.run()?;
+                        }
+                    }
+                }
+                elapsed.push(start.elapsed());
+            }
+
+            let min = elapsed.iter().min().copied().unwrap_or_default();
+            let mean = elapsed.iter().sum::<Duration>() / elapsed.len() as u32;
+            let dispatch = match dispatch {
+                ScanDispatch::Batch => format!("batch({workers})"),
+                _ => String::from("direct"),
+            };
+            let mode = match mode {
+                ScanMode::Usage => "usage",
+                _ => "stats",
+            };
+            table.add(vec![
+                String::from(mode),
+                dispatch,
+                format!("{min:.2?}"),
+                format!("{mean:.2?}"),
+            ]);
+        }
+
+        eprintln!();
+        table.show();
+        Ok(())
+    }
+}
+
+struct ScanTask {
+    mode: ScanMode,
+    detect_stats: Arc<DetectStats>,
+    path: PathBuf,
+}
+
+impl Task<()> for ScanTask {
+    fn run(&self) -> Result<()> {
+        match self.mode {
+            ScanMode::Usage => {
+                utils::dir_usage(self.path.clone(), &[])?;
+            }
+            _ => {
+                self.detect_stats.count(&self.path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The shape of the synthetic tree, the tree has `repos` git repos, each is a
+/// dir tree `depth` levels deep with `width` sub dirs and `files` rust files (of
+/// `file_size` bytes) in each dir.
+struct TreeSpec {
+    repos: usize,
+    depth: usize,
+    width: usize,
+    files: usize,
+    file_size: u64,
+}
+
+impl TreeSpec {
+    /// The mark file, to prevent removing a real dir when regenerating.
+    const MARK: &'static str = ".roxide_bench_scan";
+
+    fn generate(&self, dir: &Path) -> Result<()> {
+        if dir.exists() {
+            if !dir.join(Self::MARK).exists() {
+                bail!(
+                    "'{}' is not a synthetic tree, refuse to overwrite it",
+                    dir.display()
+                );
+            }
+            fs::remove_dir_all(dir).with_context(|| format!("remove '{}'", dir.display()))?;
+        }
+        fs::create_dir_all(dir).with_context(|| format!("create dir '{}'", dir.display()))?;
+        utils::write_file(&dir.join(Self::MARK), b"")?;
+
+        let content = Self::file_content(self.file_size);
+        for idx in 0..self.repos {
+            let repo = dir.join(format!("repo-{idx}"));
+            self.generate_dir(&repo, self.depth, &content)?;
+            // The code stats only counts the files tracked by git.
+            let repo = format!("{}", repo.display());
+            Cmd::git(&["-C", &repo, "init", "-q"]).execute()?;
+            Cmd::git(&["-C", &repo, "add", "-A"]).execute()?;
+        }
+        Ok(())
+    }
+
+    fn generate_dir(&self, dir: &Path, depth: usize, content: &[u8]) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("create dir '{}'", dir.display()))?;
+        for idx in 0..self.files {
+            let path = dir.join(format!("file_{idx}.rs"));
+            fs::write(&path, content).with_context(|| format!("write '{}'", path.display()))?;
+        }
+        if depth == 0 {
+            return Ok(());
+        }
+        for idx in 0..self.width {
+            self.generate_dir(&dir.join(format!("dir_{idx}")), depth - 1, content)?;
+        }
+        Ok(())
+    }
+
+    /// The code, comment and blank lines in turn, so that the code stats has
+    /// every kind of line to count.
+    fn file_content(size: u64) -> Vec<u8> {
+        let mut content = String::with_capacity(size as usize);
+        let mut idx = 0;
+        while (content.len() as u64) < size {
+            let line = match idx % 3 {
+                0 => format!("fn func_{idx}() {{}}\n"),
+                1 => format!("// comment {idx}\n"),
+                _ => String::from("\n"),
+            };
+            content.push_str(&line);
+            idx += 1;
+        }
+        content.truncate(size as usize);
+        content.into_bytes()
+    }
+
+    fn list_repos(dir: &Path) -> Result<Vec<PathBuf>> {
+        let read_dir =
+            fs::read_dir(dir).with_context(|| format!("read tree dir '{}'", dir.display()))?;
+        let mut repos = Vec::new();
+        for entry in read_dir {
+            let entry = entry.with_context(|| format!("read entry from '{}'", dir.display()))?;
+            let path = entry.path();
+            if path.is_dir() {
+                repos.push(path);
+            }
+        }
+        if repos.is_empty() {
+            bail!("no repo in tree '{}'", dir.display());
+        }
+        repos.sort();
+        Ok(repos)
+    }
+}
+
+#[cfg(test)]
+mod debug_tests {
+    use crate::cmd::debug::*;
+
+    #[test]
+    fn test_generate_tree() {
+        let dir = PathBuf::from("/tmp/test-roxide/bench_scan");
+        let _ = fs::remove_dir_all(&dir);
+        let spec = TreeSpec {
+            repos: 2,
+            depth: 1,
+            width: 2,
+            files: 3,
+            file_size: 100,
+        };
+        spec.generate(&dir).unwrap();
+        spec.generate(&dir).unwrap();
+
+        let repos = TreeSpec::list_repos(&dir).unwrap();
+        assert_eq!(repos.len(), 2);
+        assert!(repos[0].join(".git").is_dir());
+        let usage = utils::dir_usage(repos[0].clone(), &[".git".parse().unwrap()]).unwrap();
+        // 3 dirs (the repo and 2 sub dirs), with 3 files each.
+        assert_eq!(usage.files, 9);
+        assert_eq!(usage.size, 900);
+
+        // Never overwrite a real dir.
+        let real = PathBuf::from("/tmp/test-roxide/bench_scan_real");
+        fs::create_dir_all(&real).unwrap();
+        assert!(spec.generate(&real).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::deps::Dependencies;
+use crate::repo::Repo;
+use crate::{exec, info};
+
+/// Manage dependencies between local repositories.
+#[derive(Args)]
+pub struct DepsArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Let the current repository depend on the selected one.
+    #[clap(short, long)]
+    pub add: bool,
+
+    /// Remove the selected dependency from the current repository. If no
+    /// repository is selected, use fzf to pick one from the dependencies.
+    #[clap(short, long)]
+    pub delete: bool,
+
+    /// Show the indirect dependencies as well.
+    #[clap(short, long)]
+    pub recursive: bool,
+
+    /// Show the dependency graph of all repositories.
+    #[clap(short, long)]
+    pub graph: bool,
+
+    /// Show the dependency graph in Graphviz dot format.
+    #[clap(long)]
+    pub dot: bool,
+}
+
+impl Run for DepsArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load_readonly(cfg)?;
+        // Only lock the dependencies when they are changed.
+        let mut deps = if self.add || self.delete {
+            Dependencies::load(cfg)?
+        } else {
+            Dependencies::load_readonly(cfg)?
+        };
+
+        if self.graph || self.dot {
+            self.show_graph(&deps);
+            return Ok(());
+        }
+
+        let repo = db.must_get_current()?;
+        if self.add {
+            if self.head.is_none() {
+                bail!("please select the repository to depend on");
+            }
+            let dep = self.select(&db)?;
+            deps.add(&repo, &dep)?;
+            info!(
+                "Add dependency {} -> {}",
+                repo.name_with_remote(),
+                dep.name_with_remote()
+            );
+            return deps.save();
+        }
+
+        if self.delete {
+            let dep_name = if self.head.is_some() {
+                self.select(&db)?.name_with_remote()
+            } else {
+                let items = deps.get(&repo.name_with_remote());
+                if items.is_empty() {
+                    bail!("no dependency for current repo");
+                }
+                let idx = exec::fzf_search(&items)?;
+                items[idx].clone()
+            };
+            if !deps.remove(&repo, &dep_name) {
+                bail!("current repo does not depend on '{dep_name}'");
+            }
+            info!(
+                "Remove dependency {} -> {}",
+                repo.name_with_remote(),
+                dep_name
+            );
+            return deps.save();
+        }
+
+        let name = repo.name_with_remote();
+        let items = if self.recursive {
+            deps.closure(&name)
+        } else {
+            deps.get(&name)
+        };
+        for item in items {
+            println!("{item}");
+        }
+
+        Ok(())
+    }
+}
+
+impl DepsArgs {
+    fn select<'a>(&self, db: &'a Database) -> Result<Repo<'a>> {
+        let opts = SelectOptions::default().with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        selector.must_one(db)
+    }
+
+    fn show_graph(&self, deps: &Dependencies) {
+        let edges = deps.edges();
+        if self.dot {
+            println!("digraph deps {{");
+            for (name, deps) in edges {
+                for dep in deps {
+                    println!("  \"{name}\" -> \"{dep}\";");
+                }
+            }
+            println!("}}");
+            return;
+        }
+
+        for (name, deps) in edges {
+            println!("{name}");
+            let last = deps.len().saturating_sub(1);
+            for (idx, dep) in deps.into_iter().enumerate() {
+                let prefix = if idx == last {
+                    "└──"
+                } else {
+                    "├──"
+                };
+                println!("{prefix} {dep}");
+            }
+        }
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: None,
+        }
+    }
+}
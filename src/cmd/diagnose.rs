@@ -64,7 +64,10 @@ impl Run for DiagnoseArgs {
             .with_many_edit(self.edit);
         let selector = Selector::from_args(&self.head, &self.query, opts);
 
-        let (repos, level) = selector.many_local(&db)?;
+        let (mut repos, level) = selector.many_local(&db)?;
+        // The root of the bare repo has no work tree, its branches could not
+        // be diagnosed.
+        repos.retain(|repo| !repo.is_bare());
         if repos.is_empty() {
             eprintln!("No repo to diagnose");
             return Ok(());
@@ -75,7 +78,7 @@ impl Run for DiagnoseArgs {
 
         let tasks = self.build_tasks(cfg, repos, &level)?;
 
-        let results = batch::must_run("Diagnose", tasks)?
+        let results = batch::must_run_with("Diagnose", tasks, cfg.git.workers(None))?
             .into_iter()
             .flatten()
             .collect::<Vec<_>>();
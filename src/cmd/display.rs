@@ -23,7 +23,7 @@ impl Run for DisplayArgs {
             .map(PathBuf::from)
             .unwrap_or(cfg.get_current_dir().clone());
 
-        let db = Database::load(cfg)?;
+        let db = Database::load_readonly(cfg)?;
         let repos = db.list_all(&None);
 
         let repo = repos.into_iter().find(|repo| {
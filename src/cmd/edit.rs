@@ -0,0 +1,124 @@
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::{Config, EditorConfig};
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::Repo;
+use crate::utils;
+
+/// Open a repository in editor (or IDE).
+#[derive(Args)]
+pub struct EditArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// The editor name to use, default will use the config `editor`.
+    #[clap(short, long)]
+    pub editor: Option<String>,
+
+    /// Use search instead of fuzzy matching.
+    #[clap(short, long)]
+    pub search: bool,
+}
+
+impl Run for EditArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let mut db = Database::load(cfg)?;
+
+        let opts = SelectOptions::default()
+            .with_force_search(self.search)
+            .with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let mut repo = selector.must_one(&db)?;
+
+        let (name, editor) = self.get_editor(cfg, &repo)?;
+        let path = repo.get_path(cfg);
+        if !path.is_dir() {
+            bail!(
+                "repo '{}' does not exist in local, please use `home` to create it first",
+                repo.name_with_remote()
+            );
+        }
+        let path = format!("{}", path.display());
+        let quoted = utils::shell_quote(&path);
+        let script = if editor.cmd.contains("{path}") {
+            editor.cmd.replace("{path}", &quoted)
+        } else {
+            format!("{} {quoted}", editor.cmd)
+        };
+
+        // Save the database before launching the editor, the editor may block
+        // for a long time and the database lock should not be held across it.
+        repo.accessed += 1;
+        repo.last_accessed = cfg.now();
+        db.upsert(repo.update());
+        db.save()?;
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", script.as_str()]);
+        cmd.current_dir(&path);
+        cmd.stdout(Stdio::inherit());
+        cmd.stderr(Stdio::inherit());
+        cmd.stdin(Stdio::inherit());
+        let status = cmd
+            .status()
+            .with_context(|| format!("launch editor '{name}'"))?;
+        if !status.success() {
+            bail!("editor '{name}' exited with {status}");
+        }
+
+        Ok(())
+    }
+}
+
+impl EditArgs {
+    /// Find the editor, priority: `--editor` flag > owner's `repo_editor` > owner's
+    /// `editor` > config `editor` > the only configured editor.
+    fn get_editor<'a>(&self, cfg: &'a Config, repo: &Repo) -> Result<(String, &'a EditorConfig)> {
+        let owner_cfg = repo.remote_cfg.owners.get(repo.owner.as_ref());
+        let name = self
+            .editor
+            .clone()
+            .or_else(|| {
+                owner_cfg.and_then(|owner| owner.repo_editor.get(repo.name.as_ref()).cloned())
+            })
+            .or_else(|| owner_cfg.and_then(|owner| owner.editor.clone()))
+            .or_else(|| cfg.editor.clone());
+
+        let name = match name {
+            Some(name) => name,
+            None if cfg.editors.len() == 1 => cfg.editors.keys().next().unwrap().clone(),
+            None if cfg.editors.is_empty() => bail!("no editor in config, please add one"),
+            None => bail!("multiple editors in config, please specify one with `-e`"),
+        };
+
+        match cfg.editors.get(&name) {
+            Some(editor) => Ok((name, editor)),
+            None => bail!("could not find editor '{name}' in config"),
+        }
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: Some(|cfg, flag, to_complete| match flag {
+                'e' => {
+                    let items: Vec<String> = cfg
+                        .editors
+                        .keys()
+                        .filter(|name| name.starts_with(to_complete))
+                        .cloned()
+                        .collect();
+                    Ok(Some(CompletionResult::from(items)))
+                }
+                _ => Ok(None),
+            }),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::env::EnvFile;
+use crate::{confirm, info};
+
+/// Render the env file (such as `.envrc`) for repository.
+#[derive(Args)]
+pub struct EnvArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Write the env file into the repository rather than printing it.
+    #[clap(short, long)]
+    pub write: bool,
+
+    /// Use the current repository.
+    #[clap(short, long)]
+    pub current: bool,
+}
+
+impl Run for EnvArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let repo = if self.current {
+            db.must_get_current()?
+        } else {
+            let opts = SelectOptions::default().with_force_local(true);
+            let selector = Selector::from_args(&self.head, &self.query, opts);
+            selector.must_one(&db)?
+        };
+
+        let env_file = match EnvFile::build(cfg, &repo) {
+            Some(env_file) => env_file,
+            None => bail!(
+                "no env config for repo '{}', please add it to remote or owner config",
+                repo.name_with_remote()
+            ),
+        };
+
+        if !self.write {
+            print!("{}", env_file.render());
+            return Ok(());
+        }
+
+        let dir = repo.get_path(cfg);
+        if !dir.is_dir() {
+            bail!(
+                "repo '{}' does not exist in local, please use `home` to create it first",
+                repo.name_with_remote()
+            );
+        }
+        if dir.join(&env_file.name).exists() {
+            confirm!("Do you want to overwrite '{}'", env_file.name);
+        }
+        env_file.write(&dir)?;
+        info!("Write env file '{}'", env_file.name);
+        Ok(())
+    }
+}
+
+impl EnvArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: None,
+        }
+    }
+}
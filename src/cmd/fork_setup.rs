@@ -0,0 +1,85 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::api;
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::exec::{Cmd, GitCmd};
+use crate::info;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::Repo;
+
+/// Configure the "upstream" git remote for a forked repository, use
+/// `sync --upstream` to keep the fork current.
+#[derive(Args)]
+pub struct ForkSetupArgs {
+    /// Repository selection head, default is the current repository.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// When calling the remote API, ignore caches that are not expired.
+    #[clap(short, long)]
+    pub force: bool,
+}
+
+impl Run for ForkSetupArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load_readonly(cfg)?;
+        let repo = if self.head.is_some() {
+            let opts = SelectOptions::default().with_force_local(true);
+            Selector::from_args(&self.head, &self.query, opts).must_one(&db)?
+        } else {
+            db.must_get_current()?
+        };
+
+        let provider = api::build_provider(cfg, &repo.remote_cfg, self.force)?;
+        let api_repo = provider.get_repo(&repo.owner, &repo.name)?;
+        let api_upstream = match api_repo.upstream {
+            Some(upstream) => upstream,
+            None => bail!(
+                "repo {} is not forked, so it has not an upstream",
+                repo.name_with_remote()
+            ),
+        };
+        let upstream = Repo::from_api_upstream(cfg, &repo.remote, api_upstream);
+        let url = upstream.clone_url();
+
+        let path = format!("{}", repo.get_path(cfg).display());
+        let git = GitCmd::with_path(&path);
+        let remotes = git.lines(&["remote"])?;
+        if remotes.iter().any(|remote| remote == "upstream") {
+            let current = git.read(&["remote", "get-url", "upstream"])?;
+            if current != url {
+                Cmd::git(&["-C", &path, "remote", "set-url", "upstream", &url])
+                    .with_display(format!("Set upstream url to '{url}'"))
+                    .execute()?;
+            }
+        } else {
+            Cmd::git(&["-C", &path, "remote", "add", "upstream", &url])
+                .with_display(format!("Add upstream remote '{url}'"))
+                .execute()?;
+        }
+
+        Cmd::git(&["-C", &path, "fetch", "upstream", "--prune"])
+            .with_display("Fetch upstream")
+            .execute()?;
+
+        info!(
+            "Setup upstream {} for {}",
+            upstream.name_with_remote(),
+            repo.name_with_remote()
+        );
+        Ok(())
+    }
+}
+
+impl ForkSetupArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: None,
+        }
+    }
+}
@@ -1,16 +1,25 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use anyhow::Result;
-use clap::Args;
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use glob::Pattern as GlobPattern;
 use serde::Serialize;
 
+use crate::api::{self, ApiRepo};
+use crate::batch::{self, Task};
 use crate::cmd::{Completion, Run};
 use crate::config::Config;
 use crate::repo::database::{Database, SelectOptions, Selector};
 use crate::repo::detect::labels::DetectLabels;
+use crate::repo::detect::license;
+use crate::repo::usage;
 use crate::repo::{NameLevel, Repo};
-use crate::table::Table;
-use crate::{term, utils};
+use crate::table::{Table, TableArgs, TableCell, TableCellColor};
+use crate::utils::{self, DirUsage, UsageScan};
+use crate::{error, git, term};
 
 /// Show repository info.
 #[derive(Args)]
@@ -21,8 +30,8 @@ pub struct GetArgs {
     /// Repository selection query.
     pub query: Option<String>,
 
-    /// Show size in list info. If your workspace is large, this can cause
-    /// command to take too long to execute.
+    /// Show size (and the Git LFS objects size) in list info. If your workspace
+    /// is large, this can cause command to take too long to execute.
     #[clap(short, long)]
     pub size: bool,
 
@@ -37,6 +46,71 @@ pub struct GetArgs {
     /// Use the labels to filter repo.
     #[clap(short, long)]
     pub labels: Option<String>,
+
+    /// Only show the repositories with the license (the SPDX id, such as `MIT`,
+    /// case-insensitive), use `none` for the ones without license. The license
+    /// is detected along with the labels, see `detect`.
+    #[clap(long)]
+    pub license: Option<String>,
+
+    /// Only show the repositories outside the workspace, see `attach --path`.
+    #[clap(short = 'x', long)]
+    pub external: bool,
+
+    /// Only show the repositories whose size is at least this, such as `500M`.
+    /// This implies `--size`.
+    #[clap(long)]
+    pub min_size: Option<String>,
+
+    /// Only show the N largest repositories. This implies `--size`.
+    #[clap(long)]
+    pub top: Option<usize>,
+
+    /// Exit with error and list the repositories whose size exceeds this budget,
+    /// such as `2G`. Useful to watch the workspace growth in CI or cron jobs.
+    /// This implies `--size`.
+    #[clap(long)]
+    pub alert: Option<String>,
+
+    /// Ignore the size cache and scan all the directories again. By default,
+    /// only the directories changed since the last scan are scanned.
+    #[clap(long)]
+    pub no_cache: bool,
+
+    /// Skip the directories on other filesystems (such as the bind mounts and
+    /// network mounts) when scanning size, like `du -x`. The skipped mount
+    /// points are reported.
+    #[clap(long)]
+    pub one_file_system: bool,
+
+    /// Also show the total size with the hard links shared by repositories
+    /// (such as the git objects of the local clones) counted only once.
+    #[clap(long)]
+    pub dedup: bool,
+
+    /// Roll up the size per remote or per owner, with the percentage of the
+    /// total. The owners are indented under their remotes. This implies
+    /// `--size`.
+    #[clap(short, long)]
+    pub group: Option<UsageGroup>,
+
+    /// The number of workers to scan size, default is `scan.workers` in config.
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Use the on-disk size (allocated blocks) instead of the apparent size to
+    /// sort and to apply `--min-size`, `--top` and `--alert`. The two differ a
+    /// lot for sparse files and lots of small files.
+    #[clap(short = 'D', long)]
+    pub disk: bool,
+
+    /// Show the remote info (visibility, stars, archived and description) from
+    /// the remote api. The result is cached, see `cache_hours` in remote config.
+    #[clap(short = 'R', long)]
+    pub remote_info: bool,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
 }
 
 #[derive(Debug, Serialize)]
@@ -55,8 +129,87 @@ struct RepoInfo<'a> {
 
     size: u64,
     size_str: String,
+    disk_size: u64,
+    disk_size_str: String,
+    files: u64,
+    inodes: u64,
 
     labels: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<String>,
+
+    /// The mount points skipped by `--one-file-system`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skipped_mounts: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_info: Option<ApiRepo>,
+}
+
+/// The level to roll up the disk usage, see `--group`.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum UsageGroup {
+    Remote,
+    Owner,
+}
+
+#[derive(Debug, Serialize)]
+struct GroupUsage {
+    name: String,
+    repos: usize,
+
+    #[serde(flatten)]
+    usage: DirUsage,
+
+    /// The percentage of the total size, see `--disk`.
+    percent: f64,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    owners: Vec<GroupUsage>,
+}
+
+impl GroupUsage {
+    fn new(name: String) -> GroupUsage {
+        GroupUsage {
+            name,
+            repos: 0,
+            usage: DirUsage::default(),
+            percent: 0.0,
+            owners: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, usage: &DirUsage) {
+        self.usage.add(usage);
+        self.repos += 1;
+    }
+
+    /// Compute the percentages and sort the owners by size.
+    fn finish<F>(&mut self, total: u64, measure: &F)
+    where
+        F: Fn(&DirUsage) -> u64,
+    {
+        if total > 0 {
+            self.percent = measure(&self.usage) as f64 * 100.0 / total as f64;
+        }
+        for owner in self.owners.iter_mut() {
+            owner.finish(total, measure);
+        }
+        self.owners
+            .sort_by_key(|owner| std::cmp::Reverse(measure(&owner.usage)));
+    }
+
+    fn row(&self, indent: &str) -> Vec<String> {
+        vec![
+            format!("{indent}{}", self.name),
+            format!("{}", self.repos),
+            utils::human_bytes(self.usage.size),
+            utils::human_bytes(self.usage.disk_size),
+            format!("{}", self.usage.files),
+            format!("{:.1}%", self.percent),
+        ]
+    }
 }
 
 impl RepoInfo<'_> {
@@ -64,11 +217,24 @@ impl RepoInfo<'_> {
         cfg: &Config,
         repo: Repo<'a>,
         detect_labels: &Option<DetectLabels>,
+        no_cache: bool,
+        one_file_system: bool,
+        remote_info: Option<ApiRepo>,
     ) -> Result<RepoInfo<'a>> {
         let workspace = repo.path.is_none();
         let path = repo.get_path(cfg);
         let path = format!("{}", path.display());
-        let size = utils::dir_size(repo.get_path(cfg))?;
+        let cache = if no_cache {
+            None
+        } else {
+            Some(usage::cache_path(cfg, &repo))
+        };
+        let UsageScan { usage, mounts, .. } = usage::repo_usage(
+            &repo.get_path(cfg),
+            &cfg.size_ignores,
+            cache.as_deref(),
+            one_file_system,
+        )?;
         let labels = match detect_labels {
             Some(detect_labels) => detect_labels.sort(&repo),
             None => {
@@ -82,6 +248,10 @@ impl RepoInfo<'_> {
                 labels
             }
         };
+        let licenses = license::get_licenses(&repo)
+            .into_iter()
+            .map(String::from)
+            .collect();
         let score = repo.score(cfg);
         Ok(RepoInfo {
             remote: repo.remote,
@@ -93,9 +263,19 @@ impl RepoInfo<'_> {
             score,
             path,
             workspace,
-            size,
-            size_str: utils::human_bytes(size),
+            size: usage.size,
+            size_str: utils::human_bytes(usage.size),
+            disk_size: usage.disk_size,
+            disk_size_str: utils::human_bytes(usage.disk_size),
+            files: usage.files,
+            inodes: usage.inodes,
             labels,
+            licenses,
+            skipped_mounts: mounts
+                .iter()
+                .map(|mount| format!("{}", mount.display()))
+                .collect(),
+            remote_info,
         })
     }
 }
@@ -114,6 +294,18 @@ impl Run for GetArgs {
 
             selector.many_local(&db)?
         };
+        if self.external {
+            repos.retain(|repo| repo.is_external(cfg));
+        }
+        if let Some(filter) = self.license.as_ref() {
+            repos.retain(|repo| {
+                let licenses = license::get_licenses(repo);
+                if filter.eq_ignore_ascii_case("none") {
+                    return licenses.is_empty();
+                }
+                licenses.iter().any(|id| id.eq_ignore_ascii_case(filter))
+            });
+        }
 
         let detect_labels = if cfg.detect.auto {
             Some(DetectLabels::new(cfg))
@@ -130,41 +322,116 @@ impl Run for GetArgs {
             return Ok(());
         }
 
+        let min_size = self.min_size.as_ref().map(utils::parse_bytes).transpose()?;
+        let alert = self.alert.as_ref().map(utils::parse_bytes).transpose()?;
+        let size_filter = min_size.is_some() || self.top.is_some() || alert.is_some();
+
+        if let Some(group) = self.group {
+            return self.show_groups(cfg, repos, group, min_size);
+        }
+
         if self.json {
             let mut infos = Vec::with_capacity(repos.len());
             for repo in repos {
-                infos.push(RepoInfo::from_repo(cfg, repo, &detect_labels)?);
+                let remote_info = if self.remote_info {
+                    Self::get_remote_info(cfg, &repo)?
+                } else {
+                    None
+                };
+                infos.push(RepoInfo::from_repo(
+                    cfg,
+                    repo,
+                    &detect_labels,
+                    self.no_cache,
+                    self.one_file_system,
+                    remote_info,
+                )?);
             }
-            return term::show_json(infos);
+            let measure = |info: &RepoInfo| {
+                if self.disk {
+                    info.disk_size
+                } else {
+                    info.size
+                }
+            };
+            self.filter_sizes(&mut infos, min_size, measure);
+            term::show_json(&infos)?;
+            if let Some(alert) = alert {
+                let exceeded: Vec<_> = infos
+                    .iter()
+                    .filter(|info| measure(info) > alert)
+                    .map(|info| {
+                        (
+                            format!("{}:{}/{}", info.remote, info.owner, info.name),
+                            measure(info),
+                        )
+                    })
+                    .collect();
+                return Self::check_alert(alert, exceeded);
+            }
+            return Ok(());
+        }
+
+        let mut table_args = self.table.clone();
+        let mut default_columns = vec!["name", "labels", "access", "time", "score"];
+        if self.size || self.disk || self.dedup || size_filter {
+            default_columns.extend(["size", "disk", "files", "lfs"]);
+            if table_args.sort.is_none() {
+                let sort = if self.disk { "disk:desc" } else { "size:desc" };
+                table_args.sort = Some(String::from(sort));
+            }
+        }
+        let show_size = size_filter
+            || ["size", "disk", "files", "inodes"]
+                .iter()
+                .any(|column| table_args.need_column(&default_columns, column));
+        let show_lfs = table_args.need_column(&default_columns, "lfs");
+        if self.remote_info {
+            default_columns.extend(["visibility", "stars", "archived", "description"]);
         }
+        if self.license.is_some() {
+            default_columns.push("license");
+        }
+        let show_remote = ["visibility", "stars", "archived", "description"]
+            .iter()
+            .any(|column| table_args.need_column(&default_columns, column));
 
-        let mut table = Table::with_capacity(1 + repos.len());
-        let mut titles = vec![
+        let mut table = Table::with_capacity(2 + repos.len());
+        table.add(vec![
             String::from("Name"),
             String::from("Labels"),
             String::from("Access"),
             String::from("Time"),
             String::from("Score"),
-        ];
+            String::from("Path"),
+            String::from("Size"),
+            String::from("Disk"),
+            String::from("Files"),
+            String::from("Inodes"),
+            String::from("LFS"),
+            String::from("Visibility"),
+            String::from("Stars"),
+            String::from("Archived"),
+            String::from("Description"),
+            String::from("License"),
+        ]);
 
-        let mut size_vec: Option<Vec<u64>> = None;
-        if self.size {
-            titles.push(String::from("Size"));
-            let mut repos_with_size = Vec::with_capacity(repos.len());
-            for repo in repos {
-                let path = repo.get_path(cfg);
-                let size = utils::dir_size(path)?;
-                repos_with_size.push((size, repo));
-            }
-            repos_with_size.sort_unstable_by(|(size1, _), (size2, _)| size2.cmp(size1));
-            size_vec = Some(repos_with_size.iter().map(|(size, _)| *size).collect());
-            repos = repos_with_size.into_iter().map(|(_, repo)| repo).collect();
-        }
-        table.add(titles);
+        let scans = if show_size {
+            self.scan_usages(cfg, &repos)?
+        } else {
+            vec![UsageScan::default(); repos.len()]
+        };
+        let mut sized_repos: Vec<_> = repos.into_iter().zip(scans).collect();
+        self.filter_sizes(&mut sized_repos, min_size, |(_, scan)| {
+            self.measure(&scan.usage)
+        });
 
         let mut total_access: u64 = 0;
         let mut total_score: u64 = 0;
-        for (idx, repo) in repos.iter().enumerate() {
+        let mut total_usage = DirUsage::default();
+        let mut total_lfs: u64 = 0;
+        for (repo, scan) in sized_repos.iter() {
+            let usage = &scan.usage;
             let name = repo.to_string(&level);
             let labels = match detect_labels.as_ref() {
                 Some(detect_labels) => detect_labels.format(repo),
@@ -176,38 +443,322 @@ impl Run for GetArgs {
             let last_access = utils::format_since(cfg, repo.last_accessed);
             let score = repo.score(cfg);
             total_score += score;
-            let score = format!("{score}");
 
-            let mut row = vec![name, labels, access, last_access, score];
-            if let Some(size_vec) = size_vec.as_ref() {
-                let size = utils::human_bytes(size_vec[idx]);
-                row.push(size);
+            let path = repo.get_path(cfg);
+            let mut usage_cells = Vec::with_capacity(4);
+            if show_size {
+                total_usage.size += usage.size;
+                total_usage.disk_size += usage.disk_size;
+                total_usage.files += usage.files;
+                total_usage.inodes += usage.inodes;
+                let over_budget = matches!(alert, Some(alert) if self.measure(usage) > alert);
+                for (value, human) in [
+                    (usage.size, true),
+                    (usage.disk_size, true),
+                    (usage.files, false),
+                    (usage.inodes, false),
+                ] {
+                    let text = if human {
+                        utils::human_bytes(value)
+                    } else {
+                        format!("{value}")
+                    };
+                    let cell = if over_budget && human {
+                        TableCell::with_color(text, TableCellColor::Red)
+                    } else {
+                        TableCell::no_color(text)
+                    };
+                    usage_cells.push(cell.with_sort_key(value));
+                }
+            } else {
+                usage_cells.resize_with(4, || TableCell::no_color(String::new()));
             }
+            // A broken checkout should not fail the whole listing, show "-"
+            // for it.
+            let lfs = if !show_lfs {
+                TableCell::no_color(String::new())
+            } else {
+                let lfs_size = git::uses_lfs(&path).and_then(|uses| {
+                    if uses {
+                        git::lfs_size(&path).map(Some)
+                    } else {
+                        Ok(None)
+                    }
+                });
+                match lfs_size {
+                    Ok(Some(lfs_size)) => {
+                        total_lfs += lfs_size;
+                        TableCell::no_color(utils::human_bytes(lfs_size)).with_sort_key(lfs_size)
+                    }
+                    Ok(None) => TableCell::no_color(String::new()),
+                    Err(_) => TableCell::no_color(String::from("-")),
+                }
+            };
 
-            table.add(row);
+            let mut row = vec![
+                TableCell::no_color(name),
+                TableCell::no_color(labels),
+                TableCell::no_color(access),
+                TableCell::no_color(last_access).with_sort_key(repo.last_accessed),
+                TableCell::no_color(format!("{score}")),
+                TableCell::no_color(format!("{}", path.display())),
+            ];
+            row.extend(usage_cells);
+            row.push(lfs);
+            let remote_info = if show_remote {
+                Self::get_remote_info(cfg, repo)?
+            } else {
+                None
+            };
+            match remote_info {
+                Some(api_repo) => {
+                    let archived = if api_repo.archived {
+                        TableCell::with_color(String::from("yes"), TableCellColor::Yellow)
+                    } else {
+                        TableCell::no_color(String::new())
+                    };
+                    row.extend([
+                        TableCell::no_color(api_repo.visibility),
+                        TableCell::no_color(format!("{}", api_repo.stars))
+                            .with_sort_key(api_repo.stars),
+                        archived,
+                        TableCell::no_color(api_repo.description.unwrap_or_default()),
+                    ]);
+                }
+                None => row.resize_with(row.len() + 4, || TableCell::no_color(String::new())),
+            }
+            row.push(TableCell::no_color(license::get_licenses(repo).join(",")));
+            table.add_color(row);
         }
 
+        let total_usage = if show_size {
+            vec![
+                utils::human_bytes(total_usage.size),
+                utils::human_bytes(total_usage.disk_size),
+                format!("{}", total_usage.files),
+                format!("{}", total_usage.inodes),
+            ]
+        } else {
+            vec![String::new(); 4]
+        };
+
         table.foot();
         let mut foot = vec![
-            format!("SUM: {}", repos.len()),
+            format!("SUM: {}", sized_repos.len()),
             String::from(""),
             format!("{total_access}"),
             String::from(""),
             format!("{total_score}"),
+            String::from(""),
         ];
-        if self.size {
-            let total_size: u64 = size_vec.as_ref().unwrap().iter().sum();
-            let total_size = utils::human_bytes(total_size);
-            foot.push(total_size);
-        }
+        foot.extend(total_usage);
+        foot.push(if show_lfs {
+            utils::human_bytes(total_lfs)
+        } else {
+            String::new()
+        });
+        foot.extend(vec![String::new(); 5]);
         table.add(foot);
+        if self.dedup && show_size {
+            let dedup = UsageScan::dedup_total(sized_repos.iter().map(|(_, scan)| scan));
+            let mut row = vec![String::from("DEDUP")];
+            row.extend(vec![String::new(); 5]);
+            row.extend([
+                utils::human_bytes(dedup.size),
+                utils::human_bytes(dedup.disk_size),
+                format!("{}", dedup.files),
+                format!("{}", dedup.inodes),
+            ]);
+            row.extend(vec![String::new(); 6]);
+            table.add(row);
+        }
 
-        table.show();
+        table.show_with(&table_args, &default_columns)?;
+
+        if let Some(alert) = alert {
+            let exceeded: Vec<_> = sized_repos
+                .iter()
+                .filter(|(_, scan)| self.measure(&scan.usage) > alert)
+                .map(|(repo, scan)| (repo.to_string(&level), self.measure(&scan.usage)))
+                .collect();
+            return Self::check_alert(alert, exceeded);
+        }
         Ok(())
     }
 }
 
 impl GetArgs {
+    /// Get the repo info from the remote api, [`None`] if the remote has no
+    /// provider. The provider is built for each repo since only one of them can
+    /// hold the cache lock at a time.
+    fn get_remote_info(cfg: &Config, repo: &Repo) -> Result<Option<ApiRepo>> {
+        if repo.remote_cfg.provider.is_none() {
+            return Ok(None);
+        }
+        let provider = api::build_provider(cfg, &repo.remote_cfg, false)?;
+        let api_repo = provider
+            .get_repo(&repo.owner, &repo.name)
+            .with_context(|| format!("get remote info for {}", repo.name_with_remote()))?;
+        Ok(Some(api_repo))
+    }
+
+    /// Scan the disk usage of the repos concurrently, the number of workers is
+    /// `--jobs` or `scan.workers` in config.
+    fn scan_usages(&self, cfg: &Config, repos: &[Repo]) -> Result<Vec<UsageScan>> {
+        let size_ignores = Arc::new(cfg.size_ignores.clone());
+        let mut tasks = Vec::with_capacity(repos.len());
+        for repo in repos.iter() {
+            let task = UsageTask {
+                size_ignores: Arc::clone(&size_ignores),
+                path: repo.get_path(cfg),
+                cache: if self.no_cache {
+                    None
+                } else {
+                    Some(usage::cache_path(cfg, repo))
+                },
+                one_file_system: self.one_file_system,
+            };
+            tasks.push((repo.name_with_remote(), task));
+        }
+        cfg.scan.prepare()?;
+        let usages = batch::must_run_with("Scan", tasks, cfg.scan.workers(self.jobs))?;
+
+        let mut mounts: Vec<_> = usages
+            .iter()
+            .flat_map(|usage| usage.mounts.iter())
+            .collect();
+        if !mounts.is_empty() {
+            mounts.sort_unstable();
+            eprintln!();
+            eprintln!(
+                "Skipped {} on other filesystems:",
+                utils::plural(&mounts, "mount point")
+            );
+            for mount in mounts {
+                eprintln!("  * {}", mount.display());
+            }
+        }
+        Ok(usages)
+    }
+
+    /// Show the disk usage rolled up per remote (or per owner), the groups are
+    /// sorted by size.
+    fn show_groups(
+        &self,
+        cfg: &Config,
+        repos: Vec<Repo>,
+        group: UsageGroup,
+        min_size: Option<u64>,
+    ) -> Result<()> {
+        let scans = self.scan_usages(cfg, &repos)?;
+        let mut sized_repos: Vec<_> = repos.into_iter().zip(scans).collect();
+        self.filter_sizes(&mut sized_repos, min_size, |(_, scan)| {
+            self.measure(&scan.usage)
+        });
+
+        let mut remotes: BTreeMap<String, GroupUsage> = BTreeMap::new();
+        let mut total = DirUsage::default();
+        for (repo, scan) in sized_repos.iter() {
+            total.add(&scan.usage);
+            let remote = remotes
+                .entry(repo.remote.to_string())
+                .or_insert_with(|| GroupUsage::new(repo.remote.to_string()));
+            remote.add(&scan.usage);
+            if group == UsageGroup::Owner {
+                match remote
+                    .owners
+                    .iter_mut()
+                    .find(|owner| owner.name == repo.owner)
+                {
+                    Some(owner) => owner.add(&scan.usage),
+                    None => {
+                        let mut owner = GroupUsage::new(repo.owner.to_string());
+                        owner.add(&scan.usage);
+                        remote.owners.push(owner);
+                    }
+                }
+            }
+        }
+
+        let total_measure = self.measure(&total);
+        let mut groups: Vec<_> = remotes.into_values().collect();
+        for group in groups.iter_mut() {
+            group.finish(total_measure, &|usage| self.measure(usage));
+        }
+        groups.sort_by_key(|group| std::cmp::Reverse(self.measure(&group.usage)));
+
+        if self.json {
+            return term::show_json(groups);
+        }
+
+        let mut table = Table::with_capacity(2 + groups.len());
+        table.add(vec![
+            String::from("Name"),
+            String::from("Repos"),
+            String::from("Size"),
+            String::from("Disk"),
+            String::from("Files"),
+            String::from("Percent"),
+        ]);
+        for group in groups.iter() {
+            table.add(group.row(""));
+            for owner in group.owners.iter() {
+                table.add(owner.row("  "));
+            }
+        }
+        table.foot();
+        let mut sum = GroupUsage::new(format!("SUM: {}", sized_repos.len()));
+        sum.add(&total);
+        sum.repos = sized_repos.len();
+        sum.finish(total_measure, &|usage| self.measure(usage));
+        table.add(sum.row(""));
+
+        table.show_as(self.table.output);
+        Ok(())
+    }
+
+    /// The size used to sort and filter, see `--disk`.
+    fn measure(&self, usage: &DirUsage) -> u64 {
+        if self.disk {
+            usage.disk_size
+        } else {
+            usage.size
+        }
+    }
+
+    /// Apply `--min-size` and `--top` to the items.
+    fn filter_sizes<T, F>(&self, items: &mut Vec<T>, min_size: Option<u64>, size: F)
+    where
+        F: Fn(&T) -> u64,
+    {
+        if let Some(min_size) = min_size {
+            items.retain(|item| size(item) >= min_size);
+        }
+        if let Some(top) = self.top {
+            items.sort_by_key(|item| std::cmp::Reverse(size(item)));
+            items.truncate(top);
+        }
+    }
+
+    fn check_alert(alert: u64, exceeded: Vec<(String, u64)>) -> Result<()> {
+        if exceeded.is_empty() {
+            return Ok(());
+        }
+        eprintln!();
+        for (name, size) in exceeded.iter() {
+            error!(
+                "{}: {} exceeds the size budget {}",
+                name,
+                utils::human_bytes(*size),
+                utils::human_bytes(alert)
+            );
+        }
+        bail!(
+            "{} exceeded the size budget",
+            utils::plural(&exceeded, "repo")
+        )
+    }
+
     pub fn completion() -> Completion {
         Completion {
             args: Completion::repo_args,
@@ -215,3 +766,22 @@ impl GetArgs {
         }
     }
 }
+
+struct UsageTask {
+    size_ignores: Arc<Vec<GlobPattern>>,
+
+    path: PathBuf,
+    cache: Option<PathBuf>,
+    one_file_system: bool,
+}
+
+impl Task<UsageScan> for UsageTask {
+    fn run(&self) -> Result<UsageScan> {
+        usage::repo_usage(
+            &self.path,
+            &self.size_ignores,
+            self.cache.as_deref(),
+            self.one_file_system,
+        )
+    }
+}
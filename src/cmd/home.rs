@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fs;
 use std::io;
 use std::path::Path;
@@ -9,13 +10,15 @@ use crate::batch::Task;
 use crate::cmd::{Completion, CompletionResult, Run};
 use crate::config::Config;
 use crate::error;
-use crate::exec::Cmd;
-use crate::info;
+use crate::exec::{self, Cmd};
+use crate::git::{self, GitWorktree};
 use crate::repo::database::{Database, SelectOptions, Selector};
 use crate::repo::detect::labels::DetectLabels;
+use crate::repo::env::EnvFile;
 use crate::repo::Repo;
-use crate::workflow::Workflow;
+use crate::workflow::{Workflow, WorkflowHistory};
 use crate::{api, confirm, utils};
+use crate::{info, warn};
 
 /// Enter a repository.
 #[derive(Args)]
@@ -38,10 +41,18 @@ pub struct HomeArgs {
     #[clap(short, long)]
     pub open: bool,
 
-    /// If the repo does not exist and needs to be cloned, use `depth=1`.
+    /// If the repo does not exist and needs to be cloned, use `depth=1`. The repo
+    /// will be marked with label "thin", use `unthin` command to fetch the full
+    /// history.
     #[clap(short, long)]
     pub thin: bool,
 
+    /// If the repo does not exist and needs to be cloned, clone it as a bare
+    /// repository and checkout the default branch in a worktree. The repo will
+    /// be marked with label "bare".
+    #[clap(short = 'B', long)]
+    pub bare: bool,
+
     /// Use a scaffolding to create the repo.
     #[clap(short, long)]
     pub bootstrap: Option<String>,
@@ -85,6 +96,16 @@ impl Run for HomeArgs {
                     }
                     return result;
                 }
+                if self.bootstrap.is_none() && repo.remote_cfg.clone.is_some() {
+                    let mut labels = HashSet::new();
+                    if self.bare {
+                        labels.insert(String::from("bare"));
+                    }
+                    if self.thin {
+                        labels.insert(String::from("thin"));
+                    }
+                    repo.append_labels(Some(labels));
+                }
             }
             Err(err) => {
                 return Err(err).with_context(|| format!("read repo directory {}", path.display()));
@@ -98,7 +119,11 @@ impl Run for HomeArgs {
                 .context("auto detect labels for repo")?;
         }
 
-        println!("{}", path.display());
+        if repo.is_bare() {
+            println!("{}", Self::select_worktree(&path)?);
+        } else {
+            println!("{}", path.display());
+        }
 
         repo.append_labels(append_labels);
         repo.accessed += 1;
@@ -118,12 +143,19 @@ impl HomeArgs {
             self.create_local(path)
         }?;
 
+        if let Some(env_file) = EnvFile::build(cfg, repo) {
+            info!("Write env file '{}'", env_file.name);
+            env_file.write(path)?;
+        }
+
         if let Some(owner) = repo.remote_cfg.owners.get(repo.owner.as_ref()) {
             if let Some(on_create) = &owner.on_create {
-                for wf_name in on_create.iter() {
-                    let wf = Workflow::load(wf_name, cfg, repo)?;
-                    wf.run()?;
-                }
+                let history = WorkflowHistory::new(cfg);
+                let result = on_create.iter().try_for_each(|wf_name| {
+                    let wf = Workflow::load(wf_name, cfg, repo)?.with_history(history.recorder());
+                    wf.run()
+                });
+                history.finish(cfg, result)?;
             }
         }
 
@@ -141,6 +173,10 @@ impl HomeArgs {
     }
 
     fn clone(&self, repo: &Repo, path: &Path) -> Result<()> {
+        if self.bare {
+            return self.clone_bare(repo, path);
+        }
+
         let url = repo.clone_url();
         let path = format!("{}", path.display());
         let mut args = vec!["clone"];
@@ -150,12 +186,59 @@ impl HomeArgs {
         args.extend(&[url.as_str(), path.as_str()]);
         Cmd::git(&args)
             .with_display(format!("Clone {}", repo.name_with_remote()))
-            .execute()?;
+            .execute_git_progress()?;
 
         self.init_repo_user(repo, path.as_ref())?;
+        if let Err(err) = git::setup_lfs(path.as_ref()) {
+            warn!("Setup Git LFS failed: {:#}", err);
+        }
         Ok(())
     }
 
+    /// Clone the repo as bare into `{path}/.bare`, and checkout the default branch
+    /// in worktree `{path}/{branch}`. The `{path}/.git` file points to the bare
+    /// gitdir, so that the repository-level git commands (such as `fetch`) can
+    /// be run in `path` directly. But `path` has no work tree, the commands
+    /// like `status` and `checkout` should be run in the worktrees.
+    fn clone_bare(&self, repo: &Repo, path: &Path) -> Result<()> {
+        let url = repo.clone_url();
+        let bare_path = format!("{}", path.join(".bare").display());
+        let mut args = vec!["clone", "--bare"];
+        if self.thin {
+            args.extend(&["--depth", "1"]);
+        }
+        args.extend(&[url.as_str(), bare_path.as_str()]);
+        Cmd::git(&args)
+            .with_display(format!("Clone bare {}", repo.name_with_remote()))
+            .execute_git_progress()?;
+
+        let branch = git::setup_bare(path)?;
+        info!("Add worktree for branch {}", branch);
+        self.init_repo_user(repo, path)
+    }
+
+    /// Select a worktree of the bare repo, use fzf if there are multiple.
+    fn select_worktree(path: &Path) -> Result<String> {
+        let path = format!("{}", path.display());
+        let mut worktrees = GitWorktree::list(&path)?;
+        if worktrees.is_empty() {
+            return Ok(path);
+        }
+        if worktrees.len() == 1 {
+            return Ok(worktrees.remove(0).path);
+        }
+
+        let items: Vec<String> = worktrees
+            .iter()
+            .map(|worktree| match worktree.branch.as_ref() {
+                Some(branch) => format!("{} ({branch})", worktree.path),
+                None => format!("{} (detached)", worktree.path),
+            })
+            .collect();
+        let idx = exec::fzf_search(&items)?;
+        Ok(worktrees.remove(idx).path)
+    }
+
     fn clone_from_scaffolding(
         &self,
         name: &str,
@@ -210,14 +293,9 @@ impl HomeArgs {
 
     fn init_repo_user(&self, repo: &Repo, path: &Path) -> Result<()> {
         let path = format!("{}", path.display());
-        if let Some(user) = &repo.remote_cfg.user {
-            Cmd::git(&["-C", path.as_str(), "config", "user.name", user.as_str()])
-                .with_display(format!("Set user to {}", user))
-                .execute()?;
-        }
-        if let Some(email) = &repo.remote_cfg.email {
-            Cmd::git(&["-C", path.as_str(), "config", "user.email", email.as_str()])
-                .with_display(format!("Set email to {}", email))
+        for (key, value) in repo.remote_cfg.git_configs(repo.owner.as_ref()) {
+            Cmd::git(&["-C", path.as_str(), "config", key, value.as_str()])
+                .with_display(format!("Set {key} to {value}"))
                 .execute()?;
         }
         Ok(())
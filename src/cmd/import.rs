@@ -2,19 +2,21 @@ use std::borrow::Cow;
 use std::sync::Arc;
 use std::{fs, io};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::Args;
 
 use crate::batch::{self, Task};
 use crate::cmd::{Completion, Run};
 use crate::config::{Config, RemoteConfig};
 use crate::exec::{Cmd, GitCmd};
-use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::database::{self, Database, SelectOptions, Selector};
 use crate::repo::Repo;
-use crate::term;
-use crate::utils;
+use crate::{info, term, utils};
 
 /// Import repositories from remote in batches.
+///
+/// All the repositories of the owner are listed by the remote API, the ones
+/// already in the database (including the attached ones) are skipped.
 #[derive(Args)]
 pub struct ImportArgs {
     /// Repository selection head.
@@ -34,6 +36,11 @@ pub struct ImportArgs {
     /// Append these labels to the database.
     #[clap(short, long)]
     pub labels: Option<String>,
+
+    /// The number of repositories to clone concurrently, default is
+    /// `git.workers` in config.
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
 }
 
 impl Run for ImportArgs {
@@ -48,11 +55,20 @@ impl Run for ImportArgs {
         let selector = Selector::from_args(&head, &query, opts);
 
         let (remote_cfg, owner, names) = selector.many_remote(&db)?;
+        let remote = remote_cfg.get_name().to_string();
+        // The same as the selector, `{owner}/{name}` imports a single repo,
+        // nothing is skipped.
+        let (query_owner, query_name) = database::parse_owner(&self.owner);
+        if query_owner.is_empty() || query_name.is_empty() {
+            let skipped = db.list_by_owner(&remote, &owner, &None).len();
+            if skipped > 0 {
+                info!("Skip {} repo(s) already in the database", skipped);
+            }
+        }
         if names.is_empty() {
             eprintln!("No repo to import");
             return Ok(());
         }
-        let remote = remote_cfg.get_name().to_string();
         term::must_confirm_items(&names, "import", "import", "Repo", "Repos")?;
 
         let remote_cfg_arc = Arc::new(remote_cfg);
@@ -74,9 +90,19 @@ impl Run for ImportArgs {
 
         let labels = utils::parse_labels(&self.labels);
 
-        let names = batch::must_run("Import", tasks)?;
-        for name in names {
-            let name = Arc::try_unwrap(name).unwrap();
+        // Save the imported repos even if some of them failed, so that they
+        // are skipped when retrying.
+        let results = batch::run("Import", tasks, true, cfg.git.workers(self.jobs));
+        let total = results.len();
+        let mut failed = 0;
+        for result in results {
+            let name = match result {
+                Ok(name) => Arc::try_unwrap(name).unwrap(),
+                Err(_) => {
+                    failed += 1;
+                    continue;
+                }
+            };
             let mut repo = Repo::new(
                 cfg,
                 Cow::Borrowed(&remote),
@@ -87,8 +113,12 @@ impl Run for ImportArgs {
             repo.append_labels(labels.clone());
             db.upsert(repo);
         }
+        db.save()?;
 
-        db.save()
+        if failed > 0 {
+            bail!("import failed for {failed}/{total} repo(s)");
+        }
+        Ok(())
     }
 }
 
@@ -131,11 +161,8 @@ impl Task<Arc<String>> for ImportTask {
         Cmd::git(&["clone", url.as_str(), path.as_str()]).execute()?;
 
         let git = GitCmd::with_path(path.as_str());
-        if let Some(user) = &self.remote_cfg.user {
-            git.exec(&["config", "user.name", user.as_str()])?;
-        }
-        if let Some(email) = &self.remote_cfg.email {
-            git.exec(&["config", "user.email", email.as_str()])?;
+        for (key, value) in self.remote_cfg.git_configs(&self.owner) {
+            git.exec(&["config", key, value.as_str()])?;
         }
         Ok(Arc::clone(&self.name))
     }
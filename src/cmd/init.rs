@@ -2,8 +2,10 @@ use anyhow::Result;
 use clap::{Args, ValueEnum};
 use strum::VariantNames;
 
+use crate::cmd::watch;
 use crate::cmd::{Completion, CompletionResult, Run};
 use crate::config::Config;
+use crate::utils;
 
 /// Print the init script.
 #[derive(Args)]
@@ -30,6 +32,10 @@ impl Run for InitArgs {
         };
         let complete_script = String::from_utf8_lossy(complete_bytes).to_string();
 
+        let visits_path = watch::visits_path(cfg);
+        let visits_path = utils::shell_quote(&format!("{}", visits_path.display()));
+        let init_script = init_script.replace("_roxide_visits_file", &visits_path);
+
         let mut script = [complete_script, init_script].join("\n");
         if !cfg.cmd.is_empty() {
             script = script.replace("_roxide_base", &cfg.cmd);
@@ -0,0 +1,226 @@
+use std::io::{self, BufRead, Write};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::cmd::serve::RepoItem;
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::repo::database::{
+    Database, DefaultProviderBuilder, SelectOptions, Selector, TerminalHelper,
+};
+use crate::utils;
+
+/// Speak newline-delimited JSON over stdio, for the editor plugins to keep one
+/// long-lived process rather than running roxide for each query.
+///
+/// Each line of stdin is a request, such as
+/// `{"id": 1, "method": "select", "params": {"head": "github", "query": "fioncat/roxide"}}`,
+/// and a line is written to stdout for it, `{"id": 1, "result": ...}` or
+/// `{"id": 1, "error": "..."}`. The `id` is returned as is, it is optional.
+/// The process exits when stdin is closed.
+///
+/// The methods:
+///
+/// * `select`: Select one repository by `head` and `query`, the same as
+///   `which`, interactive selection is not supported.
+/// * `list`: List the repositories, filtered by `remote`, `owner` and
+///   `labels`.
+/// * `which`: Same as `select`, but return the path and urls of the repository.
+/// * `visit`: Record a visit of the repository `remote`, `owner`, `name`, so
+///   that it ranks higher, return the updated repository.
+#[derive(Args)]
+pub struct IpcArgs {}
+
+impl Run for IpcArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+        for line in stdin.lock().lines() {
+            let line = line.context("read request from stdin")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = handle_line(cfg, &line);
+            writeln!(stdout, "{response}").context("write response to stdout")?;
+            stdout.flush().context("flush stdout")?;
+        }
+        Ok(())
+    }
+}
+
+impl IpcArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_, _| Ok(CompletionResult::empty()),
+            flags: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+
+    method: String,
+
+    #[serde(default)]
+    params: Params,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Params {
+    head: Option<String>,
+    query: Option<String>,
+
+    remote: Option<String>,
+    owner: Option<String>,
+    name: Option<String>,
+
+    /// The labels to filter, separated by comma.
+    labels: Option<String>,
+}
+
+/// The plugins cannot answer the fzf in the middle of a request, so the
+/// selection requiring it fails.
+struct IpcTerminalHelper {}
+
+impl TerminalHelper for IpcTerminalHelper {
+    fn search(&self, _items: &[String]) -> Result<usize> {
+        bail!("interactive search is not supported in ipc, please specify the query")
+    }
+
+    fn edit(&self, _cfg: &Config, _items: Vec<String>) -> Result<Vec<String>> {
+        bail!("interactive edit is not supported in ipc")
+    }
+}
+
+fn handle_line(cfg: &Config, line: &str) -> Value {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return json!({ "id": null, "error": format!("parse request: {err}") }),
+    };
+    match handle(cfg, &request) {
+        Ok(result) => json!({ "id": request.id, "result": result }),
+        Err(err) => json!({ "id": request.id, "error": format!("{err:#}") }),
+    }
+}
+
+/// Handle the request, the database is loaded for each request, so that the
+/// changes made by other roxide processes are visible.
+fn handle(cfg: &Config, request: &Request) -> Result<Value> {
+    let params = &request.params;
+    let value = match request.method.as_str() {
+        "select" | "which" => {
+            let db = Database::load_readonly(cfg)?;
+            let opts = SelectOptions::new(IpcTerminalHelper {}, DefaultProviderBuilder {})
+                .with_force_local(true);
+            let repo = Selector::from_args(&params.head, &params.query, opts).must_one(&db)?;
+            if request.method == "select" {
+                serde_json::to_value(RepoItem::from_repo(cfg, &repo))
+            } else {
+                Ok(json!({
+                    "path": format!("{}", repo.get_path(cfg).display()),
+                    "url": repo.remote_cfg.clone.as_ref().map(|_| repo.clone_url()),
+                    "ssh": repo.ssh_url(),
+                    "web": repo.web_url(),
+                }))
+            }
+        }
+        "list" => {
+            let db = Database::load_readonly(cfg)?;
+            let labels = utils::parse_labels(&params.labels);
+            let repos = match (params.remote.as_ref(), params.owner.as_ref()) {
+                (Some(remote), Some(owner)) => db.list_by_owner(remote, owner, &labels),
+                (Some(remote), None) => db.list_by_remote(remote, &labels),
+                (None, Some(_)) => bail!("the owner requires remote"),
+                (None, None) => db.list_all(&labels),
+            };
+            serde_json::to_value(RepoItem::list(cfg, &repos))
+        }
+        "visit" => {
+            let (remote, owner, name) = match (
+                params.remote.as_ref(),
+                params.owner.as_ref(),
+                params.name.as_ref(),
+            ) {
+                (Some(remote), Some(owner), Some(name)) => (remote, owner, name),
+                _ => bail!("visit requires remote, owner and name"),
+            };
+            let mut db = Database::load(cfg)?;
+            let mut repo = db.must_get(remote, owner, name)?.update();
+            repo.accessed += 1;
+            repo.last_accessed = cfg.now();
+            let value = serde_json::to_value(RepoItem::from_repo(cfg, &repo));
+            db.upsert(repo);
+            db.save()?;
+            value
+        }
+        method => bail!("unknown method '{method}'"),
+    };
+    value.context("serialize result")
+}
+
+#[cfg(test)]
+mod ipc_tests {
+    use crate::cmd::ipc::*;
+    use crate::config::config_tests;
+    use crate::repo::database::database_tests;
+
+    #[test]
+    fn test_handle_line() {
+        let cfg = config_tests::load_test_config("ipc/handle_line");
+        let mut db = Database::load(&cfg).unwrap();
+        for repo in database_tests::get_test_repos(&cfg) {
+            db.upsert(repo);
+        }
+        db.save().unwrap();
+
+        let response = handle_line(
+            &cfg,
+            r#"{"id": 1, "method": "select", "params": {"head": "github", "query": "fioncat/csync"}}"#,
+        );
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["name"], "csync");
+        assert_eq!(response["result"]["accessed"], 0);
+
+        let response = handle_line(
+            &cfg,
+            r#"{"id": "a", "method": "list", "params": {"remote": "gitlab", "labels": "mark"}}"#,
+        );
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["result"][0]["name"], "my-repo-02");
+        assert_eq!(response["result"].as_array().unwrap().len(), 1);
+
+        let response = handle_line(
+            &cfg,
+            r#"{"method": "visit", "params": {"remote": "github", "owner": "fioncat", "name": "csync"}}"#,
+        );
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["result"]["accessed"], 1);
+        let db = Database::load_readonly(&cfg).unwrap();
+        assert_eq!(
+            db.must_get("github", "fioncat", "csync").unwrap().accessed,
+            1
+        );
+
+        let response = handle_line(
+            &cfg,
+            r#"{"method": "which", "params": {"head": "github", "query": "kubernetes/kubectl"}}"#,
+        );
+        let path = response["result"]["path"].as_str().unwrap();
+        assert!(path.ends_with("kubernetes/kubectl"), "{path}");
+
+        for line in [
+            "not json",
+            r#"{"method": "unknown"}"#,
+            r#"{"method": "visit", "params": {"remote": "github"}}"#,
+        ] {
+            assert!(handle_line(&cfg, line)["error"].is_string(), "{line}");
+        }
+    }
+}
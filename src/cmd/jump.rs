@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::repo::database::Database;
+use crate::repo::jump::{self, JumpTable};
+use crate::table::{Table, TableArgs, TableCell};
+use crate::{exec, info, utils};
+
+/// Jump to a frequently used directory or repository.
+///
+/// They are ranked by frecency. The directories outside the repositories are
+/// recorded by the shell hook (see `init`), like zoxide.
+#[derive(Args)]
+pub struct JumpArgs {
+    /// The keywords to match the path, in order, and the last one must match
+    /// the last component. If omitted, use fzf to select one.
+    pub keywords: Vec<String>,
+
+    /// Record a visit of the directory, this is called by the shell hook. The
+    /// repositories, the workspace and the home directory are skipped.
+    #[clap(short, long)]
+    pub add: Option<String>,
+
+    /// Remove the directory from the jump table.
+    #[clap(short, long)]
+    pub remove: Option<String>,
+
+    /// List the directories in the jump table, and remove the missing ones.
+    #[clap(short, long)]
+    pub list: bool,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
+}
+
+/// A jump target, the repositories and directories are ranked together.
+struct Target {
+    path: PathBuf,
+    score: u64,
+
+    /// The repository `(remote, owner, name)`, [`None`] for a directory.
+    repo: Option<(String, String, String)>,
+}
+
+impl Run for JumpArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        if let Some(path) = self.add.as_ref() {
+            return self.add(cfg, Path::new(path));
+        }
+        if let Some(path) = self.remove.as_ref() {
+            let mut table = JumpTable::load(cfg)?;
+            if !table.remove(Path::new(path)) {
+                bail!("the directory '{path}' is not in the jump table");
+            }
+            info!("Remove '{}' from the jump table", path);
+            return table.save();
+        }
+        if self.list {
+            return self.list(cfg);
+        }
+
+        let mut db = Database::load(cfg)?;
+        let mut table = JumpTable::load(cfg)?;
+        table.remove_missing();
+
+        let mut targets: Vec<Target> = db
+            .list_all(&None)
+            .into_iter()
+            .map(|repo| Target {
+                path: repo.get_path(cfg),
+                score: repo.score(cfg),
+                repo: Some((
+                    repo.remote.to_string(),
+                    repo.owner.to_string(),
+                    repo.name.to_string(),
+                )),
+            })
+            .collect();
+        targets.extend(table.list().into_iter().map(|dir| Target {
+            score: dir.score(cfg),
+            path: PathBuf::from(dir.path),
+            repo: None,
+        }));
+        targets.sort_by_key(|target| std::cmp::Reverse(target.score));
+
+        let target = if self.keywords.is_empty() {
+            let items: Vec<String> = targets
+                .iter()
+                .map(|target| format!("{}", target.path.display()))
+                .collect();
+            let idx = exec::fzf_search(&items)?;
+            targets.swap_remove(idx)
+        } else {
+            match targets
+                .into_iter()
+                .find(|target| jump::matches(&format!("{}", target.path.display()), &self.keywords))
+            {
+                Some(target) => target,
+                None => bail!("no directory or repo matches '{}'", self.keywords.join(" ")),
+            }
+        };
+
+        println!("{}", target.path.display());
+
+        match target.repo {
+            Some((remote, owner, name)) => {
+                let mut repo = db.must_get(remote, owner, name)?.update();
+                repo.accessed += 1;
+                repo.last_accessed = cfg.now();
+                db.upsert(repo);
+                db.save()?;
+            }
+            None => {
+                table.add(&target.path);
+                table.save()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl JumpArgs {
+    fn add(&self, cfg: &Config, path: &Path) -> Result<()> {
+        if !path.is_absolute() || !path.is_dir() {
+            return Ok(());
+        }
+        if path == utils::get_home_dir()? || path.starts_with(cfg.get_workspace_dir()) {
+            return Ok(());
+        }
+        // This runs in the shell hook for every directory change, do not take
+        // the database lock.
+        let db = Database::load_readonly(cfg)?;
+        if db.get_by_path(path).is_some() {
+            return Ok(());
+        }
+        drop(db);
+
+        let mut table = JumpTable::load(cfg)?;
+        table.add(path);
+        table.save()
+    }
+
+    fn list(&self, cfg: &Config) -> Result<()> {
+        let mut table = JumpTable::load(cfg)?;
+        let removed = table.remove_missing();
+        if removed > 0 {
+            info!("Remove {} missing directories", removed);
+        }
+        let dirs = table.list();
+        if dirs.is_empty() {
+            eprintln!("No directory in the jump table");
+            return table.save();
+        }
+
+        let mut t = Table::with_capacity(1 + dirs.len());
+        t.add(vec![
+            String::from("Path"),
+            String::from("Score"),
+            String::from("Accessed"),
+            String::from("Last"),
+        ]);
+        for dir in dirs {
+            t.add_color(vec![
+                TableCell::no_color(dir.path.clone()),
+                TableCell::no_color(format!("{}", dir.score(cfg))),
+                TableCell::no_color(format!("{}", dir.entry.accessed)),
+                TableCell::no_color(utils::format_since(cfg, dir.entry.last_accessed))
+                    .with_sort_key(dir.entry.last_accessed),
+            ]);
+        }
+        t.show_with(&self.table, &["path", "score", "accessed", "last"])?;
+        table.save()
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_cfg, _args| Ok(CompletionResult::empty()),
+            flags: Some(|cfg, flag, _to_complete| match flag {
+                'r' => {
+                    let table = JumpTable::load(cfg)?;
+                    let items: Vec<_> = table.list().into_iter().map(|dir| dir.path).collect();
+                    Ok(Some(CompletionResult::from(items)))
+                }
+                _ => Ok(None),
+            }),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::batch::{self, Task};
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::exec::Cmd;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::table::{Table, TableArgs};
+use crate::utils;
+
+/// Search the commits across the repositories, the results are merged into one
+/// table, the latest first.
+#[derive(Args)]
+pub struct LogArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Only search the current repository.
+    #[clap(short, long)]
+    pub current: bool,
+
+    /// Only the commits whose message matches the pattern, see
+    /// `git log --grep`.
+    #[clap(short, long)]
+    pub grep: Option<String>,
+
+    /// Only the commits whose author matches the pattern, see
+    /// `git log --author`.
+    #[clap(short, long)]
+    pub author: Option<String>,
+
+    /// Only the commits after the date, such as "2 weeks ago", "2024-01-01".
+    #[clap(short, long)]
+    pub since: Option<String>,
+
+    /// The max number of commits to search in each repository.
+    #[clap(short = 'n', long, default_value = "20")]
+    pub limit: usize,
+
+    /// Use the labels to filter repository.
+    #[clap(short, long)]
+    pub labels: Option<String>,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
+}
+
+impl Run for LogArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let repos: Vec<(String, PathBuf)> = if self.current {
+            let repo = db.must_get_current()?;
+            vec![(repo.name.to_string(), repo.get_path(cfg))]
+        } else {
+            let labels = utils::parse_labels(&self.labels);
+            let opts = SelectOptions::default().with_filter_labels(labels);
+            let selector = Selector::from_args(&self.head, &self.query, opts);
+            let (repos, level) = selector.many_local(&db)?;
+            repos
+                .iter()
+                .map(|repo| (repo.to_string(&level), repo.get_path(cfg)))
+                .collect()
+        };
+        if repos.is_empty() {
+            eprintln!("No repo to search");
+            return Ok(());
+        }
+
+        let mut args = vec![
+            String::from("log"),
+            String::from("--format=%ct%x09%h%x09%an%x09%s"),
+            format!("--max-count={}", self.limit),
+        ];
+        if let Some(grep) = self.grep.as_ref() {
+            args.push(format!("--grep={grep}"));
+        }
+        if let Some(author) = self.author.as_ref() {
+            args.push(format!("--author={author}"));
+        }
+        if let Some(since) = self.since.as_ref() {
+            args.push(format!("--since={since}"));
+        }
+
+        let tasks: Vec<_> = repos
+            .into_iter()
+            .map(|(name, path)| {
+                let task = LogTask {
+                    name: name.clone(),
+                    path,
+                    args: args.clone(),
+                };
+                (name, task)
+            })
+            .collect();
+        let mut commits: Vec<_> = batch::must_run_with("Search", tasks, cfg.git.workers(None))?
+            .into_iter()
+            .flatten()
+            .collect();
+        eprintln!();
+        if commits.is_empty() {
+            eprintln!("No commit found");
+            return Ok(());
+        }
+        commits.sort_by_key(|commit| std::cmp::Reverse(commit.time));
+
+        let mut table = Table::with_capacity(1 + commits.len());
+        table.add(vec![
+            String::from("Repo"),
+            String::from("Commit"),
+            String::from("Date"),
+            String::from("Author"),
+            String::from("Subject"),
+        ]);
+        for commit in commits {
+            table.add(vec![
+                commit.repo,
+                commit.sha,
+                utils::format_time(commit.time)?,
+                commit.author,
+                commit.subject,
+            ]);
+        }
+        table.show_with(&self.table, &["repo", "commit", "date", "subject"])
+    }
+}
+
+impl LogArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: Some(|cfg, flag, to_complete| match flag {
+                'l' => Completion::labels_flag(cfg, to_complete),
+                _ => Ok(None),
+            }),
+        }
+    }
+}
+
+struct LogTask {
+    name: String,
+    path: PathBuf,
+    args: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct LogCommit {
+    repo: String,
+    sha: String,
+    time: u64,
+    author: String,
+    subject: String,
+}
+
+impl Task<Vec<LogCommit>> for LogTask {
+    fn run(&self) -> Result<Vec<LogCommit>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let path = format!("{}", self.path.display());
+        let mut args = vec!["-C", path.as_str()];
+        args.extend(self.args.iter().map(|arg| arg.as_str()));
+        let lines = Cmd::git(&args).lines()?;
+        Ok(lines
+            .iter()
+            .filter_map(|line| Self::parse(&self.name, line))
+            .collect())
+    }
+}
+
+impl LogTask {
+    /// Parse the line in format "{time}\t{sha}\t{author}\t{subject}".
+    fn parse(repo: &str, line: &str) -> Option<LogCommit> {
+        let mut fields = line.splitn(4, '\t');
+        let time = fields.next()?.trim().parse().ok()?;
+        let sha = fields.next()?;
+        let author = fields.next()?;
+        let subject = fields.next().unwrap_or_default();
+        Some(LogCommit {
+            repo: repo.to_string(),
+            sha: sha.to_string(),
+            time,
+            author: author.to_string(),
+            subject: subject.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod log_tests {
+    use crate::cmd::log::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            LogTask::parse("o/a", "1700000000\tabc1234\tAlice\tfix: the\ttab"),
+            Some(LogCommit {
+                repo: String::from("o/a"),
+                sha: String::from("abc1234"),
+                time: 1700000000,
+                author: String::from("Alice"),
+                subject: String::from("fix: the\ttab"),
+            })
+        );
+        assert_eq!(LogTask::parse("o/a", "invalid"), None);
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+use crate::api;
+use crate::api::auth::{self, LoginToken, TokenStore};
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::{Config, ProviderType};
+use crate::{info, term, warn};
+
+/// Login to the remote and save the api token.
+///
+/// For GitHub, the OAuth device flow is used (see `oauth` in remote config);
+/// For GitLab, the personal access token is prompted. The saved token is only
+/// used when the remote has no `token` in config.
+#[derive(Args)]
+pub struct LoginArgs {
+    /// The remote name.
+    pub remote: String,
+
+    /// Remove the saved token.
+    #[clap(long)]
+    pub logout: bool,
+}
+
+impl Run for LoginArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let remote_cfg = cfg.must_get_remote(&self.remote)?;
+        let store = TokenStore::new(cfg);
+        if self.logout {
+            if store.remove(cfg, &self.remote)? {
+                info!("Removed the login token of remote {}", self.remote);
+            } else {
+                eprintln!("Remote {} is not logged in", self.remote);
+            }
+            return Ok(());
+        }
+
+        let provider_type = match remote_cfg.provider.as_ref() {
+            Some(provider_type) => provider_type,
+            None => bail!("missing provider config for remote '{}'", self.remote),
+        };
+        if remote_cfg.token.is_some() {
+            warn!("Remote {} has `token` in config, the login token will not be used until it is removed", self.remote);
+        }
+        let token = match provider_type {
+            ProviderType::Github => auth::github_device_login(cfg, &remote_cfg)?,
+            ProviderType::Gitlab => {
+                let token = term::input_secret("Input personal access token")?;
+                LoginToken::new(token)
+            }
+        };
+
+        let mut login_cfg = remote_cfg.into_owned();
+        login_cfg.token = Some(token.token.clone());
+        let provider = api::build_raw_provider(cfg, &login_cfg)?;
+        provider
+            .list_owners()
+            .context("verify the token with remote api")?;
+
+        store.save(cfg, &self.remote, &token)?;
+        info!("Logged in to remote {}", self.remote);
+        Ok(())
+    }
+}
+
+impl LoginArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: |cfg, args| match args.len() {
+                0 | 1 => Ok(CompletionResult::from(cfg.list_remotes())),
+                _ => Ok(CompletionResult::empty()),
+            },
+            flags: None,
+        }
+    }
+}
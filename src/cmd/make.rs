@@ -8,7 +8,7 @@ use crate::cmd::{Completion, CompletionResult, Run};
 use crate::config::{Config, WorkflowConfig};
 use crate::repo::database::Database;
 use crate::repo::Repo;
-use crate::workflow::Workflow;
+use crate::workflow::{Workflow, WorkflowHistory};
 
 /// Run workflow for current repository (.roxide/workflows)
 #[derive(Args)]
@@ -25,8 +25,11 @@ impl Run for MakeArgs {
         let repo = db.must_get_current()?;
         let workflows = Self::load_workflow_cfg(cfg, &repo)?;
         let workflow_cfg = Config::get_workflow_from_map(&workflows, &self.name)?;
-        let workflow = Workflow::new(cfg, &repo, workflow_cfg, true);
-        workflow.run()
+        let history = WorkflowHistory::new(cfg);
+        let workflow = Workflow::new(cfg, &repo, &self.name, workflow_cfg, true)?
+            .with_history(history.recorder());
+        let result = workflow.run();
+        history.finish(cfg, result)
     }
 }
 
@@ -41,7 +44,7 @@ impl MakeArgs {
             args: |cfg, args| -> Result<CompletionResult> {
                 match args.len() {
                     0 | 1 => {
-                        let db = Database::load(cfg)?;
+                        let db = Database::load_readonly(cfg)?;
                         let repo = db.must_get_current()?;
                         let workflows = Self::load_workflow_cfg(cfg, &repo)?;
                         let mut items: Vec<String> = workflows.into_keys().collect();
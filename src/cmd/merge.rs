@@ -1,13 +1,21 @@
-use anyhow::{bail, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
 use clap::Args;
 
-use crate::api::MergeOptions;
+use crate::api::{
+    ActionOptions, ActionTarget, CreateMergeOptions, MergeMethod, MergeOptions, Provider,
+};
+use crate::cmd::action;
 use crate::cmd::{Completion, Run};
 use crate::config::Config;
+use crate::exec::Cmd;
 use crate::git::{self, GitBranch, GitRemote};
 use crate::repo::database::Database;
 use crate::term;
-use crate::{api, confirm, info, utils};
+use crate::{api, clipboard, confirm, info, utils};
 
 /// Create or open MergeRequest (PullRequest for Github)
 #[derive(Args)]
@@ -22,6 +30,39 @@ pub struct MergeArgs {
     /// When calling the remote API, ignore caches that are not expired.
     #[clap(short, long)]
     pub force: bool,
+
+    /// Accept (merge) the opened MergeRequest rather than create it.
+    #[clap(short, long)]
+    pub accept: bool,
+
+    /// The method to accept the MergeRequest.
+    #[clap(short, long, default_value = "merge")]
+    pub method: MergeMethod,
+
+    /// Approve the opened MergeRequest rather than create it.
+    #[clap(short = 'A', long)]
+    pub approve: bool,
+
+    /// Before accepting, wait until the checks (CI/CD action) of the source branch
+    /// are completed, and refuse to accept if some of them failed.
+    #[clap(short, long)]
+    pub wait_checks: bool,
+
+    /// Copy the url to clipboard rather than opening it in browser.
+    #[clap(long)]
+    pub copy: bool,
+
+    /// Create the MergeRequest as draft.
+    #[clap(short, long)]
+    pub draft: bool,
+
+    /// The usernames to request review from when creating, split by comma.
+    #[clap(short, long)]
+    pub reviewers: Option<String>,
+
+    /// The labels to add when creating, split by comma.
+    #[clap(short, long)]
+    pub labels: Option<String>,
 }
 
 impl Run for MergeArgs {
@@ -34,6 +75,7 @@ impl Run for MergeArgs {
 
         info!("Get repo info from remote API");
         let mut api_repo = provider.get_repo(repo.owner.as_ref(), repo.name.as_ref())?;
+        api_repo.warn_archived(repo.name_with_remote());
         if self.upstream {
             if api_repo.upstream.is_none() {
                 bail!(
@@ -72,10 +114,13 @@ impl Run for MergeArgs {
             target,
         };
 
+        if self.accept || self.approve {
+            return self.review(provider.as_mut(), merge);
+        }
+
         info!("Get merge info from remote API");
         if let Some(url) = provider.get_merge(merge.clone())? {
-            utils::open_url(url)?;
-            return Ok(());
+            return clipboard::open_or_copy(url, self.copy);
         }
 
         let git_remote = if self.upstream {
@@ -88,36 +133,190 @@ impl Run for MergeArgs {
             bail!("no commit to merge");
         }
 
-        let (commit_desc, init_title) = if commits.len() == 1 {
-            (String::from("1 commit"), Some(commits[0].as_str()))
-        } else {
-            (format!("{} commits", commits.len()), None)
-        };
+        let commit_desc = utils::plural(&commits, "commit");
 
         eprintln!();
         eprintln!("About to create merge: {}", merge.pretty_display());
         eprintln!("With {}", commit_desc);
         confirm!("Continue");
 
-        let title = term::input("Please input title", true, init_title)?;
-        let body = if term::confirm("Do you need body")? {
+        let (init_title, mut init_body) = Self::fill_from_commits(&commits);
+        if commits.len() == 1 {
+            init_body = Cmd::git(&["log", "-1", "--format=%b"]).read()?;
+        }
+        let template = match Self::read_template(&repo.get_path(cfg))? {
+            Some(template) => Some(template),
+            None => repo
+                .remote_cfg
+                .merge_template(repo.owner.as_ref())
+                .map(String::from),
+        };
+        if let Some(template) = template {
+            init_body = template;
+        }
+        // Close the issue linked by `branch --issue` when merged.
+        if let Some(id) = GitBranch::get_issue(&merge.source) {
+            if !init_body.is_empty() {
+                init_body.push_str("\n\n");
+            }
+            init_body.push_str(&format!("Closes #{id}"));
+        }
+
+        let title = term::input("Please input title", true, Some(init_title.as_str()))?;
+        let body = if !init_body.trim().is_empty() {
+            // Open the editor with the pre-filled body for the final tweaks.
+            term::edit_content(cfg, init_body.as_str(), "body.md", false)?
+        } else if term::confirm("Do you need body")? {
             term::edit_content(cfg, "Please input your body (markdown)", "body.md", true)?
         } else {
             String::new()
         };
 
+        let create = CreateMergeOptions {
+            title,
+            body,
+            draft: self.draft,
+            reviewers: Self::split_names(&self.reviewers),
+            labels: Self::split_names(&self.labels),
+        };
         info!("Call remote API to create merge");
-        let url = provider.create_merge(merge, title, body)?;
+        let url = provider.create_merge(merge, create)?;
 
-        utils::open_url(url)
+        clipboard::open_or_copy(url, self.copy)
     }
 }
 
 impl MergeArgs {
+    /// The template files of MergeRequest (PullRequest), in the order of
+    /// priority.
+    const TEMPLATE_FILES: [&'static str; 5] = [
+        ".github/pull_request_template.md",
+        ".github/PULL_REQUEST_TEMPLATE.md",
+        "docs/pull_request_template.md",
+        "PULL_REQUEST_TEMPLATE.md",
+        ".gitlab/merge_request_templates/Default.md",
+    ];
+
+    /// Return the title and body from the commits (newest first): the title
+    /// is the oldest commit, and the body lists all the commits if more than
+    /// one.
+    fn fill_from_commits(commits: &[String]) -> (String, String) {
+        let title = commits.last().cloned().unwrap_or_default();
+        if commits.len() <= 1 {
+            return (title, String::new());
+        }
+        let body: Vec<String> = commits
+            .iter()
+            .rev()
+            .map(|commit| format!("- {commit}"))
+            .collect();
+        (title, body.join("\n"))
+    }
+
+    fn read_template(repo_path: &Path) -> Result<Option<String>> {
+        for name in Self::TEMPLATE_FILES {
+            let path = repo_path.join(name);
+            match fs::read_to_string(&path) {
+                Ok(template) => return Ok(Some(template)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("read merge template '{}'", path.display()))
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn split_names(names: &Option<String>) -> Vec<String> {
+        match names.as_ref() {
+            Some(names) => names
+                .split(',')
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn review(&self, provider: &mut dyn Provider, merge: MergeOptions) -> Result<()> {
+        info!("Get merge info from remote API");
+        let url = match provider.get_merge(merge.clone())? {
+            Some(url) => url,
+            None => bail!("could not find opened merge: {}", merge.pretty_display()),
+        };
+        eprintln!();
+        eprintln!("Merge: {}", merge.pretty_display());
+        eprintln!("URL:   {url}");
+
+        if self.approve {
+            confirm!("Continue to approve");
+            info!("Call remote API to approve merge");
+            provider.approve_merge(merge.clone())?;
+        }
+        if self.accept && self.wait_checks {
+            Self::wait_checks(provider, &merge)?;
+        }
+        if self.accept {
+            confirm!("Continue to accept with method {}", self.method.as_str());
+            info!("Call remote API to accept merge");
+            provider.accept_merge(merge, self.method)?;
+        }
+
+        Ok(())
+    }
+
+    fn wait_checks(provider: &dyn Provider, merge: &MergeOptions) -> Result<()> {
+        let opts = ActionOptions {
+            owner: merge.owner.clone(),
+            name: merge.name.clone(),
+            target: ActionTarget::Branch(merge.source.clone()),
+        };
+        info!("Wait checks for branch {}", merge.source);
+        let action = action::wait_action(provider.get_action(&opts)?, provider, &opts)?;
+
+        let failed = action.failed_jobs();
+        if !failed.is_empty() {
+            bail!("checks failed: {}", failed.join(", "));
+        }
+        Ok(())
+    }
+
     pub fn completion() -> Completion {
         Completion {
-            args: Completion::branch_args,
+            args: Completion::base_branch_args,
             flags: None,
         }
     }
 }
+
+#[cfg(test)]
+mod merge_tests {
+    use crate::cmd::merge::*;
+
+    #[test]
+    fn test_fill_from_commits() {
+        assert_eq!(
+            MergeArgs::fill_from_commits(&[]),
+            (String::new(), String::new())
+        );
+        let commits = vec![String::from("feat: first")];
+        assert_eq!(
+            MergeArgs::fill_from_commits(&commits),
+            (String::from("feat: first"), String::new())
+        );
+        let commits = vec![
+            String::from("fix: third"),
+            String::from("feat: second"),
+            String::from("feat: first"),
+        ];
+        assert_eq!(
+            MergeArgs::fill_from_commits(&commits),
+            (
+                String::from("feat: first"),
+                String::from("- feat: first\n- feat: second\n- fix: third")
+            )
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::mirror::Mirrors;
+use crate::repo::Repo;
+use crate::table::{Table, TableArgs, TableCell, TableCellColor};
+use crate::{info, term, utils};
+
+/// Manage the push mirrors of repository.
+///
+/// The branches and tags of origin are pushed to the mirrors by `sync` (with op
+/// "mirror").
+#[derive(Args)]
+pub struct MirrorArgs {
+    /// Repository selection head, default will use the current repo.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Add a push mirror to this remote.
+    #[clap(short, long)]
+    pub add: Option<String>,
+
+    /// The url of the mirror to add, default is the clone url of the same owner
+    /// and name in the mirror remote.
+    #[clap(long)]
+    pub url: Option<String>,
+
+    /// Remove the push mirror of this remote.
+    #[clap(short, long)]
+    pub delete: Option<String>,
+
+    /// Show result as json format.
+    #[clap(short = 'J')]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
+}
+
+impl Run for MirrorArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let repo = self.select(&db)?;
+        let mut mirrors = Mirrors::load(cfg)?;
+
+        if let Some(remote) = self.add.as_ref() {
+            let remote_cfg = cfg.must_get_remote(remote)?;
+            let url = match self.url.as_ref() {
+                Some(url) => url.clone(),
+                None => {
+                    if remote_cfg.clone.is_none() {
+                        bail!("remote '{remote}' has no clone domain, please specify the url");
+                    }
+                    Repo::get_clone_url(repo.owner.as_ref(), repo.name.as_ref(), &remote_cfg)
+                }
+            };
+            mirrors.add(&repo, remote, url.clone())?;
+            info!("Add push mirror {} -> {}", repo.name_with_remote(), url);
+            return mirrors.save();
+        }
+
+        if let Some(remote) = self.delete.as_ref() {
+            if !mirrors.remove(&repo, remote) {
+                bail!(
+                    "repo '{}' has no push mirror to remote '{remote}'",
+                    repo.name_with_remote()
+                );
+            }
+            info!(
+                "Remove push mirror of {} to remote {}",
+                repo.name_with_remote(),
+                remote
+            );
+            return mirrors.save();
+        }
+
+        let items = mirrors.get(&repo.name_with_remote());
+        if self.json {
+            return term::show_json(items);
+        }
+        if items.is_empty() {
+            eprintln!("No push mirror");
+            return Ok(());
+        }
+
+        let mut table = Table::with_capacity(items.len() + 1);
+        table.add(vec![
+            String::from("Remote"),
+            String::from("Url"),
+            String::from("LastPush"),
+            String::from("Status"),
+        ]);
+        for mirror in items {
+            let last_push = match mirror.last_push {
+                Some(time) => {
+                    TableCell::no_color(utils::format_since(cfg, time)).with_sort_key(time)
+                }
+                None => TableCell::no_color(String::from("never")).with_sort_key(0),
+            };
+            let status = match (mirror.last_push, mirror.error.as_ref()) {
+                (None, _) => TableCell::no_color(String::from("pending")),
+                (Some(_), None) => TableCell::with_color(String::from("ok"), TableCellColor::Green),
+                (Some(_), Some(err)) => TableCell::with_color(err.clone(), TableCellColor::Red),
+            };
+            table.add_color(vec![
+                TableCell::no_color(mirror.remote.clone()),
+                TableCell::no_color(mirror.url.clone()),
+                last_push,
+                status,
+            ]);
+        }
+        table.show_with(&self.table, &["remote", "url", "lastpush", "status"])
+    }
+}
+
+impl MirrorArgs {
+    fn select<'a>(&self, db: &'a Database) -> Result<Repo<'a>> {
+        if self.head.is_none() {
+            return db.must_get_current();
+        }
+        let opts = SelectOptions::default().with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        selector.must_one(db)
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: Some(|cfg, flag, _to_complete| match flag {
+                'a' | 'd' => Ok(Some(CompletionResult::from(cfg.list_remotes()))),
+                _ => Ok(None),
+            }),
+        }
+    }
+}
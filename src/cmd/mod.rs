@@ -1,54 +1,99 @@
 mod action;
 mod attach;
+mod audit;
+mod backup;
 mod branch;
+mod changelog;
 mod check;
 mod clean;
+mod commit;
 mod complete;
 mod config;
+mod container;
 mod copy;
+mod daemon;
+mod debug;
+mod deps;
 mod detach;
 mod detect;
 mod diagnose;
 mod display;
+mod edit;
+mod env;
+mod fork_setup;
 mod get;
 mod home;
 mod import;
 mod info;
 mod init;
+mod ipc;
+mod jump;
 mod label;
+mod log;
+mod login;
 mod make;
 mod merge;
+mod mirror;
+mod mv;
+mod notification;
 mod open;
+mod owner;
+mod pin;
+mod profile;
+mod prompt;
+mod push;
 mod rebase;
+mod recent;
 mod recover;
 mod remove;
 mod reset;
 mod run;
 mod secret;
+mod serve;
 mod snapshot;
 mod squash;
+mod stash;
 mod stats;
 mod sync;
 mod tag;
+mod tmux;
+mod unpin;
+mod unthin;
 mod update;
+mod watch;
+mod which;
 
 use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
 use strum::VariantNames;
 
 use crate::config::Config;
 use crate::git::{self, GitBranch, GitRemote};
 use crate::repo::database::{self, Database};
 use crate::repo::keywords::Keywords;
-use crate::{api, hashmap};
+use crate::{api, hashmap, warn};
 
 #[derive(Parser)]
 #[command(author, version = env!("ROXIDE_VERSION"), about)]
 pub struct App {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Use this workspace profile, override the `ROXIDE_PROFILE` env and the
+    /// current profile.
+    #[clap(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Print the debug logs, use `-vv` to print the trace logs too.
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Measure the phases (config, database, git, api, scan, etc.) and print
+    /// a breakdown at the end of the command.
+    #[clap(long, global = true)]
+    pub timings: bool,
 }
 
 #[derive(Subcommand, VariantNames)]
@@ -56,65 +101,218 @@ pub struct App {
 pub enum Commands {
     Action(action::ActionArgs),
     Attach(attach::AttachArgs),
+    Audit(audit::AuditArgs),
+    Backup(backup::BackupArgs),
     Branch(branch::BranchArgs),
+    Changelog(changelog::ChangelogArgs),
     Check(check::CheckArgs),
     Clean(clean::CleanArgs),
+    Commit(commit::CommitArgs),
     Complete(complete::CompleteArgs),
     Config(config::ConfigArgs),
+    Container(container::ContainerArgs),
     Copy(copy::CopyArgs),
+    Daemon(daemon::DaemonArgs),
+    #[clap(hide = true)]
+    Debug(debug::DebugArgs),
+    Deps(deps::DepsArgs),
     Detach(detach::DetachArgs),
     Detect(detect::DetectArgs),
     Diagnose(diagnose::DiagnoseArgs),
     Display(display::DisplayArgs),
+    Edit(edit::EditArgs),
+    Env(env::EnvArgs),
+    ForkSetup(fork_setup::ForkSetupArgs),
     Get(get::GetArgs),
     Home(home::HomeArgs),
     Import(import::ImportArgs),
     Info(info::InfoArgs),
     Init(init::InitArgs),
+    Ipc(ipc::IpcArgs),
+    Jump(jump::JumpArgs),
     Label(label::LabelArgs),
+    Log(log::LogArgs),
+    Login(login::LoginArgs),
     Make(make::MakeArgs),
     Merge(merge::MergeArgs),
+    Mirror(mirror::MirrorArgs),
+    Mv(mv::MvArgs),
+    Notification(notification::NotificationArgs),
     Open(open::OpenArgs),
+    Owner(owner::OwnerArgs),
+    Pin(pin::PinArgs),
+    Profile(profile::ProfileArgs),
+    Prompt(prompt::PromptArgs),
+    Push(push::PushArgs),
     Rebase(rebase::RebaseArgs),
+    Recent(recent::RecentArgs),
     Recover(recover::RecoverArgs),
     Remove(remove::RemoveArgs),
     Reset(reset::ResetArgs),
     Run(run::RunArgs),
     Secret(secret::SecretArgs),
+    Serve(serve::ServeArgs),
     Snapshot(snapshot::SnapshotArgs),
     Squash(squash::SquashArgs),
+    Stash(stash::StashArgs),
     Stats(stats::StatsArgs),
     Sync(sync::SyncArgs),
     Tag(tag::TagArgs),
+    Tmux(tmux::TmuxArgs),
+    Unpin(unpin::UnpinArgs),
+    Unthin(unthin::UnthinArgs),
     Update(update::UpdateArgs),
+    Watch(watch::WatchArgs),
+    Which(which::WhichArgs),
 }
 
 impl Commands {
+    /// Return the command name if it changes the state (the database, the
+    /// repositories, the workspace, the remote, etc.), such commands are recorded
+    /// in the audit history. Every command must be listed here, the read-only
+    /// ones (or read-only usages) opt out explicitly by returning [`None`], so
+    /// that a new command cannot be left out of the audit by accident.
+    pub fn audit_name(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Attach(_) => "attach",
+            Self::Backup(_) => "backup",
+            Self::Branch(_) => "branch",
+            Self::Check(_) => "check",
+            Self::Clean(_) => "clean",
+            Self::Commit(_) => "commit",
+            Self::Config(_) => "config",
+            Self::Container(_) => "container",
+            Self::Copy(_) => "copy",
+            Self::Daemon(args) => match args.command {
+                daemon::DaemonCommand::Install(_) => "daemon",
+                // The jobs are run as roxide subprocesses, they record
+                // themselves.
+                daemon::DaemonCommand::Start
+                | daemon::DaemonCommand::Status
+                | daemon::DaemonCommand::RunNow(_) => return None,
+            },
+            Self::Debug(args) => match &args.command {
+                debug::DebugCommand::BenchScan(args) if args.generate => "debug",
+                debug::DebugCommand::BenchScan(_) => return None,
+            },
+            Self::Deps(_) => "deps",
+            Self::Detach(_) => "detach",
+            Self::Detect(_) => "detect",
+            Self::Edit(_) => "edit",
+            Self::Env(args) if args.write => "env",
+            Self::ForkSetup(_) => "fork-setup",
+            Self::Home(_) => "home",
+            Self::Import(_) => "import",
+            Self::Ipc(_) => "ipc",
+            Self::Jump(args) if !args.list => "jump",
+            Self::Label(_) => "label",
+            Self::Login(_) => "login",
+            Self::Make(_) => "make",
+            Self::Merge(_) => "merge",
+            Self::Mirror(_) => "mirror",
+            Self::Mv(_) => "mv",
+            Self::Notification(args) if args.mark_read => "notification",
+            Self::Pin(_) => "pin",
+            Self::Profile(args) if args.reset || args.name.is_some() => "profile",
+            Self::Push(_) => "push",
+            Self::Rebase(_) => "rebase",
+            Self::Recent(args) if args.jump => "recent",
+            Self::Recover(_) => "recover",
+            Self::Remove(_) => "remove",
+            Self::Reset(_) => "reset",
+            Self::Run(_) => "run",
+            Self::Secret(_) => "secret",
+            Self::Snapshot(_) => "snapshot",
+            Self::Squash(_) => "squash",
+            Self::Stash(_) => "stash",
+            Self::Stats(args) if args.save || args.delete.is_some() => "stats",
+            Self::Sync(_) => "sync",
+            Self::Tag(_) => "tag",
+            Self::Tmux(_) => "tmux",
+            Self::Unpin(_) => "unpin",
+            Self::Unthin(_) => "unthin",
+            Self::Update(_) => "update",
+            Self::Watch(_) => "watch",
+
+            Self::Action(_)
+            | Self::Audit(_)
+            | Self::Changelog(_)
+            | Self::Complete(_)
+            | Self::Diagnose(_)
+            | Self::Display(_)
+            | Self::Env(_)
+            | Self::Get(_)
+            | Self::Info(_)
+            | Self::Init(_)
+            | Self::Jump(_)
+            | Self::Log(_)
+            | Self::Notification(_)
+            | Self::Open(_)
+            | Self::Owner(_)
+            | Self::Profile(_)
+            | Self::Prompt(_)
+            | Self::Recent(_)
+            | Self::Serve(_)
+            | Self::Stats(_)
+            | Self::Which(_) => return None,
+        })
+    }
+
     pub fn get_completions() -> HashMap<&'static str, Completion> {
         hashmap![
             "attach" => attach::AttachArgs::completion(),
+            "audit" => audit::AuditArgs::completion(),
+            "backup" => backup::BackupArgs::completion(),
             "branch" => branch::BranchArgs::completion(),
+            "changelog" => changelog::ChangelogArgs::completion(),
+            "check" => check::CheckArgs::completion(),
+            "commit" => commit::CommitArgs::completion(),
             "config" => config::ConfigArgs::completion(),
+            "container" => container::ContainerArgs::completion(),
             "copy" => copy::CopyArgs::completion(),
+            "daemon" => daemon::DaemonArgs::completion(),
+            "deps" => deps::DepsArgs::completion(),
             "detect" => detect::DetectArgs::completion(),
             "diagnose" => diagnose::DiagnoseArgs::completion(),
+            "edit" => edit::EditArgs::completion(),
+            "env" => env::EnvArgs::completion(),
+            "fork-setup" => fork_setup::ForkSetupArgs::completion(),
             "get" => get::GetArgs::completion(),
             "home" => home::HomeArgs::completion(),
             "import" => import::ImportArgs::completion(),
             "init" => init::InitArgs::completion(),
+            "ipc" => ipc::IpcArgs::completion(),
+            "jump" => jump::JumpArgs::completion(),
             "label" => label::LabelArgs::completion(),
+            "log" => log::LogArgs::completion(),
+            "login" => login::LoginArgs::completion(),
             "make" => make::MakeArgs::completion(),
             "merge" => merge::MergeArgs::completion(),
+            "mirror" => mirror::MirrorArgs::completion(),
+            "mv" => mv::MvArgs::completion(),
+            "notification" => notification::NotificationArgs::completion(),
+            "owner" => owner::OwnerArgs::completion(),
+            "pin" => pin::PinArgs::completion(),
+            "profile" => profile::ProfileArgs::completion(),
+            "prompt" => prompt::PromptArgs::completion(),
             "rebase" => rebase::RebaseArgs::completion(),
+            "recent" => recent::RecentArgs::completion(),
             "remove" => remove::RemoveArgs::completion(),
             "reset" => reset::ResetArgs::completion(),
             "run" => run::RunArgs::completion(),
             "secret" => secret::SecretArgs::completion(),
+            "serve" => serve::ServeArgs::completion(),
             "snapshot" => snapshot::SnapshotArgs::completion(),
             "squash" => squash::SquashArgs::completion(),
+            "stash" => stash::StashArgs::completion(),
             "stats" => stats::StatsArgs::completion(),
             "sync" => sync::SyncArgs::completion(),
-            "tag" => tag::TagArgs::completion()
+            "tag" => tag::TagArgs::completion(),
+            "tmux" => tmux::TmuxArgs::completion(),
+            "unpin" => unpin::UnpinArgs::completion(),
+            "unthin" => unthin::UnthinArgs::completion(),
+            "watch" => watch::WatchArgs::completion(),
+            "which" => which::WhichArgs::completion()
         ]
     }
 }
@@ -124,37 +322,68 @@ impl Run for App {
         match &self.command {
             Commands::Action(args) => args.run(cfg),
             Commands::Attach(args) => args.run(cfg),
+            Commands::Audit(args) => args.run(cfg),
+            Commands::Backup(args) => args.run(cfg),
             Commands::Branch(args) => args.run(cfg),
+            Commands::Changelog(args) => args.run(cfg),
             Commands::Check(args) => args.run(cfg),
             Commands::Clean(args) => args.run(cfg),
+            Commands::Commit(args) => args.run(cfg),
             Commands::Complete(args) => args.run(cfg),
             Commands::Config(args) => args.run(cfg),
+            Commands::Container(args) => args.run(cfg),
             Commands::Copy(args) => args.run(cfg),
+            Commands::Daemon(args) => args.run(cfg),
+            Commands::Debug(args) => args.run(cfg),
+            Commands::Deps(args) => args.run(cfg),
             Commands::Detach(args) => args.run(cfg),
             Commands::Detect(args) => args.run(cfg),
             Commands::Diagnose(args) => args.run(cfg),
             Commands::Display(args) => args.run(cfg),
+            Commands::Edit(args) => args.run(cfg),
+            Commands::Env(args) => args.run(cfg),
+            Commands::ForkSetup(args) => args.run(cfg),
             Commands::Get(args) => args.run(cfg),
             Commands::Home(args) => args.run(cfg),
             Commands::Import(args) => args.run(cfg),
             Commands::Info(args) => args.run(cfg),
             Commands::Init(args) => args.run(cfg),
+            Commands::Ipc(args) => args.run(cfg),
+            Commands::Jump(args) => args.run(cfg),
             Commands::Label(args) => args.run(cfg),
+            Commands::Log(args) => args.run(cfg),
+            Commands::Login(args) => args.run(cfg),
             Commands::Make(args) => args.run(cfg),
             Commands::Merge(args) => args.run(cfg),
+            Commands::Mirror(args) => args.run(cfg),
+            Commands::Mv(args) => args.run(cfg),
+            Commands::Notification(args) => args.run(cfg),
             Commands::Open(args) => args.run(cfg),
+            Commands::Owner(args) => args.run(cfg),
+            Commands::Pin(args) => args.run(cfg),
+            Commands::Profile(args) => args.run(cfg),
+            Commands::Prompt(args) => args.run(cfg),
+            Commands::Push(args) => args.run(cfg),
             Commands::Rebase(args) => args.run(cfg),
+            Commands::Recent(args) => args.run(cfg),
             Commands::Recover(args) => args.run(cfg),
             Commands::Remove(args) => args.run(cfg),
             Commands::Reset(args) => args.run(cfg),
             Commands::Run(args) => args.run(cfg),
             Commands::Secret(args) => args.run(cfg),
+            Commands::Serve(args) => args.run(cfg),
             Commands::Snapshot(args) => args.run(cfg),
             Commands::Squash(args) => args.run(cfg),
+            Commands::Stash(args) => args.run(cfg),
             Commands::Stats(args) => args.run(cfg),
             Commands::Sync(args) => args.run(cfg),
             Commands::Tag(args) => args.run(cfg),
+            Commands::Tmux(args) => args.run(cfg),
+            Commands::Unpin(args) => args.run(cfg),
+            Commands::Unthin(args) => args.run(cfg),
             Commands::Update(args) => args.run(cfg),
+            Commands::Watch(args) => args.run(cfg),
+            Commands::Which(args) => args.run(cfg),
         }
     }
 }
@@ -240,13 +469,25 @@ impl Completion {
                 Self::wrap_with_keywords(cfg, "", to_complete, remotes, false)
             }
             2 => {
-                let db = Database::load(cfg)?;
+                let db = Database::load_readonly(cfg)?;
 
                 let remote = &args[0];
                 let query = &args[1];
 
                 if !query.contains('/') {
-                    let owners = db.list_owners(remote);
+                    let mut owners = db.list_owners(remote);
+                    // Append the owners listed by the `owner` command, from the
+                    // remote api cache only.
+                    if let Some(remote_cfg) = cfg.get_remote(remote) {
+                        if remote_cfg.complete_cache {
+                            let cached = api::list_cached_owners(cfg, &remote_cfg)?;
+                            for owner in cached.unwrap_or_default() {
+                                if !owners.contains(&owner) {
+                                    owners.push(owner);
+                                }
+                            }
+                        }
+                    }
                     let items: Vec<_> = owners
                         .into_iter()
                         .map(|owner| format!("{}/", owner))
@@ -256,17 +497,58 @@ impl Completion {
 
                 let (owner, _) = database::parse_owner(query);
                 let repos = db.list_by_remote(remote, &None);
-                let items: Vec<_> = repos
+                let mut items: Vec<_> = repos
                     .into_iter()
                     .filter(|repo| repo.owner.as_ref() == owner.as_str())
                     .map(|repo| repo.name_with_owner())
                     .collect();
+
+                // Append the repositories that are not cloned yet, from the
+                // remote api cache only.
+                if let Some(remote_cfg) = cfg.get_remote(remote) {
+                    if remote_cfg.complete_cache {
+                        let cached = api::list_cached_repos(cfg, &remote_cfg, &owner)?;
+                        for name in cached.unwrap_or_default() {
+                            let item = format!("{owner}/{name}");
+                            if !items.contains(&item) {
+                                items.push(item);
+                            }
+                        }
+                    }
+                }
                 Ok(CompletionResult::from(items))
             }
             _ => Ok(CompletionResult::empty()),
         }
     }
 
+    /// Complete the base branches, including the remote-tracking branches of
+    /// `origin` that are not checked out. The remote branches are read from the
+    /// local refs, so this never fetches.
+    pub fn base_branch_args(cfg: &Config, args: &[&str]) -> Result<CompletionResult> {
+        if args.len() > 1 {
+            return Ok(CompletionResult::empty());
+        }
+        let mut items = Self::branch_args(cfg, args)?.items;
+        if let Ok(remote_branches) = GitBranch::list_remote("origin") {
+            items.extend(remote_branches);
+        }
+        Ok(CompletionResult::from(items))
+    }
+
+    /// Complete the owners of all the remotes in the database.
+    pub fn owners_flag(cfg: &Config) -> Result<Option<CompletionResult>> {
+        let db = Database::load_readonly(cfg)?;
+        let mut items: Vec<_> = cfg
+            .list_remotes()
+            .into_iter()
+            .flat_map(|remote| db.list_owners(remote))
+            .collect();
+        items.sort_unstable();
+        items.dedup();
+        Ok(Some(CompletionResult::from(items)))
+    }
+
     pub fn files(_: &Config, _: &[&str]) -> Result<CompletionResult> {
         Ok(CompletionResult::files())
     }
@@ -306,7 +588,7 @@ impl Completion {
         // Return the matched keywords and repository names as the completion items.
         let keywords = Keywords::load(cfg)?;
         let mut keywords = keywords.complete(remote);
-        let db = Database::load(cfg)?;
+        let db = Database::load_readonly(cfg)?;
         let names: Vec<_> = if !remote.is_empty() {
             db.list_by_remote(remote, &None)
         } else {
@@ -349,7 +631,7 @@ impl Completion {
             }
             2 => {
                 let remote = &args[0];
-                let db = Database::load(cfg)?;
+                let db = Database::load_readonly(cfg)?;
                 let owners = db.list_owners(remote);
                 let items: Vec<_> = owners
                     .into_iter()
@@ -390,7 +672,7 @@ impl Completion {
             }
         }
 
-        let db = Database::load(cfg)?;
+        let db = Database::load_readonly(cfg)?;
         let repos = db.list_all(&None);
         for repo in repos {
             if let Some(labels) = repo.labels.as_ref() {
@@ -419,7 +701,7 @@ impl Completion {
                 items.push(item);
             }
         } else {
-            let last_to_complete = to_complete.split(',').last();
+            let last_to_complete = to_complete.split(',').next_back();
             match last_to_complete {
                 Some(last) => {
                     for item in candidates {
@@ -452,6 +734,23 @@ impl Completion {
     }
 }
 
+/// Warn if the current repo is a thin (shallow) clone, since the commits history
+/// might be incomplete for some git operations.
+pub fn warn_thin_repo(cfg: &Config) -> Result<()> {
+    let db = Database::load_readonly(cfg)?;
+    if let Some(repo) = db.get_current() {
+        if let Some(labels) = repo.labels.as_ref() {
+            if labels.contains("thin") {
+                warn!(
+                    "Repo {} is a thin clone, the history might be incomplete, use `unthin` to fetch the full history",
+                    repo.name_with_remote()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn get_git_remote(cfg: &Config, upstream: bool, force: bool) -> Result<GitRemote> {
     git::ensure_no_uncommitted()?;
     if upstream {
@@ -464,3 +763,31 @@ pub fn get_git_remote(cfg: &Config, upstream: bool, force: bool) -> Result<GitRe
         Ok(GitRemote::new())
     }
 }
+
+#[cfg(test)]
+mod cmd_tests {
+    use crate::cmd::*;
+
+    #[test]
+    fn test_audit_name() {
+        let cases = [
+            (vec!["home"], Some("home")),
+            (vec!["check"], Some("check")),
+            (vec!["watch"], Some("watch")),
+            (vec!["login", "github"], Some("login")),
+            (vec!["merge", "--accept"], Some("merge")),
+            (vec!["env", "--write"], Some("env")),
+            (vec!["env"], None),
+            (vec!["profile"], None),
+            (vec!["profile", "work"], Some("profile")),
+            (vec!["daemon", "install"], Some("daemon")),
+            (vec!["daemon", "status"], None),
+            (vec!["jump", "--list"], None),
+            (vec!["get"], None),
+        ];
+        for (args, expect) in cases {
+            let app = App::parse_from(["roxide"].into_iter().chain(args.iter().copied()));
+            assert_eq!(app.command.audit_name(), expect, "{args:?}");
+        }
+    }
+}
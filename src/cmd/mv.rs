@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+use std::fs;
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+use crate::api;
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::errors::ErrorKind;
+use crate::exec::Cmd;
+use crate::repo::database::{self, Database, SelectOptions, Selector};
+use crate::repo::deps::Dependencies;
+use crate::repo::env::EnvFile;
+use crate::repo::mirror::Mirrors;
+use crate::repo::snapshot::Snapshot;
+use crate::repo::{usage, Repo};
+use crate::{confirm, info, term, utils};
+
+/// Move (rename) a repository, including its directory, database record and
+/// origin url.
+#[derive(Args)]
+pub struct MvArgs {
+    /// The repository to move.
+    pub repo: String,
+
+    /// The new name, in the format `{owner}/{name}`. If the owner is omitted,
+    /// keep the current one.
+    pub target: String,
+
+    /// Also rename the repository in the remote, via the remote API. The owner
+    /// cannot be changed in this mode.
+    #[clap(short, long)]
+    pub remote: bool,
+}
+
+impl Run for MvArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let mut db = Database::load(cfg)?;
+
+        let head = Some(self.repo.clone());
+        let opts = SelectOptions::default().with_force_local(true);
+        let repo = Selector::from_args(&head, &None, opts).must_one(&db)?;
+
+        let (owner, name) = parse_target(&repo, &self.target);
+        if owner == repo.owner && name == repo.name {
+            bail!("the target is the same as the repo");
+        }
+        if db.get(repo.remote.as_ref(), &owner, &name).is_some() {
+            return Err(ErrorKind::Conflict.error(format!(
+                "the repo '{}:{owner}/{name}' already exists",
+                repo.remote
+            )));
+        }
+        if self.remote && owner != repo.owner {
+            bail!("could not change the owner in the remote");
+        }
+
+        let old_path = repo.get_path(cfg);
+        let mut new_repo = repo.clone();
+        new_repo.owner = Cow::Owned(owner);
+        new_repo.name = Cow::Owned(name);
+        let new_path = new_repo.get_path(cfg);
+        let move_dir = old_path != new_path && old_path.exists();
+        if move_dir && new_path.exists() {
+            return Err(ErrorKind::Conflict.error(format!(
+                "the target path '{}' already exists",
+                new_path.display()
+            )));
+        }
+
+        if repo.is_protected() {
+            term::must_confirm_protected(cfg, &repo.name)?;
+        }
+        confirm!(
+            "Do you want to move {} to {}",
+            repo.name_with_remote(),
+            new_repo.name_with_remote()
+        );
+
+        // Move the local directory before renaming the remote, so that the
+        // local failure does not leave the remote renamed. And if the remote
+        // renaming fails, move the directory back.
+        if move_dir {
+            utils::ensure_dir(&new_path)?;
+            fs::rename(&old_path, &new_path).with_context(|| {
+                format!("move '{}' to '{}'", old_path.display(), new_path.display())
+            })?;
+            info!("Move '{}' to '{}'", old_path.display(), new_path.display());
+        }
+
+        if self.remote {
+            info!("Rename remote repo to {}", new_repo.name);
+            let result =
+                api::build_provider(cfg, &repo.remote_cfg, false).and_then(|mut provider| {
+                    provider.rename_repo(&repo.owner, &repo.name, &new_repo.name)
+                });
+            if let Err(err) = result {
+                if move_dir {
+                    fs::rename(&new_path, &old_path).with_context(|| {
+                        format!(
+                            "move '{}' back to '{}'",
+                            new_path.display(),
+                            old_path.display()
+                        )
+                    })?;
+                    if let Some(dir) = new_path.parent() {
+                        utils::remove_empty_dirs(dir, false)?;
+                    }
+                }
+                return Err(err);
+            }
+        }
+
+        if move_dir {
+            if let Some(dir) = old_path.parent() {
+                utils::remove_empty_dirs(dir, true)?;
+            }
+        }
+
+        if new_path.exists() {
+            if new_repo.remote_cfg.clone.is_some() {
+                let url = new_repo.clone_url();
+                let path = format!("{}", new_path.display());
+                Cmd::git(&[
+                    "-C",
+                    path.as_str(),
+                    "remote",
+                    "set-url",
+                    "origin",
+                    url.as_str(),
+                ])
+                .with_display(format!("Set remote origin url to '{url}'"))
+                .execute()?;
+            }
+            if let Some(env_file) = EnvFile::build(cfg, &new_repo) {
+                info!("Write env file '{}'", env_file.name);
+                env_file.write(&new_path)?;
+            }
+        }
+
+        migrate_meta(cfg, &repo, &new_repo)?;
+
+        let new_repo = new_repo.update();
+        db.remove(repo.update());
+        db.upsert(new_repo);
+        db.save()
+    }
+}
+
+/// Migrate the metadata keyed by the repo name to the new name, including the
+/// dependencies, push mirrors, usage cache and snapshots.
+fn migrate_meta(cfg: &Config, repo: &Repo, new_repo: &Repo) -> Result<()> {
+    let name = repo.name_with_remote();
+    let new_name = new_repo.name_with_remote();
+
+    let mut deps = Dependencies::load(cfg)?;
+    deps.rename(&name, &new_name);
+    deps.save()?;
+
+    let mut mirrors = Mirrors::load(cfg)?;
+    mirrors.rename(&name, &new_name);
+    mirrors.save()?;
+
+    usage::move_cache(cfg, repo, new_repo)?;
+
+    for snapshot_name in Snapshot::list(cfg)? {
+        let mut snapshot = Snapshot::load(cfg, snapshot_name)?;
+        if snapshot.rename_repo(&name, &new_name) {
+            info!("Update snapshot '{}'", snapshot.name);
+            snapshot.save(false)?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse the target `{owner}/{name}` or `{name}`, the owner can contain `/`
+/// (such as GitLab groups).
+fn parse_target(repo: &Repo, target: &str) -> (String, String) {
+    let target = target.trim_matches('/');
+    if !target.contains('/') {
+        return (repo.owner.to_string(), target.to_string());
+    }
+    database::parse_owner(target)
+}
+
+impl MvArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: Self::complete_args,
+            flags: None,
+        }
+    }
+
+    fn complete_args(cfg: &Config, args: &[&str]) -> Result<CompletionResult> {
+        match args.len() {
+            0 | 1 => {
+                let db = Database::load_readonly(cfg)?;
+                let mut items: Vec<_> = db
+                    .list_all(&None)
+                    .into_iter()
+                    .map(|repo| repo.name.to_string())
+                    .collect();
+                items.sort_unstable();
+                items.dedup();
+                Ok(CompletionResult::from(items))
+            }
+            _ => Ok(CompletionResult::empty()),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{bail, Result};
+use chrono::DateTime;
+use clap::Args;
+
+use crate::api::{self, Notification};
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::repo::database::Database;
+use crate::table::{Table, TableArgs, TableCell, TableCellColor};
+use crate::{exec, info, term, utils};
+
+/// Show the notifications (GitLab todos) of remote.
+#[derive(Args)]
+pub struct NotificationArgs {
+    /// The remote name, default will use the remote of current repo.
+    pub remote: Option<String>,
+
+    /// Show all notifications, including the read ones.
+    #[clap(short, long)]
+    pub all: bool,
+
+    /// Select a notification and open it in default browser, it will be marked as
+    /// read.
+    #[clap(short, long)]
+    pub open: bool,
+
+    /// Mark all the listed notifications as read.
+    #[clap(short, long)]
+    pub mark_read: bool,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
+}
+
+impl Run for NotificationArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let remote = match self.remote.as_ref() {
+            Some(remote) => remote.clone(),
+            None => {
+                let db = Database::load(cfg)?;
+                match db.get_current() {
+                    Some(repo) => repo.remote.to_string(),
+                    None => bail!("you are not in a repo, please specify the remote"),
+                }
+            }
+        };
+        let remote_cfg = cfg.must_get_remote(&remote)?;
+        if remote_cfg.provider.is_none() {
+            bail!("missing provider config for remote '{remote}'");
+        }
+
+        let mut provider = api::build_raw_provider(cfg, &remote_cfg)?;
+        info!("Get notifications from remote API");
+        let notifications = provider.list_notifications(self.all)?;
+        if notifications.is_empty() {
+            eprintln!("No notification");
+            return Ok(());
+        }
+
+        if self.open {
+            let items: Vec<String> = notifications
+                .iter()
+                .map(|notification| format!("{}: {}", notification.repo, notification.title))
+                .collect();
+            let idx = exec::fzf_search(&items)?;
+            let notification = &notifications[idx];
+            utils::open_url(&notification.url)?;
+            if notification.unread {
+                provider.mark_notification_read(&notification.id)?;
+            }
+            return Ok(());
+        }
+
+        if self.mark_read {
+            let unread: Vec<&Notification> = notifications
+                .iter()
+                .filter(|notification| notification.unread)
+                .collect();
+            if unread.is_empty() {
+                eprintln!("No unread notification");
+                return Ok(());
+            }
+            let items: Vec<String> = unread
+                .iter()
+                .map(|notification| format!("{}: {}", notification.repo, notification.title))
+                .collect();
+            term::must_confirm_items(
+                &items,
+                "mark as read",
+                "marking",
+                "Notification",
+                "Notifications",
+            )?;
+            for notification in unread {
+                provider.mark_notification_read(&notification.id)?;
+            }
+            return Ok(());
+        }
+
+        self.show(cfg, notifications)
+    }
+}
+
+impl NotificationArgs {
+    fn show(&self, cfg: &Config, notifications: Vec<Notification>) -> Result<()> {
+        let mut table = Table::with_capacity(notifications.len() + 1);
+        table.add(vec![
+            String::from("Repo"),
+            String::from("Type"),
+            String::from("Title"),
+            String::from("Reason"),
+            String::from("Updated"),
+            String::from("Url"),
+        ]);
+        for notification in notifications {
+            let updated = match DateTime::parse_from_rfc3339(&notification.updated_at) {
+                Ok(time) => {
+                    let time = time.timestamp() as u64;
+                    TableCell::no_color(utils::format_since(cfg, time)).with_sort_key(time)
+                }
+                Err(_) => TableCell::no_color(notification.updated_at),
+            };
+            let title = if notification.unread {
+                TableCell::with_color(notification.title, TableCellColor::Yellow)
+            } else {
+                TableCell::no_color(notification.title)
+            };
+            table.add_color(vec![
+                TableCell::no_color(notification.repo),
+                TableCell::no_color(notification.kind),
+                title,
+                TableCell::no_color(notification.reason),
+                updated,
+                TableCell::no_color(notification.url),
+            ]);
+        }
+        table.show_with(&self.table, &["repo", "type", "title", "reason", "updated"])
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |cfg, args| match args.len() {
+                0 | 1 => Ok(CompletionResult::from(cfg.list_remotes())),
+                _ => Ok(CompletionResult::empty()),
+            },
+            flags: None,
+        }
+    }
+}
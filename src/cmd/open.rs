@@ -1,24 +1,38 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::Args;
 
 use crate::cmd::Run;
-use crate::config::Config;
+use crate::config::{Config, ProviderType};
+use crate::exec::Cmd;
 use crate::git::GitBranch;
 use crate::repo::database::Database;
-use crate::{api, utils};
+use crate::{api, clipboard};
 
 /// Open current repository in default browser
 #[derive(Args)]
 pub struct OpenArgs {
+    /// Open the file in current repository, in format `path[:line[-end]]`, the
+    /// path is relative to current directory.
+    pub file: Option<String>,
+
     /// Open current branch
     #[clap(short, long)]
     pub branch: bool,
 
+    /// When opening file, use current commit instead of branch, so that the
+    /// link will not change as the branch moves.
+    #[clap(short, long)]
+    pub commit: bool,
+
     /// When calling the remote API, ignore caches that are not expired.
     #[clap(short, long)]
     pub force: bool,
+
+    /// Copy the url to clipboard rather than opening it in browser.
+    #[clap(long)]
+    pub copy: bool,
 }
 
 impl Run for OpenArgs {
@@ -31,12 +45,168 @@ impl Run for OpenArgs {
         let api_repo = provider.get_repo(&repo.owner, &repo.name)?;
         let mut url = api_repo.web_url;
 
-        if self.branch {
+        if let Some(file) = self.file.as_ref() {
+            let provider_type = repo.remote_cfg.provider.as_ref().unwrap();
+            url = self.file_url(cfg, provider_type, &url, file)?;
+        } else if self.branch {
             let branch = GitBranch::current(false)?;
             let path = PathBuf::from(url).join("tree").join(branch);
             url = format!("{}", path.display());
         }
 
-        utils::open_url(&url)
+        clipboard::open_or_copy(&url, self.copy)
+    }
+}
+
+impl OpenArgs {
+    fn file_url(
+        &self,
+        cfg: &Config,
+        provider: &ProviderType,
+        web_url: &str,
+        file: &str,
+    ) -> Result<String> {
+        let (path, lines) = parse_file_arg(file)?;
+        let path = cfg.get_current_dir().join(path);
+        if !path.exists() {
+            bail!("file '{}' does not exist", path.display());
+        }
+
+        let root = Cmd::git(&["rev-parse", "--show-toplevel"]).read()?;
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("canonicalize path '{}'", path.display()))?;
+        let rel_path = match path.strip_prefix(Path::new(&root)) {
+            Ok(rel_path) => rel_path,
+            Err(_) => bail!("file '{}' is not in current repository", path.display()),
+        };
+        let rel_path = format!("{}", rel_path.display());
+
+        let rev = if self.commit {
+            Cmd::git(&["rev-parse", "HEAD"]).read()?
+        } else {
+            let branch = GitBranch::current(true)?;
+            if branch.is_empty() {
+                // The HEAD is detached, fallback to the commit.
+                Cmd::git(&["rev-parse", "HEAD"]).read()?
+            } else {
+                branch
+            }
+        };
+
+        Ok(build_file_url(provider, web_url, &rev, &rel_path, lines))
+    }
+}
+
+/// The line range of file, `(start, end)`.
+type LineRange = (u32, Option<u32>);
+
+/// Parse `path[:line[-end]]` to the path and line range.
+fn parse_file_arg(file: &str) -> Result<(&str, Option<LineRange>)> {
+    let (path, lines) = match file.rsplit_once(':') {
+        Some((path, lines))
+            if !lines.is_empty() && lines.chars().all(|c| c.is_ascii_digit() || c == '-') =>
+        {
+            (path, lines)
+        }
+        _ => return Ok((file, None)),
+    };
+    let (start, end) = match lines.split_once('-') {
+        Some((start, end)) => (start, Some(end)),
+        None => (lines, None),
+    };
+    let start: u32 = match start.parse() {
+        Ok(start) => start,
+        Err(_) => bail!("invalid line '{start}' in '{file}'"),
+    };
+    let end: Option<u32> = match end {
+        Some(end) => match end.parse() {
+            Ok(end) if end >= start => Some(end),
+            _ => bail!("invalid line range '{lines}' in '{file}'"),
+        },
+        None => None,
+    };
+    Ok((path, Some((start, end))))
+}
+
+/// Build the web url of the file, the line anchor format is different between
+/// providers:
+///
+/// * GitHub: `{web_url}/blob/{rev}/{path}#L{start}-L{end}`
+/// * GitLab: `{web_url}/-/blob/{rev}/{path}#L{start}-{end}`
+fn build_file_url(
+    provider: &ProviderType,
+    web_url: &str,
+    rev: &str,
+    path: &str,
+    lines: Option<LineRange>,
+) -> String {
+    let web_url = web_url.trim_end_matches('/');
+    let (mut url, end_prefix) = match provider {
+        ProviderType::Github => (format!("{web_url}/blob/{rev}/{path}"), "L"),
+        ProviderType::Gitlab => (format!("{web_url}/-/blob/{rev}/{path}"), ""),
+    };
+    if let Some((start, end)) = lines {
+        url.push_str(&format!("#L{start}"));
+        if let Some(end) = end {
+            if end != start {
+                url.push_str(&format!("-{end_prefix}{end}"));
+            }
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod open_tests {
+    use crate::cmd::open::*;
+
+    #[test]
+    fn test_file_url() {
+        assert_eq!(
+            parse_file_arg("src/main.rs").unwrap(),
+            ("src/main.rs", None)
+        );
+        assert_eq!(
+            parse_file_arg("src/main.rs:10").unwrap(),
+            ("src/main.rs", Some((10, None)))
+        );
+        assert_eq!(
+            parse_file_arg("src/main.rs:10-20").unwrap(),
+            ("src/main.rs", Some((10, Some(20))))
+        );
+        assert_eq!(parse_file_arg("a:b.rs").unwrap(), ("a:b.rs", None));
+        assert!(parse_file_arg("src/main.rs:20-10").is_err());
+
+        assert_eq!(
+            build_file_url(
+                &ProviderType::Github,
+                "https://github.com/fioncat/roxide",
+                "main",
+                "src/main.rs",
+                Some((10, Some(20)))
+            ),
+            "https://github.com/fioncat/roxide/blob/main/src/main.rs#L10-L20"
+        );
+        assert_eq!(
+            build_file_url(
+                &ProviderType::Gitlab,
+                "https://gitlab.com/test/repo/",
+                "dev",
+                "README.md",
+                Some((3, None))
+            ),
+            "https://gitlab.com/test/repo/-/blob/dev/README.md#L3"
+        );
+        assert_eq!(
+            build_file_url(
+                &ProviderType::Gitlab,
+                "https://gitlab.com/test/repo",
+                "dev",
+                "README.md",
+                Some((3, Some(5)))
+            ),
+            "https://gitlab.com/test/repo/-/blob/dev/README.md#L3-5"
+        );
     }
 }
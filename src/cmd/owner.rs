@@ -0,0 +1,106 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use serde::Serialize;
+
+use crate::api;
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::repo::database::Database;
+use crate::table::{Table, TableArgs, TableCell, TableCellColor};
+use crate::{info, term};
+
+/// List the owners accessible on the remote.
+///
+/// Such as the groups and subgroups on GitLab, or the user and its
+/// organizations on GitHub. The result is cached like the repositories, see
+/// `cache_hours` in remote config.
+#[derive(Args)]
+pub struct OwnerArgs {
+    /// The remote name, default will use the remote of current repo.
+    pub remote: Option<String>,
+
+    /// Only show the owners that contain this.
+    #[clap(short, long)]
+    pub query: Option<String>,
+
+    /// Force to refresh the cache.
+    #[clap(short, long)]
+    pub force: bool,
+
+    /// Show result as json format.
+    #[clap(short = 'J')]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
+}
+
+#[derive(Debug, Serialize)]
+struct OwnerItem {
+    name: String,
+    /// The count of the cloned repositories under this owner.
+    cloned: usize,
+}
+
+impl Run for OwnerArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let remote = match self.remote.as_ref() {
+            Some(remote) => remote.clone(),
+            None => match db.get_current() {
+                Some(repo) => repo.remote.to_string(),
+                None => bail!("you are not in a repo, please specify the remote"),
+            },
+        };
+        let remote_cfg = cfg.must_get_remote(&remote)?;
+
+        let provider = api::build_provider(cfg, &remote_cfg, self.force)?;
+        info!("List owners from remote API");
+        let owners: Vec<OwnerItem> = provider
+            .list_owners()?
+            .into_iter()
+            .filter(|owner| match self.query.as_ref() {
+                Some(query) => owner.contains(query.as_str()),
+                None => true,
+            })
+            .map(|owner| {
+                let cloned = db.list_by_owner(&remote, &owner, &None).len();
+                OwnerItem {
+                    name: owner,
+                    cloned,
+                }
+            })
+            .collect();
+        if self.json {
+            return term::show_json(&owners);
+        }
+        if owners.is_empty() {
+            eprintln!("No owner");
+            return Ok(());
+        }
+
+        let mut table = Table::with_capacity(owners.len() + 1);
+        table.add(vec![String::from("Owner"), String::from("Cloned")]);
+        for owner in owners {
+            let name = if owner.cloned > 0 {
+                TableCell::with_color(owner.name, TableCellColor::Green)
+            } else {
+                TableCell::no_color(owner.name)
+            };
+            table.add_color(vec![name, TableCell::no_color(format!("{}", owner.cloned))]);
+        }
+        table.show_with(&self.table, &["owner", "cloned"])
+    }
+}
+
+impl OwnerArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: |cfg, args| match args.len() {
+                0 | 1 => Ok(CompletionResult::from(cfg.list_remotes())),
+                _ => Ok(CompletionResult::empty()),
+            },
+            flags: None,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::Result;
+use clap::Args;
+
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::info;
+use crate::repo::database::{Database, SelectOptions, Selector};
+
+/// Pin repository, the pinned repositories will be listed first.
+#[derive(Args)]
+pub struct PinArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// The pin priority, repositories with higher priority will be listed first.
+    #[clap(short, long, default_value = "0")]
+    pub priority: u32,
+
+    /// Use search instead of fuzzy matching.
+    #[clap(short, long)]
+    pub search: bool,
+}
+
+impl Run for PinArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let mut db = Database::load(cfg)?;
+
+        let opts = SelectOptions::default()
+            .with_force_search(self.search)
+            .with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let mut repo = selector.must_one(&db)?;
+
+        repo.set_pin_priority(Some(self.priority));
+        info!(
+            "Pin {} with priority {}",
+            repo.name_with_remote(),
+            self.priority
+        );
+
+        db.upsert(repo.update());
+        db.save()
+    }
+}
+
+impl PinArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: None,
+        }
+    }
+}
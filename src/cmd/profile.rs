@@ -0,0 +1,91 @@
+use anyhow::Result;
+use clap::Args;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::info;
+use crate::table::{Table, TableCell, TableCellColor};
+
+/// Manage workspace profiles.
+///
+/// Without any argument, list the profiles, the active one is highlighted.
+#[derive(Args)]
+pub struct ProfileArgs {
+    /// Switch the current profile. It can be overridden by the `ROXIDE_PROFILE`
+    /// env or the `--profile` flag.
+    pub name: Option<String>,
+
+    /// Switch back to use no profile.
+    #[clap(short, long)]
+    pub reset: bool,
+}
+
+impl Run for ProfileArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        if self.reset {
+            cfg.switch_profile(None)?;
+            info!("Switch to use no profile");
+            return Ok(());
+        }
+
+        if let Some(name) = self.name.as_ref() {
+            cfg.switch_profile(Some(name))?;
+            info!("Switch to profile {}", name);
+            return Ok(());
+        }
+
+        if cfg.profiles.is_empty() {
+            eprintln!("No profile");
+            return Ok(());
+        }
+
+        let mut names: Vec<_> = cfg.profiles.keys().collect();
+        names.sort_unstable();
+
+        let mut table = Table::with_capacity(names.len() + 1);
+        table.add(vec![
+            String::from("Name"),
+            String::from("Workspace"),
+            String::from("Metadir"),
+            String::from("Remotes"),
+        ]);
+        for name in names {
+            let profile = cfg.profiles.get(name).unwrap();
+            let name_cell = if cfg.profile.as_ref() == Some(name) {
+                TableCell::with_color(format!("* {name}"), TableCellColor::Green)
+            } else {
+                TableCell::no_color(name.clone())
+            };
+            let remotes = if profile.remotes.is_empty() {
+                String::from("<all>")
+            } else {
+                profile.remotes.join(",")
+            };
+            table.add_color(vec![
+                name_cell,
+                TableCell::no_color(profile.workspace.clone()),
+                TableCell::no_color(profile.metadir.clone().unwrap_or_default()),
+                TableCell::no_color(remotes),
+            ]);
+        }
+        table.show();
+
+        Ok(())
+    }
+}
+
+impl ProfileArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: |cfg, args| match args.len() {
+                0 | 1 => {
+                    let mut names: Vec<_> = cfg.profiles.keys().cloned().collect();
+                    names.sort_unstable();
+                    Ok(CompletionResult::from(names))
+                }
+                _ => Ok(CompletionResult::empty()),
+            },
+            flags: None,
+        }
+    }
+}
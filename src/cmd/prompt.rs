@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::git::GitStatus;
+use crate::repo::database::Database;
+
+/// Print the current repository status for shell prompts.
+///
+/// The fields are separated by spaces:
+/// `{remote}:{owner}/{name} {branch} {clean|dirty} {ahead} {behind}`, the
+/// branch is `HEAD` if detached. Print nothing if not in a repository.
+#[derive(Args)]
+pub struct PromptArgs {
+    /// Show result as json format, such as for the starship custom module.
+    #[clap(short = 'J')]
+    pub json: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PromptInfo<'a> {
+    repo: String,
+    remote: &'a str,
+    owner: &'a str,
+    name: &'a str,
+    branch: Option<String>,
+    dirty: bool,
+    ahead: u32,
+    behind: u32,
+}
+
+impl Run for PromptArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load_readonly(cfg)?;
+        let repo = match db.get_current() {
+            Some(repo) => repo,
+            None => return Ok(()),
+        };
+
+        // The prompt should never fail, a repository that is not a git
+        // directory (yet) just shows an empty status.
+        let path = format!("{}", repo.get_path(cfg).display());
+        let status = GitStatus::read(&path).unwrap_or_default();
+
+        let info = PromptInfo {
+            repo: repo.name_with_remote(),
+            remote: repo.remote.as_ref(),
+            owner: repo.owner.as_ref(),
+            name: repo.name.as_ref(),
+            branch: status.branch,
+            dirty: status.dirty,
+            ahead: status.ahead,
+            behind: status.behind,
+        };
+
+        if self.json {
+            // Keep it in one line, unlike `term::show_json`.
+            let json = serde_json::to_string(&info).context("encode json")?;
+            println!("{json}");
+            return Ok(());
+        }
+
+        println!(
+            "{} {} {} {} {}",
+            info.repo,
+            info.branch.as_deref().unwrap_or("HEAD"),
+            if info.dirty { "dirty" } else { "clean" },
+            info.ahead,
+            info.behind
+        );
+        Ok(())
+    }
+}
+
+impl PromptArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_cfg, _args| Ok(CompletionResult::empty()),
+            flags: None,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::api::MergeMethod;
+use crate::cmd::merge::MergeArgs;
+use crate::cmd::Run;
+use crate::config::Config;
+use crate::exec::Cmd;
+use crate::git::GitBranch;
+
+/// Push the current branch to origin, the upstream is set on the first push.
+#[derive(Args)]
+pub struct PushArgs {
+    /// Force push, but refuse if the remote branch has been updated by others
+    /// since the last fetch, that is `git push --force-with-lease`.
+    #[clap(short = 'F', long)]
+    pub force_with_lease: bool,
+
+    /// After pushing, create or open the MergeRequest (PullRequest for Github)
+    /// of the branch, see the `merge` command.
+    #[clap(short, long)]
+    pub merge: bool,
+
+    /// Upstream mode for `--merge`, only used for forked repo.
+    #[clap(short, long)]
+    pub upstream: bool,
+
+    /// Copy the merge url to clipboard rather than opening it in browser.
+    #[clap(long)]
+    pub copy: bool,
+}
+
+impl Run for PushArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let branch = GitBranch::current(false)?;
+        if branch.is_empty() {
+            bail!("HEAD is detached, please checkout a branch to push");
+        }
+
+        let has_upstream = Cmd::git(&["rev-parse", "--abbrev-ref", "@{upstream}"])
+            .read()
+            .is_ok();
+        let mut args = if has_upstream {
+            vec!["push"]
+        } else {
+            vec!["push", "--set-upstream", "origin", branch.as_str()]
+        };
+        if self.force_with_lease {
+            args.push("--force-with-lease");
+        }
+        Cmd::git(&args).with_display_cmd().execute()?;
+
+        if !self.merge {
+            return Ok(());
+        }
+        let merge = MergeArgs {
+            target: None,
+            upstream: self.upstream,
+            force: false,
+            accept: false,
+            method: MergeMethod::Merge,
+            approve: false,
+            wait_checks: false,
+            copy: self.copy,
+            draft: false,
+            reviewers: None,
+            labels: None,
+        };
+        merge.run(cfg)
+    }
+}
@@ -1,8 +1,13 @@
-use anyhow::Result;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{bail, Result};
 use clap::Args;
 
 use crate::cmd::{self, Completion, Run};
 use crate::config::Config;
+use crate::errors::SilentExit;
+use crate::exec;
 use crate::exec::Cmd;
 
 /// Rebase the current branch
@@ -18,27 +23,103 @@ pub struct RebaseArgs {
     /// When calling the remote API, ignore caches that are not expired.
     #[clap(short, long)]
     pub force: bool,
+
+    /// Move the commits after the target branch onto this branch, that is
+    /// `git rebase --onto <onto> <target>`.
+    #[clap(short, long)]
+    pub onto: Option<String>,
+
+    /// Continue the in-progress rebase after resolving conflicts.
+    #[clap(short, long = "continue")]
+    pub continue_rebase: bool,
+
+    /// Abort the in-progress rebase.
+    #[clap(short, long)]
+    pub abort: bool,
+
+    /// Skip the current patch of the in-progress rebase.
+    #[clap(short, long)]
+    pub skip: bool,
 }
 
 impl Run for RebaseArgs {
     fn run(&self, cfg: &Config) -> Result<()> {
+        let in_progress = Self::in_progress()?;
+        if let Some(op) = self.get_progress_op() {
+            if !in_progress {
+                bail!("no rebase in progress");
+            }
+            return Self::run_interactive(&["rebase", op]);
+        }
+        if in_progress {
+            bail!("a rebase is in progress, please use `--continue`, `--abort` or `--skip`");
+        }
+
+        cmd::warn_thin_repo(cfg)?;
         let remote = cmd::get_git_remote(cfg, self.upstream, self.force)?;
 
         let branch = self.target.as_deref();
 
         let target = remote.target(branch)?;
 
-        Cmd::git(&["rebase", target.as_str()])
-            .with_display_cmd()
-            .execute()
+        // The dirty changes will be stashed before rebasing, and restored after
+        // it is done.
+        let mut args = vec!["rebase", "--autostash"];
+        let onto = match self.onto.as_ref() {
+            Some(onto) => Some(remote.target(Some(onto))?),
+            None => None,
+        };
+        if let Some(onto) = onto.as_ref() {
+            args.extend(["--onto", onto.as_str()]);
+        }
+        args.push(target.as_str());
+
+        Cmd::git(&args).with_display_cmd().execute()
     }
 }
 
 impl RebaseArgs {
+    fn get_progress_op(&self) -> Option<&'static str> {
+        if self.continue_rebase {
+            Some("--continue")
+        } else if self.abort {
+            Some("--abort")
+        } else if self.skip {
+            Some("--skip")
+        } else {
+            None
+        }
+    }
+
+    /// Return `true` if there is an in-progress rebase in current repository.
+    fn in_progress() -> Result<bool> {
+        for name in ["rebase-merge", "rebase-apply"] {
+            let path = Cmd::git(&["rev-parse", "--git-path", name]).read()?;
+            if PathBuf::from(path).exists() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Run git command with inherited stdio, since it may open editor for
+    /// commit message.
+    fn run_interactive(args: &[&str]) -> Result<()> {
+        exec!("git {}", args.join(" "));
+        let status = Command::new("git").args(args).status();
+        match status {
+            Ok(status) if status.success() => Ok(()),
+            _ => bail!(SilentExit { code: 101 }),
+        }
+    }
+
     pub fn completion() -> Completion {
         Completion {
-            args: Completion::branch_args,
-            flags: None,
+            args: Completion::base_branch_args,
+            flags: Some(|cfg, flag, to_complete| match flag {
+                'o' => Completion::base_branch_args(cfg, &[to_complete]).map(Some),
+                _ => Ok(None),
+            }),
         }
     }
 }
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use clap::Args;
+
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::exec;
+use crate::repo::database::Database;
+use crate::repo::visits::Visits;
+use crate::repo::Repo;
+use crate::table::Table;
+use crate::utils;
+
+/// Show the recently accessed repositories, grouped by time.
+#[derive(Args)]
+pub struct RecentArgs {
+    /// Show the repositories accessed in these days.
+    #[clap(short, long, default_value = "7")]
+    pub days: u64,
+
+    /// Use fzf to select a repository and jump into it.
+    #[clap(short, long)]
+    pub jump: bool,
+
+    /// Use the labels to filter repository.
+    #[clap(short, long)]
+    pub labels: Option<String>,
+}
+
+impl Run for RecentArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let mut db = Database::load(cfg)?;
+
+        let filter_labels = utils::parse_labels(&self.labels);
+        let since = cfg.now().saturating_sub(self.days * utils::DAY);
+        let mut repos: Vec<Repo> = db
+            .list_all(&filter_labels)
+            .into_iter()
+            .filter(|repo| repo.last_accessed > 0 && repo.last_accessed >= since)
+            .collect();
+        if repos.is_empty() {
+            eprintln!("No repo accessed in {} days", self.days);
+            return Ok(());
+        }
+        repos.sort_unstable_by_key(|repo| std::cmp::Reverse(repo.last_accessed));
+
+        if !self.jump {
+            let visits = Visits::load(cfg)?;
+            self.show(cfg, repos, &visits, since);
+            return Ok(());
+        }
+
+        let items: Vec<String> = repos.iter().map(|repo| repo.name_with_remote()).collect();
+        let idx = exec::fzf_search(&items)?;
+        let mut repo = repos.remove(idx).update();
+        drop(repos);
+
+        println!("{}", repo.get_path(cfg).display());
+
+        repo.accessed += 1;
+        repo.last_accessed = cfg.now();
+        db.upsert(repo);
+        db.save()
+    }
+}
+
+impl RecentArgs {
+    const BUCKETS: [&'static str; 4] = ["Today", "Yesterday", "This Week", "Earlier"];
+
+    /// Show the repos grouped by the buckets, the "Visits" is the number of
+    /// visits in the bucket, the "Delta" compares it with the same length of time
+    /// right before the bucket.
+    fn show(&self, cfg: &Config, repos: Vec<Repo>, visits: &Visits, since: u64) {
+        let today = Self::today_start(cfg);
+        let ranges = Self::bucket_ranges(today, since);
+        let mut buckets: Vec<Vec<Repo>> = (0..Self::BUCKETS.len()).map(|_| Vec::new()).collect();
+        for repo in repos {
+            let idx = ranges
+                .iter()
+                .position(|(start, _)| repo.last_accessed >= *start)
+                .unwrap_or(Self::BUCKETS.len() - 1);
+            buckets[idx].push(repo);
+        }
+
+        for ((name, repos), (start, end)) in Self::BUCKETS.iter().zip(buckets).zip(ranges) {
+            if repos.is_empty() {
+                continue;
+            }
+            let prev_start = start.saturating_sub(end.min(cfg.now() + 1) - start);
+            eprintln!();
+            eprintln!("{name}:");
+            let mut table = Table::with_capacity(repos.len() + 1);
+            table.add(vec![
+                String::from("Repo"),
+                String::from("Visits"),
+                String::from("Delta"),
+                String::from("Total"),
+                String::from("Last"),
+            ]);
+            for repo in repos {
+                let name = repo.name_with_remote();
+                let count = visits.count(&name, start, end);
+                let prev = visits.count(&name, prev_start, start);
+                table.add(vec![
+                    name,
+                    format!("{count}"),
+                    Self::format_delta(count as i64 - prev as i64),
+                    format!("{}", repo.accessed),
+                    utils::format_since(cfg, repo.last_accessed),
+                ]);
+            }
+            table.show();
+        }
+    }
+
+    /// Return the time range `[start, end)` of each bucket, the last one starts
+    /// at `since`, which is the beginning of `--days`.
+    fn bucket_ranges(today: u64, since: u64) -> [(u64, u64); 4] {
+        let yesterday = today.saturating_sub(utils::DAY);
+        let week = today.saturating_sub(6 * utils::DAY);
+        [
+            (today, u64::MAX),
+            (yesterday, today),
+            (week, yesterday),
+            (since.min(week), week),
+        ]
+    }
+
+    fn format_delta(delta: i64) -> String {
+        if delta > 0 {
+            format!("+{delta}")
+        } else {
+            format!("{delta}")
+        }
+    }
+
+    /// Return the timestamp of today's 00:00 in local timezone.
+    fn today_start(cfg: &Config) -> u64 {
+        let now = match Local.timestamp_opt(cfg.now() as i64, 0).single() {
+            Some(now) => now,
+            None => return cfg.now(),
+        };
+        let start = now
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|start| Local.from_local_datetime(&start).earliest());
+        match start {
+            Some(start) => start.timestamp() as u64,
+            None => cfg.now(),
+        }
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_, _| Ok(crate::cmd::CompletionResult::empty()),
+            flags: Some(|cfg, flag, to_complete| match flag {
+                'l' => Completion::labels_flag(cfg, to_complete),
+                _ => Ok(None),
+            }),
+        }
+    }
+}
@@ -62,14 +62,14 @@ impl Run for RecoverArgs {
 }
 
 impl RecoverArgs {
-    fn scan_workspace(cfg: &Config) -> Result<Vec<Repo>> {
+    fn scan_workspace(cfg: &Config) -> Result<Vec<Repo<'_>>> {
         info!("Scanning workspace");
         let mut repos = Vec::new();
         let dir = cfg.get_workspace_dir().clone();
         let workspace = dir.clone();
 
-        utils::walk_dir(dir, |path, meta| {
-            if !meta.is_dir() {
+        utils::walk_dir(dir, |path, entry| {
+            if !entry.is_dir() {
                 return Ok(false);
             }
             let git_dir = path.join(".git");
@@ -4,6 +4,7 @@ use clap::Args;
 use crate::cmd::{Completion, Run};
 use crate::config::Config;
 use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::usage;
 use crate::repo::Repo;
 use crate::{confirm, term, utils};
 
@@ -64,9 +65,13 @@ impl RemoveArgs {
         let repo = selector.must_one(db)?;
 
         confirm!("Do you want to remove repo {}", repo.name_with_remote());
+        if repo.is_protected() {
+            term::must_confirm_protected(cfg, &repo.name)?;
+        }
 
         let path = repo.get_path(cfg);
         utils::remove_dir_recursively(path, true)?;
+        usage::remove_cache(cfg, &repo)?;
 
         db.remove(repo.update());
 
@@ -89,11 +94,15 @@ impl RemoveArgs {
 
         let items: Vec<_> = repos.iter().map(|repo| repo.to_string(&level)).collect();
         term::must_confirm_items(&items, "remove", "removal", "Repo", "Repos")?;
+        for repo in repos.iter().filter(|repo| repo.is_protected()) {
+            term::must_confirm_protected(cfg, &repo.name)?;
+        }
 
         let mut update_repos = Vec::with_capacity(repos.len());
         for repo in repos {
             let path = repo.get_path(cfg);
             utils::remove_dir_recursively(path, true)?;
+            usage::remove_cache(cfg, &repo)?;
             update_repos.push(repo.update());
         }
         for repo in update_repos {
@@ -4,6 +4,8 @@ use clap::Args;
 use crate::cmd::{self, Completion, Run};
 use crate::config::Config;
 use crate::exec::Cmd;
+use crate::repo::database::Database;
+use crate::term;
 
 /// Reset the current branch
 #[derive(Args)]
@@ -22,6 +24,13 @@ pub struct ResetArgs {
 
 impl Run for ResetArgs {
     fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load_readonly(cfg)?;
+        if let Some(repo) = db.get_current() {
+            if repo.is_protected() {
+                term::must_confirm_protected(cfg, &repo.name)?;
+            }
+        }
+
         let remote = cmd::get_git_remote(cfg, self.upstream, self.force)?;
         let branch = self.target.as_deref();
 
@@ -36,7 +45,7 @@ impl Run for ResetArgs {
 impl ResetArgs {
     pub fn completion() -> Completion {
         Completion {
-            args: Completion::branch_args,
+            args: Completion::base_branch_args,
             flags: None,
         }
     }
@@ -1,15 +1,22 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{bail, Result};
-use clap::Args;
+use clap::{Args, ValueEnum};
+use console::style;
 
 use crate::batch::{self, Task};
 use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::local::LocalConfig;
 use crate::config::{Config, WorkflowConfig, WorkflowStep};
 use crate::repo::database::{Database, SelectOptions, Selector};
-use crate::workflow::Workflow;
-use crate::{term, utils};
+use crate::repo::detect;
+use crate::repo::Repo;
+use crate::table::{Table, TableArgs, TableCell, TableCellColor};
+use crate::workflow::{Workflow, WorkflowHistory};
+use crate::{error, exec, info, term, utils, warn};
 
 /// Run workflow in repository.
 #[derive(Args)]
@@ -39,14 +46,93 @@ pub struct RunArgs {
     /// Ignore workflow, execute this command.
     #[clap(short, long)]
     pub exec: Option<String>,
+
+    /// Ignore workflow, run the task with the command for the module detected
+    /// in the repository, such as `cargo build`, `go test ./...`, `npm test`.
+    /// The command can be overridden by `tasks` in the local config
+    /// `.roxide.toml`, such as `build = "make"`.
+    #[clap(short, long)]
+    pub task: Option<RunTask>,
+
+    /// Show the workflow running history.
+    #[clap(short = 'H', long)]
+    pub history: bool,
+
+    /// Show the steps and output logs of a workflow running history.
+    #[clap(short = 'L', long)]
+    pub logs: Option<u64>,
+    #[clap(flatten)]
+    pub table: TableArgs,
+}
+
+/// The task to run, see `--task`.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum RunTask {
+    Build,
+    Test,
+    Lint,
+}
+
+impl RunTask {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Build => "build",
+            Self::Test => "test",
+            Self::Lint => "lint",
+        }
+    }
+
+    /// Return the builtin command for the module, see
+    /// [`detect::detect_root_modules`].
+    fn builtin_command(&self, module: &str) -> Option<&'static str> {
+        let commands = match module {
+            "cargo" => ["cargo build", "cargo test", "cargo clippy --all-targets"],
+            "gomod" => ["go build ./...", "go test ./...", "go vet ./..."],
+            "nodejs" => ["npm run build", "npm test", "npm run lint"],
+            "maven" => ["mvn compile", "mvn test", "mvn verify -DskipTests"],
+            "gradle" => [
+                "./gradlew assemble",
+                "./gradlew test",
+                "./gradlew check -x test",
+            ],
+            "cmake" => [
+                "cmake -B build && cmake --build build",
+                "ctest --test-dir build",
+                "",
+            ],
+            "composer" => ["composer install", "composer test", ""],
+            "gem" => [
+                "bundle install",
+                "bundle exec rake test",
+                "bundle exec rubocop",
+            ],
+            _ => return None,
+        };
+        let command = match self {
+            Self::Build => commands[0],
+            Self::Test => commands[1],
+            Self::Lint => commands[2],
+        };
+        if command.is_empty() {
+            return None;
+        }
+        Some(command)
+    }
 }
 
 impl Run for RunArgs {
     fn run(&self, cfg: &Config) -> Result<()> {
+        if self.history {
+            return self.show_history(cfg);
+        }
+        if let Some(id) = self.logs {
+            return self.show_logs(cfg, id);
+        }
+
         let db = Database::load(cfg)?;
 
-        if self.name.is_none() && self.exec.is_none() {
-            bail!("name or exec should be provided");
+        if self.name.is_none() && self.exec.is_none() && self.task.is_none() {
+            bail!("name, exec or task should be provided");
         }
 
         if self.current {
@@ -54,8 +140,27 @@ impl Run for RunArgs {
                 bail!("not allowed to use exec in current mode");
             }
             let repo = db.must_get_current()?;
-            let workflow = Workflow::load(self.name.as_ref().unwrap(), cfg, &repo)?;
-            return workflow.run();
+            let history = WorkflowHistory::new(cfg);
+            let result = match self.task {
+                Some(task) => {
+                    let workflow_cfg = match Self::get_task_cfg(cfg, &repo, task)? {
+                        Some(workflow_cfg) => workflow_cfg,
+                        None => bail!(
+                            "no {} command for the current repo, please set `tasks.{}` in {}",
+                            task.name(),
+                            task.name(),
+                            LocalConfig::FILE_NAME
+                        ),
+                    };
+                    Workflow::new(cfg, &repo, task.name(), Arc::new(workflow_cfg), true)?
+                        .with_history(history.recorder())
+                        .run()
+                }
+                None => Workflow::load(self.name.as_ref().unwrap(), cfg, &repo)?
+                    .with_history(history.recorder())
+                    .run(),
+            };
+            return history.finish(cfg, result);
         }
 
         let filter_labels = utils::parse_labels(&self.labels);
@@ -75,47 +180,178 @@ impl Run for RunArgs {
 
         let level = Arc::new(level);
         let mut tasks = Vec::with_capacity(repos.len());
-        let workflow_cfg = Arc::new(self.get_workflow_cfg(cfg)?.into_owned());
+        let workflow_cfg = match self.task {
+            Some(_) => None,
+            None => Some(Arc::new(self.get_workflow_cfg(cfg)?.into_owned())),
+        };
+        let workflow_name = match (self.task, self.exec.as_ref()) {
+            (Some(task), _) => task.name(),
+            (None, Some(exec)) => exec.as_str(),
+            (None, None) => self.name.as_ref().unwrap().as_str(),
+        };
 
+        let history = WorkflowHistory::new(cfg);
         for repo in repos {
             let show_name = repo.to_string(&level);
-            let workflow = Workflow::load_for_batch(cfg, &repo, Arc::clone(&workflow_cfg));
+            let workflow_cfg = match (workflow_cfg.as_ref(), self.task) {
+                (Some(workflow_cfg), _) => Arc::clone(workflow_cfg),
+                (None, Some(task)) => match Self::get_task_cfg(cfg, &repo, task)? {
+                    Some(workflow_cfg) => Arc::new(workflow_cfg),
+                    None => {
+                        warn!("No {} command for repo {}, skip", workflow_name, show_name);
+                        continue;
+                    }
+                },
+                (None, None) => unreachable!(),
+            };
+            let workflow = Workflow::load_for_batch(cfg, &repo, workflow_name, workflow_cfg)?
+                .with_history(history.recorder());
             tasks.push((show_name, workflow))
         }
+        if tasks.is_empty() {
+            eprintln!("No repo to run");
+            return Ok(());
+        }
 
-        batch::must_run("Run", tasks)?;
+        let result = batch::must_run("Run", tasks);
+        history.finish(cfg, result)?;
         Ok(())
     }
 }
 
 impl RunArgs {
+    fn show_history(&self, cfg: &Config) -> Result<()> {
+        let history = WorkflowHistory::new(cfg);
+        let records = history.list()?;
+        if records.is_empty() {
+            eprintln!("No workflow history");
+            return Ok(());
+        }
+
+        let mut table = Table::with_capacity(records.len() + 1);
+        table.add(vec![
+            String::from("ID"),
+            String::from("Repo"),
+            String::from("Workflow"),
+            String::from("Status"),
+            String::from("Time"),
+            String::from("Elapsed"),
+        ]);
+        for record in records.into_iter().rev() {
+            let status = match record.error {
+                Some(_) => TableCell::with_color(String::from("failed"), TableCellColor::Red),
+                None => TableCell::with_color(String::from("ok"), TableCellColor::Green),
+            };
+            table.add_color(vec![
+                TableCell::no_color(format!("{}", record.id)),
+                TableCell::no_color(record.repo),
+                TableCell::no_color(record.name),
+                status,
+                TableCell::no_color(utils::format_since(cfg, record.time))
+                    .with_sort_key(record.time),
+                TableCell::no_color(utils::format_elapsed(Duration::from_millis(
+                    record.elapsed_ms,
+                )))
+                .with_sort_key(record.elapsed_ms),
+            ]);
+        }
+        table.show_with(
+            &self.table,
+            &["id", "repo", "workflow", "status", "time", "elapsed"],
+        )
+    }
+
+    fn show_logs(&self, cfg: &Config, id: u64) -> Result<()> {
+        let history = WorkflowHistory::new(cfg);
+        let record = history.get(id)?;
+
+        eprintln!(
+            "Workflow {} for {}, {}",
+            style(&record.name).cyan().bold(),
+            style(&record.repo).magenta(),
+            utils::format_time(record.time)?
+        );
+        for step in record.steps.iter() {
+            eprintln!();
+            exec!("{}", style(&step.name).bold().cyan());
+            let log = step.get_log()?;
+            if !log.is_empty() {
+                print!("{log}");
+                if !log.ends_with('\n') {
+                    println!();
+                }
+            }
+            if step.attempts > 1 {
+                warn!("Ran {} times with retries", step.attempts);
+            }
+            if step.timed_out {
+                warn!("Timed out");
+            }
+            if let Some(err) = step.error.as_ref() {
+                error!("{}", err);
+            } else if !step.message.is_empty() {
+                info!("{}", step.message);
+            }
+        }
+
+        eprintln!();
+        match record.error.as_ref() {
+            Some(err) => eprintln!("Result: {} {err}", style("failed").red()),
+            None => eprintln!("Result: {}", style("ok").green()),
+        }
+        Ok(())
+    }
+
     fn get_workflow_cfg<'a>(&self, cfg: &'a Config) -> Result<Cow<'a, WorkflowConfig>> {
         match self.exec.as_ref() {
-            Some(exec) => Ok(Cow::Owned(WorkflowConfig {
-                env: Vec::new(),
-                include: Vec::new(),
-                steps: vec![WorkflowStep {
-                    name: exec.clone(),
-                    os: None,
-                    condition: Vec::new(),
-                    allow_failure: false,
-                    if_condition: None,
-                    image: None,
-                    ssh: None,
-                    set_env: None,
-                    docker_build: None,
-                    docker_push: None,
-                    work_dir: String::new(),
-                    env: Vec::new(),
-                    file: None,
-                    run: Some(exec.clone()),
-                    capture_output: None,
-                }],
-            })),
+            Some(exec) => Ok(Cow::Owned(Self::exec_workflow(exec))),
             None => cfg.get_workflow(self.name.as_ref().unwrap()),
         }
     }
 
+    /// Return the workflow to run the task in the repository, [`None`] if no
+    /// module is detected and the local config does not set the command.
+    fn get_task_cfg(cfg: &Config, repo: &Repo, task: RunTask) -> Result<Option<WorkflowConfig>> {
+        let path = repo.get_path(cfg);
+        let local = LocalConfig::load(&path)?;
+        if let Some(command) = local.tasks.get(task.name()) {
+            return Ok(Some(Self::exec_workflow(command)));
+        }
+        let command = detect::detect_root_modules(&path)
+            .into_iter()
+            .find_map(|module| task.builtin_command(module));
+        Ok(command.map(Self::exec_workflow))
+    }
+
+    /// Return the workflow with only one step to execute the command.
+    fn exec_workflow(exec: &str) -> WorkflowConfig {
+        WorkflowConfig {
+            env: Vec::new(),
+            include: Vec::new(),
+            vars: HashMap::new(),
+            secrets: Vec::new(),
+            steps: vec![WorkflowStep {
+                name: exec.to_string(),
+                os: None,
+                condition: Vec::new(),
+                allow_failure: false,
+                timeout_secs: None,
+                retries: 0,
+                if_condition: None,
+                image: None,
+                ssh: None,
+                set_env: None,
+                docker_build: None,
+                docker_push: None,
+                work_dir: String::new(),
+                env: Vec::new(),
+                file: None,
+                run: Some(exec.to_string()),
+                capture_output: None,
+            }],
+        }
+    }
+
     pub fn completion() -> Completion {
         Completion {
             args: Completion::repo_args,
@@ -1,28 +1,75 @@
-use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
 use clap::Args;
 
 use crate::cmd::{Completion, CompletionResult, Run};
 use crate::config::Config;
-use crate::secret;
+use crate::{info, s3, secret};
 
 /// Encrypt/Decrypt secret file
 #[derive(Args)]
 pub struct SecretArgs {
-    /// The file to encrypt/decrypt.
+    /// The file to encrypt/decrypt, use `s3://{key}` to download it from the
+    /// s3 storage (see `s3` in config).
     pub file: String,
 
     /// Write content to path
     #[clap(short = 'f', long)]
     pub write_path: Option<String>,
+
+    /// Upload the written file to the s3 storage, with key
+    /// `secrets/{file_name}`, so that it can be shared across machines.
+    #[clap(short, long)]
+    pub upload: bool,
 }
 
 impl Run for SecretArgs {
-    fn run(&self, _: &Config) -> Result<()> {
-        secret::handle(&self.file, &self.write_path, None)
+    fn run(&self, cfg: &Config) -> Result<()> {
+        if self.upload && self.write_path.is_none() {
+            bail!("the upload requires the result to be written to a file by `-f`");
+        }
+
+        match self.file.strip_prefix("s3://") {
+            Some(key) => {
+                let path = cfg.get_meta_dir().join("secret_download");
+                s3::download(cfg, key, &path)?;
+                let result = secret::handle(&path, &self.write_path, None);
+                let _ = fs::remove_file(&path);
+                result?;
+            }
+            None => secret::handle(&self.file, &self.write_path, None)?,
+        }
+
+        if self.upload {
+            let path = PathBuf::from(self.write_path.as_ref().unwrap());
+            self.upload(cfg, &path)?;
+        }
+        Ok(())
     }
 }
 
 impl SecretArgs {
+    fn upload(&self, cfg: &Config, path: &Path) -> Result<()> {
+        // The file is decrypted if the source is encrypted, never upload the
+        // plain content.
+        if !secret::is_secret(path)? {
+            bail!(
+                "the written file '{}' is not encrypted, refuse to upload it",
+                path.display()
+            );
+        }
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => bail!("invalid write path '{}'", path.display()),
+        };
+        let key = format!("secrets/{name}");
+        s3::upload(cfg, &key, path)?;
+        info!("Upload secret to s3://{}", key);
+        Ok(())
+    }
+
     pub fn completion() -> Completion {
         Completion {
             args: Completion::files,
@@ -33,3 +80,35 @@ impl SecretArgs {
         }
     }
 }
+
+#[cfg(test)]
+mod secret_tests {
+    use crate::cmd::secret::*;
+    use crate::config::config_tests;
+
+    #[test]
+    fn test_upload_decrypted() {
+        let cfg = config_tests::load_test_config("secret/upload");
+        let dir = cfg.get_meta_dir().join("secret_upload");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // The PBKDF2 rounds make the real encryption slow in tests, only the
+        // head matters here.
+        let encrypted = dir.join("plain.secret");
+        fs::write(&encrypted, "-----BEGIN ROXIDE SECRET-----\nxxx\n").unwrap();
+        let decrypted = dir.join("decrypted.txt");
+        fs::write(&decrypted, "token=123\n").unwrap();
+
+        // `rox secret plain.secret -f decrypted.txt -u` decrypts the file, the
+        // plain content must not be uploaded.
+        let args = SecretArgs {
+            file: format!("{}", encrypted.display()),
+            write_path: Some(format!("{}", decrypted.display())),
+            upload: true,
+        };
+        let err = args.upload(&cfg, &decrypted).unwrap_err();
+        assert!(format!("{err:#}").contains("not encrypted"), "{err:#}");
+        assert!(secret::is_secret(&encrypted).unwrap());
+    }
+}
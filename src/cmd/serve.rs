@@ -0,0 +1,528 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Take, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::repo::database::Database;
+use crate::repo::Repo;
+use crate::{info, utils, warn};
+
+/// Serve the roxide state over HTTP, so that the editor plugins and dashboards
+/// can query it without running roxide for each request.
+///
+/// The `--api` serves a read-only JSON API, all the endpoints accept `GET`:
+///
+/// * `/api/repos?remote=&owner=&labels=`: List the repositories.
+/// * `/api/search?q=&remote=`: Search the repositories whose `{owner}/{name}`
+///   contains the keyword.
+/// * `/api/remotes`: List the remotes, with the number of owners and repos.
+/// * `/api/owners?remote=`: List the owners, with the number of repos.
+/// * `/api/stats`: The numbers of the repositories, grouped by remote and label.
+///
+/// The repositories are sorted by score, the same as `get`.
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Serve the read-only JSON API.
+    #[clap(long)]
+    pub api: bool,
+
+    /// The address to listen, the API has no authentication, be careful to
+    /// listen on a public address. The requests whose `Host` is not this address
+    /// or localhost are rejected.
+    #[clap(short, long, default_value = "127.0.0.1:7415")]
+    pub addr: String,
+}
+
+impl Run for ServeArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        if !self.api {
+            bail!("please specify what to serve, such as `--api`");
+        }
+
+        let listener =
+            TcpListener::bind(&self.addr).with_context(|| format!("listen on '{}'", self.addr))?;
+        let addr = listener.local_addr().context("get listen address")?;
+        info!("Serve API on http://{}, press Ctrl-C to stop", addr);
+
+        // The connections are handled by a fixed number of workers, the accepting
+        // is blocked (backpressure) when all of them are busy.
+        let (tx, rx) = mpsc::sync_channel::<TcpStream>(Self::WORKERS);
+        let rx = Mutex::new(rx);
+        thread::scope(|s| {
+            for _ in 0..Self::WORKERS {
+                let rx = &rx;
+                s.spawn(move || loop {
+                    let stream = match rx.lock() {
+                        Ok(rx) => match rx.recv() {
+                            Ok(stream) => stream,
+                            Err(_) => return,
+                        },
+                        Err(_) => return,
+                    };
+                    if let Err(err) = handle_connection(cfg, &addr, stream) {
+                        warn!("Handle request: {:#}", err);
+                    }
+                });
+            }
+
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if tx.send(stream).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => warn!("Accept connection: {}", err),
+                }
+            }
+            drop(tx);
+        });
+        Ok(())
+    }
+}
+
+impl ServeArgs {
+    /// The number of the connections handled at the same time.
+    const WORKERS: usize = 4;
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_, _| Ok(CompletionResult::empty()),
+            flags: None,
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+
+    /// The `Host` header.
+    host: Option<String>,
+}
+
+impl Request {
+    /// The max size of the request head, the API has no request body.
+    const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+    fn read(stream: impl Read) -> Result<Request> {
+        // Limit the reading, otherwise a line without newline would be buffered
+        // without limit.
+        let mut reader = BufReader::new(stream.take(Self::MAX_HEAD_SIZE as u64));
+        let mut line = String::new();
+        reader.read_line(&mut line).context("read request line")?;
+        Self::check_size(&reader, &line)?;
+        let mut request = Self::parse(&line)?;
+
+        // Only the `Host` header is used, drain the others.
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).context("read header")?;
+            Self::check_size(&reader, &header)?;
+            if header.is_empty() || header == "\r\n" || header == "\n" {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("host") {
+                    request.host = Some(value.trim().to_string());
+                }
+            }
+        }
+        Ok(request)
+    }
+
+    /// The line without newline is incomplete, if the limit is reached, the
+    /// request head is too large.
+    fn check_size<R: Read>(reader: &BufReader<Take<R>>, line: &str) -> Result<()> {
+        if !line.ends_with('\n') && reader.get_ref().limit() == 0 {
+            bail!("request head is too large");
+        }
+        Ok(())
+    }
+
+    /// Parse the request line, such as `GET /api/repos?remote=github HTTP/1.1`.
+    fn parse(line: &str) -> Result<Request> {
+        let mut fields = line.split_whitespace();
+        let (method, target) = match (fields.next(), fields.next()) {
+            (Some(method), Some(target)) => (method, target),
+            _ => bail!("invalid request line '{}'", line.trim()),
+        };
+        let (path, query_str) = target.split_once('?').unwrap_or((target, ""));
+
+        let mut query = HashMap::new();
+        for pair in query_str.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |s: &str| {
+                urlencoding::decode(&s.replace('+', " "))
+                    .map(|s| s.into_owned())
+                    .with_context(|| format!("decode query '{s}'"))
+            };
+            query.insert(decode(key)?, decode(value)?);
+        }
+
+        Ok(Request {
+            method: method.to_string(),
+            path: path.trim_end_matches('/').to_string(),
+            query,
+            host: None,
+        })
+    }
+
+    /// Return `true` if the `Host` is the listen address or localhost, to
+    /// prevent the DNS rebinding attack from the browsers (a malicious page
+    /// resolving its domain to the local address to read the API).
+    fn is_host_allowed(&self, addr: &SocketAddr) -> bool {
+        let host = match self.host.as_ref() {
+            Some(host) => host.to_lowercase(),
+            None => return false,
+        };
+        let port = addr.port().to_string();
+        let name = match host.rsplit_once(':') {
+            Some((name, host_port)) if !host_port.ends_with(']') => {
+                if host_port != port {
+                    return false;
+                }
+                name
+            }
+            _ => host.as_str(),
+        };
+        let name = name.trim_start_matches('[').trim_end_matches(']');
+        matches!(name, "localhost" | "127.0.0.1" | "::1") || name == addr.ip().to_string()
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.query
+            .get(key)
+            .map(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+struct Response {
+    status: u16,
+    body: serde_json::Value,
+}
+
+impl Response {
+    fn ok<T: Serialize>(value: T) -> Result<Response> {
+        Ok(Response {
+            status: 200,
+            body: serde_json::to_value(value).context("serialize response")?,
+        })
+    }
+
+    fn error(status: u16, message: impl ToString) -> Response {
+        Response {
+            status,
+            body: json!({ "error": message.to_string() }),
+        }
+    }
+
+    fn write(&self, stream: &mut TcpStream) -> Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        let body = self.body.to_string();
+        let head = format!(
+            "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            body.len()
+        );
+        stream
+            .write_all(head.as_bytes())
+            .and_then(|_| stream.write_all(body.as_bytes()))
+            .and_then(|_| stream.flush())
+            .context("write response")
+    }
+}
+
+fn handle_connection(cfg: &Config, addr: &SocketAddr, mut stream: TcpStream) -> Result<()> {
+    let timeout = Some(Duration::from_secs(10));
+    stream
+        .set_read_timeout(timeout)
+        .context("set read timeout")?;
+    stream
+        .set_write_timeout(timeout)
+        .context("set write timeout")?;
+
+    let response = match Request::read(&stream) {
+        Ok(request) if !request.is_host_allowed(addr) => Response::error(
+            403,
+            format!(
+                "host '{}' is not allowed",
+                request.host.as_deref().unwrap_or_default()
+            ),
+        ),
+        Ok(request) if request.method != "GET" => {
+            Response::error(405, format!("method {} is not allowed", request.method))
+        }
+        Ok(request) => match handle(cfg, &request) {
+            Ok(response) => response,
+            Err(err) => Response::error(500, format!("{err:#}")),
+        },
+        Err(err) => Response::error(400, format!("{err:#}")),
+    };
+    response.write(&mut stream)
+}
+
+/// The repository in the JSON responses, shared with the `ipc` command.
+#[derive(Debug, Serialize)]
+pub struct RepoItem<'a> {
+    remote: &'a str,
+    owner: &'a str,
+    name: &'a str,
+    path: String,
+    labels: Vec<&'a str>,
+    accessed: u64,
+    last_accessed: u64,
+    score: u64,
+}
+
+impl RepoItem<'_> {
+    pub fn from_repo<'a>(cfg: &Config, repo: &'a Repo) -> RepoItem<'a> {
+        let mut labels: Vec<&str> = match repo.labels.as_ref() {
+            Some(labels) => labels.iter().map(|label| label.as_ref()).collect(),
+            None => vec![],
+        };
+        labels.sort_unstable();
+        RepoItem {
+            remote: &repo.remote,
+            owner: &repo.owner,
+            name: &repo.name,
+            path: format!("{}", repo.get_path(cfg).display()),
+            labels,
+            accessed: repo.accessed,
+            last_accessed: repo.last_accessed,
+            score: repo.score(cfg),
+        }
+    }
+
+    pub fn list<'a>(cfg: &Config, repos: &'a [Repo]) -> Vec<RepoItem<'a>> {
+        repos
+            .iter()
+            .map(|repo| RepoItem::from_repo(cfg, repo))
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RemoteItem {
+    name: String,
+    owners: usize,
+    repos: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct OwnerItem {
+    remote: String,
+    name: String,
+    repos: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    repos: usize,
+    remotes: BTreeMap<String, usize>,
+    labels: BTreeMap<String, usize>,
+}
+
+/// Handle the request with a snapshot of the database, the database is loaded
+/// for each request without lock, so that the changes made by other roxide
+/// processes are visible and not blocked.
+fn handle(cfg: &Config, request: &Request) -> Result<Response> {
+    let db = Database::load_readonly(cfg)?;
+    match request.path.as_str() {
+        "/api/repos" => {
+            let labels = utils::parse_labels(&request.get("labels").map(String::from));
+            let repos = match (request.get("remote"), request.get("owner")) {
+                (Some(remote), Some(owner)) => db.list_by_owner(remote, owner, &labels),
+                (Some(remote), None) => db.list_by_remote(remote, &labels),
+                (None, Some(_)) => return Ok(Response::error(400, "owner requires remote")),
+                (None, None) => db.list_all(&labels),
+            };
+            Response::ok(RepoItem::list(cfg, &repos))
+        }
+        "/api/search" => {
+            let keyword = match request.get("q") {
+                Some(keyword) => keyword.to_lowercase(),
+                None => return Ok(Response::error(400, "missing query 'q'")),
+            };
+            let mut repos = match request.get("remote") {
+                Some(remote) => db.list_by_remote(remote, &None),
+                None => db.list_all(&None),
+            };
+            repos.retain(|repo| repo.name_with_owner().to_lowercase().contains(&keyword));
+            Response::ok(RepoItem::list(cfg, &repos))
+        }
+        "/api/remotes" => {
+            let mut remotes: BTreeMap<String, RemoteItem> = BTreeMap::new();
+            for repo in db.list_all(&None) {
+                let item = remotes
+                    .entry(repo.remote.to_string())
+                    .or_insert_with(|| RemoteItem {
+                        name: repo.remote.to_string(),
+                        owners: db.list_owners(&repo.remote).len(),
+                        repos: 0,
+                    });
+                item.repos += 1;
+            }
+            Response::ok(remotes.into_values().collect::<Vec<_>>())
+        }
+        "/api/owners" => {
+            let repos = match request.get("remote") {
+                Some(remote) => db.list_by_remote(remote, &None),
+                None => db.list_all(&None),
+            };
+            let mut owners: BTreeMap<(String, String), usize> = BTreeMap::new();
+            for repo in repos {
+                *owners
+                    .entry((repo.remote.to_string(), repo.owner.to_string()))
+                    .or_default() += 1;
+            }
+            let owners: Vec<_> = owners
+                .into_iter()
+                .map(|((remote, name), repos)| OwnerItem {
+                    remote,
+                    name,
+                    repos,
+                })
+                .collect();
+            Response::ok(owners)
+        }
+        "/api/stats" => {
+            let repos = db.list_all(&None);
+            let mut stats = Stats {
+                repos: repos.len(),
+                remotes: BTreeMap::new(),
+                labels: BTreeMap::new(),
+            };
+            for repo in repos.iter() {
+                *stats.remotes.entry(repo.remote.to_string()).or_default() += 1;
+                for label in repo.labels.iter().flatten() {
+                    *stats.labels.entry(label.to_string()).or_default() += 1;
+                }
+            }
+            Response::ok(stats)
+        }
+        path => Ok(Response::error(404, format!("no such endpoint '{path}'"))),
+    }
+}
+
+#[cfg(test)]
+mod serve_tests {
+    use crate::cmd::serve::*;
+    use crate::config::config_tests;
+    use crate::repo::database::database_tests;
+
+    #[test]
+    fn test_parse_request() {
+        let request =
+            Request::parse("GET /api/search/?q=kube%2Dproxy&remote=github HTTP/1.1\r\n").unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/api/search");
+        assert_eq!(request.get("q"), Some("kube-proxy"));
+        assert_eq!(request.get("remote"), Some("github"));
+        assert_eq!(request.get("owner"), None);
+        assert!(Request::parse("\r\n").is_err());
+    }
+
+    #[test]
+    fn test_read_request() {
+        let head = "GET /api/repos HTTP/1.1\r\nHost: localhost:7415\r\nAccept: */*\r\n\r\n";
+        let request = Request::read(head.as_bytes()).unwrap();
+        assert_eq!(request.path, "/api/repos");
+        assert_eq!(request.host.as_deref(), Some("localhost:7415"));
+
+        let line = format!("GET /{} HTTP/1.1", "a".repeat(Request::MAX_HEAD_SIZE));
+        assert!(Request::read(line.as_bytes()).is_err());
+        let head = format!(
+            "GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+            "a".repeat(Request::MAX_HEAD_SIZE)
+        );
+        assert!(Request::read(head.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_host_allowed() {
+        let addr: SocketAddr = "127.0.0.1:7415".parse().unwrap();
+        let mut request = Request::parse("GET /api/repos HTTP/1.1").unwrap();
+        assert!(!request.is_host_allowed(&addr));
+        for (host, allowed) in [
+            ("127.0.0.1:7415", true),
+            ("localhost:7415", true),
+            ("LOCALHOST", true),
+            ("[::1]:7415", true),
+            ("localhost:8080", false),
+            ("evil.com:7415", false),
+            ("127.0.0.1.evil.com", false),
+        ] {
+            request.host = Some(host.to_string());
+            assert_eq!(request.is_host_allowed(&addr), allowed, "{host}");
+        }
+    }
+
+    #[test]
+    fn test_handle() {
+        let cfg = config_tests::load_test_config("serve/handle");
+        let mut db = Database::load(&cfg).unwrap();
+        for repo in database_tests::get_test_repos(&cfg) {
+            db.upsert(repo);
+        }
+        db.save().unwrap();
+
+        let get = |line: &str| {
+            let request = Request::parse(&format!("GET {line} HTTP/1.1")).unwrap();
+            let response = handle(&cfg, &request).unwrap();
+            (response.status, response.body)
+        };
+
+        let (status, body) = get("/api/repos?remote=gitlab&owner=my-owner-01&labels=sync");
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["name"], "my-repo-01");
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        let (_, body) = get("/api/search?q=KUBE&remote=github");
+        assert_eq!(body.as_array().unwrap().len(), 4);
+
+        let (_, body) = get("/api/remotes");
+        assert_eq!(
+            body,
+            json!([
+                {"name": "github", "owners": 4, "repos": 9},
+                {"name": "gitlab", "owners": 2, "repos": 4},
+            ])
+        );
+
+        let (_, body) = get("/api/owners?remote=gitlab");
+        assert_eq!(
+            body,
+            json!([
+                {"remote": "gitlab", "name": "my-owner-01", "repos": 3},
+                {"remote": "gitlab", "name": "my-owner-02", "repos": 1},
+            ])
+        );
+
+        let (_, body) = get("/api/stats");
+        assert_eq!(body["repos"], 13);
+        assert_eq!(body["labels"]["sync"], 5);
+
+        assert_eq!(get("/api/owners?owner=x").0, 200);
+        assert_eq!(get("/api/repos?owner=x").0, 400);
+        assert_eq!(get("/api/unknown").0, 404);
+    }
+}
@@ -1,13 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Args;
 use console::style;
 
+use crate::batch::{self, Task};
 use crate::cmd::{Completion, CompletionResult, Run};
 use crate::config::Config;
+use crate::exec::{Cmd, GitCmd};
 use crate::repo::database::{self, Database};
-use crate::repo::snapshot::Snapshot;
+use crate::repo::snapshot::{Revision, Snapshot};
 use crate::{confirm, info};
 
 /// Snapshot operations for workspace
@@ -35,6 +38,11 @@ pub struct SnapshotArgs {
     /// Save snapshot with pretty json.
     #[clap(short, long)]
     pub pretty: bool,
+
+    /// When restoring, also clone the missing repositories and checkout the
+    /// recorded commits.
+    #[clap(short = 'C', long)]
+    pub checkout: bool,
 }
 
 impl Run for SnapshotArgs {
@@ -68,16 +76,54 @@ impl SnapshotArgs {
 
         database::backup_replace(cfg, "snapshot")?;
 
+        let revisions = snapshot.revisions.clone();
+
         info!("Restore database with snapshot {}", snapshot.name);
         let db = Database::load(cfg)?;
         snapshot.restore(db)?;
 
+        if self.checkout {
+            return self.checkout(cfg, revisions);
+        }
+
         println!();
         println!("Restore done, you should use the {} and {} commands to take the effects to the workspace.", style("sync").cyan().bold(), style("gc").cyan().bold());
 
         Ok(())
     }
 
+    fn checkout(&self, cfg: &Config, revisions: BTreeMap<String, Revision>) -> Result<()> {
+        let db = Database::load(cfg)?;
+        let mut tasks = Vec::with_capacity(revisions.len());
+        for repo in db.list_all(&None) {
+            // The root of the bare repo has no work tree to checkout.
+            if repo.is_bare() {
+                continue;
+            }
+            let name = repo.name_with_remote();
+            let revision = match revisions.get(&name) {
+                Some(revision) => revision.clone(),
+                None => continue,
+            };
+            tasks.push((
+                name,
+                CheckoutTask {
+                    path: repo.get_path(cfg),
+                    url: repo.clone_url(),
+                    git_configs: repo.remote_cfg.git_configs(repo.owner.as_ref()),
+                    revision,
+                },
+            ));
+        }
+        if tasks.is_empty() {
+            eprintln!("No revision to checkout");
+            return Ok(());
+        }
+
+        batch::must_run_with::<_, ()>("Checkout", tasks, cfg.git.workers(None))?;
+        Ok(())
+    }
+
     fn create(&self, cfg: &Config, name: String) -> Result<()> {
         let set: HashSet<_> = Snapshot::list(cfg)?.into_iter().collect();
         if set.contains(&name) {
@@ -105,3 +151,46 @@ impl SnapshotArgs {
         }
     }
 }
+
+struct CheckoutTask {
+    path: PathBuf,
+    url: String,
+
+    /// The git configs (such as `user.name`) to apply after cloning.
+    git_configs: Vec<(&'static str, String)>,
+
+    revision: Revision,
+}
+
+impl Task<()> for CheckoutTask {
+    fn run(&self) -> Result<()> {
+        let path = format!("{}", self.path.display());
+        let git = GitCmd::with_path(&path);
+        if self.path.is_dir() {
+            let lines = git.lines(&["status", "-s"])?;
+            if !lines.is_empty() {
+                bail!("have uncommitted change(s), skip checkout");
+            }
+            git.exec(&["fetch", "origin", "--prune"])?;
+        } else {
+            Cmd::git(&["clone", self.url.as_str(), path.as_str()]).execute()?;
+            for (key, value) in self.git_configs.iter() {
+                git.exec(&["config", key, value.as_str()])?;
+            }
+        }
+
+        let commit = self.revision.commit.as_str();
+        if let Some(branch) = self.revision.branch.as_ref() {
+            // Prefer to checkout the branch if it still points to the recorded
+            // commit, so that the repository does not stay in detached HEAD.
+            let branch_commit = git
+                .read(&["rev-parse", "--verify", "--quiet", branch.as_str()])
+                .unwrap_or_default();
+            if branch_commit == commit {
+                return git.checkout(branch);
+            }
+        }
+
+        git.exec(&["checkout", "--detach", commit])
+    }
+}
@@ -27,18 +27,30 @@ pub struct SquashArgs {
     /// When calling the remote API, ignore caches that are not expired.
     #[clap(short, long)]
     pub force: bool,
+
+    /// Use fzf to pick the oldest commit to squash, only the commits after it
+    /// (including itself) will be squashed. The commit message will be drafted
+    /// from the selected commits and opened in editor.
+    #[clap(short, long)]
+    pub interactive: bool,
 }
 
 impl Run for SquashArgs {
     fn run(&self, cfg: &Config) -> Result<()> {
+        cmd::warn_thin_repo(cfg)?;
         let remote = cmd::get_git_remote(cfg, self.upstream, self.force)?;
 
         let branch = self.target.as_deref();
-        let commits = remote.commits_between(branch)?;
+        let mut commits = remote.commits_between(branch)?;
         if commits.is_empty() {
             eprintln!("No commit to squash");
             return Ok(());
         }
+        if self.interactive && commits.len() > 1 {
+            // The commits are listed from newest to oldest.
+            let idx = exec::fzf_search(&commits)?;
+            commits.truncate(idx + 1);
+        }
         if commits.len() == 1 {
             eprintln!("Only found one commit ahead target, no need to squash");
             return Ok(());
@@ -52,6 +64,12 @@ impl Run for SquashArgs {
 
         confirm!("Continue");
 
+        let draft = if self.interactive && self.message.is_none() {
+            Some(Self::draft_message(commits.len())?)
+        } else {
+            None
+        };
+
         let set = format!("HEAD~{}", commits.len());
         Cmd::git(&["reset", "--soft", set.as_str()])
             .with_display_cmd()
@@ -61,6 +79,9 @@ impl Run for SquashArgs {
         if let Some(msg) = &self.message {
             args.push("-m");
             args.push(msg);
+        } else if let Some(draft) = draft.as_ref() {
+            // Let user edit the drafted message in editor.
+            args.extend(["-e", "-m", draft.as_str()]);
         }
 
         exec!("git commit");
@@ -80,10 +101,41 @@ impl Run for SquashArgs {
 }
 
 impl SquashArgs {
+    /// Draft the commit message from the latest `count` commits, the messages
+    /// are joined from oldest to newest.
+    fn draft_message(count: usize) -> Result<String> {
+        let count = format!("{count}");
+        let output = Cmd::git(&["log", "--format=%B%x00", "-n", count.as_str()]).read()?;
+        Ok(Self::join_messages(&output))
+    }
+
+    fn join_messages(output: &str) -> String {
+        let messages: Vec<&str> = output
+            .split('\0')
+            .map(|message| message.trim())
+            .filter(|message| !message.is_empty())
+            .collect();
+        messages.into_iter().rev().collect::<Vec<_>>().join("\n\n")
+    }
+
     pub fn completion() -> Completion {
         Completion {
-            args: Completion::branch_args,
+            args: Completion::base_branch_args,
             flags: None,
         }
     }
 }
+
+#[cfg(test)]
+mod squash_tests {
+    use crate::cmd::squash::*;
+
+    #[test]
+    fn test_join_messages() {
+        let output = "feat: third\n\0\nfix: second\n\nThe body.\n\0\nfeat: first\n\0\n";
+        assert_eq!(
+            SquashArgs::join_messages(output),
+            "feat: first\n\nfix: second\n\nThe body.\n\nfeat: third"
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::batch::{self, Task};
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::exec::{self, Cmd};
+use crate::git::GitStash;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::table::{Table, TableCell, TableCellColor};
+use crate::{info, utils};
+
+/// Manage git stash entries of the repository.
+#[derive(Args)]
+pub struct StashArgs {
+    /// Repository selection head, default is the current repository.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Stash the changes in the working directory.
+    #[clap(short, long)]
+    pub push: bool,
+
+    /// The message of the stash entry, use with `--push`.
+    #[clap(short, long)]
+    pub message: Option<String>,
+
+    /// Also stash the untracked files, use with `--push`.
+    #[clap(short, long)]
+    pub untracked: bool,
+
+    /// Use fzf to select a stash entry and pop it.
+    #[clap(short = 'P', long)]
+    pub pop: bool,
+
+    /// Use fzf to select a stash entry and show its diff.
+    #[clap(short, long)]
+    pub show: bool,
+
+    /// Report all the local repositories that have stash entries, so that the
+    /// stashed work won't be forgotten.
+    #[clap(short = 'A', long)]
+    pub all_repos: bool,
+
+    /// Use the labels to filter repository, use with `--all-repos`.
+    #[clap(short, long)]
+    pub labels: Option<String>,
+}
+
+impl Run for StashArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load_readonly(cfg)?;
+        if self.all_repos {
+            return self.report(cfg, &db);
+        }
+
+        let path = if self.head.is_some() {
+            let opts = SelectOptions::default().with_force_local(true);
+            let repo = Selector::from_args(&self.head, &self.query, opts).must_one(&db)?;
+            format!("{}", repo.get_path(cfg).display())
+        } else {
+            String::from(".")
+        };
+
+        if self.push {
+            let mut args = vec!["-C", path.as_str(), "stash", "push"];
+            if self.untracked {
+                args.push("--include-untracked");
+            }
+            if let Some(message) = self.message.as_ref() {
+                args.push("-m");
+                args.push(message.as_str());
+            }
+            return Cmd::git(&args).with_display_cmd().execute();
+        }
+
+        let stashes = GitStash::list(&path)?;
+        if stashes.is_empty() {
+            eprintln!("No stash entry");
+            return Ok(());
+        }
+
+        if self.pop || self.show {
+            let items: Vec<String> = stashes.iter().map(|stash| stash.to_string()).collect();
+            let idx = exec::fzf_search(&items)?;
+            let name = stashes[idx].name.as_str();
+            if self.pop {
+                Cmd::git(&["-C", path.as_str(), "stash", "pop", name])
+                    .with_display_cmd()
+                    .execute()?;
+                info!("Pop stash {}", name);
+                return Ok(());
+            }
+            let diff = Cmd::git(&["-C", path.as_str(), "stash", "show", "-p", name]).read()?;
+            println!("{diff}");
+            return Ok(());
+        }
+
+        for stash in stashes {
+            println!("{stash}");
+        }
+        Ok(())
+    }
+}
+
+impl StashArgs {
+    fn report(&self, cfg: &Config, db: &Database) -> Result<()> {
+        let labels = utils::parse_labels(&self.labels);
+        let opts = SelectOptions::default().with_filter_labels(labels);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let (mut repos, level) = selector.many_local(db)?;
+        // The root of the bare repo has no work tree to stash.
+        repos.retain(|repo| !repo.is_bare());
+        if repos.is_empty() {
+            eprintln!("No repo to check");
+            return Ok(());
+        }
+
+        let tasks: Vec<_> = repos
+            .iter()
+            .map(|repo| {
+                let name = repo.to_string(&level);
+                let task = StashTask {
+                    name: name.clone(),
+                    path: repo.get_path(cfg),
+                };
+                (name, task)
+            })
+            .collect();
+
+        let results: Vec<_> =
+            batch::must_run_with::<_, Option<StashResult>>("Stash", tasks, cfg.git.workers(None))?
+                .into_iter()
+                .flatten()
+                .collect();
+        if results.is_empty() {
+            eprintln!();
+            eprintln!("No stashed work, great!");
+            return Ok(());
+        }
+
+        eprintln!();
+        let mut table = Table::with_capacity(1 + results.len());
+        table.add(vec![
+            String::from("Repo"),
+            String::from("Stashes"),
+            String::from("Latest"),
+        ]);
+        for result in results {
+            let latest = result.stashes.first().map(|stash| stash.to_string());
+            table.add_color(vec![
+                TableCell::no_color(result.name),
+                TableCell::with_color(format!("{}", result.stashes.len()), TableCellColor::Yellow),
+                TableCell::no_color(latest.unwrap_or_default()),
+            ]);
+        }
+        table.show();
+
+        Ok(())
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: Some(|cfg, flag, to_complete| match flag {
+                'l' => Completion::labels_flag(cfg, to_complete),
+                _ => Ok(None),
+            }),
+        }
+    }
+}
+
+struct StashTask {
+    name: String,
+    path: PathBuf,
+}
+
+struct StashResult {
+    name: String,
+    stashes: Vec<GitStash>,
+}
+
+impl Task<Option<StashResult>> for StashTask {
+    fn run(&self) -> Result<Option<StashResult>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let path = format!("{}", self.path.display());
+        let stashes = GitStash::list(&path)?;
+        if stashes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(StashResult {
+            name: self.name.clone(),
+            stashes,
+        }))
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
@@ -6,14 +6,18 @@ use std::time::Instant;
 use anyhow::{bail, Context, Result};
 use clap::Args;
 use console::style;
+use glob::Pattern as GlobPattern;
+use serde::Serialize;
 
 use crate::batch::{self, Task};
 use crate::cmd::{Completion, CompletionResult, Run};
-use crate::config::Config;
+use crate::config::{local, Config};
+use crate::exec::Cmd;
 use crate::repo::database::{Database, SelectOptions, Selector};
 use crate::repo::detect::stats::{DetectStats, LanguageStats, LanguageStatsChange, StatsStorage};
-use crate::table::{Table, TableCell, TableCellColor};
-use crate::{confirm, utils};
+use crate::repo::Repo;
+use crate::table::{Table, TableCell, TableCellColor, TableOutput};
+use crate::{confirm, term, utils};
 
 /// Count and display repository code stats.
 #[derive(Args)]
@@ -47,10 +51,53 @@ pub struct StatsArgs {
     /// Save current stats.
     #[clap(short, long)]
     pub save: bool,
+
+    /// Show the usage report of the whole workspace, including repository
+    /// counts, disk usage, languages and access records.
+    #[clap(short, long)]
+    pub workspace: bool,
+
+    /// In workspace report, the repositories not accessed in these days are
+    /// considered stale.
+    #[clap(long, default_value = "30")]
+    pub stale_days: u64,
+
+    /// Show the workspace report in json format.
+    #[clap(short = 'J', long)]
+    pub json: bool,
+
+    /// The number of workers to scan, default is `scan.workers` in config.
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Show the top contributors of the current repository (or the path in
+    /// it) by commits and changed lines, to find the code owner quickly.
+    #[clap(short = 'O', long)]
+    pub owners: Option<Option<String>>,
+
+    /// Only count the commits after the date, such as "6 months ago", use with
+    /// `--owners`.
+    #[clap(long)]
+    pub since: Option<String>,
+
+    /// The number of contributors to show, use with `--owners`.
+    #[clap(long, default_value = "10")]
+    pub top: usize,
+
+    /// The output format of the stats table.
+    #[clap(long, value_enum, default_value_t = TableOutput::Table)]
+    pub output: TableOutput,
 }
 
 impl Run for StatsArgs {
     fn run(&self, cfg: &Config) -> Result<()> {
+        if self.workspace {
+            return self.show_workspace(cfg);
+        }
+        if let Some(path) = self.owners.as_ref() {
+            return self.show_owners(path.as_deref());
+        }
+
         let storage = StatsStorage::load(cfg)?;
 
         if let Some(name) = self.delete.as_ref() {
@@ -74,7 +121,7 @@ impl Run for StatsArgs {
             (stats, None)
         } else {
             let start = Instant::now();
-            let db = Database::load(cfg)?;
+            let db = Database::load_readonly(cfg)?;
             let stats = if self.recursive {
                 self.stats_many(cfg, &db)
             } else {
@@ -112,7 +159,7 @@ impl Run for StatsArgs {
             assert!(total_lines_f64 > 0.0);
             lang.percent = (lang.lines as f64 / total_lines_f64) * 100.0;
         }
-        stats.sort_unstable_by(|a, b| b.lines.cmp(&a.lines));
+        stats.sort_unstable_by_key(|lang| std::cmp::Reverse(lang.lines));
 
         let save_stats = if self.save { Some(stats.clone()) } else { None };
         let compare_stats = if self.compare.is_some() {
@@ -175,7 +222,7 @@ impl Run for StatsArgs {
         if let Some(start) = start {
             self.show_speed(start, total_files, total_lines);
         }
-        table.show();
+        table.show_as(self.output);
 
         if let Some(stats) = compare_stats {
             let (target, name) = storage
@@ -212,7 +259,7 @@ impl StatsArgs {
 
         let detect_stats = DetectStats::new(cfg);
         let path = repo.get_path(cfg);
-        detect_stats.count(&path)
+        detect_stats.count_progress(&path)
     }
 
     fn stats_many(&self, cfg: &Config, db: &Database) -> Result<Vec<LanguageStats>> {
@@ -237,7 +284,8 @@ impl StatsArgs {
             tasks.push((name, task));
         }
 
-        let all_stats = batch::must_run("Stats", tasks)?;
+        cfg.scan.prepare()?;
+        let all_stats = batch::must_run_with("Stats", tasks, cfg.scan.workers(self.jobs))?;
         eprintln!();
 
         let mut result: HashMap<String, LanguageStats> = HashMap::new();
@@ -261,6 +309,99 @@ impl StatsArgs {
         Ok(result)
     }
 
+    fn show_workspace(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load_readonly(cfg)?;
+        let filter_labels = utils::parse_labels(&self.labels);
+        let repos = db.list_all(&filter_labels);
+        if repos.is_empty() {
+            bail!("no repo in workspace");
+        }
+
+        let detect_stats = Arc::new(DetectStats::new(cfg));
+        let size_ignores = Arc::new(cfg.size_ignores.clone());
+        let mut tasks = Vec::with_capacity(repos.len());
+        for repo in repos.iter() {
+            let task = WorkspaceTask {
+                detect_stats: Arc::clone(&detect_stats),
+                size_ignores: Arc::clone(&size_ignores),
+                path: repo.get_path(cfg),
+            };
+            tasks.push((repo.name_with_remote(), task));
+        }
+        cfg.scan.prepare()?;
+        let results = batch::must_run_with("Scan", tasks, cfg.scan.workers(self.jobs))?;
+        eprintln!();
+
+        let report = WorkspaceReport::build(cfg, self.stale_days, repos, results);
+        if self.output == TableOutput::Csv {
+            bail!("the workspace report has multiple tables, use `-J` instead of csv");
+        }
+        if self.json {
+            return term::show_json(report);
+        }
+        report.show(cfg);
+        Ok(())
+    }
+
+    fn show_owners(&self, path: Option<&str>) -> Result<()> {
+        let mut args = vec![
+            String::from("log"),
+            String::from("--no-merges"),
+            String::from("--format=%x00%aN%x09%aE"),
+            String::from("--numstat"),
+        ];
+        if let Some(since) = self.since.as_ref() {
+            args.push(format!("--since={since}"));
+        }
+        args.push(String::from("--"));
+        args.push(String::from(path.unwrap_or(".")));
+        let args: Vec<_> = args.iter().map(|arg| arg.as_str()).collect();
+        let lines = Cmd::git(&args).with_display("Get commit history").lines()?;
+
+        let mut owners = OwnerStats::parse(lines);
+        if owners.is_empty() {
+            eprintln!("No commit to count");
+            return Ok(());
+        }
+        let total = owners.len();
+        let total_commits: usize = owners.iter().map(|owner| owner.commits).sum();
+        let total_added: usize = owners.iter().map(|owner| owner.added).sum();
+        let total_deleted: usize = owners.iter().map(|owner| owner.deleted).sum();
+        owners.truncate(self.top);
+
+        let mut table = Table::with_capacity(owners.len() + 2);
+        table.add(vec![
+            String::from("Author"),
+            String::from("Email"),
+            String::from("Commits"),
+            String::from("Added"),
+            String::from("Deleted"),
+            String::from("Percent"),
+        ]);
+        for owner in owners {
+            let percent = owner.commits as f64 * 100.0 / total_commits as f64;
+            table.add(vec![
+                owner.name,
+                owner.email,
+                format!("{}", owner.commits),
+                format!("{}", owner.added),
+                format!("{}", owner.deleted),
+                format!("{:.2}%", percent),
+            ]);
+        }
+        table.foot();
+        table.add(vec![
+            format!("SUM: {total}"),
+            String::new(),
+            format!("{total_commits}"),
+            format!("{total_added}"),
+            format!("{total_deleted}"),
+            String::new(),
+        ]);
+        table.show_as(self.output);
+        Ok(())
+    }
+
     fn show_compare(&self, old: Vec<LanguageStats>, current: Vec<LanguageStats>) {
         let changes = LanguageStatsChange::compare(old, current);
         if changes.is_empty() {
@@ -342,7 +483,7 @@ impl StatsArgs {
             ]);
         }
 
-        table.show();
+        table.show_as(self.output);
     }
 
     fn show_speed(&self, start: Instant, files: usize, lines: usize) {
@@ -396,6 +537,65 @@ impl StatsArgs {
     }
 }
 
+/// The contribution of an author, the same author is identified by the email
+/// (case-insensitive), like `git shortlog -se`.
+#[derive(Debug, PartialEq)]
+struct OwnerStats {
+    name: String,
+    email: String,
+    commits: usize,
+    added: usize,
+    deleted: usize,
+}
+
+impl OwnerStats {
+    /// Parse the output of `git log --format=%x00%aN%x09%aE --numstat`, the
+    /// result is sorted by commits and then changed lines.
+    fn parse(lines: Vec<String>) -> Vec<OwnerStats> {
+        let mut owners: HashMap<String, OwnerStats> = HashMap::new();
+        let mut current: Option<String> = None;
+        for line in lines {
+            if let Some(author) = line.strip_prefix('\0') {
+                let (name, email) = author.split_once('\t').unwrap_or((author, ""));
+                let key = email.to_lowercase();
+                let owner = owners.entry(key.clone()).or_insert_with(|| OwnerStats {
+                    name: name.to_string(),
+                    email: email.to_string(),
+                    commits: 0,
+                    added: 0,
+                    deleted: 0,
+                });
+                owner.commits += 1;
+                current = Some(key);
+                continue;
+            }
+
+            // The numstat line: "{added}\t{deleted}\t{file}", the binary files
+            // are shown as "-".
+            let mut fields = line.split('\t');
+            let (added, deleted) = match (fields.next(), fields.next()) {
+                (Some(added), Some(deleted)) => (added, deleted),
+                _ => continue,
+            };
+            let owner = match current.as_ref().and_then(|key| owners.get_mut(key)) {
+                Some(owner) => owner,
+                None => continue,
+            };
+            owner.added += added.parse::<usize>().unwrap_or(0);
+            owner.deleted += deleted.parse::<usize>().unwrap_or(0);
+        }
+
+        let mut owners: Vec<_> = owners.into_values().collect();
+        owners.sort_unstable_by(|a, b| {
+            b.commits
+                .cmp(&a.commits)
+                .then((b.added + b.deleted).cmp(&(a.added + a.deleted)))
+                .then(a.email.cmp(&b.email))
+        });
+        owners
+    }
+}
+
 struct StatsTask {
     detect_stats: Arc<DetectStats>,
 
@@ -407,3 +607,279 @@ impl Task<Vec<LanguageStats>> for StatsTask {
         self.detect_stats.count(&self.path)
     }
 }
+
+struct WorkspaceTask {
+    detect_stats: Arc<DetectStats>,
+
+    size_ignores: Arc<Vec<GlobPattern>>,
+
+    path: PathBuf,
+}
+
+impl Task<(u64, Vec<LanguageStats>)> for WorkspaceTask {
+    fn run(&self) -> Result<(u64, Vec<LanguageStats>)> {
+        if !self.path.exists() {
+            return Ok((0, Vec::new()));
+        }
+        let size = local::repo_usage(&self.path, &self.size_ignores)?.size;
+        let stats = self.detect_stats.count(&self.path)?;
+        Ok((size, stats))
+    }
+}
+
+/// The count of repositories shown in the most/least visited sections.
+const WORKSPACE_TOP: usize = 5;
+
+#[derive(Debug, Serialize)]
+struct WorkspaceReport {
+    repos: usize,
+    size: u64,
+    remotes: Vec<WorkspaceGroup>,
+    owners: Vec<WorkspaceGroup>,
+    languages: Vec<WorkspaceLanguage>,
+    most_visited: Vec<WorkspaceRepo>,
+    least_visited: Vec<WorkspaceRepo>,
+    stale_days: u64,
+    stale: Vec<WorkspaceRepo>,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceGroup {
+    name: String,
+    repos: usize,
+    size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceLanguage {
+    name: String,
+    files: usize,
+    code: usize,
+    lines: usize,
+    percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct WorkspaceRepo {
+    name: String,
+    size: u64,
+    accessed: u64,
+    last_accessed: u64,
+}
+
+impl WorkspaceReport {
+    fn build(
+        cfg: &Config,
+        stale_days: u64,
+        repos: Vec<Repo>,
+        results: Vec<(u64, Vec<LanguageStats>)>,
+    ) -> Self {
+        let mut remotes: BTreeMap<String, WorkspaceGroup> = BTreeMap::new();
+        let mut owners: BTreeMap<String, WorkspaceGroup> = BTreeMap::new();
+        let mut languages: HashMap<String, WorkspaceLanguage> = HashMap::new();
+        let mut items = Vec::with_capacity(repos.len());
+        let mut total_size: u64 = 0;
+
+        let add_group = |groups: &mut BTreeMap<String, WorkspaceGroup>, name: String, size| {
+            let group = groups.entry(name.clone()).or_insert(WorkspaceGroup {
+                name,
+                repos: 0,
+                size: 0,
+            });
+            group.repos += 1;
+            group.size += size;
+        };
+
+        for (repo, (size, stats)) in repos.into_iter().zip(results) {
+            total_size += size;
+            add_group(&mut remotes, repo.remote.to_string(), size);
+            add_group(&mut owners, format!("{}:{}", repo.remote, repo.owner), size);
+
+            for lang in stats {
+                let lines = lang.blank + lang.comment + lang.code;
+                let item = languages
+                    .entry(lang.name.to_string())
+                    .or_insert(WorkspaceLanguage {
+                        name: lang.name.to_string(),
+                        files: 0,
+                        code: 0,
+                        lines: 0,
+                        percent: 0.0,
+                    });
+                item.files += lang.files;
+                item.code += lang.code;
+                item.lines += lines;
+            }
+
+            items.push(WorkspaceRepo {
+                name: repo.name_with_remote(),
+                size,
+                accessed: repo.accessed,
+                last_accessed: repo.last_accessed,
+            });
+        }
+
+        let mut languages: Vec<_> = languages.into_values().collect();
+        let total_lines: usize = languages.iter().map(|lang| lang.lines).sum();
+        if total_lines > 0 {
+            for lang in languages.iter_mut() {
+                lang.percent = (lang.lines as f64 / total_lines as f64) * 100.0;
+            }
+        }
+        languages.sort_unstable_by_key(|lang| std::cmp::Reverse(lang.lines));
+
+        let stale_time = cfg.now().saturating_sub(stale_days * utils::DAY);
+        let mut stale: Vec<_> = items
+            .iter()
+            .filter(|repo| repo.last_accessed < stale_time)
+            .cloned()
+            .collect();
+        stale.sort_unstable_by_key(|repo| repo.last_accessed);
+
+        items.sort_unstable_by_key(|repo| std::cmp::Reverse(repo.accessed));
+        let most_visited: Vec<_> = items.iter().take(WORKSPACE_TOP).cloned().collect();
+        let least_visited: Vec<_> = items.iter().rev().take(WORKSPACE_TOP).cloned().collect();
+
+        let mut remotes: Vec<_> = remotes.into_values().collect();
+        remotes.sort_by_key(|group| std::cmp::Reverse(group.repos));
+        let mut owners: Vec<_> = owners.into_values().collect();
+        owners.sort_by_key(|group| std::cmp::Reverse(group.repos));
+
+        Self {
+            repos: items.len(),
+            size: total_size,
+            remotes,
+            owners,
+            languages,
+            most_visited,
+            least_visited,
+            stale_days,
+            stale,
+        }
+    }
+
+    fn show(self, cfg: &Config) {
+        eprintln!("Repos: {}", self.repos);
+        eprintln!("Disk Usage: {}", utils::human_bytes(self.size));
+
+        Self::show_section("Remotes");
+        Self::show_groups(self.remotes);
+
+        Self::show_section("Owners");
+        Self::show_groups(self.owners);
+
+        if !self.languages.is_empty() {
+            Self::show_section("Languages");
+            let mut table = Table::with_capacity(self.languages.len() + 1);
+            table.add(vec![
+                String::from("Language"),
+                String::from("files"),
+                String::from("code"),
+                String::from("lines"),
+                String::from("percent"),
+            ]);
+            for lang in self.languages {
+                table.add(vec![
+                    lang.name,
+                    format!("{}", lang.files),
+                    format!("{}", lang.code),
+                    format!("{}", lang.lines),
+                    format!("{:.2}%", lang.percent),
+                ]);
+            }
+            table.show();
+        }
+
+        Self::show_section("Most Visited");
+        Self::show_repos(cfg, self.most_visited);
+
+        Self::show_section("Least Visited");
+        Self::show_repos(cfg, self.least_visited);
+
+        if !self.stale.is_empty() {
+            Self::show_section(&format!("Stale (> {} days)", self.stale_days));
+            Self::show_repos(cfg, self.stale);
+        }
+    }
+
+    fn show_section(name: &str) {
+        eprintln!();
+        eprintln!("{}", style(name).bold());
+    }
+
+    fn show_groups(groups: Vec<WorkspaceGroup>) {
+        let mut table = Table::with_capacity(groups.len() + 1);
+        table.add(vec![
+            String::from("Name"),
+            String::from("Repos"),
+            String::from("Size"),
+        ]);
+        for group in groups {
+            table.add(vec![
+                group.name,
+                format!("{}", group.repos),
+                utils::human_bytes(group.size),
+            ]);
+        }
+        table.show();
+    }
+
+    fn show_repos(cfg: &Config, repos: Vec<WorkspaceRepo>) {
+        let mut table = Table::with_capacity(repos.len() + 1);
+        table.add(vec![
+            String::from("Repo"),
+            String::from("Accessed"),
+            String::from("Last"),
+            String::from("Size"),
+        ]);
+        for repo in repos {
+            table.add(vec![
+                repo.name,
+                format!("{}", repo.accessed),
+                utils::format_since(cfg, repo.last_accessed),
+                utils::human_bytes(repo.size),
+            ]);
+        }
+        table.show();
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::cmd::stats::*;
+
+    #[test]
+    fn test_parse_owners() {
+        let lines = vec![
+            "\0Alice\talice@example.com",
+            "10\t2\tsrc/main.rs",
+            "-\t-\tlogo.png",
+            "",
+            "\0Bob\tbob@example.com",
+            "1\t1\tREADME.md",
+            "\0alice\tAlice@Example.com",
+            "",
+            "3\t0\tsrc/lib.rs",
+        ];
+        let lines = lines.into_iter().map(String::from).collect();
+        assert_eq!(
+            OwnerStats::parse(lines),
+            vec![
+                OwnerStats {
+                    name: String::from("Alice"),
+                    email: String::from("alice@example.com"),
+                    commits: 2,
+                    added: 13,
+                    deleted: 2,
+                },
+                OwnerStats {
+                    name: String::from("Bob"),
+                    email: String::from("bob@example.com"),
+                    commits: 1,
+                    added: 1,
+                    deleted: 1,
+                },
+            ]
+        );
+    }
+}
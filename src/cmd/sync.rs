@@ -9,13 +9,16 @@ use regex::Regex;
 
 use crate::batch::{self, Task};
 use crate::cmd::{Completion, Run};
-use crate::config::{Config, RemoteConfig};
+use crate::config::{Config, DivergeStrategy, RemoteConfig};
 use crate::exec::{Cmd, GitCmd};
-use crate::git::{BranchStatus, GitBranch};
+use crate::git::{self, BranchStatus, GitBranch};
 use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::deps::Dependencies;
+use crate::repo::mirror::Mirrors;
+use crate::repo::usage;
 use crate::repo::{NameLevel, Repo};
 use crate::term;
-use crate::{hashset_strings, utils};
+use crate::{hashset_strings, info, utils};
 
 /// Sync repositories (filter with "sync" label) git branches.
 #[derive(Args)]
@@ -38,8 +41,11 @@ pub struct SyncArgs {
     #[clap(short, long)]
     pub dry_run: bool,
 
-    /// The operations to perform. Available: [push, pull, add, delete, force].
-    #[clap(short, long, default_value = "push,pull,add,delete")]
+    /// The operations to perform. Available: [push, pull, add, delete, force, mirror].
+    /// The "force" op force-pushes the diverged branches, ignoring the diverge
+    /// strategy. The "mirror" op pushes origin to the push mirrors, see the
+    /// `mirror` command.
+    #[clap(short, long, default_value = "push,pull,add,delete,mirror")]
     pub ops: String,
 
     /// Force sync, ignore label "sync".
@@ -49,6 +55,32 @@ pub struct SyncArgs {
     /// Use the labels to filter repository.
     #[clap(short, long)]
     pub labels: Option<String>,
+
+    /// Also sync the dependencies of the selected repositories, see the
+    /// `deps` command.
+    #[clap(short = 'D', long)]
+    pub with_deps: bool,
+
+    /// For forked repositories with an "upstream" remote (see `fork-setup`),
+    /// fetch upstream and fast-forward the default branch into the fork.
+    #[clap(short, long)]
+    pub upstream: bool,
+
+    /// Only rewrite the origin url of the repositories to match the current
+    /// clone protocol config, without syncing branches. The label "sync" is
+    /// ignored.
+    #[clap(long)]
+    pub fix_origin: bool,
+
+    /// The number of repositories to sync concurrently, default is
+    /// `git.workers` in config.
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+
+    /// How to resolve the branches diverged from their upstream, default is
+    /// owner's `repo_diverge` or `diverge`, or `git.diverge` in config.
+    #[clap(long, value_enum)]
+    pub diverge: Option<DivergeStrategy>,
 }
 
 impl Run for SyncArgs {
@@ -70,7 +102,7 @@ impl Run for SyncArgs {
         let db = Database::load(cfg)?;
 
         let filter_labels = utils::parse_labels(&self.labels);
-        let filter_labels = if self.force {
+        let filter_labels = if self.force || self.fix_origin {
             filter_labels
         } else {
             match filter_labels {
@@ -89,31 +121,83 @@ impl Run for SyncArgs {
             .with_many_edit(self.edit);
         let selector = Selector::from_args(&self.head, &self.query, opts);
 
-        let (repos, level) = selector.many_local(&db)?;
+        let (mut repos, mut level) = selector.many_local(&db)?;
+        if self.with_deps {
+            let count = repos.len();
+            repos = Dependencies::load_readonly(cfg)?.extend_repos(&db, repos);
+            if repos.len() > count {
+                level = NameLevel::Remote;
+            }
+        }
         if repos.is_empty() {
             eprintln!("No repo to sync");
             return Ok(());
         }
 
+        if self.fix_origin {
+            return self.fix_origin(cfg, repos, &level);
+        }
+
         let items: Vec<String> = repos.iter().map(|repo| repo.to_string(&level)).collect();
         term::must_confirm_items(&items, "sync", "synchronization", "Repo", "Repos")?;
 
-        let tasks = self.build_tasks(cfg, repos, ops, &level)?;
+        if !self.dry_run {
+            // The synced repos are changed, drop their size caches.
+            for repo in repos.iter() {
+                usage::remove_cache(cfg, repo)?;
+            }
+        }
+
+        let force = ops.contains("force");
+        let mut mirrors = if ops.contains("mirror") {
+            Some(Mirrors::load(cfg)?)
+        } else {
+            None
+        };
+        let tasks = self.build_tasks(cfg, repos, ops, &level, mirrors.as_ref())?;
+        let workers = cfg.git.workers(self.jobs);
 
         if self.dry_run {
-            let results = batch::must_run::<_, Option<String>>("DryRun", tasks)?;
+            let results = batch::must_run_with::<_, Option<String>>("DryRun", tasks, workers)?;
             Self::show_dry_run(results);
             return Ok(());
         }
 
-        batch::must_run::<_, ()>("Sync", tasks)?;
+        let resets = tasks
+            .iter()
+            .filter(|(_, task)| task.diverge == DivergeStrategy::Reset)
+            .count();
+        if resets > 0 && !force {
+            term::must_confirm(format!(
+                "Reset the diverged branches of {resets} repo(s) to upstream, discarding the local commits"
+            ))?;
+        }
+
+        let names: Vec<String> = tasks.iter().map(|(name, _)| name.clone()).collect();
+        let full_names: Vec<String> = tasks
+            .iter()
+            .map(|(_, task)| task.name_with_remote())
+            .collect();
+        let results = batch::must_run_with::<_, SyncResult>("Sync", tasks, workers)?;
+
+        if let Some(mut mirrors) = mirrors.take() {
+            let now = cfg.now();
+            for (name, result) in full_names.iter().zip(results.iter()) {
+                for (remote, error) in result.mirrors.iter() {
+                    mirrors.update(name, remote, now, error.clone());
+                }
+            }
+            mirrors.save()?;
+        }
+
+        Self::show_skipped(names, results);
         Ok(())
     }
 }
 
 impl SyncArgs {
     fn get_all_ops() -> HashSet<String> {
-        hashset_strings!["push", "pull", "add", "delete", "force"]
+        hashset_strings!["push", "pull", "add", "delete", "force", "mirror"]
     }
 
     fn build_tasks(
@@ -122,6 +206,7 @@ impl SyncArgs {
         repos: Vec<Repo>,
         ops: HashSet<String>,
         level: &NameLevel,
+        mirrors: Option<&Mirrors>,
     ) -> Result<Vec<(String, SyncTask)>> {
         let message = Arc::new(self.message.clone());
         let branch_re = Arc::new(GitBranch::get_regex());
@@ -151,6 +236,21 @@ impl SyncArgs {
             owners.insert(owner, owner_arc);
 
             let path = repo.get_path(cfg);
+            let mut diverge = self.get_diverge(cfg, repo);
+            // Resetting discards the local commits, never do it for the protected
+            // repos, even with the confirmation (which is skipped in daemon).
+            let protected = diverge == DivergeStrategy::Reset && repo.is_protected();
+            if protected {
+                diverge = DivergeStrategy::Skip;
+            }
+            let task_mirrors = match mirrors {
+                Some(mirrors) => mirrors
+                    .get(&repo.name_with_remote())
+                    .iter()
+                    .map(|mirror| (mirror.remote.clone(), mirror.url.clone()))
+                    .collect(),
+                None => Vec::new(),
+            };
 
             tasks.push((
                 repo.to_string(level),
@@ -162,12 +262,77 @@ impl SyncArgs {
                     ops: Arc::clone(&ops),
                     branch_re: Arc::clone(&branch_re),
                     message: Arc::clone(&message),
+                    upstream: self.upstream,
+                    diverge,
+                    protected,
+                    bare: repo.is_bare(),
+                    mirrors: task_mirrors,
                 },
             ));
         }
         Ok(tasks)
     }
 
+    /// Priority: `--diverge` flag > owner's `repo_diverge` > owner's `diverge`
+    /// > `git.diverge`.
+    fn get_diverge(&self, cfg: &Config, repo: &Repo) -> DivergeStrategy {
+        if let Some(diverge) = self.diverge {
+            return diverge;
+        }
+        let owner_cfg = repo.remote_cfg.owners.get(repo.owner.as_ref());
+        owner_cfg
+            .and_then(|owner| {
+                owner
+                    .repo_diverge
+                    .get(repo.name.as_ref())
+                    .copied()
+                    .or(owner.diverge)
+            })
+            .unwrap_or(cfg.git.diverge)
+    }
+
+    fn fix_origin(&self, cfg: &Config, repos: Vec<Repo>, level: &NameLevel) -> Result<()> {
+        let mut fixes = Vec::new();
+        for repo in repos {
+            if repo.remote_cfg.clone.is_none() {
+                continue;
+            }
+            let path = format!("{}", repo.get_path(cfg).display());
+            let current = match GitCmd::with_path(&path).read(&["remote", "get-url", "origin"]) {
+                Ok(url) => url,
+                // The repo is not cloned or has no origin, skip it.
+                Err(_) => continue,
+            };
+            let url = repo.clone_url();
+            if current != url {
+                fixes.push((repo.to_string(level), path, current, url));
+            }
+        }
+        if fixes.is_empty() {
+            eprintln!("All origins are up to date");
+            return Ok(());
+        }
+
+        let items: Vec<String> = fixes
+            .iter()
+            .map(|(name, _, current, url)| format!("{name}: {current} -> {url}"))
+            .collect();
+        if self.dry_run {
+            println!("DryRun: {} to fix", utils::plural(&items, "origin"));
+            for item in items {
+                println!("  {item}");
+            }
+            return Ok(());
+        }
+        term::must_confirm_items(&items, "fix origin", "fixing", "Origin", "Origins")?;
+
+        for (name, path, _, url) in fixes {
+            GitCmd::with_path(&path).exec(&["remote", "set-url", "origin", url.as_str()])?;
+            info!("Fix origin of {} to {}", name, url);
+        }
+        Ok(())
+    }
+
     fn show_dry_run(results: Vec<Option<String>>) {
         let mut count: usize = 0;
         for result in results.iter() {
@@ -190,6 +355,32 @@ impl SyncArgs {
         }
     }
 
+    /// Show the branches skipped by the sync tasks, such as the diverged ones,
+    /// and the failed mirrors.
+    fn show_skipped(names: Vec<String>, results: Vec<SyncResult>) {
+        let items: Vec<String> = names
+            .into_iter()
+            .zip(results)
+            .flat_map(|(name, result)| {
+                result
+                    .skipped
+                    .into_iter()
+                    .map(move |branch| format!("{name}: {branch}"))
+            })
+            .collect();
+        if items.is_empty() {
+            return;
+        }
+        println!();
+        println!(
+            "Skipped {}",
+            utils::plural_full(&items, "branch", "branches")
+        );
+        for item in items {
+            println!("  {item}");
+        }
+    }
+
     pub fn completion() -> Completion {
         Completion {
             args: Completion::repo_args,
@@ -214,12 +405,35 @@ struct SyncTask {
     branch_re: Arc<Regex>,
 
     message: Arc<Option<String>>,
+
+    upstream: bool,
+
+    diverge: DivergeStrategy,
+    /// The diverge strategy is reset, but skipped for the protected repo.
+    protected: bool,
+
+    /// The repo is cloned as bare with worktrees, its root has no work tree,
+    /// so only fetch it, the branches are left to the worktrees.
+    bare: bool,
+
+    /// The push mirrors, `(remote, url)`.
+    mirrors: Vec<(String, String)>,
 }
 
-impl Task<()> for SyncTask {
-    fn run(&self) -> Result<()> {
+#[derive(Default)]
+struct SyncResult {
+    /// The skipped branches and the reasons.
+    skipped: Vec<String>,
+
+    /// The mirrors pushed, `(remote, error)`.
+    mirrors: Vec<(String, Option<String>)>,
+}
+
+impl Task<SyncResult> for SyncTask {
+    fn run(&self) -> Result<SyncResult> {
+        let mut result = SyncResult::default();
         if self.remote_cfg.clone.is_none() {
-            return Ok(());
+            return Ok(result);
         }
 
         let need_clone = match fs::read_dir(&self.path) {
@@ -234,18 +448,29 @@ impl Task<()> for SyncTask {
         let git = GitCmd::with_path(&path);
 
         let url = Repo::get_clone_url(self.owner.as_str(), self.name.as_str(), &self.remote_cfg);
-        if need_clone {
-            Cmd::git(&["clone", url.as_str(), path.as_str()]).execute()?;
+        if need_clone && self.bare {
+            // The same layout as `home --bare`.
+            let bare_path = format!("{path}/.bare");
+            Cmd::git(&["clone", "--bare", url.as_str(), bare_path.as_str()])
+                .execute_git_progress()?;
+            git::setup_bare(&self.path)?;
+        } else if need_clone {
+            Cmd::git(&["clone", url.as_str(), path.as_str()]).execute_git_progress()?;
         } else {
             git.exec(&["remote", "set-url", "origin", url.as_str()])?;
             git.exec(&["fetch", "origin", "--prune"])?;
         }
 
-        if let Some(user) = &self.remote_cfg.user {
-            git.exec(&["config", "user.name", user.as_str()])?;
+        for (key, value) in self.remote_cfg.git_configs(&self.owner) {
+            git.exec(&["config", key, value.as_str()])?;
         }
-        if let Some(email) = &self.remote_cfg.email {
-            git.exec(&["config", "user.email", email.as_str()])?;
+
+        if self.bare {
+            result
+                .skipped
+                .push(String::from("all branches (bare repo, only fetched)"));
+            self.push_mirrors(&git, &mut result);
+            return Ok(result);
         }
 
         let lines = git.lines(&["status", "-s"])?;
@@ -273,6 +498,13 @@ impl Task<()> for SyncTask {
             None
         };
 
+        if self.upstream && has_upstream(&git)? {
+            git.exec(&["fetch", "upstream", "--prune"])?;
+            git.checkout(&default_branch)?;
+            let target = format!("upstream/{default_branch}");
+            git.exec(&["merge", "--ff-only", target.as_str()])?;
+        }
+
         let lines = git.lines(&["branch", "-vv"])?;
         for line in lines {
             let branch = GitBranch::parse(&self.branch_re, line.as_str())?;
@@ -308,11 +540,14 @@ impl Task<()> for SyncTask {
                     git.exec(&["branch", "-D", &branch.name])?;
                 }
                 BranchStatus::Conflict => {
-                    if !self.ops.contains("force") {
+                    if self.ops.contains("force") {
+                        git.checkout(&branch.name)?;
+                        git.exec(&["push", "-f"])?;
                         continue;
                     }
-                    git.checkout(&branch.name)?;
-                    git.exec(&["push", "-f"])?;
+                    if let Some(reason) = self.resolve_diverge(&git, &branch.name)? {
+                        result.skipped.push(format!("{} ({reason})", branch.name));
+                    }
                 }
                 BranchStatus::Detached => {
                     if !self.ops.contains("add") {
@@ -328,7 +563,8 @@ impl Task<()> for SyncTask {
         let target = head.as_ref().unwrap_or(&backup_branch);
         git.checkout(target)?;
 
-        Ok(())
+        self.push_mirrors(&git, &mut result);
+        Ok(result)
     }
 }
 
@@ -348,6 +584,80 @@ impl Task<Option<String>> for SyncTask {
 }
 
 impl SyncTask {
+    fn name_with_remote(&self) -> String {
+        format!(
+            "{}:{}/{}",
+            self.remote_cfg.get_name(),
+            self.owner,
+            self.name
+        )
+    }
+
+    fn push_mirrors(&self, git: &GitCmd, result: &mut SyncResult) {
+        for (remote, url) in self.mirrors.iter() {
+            let error = self.push_mirror(git, url);
+            if error.is_some() {
+                result
+                    .skipped
+                    .push(format!("mirror {remote} (push failed)"));
+            }
+            result.mirrors.push((remote.clone(), error));
+        }
+    }
+
+    /// Push the branches and tags of origin to the mirror, return the error
+    /// message (the first line) if failed. The failure does not fail the
+    /// whole repository.
+    fn push_mirror(&self, git: &GitCmd, url: &str) -> Option<String> {
+        let args = [
+            "push",
+            "--prune",
+            url,
+            "+refs/remotes/origin/*:refs/heads/*",
+            "+refs/tags/*:refs/tags/*",
+            "^refs/remotes/origin/HEAD",
+        ];
+        match git.exec(&args) {
+            Ok(()) => None,
+            Err(err) => {
+                let msg = format!("{err:#}");
+                Some(msg.lines().next().unwrap_or_default().to_string())
+            }
+        }
+    }
+
+    /// Resolve the diverged branch with the strategy, return the reason if it
+    /// is skipped. The failed rebase or merge (such as conflicts) is aborted
+    /// and skipped, rather than failing the whole repository.
+    fn resolve_diverge(&self, git: &GitCmd, branch: &str) -> Result<Option<String>> {
+        let (args, abort): (&[&str], &[&str]) = match self.diverge {
+            DivergeStrategy::Skip if self.protected => {
+                return Ok(Some(String::from("diverged, protected repo is not reset")))
+            }
+            DivergeStrategy::Skip => return Ok(Some(String::from("diverged"))),
+            DivergeStrategy::Rebase => (&["rebase", "@{upstream}"], &["rebase", "--abort"]),
+            DivergeStrategy::Merge => (
+                &["merge", "--no-edit", "@{upstream}"],
+                &["merge", "--abort"],
+            ),
+            DivergeStrategy::Reset => {
+                git.checkout(branch)?;
+                git.exec(&["reset", "--hard", "@{upstream}"])?;
+                return Ok(None);
+            }
+        };
+        git.checkout(branch)?;
+        if git.exec(args).is_err() {
+            let _ = git.exec(abort);
+            return Ok(Some(format!("diverged, {} failed and aborted", args[0])));
+        }
+        if !self.ops.contains("push") {
+            return Ok(Some(format!("{}d, but the push op is disabled", args[0])));
+        }
+        git.exec(&["push"])?;
+        Ok(None)
+    }
+
     fn dry_run(&self) -> Result<Option<String>> {
         if self.remote_cfg.clone.as_ref().is_none() {
             return Ok(None);
@@ -355,6 +665,9 @@ impl SyncTask {
 
         match fs::read_dir(&self.path) {
             Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound && self.bare => {
+                return Ok(Some(String::from("clone (bare)")))
+            }
             Err(err) if err.kind() == io::ErrorKind::NotFound => {
                 return Ok(Some(String::from("clone")))
             }
@@ -371,6 +684,13 @@ impl SyncTask {
         git.exec(&["fetch", "origin", "--prune"])?;
 
         let mut actions: Vec<String> = Vec::new();
+        if self.bare {
+            actions.push(String::from("skip branches of bare repo"));
+            for (remote, _) in self.mirrors.iter() {
+                actions.push(format!("push mirror {remote}"));
+            }
+            return Ok(Some(actions.join(", ")));
+        }
 
         let lines = git.lines(&["status", "-s"])?;
         if !lines.is_empty() {
@@ -381,6 +701,17 @@ impl SyncTask {
             }
         }
 
+        if self.upstream && has_upstream(&git)? {
+            git.exec(&["fetch", "upstream", "--prune"])?;
+            let lines = git.lines(&["remote", "show", "origin"])?;
+            let default_branch = GitBranch::parse_default_branch(lines)?;
+            let compare = format!("origin/{default_branch}..upstream/{default_branch}");
+            let count = git.read(&["rev-list", "--count", compare.as_str()])?;
+            if count != "0" {
+                actions.push(format!("fast-forward {default_branch} from upstream"));
+            }
+        }
+
         let current = git.read(&["branch", "--show-current"])?;
         let head_detached = current.is_empty();
 
@@ -410,10 +741,30 @@ impl SyncTask {
                     actions.push(format!("delete {}", branch.name));
                 }
                 BranchStatus::Conflict => {
-                    if !self.ops.contains("force") {
+                    if self.ops.contains("force") {
+                        actions.push(format!("force-push {}", branch.name));
                         continue;
                     }
-                    actions.push(format!("force-push {}", branch.name));
+                    let upstream = format!("{}@{{upstream}}", branch.name);
+                    let range = format!("{}...{upstream}", branch.name);
+                    let counts = git.read(&["rev-list", "--left-right", "--count", &range])?;
+                    let (ahead, behind) = counts.split_once('\t').unwrap_or(("?", "?"));
+                    let counts = format!("ahead {ahead}, behind {behind}");
+                    actions.push(match self.diverge {
+                        DivergeStrategy::Skip if self.protected => format!(
+                            "skip diverged {} ({counts}, protected repo is not reset)",
+                            branch.name
+                        ),
+                        DivergeStrategy::Skip => {
+                            format!("skip diverged {} ({counts})", branch.name)
+                        }
+                        DivergeStrategy::Rebase => format!("rebase {} ({counts})", branch.name),
+                        DivergeStrategy::Merge => format!("merge {} ({counts})", branch.name),
+                        DivergeStrategy::Reset => format!(
+                            "reset {} ({counts}, discard {ahead} local commit(s))",
+                            branch.name
+                        ),
+                    });
                 }
                 BranchStatus::Detached => {
                     if !self.ops.contains("add") {
@@ -425,6 +776,10 @@ impl SyncTask {
             }
         }
 
+        for (remote, _) in self.mirrors.iter() {
+            actions.push(format!("push mirror {remote}"));
+        }
+
         if actions.is_empty() {
             return Ok(None);
         }
@@ -432,3 +787,109 @@ impl SyncTask {
         Ok(Some(actions.join(", ")))
     }
 }
+
+fn has_upstream(git: &GitCmd) -> Result<bool> {
+    let remotes = git.lines(&["remote"])?;
+    Ok(remotes.iter().any(|remote| remote == "upstream"))
+}
+
+#[cfg(test)]
+mod sync_tests {
+    use std::env;
+    use std::fs;
+    use std::sync::Arc;
+
+    use crate::cmd::sync::*;
+    use crate::config::config_tests;
+
+    #[test]
+    fn test_sync_bare() {
+        let cfg = config_tests::load_test_config("sync/bare");
+        let dir = cfg.get_workspace_dir().join("bare");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let origin = format!("{}", dir.join("origin").display());
+        let commit = |message: &str| {
+            Cmd::git(&[
+                "-C",
+                &origin,
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@test.com",
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                message,
+            ])
+            .execute()
+            .unwrap();
+        };
+        Cmd::git(&["init", "-q", "-b", "main", &origin])
+            .execute()
+            .unwrap();
+        commit("first");
+
+        // Redirect the clone url of the remote to the local origin, the owner
+        // is only used by this test.
+        env::set_var("GIT_CONFIG_COUNT", "1");
+        env::set_var("GIT_CONFIG_KEY_0", format!("url.{origin}.insteadOf"));
+        env::set_var(
+            "GIT_CONFIG_VALUE_0",
+            "https://github.com/roxide-test/bare.git",
+        );
+
+        let path = dir.join("roxide-test/bare");
+        let remote_cfg = cfg.must_get_remote("github").unwrap().into_owned();
+        let task = SyncTask {
+            remote_cfg: Arc::new(remote_cfg),
+            owner: Arc::new(String::from("roxide-test")),
+            name: String::from("bare"),
+            path: path.clone(),
+            ops: Arc::new(SyncArgs::get_all_ops()),
+            branch_re: Arc::new(GitBranch::get_regex()),
+            message: Arc::new(None),
+            upstream: false,
+            diverge: DivergeStrategy::Skip,
+            protected: false,
+            bare: true,
+            mirrors: vec![],
+        };
+        let dry_run: Option<String> = Task::run(&task).unwrap();
+        assert_eq!(
+            dry_run.as_deref(),
+            Some("github:roxide-test/bare => clone (bare)")
+        );
+
+        // The missing repo is cloned as bare, the same layout as `home --bare`.
+        let result: SyncResult = Task::run(&task).unwrap();
+        assert_eq!(
+            result.skipped,
+            vec![String::from("all branches (bare repo, only fetched)")]
+        );
+        assert!(path.join(".bare").is_dir());
+        assert_eq!(
+            fs::read_to_string(path.join(".git")).unwrap(),
+            "gitdir: ./.bare\n"
+        );
+        assert!(path.join("main").join(".git").is_file());
+
+        commit("second");
+        let result: SyncResult = Task::run(&task).unwrap();
+        assert_eq!(
+            result.skipped,
+            vec![String::from("all branches (bare repo, only fetched)")]
+        );
+
+        let expect = Cmd::git(&["-C", &origin, "rev-parse", "HEAD"])
+            .read()
+            .unwrap();
+        let path = format!("{}", path.display());
+        let fetched = GitCmd::with_path(&path)
+            .read(&["rev-parse", "origin/main"])
+            .unwrap();
+        assert_eq!(fetched, expect);
+    }
+}
@@ -5,7 +5,8 @@ use crate::cmd::{Completion, CompletionResult, Run};
 use crate::config::Config;
 use crate::confirm;
 use crate::exec::Cmd;
-use crate::git::GitTag;
+use crate::git::{GitTag, TagBump};
+use crate::term;
 
 /// Git tag operations
 #[derive(Args)]
@@ -28,6 +29,24 @@ pub struct TagArgs {
     /// Apply release rule to tag. Enable this will create a new tag and ignore `-c`
     #[clap(short, long)]
     pub rule: Option<String>,
+
+    /// Bump the semantic version of the latest tag (or the given tag), and create
+    /// the new tag. This will ignore `-c`
+    #[clap(short, long)]
+    pub bump: Option<TagBump>,
+
+    /// Create an annotated tag, if `-m` is not provided, the message will be
+    /// edited in the editor
+    #[clap(short, long)]
+    pub annotate: bool,
+
+    /// Create a GPG-signed tag, this implies `-a`
+    #[clap(short, long)]
+    pub sign: bool,
+
+    /// The message of the annotated tag, this implies `-a`
+    #[clap(short, long)]
+    pub message: Option<String>,
 }
 
 impl Run for TagArgs {
@@ -49,7 +68,23 @@ impl Run for TagArgs {
                 new_tag.as_str()
             );
 
-            return self.create_tag(new_tag);
+            return self.create_tag(cfg, new_tag);
+        }
+
+        if let Some(bump) = self.bump {
+            let tag = match self.tag.as_ref() {
+                Some(tag) => GitTag::get(tag),
+                None => GitTag::latest(),
+            }?;
+
+            let new_tag = tag.bump(bump)?;
+            confirm!(
+                "Do you want to release: {} -> {}",
+                tag.as_str(),
+                new_tag.as_str()
+            );
+
+            return self.create_tag(cfg, new_tag);
         }
 
         if self.create {
@@ -57,7 +92,7 @@ impl Run for TagArgs {
                 Some(tag) => GitTag::new(tag),
                 None => bail!("please provide tag to create"),
             };
-            return self.create_tag(tag);
+            return self.create_tag(cfg, tag);
         }
 
         if self.delete {
@@ -100,11 +135,27 @@ impl Run for TagArgs {
 }
 
 impl TagArgs {
-    fn create_tag(&self, tag: GitTag) -> Result<()> {
+    fn create_tag(&self, cfg: &Config, tag: GitTag) -> Result<()> {
         let tags = GitTag::list()?;
         if !tags.iter().any(|t| t.as_str() == tag.as_str()) {
-            Cmd::git(&["tag", tag.as_str()])
-                .with_display_cmd()
+            let mut args = vec!["tag"];
+            let message = if self.annotate || self.sign || self.message.is_some() {
+                let message = match self.message.as_ref() {
+                    Some(message) => message.clone(),
+                    None => term::edit_content(cfg, "", "tag_message", true)?,
+                };
+                args.push(if self.sign { "-s" } else { "-a" });
+                Some(message)
+            } else {
+                None
+            };
+            if let Some(message) = message.as_ref() {
+                args.push("-m");
+                args.push(message.as_str());
+            }
+            args.push(tag.as_str());
+            Cmd::git(&args)
+                .with_display(format!("Create tag {}", tag.as_str()))
                 .execute()?;
         }
         if self.push {
@@ -131,6 +182,11 @@ impl TagArgs {
                     rules.sort();
                     Ok(Some(CompletionResult::from(rules)))
                 }
+                'b' => Ok(Some(CompletionResult::from(vec![
+                    String::from("major"),
+                    String::from("minor"),
+                    String::from("patch"),
+                ]))),
                 _ => Ok(None),
             }),
         }
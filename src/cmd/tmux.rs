@@ -0,0 +1,170 @@
+use std::env;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::exec::Cmd;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::Repo;
+
+/// Create or attach to a tmux session for repository.
+#[derive(Args)]
+pub struct TmuxArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Attach to an existing session by name rather than selecting a repository.
+    #[clap(short, long)]
+    pub target: Option<String>,
+
+    /// Use search instead of fuzzy matching.
+    #[clap(short, long)]
+    pub search: bool,
+}
+
+impl Run for TmuxArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        if let Some(target) = self.target.as_ref() {
+            return Self::attach(target);
+        }
+
+        let mut db = Database::load(cfg)?;
+        let opts = SelectOptions::default()
+            .with_force_search(self.search)
+            .with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let mut repo = selector.must_one(&db)?;
+
+        let path = repo.get_path(cfg);
+        if !path.is_dir() {
+            bail!(
+                "repo '{}' does not exist in local, please use `home` to create it first",
+                repo.name_with_remote()
+            );
+        }
+        let path = format!("{}", path.display());
+
+        let session = Self::session_name(&repo);
+        if !Self::has_session(&session) {
+            Self::create_session(cfg, &session, &path)?;
+        }
+
+        repo.accessed += 1;
+        repo.last_accessed = cfg.now();
+        db.upsert(repo.update());
+        db.save()?;
+
+        Self::attach(&session)
+    }
+}
+
+impl TmuxArgs {
+    /// The session name is `{owner}/{name}`, tmux does not allow "." and ":" in
+    /// session name, replace them with "_".
+    fn session_name(repo: &Repo) -> String {
+        repo.name_with_owner().replace(['.', ':'], "_")
+    }
+
+    fn has_session(session: &str) -> bool {
+        let target = format!("={session}");
+        Cmd::with_args("tmux", &["has-session", "-t", target.as_str()])
+            .execute()
+            .is_ok()
+    }
+
+    fn create_session(cfg: &Config, session: &str, path: &str) -> Result<()> {
+        let windows = &cfg.tmux.windows;
+        let mut args = vec!["new-session", "-d", "-s", session, "-c", path];
+        if let Some(window) = windows.first() {
+            args.extend(["-n", window.name.as_str()]);
+        }
+        Cmd::with_args("tmux", &args)
+            .with_display(format!("Create tmux session {session}"))
+            .execute()?;
+
+        for (idx, window) in windows.iter().enumerate() {
+            // Use window name rather than index as target, because the index is
+            // affected by the `base-index` option of tmux.
+            let target = format!("={session}:{}", window.name);
+            if idx > 0 {
+                let window_target = format!("={session}:");
+                Cmd::with_args(
+                    "tmux",
+                    &[
+                        "new-window",
+                        "-t",
+                        window_target.as_str(),
+                        "-n",
+                        window.name.as_str(),
+                        "-c",
+                        path,
+                    ],
+                )
+                .execute()?;
+            }
+            if let Some(cmd) = window.cmd.as_ref() {
+                // Use `send-keys` rather than passing the command to `new-window`,
+                // so that the window will not be closed after the command exits.
+                Cmd::with_args(
+                    "tmux",
+                    &["send-keys", "-t", target.as_str(), cmd.as_str(), "Enter"],
+                )
+                .execute()?;
+            }
+        }
+        if windows.len() > 1 {
+            let target = format!("={session}:{}", windows[0].name);
+            Cmd::with_args("tmux", &["select-window", "-t", target.as_str()]).execute()?;
+        }
+
+        Ok(())
+    }
+
+    fn attach(session: &str) -> Result<()> {
+        let target = format!("={session}");
+        // Inside tmux, attaching will create a nested session, switch the client
+        // instead.
+        let action = if env::var("TMUX").is_ok() {
+            "switch-client"
+        } else {
+            "attach-session"
+        };
+        let mut cmd = Command::new("tmux");
+        cmd.args([action, "-t", target.as_str()]);
+        cmd.stdout(Stdio::inherit());
+        cmd.stderr(Stdio::inherit());
+        cmd.stdin(Stdio::inherit());
+        let status = cmd.status().context("launch tmux")?;
+        if !status.success() {
+            bail!("tmux {action} exited with {status}");
+        }
+        Ok(())
+    }
+
+    fn list_sessions() -> Result<Vec<String>> {
+        Cmd::with_args("tmux", &["list-sessions", "-F", "#S"]).lines()
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: Some(|_cfg, flag, to_complete| match flag {
+                't' => {
+                    let sessions = Self::list_sessions().unwrap_or_default();
+                    let items: Vec<String> = sessions
+                        .into_iter()
+                        .filter(|session| session.starts_with(to_complete))
+                        .collect();
+                    Ok(Some(CompletionResult::from(items)))
+                }
+                _ => Ok(None),
+            }),
+        }
+    }
+}
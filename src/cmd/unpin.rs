@@ -0,0 +1,51 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::info;
+use crate::repo::database::{Database, SelectOptions, Selector};
+
+/// Unpin repository.
+#[derive(Args)]
+pub struct UnpinArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Use search instead of fuzzy matching.
+    #[clap(short, long)]
+    pub search: bool,
+}
+
+impl Run for UnpinArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let mut db = Database::load(cfg)?;
+
+        let opts = SelectOptions::default()
+            .with_force_search(self.search)
+            .with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let mut repo = selector.must_one(&db)?;
+
+        if repo.pin_priority().is_none() {
+            bail!("repo '{}' is not pinned", repo.name_with_remote());
+        }
+        repo.set_pin_priority(None);
+        info!("Unpin {}", repo.name_with_remote());
+
+        db.upsert(repo.update());
+        db.save()
+    }
+}
+
+impl UnpinArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: None,
+        }
+    }
+}
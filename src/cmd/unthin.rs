@@ -0,0 +1,55 @@
+use anyhow::Result;
+use clap::Args;
+
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::exec::{Cmd, GitCmd};
+use crate::repo::database::{Database, SelectOptions, Selector};
+
+/// Fetch the full history for a thin (shallow) repository.
+#[derive(Args)]
+pub struct UnthinArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+}
+
+impl Run for UnthinArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let mut db = Database::load(cfg)?;
+
+        let opts = SelectOptions::default().with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let mut repo = selector.must_one(&db)?;
+
+        let path = format!("{}", repo.get_path(cfg).display());
+        let shallow = GitCmd::with_path(&path).read(&["rev-parse", "--is-shallow-repository"])?;
+        if shallow == "true" {
+            Cmd::git(&["-C", path.as_str(), "fetch", "--unshallow"])
+                .with_display(format!("Unshallow {}", repo.name_with_remote()))
+                .execute()?;
+        } else {
+            eprintln!("Repo {} is not shallow", repo.name_with_remote());
+        }
+
+        if let Some(labels) = repo.labels.as_mut() {
+            labels.remove("thin");
+            if labels.is_empty() {
+                repo.labels = None;
+            }
+        }
+        db.upsert(repo.update());
+        db.save()
+    }
+}
+
+impl UnthinArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: None,
+        }
+    }
+}
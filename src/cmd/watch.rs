@@ -0,0 +1,333 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use notify::event::{CreateKind, EventKind};
+use notify::{Event, RecursiveMode, Watcher};
+
+use crate::cmd::{Completion, CompletionResult, Run};
+use crate::config::Config;
+use crate::exec::Cmd;
+use crate::interrupt::{self, Interrupt};
+use crate::repo::database::{self, Database};
+use crate::repo::{usage, Repo};
+use crate::{error, info, utils, warn};
+
+/// Watch the workspace to keep the database up to date.
+///
+/// It runs in the foreground, run it with systemd, tmux, etc. as a daemon. It
+/// attaches the newly cloned repositories, keeps the size caches up to date,
+/// and records the visits reported by the shell hook (see `init`).
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Wait for more events these seconds before handling them.
+    #[clap(short, long, default_value = "2")]
+    pub debounce: u64,
+}
+
+/// The file to report the visited directories, the shell hook appends the
+/// `$PWD` to it only when it exists, that is, when the watcher is running.
+pub fn visits_path(cfg: &Config) -> PathBuf {
+    cfg.get_meta_dir().join("watch_visits")
+}
+
+/// The visits file created by the watcher, it is removed when dropped, so that
+/// the shell hook stops writing to it when the watcher exits, even with error.
+struct VisitsFile {
+    path: PathBuf,
+}
+
+impl VisitsFile {
+    fn create(cfg: &Config) -> Result<VisitsFile> {
+        let path = visits_path(cfg);
+        utils::write_file(&path, b"")?;
+        Ok(VisitsFile { path })
+    }
+}
+
+impl Drop for VisitsFile {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Remove visits file: {}", err);
+            }
+        }
+    }
+}
+
+/// The changes collected from the events, between two handlings.
+#[derive(Default)]
+struct Changes {
+    /// The created directories that may be new repositories, such as the
+    /// parent of a created `.git`.
+    repos: HashSet<PathBuf>,
+
+    /// The directories whose entries are changed.
+    dirs: HashSet<PathBuf>,
+
+    /// The visits file is written.
+    visits: bool,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.repos.is_empty() && self.dirs.is_empty() && !self.visits
+    }
+}
+
+struct State<'a> {
+    cfg: &'a Config,
+    visits_path: PathBuf,
+
+    /// The read offset of the visits file.
+    visits_offset: u64,
+    /// The last visited repository, so that entering its sub directories are
+    /// not counted as new visits.
+    last_visit: Option<PathBuf>,
+}
+
+impl Run for WatchArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let workspace = cfg.get_workspace_dir().clone();
+        let visits = VisitsFile::create(cfg)?;
+        let visits_path = visits.path.clone();
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).context("create file watcher")?;
+        watcher
+            .watch(&workspace, RecursiveMode::Recursive)
+            .with_context(|| format!("watch workspace '{}'", workspace.display()))?;
+        watcher
+            .watch(&visits_path, RecursiveMode::NonRecursive)
+            .with_context(|| format!("watch visits file '{}'", visits_path.display()))?;
+        info!(
+            "Watch workspace '{}', press Ctrl-C to stop",
+            workspace.display()
+        );
+
+        let mut state = State {
+            cfg,
+            visits_path,
+            visits_offset: 0,
+            last_visit: None,
+        };
+        let debounce = Duration::from_secs(self.debounce);
+        let interrupt = Interrupt::catch();
+        let mut changes = Changes::default();
+        let mut last_event = Instant::now();
+        loop {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(Ok(event)) => {
+                    state.collect(&mut changes, event);
+                    last_event = Instant::now();
+                }
+                Ok(Err(err)) => warn!("Watch error: {}", err),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if interrupt::is_interrupted() {
+                break;
+            }
+            if changes.is_empty() || last_event.elapsed() < debounce {
+                continue;
+            }
+            let changes = std::mem::take(&mut changes);
+            // The daemon should keep running, only report the errors.
+            if let Err(err) = state.handle(changes) {
+                error!("Handle changes: {:#}", err);
+            }
+        }
+        drop(interrupt);
+        drop(visits);
+
+        info!("Stop watching");
+        Ok(())
+    }
+}
+
+impl State<'_> {
+    fn collect(&self, changes: &mut Changes, event: Event) {
+        for path in event.paths {
+            if path == self.visits_path {
+                changes.visits = true;
+                continue;
+            }
+            if path.file_name().is_some_and(|name| name == ".git") {
+                if matches!(event.kind, EventKind::Create(CreateKind::Folder)) {
+                    if let Some(dir) = path.parent() {
+                        changes.repos.insert(dir.to_path_buf());
+                    }
+                }
+                continue;
+            }
+            // The git internal changes are too noisy, and they rarely modify
+            // files in place.
+            if path.components().any(|part| part.as_os_str() == ".git") {
+                continue;
+            }
+            // The watches of new directories are added asynchronously, so the
+            // `.git` creation inside them can be missed, check them later.
+            if matches!(event.kind, EventKind::Create(CreateKind::Folder)) {
+                changes.repos.insert(path.clone());
+            }
+            if let Some(dir) = path.parent() {
+                changes.dirs.insert(dir.to_path_buf());
+            }
+        }
+    }
+
+    fn handle(&mut self, changes: Changes) -> Result<()> {
+        // The ordinary file changes only invalidate the usage caches, do not
+        // lock (and rewrite) the database for them.
+        if !changes.dirs.is_empty() {
+            let db = Database::load_readonly(self.cfg)?;
+            self.invalidate_usage(&db, changes.dirs)?;
+        }
+        if changes.repos.is_empty() && !changes.visits {
+            return Ok(());
+        }
+
+        let mut db = Database::load(self.cfg)?;
+        let mut updated = false;
+        for dir in changes.repos {
+            updated |= self.attach(&mut db, &dir)?;
+        }
+        if changes.visits {
+            updated |= self.record_visits(&mut db)?;
+        }
+        if updated {
+            db.save()?;
+        }
+        Ok(())
+    }
+
+    /// Attach the new repo in `dir` to the database, return `true` if attached.
+    fn attach(&self, db: &mut Database, dir: &Path) -> Result<bool> {
+        if !dir.join(".git").exists() || db.get_by_path(dir).is_some() {
+            return Ok(false);
+        }
+        let (remote, owner, name) = match database::parse_workspace_path(self.cfg, dir) {
+            Some(parsed) => parsed,
+            None => return Ok(false),
+        };
+        if self.cfg.get_remote(&remote).is_none() {
+            return Ok(false);
+        }
+        let repo = Repo::new(
+            self.cfg,
+            Cow::Owned(remote),
+            Cow::Owned(owner),
+            Cow::Owned(name),
+            None,
+        )?;
+        let path = format!("{}", dir.display());
+        for (key, value) in repo.remote_cfg.git_configs(repo.owner.as_ref()) {
+            Cmd::git(&["-C", path.as_str(), "config", key, value.as_str()]).execute()?;
+        }
+        info!("Attach {}", repo.name_with_remote());
+        db.upsert(repo.update());
+        Ok(true)
+    }
+
+    fn invalidate_usage(&self, db: &Database, dirs: HashSet<PathBuf>) -> Result<()> {
+        let mut caches: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+        for dir in dirs {
+            let repo = match db.get_by_path(&dir) {
+                Some(repo) => repo,
+                None => continue,
+            };
+            let rel = match dir.strip_prefix(repo.get_path(self.cfg)) {
+                Ok(rel) => rel.to_string_lossy().into_owned(),
+                Err(_) => continue,
+            };
+            caches
+                .entry(usage::cache_path(self.cfg, &repo))
+                .or_default()
+                .insert(rel);
+        }
+        for (cache_path, dirs) in caches {
+            usage::invalidate_dirs(&cache_path, &dirs)?;
+        }
+        Ok(())
+    }
+
+    /// Record the new visits in the visits file, return `true` if any repo is
+    /// visited.
+    fn record_visits(&mut self, db: &mut Database) -> Result<bool> {
+        let mut file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.visits_path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err).context("open visits file"),
+        };
+        let len = file.metadata().context("read visits file metadata")?.len();
+        if len < self.visits_offset {
+            // The file is truncated.
+            self.visits_offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.visits_offset))
+            .context("seek visits file")?;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .context("system clock set to invalid time")?
+            .as_secs();
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        let mut visited = false;
+        loop {
+            line.clear();
+            let size = reader.read_line(&mut line).context("read visits file")?;
+            // Leave the incomplete line to the next time.
+            if size == 0 || !line.ends_with('\n') {
+                break;
+            }
+            self.visits_offset += size as u64;
+
+            let mut repo = match db.get_by_path(Path::new(line.trim())) {
+                Some(repo) => repo.update(),
+                None => {
+                    self.last_visit = None;
+                    continue;
+                }
+            };
+            let path = repo.get_path(self.cfg);
+            if self.last_visit.as_ref() == Some(&path) {
+                continue;
+            }
+            self.last_visit = Some(path);
+            repo.accessed += 1;
+            repo.last_accessed = now;
+            db.upsert(repo);
+            visited = true;
+        }
+
+        // Truncate the file after all the lines are read, so that it won't grow
+        // forever.
+        let file = reader.into_inner();
+        let len = file.metadata().context("read visits file metadata")?.len();
+        if len == self.visits_offset {
+            file.set_len(0).context("truncate visits file")?;
+            self.visits_offset = 0;
+        }
+        Ok(visited)
+    }
+}
+
+impl WatchArgs {
+    pub fn completion() -> Completion {
+        Completion {
+            args: |_cfg, _args| Ok(CompletionResult::empty()),
+            flags: None,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::cmd::{Completion, Run};
+use crate::config::Config;
+use crate::exec::GitCmd;
+use crate::repo::database::{Database, SelectOptions, Selector};
+use crate::repo::Repo;
+use crate::{clipboard, info};
+
+/// Print the path or url of a repository, for composing with other tools.
+#[derive(Args)]
+pub struct WhichArgs {
+    /// Repository selection head.
+    pub head: Option<String>,
+
+    /// Repository selection query.
+    pub query: Option<String>,
+
+    /// Print the clone url (respect the `ssh` config).
+    #[clap(short, long)]
+    pub url: bool,
+
+    /// Print the SSH clone url.
+    #[clap(short, long)]
+    pub ssh: bool,
+
+    /// Print the web url.
+    #[clap(short, long)]
+    pub web: bool,
+
+    /// Print the path relative to the workspace.
+    #[clap(short, long)]
+    pub relative: bool,
+
+    /// Append the current branch of the repository. For web url, this is the
+    /// branch tree url; for others, this is `{output} {branch}`.
+    #[clap(short, long)]
+    pub branch: bool,
+
+    /// Copy the output to clipboard rather than printing it.
+    #[clap(long)]
+    pub copy: bool,
+}
+
+impl Run for WhichArgs {
+    fn run(&self, cfg: &Config) -> Result<()> {
+        let db = Database::load_readonly(cfg)?;
+        let opts = SelectOptions::default().with_force_local(true);
+        let selector = Selector::from_args(&self.head, &self.query, opts);
+        let repo = selector.must_one(&db)?;
+
+        let path = repo.get_path(cfg);
+        let branch = if self.branch {
+            let path = format!("{}", path.display());
+            let branch = GitCmd::with_path(&path).read(&["branch", "--show-current"])?;
+            if branch.is_empty() {
+                bail!("the repo is in detached HEAD, no current branch");
+            }
+            Some(branch)
+        } else {
+            None
+        };
+
+        let output = if self.url {
+            Self::require_url(
+                &repo,
+                repo.remote_cfg.clone.as_ref().map(|_| repo.clone_url()),
+            )?
+        } else if self.ssh {
+            Self::require_url(&repo, repo.ssh_url())?
+        } else if self.web {
+            let url = Self::require_url(&repo, repo.web_url())?;
+            match branch {
+                Some(branch) => return self.output(format!("{url}/tree/{branch}")),
+                None => url,
+            }
+        } else if self.relative {
+            match path.strip_prefix(cfg.get_workspace_dir()) {
+                Ok(relative) => format!("{}", relative.display()),
+                Err(_) => bail!(
+                    "the repo '{}' is not in the workspace",
+                    repo.name_with_remote()
+                ),
+            }
+        } else {
+            format!("{}", path.display())
+        };
+
+        match branch {
+            Some(branch) => self.output(format!("{output} {branch}")),
+            None => self.output(output),
+        }
+    }
+}
+
+impl WhichArgs {
+    fn output(&self, output: String) -> Result<()> {
+        if self.copy {
+            clipboard::copy(&output)?;
+            info!("Copied {} to clipboard", output);
+            return Ok(());
+        }
+        println!("{output}");
+        Ok(())
+    }
+
+    fn require_url(repo: &Repo, url: Option<String>) -> Result<String> {
+        match url {
+            Some(url) => Ok(url),
+            None => bail!(
+                "the remote of repo '{}' has no clone domain",
+                repo.name_with_remote()
+            ),
+        }
+    }
+
+    pub fn completion() -> Completion {
+        Completion {
+            args: Completion::repo_args,
+            flags: None,
+        }
+    }
+}
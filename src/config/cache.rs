@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+/// The parsed config items (remotes, workflows and scaffoldings), cached with
+/// the modified time and size of their files, so that only the changed files
+/// are parsed again. Parsing dozens of toml files on every invocation slows
+/// down the hot paths, such as completion and `which`.
+///
+/// The cache is stored in `{config_dir}/.cache`, and is dropped when roxide is
+/// rebuilt, since the layout of the config types might change. All the errors
+/// are ignored, a broken cache just makes the files to be parsed again.
+pub struct ItemsCache {
+    path: PathBuf,
+
+    entries: HashMap<String, CacheEntry>,
+
+    /// The files loaded this time, the cache of the removed files are dropped
+    /// when saving.
+    used: HashSet<String>,
+
+    dirty: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    build: String,
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    modified: u128,
+    size: u64,
+    data: Vec<u8>,
+}
+
+impl ItemsCache {
+    const BUILD: &'static str = env!("VERGEN_BUILD_TIMESTAMP");
+
+    pub fn load(root: &Path) -> ItemsCache {
+        let path = root.join(".cache");
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|data| bincode::deserialize::<CacheFile>(&data).ok())
+            .filter(|file| file.build == Self::BUILD)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+        ItemsCache {
+            path,
+            entries,
+            used: HashSet::new(),
+            dirty: false,
+        }
+    }
+
+    /// Return the cached item if the file is not changed since it was cached.
+    pub fn get<T: DeserializeOwned>(&mut self, path: &Path, meta: &Metadata) -> Option<T> {
+        let key = format!("{}", path.display());
+        let modified = Self::modified(meta)?;
+        let entry = self.entries.get(&key)?;
+        if entry.modified != modified || entry.size != meta.len() {
+            return None;
+        }
+        let item = bincode::deserialize(&entry.data).ok()?;
+        self.used.insert(key);
+        Some(item)
+    }
+
+    pub fn put<T: Serialize>(&mut self, path: &Path, meta: &Metadata, item: &T) {
+        let modified = match Self::modified(meta) {
+            Some(modified) => modified,
+            None => return,
+        };
+        let data = match bincode::serialize(item) {
+            Ok(data) => data,
+            Err(_) => return,
+        };
+        let key = format!("{}", path.display());
+        self.entries.insert(
+            key.clone(),
+            CacheEntry {
+                modified,
+                size: meta.len(),
+                data,
+            },
+        );
+        self.used.insert(key);
+        self.dirty = true;
+    }
+
+    /// Write the cache if it is changed, or some cached files were removed.
+    pub fn save(mut self) {
+        let count = self.entries.len();
+        self.entries.retain(|key, _| self.used.contains(key));
+        if !self.dirty && self.entries.len() == count {
+            return;
+        }
+        let file = CacheFile {
+            build: String::from(Self::BUILD),
+            entries: self.entries,
+        };
+        if let Ok(data) = bincode::serialize(&file) {
+            let _ = utils::write_file_atomic(&self.path, &data);
+        }
+    }
+
+    fn modified(meta: &Metadata) -> Option<u128> {
+        let modified = meta.modified().ok()?;
+        let modified = modified.duration_since(UNIX_EPOCH).ok()?;
+        Some(modified.as_nanos())
+    }
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use std::fs;
+
+    use crate::config::cache::*;
+    use crate::config::RemoteConfig;
+
+    #[test]
+    fn test_items_cache() {
+        let root = PathBuf::from("/tmp/test-roxide/config_cache");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let path = root.join("github.toml");
+        fs::write(&path, "clone = \"github.com\"\n").unwrap();
+        let meta = fs::metadata(&path).unwrap();
+        let remote: RemoteConfig = toml::from_str("clone = \"github.com\"\n").unwrap();
+
+        let mut cache = ItemsCache::load(&root);
+        assert!(cache.get::<RemoteConfig>(&path, &meta).is_none());
+        cache.put(&path, &meta, &remote);
+        cache.save();
+
+        let mut cache = ItemsCache::load(&root);
+        assert_eq!(cache.get::<RemoteConfig>(&path, &meta), Some(remote));
+
+        // The changed file is not hit.
+        fs::write(&path, "clone = \"github.com\"\nuser = \"test\"\n").unwrap();
+        let meta = fs::metadata(&path).unwrap();
+        assert!(cache.get::<RemoteConfig>(&path, &meta).is_none());
+    }
+}
@@ -1,10 +1,33 @@
 use std::collections::HashMap;
 
+use crate::config::ApiCache;
+use crate::config::ApiStyle;
+use crate::config::Audit;
+use crate::config::Backup;
+use crate::config::Daemon;
 use crate::config::Detect;
+use crate::config::DivergeStrategy;
 use crate::config::Docker;
+use crate::config::Git;
+use crate::config::Log;
+use crate::config::ProtectPolicy;
 use crate::config::RemoteConfig;
+use crate::config::Scan;
+use crate::config::Size;
+use crate::config::Tmux;
+use crate::config::S3;
+use crate::log::Level;
 use crate::utils;
 
+/// The fully-commented sample of the base config.
+pub const CONFIG_TOML: &str = include_str!("../../config/config.toml");
+
+/// The fully-commented sample of a remote config.
+pub const REMOTE_TOML: &str = include_str!("../../config/remotes/github.toml");
+
+/// The sample of a workflow config.
+pub const WORKFLOW_TOML: &str = include_str!("../../config/workflows/cargo.toml");
+
 pub fn workspace() -> String {
     String::from("~/dev")
 }
@@ -36,27 +59,171 @@ pub fn detect() -> Detect {
     }
 }
 
+pub fn size() -> Size {
+    Size {
+        ignores: empty_vec(),
+    }
+}
+
+pub fn scan() -> Scan {
+    Scan {
+        workers: 0,
+        idle: false,
+    }
+}
+
+pub fn log() -> Log {
+    Log {
+        file: false,
+        level: log_level(),
+        json: false,
+        max_size: log_max_size(),
+        max_files: log_max_files(),
+    }
+}
+
+pub fn log_level() -> Level {
+    Level::Info
+}
+
+pub fn log_max_size() -> u64 {
+    10
+}
+
+pub fn log_max_files() -> usize {
+    3
+}
+
+pub fn audit() -> Audit {
+    Audit {
+        retention_days: audit_retention_days(),
+    }
+}
+
+pub fn audit_retention_days() -> u64 {
+    90
+}
+
+pub fn api_cache() -> ApiCache {
+    ApiCache {
+        max_size: api_cache_max_size(),
+    }
+}
+
+pub fn api_cache_max_size() -> u64 {
+    50
+}
+
+pub fn backup() -> Backup {
+    Backup {
+        target: empty_string(),
+        bundle: disable(),
+    }
+}
+
+pub fn s3() -> S3 {
+    S3 {
+        endpoint: empty_string(),
+        region: s3_region(),
+        bucket: empty_string(),
+        access_key: empty_string(),
+        secret_key: empty_string(),
+        virtual_host: disable(),
+    }
+}
+
+pub fn s3_region() -> String {
+    String::from("us-east-1")
+}
+
+pub fn tmux() -> Tmux {
+    Tmux {
+        windows: empty_vec(),
+    }
+}
+
+pub fn daemon() -> Daemon {
+    Daemon { jobs: empty_vec() }
+}
+
+pub fn git() -> Git {
+    Git {
+        retry: git_retry(),
+        retry_backoff: git_retry_backoff(),
+        workers: 0,
+        diverge: diverge_strategy(),
+        issue_branch: issue_branch(),
+    }
+}
+
+pub fn issue_branch() -> String {
+    String::from("{id}-{slug}")
+}
+
+pub fn diverge_strategy() -> DivergeStrategy {
+    DivergeStrategy::Skip
+}
+
+pub fn protect_policy() -> ProtectPolicy {
+    ProtectPolicy::Confirm
+}
+
+pub fn git_retry() -> u32 {
+    2
+}
+
+pub fn git_retry_backoff() -> u64 {
+    1
+}
+
 pub fn remote(remote: impl AsRef<str>) -> RemoteConfig {
     RemoteConfig {
         clone: None,
         user: None,
         email: None,
+        signing_key: None,
+        gpg_sign: None,
         icon: None,
         ssh: false,
+        clone_protocol: None,
         labels: None,
         provider: None,
         token: None,
         cache_hours: cache_hours(),
+        complete_cache: false,
         list_limit: list_limit(),
         api_timeout: api_timeout(),
+        api_style: api_style(),
+        proxy: None,
+        no_proxy: None,
+        ca_bundle: None,
+        insecure_skip_verify: false,
+        oauth: None,
         api_domain: None,
         owners: empty_map(),
+        env: None,
+        commit: None,
+        merge_template: None,
         name: Some(remote.as_ref().to_string()),
         alias_owner_map: None,
         alias_repo_map: None,
     }
 }
 
+pub fn commit_types() -> Vec<String> {
+    [
+        "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore",
+        "revert",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+pub fn commit_max_summary() -> usize {
+    72
+}
+
 pub fn docker_name() -> String {
     String::from("docker")
 }
@@ -69,6 +236,10 @@ pub fn keyword_expire() -> u64 {
     utils::DAY
 }
 
+pub fn lock_timeout() -> u64 {
+    3000
+}
+
 pub fn empty_map<K, V>() -> HashMap<K, V> {
     HashMap::new()
 }
@@ -97,6 +268,10 @@ pub fn step_work_dir() -> String {
     String::from("/work")
 }
 
+pub fn step_retries() -> u32 {
+    0
+}
+
 pub fn cache_hours() -> u32 {
     24
 }
@@ -109,6 +284,14 @@ pub fn api_timeout() -> u64 {
     10
 }
 
+pub fn api_style() -> ApiStyle {
+    ApiStyle::Rest
+}
+
+pub fn oauth_scopes() -> String {
+    String::from("repo read:org notifications workflow")
+}
+
 pub fn disable() -> bool {
     false
 }
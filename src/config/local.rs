@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use glob::Pattern as GlobPattern;
+use serde::Deserialize;
+
+use crate::config::{defaults, Config};
+use crate::utils::{self, DirUsage};
+
+/// The local config in the root of a repository, named `.roxide.toml`. It
+/// extends the global ignores, so that the vendored directories can be
+/// excluded only where needed.
+#[derive(Debug, Deserialize, Default)]
+pub struct LocalConfig {
+    /// Extend the global `detect.ignores`.
+    #[serde(default = "defaults::empty_vec")]
+    pub detect_ignores: Vec<String>,
+
+    /// Extend the global `size.ignores`.
+    #[serde(default = "defaults::empty_vec")]
+    pub size_ignores: Vec<String>,
+
+    /// Override the commands of `run --task`, such as `build = "make"`.
+    #[serde(default = "defaults::empty_map")]
+    pub tasks: HashMap<String, String>,
+
+    /// The dev container of the repository, see `container`.
+    #[serde(default)]
+    pub container: LocalContainer,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct LocalContainer {
+    /// The devcontainer config, compose file or Dockerfile, relative to the
+    /// repository root. Default is detected.
+    pub file: Option<String>,
+
+    /// The compose service to enter, default is the first one.
+    pub service: Option<String>,
+
+    /// The shell to enter, default is `docker.shell` in config.
+    pub shell: Option<String>,
+
+    /// Where the repository is mounted in the container built from the
+    /// Dockerfile, default is `/work`.
+    pub work_dir: Option<String>,
+}
+
+impl LocalConfig {
+    pub const FILE_NAME: &'static str = ".roxide.toml";
+
+    /// Load the local config of the repository in `path`, return the default
+    /// (empty) one if the file does not exist.
+    pub fn load(path: &Path) -> Result<LocalConfig> {
+        let path = path.join(Self::FILE_NAME);
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read local config '{}'", path.display()))
+            }
+        };
+        toml::from_str(&data).with_context(|| format!("parse local config '{}'", path.display()))
+    }
+
+    /// Return the detect ignores, merged with the `global` ones.
+    pub fn detect_ignores(&self, global: &[GlobPattern]) -> Result<Vec<GlobPattern>> {
+        Self::merge(global, &self.detect_ignores).context("parse local detect ignores")
+    }
+
+    /// Return the size ignores, merged with the `global` ones.
+    pub fn size_ignores(&self, global: &[GlobPattern]) -> Result<Vec<GlobPattern>> {
+        Self::merge(global, &self.size_ignores).context("parse local size ignores")
+    }
+
+    fn merge(global: &[GlobPattern], local: &[String]) -> Result<Vec<GlobPattern>> {
+        let mut patterns = global.to_vec();
+        patterns.extend(Config::parse_patterns(local)?);
+        Ok(patterns)
+    }
+}
+
+/// Return the disk usage of the repository in `path`, the files matched by the
+/// global and local size ignores are excluded.
+pub fn repo_usage(path: &Path, global_ignores: &[GlobPattern]) -> Result<DirUsage> {
+    let ignores = LocalConfig::load(path)?.size_ignores(global_ignores)?;
+    utils::dir_usage(path.to_path_buf(), &ignores)
+}
+
+#[cfg(test)]
+mod local_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::config::local::*;
+    use crate::utils;
+
+    #[test]
+    fn test_repo_usage() {
+        let path = PathBuf::from("_test/local_config");
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        utils::write_file(&path.join("src").join("main.rs"), b"fn main() {}").unwrap();
+        utils::write_file(&path.join("vendor").join("lib.rs"), b"pub fn lib() {}").unwrap();
+        utils::write_file(&path.join("target").join("bin"), b"0123456789").unwrap();
+
+        let local = b"size_ignores = [\"vendor\"]\n";
+        utils::write_file(&path.join(LocalConfig::FILE_NAME), local).unwrap();
+
+        let global = vec![GlobPattern::new("target").unwrap()];
+        let usage = repo_usage(&path, &global).unwrap();
+        assert_eq!(usage.size, (12 + local.len()) as u64);
+        assert_eq!(usage.files, 2);
+        // The root is not walked, so the inodes are: src, main.rs, .roxide.toml
+        assert_eq!(usage.inodes, 3);
+
+        fs::hard_link(path.join("src/main.rs"), path.join("main.rs")).unwrap();
+        assert_eq!(repo_usage(&path, &global).unwrap(), usage);
+
+        let local = LocalConfig::load(&path).unwrap();
+        assert_eq!(local.size_ignores, vec![String::from("vendor")]);
+        assert!(local.detect_ignores(&global).unwrap().len() == 1);
+
+        let empty = LocalConfig::load(&path.join("src")).unwrap();
+        assert!(empty.size_ignores.is_empty());
+    }
+}
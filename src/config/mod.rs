@@ -1,4 +1,6 @@
+pub mod cache;
 pub mod defaults;
+pub mod local;
 
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
@@ -7,11 +9,17 @@ use std::time::SystemTime;
 use std::{env, fs, io};
 
 use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
 use glob::Pattern as GlobPattern;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use crate::utils;
+use crate::config::cache::ItemsCache;
+use crate::errors::ErrorKind;
+use crate::git::ConventionalCommit;
+use crate::log::Level;
+use crate::schedule::Schedule;
+use crate::{utils, warn};
 
 /// The basic configuration, defining some global behaviors of roxide.
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -39,16 +47,86 @@ pub struct Config {
     #[serde(default = "defaults::detect")]
     pub detect: Detect,
 
+    /// The disk usage scanning.
+    #[serde(default = "defaults::size")]
+    pub size: Size,
+
+    /// The resources used by the concurrent scans, such as stats.
+    #[serde(default = "defaults::scan")]
+    pub scan: Scan,
+
+    /// The log file, for diagnosing the intermittent issues.
+    #[serde(default = "defaults::log")]
+    pub log: Log,
+
+    /// The audit history of the state-changing commands.
+    #[serde(default = "defaults::audit")]
+    pub audit: Audit,
+
+    /// The disk quota of the remote api cache, see `cache_hours` in remote.
+    #[serde(default = "defaults::api_cache")]
+    pub api_cache: ApiCache,
+
+    /// The workspace backup, see `backup` command.
+    #[serde(default = "defaults::backup")]
+    pub backup: Backup,
+
+    /// The S3-compatible object storage, used as the target of secrets and
+    /// backups. Requires the `s3` feature.
+    #[serde(default = "defaults::s3")]
+    pub s3: S3,
+
     #[serde(default = "defaults::docker")]
     pub docker: Docker,
 
     #[serde(default = "defaults::keyword_expire")]
     pub keyword_expire: u64,
 
+    /// The milliseconds to wait for the metadata (such as the database) locked
+    /// by another roxide process, 0 means failing immediately.
+    #[serde(default = "defaults::lock_timeout")]
+    pub lock_timeout: u64,
+
     /// The tag release rule.
     #[serde(default = "defaults::release")]
     pub release: HashMap<String, String>,
 
+    /// The editors to open repository with `edit` command, the key is the editor
+    /// name.
+    #[serde(default = "defaults::empty_map")]
+    pub editors: HashMap<String, EditorConfig>,
+
+    /// The default editor name for `edit` command. Can be overridden by owner's
+    /// `editor` and `repo_editor`.
+    pub editor: Option<String>,
+
+    /// The window layout of tmux session created by `tmux` command.
+    #[serde(default = "defaults::tmux")]
+    pub tmux: Tmux,
+
+    /// The scheduled jobs run by the `daemon` command.
+    #[serde(default = "defaults::daemon")]
+    pub daemon: Daemon,
+
+    /// What to do when a destructive operation (such as `remove` and `reset`)
+    /// hits a protected repository. See [`ProtectPolicy`].
+    #[serde(default = "defaults::protect_policy")]
+    pub protect_policy: ProtectPolicy,
+
+    /// The behavior of git commands.
+    #[serde(default = "defaults::git")]
+    pub git: Git,
+
+    /// The named workspace profiles, such as `work` and `personal`. See
+    /// [`ProfileConfig`].
+    #[serde(default = "defaults::empty_map")]
+    pub profiles: HashMap<String, ProfileConfig>,
+
+    /// The extra languages to detect and count stats, they take precedence
+    /// over the builtin ones. See [`LanguageConfig`].
+    #[serde(default = "defaults::empty_vec")]
+    pub languages: Vec<LanguageConfig>,
+
     /// The remotes' config.
     #[serde(skip)]
     pub remotes: HashMap<String, RemoteConfig>,
@@ -56,6 +134,9 @@ pub struct Config {
     #[serde(skip)]
     pub detect_ignores: Vec<GlobPattern>,
 
+    #[serde(skip)]
+    pub size_ignores: Vec<GlobPattern>,
+
     /// Workflow can execute some pre-defined scripts on the repo.
     #[serde(skip)]
     pub workflows: HashMap<String, WorkflowConfig>,
@@ -82,6 +163,30 @@ pub struct Config {
 
     #[serde(skip)]
     pub is_default: bool,
+
+    /// The active profile name, [`None`] means no profile is used.
+    #[serde(skip)]
+    pub profile: Option<String>,
+
+    /// The remotes hidden by the active profile, see [`ProfileConfig::remotes`].
+    #[serde(skip)]
+    pub hidden_remotes: HashMap<String, RemoteConfig>,
+}
+
+/// A workspace profile. When a profile is active, its workspace and metadir
+/// replace the global ones, so that the repositories and database of different
+/// contexts stay isolated.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ProfileConfig {
+    /// The workspace of this profile.
+    pub workspace: String,
+
+    /// The metadir of this profile, default is `{metadir}/profiles/{name}`.
+    pub metadir: Option<String>,
+
+    /// The remotes available in this profile, empty means all.
+    #[serde(default = "defaults::empty_vec")]
+    pub remotes: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
@@ -96,6 +201,117 @@ pub struct Docker {
     pub shell: String,
 }
 
+/// An editor (or IDE) profile to open repository.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct EditorConfig {
+    /// The command to open the repository, such as `code {path}`, `nvim`, etc.
+    /// The `{path}` placeholder will be replaced with the repository path, if
+    /// there is no placeholder, the path will be appended to the command.
+    pub cmd: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Tmux {
+    /// The windows to create in the new session, if empty, the session will only
+    /// have one default window.
+    #[serde(default = "defaults::empty_vec")]
+    pub windows: Vec<TmuxWindow>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Daemon {
+    #[serde(default = "defaults::empty_vec")]
+    pub jobs: Vec<DaemonJob>,
+}
+
+/// A job run by the daemon periodically, such as syncing all the repositories
+/// every night.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct DaemonJob {
+    pub name: String,
+
+    /// The cron-like schedule, such as `0 3 * * *`. See
+    /// [`crate::schedule::Schedule`].
+    pub schedule: String,
+
+    /// The roxide arguments to run, such as `["sync", "-o", "pull"]`. The job runs
+    /// without confirmation.
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct TmuxWindow {
+    pub name: String,
+
+    /// The command to run in the window, optional.
+    pub cmd: Option<String>,
+}
+
+/// The policy for destructive operations on protected repositories. A repository
+/// is protected if it has label `protected`, or its name matches the owner's
+/// `protect` patterns.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtectPolicy {
+    /// Require typing the repository name to confirm.
+    Confirm,
+    /// Refuse the operation entirely.
+    Refuse,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Git {
+    /// The max retry times for git network operations (clone, fetch, pull and
+    /// push) that failed with transient network errors. 0 means no retry.
+    #[serde(default = "defaults::git_retry")]
+    pub retry: u32,
+
+    /// The wait time (seconds) before the first retry, it will be doubled for
+    /// each next retry.
+    #[serde(default = "defaults::git_retry_backoff")]
+    pub retry_backoff: u64,
+
+    /// The number of concurrent git operations in the batch commands (such as
+    /// `sync`, `stash` and `import`), `0` means the number of cpu cores. They
+    /// mostly wait for the network, so a larger number (such as `32`) can make
+    /// them much faster. Can be overridden by the `--jobs` flag of `sync`.
+    #[serde(default)]
+    pub workers: usize,
+
+    /// How `sync` resolves the branches diverged from their upstream, can be
+    /// overridden by owner's `diverge` and `repo_diverge`, and the `--diverge`
+    /// flag of `sync`.
+    #[serde(default = "defaults::diverge_strategy")]
+    pub diverge: DivergeStrategy,
+
+    /// The branch name pattern for `branch --issue`, `{id}` is the issue
+    /// number, and `{slug}` is generated from the issue title.
+    #[serde(default = "defaults::issue_branch")]
+    pub issue_branch: String,
+}
+
+impl Git {
+    pub fn workers(&self, jobs: Option<usize>) -> usize {
+        jobs.unwrap_or(self.workers)
+    }
+}
+
+/// The strategy to resolve a local branch diverged from its upstream (both have
+/// their own commits) in `sync`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DivergeStrategy {
+    /// Leave the branch as is, and report it in the summary.
+    Skip,
+    /// Rebase the local commits onto the upstream, then push.
+    Rebase,
+    /// Merge the upstream into the branch, then push.
+    Merge,
+    /// Reset the branch to the upstream, the local commits are discarded. This
+    /// requires confirmation, and the protected repos are skipped.
+    Reset,
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Detect {
     #[serde(default = "defaults::disable")]
@@ -105,6 +321,158 @@ pub struct Detect {
     pub ignores: Vec<String>,
 }
 
+/// A user-defined language, so that the niche languages or internal DSLs can
+/// be counted too.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct LanguageConfig {
+    /// The display name in stats, such as `Jsonnet`.
+    pub name: String,
+
+    /// The label used in labels detection, default is the lowercase name.
+    pub label: Option<String>,
+
+    /// The file extensions, without the leading `.`.
+    #[serde(default = "defaults::empty_vec")]
+    pub extensions: Vec<String>,
+
+    /// The interpreters in shebang line, to detect the scripts without
+    /// extension.
+    #[serde(default = "defaults::empty_vec")]
+    pub interpreters: Vec<String>,
+
+    /// The prefixes of line comments, such as `//`.
+    #[serde(default = "defaults::empty_vec")]
+    pub line_comment: Vec<String>,
+
+    /// The start and end of block comments, such as `["/*", "*/"]`. For now,
+    /// only the lines starting with the start token are counted as comments.
+    pub block_comment: Option<(String, String)>,
+}
+
+impl LanguageConfig {
+    /// Return the label, default is the lowercase name.
+    pub fn get_label(&self) -> String {
+        match self.label.as_ref() {
+            Some(label) => label.clone(),
+            None => self.name.to_lowercase(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Size {
+    /// Ignore glob patterns (relative to the repository root) when scanning the
+    /// disk usage of repositories, such as `target` or `node_modules`.
+    #[serde(default = "defaults::empty_vec")]
+    pub ignores: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Scan {
+    /// The number of workers, `0` means the number of cpu cores. Can be
+    /// overridden by the `--jobs` flag.
+    #[serde(default)]
+    pub workers: usize,
+
+    /// Run the scans with the idle cpu and IO priority, so that they don't
+    /// starve the interactive work.
+    #[serde(default = "defaults::disable")]
+    pub idle: bool,
+}
+
+impl Scan {
+    /// Return the number of workers, `jobs` (the `--jobs` flag) takes
+    /// precedence. `0` means the default.
+    pub fn workers(&self, jobs: Option<usize>) -> usize {
+        jobs.unwrap_or(self.workers)
+    }
+
+    /// Lower the priority of the current process if `idle` is enabled. This
+    /// should be called before spawning the workers, so that they inherit it.
+    pub fn prepare(&self) -> Result<()> {
+        if self.idle {
+            utils::set_idle_priority()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Log {
+    /// Write the logs to `{metadir}/logs/roxide.log`.
+    #[serde(default = "defaults::disable")]
+    pub file: bool,
+
+    /// The max level written to the log file.
+    #[serde(default = "defaults::log_level")]
+    pub level: Level,
+
+    /// Write the log lines in json format.
+    #[serde(default = "defaults::disable")]
+    pub json: bool,
+
+    /// Rotate the log file when it exceeds this size, in MiB.
+    #[serde(default = "defaults::log_max_size")]
+    pub max_size: u64,
+
+    /// The number of the rotated log files to keep.
+    #[serde(default = "defaults::log_max_files")]
+    pub max_files: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Audit {
+    /// Keep the audit records for these days, 0 means disable auditing.
+    #[serde(default = "defaults::audit_retention_days")]
+    pub retention_days: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ApiCache {
+    /// The max total size of the api cache files, in MiB, 0 means unlimited.
+    /// When exceeded, the least recently used files are evicted.
+    #[serde(default = "defaults::api_cache_max_size")]
+    pub max_size: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Backup {
+    /// Where to upload the backup files, a local directory or a rsync
+    /// destination such as `user@host:path`. Empty means no default target.
+    #[serde(default = "defaults::empty_string")]
+    pub target: String,
+
+    /// Also bundle the git data of the pinned repositories by default.
+    #[serde(default = "defaults::disable")]
+    pub bundle: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct S3 {
+    /// The endpoint url, such as `https://s3.amazonaws.com` or
+    /// `http://localhost:9000` for MinIO.
+    #[serde(default = "defaults::empty_string")]
+    pub endpoint: String,
+
+    #[serde(default = "defaults::s3_region")]
+    pub region: String,
+
+    #[serde(default = "defaults::empty_string")]
+    pub bucket: String,
+
+    /// The credentials, support environment variables such as
+    /// `${S3_ACCESS_KEY}`.
+    #[serde(default = "defaults::empty_string")]
+    pub access_key: String,
+    #[serde(default = "defaults::empty_string")]
+    pub secret_key: String,
+
+    /// Use the virtual-hosted style url `{bucket}.{endpoint}`, rather than the
+    /// path style url `{endpoint}/{bucket}`.
+    #[serde(default = "defaults::disable")]
+    pub virtual_host: bool,
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct WorkflowConfig {
     #[serde(default = "defaults::empty_vec")]
@@ -115,6 +483,26 @@ pub struct WorkflowConfig {
 
     #[serde(default = "defaults::empty_vec")]
     pub include: Vec<String>,
+
+    /// The custom variables for the templates in steps, such as
+    /// `{{ image_tag }}`. See: [`crate::workflow::render_template`].
+    #[serde(default = "defaults::empty_map")]
+    pub vars: HashMap<String, String>,
+
+    /// The secrets decrypted from the roxide secret files (see the `secret`
+    /// command), only exposed to the step commands as env.
+    #[serde(default = "defaults::empty_vec")]
+    pub secrets: Vec<WorkflowSecret>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct WorkflowSecret {
+    /// The env name.
+    pub name: String,
+
+    /// The encrypted file, relative path is joined to the repository path. The
+    /// decrypted content (without the trailing newline) is the env value.
+    pub file: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
@@ -138,6 +526,9 @@ pub enum WorkflowFromRepo {
     Path,
     #[serde(rename = "clone")]
     Clone,
+    /// The remote api token.
+    #[serde(rename = "token")]
+    Token,
 }
 
 /// Indicates an execution step in Workflow, which can be writing a file or
@@ -153,9 +544,18 @@ pub struct WorkflowStep {
     #[serde(default = "defaults::empty_vec")]
     pub condition: Vec<WorkflowCondition>,
 
-    #[serde(default = "defaults::disable")]
+    /// Continue the workflow even if this step failed (or timed out).
+    #[serde(default = "defaults::disable", alias = "continue_on_error")]
     pub allow_failure: bool,
 
+    /// Kill the step (and all the processes it spawned) if it is still running
+    /// after these seconds. Default is no timeout.
+    pub timeout_secs: Option<u64>,
+
+    /// How many times to retry the step when it failed. Default is no retry.
+    #[serde(default = "defaults::step_retries")]
+    pub retries: u32,
+
     #[serde(rename = "if")]
     pub if_condition: Option<String>,
 
@@ -242,6 +642,14 @@ pub struct RemoteConfig {
     /// each repo: `git config user.email {name}`
     pub email: Option<String>,
 
+    /// The key to sign commits and tags, optional, if not empty, will execute the
+    /// following command for each repo: `git config user.signingkey {key}`
+    pub signing_key: Option<String>,
+
+    /// Whether to sign all commits, optional, if not empty, will execute the
+    /// following command for each repo: `git config commit.gpgsign {gpg_sign}`
+    pub gpg_sign: Option<bool>,
+
     // The icon to display.
     pub icon: Option<String>,
 
@@ -249,6 +657,10 @@ pub struct RemoteConfig {
     #[serde(default = "defaults::disable")]
     pub ssh: bool,
 
+    /// The protocol to clone repo, if not empty, override `ssh`. See
+    /// [`CloneProtocol`].
+    pub clone_protocol: Option<CloneProtocol>,
+
     /// For new or cloned repositories, add the following labels.
     pub labels: Option<HashSet<String>>,
 
@@ -282,6 +694,14 @@ pub struct RemoteConfig {
     #[serde(default = "defaults::cache_hours")]
     pub cache_hours: u32,
 
+    /// Complete the repositories that are not cloned yet from the cached remote
+    /// api data (see `cache_hours`), such as `home {remote} {owner}/{partial}`.
+    /// The completion never calls the remote api, so only the owners that have
+    /// been listed before can be completed. The owners listed by the `owner`
+    /// command are completed too.
+    #[serde(default = "defaults::disable")]
+    pub complete_cache: bool,
+
     /// The list limit when perform searching.
     #[serde(default = "defaults::list_limit")]
     pub list_limit: u32,
@@ -290,14 +710,52 @@ pub struct RemoteConfig {
     #[serde(default = "defaults::api_timeout")]
     pub api_timeout: u64,
 
-    /// API domain, only useful for Gitlab. If your Git remote is self-built, it
-    /// should be set to your self-built domain host.
+    /// The api style, only useful for GitHub. The `graphql` style fetches the
+    /// repository, PullRequest and action data in single queries rather than
+    /// many REST round trips, it requires `token`.
+    #[serde(default = "defaults::api_style")]
+    pub api_style: ApiStyle,
+
+    /// API domain, only useful for Gitlab and the GitHub login. If your Git
+    /// remote is self-built, it should be set to your self-built domain host.
     pub api_domain: Option<String>,
 
+    /// The proxy for the remote api, such as `http://127.0.0.1:7890`. If not
+    /// set, the `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` envs are used.
+    pub proxy: Option<String>,
+
+    /// The comma-separated hosts to bypass the `proxy`, such as
+    /// `localhost,.corp.example.com`. Only useful with `proxy`.
+    pub no_proxy: Option<String>,
+
+    /// The PEM file of the extra CA certificates to trust when calling the
+    /// remote api, for the private CAs or the corporate MITM proxies. You can
+    /// fill in environment variables here, and they will be expanded when used.
+    pub ca_bundle: Option<String>,
+
+    /// Skip the TLS certificate verification when calling the remote api. This
+    /// is dangerous, prefer `ca_bundle` if possible.
+    #[serde(default = "defaults::disable")]
+    pub insecure_skip_verify: bool,
+
+    /// The OAuth app used by the `login` command, only useful for GitHub.
+    pub oauth: Option<OAuthConfig>,
+
     /// Some personalized configurations for different owners.
     #[serde(default = "defaults::empty_map")]
     pub owners: HashMap<String, OwnerConfig>,
 
+    /// The env file to generate for repositories, see `env` command.
+    pub env: Option<EnvFileConfig>,
+
+    /// The conventional-commit rules for `commit` command.
+    pub commit: Option<CommitConfig>,
+
+    /// The initial body of the MergeRequest created by `merge` command, used
+    /// when the repository has no template file (such as
+    /// `.github/pull_request_template.md`).
+    pub merge_template: Option<String>,
+
     #[serde(skip)]
     name: Option<String>,
 
@@ -321,11 +779,221 @@ pub struct OwnerConfig {
     #[serde(default = "defaults::empty_map")]
     pub repo_alias: HashMap<String, String>,
 
+    /// If not empty, override remote's user.
+    pub user: Option<String>,
+
+    /// If not empty, override remote's email.
+    pub email: Option<String>,
+
+    /// If not empty, override remote's signing_key.
+    pub signing_key: Option<String>,
+
+    /// If not empty, override remote's gpg_sign.
+    pub gpg_sign: Option<bool>,
+
     /// If not empty, override remote's ssh.
     pub ssh: Option<bool>,
 
+    /// If not empty, override remote's `clone_protocol` and `ssh`, and this
+    /// owner's `ssh`.
+    pub clone_protocol: Option<CloneProtocol>,
+
     /// After cloning or creating a repo, perform some additional workflows.
     pub on_create: Option<Vec<String>>,
+
+    /// The glob patterns of protected repository names, such as `["*"]` to
+    /// protect all the repositories of this owner. See [`ProtectPolicy`].
+    #[serde(default = "defaults::empty_vec")]
+    pub protect: Vec<String>,
+
+    /// If not empty, override the default editor for `edit` command.
+    pub editor: Option<String>,
+
+    /// Override the editor for some repositories, the key is the repository name.
+    #[serde(default = "defaults::empty_map")]
+    pub repo_editor: HashMap<String, String>,
+
+    /// If not empty, the variables will be merged into remote's env file (the
+    /// same name will be overridden), and the file name will override remote's.
+    pub env: Option<EnvFileConfig>,
+
+    /// If not empty, override remote's commit config.
+    pub commit: Option<CommitConfig>,
+
+    /// If not empty, override remote's merge_template.
+    pub merge_template: Option<String>,
+
+    /// If not empty, override the `git.diverge` strategy for `sync`.
+    pub diverge: Option<DivergeStrategy>,
+
+    /// Override the `sync` diverge strategy for some repositories, the key is
+    /// the repository name.
+    #[serde(default = "defaults::empty_map")]
+    pub repo_diverge: HashMap<String, DivergeStrategy>,
+}
+
+/// The conventional-commit rules for `commit` command.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CommitConfig {
+    /// The allowed commit types.
+    #[serde(default = "defaults::commit_types")]
+    pub types: Vec<String>,
+
+    /// The allowed scopes. If empty, any scope is allowed.
+    #[serde(default = "defaults::empty_vec")]
+    pub scopes: Vec<String>,
+
+    /// If true, the scope must be provided.
+    #[serde(default = "defaults::disable")]
+    pub require_scope: bool,
+
+    /// The max length of the summary.
+    #[serde(default = "defaults::commit_max_summary")]
+    pub max_summary: usize,
+
+    /// The template of the commit body. If not empty, the body will be edited
+    /// in the editor with this as initial content.
+    pub template: Option<String>,
+}
+
+impl Default for CommitConfig {
+    fn default() -> Self {
+        Self {
+            types: defaults::commit_types(),
+            scopes: vec![],
+            require_scope: false,
+            max_summary: defaults::commit_max_summary(),
+            template: None,
+        }
+    }
+}
+
+impl CommitConfig {
+    /// Validate the conventional-commit header.
+    pub fn validate(&self, commit: &ConventionalCommit) -> Result<()> {
+        if !self.types.contains(&commit.kind) {
+            bail!(
+                "invalid commit type '{}', available types: {}",
+                commit.kind,
+                self.types.join(", ")
+            );
+        }
+        match commit.scope.as_ref() {
+            Some(scope) => {
+                if !self.scopes.is_empty() && !self.scopes.contains(scope) {
+                    bail!(
+                        "invalid commit scope '{scope}', available scopes: {}",
+                        self.scopes.join(", ")
+                    );
+                }
+            }
+            None => {
+                if self.require_scope {
+                    bail!("commit scope is required");
+                }
+            }
+        }
+        if commit.summary.is_empty() {
+            bail!("commit summary is required");
+        }
+        if commit.summary.ends_with('.') {
+            bail!("commit summary should not end with '.'");
+        }
+        let len = commit.summary.chars().count();
+        if len > self.max_summary {
+            bail!(
+                "commit summary is too long ({len}), the max length is {}",
+                self.max_summary
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The env file (such as `.envrc` for direnv, or `.env`) to generate for the
+/// repositories. It will be written after cloning or creating a repository.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct EnvFileConfig {
+    /// The file name. If it is `.envrc`, the variables will be rendered as
+    /// `export NAME=value` for direnv, else, as `NAME=value`.
+    pub file: Option<String>,
+
+    /// The variables, the same as workflow env.
+    #[serde(default = "defaults::empty_vec")]
+    pub vars: Vec<WorkflowEnv>,
+}
+
+/// The protocol to clone repositories.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CloneProtocol {
+    Ssh,
+    Https,
+    /// Use ssh if there is an ssh agent running or an ssh private key in
+    /// `~/.ssh`, else, use https.
+    Auto,
+}
+
+impl CloneProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ssh => "ssh",
+            Self::Https => "https",
+            Self::Auto => "auto",
+        }
+    }
+
+    /// Return `true` if should clone with ssh protocol.
+    pub fn use_ssh(&self) -> bool {
+        match self {
+            Self::Ssh => true,
+            Self::Https => false,
+            Self::Auto => Self::has_ssh_key(),
+        }
+    }
+
+    fn has_ssh_key() -> bool {
+        if env::var_os("SSH_AUTH_SOCK").is_some() {
+            return true;
+        }
+        let home = match utils::get_home_dir() {
+            Ok(home) => home,
+            Err(_) => return false,
+        };
+        let dir = match fs::read_dir(home.join(".ssh")) {
+            Ok(dir) => dir,
+            Err(_) => return false,
+        };
+        dir.flatten().any(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("id_") && !name.ends_with(".pub")
+        })
+    }
+}
+
+/// The OAuth app for the GitHub device flow, see `login` command. You need to
+/// register an OAuth app (or GitHub App) with the device flow enabled, see:
+/// https://docs.github.com/en/apps/oauth-apps/building-oauth-apps/authorizing-oauth-apps#device-flow
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OAuthConfig {
+    pub client_id: String,
+
+    /// Only required to refresh the expiring user tokens of GitHub App. You can
+    /// fill in environment variables here, and they will be expanded when used.
+    pub client_secret: Option<String>,
+
+    /// The scopes to request, ignored by GitHub App.
+    #[serde(default = "defaults::oauth_scopes")]
+    pub scopes: String,
+}
+
+/// The style of the remote api, see [`RemoteConfig::api_style`].
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiStyle {
+    Rest,
+    Graphql,
 }
 
 /// The remote api provider type.
@@ -352,6 +1020,72 @@ impl RemoteConfig {
         self.name.as_ref().unwrap().as_str()
     }
 
+    /// Return `true` if the repositories of owner should be cloned with ssh
+    /// protocol. The owner's config takes precedence over remote's, and
+    /// `clone_protocol` takes precedence over `ssh`.
+    pub fn use_ssh(&self, owner: &str) -> bool {
+        if let Some(owner_cfg) = self.owners.get(owner) {
+            if let Some(protocol) = owner_cfg.clone_protocol {
+                return protocol.use_ssh();
+            }
+            if let Some(ssh) = owner_cfg.ssh {
+                return ssh;
+            }
+        }
+        match self.clone_protocol {
+            Some(protocol) => protocol.use_ssh(),
+            None => self.ssh,
+        }
+    }
+
+    /// Return the git configs (`user.name`, `user.email`, `user.signingkey` and
+    /// `commit.gpgsign`) to apply to the repositories of owner. The owner's
+    /// config takes precedence over remote's.
+    pub fn git_configs(&self, owner: &str) -> Vec<(&'static str, String)> {
+        let owner_cfg = self.owners.get(owner);
+        let pick = |get: fn(&OwnerConfig) -> Option<&String>, value: &Option<String>| {
+            owner_cfg
+                .and_then(get)
+                .or(value.as_ref())
+                .map(|value| value.to_string())
+        };
+        let user = pick(|owner| owner.user.as_ref(), &self.user);
+        let email = pick(|owner| owner.email.as_ref(), &self.email);
+        let signing_key = pick(|owner| owner.signing_key.as_ref(), &self.signing_key);
+        let gpg_sign = owner_cfg
+            .and_then(|owner| owner.gpg_sign)
+            .or(self.gpg_sign)
+            .map(|sign| sign.to_string());
+
+        [
+            ("user.name", user),
+            ("user.email", email),
+            ("user.signingkey", signing_key),
+            ("commit.gpgsign", gpg_sign),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key, value)))
+        .collect()
+    }
+
+    /// Return the commit config, the owner's will override remote's.
+    pub fn commit_config(&self, owner: &str) -> CommitConfig {
+        self.owners
+            .get(owner)
+            .and_then(|owner| owner.commit.as_ref())
+            .or(self.commit.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Return the merge template, the owner's will override remote's.
+    pub fn merge_template(&self, owner: &str) -> Option<&str> {
+        self.owners
+            .get(owner)
+            .and_then(|owner| owner.merge_template.as_deref())
+            .or(self.merge_template.as_deref())
+    }
+
     pub fn has_alias(&self) -> bool {
         if self.alias_owner_map.is_some() {
             return true;
@@ -403,10 +1137,85 @@ impl RemoteConfig {
         None
     }
 
+    /// The fields that can be overridden by `ROXIDE_REMOTE_{NAME}_{FIELD}` env.
+    /// The longer ones go first, so that `CLONE_PROTOCOL` won't be matched as
+    /// `CLONE`.
+    const ENV_FIELDS: [&'static str; 19] = [
+        "INSECURE_SKIP_VERIFY",
+        "CLONE_PROTOCOL",
+        "SIGNING_KEY",
+        "CACHE_HOURS",
+        "API_TIMEOUT",
+        "LIST_LIMIT",
+        "API_DOMAIN",
+        "API_STYLE",
+        "CA_BUNDLE",
+        "GPG_SIGN",
+        "NO_PROXY",
+        "PROVIDER",
+        "CLONE",
+        "EMAIL",
+        "PROXY",
+        "TOKEN",
+        "USER",
+        "ICON",
+        "SSH",
+    ];
+
+    fn apply_env(&mut self, key: &str, field: &str, value: &str) -> Result<()> {
+        match field {
+            "CLONE" => self.clone = Some(parse_env(key, value)?),
+            "USER" => self.user = Some(parse_env(key, value)?),
+            "EMAIL" => self.email = Some(parse_env(key, value)?),
+            "SIGNING_KEY" => self.signing_key = Some(parse_env(key, value)?),
+            "GPG_SIGN" => self.gpg_sign = Some(parse_env(key, value)?),
+            "ICON" => self.icon = Some(parse_env(key, value)?),
+            "SSH" => self.ssh = parse_env(key, value)?,
+            "CLONE_PROTOCOL" => self.clone_protocol = Some(parse_env(key, value)?),
+            "PROVIDER" => self.provider = Some(parse_env(key, value)?),
+            "TOKEN" => self.token = Some(parse_env(key, value)?),
+            "CACHE_HOURS" => self.cache_hours = parse_env(key, value)?,
+            "LIST_LIMIT" => self.list_limit = parse_env(key, value)?,
+            "API_TIMEOUT" => self.api_timeout = parse_env(key, value)?,
+            "API_DOMAIN" => self.api_domain = Some(parse_env(key, value)?),
+            "API_STYLE" => self.api_style = parse_env(key, value)?,
+            "PROXY" => self.proxy = Some(parse_env(key, value)?),
+            "NO_PROXY" => self.no_proxy = Some(parse_env(key, value)?),
+            "CA_BUNDLE" => self.ca_bundle = Some(parse_env(key, value)?),
+            "INSECURE_SKIP_VERIFY" => self.insecure_skip_verify = parse_env(key, value)?,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Replace the credentials (token and oauth secret) with a placeholder, for
+    /// displaying the config.
+    pub fn redact(&mut self) {
+        if let Some(token) = self.token.as_mut() {
+            redact_value(token);
+        }
+        if let Some(secret) = self
+            .oauth
+            .as_mut()
+            .and_then(|oauth| oauth.client_secret.as_mut())
+        {
+            redact_value(secret);
+        }
+    }
+
     fn validate(&mut self) -> Result<()> {
         if let Some(token) = &self.token {
             self.token = Some(utils::expandenv(token).context("expand env for token")?);
         }
+        if let Some(ca_bundle) = &self.ca_bundle {
+            self.ca_bundle = Some(utils::expandenv(ca_bundle).context("expand env for ca_bundle")?);
+        }
+        if let Some(oauth) = self.oauth.as_mut() {
+            if let Some(secret) = &oauth.client_secret {
+                let secret = utils::expandenv(secret).context("expand env for oauth secret")?;
+                oauth.client_secret = Some(secret);
+            }
+        }
 
         let mut owner_alias = HashMap::new();
         let mut repo_alias = HashMap::new();
@@ -438,12 +1247,18 @@ impl RemoteConfig {
         if self.api_timeout == 0 {
             self.api_timeout = defaults::api_timeout();
         }
+        if self.api_style == ApiStyle::Graphql && self.provider != Some(ProviderType::Github) {
+            bail!("the graphql api style is only supported by the github provider");
+        }
 
         Ok(())
     }
 }
 
 impl Config {
+    /// The file under config dir to store the current profile name.
+    const CURRENT_PROFILE_FILE: &'static str = "current_profile";
+
     pub fn get_path() -> Result<PathBuf> {
         match env::var_os("ROXIDE_CONFIG") {
             Some(path) => Ok(PathBuf::from(path)),
@@ -454,7 +1269,10 @@ impl Config {
         }
     }
 
-    pub fn load() -> Result<Config> {
+    /// Load the config. The profile is `profile` if provided, else, use the
+    /// `ROXIDE_PROFILE` env, or the current profile switched by `profile`
+    /// command.
+    pub fn load(profile: Option<&str>) -> Result<Config> {
         let root = Self::get_path()?;
 
         let path = root.join("config.toml");
@@ -467,37 +1285,211 @@ impl Config {
             Err(err) => return Err(err).context("read config file"),
         };
 
+        let mut cache = ItemsCache::load(&root);
+
         let remotes_dir = root.join("remotes");
-        let remotes = Self::load_remotes(&remotes_dir)?;
+        let remotes = Self::load_config_items(&remotes_dir, Some(&mut cache))?;
 
         let workflows_dir = root.join("workflows");
-        let workflows = Self::load_workflows(&workflows_dir)?;
+        let workflows = Self::load_config_items(&workflows_dir, Some(&mut cache))?;
 
         let scaffoldings_dir = root.join("scaffoldings");
-        let scaffoldings = Self::load_scaffoldings(&scaffoldings_dir)?;
+        let scaffoldings = Self::load_config_items(&scaffoldings_dir, Some(&mut cache))?;
+
+        cache.save();
 
         cfg.remotes = remotes;
         cfg.workflows = workflows;
         cfg.scaffoldings = scaffoldings;
 
+        let profile = match profile {
+            Some(profile) => Some(profile.to_string()),
+            None => match env::var("ROXIDE_PROFILE") {
+                Ok(profile) if !profile.is_empty() => Some(profile),
+                // The persisted profile may be removed from the config after
+                // switching, do not fail, otherwise even `profile --reset`
+                // cannot run.
+                _ => match Self::read_current_profile(&root)? {
+                    Some(profile) if !cfg.profiles.contains_key(&profile) => {
+                        warn!(
+                            "The current profile '{}' is not found in config, ignore it, use `profile --reset` to clear it",
+                            profile
+                        );
+                        None
+                    }
+                    profile => profile,
+                },
+            },
+        };
+        cfg.apply_profile(profile.as_deref())
+            .with_context(|| format!("apply profile '{}'", profile.unwrap_or_default()))?;
+        // The env overrides go after the profile, so that they always win.
+        cfg.apply_env(env::vars())
+            .context("apply env overrides to config")?;
         cfg.validate().context("validate config content")?;
 
         Ok(cfg)
     }
 
-    pub fn load_remotes(dir: &Path) -> Result<HashMap<String, RemoteConfig>> {
-        Self::load_config_items(dir)
+    fn read_current_profile(root: &Path) -> Result<Option<String>> {
+        let path = root.join(Self::CURRENT_PROFILE_FILE);
+        match fs::read_to_string(&path) {
+            Ok(profile) => {
+                let profile = profile.trim();
+                if profile.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(profile.to_string()))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("read '{}'", path.display())),
+        }
     }
 
-    pub fn load_workflows(dir: &Path) -> Result<HashMap<String, WorkflowConfig>> {
-        Self::load_config_items(dir)
+    /// Switch the current profile, [`None`] means to use no profile.
+    pub fn switch_profile(&self, profile: Option<&str>) -> Result<()> {
+        let root = Self::get_path()?;
+        let path = root.join(Self::CURRENT_PROFILE_FILE);
+        match profile {
+            Some(profile) => {
+                if !self.profiles.contains_key(profile) {
+                    bail!("could not find profile '{profile}'");
+                }
+                utils::write_file(&path, profile.as_bytes())
+            }
+            None => match fs::remove_file(&path) {
+                Ok(()) => Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(err).with_context(|| format!("remove '{}'", path.display())),
+            },
+        }
     }
 
-    pub fn load_scaffoldings(dir: &Path) -> Result<HashMap<String, ScaffoldingConfig>> {
-        Self::load_config_items(dir)
+    /// Fill the default metadir of the profiles, and apply the profile `name`
+    /// if it is provided.
+    fn apply_profile(&mut self, name: Option<&str>) -> Result<()> {
+        for (profile_name, profile) in self.profiles.iter_mut() {
+            if profile.metadir.is_none() {
+                profile.metadir = Some(format!("{}/profiles/{profile_name}", self.metadir));
+            }
+        }
+
+        let name = match name {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let profile = match self.profiles.get(name) {
+            Some(profile) => profile,
+            None => bail!("could not find profile '{name}'"),
+        };
+
+        self.workspace = profile.workspace.clone();
+        self.metadir = profile.metadir.clone().unwrap();
+        if !profile.remotes.is_empty() {
+            for remote in profile.remotes.iter() {
+                if !self.remotes.contains_key(remote) {
+                    bail!("could not find remote '{remote}'");
+                }
+            }
+            let remotes = std::mem::take(&mut self.remotes);
+            for (name, remote) in remotes {
+                if profile.remotes.contains(&name) {
+                    self.remotes.insert(name, remote);
+                } else {
+                    self.hidden_remotes.insert(name, remote);
+                }
+            }
+        }
+
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Override the config fields with `ROXIDE_{FIELD}` env, such as
+    /// `ROXIDE_WORKSPACE`. The remote fields ([`RemoteConfig::ENV_FIELDS`]) can
+    /// be overridden with `ROXIDE_REMOTE_{NAME}_{FIELD}`, such as
+    /// `ROXIDE_REMOTE_GITHUB_TOKEN`, if the remote does not exist, it will be
+    /// created. This allows using roxide in containers and CI without writing
+    /// config files.
+    ///
+    /// The covered fields are `workspace`, `metadir`, `cmd`, `display_format`,
+    /// `keyword_expire`, `lock_timeout`, `editor` and `protect_policy`. This is
+    /// applied after the profile, so the env takes precedence over the
+    /// workspace and metadir of an active profile.
+    fn apply_env<I>(&mut self, vars: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        for (key, value) in vars {
+            let field = match key.strip_prefix("ROXIDE_") {
+                Some(field) => field,
+                None => continue,
+            };
+            if let Some(field) = field.strip_prefix("REMOTE_") {
+                self.apply_remote_env(&key, field, &value)?;
+                continue;
+            }
+            match field {
+                "WORKSPACE" => self.workspace = parse_env(&key, &value)?,
+                "METADIR" => self.metadir = parse_env(&key, &value)?,
+                "CMD" => self.cmd = parse_env(&key, &value)?,
+                "DISPLAY_FORMAT" => self.display_format = parse_env(&key, &value)?,
+                "KEYWORD_EXPIRE" => self.keyword_expire = parse_env(&key, &value)?,
+                "LOCK_TIMEOUT" => self.lock_timeout = parse_env(&key, &value)?,
+                "EDITOR" => self.editor = Some(parse_env(&key, &value)?),
+                "PROTECT_POLICY" => self.protect_policy = parse_env(&key, &value)?,
+                // Other env, such as `ROXIDE_CONFIG`, are not config fields.
+                _ => {}
+            }
+        }
+        Ok(())
     }
 
-    fn load_config_items<T: DeserializeOwned>(dir: &Path) -> Result<HashMap<String, T>> {
+    fn apply_remote_env(&mut self, key: &str, name: &str, value: &str) -> Result<()> {
+        let (name, field) = match RemoteConfig::ENV_FIELDS.iter().find_map(|field| {
+            let name = name.strip_suffix(field)?.strip_suffix('_')?;
+            if name.is_empty() {
+                return None;
+            }
+            Some((name, *field))
+        }) {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+
+        let matches = |remote: &String| remote.to_uppercase().replace('-', "_") == name;
+        // The remotes hidden by the profile are overridden too, so that they
+        // are still correct when used by other profiles.
+        if let Some((_, remote_cfg)) = self
+            .remotes
+            .iter_mut()
+            .chain(self.hidden_remotes.iter_mut())
+            .find(|(remote, _)| matches(remote))
+        {
+            return remote_cfg.apply_env(key, field, value);
+        }
+
+        let remote_name = name.to_lowercase();
+        self.remotes
+            .entry(remote_name.clone())
+            .or_insert_with(|| defaults::remote(&remote_name))
+            .apply_env(key, field, value)
+    }
+
+    pub fn load_remotes(dir: &Path) -> Result<HashMap<String, RemoteConfig>> {
+        Self::load_config_items(dir, None)
+    }
+
+    pub fn load_workflows(dir: &Path) -> Result<HashMap<String, WorkflowConfig>> {
+        Self::load_config_items(dir, None)
+    }
+
+    /// Load the toml config items in the dir, the unchanged files are loaded
+    /// from the cache if provided, see [`ItemsCache`].
+    fn load_config_items<T: DeserializeOwned + Serialize>(
+        dir: &Path,
+        mut cache: Option<&mut ItemsCache>,
+    ) -> Result<HashMap<String, T>> {
         let dir_read = match fs::read_dir(dir) {
             Ok(read) => read,
             Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
@@ -525,6 +1517,12 @@ impl Config {
                 continue;
             }
 
+            let name = name.strip_suffix(".toml").unwrap();
+            if let Some(item) = cache.as_mut().and_then(|cache| cache.get(&path, &meta)) {
+                items.insert(name.to_string(), item);
+                continue;
+            }
+
             let data = fs::read(&path)
                 .with_context(|| format!("read config item '{}'", path.display()))?;
             let data = String::from_utf8(data).with_context(|| {
@@ -535,8 +1533,9 @@ impl Config {
             })?;
             let item: T = toml::from_str(&data)
                 .with_context(|| format!("parse config file '{}' toml", path.display()))?;
-
-            let name = name.strip_suffix(".toml").unwrap();
+            if let Some(cache) = cache.as_mut() {
+                cache.put(&path, &meta, &item);
+            }
 
             items.insert(name.to_string(), item);
         }
@@ -551,18 +1550,37 @@ impl Config {
             docker: defaults::docker(),
             display_format: defaults::display_format(),
             keyword_expire: defaults::keyword_expire(),
+            lock_timeout: defaults::lock_timeout(),
             cmd: defaults::cmd(),
             detect: defaults::detect(),
             remotes: HashMap::new(),
             release: defaults::release(),
+            editors: defaults::empty_map(),
+            editor: None,
+            tmux: defaults::tmux(),
+            daemon: defaults::daemon(),
+            git: defaults::git(),
+            protect_policy: defaults::protect_policy(),
             workflows: defaults::empty_map(),
             scaffoldings: defaults::empty_map(),
             detect_ignores: defaults::empty_vec(),
+            size: defaults::size(),
+            scan: defaults::scan(),
+            log: defaults::log(),
+            audit: defaults::audit(),
+            api_cache: defaults::api_cache(),
+            backup: defaults::backup(),
+            s3: defaults::s3(),
+            size_ignores: defaults::empty_vec(),
             current_dir: None,
             now: None,
             workspace_path: None,
             meta_path: None,
             is_default: true,
+            profiles: defaults::empty_map(),
+            languages: defaults::empty_vec(),
+            profile: None,
+            hidden_remotes: HashMap::new(),
         }
     }
 
@@ -584,6 +1602,21 @@ impl Config {
             self.detect_ignores =
                 Self::parse_patterns(&self.detect.ignores).context("validate detect ignores")?;
         }
+        if !self.size.ignores.is_empty() {
+            self.size_ignores =
+                Self::parse_patterns(&self.size.ignores).context("validate size ignores")?;
+        }
+        for lang in self.languages.iter() {
+            if lang.name.is_empty() {
+                bail!("language name is empty");
+            }
+            if lang.extensions.is_empty() && lang.interpreters.is_empty() {
+                bail!(
+                    "language '{}' should have at least one extension or interpreter",
+                    lang.name
+                );
+            }
+        }
 
         if self.metadir.is_empty() {
             self.metadir = defaults::metadir();
@@ -632,30 +1665,75 @@ impl Config {
         Ok(())
     }
 
+    /// Check the cross-references in the config, such as the workflows used by
+    /// owners, which are not checked when loading. Return all the problems found.
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for remote_name in self.list_remotes() {
+            let remote = self.remotes.get(&remote_name).unwrap();
+            let mut owners: Vec<_> = remote.owners.iter().collect();
+            owners.sort_unstable_by_key(|(owner, _)| *owner);
+            for (owner, owner_cfg) in owners {
+                let prefix = format!("remote '{remote_name}' owner '{owner}'");
+                for wf_name in owner_cfg.on_create.iter().flatten() {
+                    if !self.workflows.contains_key(wf_name) {
+                        problems.push(format!(
+                            "{prefix}: on_create workflow '{wf_name}' not found"
+                        ));
+                    }
+                }
+                for pattern in owner_cfg.protect.iter() {
+                    if let Err(err) = GlobPattern::new(pattern) {
+                        problems.push(format!(
+                            "{prefix}: invalid protect pattern '{pattern}': {err}"
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut job_names = HashSet::with_capacity(self.daemon.jobs.len());
+        for job in self.daemon.jobs.iter() {
+            if !job_names.insert(job.name.as_str()) {
+                problems.push(format!("daemon job '{}': duplicate name", job.name));
+            }
+            if let Err(err) = Schedule::parse(&job.schedule) {
+                problems.push(format!("daemon job '{}': {err:#}", job.name));
+            }
+            if job.args.is_empty() {
+                problems.push(format!("daemon job '{}': args is empty", job.name));
+            }
+        }
+        problems
+    }
+
     pub fn list_remotes(&self) -> Vec<String> {
         let mut names: Vec<_> = self.remotes.keys().cloned().collect();
         names.sort();
         names
     }
 
-    pub fn get_remote(&self, remote: impl AsRef<str>) -> Option<Cow<RemoteConfig>> {
+    pub fn get_remote(&self, remote: impl AsRef<str>) -> Option<Cow<'_, RemoteConfig>> {
         let remote_cfg = self.remotes.get(remote.as_ref())?;
         Some(Cow::Borrowed(remote_cfg))
     }
 
-    pub fn must_get_remote(&self, remote: impl AsRef<str>) -> Result<Cow<RemoteConfig>> {
+    pub fn must_get_remote(&self, remote: impl AsRef<str>) -> Result<Cow<'_, RemoteConfig>> {
         match self.get_remote(remote.as_ref()) {
             Some(remote) => Ok(remote),
-            None => bail!("could not find remote '{}' in config", remote.as_ref()),
+            None => Err(ErrorKind::NotFound.error(format!(
+                "could not find remote '{}' in config",
+                remote.as_ref()
+            ))),
         }
     }
 
-    pub fn get_remote_or_default(&self, remote: impl AsRef<str>) -> Cow<RemoteConfig> {
+    pub fn get_remote_or_default(&self, remote: impl AsRef<str>) -> Cow<'_, RemoteConfig> {
         self.get_remote(remote.as_ref())
             .unwrap_or(Cow::Owned(defaults::remote(remote)))
     }
 
-    pub fn get_owner<R, O>(&self, remote: R, owner: O) -> Option<Cow<OwnerConfig>>
+    pub fn get_owner<R, O>(&self, remote: R, owner: O) -> Option<Cow<'_, OwnerConfig>>
     where
         R: AsRef<str>,
         O: AsRef<str>,
@@ -718,10 +1796,17 @@ impl Config {
                 env,
                 steps,
                 include: _,
+                vars,
+                secrets,
             } = include_workflow.clone();
 
             workflow.env.splice(0..0, env);
             workflow.steps.splice(0..0, steps);
+            workflow.secrets.splice(0..0, secrets);
+            // The variables of the main workflow take precedence.
+            for (key, value) in vars {
+                workflow.vars.entry(key).or_insert(value);
+            }
         }
 
         Ok(Cow::Owned(workflow))
@@ -771,7 +1856,7 @@ impl Config {
         Ok(Cow::Borrowed(scaffolding))
     }
 
-    fn parse_patterns(raw: &[String]) -> Result<Vec<GlobPattern>> {
+    pub fn parse_patterns(raw: &[String]) -> Result<Vec<GlobPattern>> {
         let mut patterns = Vec::with_capacity(raw.len());
         for str in raw.iter() {
             let pattern =
@@ -781,12 +1866,46 @@ impl Config {
         Ok(patterns)
     }
 
+    /// Replace the credentials with a placeholder, for displaying the config.
+    /// See: [`RemoteConfig::redact`].
+    pub fn redact(&mut self) {
+        redact_value(&mut self.s3.access_key);
+        redact_value(&mut self.s3.secret_key);
+        for remote in self.remotes.values_mut() {
+            remote.redact();
+        }
+    }
+
     #[cfg(test)]
     pub fn set_now(&mut self, now: u64) {
         self.now = Some(now);
     }
 }
 
+/// Replace the non-empty credential with a placeholder.
+fn redact_value(value: &mut String) {
+    if !value.is_empty() {
+        *value = String::from("******");
+    }
+}
+
+/// Parse the env value to the config field type. The number and bool value
+/// will be parsed as toml, others are treated as string.
+fn parse_env<T: DeserializeOwned>(key: &str, value: &str) -> Result<T> {
+    let toml_str = format!("value = {value}");
+    if let Ok(EnvValue { value }) = toml::from_str(&toml_str) {
+        return Ok(value);
+    }
+    toml::Value::String(value.to_string())
+        .try_into()
+        .with_context(|| format!("invalid value '{value}' for env {key}"))
+}
+
+#[derive(Deserialize)]
+struct EnvValue<T> {
+    value: T,
+}
+
 #[cfg(test)]
 pub mod config_tests {
     use crate::config::*;
@@ -889,6 +2008,167 @@ env = [
 
 "#;
 
+    #[test]
+    fn test_check() {
+        let mut cfg = load_test_config("config_check");
+        assert!(cfg.check().is_empty());
+
+        let remote = cfg.remotes.get_mut("test").unwrap();
+        let owner = remote.owners.get_mut("golang").unwrap();
+        owner.on_create = Some(vec![String::from("golang"), String::from("unknown")]);
+        owner.protect = vec![String::from("[")];
+
+        let problems = cfg.check();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(
+            problems[0],
+            "remote 'test' owner 'golang': on_create workflow 'unknown' not found"
+        );
+        assert!(problems[1].starts_with("remote 'test' owner 'golang': invalid protect pattern"));
+    }
+
+    #[test]
+    fn test_apply_env() {
+        let mut cfg = load_test_config("config_env");
+        let vars = vec![
+            ("ROXIDE_WORKSPACE", "/tmp/workspace"),
+            ("ROXIDE_KEYWORD_EXPIRE", "100"),
+            ("ROXIDE_PROTECT_POLICY", "refuse"),
+            ("ROXIDE_CONFIG", "/tmp/config"),
+            ("ROXIDE_REMOTE_GITHUB_TOKEN", "12345"),
+            ("ROXIDE_REMOTE_GITHUB_CLONE_PROTOCOL", "https"),
+            ("ROXIDE_REMOTE_GITLAB_SSH", "true"),
+            ("ROXIDE_REMOTE_GITLAB_CACHE_HOURS", "2"),
+            ("HOME", "/root"),
+        ];
+        let vars = vars
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()));
+        cfg.apply_env(vars).unwrap();
+
+        assert_eq!(cfg.workspace, "/tmp/workspace");
+        assert_eq!(cfg.keyword_expire, 100);
+        assert_eq!(cfg.protect_policy, ProtectPolicy::Refuse);
+
+        let github = cfg.get_remote("github").unwrap();
+        assert_eq!(github.token.as_deref(), Some("12345"));
+        assert_eq!(github.clone_protocol, Some(CloneProtocol::Https));
+        assert_eq!(github.user.as_deref(), Some("fioncat"));
+
+        let gitlab = cfg.get_remote("gitlab").unwrap();
+        assert!(gitlab.ssh);
+        assert_eq!(gitlab.cache_hours, 2);
+
+        let vars = vec![(
+            String::from("ROXIDE_REMOTE_MY_GIT_CLONE"),
+            String::from("git.my.domain.com"),
+        )];
+        cfg.apply_env(vars).unwrap();
+        let my_git = cfg.get_remote("my_git").unwrap();
+        assert_eq!(my_git.clone.as_deref(), Some("git.my.domain.com"));
+
+        let vars = vec![(String::from("ROXIDE_KEYWORD_EXPIRE"), String::from("abc"))];
+        assert!(cfg.apply_env(vars).is_err());
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let mut cfg = load_test_config("config_profile");
+        cfg.metadir = String::from("/tmp/meta");
+        cfg.profiles.insert(
+            String::from("work"),
+            ProfileConfig {
+                workspace: String::from("/tmp/work"),
+                metadir: None,
+                remotes: vec![String::from("gitlab")],
+            },
+        );
+        cfg.profiles.insert(
+            String::from("bad"),
+            ProfileConfig {
+                workspace: String::from("/tmp/bad"),
+                metadir: None,
+                remotes: vec![String::from("unknown")],
+            },
+        );
+
+        assert!(cfg.clone().apply_profile(Some("oss")).is_err());
+        assert!(cfg.clone().apply_profile(Some("bad")).is_err());
+
+        cfg.apply_profile(Some("work")).unwrap();
+        assert_eq!(cfg.profile.as_deref(), Some("work"));
+        assert_eq!(cfg.workspace, "/tmp/work");
+        assert_eq!(cfg.metadir, "/tmp/meta/profiles/work");
+        assert_eq!(cfg.list_remotes(), vec![String::from("gitlab")]);
+    }
+
+    #[test]
+    fn test_apply_env_profile() {
+        let mut cfg = load_test_config("config_env_profile");
+        cfg.profiles.insert(
+            String::from("work"),
+            ProfileConfig {
+                workspace: String::from("/tmp/work"),
+                metadir: None,
+                remotes: vec![String::from("gitlab")],
+            },
+        );
+        let vars = vec![
+            (
+                String::from("ROXIDE_WORKSPACE"),
+                String::from("/tmp/env-workspace"),
+            ),
+            (
+                String::from("ROXIDE_METADIR"),
+                String::from("/tmp/env-meta"),
+            ),
+            (
+                String::from("ROXIDE_REMOTE_GITHUB_TOKEN"),
+                String::from("12345"),
+            ),
+        ];
+        // The same order as `Config::load`, the env wins over the profile.
+        cfg.apply_profile(Some("work")).unwrap();
+        cfg.apply_env(vars).unwrap();
+
+        assert_eq!(cfg.workspace, "/tmp/env-workspace");
+        assert_eq!(cfg.metadir, "/tmp/env-meta");
+        assert_eq!(cfg.list_remotes(), vec![String::from("gitlab")]);
+        assert_eq!(cfg.hidden_remotes["github"].token.as_deref(), Some("12345"));
+    }
+
+    #[test]
+    fn test_redact() {
+        let mut cfg = load_test_config("config_redact");
+        cfg.s3.access_key = String::from("access");
+        cfg.s3.secret_key = String::new();
+        cfg.redact();
+
+        assert_eq!(cfg.s3.access_key, "******");
+        assert_eq!(cfg.s3.secret_key, "");
+        let gitlab = cfg.get_remote("gitlab").unwrap();
+        assert_eq!(gitlab.token.as_deref(), Some("******"));
+        let github = cfg.get_remote("github").unwrap();
+        assert_eq!(github.token, None);
+
+        let content = toml::to_string_pretty(&cfg).unwrap();
+        assert!(!content.contains("access\""), "{content}");
+    }
+
+    #[test]
+    fn test_default_samples() {
+        let mut cfg: Config = toml::from_str(defaults::CONFIG_TOML).unwrap();
+        let remote: RemoteConfig = toml::from_str(defaults::REMOTE_TOML).unwrap();
+        let workflow: WorkflowConfig = toml::from_str(defaults::WORKFLOW_TOML).unwrap();
+        cfg.remotes.insert(String::from("github"), remote);
+        cfg.workflows.insert(String::from("cargo"), workflow);
+        cfg.validate().unwrap();
+
+        toml::to_string_pretty(&cfg).unwrap();
+        toml::to_string_pretty(cfg.get_remote("github").unwrap().as_ref()).unwrap();
+        toml::to_string_pretty(cfg.get_workflow("cargo").unwrap().as_ref()).unwrap();
+    }
+
     pub fn load_test_config(name: &str) -> Config {
         let toml_str = TEST_CONFIG_TOML.replace("{NAME}", name);
         let mut cfg: Config = parse_toml(&toml_str);
@@ -952,6 +2232,19 @@ env = [
                 "roxide" => "rox"
             ],
             ssh: Some(true),
+            clone_protocol: None,
+            user: None,
+            email: None,
+            signing_key: None,
+            gpg_sign: None,
+            editor: None,
+            repo_editor: defaults::empty_map(),
+            commit: None,
+            merge_template: None,
+            diverge: None,
+            repo_diverge: defaults::empty_map(),
+            protect: vec![],
+            env: None,
         };
         let owner1 = OwnerConfig {
             alias: Some("k8s".to_string()),
@@ -961,12 +2254,28 @@ env = [
                 "kubernetes" => "k8s"
             ],
             ssh: None,
+            clone_protocol: None,
+            user: None,
+            email: None,
+            signing_key: None,
+            gpg_sign: None,
+            editor: None,
+            repo_editor: defaults::empty_map(),
+            commit: None,
+            merge_template: None,
+            diverge: None,
+            repo_diverge: defaults::empty_map(),
+            protect: vec![],
+            env: None,
         };
         let github_remote = RemoteConfig {
             clone: Some("github.com".to_string()),
             user: Some("fioncat".to_string()),
             email: Some("lazycat7706@gmail.com".to_string()),
             ssh: false,
+            clone_protocol: None,
+            signing_key: None,
+            gpg_sign: None,
             icon: None,
             labels: Some(hashset_strings!["sync"]),
             provider: Some(ProviderType::Github),
@@ -986,7 +2295,14 @@ env = [
 
             api_domain: None,
             api_timeout: defaults::api_timeout(),
+            api_style: defaults::api_style(),
+            proxy: None,
+            no_proxy: None,
+            ca_bundle: None,
+            insecure_skip_verify: false,
+            oauth: None,
             cache_hours: defaults::cache_hours(),
+            complete_cache: false,
             list_limit: defaults::list_limit(),
             token: None,
 
@@ -996,6 +2312,11 @@ env = [
             ],
 
             name: Some("github".to_string()),
+            env: None,
+
+            commit: None,
+
+            merge_template: None,
         };
         assert_eq!(cfg.get_remote("github").unwrap().as_ref(), &github_remote);
 
@@ -1006,6 +2327,19 @@ env = [
             on_create: None,
             repo_alias: defaults::empty_map(),
             ssh: None,
+            clone_protocol: None,
+            user: None,
+            email: None,
+            signing_key: None,
+            gpg_sign: None,
+            editor: None,
+            repo_editor: defaults::empty_map(),
+            commit: None,
+            merge_template: None,
+            diverge: None,
+            repo_diverge: defaults::empty_map(),
+            protect: vec![],
+            env: None,
         };
         let gitlab_remote = RemoteConfig {
             clone: Some("gitlab.com".to_string()),
@@ -1013,11 +2347,21 @@ env = [
             email: Some("test-email@test.com".to_string()),
             icon: None,
             ssh: false,
+            clone_protocol: None,
+            signing_key: None,
+            gpg_sign: None,
             provider: Some(ProviderType::Gitlab),
             token: Some("test-token-gitlab".to_string()),
             cache_hours: 100,
+            complete_cache: false,
             list_limit: 500,
             api_timeout: 30,
+            api_style: ApiStyle::Rest,
+            proxy: None,
+            no_proxy: None,
+            ca_bundle: None,
+            insecure_skip_verify: false,
+            oauth: None,
             api_domain: Some("gitlab.com".to_string()),
             owners: hashmap!["test".to_string() => owner2],
             labels: None,
@@ -1026,6 +2370,11 @@ env = [
             alias_repo_map: None,
 
             name: Some("gitlab".to_string()),
+            env: None,
+
+            commit: None,
+
+            merge_template: None,
         };
         assert_eq!(cfg.get_remote("gitlab").unwrap().as_ref(), &gitlab_remote);
 
@@ -1036,6 +2385,19 @@ env = [
             labels: None,
             repo_alias: defaults::empty_map(),
             ssh: None,
+            clone_protocol: None,
+            user: None,
+            email: None,
+            signing_key: None,
+            gpg_sign: None,
+            editor: None,
+            repo_editor: defaults::empty_map(),
+            commit: None,
+            merge_template: None,
+            diverge: None,
+            repo_diverge: defaults::empty_map(),
+            protect: vec![],
+            env: None,
         };
         let owner4 = OwnerConfig {
             on_create: Some(vec!["rust".to_string()]),
@@ -1044,6 +2406,19 @@ env = [
             labels: None,
             repo_alias: defaults::empty_map(),
             ssh: None,
+            clone_protocol: None,
+            user: None,
+            email: None,
+            signing_key: None,
+            gpg_sign: None,
+            editor: None,
+            repo_editor: defaults::empty_map(),
+            commit: None,
+            merge_template: None,
+            diverge: None,
+            repo_diverge: defaults::empty_map(),
+            protect: vec![],
+            env: None,
         };
         let test_remote = RemoteConfig {
             clone: None,
@@ -1051,10 +2426,20 @@ env = [
             email: None,
             icon: None,
             ssh: false,
+            clone_protocol: None,
+            signing_key: None,
+            gpg_sign: None,
             provider: None,
             token: None,
             api_timeout: defaults::api_timeout(),
+            api_style: defaults::api_style(),
+            proxy: None,
+            no_proxy: None,
+            ca_bundle: None,
+            insecure_skip_verify: false,
+            oauth: None,
             cache_hours: defaults::cache_hours(),
+            complete_cache: false,
             list_limit: defaults::list_limit(),
             api_domain: None,
             owners: hashmap![
@@ -1067,6 +2452,11 @@ env = [
             alias_repo_map: None,
 
             name: Some("test".to_string()),
+            env: None,
+
+            commit: None,
+
+            merge_template: None,
         };
         assert_eq!(cfg.get_remote("test").unwrap().as_ref(), &test_remote);
     }
@@ -1080,6 +2470,122 @@ func main() {
 }
 "#;
 
+    #[test]
+    fn test_clone_protocol() {
+        let mut remote = defaults::remote("test");
+        assert!(!remote.use_ssh("fioncat"));
+
+        remote.ssh = true;
+        assert!(remote.use_ssh("fioncat"));
+
+        remote.clone_protocol = Some(CloneProtocol::Https);
+        assert!(!remote.use_ssh("fioncat"));
+
+        let mut owner = OwnerConfig {
+            alias: None,
+            labels: None,
+            repo_alias: HashMap::new(),
+            ssh: Some(true),
+            clone_protocol: None,
+            user: None,
+            email: None,
+            signing_key: None,
+            gpg_sign: None,
+            on_create: None,
+            editor: None,
+            repo_editor: HashMap::new(),
+            commit: None,
+            merge_template: None,
+            diverge: None,
+            repo_diverge: HashMap::new(),
+            protect: vec![],
+            env: None,
+        };
+        remote.owners.insert(String::from("fioncat"), owner.clone());
+        assert!(remote.use_ssh("fioncat"));
+        assert!(!remote.use_ssh("kubernetes"));
+
+        owner.clone_protocol = Some(CloneProtocol::Https);
+        remote.owners.insert(String::from("fioncat"), owner);
+        assert!(!remote.use_ssh("fioncat"));
+    }
+
+    #[test]
+    fn test_git_configs() {
+        let mut remote = defaults::remote("test");
+        assert!(remote.git_configs("fioncat").is_empty());
+
+        remote.user = Some(String::from("fioncat"));
+        remote.email = Some(String::from("personal@test.com"));
+        remote.gpg_sign = Some(false);
+        let owner = OwnerConfig {
+            alias: None,
+            labels: None,
+            repo_alias: HashMap::new(),
+            ssh: None,
+            clone_protocol: None,
+            user: None,
+            email: Some(String::from("work@test.com")),
+            signing_key: Some(String::from("ABCD1234")),
+            gpg_sign: Some(true),
+            on_create: None,
+            editor: None,
+            repo_editor: HashMap::new(),
+            commit: None,
+            merge_template: None,
+            diverge: None,
+            repo_diverge: HashMap::new(),
+            protect: vec![],
+            env: None,
+        };
+        remote.owners.insert(String::from("work"), owner);
+
+        assert_eq!(
+            remote.git_configs("fioncat"),
+            vec![
+                ("user.name", String::from("fioncat")),
+                ("user.email", String::from("personal@test.com")),
+                ("commit.gpgsign", String::from("false")),
+            ]
+        );
+        assert_eq!(
+            remote.git_configs("work"),
+            vec![
+                ("user.name", String::from("fioncat")),
+                ("user.email", String::from("work@test.com")),
+                ("user.signingkey", String::from("ABCD1234")),
+                ("commit.gpgsign", String::from("true")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_config() {
+        let mut remote = defaults::remote("github");
+        assert_eq!(remote.commit_config("fioncat"), CommitConfig::default());
+
+        remote.commit = Some(CommitConfig {
+            scopes: vec![String::from("api"), String::from("cmd")],
+            require_scope: true,
+            max_summary: 20,
+            ..Default::default()
+        });
+        let commit_cfg = remote.commit_config("fioncat");
+
+        let cases = vec![
+            ("feat(api): add release", true),
+            ("feat: add release", false),
+            ("feat(db): add release", false),
+            ("feature(api): add release", false),
+            ("fix(cmd): fix the panic.", false),
+            ("fix(cmd): a very long summary here", false),
+        ];
+        for (header, expect) in cases {
+            let commit = ConventionalCommit::parse(header).unwrap();
+            assert_eq!(commit_cfg.validate(&commit).is_ok(), expect, "{header}");
+        }
+    }
+
     #[test]
     fn test_workflows() {
         let cfg = load_test_config("config_workflow");
@@ -1098,6 +2604,8 @@ func main() {
                 docker_push: None,
                 set_env: None,
                 allow_failure: false,
+                timeout_secs: None,
+                retries: 0,
                 os: None,
                 condition: vec![],
                 if_condition: None,
@@ -1117,6 +2625,8 @@ func main() {
                 docker_push: None,
                 set_env: None,
                 allow_failure: false,
+                timeout_secs: None,
+                retries: 0,
                 os: None,
                 condition: vec![],
                 if_condition: None,
@@ -1143,6 +2653,8 @@ func main() {
             env: w0_env,
             steps: w0_steps,
             include: vec![],
+            vars: HashMap::new(),
+            secrets: vec![],
         };
 
         let w1_steps = vec![WorkflowStep {
@@ -1158,6 +2670,8 @@ func main() {
             docker_push: None,
             set_env: None,
             allow_failure: false,
+            timeout_secs: None,
+            retries: 0,
             os: None,
             condition: vec![],
             if_condition: None,
@@ -1166,6 +2680,8 @@ func main() {
             env: vec![],
             steps: w1_steps,
             include: vec![],
+            vars: HashMap::new(),
+            secrets: vec![],
         };
 
         let w2_steps = vec![WorkflowStep {
@@ -1185,6 +2701,8 @@ func main() {
             docker_push: None,
             set_env: None,
             allow_failure: false,
+            timeout_secs: None,
+            retries: 0,
             os: None,
             condition: vec![],
             if_condition: None,
@@ -1193,6 +2711,8 @@ func main() {
             env: vec![],
             steps: w2_steps,
             include: vec![],
+            vars: HashMap::new(),
+            secrets: vec![],
         };
 
         let wf = hashmap![
@@ -1287,12 +2807,16 @@ func main() {
                         docker_push: None,
                         set_env: None,
                         allow_failure: false,
+                        timeout_secs: None,
+                        retries: 0,
                         os: None,
                         condition: vec![],
                         if_condition: None,
                     })
                     .collect(),
                 include: includes.into_iter().map(|s| s.to_string()).collect(),
+                vars: HashMap::new(),
+                secrets: vec![],
             }
         };
 
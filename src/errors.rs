@@ -18,3 +18,139 @@ impl Display for SilentExit {
         Ok(())
     }
 }
+
+/// The exit codes of the [`ErrorKind`]s, so that the wrappers and scripts can
+/// branch on the failure type. The other command failures exit with
+/// [`CODE_COMMAND_FAILED`].
+pub const CODE_NOT_FOUND: i32 = 20;
+pub const CODE_CONFLICT: i32 = 21;
+pub const CODE_AUTH_FAILED: i32 = 22;
+pub const CODE_NETWORK: i32 = 23;
+/// The same as declining a confirmation, see [`crate::term::must_confirm`].
+pub const CODE_USER_ABORTED: i32 = CODE_SILENT_EXIT + 60;
+
+/// The type of a command failure, see [`KindError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The repository, remote, branch, etc. does not exist.
+    NotFound,
+    /// The target already exists, or the change is rejected (such as a
+    /// non-fast-forward push or a merge conflict).
+    Conflict,
+    /// The token or the git credential is missing or rejected.
+    AuthFailed,
+    /// The network is unreachable or the server is unavailable.
+    Network,
+    /// The user refused to continue.
+    UserAborted,
+}
+
+impl ErrorKind {
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::NotFound => CODE_NOT_FOUND,
+            Self::Conflict => CODE_CONFLICT,
+            Self::AuthFailed => CODE_AUTH_FAILED,
+            Self::Network => CODE_NETWORK,
+            Self::UserAborted => CODE_USER_ABORTED,
+        }
+    }
+
+    /// Create an error of this kind, use it like `bail!`:
+    ///
+    /// ```
+    /// return Err(ErrorKind::NotFound.error(format!("could not find '{name}'")));
+    /// ```
+    pub fn error(self, message: impl Into<String>) -> anyhow::Error {
+        anyhow::Error::new(KindError {
+            kind: self,
+            message: message.into(),
+        })
+    }
+
+    /// Return the kind of a failed http response status, [`None`] if it has no
+    /// specific kind.
+    pub fn from_http_status(status: u16) -> Option<ErrorKind> {
+        match status {
+            401 | 403 => Some(Self::AuthFailed),
+            404 => Some(Self::NotFound),
+            409 | 422 => Some(Self::Conflict),
+            429 | 502 | 503 | 504 => Some(Self::Network),
+            _ => None,
+        }
+    }
+}
+
+/// An error with its [`ErrorKind`], the kind can be anywhere in the error
+/// chain, so adding context to it is fine.
+#[derive(Debug)]
+pub struct KindError {
+    pub kind: ErrorKind,
+    message: String,
+}
+
+impl Display for KindError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for KindError {}
+
+/// The error of a command killed for running too long, see
+/// [`crate::exec::Cmd::with_timeout`]. Use [`is_timeout`] to check it.
+#[derive(Debug)]
+pub struct TimeoutError {
+    pub message: String,
+}
+
+impl Display for TimeoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+/// Return `true` if the error is caused by [`TimeoutError`].
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<TimeoutError>().is_some())
+}
+
+/// Return the exit code of the command error: the [`SilentExit`] code, the
+/// [`ErrorKind`] code, or `default` if the error has no kind.
+pub fn exit_code(err: &anyhow::Error, default: i32) -> i32 {
+    if let Some(SilentExit { code }) = err.downcast_ref::<SilentExit>() {
+        return CODE_SILENT_EXIT + *code as i32;
+    }
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<KindError>())
+        .map(|err| err.kind.code())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod errors_tests {
+    use anyhow::{anyhow, Context, Result};
+
+    use crate::errors::*;
+
+    #[test]
+    fn test_exit_code() {
+        let err = ErrorKind::NotFound.error("could not find repo");
+        assert_eq!(err.to_string(), "could not find repo");
+        assert_eq!(exit_code(&err, CODE_COMMAND_FAILED), CODE_NOT_FOUND);
+
+        let result: Result<()> = Err(ErrorKind::Network.error("connection refused"));
+        let err = result.context("sync repo").unwrap_err();
+        assert_eq!(format!("{err:#}"), "sync repo: connection refused");
+        assert_eq!(exit_code(&err, CODE_COMMAND_FAILED), CODE_NETWORK);
+
+        let err = anyhow!(SilentExit { code: 60 });
+        assert_eq!(exit_code(&err, CODE_COMMAND_FAILED), CODE_USER_ABORTED);
+
+        let err = anyhow!("unknown error");
+        assert_eq!(exit_code(&err, CODE_COMMAND_FAILED), CODE_COMMAND_FAILED);
+    }
+}
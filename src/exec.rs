@@ -1,12 +1,21 @@
-use std::io::{self, Read, Write};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
 use console::style;
 
-use crate::errors::SilentExit;
-use crate::info;
+use crate::errors::{ErrorKind, SilentExit, TimeoutError};
+use crate::interrupt::{self, Interrupt};
+use crate::progress::{self, Progress, ProgressUnit};
+use crate::{debug, info, term, timings, trace, utils, warn};
 
 /// Represents the result of a command execution, containing both the command
 /// output and the return code. Different functions can be used to further process
@@ -63,6 +72,11 @@ impl CmdResult {
             ));
         }
 
+        if cmd_name.starts_with("git ") {
+            if let Some(kind) = git_error_kind(&self.stderr) {
+                return Err(kind.error(msg));
+            }
+        }
         bail!(msg)
     }
 
@@ -118,7 +132,43 @@ pub struct Cmd {
     cmd: Command,
     input: Option<String>,
 
+    timeout: Option<Duration>,
+
     display: CmdDisplay,
+
+    retry: Option<RetryPolicy>,
+    /// When the command is displayed and can be retried, its stderr is piped
+    /// and copied to terminal, so that we can check the error message.
+    tee_stderr: bool,
+    /// Copy the piped stdout to terminal (stderr) as well, see [`Cmd::with_tee`].
+    tee_stdout: bool,
+
+    /// The secret values passed by [`Cmd::with_secret_env`], they are masked
+    /// in the debug logs.
+    secrets: Vec<String>,
+}
+
+/// The retry policy for git network operations, see [`set_git_retry`].
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    attempts: u32,
+    backoff: Duration,
+}
+
+static GIT_RETRY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// Set the retry policy for git network operations (clone, fetch, pull and push).
+/// When these commands failed with transient network errors (see
+/// [`is_retryable_git_error`]), they will be retried at most `attempts` times.
+/// The wait time before the first retry is `backoff`, and it will be doubled
+/// for each next retry.
+///
+/// This should be called once after the config is loaded.
+pub fn set_git_retry(attempts: u32, backoff: Duration) {
+    if attempts == 0 {
+        return;
+    }
+    let _ = GIT_RETRY.set(RetryPolicy { attempts, backoff });
 }
 
 enum CmdDisplay {
@@ -148,13 +198,26 @@ impl Cmd {
         Cmd {
             cmd,
             input: None,
+            timeout: None,
             display: CmdDisplay::None,
+            retry: None,
+            tee_stderr: false,
+            tee_stdout: false,
+            secrets: Vec::new(),
         }
     }
 
-    /// Create a new git [`Cmd`].
+    /// Create a new git [`Cmd`]. The network operations will be retried according
+    /// to the policy set by [`set_git_retry`].
     pub fn git(args: &[&str]) -> Cmd {
-        Self::with_args("git", args)
+        let mut cmd = Self::with_args("git", args);
+        if matches!(
+            git_subcommand(args),
+            Some("clone" | "fetch" | "pull" | "push")
+        ) {
+            cmd.retry = GIT_RETRY.get().copied();
+        }
+        cmd
     }
 
     /// When executing a command, display the command name, args, and a prompt.
@@ -190,12 +253,56 @@ impl Cmd {
         self
     }
 
+    /// Like [`Cmd::with_env`], but the value is a secret, it is masked if it
+    /// appears in the debug logs.
+    pub fn with_secret_env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.with_env(key.as_ref(), val.as_ref());
+        if !val.as_ref().is_empty() {
+            self.secrets.push(val.as_ref().to_string());
+        }
+        self
+    }
+
     /// Set the work directory for the command.
     pub fn with_path(&mut self, path: &PathBuf) -> &mut Self {
         self.cmd.current_dir(path);
         self
     }
 
+    /// Kill the command if it is still running after `timeout`. The command will
+    /// be launched in its own process group, so that the whole group (including
+    /// the subprocesses it spawned) can be killed together.
+    ///
+    /// Since the group is no longer the terminal's foreground group, the command
+    /// cannot read from the terminal (it would be stopped by SIGTTIN), so its
+    /// stdin is set to null unless [`Cmd::with_input`] is used. The Ctrl-C is not
+    /// delivered to the group either, it is caught while waiting and the group is
+    /// killed then.
+    pub fn with_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self.cmd.process_group(0);
+        if self.input.is_none() {
+            self.cmd.stdin(Stdio::null());
+        }
+        self
+    }
+
+    /// Copy the command output to terminal while capturing it, so that the output
+    /// can be shown and saved at the same time. The stdout is copied (to stderr,
+    /// see [`Cmd::execute`]) only when `stdout` is true, otherwise it is left
+    /// to the caller to read.
+    pub fn with_tee(&mut self, stdout: bool) -> &mut Self {
+        self.cmd.stdout(Stdio::piped());
+        self.cmd.stderr(Stdio::piped());
+        self.tee_stdout = stdout;
+        self.tee_stderr = true;
+        self
+    }
+
     /// Create a sh command, encapsulating the command using `sh -c "xxx"`.
     ///
     /// ## Compatibility
@@ -216,6 +323,10 @@ impl Cmd {
     fn set_display(&mut self, display: CmdDisplay) {
         if let CmdDisplay::None = self.display {
             self.display = display;
+            if self.retry.is_some() {
+                self.tee_stderr = true;
+                return;
+            }
             // We redirect command's stderr to program's. So that user can view
             // command's error output directly.
             self.cmd.stderr(Stdio::inherit());
@@ -256,20 +367,21 @@ impl Cmd {
     /// See: [`CmdResult`].
     pub fn execute_unchecked(&mut self) -> Result<CmdResult> {
         let result_display = self.show();
-
-        let mut child = match self.cmd.spawn() {
-            Ok(child) => child,
-            Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                bail!(
-                    "could not find command `{}`, please make sure it is installed",
-                    self.get_name()
-                );
-            }
-            Err(e) => {
-                return Err(e)
-                    .with_context(|| format!("could not launch command `{}`", self.get_name()))
+        let mut attempt: u32 = 0;
+        loop {
+            let result = self.execute_once(result_display.clone())?;
+            if !self.should_retry(result.code, &result.stderr, attempt) {
+                return Ok(result);
             }
-        };
+            attempt += 1;
+            self.wait_retry(attempt);
+        }
+    }
+
+    fn execute_once(&mut self, result_display: Option<String>) -> Result<CmdResult> {
+        debug!("Execute command: {}", self.debug_string());
+        let _span = self.span();
+        let mut child = self.spawn()?;
 
         if let Some(input) = &self.input {
             let handle = child.stdin.as_mut().unwrap();
@@ -282,9 +394,20 @@ impl Cmd {
         }
 
         let mut stdout = child.stdout.take();
-        let mut stderr = child.stderr.take();
+        let stderr = child.stderr.take();
+
+        if let Some(timeout) = self.timeout {
+            return self.wait_timeout(child, timeout, result_display, stdout, stderr);
+        }
+
+        // The stderr is drained in another thread while reading stdout, or the
+        // command would block forever once the stderr pipe buffer is full (such
+        // as git printing lots of warnings).
+        let tee_stderr = self.tee_stderr;
+        let stderr = stderr.map(|pipe| thread::spawn(move || Self::drain_pipe(pipe, tee_stderr)));
 
         let stdout = match stdout.as_mut() {
+            Some(stdout) if self.tee_stdout => Self::tee_pipe(stdout),
             Some(stdout) => {
                 let mut out = String::new();
                 stdout
@@ -294,18 +417,17 @@ impl Cmd {
             }
             None => String::new(),
         };
-        let stderr = match stderr.as_mut() {
-            Some(stderr) => {
-                let mut out = String::new();
-                stderr
-                    .read_to_string(&mut out)
-                    .with_context(|| format!("read stderr from command `{}`", self.get_name()))?;
-                out
-            }
+
+        let stderr = match stderr {
+            Some(handle) => handle.join().unwrap_or_default(),
             None => String::new(),
         };
 
         let status = child.wait().context("Wait command done")?;
+        debug!("Command `{}` exited with {}", self.get_name(), status);
+        if !status.success() {
+            trace!("Command `{}` stderr: {}", self.get_name(), stderr.trim());
+        }
         Ok(CmdResult {
             code: status.code(),
             display: result_display,
@@ -314,6 +436,191 @@ impl Cmd {
         })
     }
 
+    fn should_retry(&self, code: Option<i32>, stderr: &str, attempt: u32) -> bool {
+        let retry = match self.retry {
+            Some(retry) => retry,
+            None => return false,
+        };
+        if code == Some(0) || attempt >= retry.attempts {
+            return false;
+        }
+        is_retryable_git_error(stderr)
+    }
+
+    fn wait_retry(&self, attempt: u32) {
+        let retry = self.retry.unwrap();
+        let backoff = retry.backoff * 2_u32.pow(attempt - 1);
+        warn!(
+            "Command `{}` failed with network error, retry in {} ({}/{})",
+            self.full(),
+            utils::format_elapsed(backoff),
+            attempt,
+            retry.attempts
+        );
+        thread::sleep(backoff);
+    }
+
+    /// Copy the pipe to stderr, and return the content.
+    fn tee_pipe(pipe: impl Read) -> String {
+        let mut out = String::new();
+        let mut reader = BufReader::new(pipe);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    eprint!("{line}");
+                    out.push_str(&line);
+                }
+            }
+        }
+        out
+    }
+
+    /// Execute the git command with `--progress`, parse the progress reported in
+    /// its stderr and render it with [`Progress`], so that user can see what is
+    /// happening in long operations such as clone.
+    ///
+    /// If the progress is quiet (see [`progress::is_quiet`]), fallback to
+    /// [`Cmd::execute`], the git output will be passed through directly.
+    pub fn execute_git_progress(&mut self) -> Result<()> {
+        if progress::is_quiet() {
+            return self.execute();
+        }
+
+        self.cmd.arg("--progress");
+        self.cmd.stdout(io::stderr());
+        self.cmd.stderr(Stdio::piped());
+
+        let result_display = self.show();
+        let mut attempt: u32 = 0;
+        loop {
+            let _span = self.span();
+            let mut child = self.spawn()?;
+
+            let mut tracker = GitProgress::default();
+            if let Some(stderr) = child.stderr.take() {
+                tracker
+                    .consume(stderr)
+                    .with_context(|| format!("read stderr from command `{}`", self.get_name()))?;
+            }
+
+            let status = child.wait().context("Wait command done")?;
+            let output = tracker.finish();
+            if !status.success() && result_display.is_none() && !progress::in_worker() {
+                // The error output is captured, show it to user.
+                eprint!("{output}");
+            }
+
+            if self.should_retry(status.code(), &output, attempt) {
+                attempt += 1;
+                self.wait_retry(attempt);
+                continue;
+            }
+
+            return CmdResult {
+                code: status.code(),
+                display: result_display,
+                stdout: String::new(),
+                stderr: output,
+            }
+            .check();
+        }
+    }
+
+    fn spawn(&mut self) -> Result<Child> {
+        match self.cmd.spawn() {
+            Ok(child) => Ok(child),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                bail!(
+                    "could not find command `{}`, please make sure it is installed",
+                    self.get_name()
+                );
+            }
+            Err(e) => {
+                Err(e).with_context(|| format!("could not launch command `{}`", self.get_name()))
+            }
+        }
+    }
+
+    /// Wait for the child to exit, killing its process group if it does not exit
+    /// within `timeout`. The outputs are read in background threads so that a
+    /// command with large output cannot block the waiting.
+    fn wait_timeout<O, E>(
+        &self,
+        mut child: Child,
+        timeout: Duration,
+        result_display: Option<String>,
+        stdout: Option<O>,
+        stderr: Option<E>,
+    ) -> Result<CmdResult>
+    where
+        O: Read + Send + 'static,
+        E: Read + Send + 'static,
+    {
+        let (tee_stdout, tee_stderr) = (self.tee_stdout, self.tee_stderr);
+        let stdout = stdout.map(|out| thread::spawn(move || Self::drain_pipe(out, tee_stdout)));
+        let stderr = stderr.map(|out| thread::spawn(move || Self::drain_pipe(out, tee_stderr)));
+
+        // The command runs in its own process group (see `with_timeout`), the
+        // negative pid means sending signal to the whole group.
+        let kill_group = |child: &mut Child| {
+            unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+            let _ = child.wait();
+        };
+
+        let _interrupt = Interrupt::catch();
+        let start = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait().context("Wait command done")? {
+                break status;
+            }
+            if interrupt::is_interrupted() {
+                kill_group(&mut child);
+                bail!("command `{}` interrupted", self.get_name());
+            }
+            if start.elapsed() >= timeout {
+                kill_group(&mut child);
+                bail!(TimeoutError {
+                    message: format!(
+                        "command `{}` timed out after {}",
+                        result_display.as_deref().unwrap_or(self.get_name()),
+                        utils::format_elapsed(timeout)
+                    ),
+                });
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        let join = |handle: Option<thread::JoinHandle<String>>| -> String {
+            handle
+                .map(|handle| handle.join().unwrap_or_default())
+                .unwrap_or_default()
+        };
+
+        Ok(CmdResult {
+            code: status.code(),
+            display: result_display,
+            stdout: join(stdout),
+            stderr: join(stderr),
+        })
+    }
+
+    fn drain_pipe(pipe: impl Read, tee: bool) -> String {
+        if tee {
+            Self::tee_pipe(pipe)
+        } else {
+            Self::read_pipe(pipe)
+        }
+    }
+
+    fn read_pipe(mut pipe: impl Read) -> String {
+        let mut out = String::new();
+        let _ = pipe.read_to_string(&mut out);
+        out
+    }
+
     fn show(&self) -> Option<String> {
         match &self.display {
             CmdDisplay::None => Some(self.full()),
@@ -360,12 +667,236 @@ impl Cmd {
         cmd_args.join(" ")
     }
 
+    /// The program and args (quoted) for debug logs. Unlike the [`Command`]'s
+    /// debug format, the envs are not included, since they may contain secrets;
+    /// and the secret values in args are masked.
+    fn debug_string(&self) -> String {
+        let mut parts = vec![format!("{:?}", self.get_name())];
+        for arg in self.cmd.get_args() {
+            parts.push(format!("{:?}", arg.to_string_lossy()));
+        }
+        let mut s = parts.join(" ");
+        for secret in self.secrets.iter() {
+            s = s.replace(secret.as_str(), "******");
+        }
+        s
+    }
+
     #[inline]
+    fn span(&self) -> timings::Span {
+        match self.get_name() {
+            "git" => timings::span("git"),
+            _ => timings::span("command"),
+        }
+    }
+
     fn get_name(&self) -> &str {
         self.cmd.get_program().to_str().unwrap_or("<unknown>")
     }
 }
 
+/// Return the git subcommand in args, the global options (such as `-C <path>`)
+/// are skipped.
+fn git_subcommand<'a>(args: &[&'a str]) -> Option<&'a str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if matches!(*arg, "-C" | "-c") {
+            iter.next();
+            continue;
+        }
+        if arg.starts_with('-') {
+            continue;
+        }
+        return Some(arg);
+    }
+    None
+}
+
+/// Return `true` if the git error message indicates a transient network error,
+/// which is worth retrying. Errors such as authentication failure, missing
+/// repository or rejected push are not retryable.
+pub fn is_retryable_git_error(stderr: &str) -> bool {
+    git_error_kind(stderr) == Some(ErrorKind::Network)
+}
+
+/// Return the kind of the git error message, [`None`] if it is unknown.
+pub fn git_error_kind(stderr: &str) -> Option<ErrorKind> {
+    const FATAL_PATTERNS: [(&str, ErrorKind); 8] = [
+        ("authentication failed", ErrorKind::AuthFailed),
+        ("permission denied", ErrorKind::AuthFailed),
+        ("could not read username", ErrorKind::AuthFailed),
+        ("repository not found", ErrorKind::NotFound),
+        (
+            "does not appear to be a git repository",
+            ErrorKind::NotFound,
+        ),
+        ("[rejected]", ErrorKind::Conflict),
+        ("conflict", ErrorKind::Conflict),
+        (
+            "already exists and is not an empty directory",
+            ErrorKind::Conflict,
+        ),
+    ];
+    const RETRYABLE_PATTERNS: [&str; 14] = [
+        "could not resolve host",
+        "temporary failure in name resolution",
+        "connection timed out",
+        "operation timed out",
+        "connection reset",
+        "connection refused",
+        "failed to connect",
+        "network is unreachable",
+        "the remote end hung up unexpectedly",
+        "early eof",
+        "rpc failed",
+        "gnutls",
+        "ssl_error",
+        "the requested url returned error: 5",
+    ];
+
+    let stderr = stderr.to_lowercase();
+    if let Some((_, kind)) = FATAL_PATTERNS
+        .iter()
+        .find(|(pattern, _)| stderr.contains(pattern))
+    {
+        return Some(*kind);
+    }
+    if RETRYABLE_PATTERNS
+        .iter()
+        .any(|pattern| stderr.contains(pattern))
+    {
+        return Some(ErrorKind::Network);
+    }
+    None
+}
+
+/// Parse git `--progress` output and drive [`Progress`] for each phase, such as
+/// "Receiving objects" and "Resolving deltas". In a batch worker, the phases are
+/// reported to the worker bar instead, and the received bytes and checked out
+/// files are added to the combined counter (see [`progress::MultiProgress`]).
+#[derive(Default)]
+struct GitProgress {
+    phase: Option<(String, Progress)>,
+
+    /// The received bytes and the checked out files counted so far.
+    bytes: u64,
+    files: u64,
+
+    /// The lines that are not progress, such as error messages.
+    output: String,
+}
+
+impl GitProgress {
+    /// Read the output, git uses `\r` to refresh the progress line, so both
+    /// `\r` and `\n` are treated as line separators.
+    fn consume(&mut self, pipe: impl Read) -> io::Result<()> {
+        let mut line = Vec::new();
+        for byte in BufReader::new(pipe).bytes() {
+            let byte = byte?;
+            if byte == b'\r' || byte == b'\n' {
+                self.handle_line(&String::from_utf8_lossy(&line));
+                line.clear();
+                continue;
+            }
+            line.push(byte);
+        }
+        if !line.is_empty() {
+            self.handle_line(&String::from_utf8_lossy(&line));
+        }
+        Ok(())
+    }
+
+    fn handle_line(&mut self, line: &str) {
+        if let Some(remote_line) = line.strip_prefix("remote:") {
+            // The remote side progress, such as "remote: Counting objects", is
+            // usually fast, ignore it.
+            if parse_git_progress(remote_line).is_some() {
+                return;
+            }
+        }
+        let (phase, current, total) = match parse_git_progress(line) {
+            Some(progress) => progress,
+            None => {
+                if !line.trim().is_empty() {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+                return;
+            }
+        };
+        if progress::report(phase, current, total) {
+            self.count(line, phase, current);
+            return;
+        }
+        let is_current = matches!(self.phase.as_ref(), Some((name, _)) if name == phase);
+        if !is_current {
+            if let Some((_, mut progress)) = self.phase.take() {
+                progress.finish();
+            }
+            let progress = Progress::new(
+                format!("{phase}:"),
+                phase.to_string(),
+                total,
+                ProgressUnit::Count,
+            );
+            self.phase = Some((phase.to_string(), progress));
+        }
+        if let Some((_, progress)) = self.phase.as_mut() {
+            progress.set(current, total);
+        }
+    }
+
+    fn count(&mut self, line: &str, phase: &str, current: u64) {
+        match phase {
+            "Receiving objects" => {
+                if let Some(bytes) = parse_git_bytes(line) {
+                    progress::count(bytes.saturating_sub(self.bytes), 0);
+                    self.bytes = self.bytes.max(bytes);
+                }
+            }
+            "Updating files" => {
+                progress::count(0, current.saturating_sub(self.files));
+                self.files = self.files.max(current);
+            }
+            _ => {}
+        }
+    }
+
+    /// Return the output that is not progress. If the last phase is not done,
+    /// it will be marked as failed.
+    fn finish(self) -> String {
+        self.output
+    }
+}
+
+/// Parse git progress line, for example,
+/// "Receiving objects:  45% (450/1000), 1.20 MiB | 1.10 MiB/s", return the phase
+/// name, current and total.
+fn parse_git_progress(line: &str) -> Option<(&str, u64, u64)> {
+    let (phase, rest) = line.split_once(':')?;
+    let rest = rest.trim_start();
+    if !rest.chars().next()?.is_ascii_digit() {
+        return None;
+    }
+    let start = rest.find('(')?;
+    let end = start + rest[start..].find(')')?;
+    let (current, total) = rest[start + 1..end].split_once('/')?;
+    let current = current.trim().parse().ok()?;
+    let total = total.trim().parse().ok()?;
+    Some((phase.trim(), current, total))
+}
+
+/// Parse the received size in git progress line, for example,
+/// "Receiving objects:  45% (450/1000), 1.20 MiB | 1.10 MiB/s", return the
+/// bytes of "1.20 MiB".
+fn parse_git_bytes(line: &str) -> Option<u64> {
+    let (_, rest) = line.split_once(')')?;
+    let rest = rest.strip_prefix(',')?;
+    let size = rest.split('|').next()?.trim();
+    let size = size.strip_suffix(" bytes").unwrap_or(size);
+    utils::parse_bytes(size).ok()
+}
+
 /// This is a simple wrapper for [`Cmd`] that allows setting the working directory.
 /// For GitCmd, this is achieved using args `-C <work-dir>`.
 ///
@@ -417,7 +948,8 @@ impl<'a> GitCmd<'a> {
 }
 
 /// Use the `fzf` command to search through multiple items. Return the index of the
-/// selected item from the search results.
+/// selected item from the search results. If `fzf` is not installed, use the
+/// builtin fuzzy finder, see [`term::select::fuzzy_select`].
 ///
 /// # Examples
 ///
@@ -430,6 +962,10 @@ pub fn fzf_search<S>(keys: &[S]) -> Result<usize>
 where
     S: AsRef<str>,
 {
+    if !has_program("fzf") {
+        return term::select::fuzzy_select(keys);
+    }
+
     let mut input = String::with_capacity(keys.len());
     for key in keys {
         input.push_str(key.as_ref());
@@ -455,3 +991,196 @@ where
         _ => bail!("fzf returned an unknown error"),
     }
 }
+
+/// Return `true` if the executable `name` can be found in `$PATH`.
+pub fn has_program(name: &str) -> bool {
+    let paths = match env::var_os("PATH") {
+        Some(paths) => paths,
+        None => return false,
+    };
+    env::split_paths(&paths).any(|dir| {
+        let path = dir.join(name);
+        match fs::metadata(&path) {
+            Ok(meta) => meta.is_file() && meta.permissions().mode() & 0o111 != 0,
+            Err(_) => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod exec_tests {
+    use std::time::{Duration, Instant};
+
+    use crate::errors;
+    use crate::exec::*;
+
+    #[test]
+    fn test_debug_string() {
+        let mut cmd = Cmd::sh("echo s3cr3t; echo $API_TOKEN", false);
+        cmd.with_secret_env("API_TOKEN", "s3cr3t");
+        cmd.with_env("NAME", "roxide");
+        assert_eq!(
+            cmd.debug_string(),
+            r#""sh" "-c" "echo ******; echo $API_TOKEN""#
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let start = Instant::now();
+        let mut cmd = Cmd::sh("sleep 5 & sleep 5", false);
+        cmd.with_timeout(Duration::from_millis(200));
+        let err = cmd.execute().unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(errors::is_timeout(&err));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let mut cmd = Cmd::sh("echo hello", false);
+        cmd.with_timeout(Duration::from_secs(5));
+        assert_eq!(cmd.read().unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_timeout_interrupt() {
+        let pid_path = env::temp_dir().join(format!("roxide-interrupt-{}", std::process::id()));
+        let script = format!("sleep 30 & echo $! > {}; wait", pid_path.display());
+
+        let current = std::thread::current().id();
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            interrupt::simulate(current);
+        });
+        let start = Instant::now();
+        let mut cmd = Cmd::sh(&script, false);
+        cmd.with_timeout(Duration::from_secs(60));
+        let err = cmd.execute().unwrap_err();
+        interrupter.join().unwrap();
+        assert!(err.to_string().contains("interrupted"));
+        assert!(start.elapsed() < Duration::from_secs(30));
+
+        // The background sleep is in the same group, it must be killed too.
+        let pid = fs::read_to_string(&pid_path).unwrap();
+        fs::remove_file(&pid_path).unwrap();
+        let stat = PathBuf::from(format!("/proc/{}/stat", pid.trim()));
+        for _ in 0..50 {
+            match fs::read_to_string(&stat) {
+                Ok(stat) if !stat.contains(") Z ") => {}
+                _ => return,
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("the interrupted step {} is still running", pid.trim());
+    }
+
+    #[test]
+    fn test_tee() {
+        let mut cmd = Cmd::sh("echo out; echo err >&2", true);
+        cmd.with_tee(true);
+        let result = cmd.execute_unchecked().unwrap();
+        assert_eq!(result.stdout, "out\n");
+        assert_eq!(result.stderr, "err\n");
+
+        let mut cmd = Cmd::sh("echo out; echo err >&2", true);
+        cmd.with_tee(false).with_timeout(Duration::from_secs(5));
+        let result = cmd.execute_unchecked().unwrap();
+        assert_eq!(result.stdout, "out\n");
+        assert_eq!(result.stderr, "err\n");
+    }
+
+    #[test]
+    fn test_large_stderr() {
+        // Larger than the pipe buffer, reading stdout first should not block.
+        let mut cmd = Cmd::sh("head -c 1048576 /dev/zero >&2; echo hello", false);
+        assert_eq!(cmd.read().unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_parse_git_progress() {
+        assert_eq!(
+            parse_git_progress("Receiving objects:  45% (450/1000), 1.20 MiB | 1.10 MiB/s"),
+            Some(("Receiving objects", 450, 1000))
+        );
+        assert_eq!(
+            parse_git_progress("Resolving deltas: 100% (20/20), done."),
+            Some(("Resolving deltas", 20, 20))
+        );
+        assert_eq!(
+            parse_git_progress(" Counting objects: 100% (5/5), done."),
+            Some(("Counting objects", 5, 5))
+        );
+        assert_eq!(parse_git_progress("Cloning into 'roxide'..."), None);
+        assert_eq!(
+            parse_git_progress("fatal: repository 'x' not found (code: 1/2)"),
+            None
+        );
+
+        let mut tracker = GitProgress::default();
+        tracker
+            .consume(
+                "Cloning into 'test'...\nremote: Counting objects: 100% (5/5), done.\nfatal: error\n"
+                    .as_bytes(),
+            )
+            .unwrap();
+        assert_eq!(tracker.finish(), "Cloning into 'test'...\nfatal: error\n");
+
+        assert_eq!(
+            parse_git_bytes("Receiving objects:  45% (450/1000), 1.20 MiB | 1.10 MiB/s"),
+            Some(1258291)
+        );
+        assert_eq!(
+            parse_git_bytes("Receiving objects: 100% (3/3), 512 bytes | 512.00 KiB/s, done."),
+            Some(512)
+        );
+        assert_eq!(
+            parse_git_bytes("Resolving deltas: 100% (20/20), done."),
+            None
+        );
+    }
+
+    #[test]
+    fn test_git_retry() {
+        assert_eq!(git_subcommand(&["clone", "url"]), Some("clone"));
+        assert_eq!(
+            git_subcommand(&["-C", "/path", "-c", "a=b", "--no-pager", "fetch"]),
+            Some("fetch")
+        );
+        assert_eq!(git_subcommand(&["--version"]), None);
+
+        assert!(is_retryable_git_error(
+            "fatal: unable to access 'https://github.com/a/b.git/': Could not resolve host: github.com"
+        ));
+        assert!(is_retryable_git_error(
+            "error: RPC failed; curl 56 GnuTLS recv error (-9)\nfatal: early EOF"
+        ));
+        assert!(is_retryable_git_error(
+            "fatal: unable to access 'x': The requested URL returned error: 502"
+        ));
+        assert!(!is_retryable_git_error(
+            "remote: Repository not found.\nfatal: repository 'x' not found"
+        ));
+        assert!(!is_retryable_git_error(
+            "fatal: Authentication failed for 'https://github.com/a/b.git/'"
+        ));
+        assert!(!is_retryable_git_error(
+            " ! [rejected]        main -> main (non-fast-forward)"
+        ));
+        assert!(!is_retryable_git_error(
+            "CONFLICT (content): Merge conflict in a.rs"
+        ));
+
+        assert_eq!(
+            git_error_kind("remote: Repository not found."),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(
+            git_error_kind("git@github.com: Permission denied (publickey)."),
+            Some(ErrorKind::AuthFailed)
+        );
+        assert_eq!(
+            git_error_kind("CONFLICT (content): Merge conflict in a.rs"),
+            Some(ErrorKind::Conflict)
+        );
+        assert_eq!(git_error_kind("fatal: early EOF"), Some(ErrorKind::Network));
+        assert_eq!(git_error_kind("fatal: bad revision 'x'"), None);
+    }
+}
@@ -1,6 +1,8 @@
 use std::io::Write;
 use std::path::PathBuf;
 use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
 
@@ -9,7 +11,8 @@ use crate::utils;
 
 /// UNIX file lock are utilized to lock an entire process during an operation,
 /// enabling certain process-level atomic operations. Once a process acquires a file
-/// lock, any attempts by other identical processes to acquire the lock will fail.
+/// lock, any attempts by other identical processes to acquire the lock will wait
+/// for `lock_timeout` in config, and then fail.
 /// There's no need for manual release of the file lock; it automatically releases
 /// upon object release.
 ///
@@ -23,10 +26,13 @@ pub struct FileLock {
 impl FileLock {
     const RESOURCE_TEMPORARILY_UNAVAILABLE_CODE: i32 = 11;
 
+    /// The interval to retry when the lock is occupied by another process.
+    const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
     /// Attempt to acquire the file lock; this function will fail if there are
-    /// issues with the filesystem or if another process has already acquired the
-    /// lock. We will create a `lock_{name}` file lock under the metadir directory,
-    /// which will store the current process's PID.
+    /// issues with the filesystem or if another process still holds the lock
+    /// after `lock_timeout`. We will create a `lock_{name}` file lock under the
+    /// metadir directory, which will store the current process's PID.
     ///
     /// # Arguments
     ///
@@ -37,20 +43,28 @@ impl FileLock {
         let path = cfg.get_meta_dir().join("lock").join(name.as_ref());
         utils::ensure_dir(&path)?;
 
-        let lock_opts = file_lock::FileOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true);
-        let mut file_lock = match file_lock::FileLock::lock(&path, false, lock_opts) {
-            Ok(lock) => lock,
-            Err(err) => match err.raw_os_error() {
-                Some(code) if code == Self::RESOURCE_TEMPORARILY_UNAVAILABLE_CODE => {
-                    bail!("acquire file lock error, {} is occupied by another roxide, please wait for it to complete", name.as_ref());
-                }
-                _ => {
-                    return Err(err).with_context(|| format!("acquire file lock {}", name.as_ref()))
-                }
-            },
+        let deadline = Instant::now() + Duration::from_millis(cfg.lock_timeout);
+        let mut file_lock = loop {
+            let lock_opts = file_lock::FileOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true);
+            match file_lock::FileLock::lock(&path, false, lock_opts) {
+                Ok(lock) => break lock,
+                Err(err) => match err.raw_os_error() {
+                    Some(code) if code == Self::RESOURCE_TEMPORARILY_UNAVAILABLE_CODE => {
+                        if Instant::now() < deadline {
+                            thread::sleep(Self::RETRY_INTERVAL);
+                            continue;
+                        }
+                        bail!("acquire file lock error, {} is occupied by another roxide, please wait for it to complete", name.as_ref());
+                    }
+                    _ => {
+                        return Err(err)
+                            .with_context(|| format!("acquire file lock {}", name.as_ref()))
+                    }
+                },
+            }
         };
 
         // Write current pid to file lock.
@@ -1,18 +1,22 @@
 use std::collections::HashSet;
-use std::path::Path;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context, Result};
 use chrono::Local;
+use clap::ValueEnum;
 use console::{style, StyledObject};
 use glob::Pattern as GlobPattern;
 use regex::{Captures, Regex};
 
 use crate::api::Provider;
 use crate::config::Config;
+use crate::errors::ErrorKind;
 use crate::exec::Cmd;
 use crate::repo::Repo;
-use crate::utils;
 use crate::{confirm, info};
+use crate::{term, utils};
 
 /// List all git files in `path`, use `git ls-files`, this will respect `.gitignore` file.
 /// Also, this function will respect `ignores` arg, matched path will not be returned.
@@ -50,6 +54,92 @@ pub fn ensure_no_uncommitted() -> Result<()> {
     Ok(())
 }
 
+/// Commit the staged changes with `message`. If `all` is true, also stage the
+/// modified and deleted files, see `git commit -a`.
+pub fn commit(message: &str, all: bool) -> Result<()> {
+    let mut args = vec!["commit", "-m", message];
+    if all {
+        args.push("-a");
+    }
+    let header = message.lines().next().unwrap_or_default();
+    Cmd::git(&args)
+        .with_display(format!("Commit '{header}'"))
+        .execute()
+}
+
+/// Setup the layout of the bare clone in `{path}/.bare`: the `{path}/.git` file
+/// points to the bare gitdir, and the default branch is checked out in
+/// worktree `{path}/{branch}`. Return the default branch.
+pub fn setup_bare(path: &Path) -> Result<String> {
+    utils::write_file(&path.join(".git"), b"gitdir: ./.bare\n")?;
+
+    let path = format!("{}", path.display());
+    // The bare clone does not setup the fetch refspec, without it, the remote
+    // branches could not be tracked.
+    Cmd::git(&[
+        "-C",
+        path.as_str(),
+        "config",
+        "remote.origin.fetch",
+        "+refs/heads/*:refs/remotes/origin/*",
+    ])
+    .execute()?;
+
+    let branch = Cmd::git(&["-C", path.as_str(), "symbolic-ref", "--short", "HEAD"]).read()?;
+    let worktree = format!("{path}/{branch}");
+    Cmd::git(&["-C", path.as_str(), "worktree", "add", &worktree, &branch]).execute()?;
+    Ok(branch)
+}
+
+/// Return `true` if the repository in `path` uses Git LFS, that is, its
+/// `.gitattributes` file contains the `filter=lfs` attribute.
+pub fn uses_lfs(path: &Path) -> Result<bool> {
+    let attrs_path = path.join(".gitattributes");
+    match fs::read_to_string(&attrs_path) {
+        Ok(content) => Ok(has_lfs_filter(&content)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err).with_context(|| format!("read file '{}'", attrs_path.display())),
+    }
+}
+
+fn has_lfs_filter(content: &str) -> bool {
+    content.lines().any(|line| {
+        let line = line.trim();
+        !line.starts_with('#') && line.split_whitespace().any(|attr| attr == "filter=lfs")
+    })
+}
+
+/// Return the size of the LFS objects downloaded to the repository in `path`,
+/// they are stored in `{gitdir}/lfs/objects`.
+pub fn lfs_size(path: &Path) -> Result<u64> {
+    let path = format!("{}", path.display());
+    let git_dir = Cmd::git(&["-C", path.as_str(), "rev-parse", "--git-common-dir"]).read()?;
+    let git_dir = PathBuf::from(&path).join(git_dir.trim());
+    utils::dir_size(git_dir.join("lfs").join("objects"))
+}
+
+/// If the repository in `path` uses Git LFS, ask the user whether to run
+/// `git lfs install` and `git lfs pull` to download the LFS objects.
+pub fn setup_lfs(path: &Path) -> Result<()> {
+    if !uses_lfs(path)? {
+        return Ok(());
+    }
+    let path = format!("{}", path.display());
+    if Cmd::git(&["lfs", "version"]).read().is_err() {
+        bail!("the repo uses Git LFS, but git-lfs is not installed");
+    }
+    if !term::confirm("The repo uses Git LFS, do you want to pull the LFS objects")? {
+        return Ok(());
+    }
+    Cmd::git(&["-C", path.as_str(), "lfs", "install", "--local"])
+        .with_display("Install Git LFS hooks")
+        .execute()?;
+    Cmd::git(&["-C", path.as_str(), "lfs", "pull"])
+        .with_display("Pull LFS objects")
+        .execute()?;
+    Ok(())
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum BranchStatus {
     Sync,
@@ -186,6 +276,20 @@ impl GitBranch {
         bail!("no default branch returned by git remote show, please check your git command");
     }
 
+    /// Return the issue linked to the branch by `branch --issue`, stored in
+    /// the git config `branch.{name}.issue`.
+    pub fn get_issue(name: &str) -> Option<u64> {
+        let key = format!("branch.{name}.issue");
+        let value = Cmd::git(&["config", "--get", key.as_str()]).read().ok()?;
+        value.parse().ok()
+    }
+
+    pub fn set_issue(name: &str, id: u64) -> Result<()> {
+        let key = format!("branch.{name}.issue");
+        let value = format!("{id}");
+        Cmd::git(&["config", key.as_str(), value.as_str()]).execute()
+    }
+
     pub fn current(mute: bool) -> Result<String> {
         let mut cmd = Cmd::git(&["branch", "--show-current"]);
         if !mute {
@@ -389,7 +493,7 @@ impl GitTag {
                 return Ok(tag);
             }
         }
-        bail!("could not find tag '{}'", s.as_ref())
+        Err(ErrorKind::NotFound.error(format!("could not find tag '{}'", s.as_ref())))
     }
 
     pub fn new(s: impl AsRef<str>) -> GitTag {
@@ -451,6 +555,268 @@ impl GitTag {
 
         Ok(GitTag(result))
     }
+
+    /// Bump the tag as a semantic version. The prefix before the version
+    /// (such as `v`) is kept, the pre-release and build suffix is dropped.
+    pub fn bump(&self, bump: TagBump) -> Result<GitTag> {
+        let tag = self.as_str();
+        let idx = match tag.find(|c: char| c.is_ascii_digit()) {
+            Some(idx) => idx,
+            None => bail!("tag '{tag}' is not a semantic version"),
+        };
+        let (prefix, version) = tag.split_at(idx);
+        let version = version.split(['-', '+']).next().unwrap_or_default();
+
+        let mut nums = [0_u64; 3];
+        for (i, part) in version.split('.').enumerate() {
+            if i >= nums.len() {
+                bail!("tag '{tag}' is not a semantic version");
+            }
+            nums[i] = match part.parse() {
+                Ok(num) => num,
+                Err(_) => bail!("tag '{tag}' is not a semantic version"),
+            };
+        }
+
+        let [major, minor, patch] = nums;
+        let (major, minor, patch) = match bump {
+            TagBump::Major => (major + 1, 0, 0),
+            TagBump::Minor => (major, minor + 1, 0),
+            TagBump::Patch => (major, minor, patch + 1),
+        };
+        Ok(GitTag(format!("{prefix}{major}.{minor}.{patch}")))
+    }
+}
+
+/// A commit message header in the conventional-commit format, that is
+/// `type(scope)!: summary`, see <https://www.conventionalcommits.org>.
+#[derive(Debug, PartialEq)]
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub summary: String,
+}
+
+impl std::fmt::Display for ConventionalCommit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(scope) = self.scope.as_ref() {
+            write!(f, "({scope})")?;
+        }
+        if self.breaking {
+            write!(f, "!")?;
+        }
+        write!(f, ": {}", self.summary)
+    }
+}
+
+impl ConventionalCommit {
+    /// Parse the commit header, return [`None`] if it is not a conventional
+    /// commit.
+    pub fn parse(header: &str) -> Option<ConventionalCommit> {
+        let (prefix, summary) = header.split_once(':')?;
+        let summary = summary.trim();
+        if summary.is_empty() {
+            return None;
+        }
+
+        let (prefix, breaking) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => {
+                let scope = scope.strip_suffix(')')?.trim();
+                if scope.is_empty() {
+                    return None;
+                }
+                (kind, Some(scope.to_string()))
+            }
+            None => (prefix, None),
+        };
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        Some(ConventionalCommit {
+            kind: kind.to_lowercase(),
+            scope,
+            breaking,
+            summary: summary.to_string(),
+        })
+    }
+}
+
+/// The semantic version part to bump for a tag.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TagBump {
+    Major,
+    Minor,
+    Patch,
+}
+
+/// A linked worktree of a repository, see `git worktree`.
+#[derive(Debug, PartialEq)]
+pub struct GitWorktree {
+    pub path: String,
+
+    /// The checked out branch, [`None`] if the HEAD is detached.
+    pub branch: Option<String>,
+}
+
+impl GitWorktree {
+    /// List the non-bare worktrees of the repository in `path`.
+    pub fn list(path: &str) -> Result<Vec<GitWorktree>> {
+        let lines = Cmd::git(&["-C", path, "worktree", "list", "--porcelain"]).lines()?;
+        Ok(Self::parse(lines))
+    }
+
+    /// Parse the output of `git worktree list --porcelain`, the bare entry will
+    /// be skipped.
+    pub fn parse(lines: Vec<String>) -> Vec<GitWorktree> {
+        let mut worktrees = Vec::new();
+        let mut current: Option<GitWorktree> = None;
+        let mut bare = false;
+        for line in lines.into_iter().chain([String::new()]) {
+            let line = line.trim();
+            if line.is_empty() {
+                if let Some(worktree) = current.take() {
+                    if !bare {
+                        worktrees.push(worktree);
+                    }
+                }
+                bare = false;
+                continue;
+            }
+            if let Some(path) = line.strip_prefix("worktree ") {
+                current = Some(GitWorktree {
+                    path: path.to_string(),
+                    branch: None,
+                });
+                continue;
+            }
+            if line == "bare" {
+                bare = true;
+                continue;
+            }
+            if let Some(branch) = line.strip_prefix("branch ") {
+                if let Some(worktree) = current.as_mut() {
+                    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+                    worktree.branch = Some(branch.to_string());
+                }
+            }
+        }
+        worktrees
+    }
+}
+
+/// A stash entry of a repository, see `git stash`.
+#[derive(Debug, PartialEq)]
+pub struct GitStash {
+    /// The stash reference, like `stash@{0}`.
+    pub name: String,
+
+    /// The branch the stash was created on, [`None`] if it cannot be parsed.
+    pub branch: Option<String>,
+
+    pub message: String,
+}
+
+impl GitStash {
+    /// List the stash entries of the repository in `path`, the newest first.
+    pub fn list(path: &str) -> Result<Vec<GitStash>> {
+        let lines = Cmd::git(&["-C", path, "stash", "list", "--format=%gd%x00%gs"]).lines()?;
+        Ok(lines.into_iter().filter_map(Self::parse).collect())
+    }
+
+    /// Parse a line of `git stash list --format=%gd%x00%gs`. The subject is
+    /// something like `WIP on main: 1a2b3c4 commit message` or `On main: message`.
+    pub fn parse(line: impl AsRef<str>) -> Option<GitStash> {
+        let (name, subject) = line.as_ref().split_once('\0')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let rest = subject
+            .strip_prefix("WIP on ")
+            .or_else(|| subject.strip_prefix("On "));
+        let (branch, message) = match rest.and_then(|rest| rest.split_once(": ")) {
+            Some((branch, message)) => (Some(branch.to_string()), message),
+            None => (None, subject),
+        };
+
+        Some(GitStash {
+            name: name.to_string(),
+            branch,
+            message: message.trim().to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for GitStash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.branch.as_ref() {
+            Some(branch) => write!(f, "{}: ({}) {}", self.name, branch, self.message),
+            None => write!(f, "{}: {}", self.name, self.message),
+        }
+    }
+}
+
+/// The working tree status of a repository, for shell prompts.
+#[derive(Debug, Default, PartialEq)]
+pub struct GitStatus {
+    /// The current branch, [`None`] if the HEAD is detached.
+    pub branch: Option<String>,
+
+    /// There are uncommitted changes, including untracked files.
+    pub dirty: bool,
+
+    /// The commits ahead of and behind the upstream, zero if no upstream.
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+impl GitStatus {
+    /// Read the status of the repository in `path`, this only runs one git
+    /// command, so it is fast enough to embed in prompts.
+    pub fn read(path: &str) -> Result<GitStatus> {
+        let lines = Cmd::git(&["-C", path, "status", "--porcelain=v2", "--branch"]).lines()?;
+        Ok(Self::parse(lines))
+    }
+
+    /// Parse the output of `git status --porcelain=v2 --branch`.
+    pub fn parse(lines: Vec<String>) -> GitStatus {
+        let mut status = GitStatus::default();
+        for line in lines {
+            let header = match line.strip_prefix("# ") {
+                Some(header) => header,
+                None => {
+                    if !line.trim().is_empty() {
+                        status.dirty = true;
+                    }
+                    continue;
+                }
+            };
+            if let Some(head) = header.strip_prefix("branch.head ") {
+                if head != "(detached)" {
+                    status.branch = Some(head.to_string());
+                }
+                continue;
+            }
+            if let Some(ab) = header.strip_prefix("branch.ab ") {
+                for field in ab.split_whitespace() {
+                    if let Some(ahead) = field.strip_prefix('+') {
+                        status.ahead = ahead.parse().unwrap_or_default();
+                    } else if let Some(behind) = field.strip_prefix('-') {
+                        status.behind = behind.parse().unwrap_or_default();
+                    }
+                }
+            }
+        }
+        status
+    }
 }
 
 #[cfg(test)]
@@ -553,4 +919,183 @@ mod git_tests {
             assert_eq!(result.as_str(), expect);
         }
     }
+
+    #[test]
+    fn test_bump_tag() {
+        let cases = vec![
+            ("v0.1.0", TagBump::Patch, "v0.1.1"),
+            ("v0.1.3", TagBump::Minor, "v0.2.0"),
+            ("v1.9.3", TagBump::Major, "v2.0.0"),
+            ("1.2", TagBump::Patch, "1.2.1"),
+            ("release-2.0.1-rc1", TagBump::Patch, "release-2.0.2"),
+        ];
+
+        for (before, bump, expect) in cases {
+            let tag = GitTag(String::from(before));
+            let result = tag.bump(bump).unwrap();
+            assert_eq!(result.as_str(), expect);
+        }
+
+        assert!(GitTag(String::from("latest")).bump(TagBump::Patch).is_err());
+        assert!(GitTag(String::from("v1.2.3.4"))
+            .bump(TagBump::Patch)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_conventional_commit() {
+        let cases = vec![
+            (
+                "feat: add changelog",
+                Some(ConventionalCommit {
+                    kind: String::from("feat"),
+                    scope: None,
+                    breaking: false,
+                    summary: String::from("add changelog"),
+                }),
+            ),
+            (
+                "fix(api)!: drop the old endpoint",
+                Some(ConventionalCommit {
+                    kind: String::from("fix"),
+                    scope: Some(String::from("api")),
+                    breaking: true,
+                    summary: String::from("drop the old endpoint"),
+                }),
+            ),
+            ("Update README", None),
+            ("feat():  empty scope", None),
+            ("feat: ", None),
+            ("Merge branch 'main': conflicts", None),
+        ];
+
+        for (header, expect) in cases {
+            let commit = ConventionalCommit::parse(header);
+            if let Some(commit) = commit.as_ref() {
+                assert_eq!(commit.to_string(), header);
+            }
+            assert_eq!(commit, expect);
+        }
+    }
+
+    #[test]
+    fn test_parse_worktree() {
+        let output = r#"
+worktree /path/to/repo/.bare
+bare
+
+worktree /path/to/repo/main
+HEAD 8f3c1a6b2d9e4f7a0c5b1e2d3f4a5b6c7d8e9f01
+branch refs/heads/main
+
+worktree /path/to/repo/feat
+HEAD 1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b
+detached
+"#;
+        let lines: Vec<String> = output.lines().map(|line| line.to_string()).collect();
+        let worktrees = GitWorktree::parse(lines);
+        assert_eq!(
+            worktrees,
+            vec![
+                GitWorktree {
+                    path: String::from("/path/to/repo/main"),
+                    branch: Some(String::from("main")),
+                },
+                GitWorktree {
+                    path: String::from("/path/to/repo/feat"),
+                    branch: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_stash() {
+        let cases = vec![
+            (
+                "stash@{0}\0WIP on main: 1a2b3c4 Fix the bug",
+                Some(GitStash {
+                    name: String::from("stash@{0}"),
+                    branch: Some(String::from("main")),
+                    message: String::from("1a2b3c4 Fix the bug"),
+                }),
+            ),
+            (
+                "stash@{1}\0On feat/stash: save the work",
+                Some(GitStash {
+                    name: String::from("stash@{1}"),
+                    branch: Some(String::from("feat/stash")),
+                    message: String::from("save the work"),
+                }),
+            ),
+            (
+                "stash@{2}\0autostash",
+                Some(GitStash {
+                    name: String::from("stash@{2}"),
+                    branch: None,
+                    message: String::from("autostash"),
+                }),
+            ),
+            ("invalid line", None),
+        ];
+        for (line, expect) in cases {
+            assert_eq!(GitStash::parse(line), expect);
+        }
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let cases = vec![
+            (
+                vec![
+                    "# branch.oid 1a2b3c4d",
+                    "# branch.head main",
+                    "# branch.upstream origin/main",
+                    "# branch.ab +2 -1",
+                ],
+                GitStatus {
+                    branch: Some(String::from("main")),
+                    dirty: false,
+                    ahead: 2,
+                    behind: 1,
+                },
+            ),
+            (
+                vec![
+                    "# branch.oid 1a2b3c4d",
+                    "# branch.head feat/prompt",
+                    "1 .M N... 100644 100644 100644 1a2b3c 1a2b3c src/main.rs",
+                    "? new.txt",
+                ],
+                GitStatus {
+                    branch: Some(String::from("feat/prompt")),
+                    dirty: true,
+                    ahead: 0,
+                    behind: 0,
+                },
+            ),
+            (
+                vec!["# branch.oid 1a2b3c4d", "# branch.head (detached)"],
+                GitStatus::default(),
+            ),
+        ];
+        for (lines, expect) in cases {
+            let lines = lines.into_iter().map(String::from).collect();
+            assert_eq!(GitStatus::parse(lines), expect);
+        }
+    }
+
+    #[test]
+    fn test_lfs_filter() {
+        let content = r#"
+# Images
+*.png filter=lfs diff=lfs merge=lfs -text
+*.rs text eol=lf
+"#;
+        assert!(has_lfs_filter(content));
+        assert!(!has_lfs_filter("*.rs text eol=lf\n"));
+        assert!(!has_lfs_filter(
+            "# *.png filter=lfs diff=lfs merge=lfs -text\n"
+        ));
+    }
 }
@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Result};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The thread marked as interrupted by [`simulate`], the global flag is not used
+/// in tests, or the tests running in parallel would be interrupted too.
+#[cfg(test)]
+static SIMULATED: std::sync::Mutex<Option<std::thread::ThreadId>> = std::sync::Mutex::new(None);
+
+extern "C" fn handle_interrupt(_: libc::c_int) {
+    // Only the atomic operations are async-signal-safe here.
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Catch the Ctrl-C (SIGINT) during a long-running scan, so that the scan can
+/// stop at the next checkpoint and clean up, rather than killing the process
+/// immediately. The previous handler is restored when this is dropped.
+pub struct Interrupt {
+    previous: libc::sighandler_t,
+}
+
+impl Interrupt {
+    pub fn catch() -> Interrupt {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        let handler = handle_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        let previous = unsafe { libc::signal(libc::SIGINT, handler) };
+        Interrupt { previous }
+    }
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        unsafe { libc::signal(libc::SIGINT, self.previous) };
+    }
+}
+
+/// Return `true` if the Ctrl-C is pressed since the last [`Interrupt::catch`].
+pub fn is_interrupted() -> bool {
+    #[cfg(test)]
+    if *SIMULATED.lock().unwrap() == Some(std::thread::current().id()) {
+        return true;
+    }
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Mark the `thread` as interrupted without a real signal, a process-wide SIGINT
+/// in tests would kill the test runner once the handler is restored.
+#[cfg(test)]
+pub fn simulate(thread: std::thread::ThreadId) {
+    *SIMULATED.lock().unwrap() = Some(thread);
+}
+
+/// Return an error if the Ctrl-C is pressed, use this as the checkpoint in loops.
+pub fn check() -> Result<()> {
+    if is_interrupted() {
+        bail!("interrupted");
+    }
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// The log levels, the former is more important.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.trim().to_lowercase().as_str() {
+            "error" => Self::Error,
+            "warn" => Self::Warn,
+            "info" => Self::Info,
+            "debug" => Self::Debug,
+            "trace" => Self::Trace,
+            _ => bail!("unknown log level '{s}'"),
+        })
+    }
+}
+
+/// The leveled logger. The messages up to `Info` are always printed to stderr
+/// (by the `info!`, `warn!`, etc. macros), the `debug!` and `trace!` ones are
+/// printed only with `-v` and `-vv` (or the `ROXIDE_LOG` env). Optionally, all
+/// the messages are also written to a rotating log file, so that the
+/// intermittent issues can be diagnosed after the fact, see `log` in config.
+struct Logger {
+    stderr_level: Level,
+
+    file: Option<LogFile>,
+}
+
+struct LogFile {
+    level: Level,
+    json: bool,
+    file: Mutex<File>,
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    time: String,
+    level: &'static str,
+    pid: u32,
+    msg: &'a str,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Initialize the logger, `verbose` is the count of the `-v` flags. The
+/// `ROXIDE_LOG` env sets the stderr level too, the more verbose one wins.
+pub fn init(cfg: &Config, verbose: u8) -> Result<()> {
+    let mut stderr_level = match verbose {
+        0 => Level::Info,
+        1 => Level::Debug,
+        _ => Level::Trace,
+    };
+    if let Ok(value) = env::var("ROXIDE_LOG") {
+        if !value.is_empty() {
+            let level = Level::from_str(&value).context("parse env ROXIDE_LOG")?;
+            stderr_level = stderr_level.max(level);
+        }
+    }
+
+    let file = if cfg.log.file {
+        let path = log_path(cfg);
+        rotate(&path, cfg.log.max_size << 20, cfg.log.max_files)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("open log file '{}'", path.display()))?;
+        Some(LogFile {
+            level: cfg.log.level,
+            json: cfg.log.json,
+            file: Mutex::new(file),
+        })
+    } else {
+        None
+    };
+
+    let _ = LOGGER.set(Logger { stderr_level, file });
+    Ok(())
+}
+
+/// The log file path, under `{metadir}/logs`.
+pub fn log_path(cfg: &Config) -> PathBuf {
+    cfg.get_meta_dir().join("logs").join("roxide.log")
+}
+
+/// Rotate the log file if it exceeds `max_size` bytes: `roxide.log` is renamed
+/// to `roxide.log.1`, `roxide.log.1` to `roxide.log.2`, and so on, at most
+/// `max_files` old files are kept.
+fn rotate(path: &Path, max_size: u64, max_files: usize) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("create log directory '{}'", dir.display()))?;
+    }
+    let size = match fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).context("read log file metadata"),
+    };
+    if size < max_size {
+        return Ok(());
+    }
+
+    let rotated = |idx: usize| PathBuf::from(format!("{}.{idx}", path.display()));
+    if max_files == 0 {
+        return fs::remove_file(path).context("remove log file");
+    }
+    for idx in (1..max_files).rev() {
+        let from = rotated(idx);
+        if from.exists() {
+            fs::rename(&from, rotated(idx + 1)).context("rotate log file")?;
+        }
+    }
+    fs::rename(path, rotated(1)).context("rotate log file")
+}
+
+/// Return `true` if the messages of `level` should be printed to stderr.
+pub fn stderr_enabled(level: Level) -> bool {
+    let stderr_level = LOGGER
+        .get()
+        .map(|logger| logger.stderr_level)
+        .unwrap_or(Level::Info);
+    level <= stderr_level
+}
+
+/// Return `true` if the messages of `level` go anywhere, use this to skip
+/// formatting the expensive messages.
+pub fn enabled(level: Level) -> bool {
+    if stderr_enabled(level) {
+        return true;
+    }
+    LOGGER
+        .get()
+        .and_then(|logger| logger.file.as_ref())
+        .is_some_and(|file| level <= file.level)
+}
+
+/// Write the message to the log file, if it is enabled for the level. The
+/// terminal styles in the message are stripped.
+pub fn record(level: Level, msg: &str) {
+    let file = match LOGGER.get().and_then(|logger| logger.file.as_ref()) {
+        Some(file) if level <= file.level => file,
+        _ => return,
+    };
+    let msg = console::strip_ansi_codes(msg);
+    let time = Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string();
+    let pid = std::process::id();
+    let line = if file.json {
+        let line = JsonLine {
+            time,
+            level: level.as_str(),
+            pid,
+            msg: msg.as_ref(),
+        };
+        match serde_json::to_string(&line) {
+            Ok(line) => line,
+            Err(_) => return,
+        }
+    } else {
+        format!("{time} {:<5} [{pid}] {msg}", level.as_str().to_uppercase())
+    };
+    // Logging should never break the command, ignore the write errors.
+    if let Ok(mut file) = file.file.lock() {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+#[cfg(test)]
+mod log_tests {
+    use crate::log::*;
+
+    #[test]
+    fn test_rotate() {
+        let dir = PathBuf::from("_test/log_rotate");
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        let path = dir.join("roxide.log");
+        let rotated = |idx: usize| PathBuf::from(format!("{}.{idx}", path.display()));
+
+        // Not exists.
+        rotate(&path, 4, 2).unwrap();
+        assert!(!path.exists());
+
+        for content in ["0123", "4567", "89ab"] {
+            fs::write(&path, content).unwrap();
+            rotate(&path, 4, 2).unwrap();
+            assert!(!path.exists());
+        }
+        assert_eq!(fs::read_to_string(rotated(1)).unwrap(), "89ab");
+        assert_eq!(fs::read_to_string(rotated(2)).unwrap(), "4567");
+        assert!(!rotated(3).exists());
+
+        // Under the size.
+        fs::write(&path, "01").unwrap();
+        rotate(&path, 4, 2).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "01");
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(Level::from_str("DEBUG").unwrap(), Level::Debug);
+        assert_eq!(Level::from_str(" warn").unwrap(), Level::Warn);
+        assert!(Level::from_str("verbose").is_err());
+        assert!(Level::Error < Level::Trace);
+    }
+}
@@ -1,16 +1,24 @@
 mod api;
+mod audit;
 mod batch;
+mod clipboard;
 mod cmd;
 mod config;
 mod errors;
 mod exec;
 mod filelock;
 mod git;
+mod interrupt;
+mod log;
 mod progress;
 mod repo;
+mod s3;
+mod scan;
+mod schedule;
 mod secret;
 mod table;
 mod term;
+mod timings;
 mod utils;
 mod workflow;
 
@@ -18,11 +26,13 @@ use std::env;
 use std::ffi::OsString;
 use std::io;
 use std::process;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use clap::error::ErrorKind as ArgsErrorKind;
 use clap::Parser;
 
+use crate::audit::AuditHistory;
 use crate::cmd::{App, Run};
 use crate::config::Config;
 use crate::errors::SilentExit;
@@ -31,20 +41,29 @@ use crate::errors::SilentExit;
 /// TODO: Hide these commands in help message, prefix these commands with an underscore.
 #[inline(always)]
 fn is_embed_command(action: &str) -> bool {
-    matches!(action, "init" | "complete" | "display")
+    matches!(
+        action,
+        "init" | "complete" | "display" | "prompt" | "jump" | "debug" | "ipc"
+    )
+}
+
+/// The daemon is usually run by systemd, and its jobs write the output to log
+/// files (see the `daemon` command), so they cannot require a terminal.
+fn is_daemon(args: &[OsString]) -> bool {
+    args.get(1).is_some_and(|arg| arg == "daemon")
+        || env::var_os("ROXIDE_DAEMON_JOB").is_some_and(|s| s == "true")
 }
 
 #[inline(always)]
 fn wrap_result<T>(result: Result<T>, message: &str, error_code: i32) -> T {
     match result {
         Ok(value) => value,
-        Err(err) => match err.downcast::<SilentExit>() {
-            Ok(SilentExit { code }) => process::exit(errors::CODE_SILENT_EXIT + code as i32),
-            Err(err) => {
+        Err(err) => {
+            if err.downcast_ref::<SilentExit>().is_none() {
                 error!("{} error: {:#}", message, err);
-                process::exit(error_code);
             }
-        },
+            process::exit(errors::exit_code(&err, error_code));
+        }
     }
 }
 
@@ -52,7 +71,8 @@ fn main() {
     let args: Vec<OsString> = env::args_os().collect();
     let allow_non_tty = args
         .get(1)
-        .is_some_and(|arg| arg.to_str().is_some_and(is_embed_command));
+        .is_some_and(|arg| arg.to_str().is_some_and(is_embed_command))
+        || is_daemon(&args);
 
     if !allow_non_tty && !termion::is_tty(&io::stderr()) {
         // We don't allow stderr been redirected, this will cause message been dismissed.
@@ -84,6 +104,33 @@ fn main() {
         }
     };
 
-    let cfg = wrap_result(Config::load(), "Load config", errors::CODE_LOAD_CONFIG);
-    wrap_result(app.run(&cfg), "Command", errors::CODE_COMMAND_FAILED);
+    let start = Instant::now();
+    if app.timings {
+        timings::enable();
+    }
+    let config_span = timings::span("config");
+    let cfg = wrap_result(
+        Config::load(app.profile.as_deref()),
+        "Load config",
+        errors::CODE_LOAD_CONFIG,
+    );
+    wrap_result(
+        log::init(&cfg, app.verbose),
+        "Init logger",
+        errors::CODE_LOAD_CONFIG,
+    );
+    drop(config_span);
+    debug!("Run command: {:?}", env::args().collect::<Vec<_>>());
+    exec::set_git_retry(cfg.git.retry, Duration::from_secs(cfg.git.retry_backoff));
+
+    let run_start = Instant::now();
+    let result = app.run(&cfg);
+    if let Some(name) = app.command.audit_name() {
+        let history = AuditHistory::new(&cfg);
+        if let Err(err) = history.record(&cfg, name, run_start.elapsed(), &result) {
+            warn!("Record audit history: {:#}", err);
+        }
+    }
+    timings::show(start.elapsed());
+    wrap_result(result, "Command", errors::CODE_COMMAND_FAILED);
 }
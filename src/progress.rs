@@ -1,10 +1,14 @@
+use std::cell::RefCell;
+use std::env;
 use std::fs::OpenOptions;
 use std::io::{self, Read, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
-use console::style;
+use console::{style, Term};
 use reqwest::blocking::Client;
 use reqwest::{Method, Url};
 
@@ -12,43 +16,105 @@ use crate::info;
 use crate::term;
 use crate::utils;
 
-struct ProgressWrapper {
+/// Return `true` if the progress should not be rendered. This happens when the
+/// stderr is not a terminal (e.g. redirected to a file), or user set env
+/// `ROXIDE_QUIET=true`. In quiet mode, only the final results are printed.
+pub fn is_quiet() -> bool {
+    if env::var_os("ROXIDE_QUIET").is_some_and(|s| s == "true") {
+        return true;
+    }
+    !Term::stderr().is_term()
+}
+
+fn speed(start: Instant, current: u64) -> Option<f64> {
+    let elapsed_seconds = start.elapsed().as_secs_f64();
+    if elapsed_seconds > 0.0 && current > 0 {
+        return Some(current as f64 / elapsed_seconds);
+    }
+    None
+}
+
+/// Estimate the remaining time according to the average speed since `start`,
+/// return `ETA {time}`. Return [`None`] if nothing is done yet or all are done.
+pub fn render_eta(start: Instant, current: u64, total: u64) -> Option<String> {
+    if current >= total {
+        return None;
+    }
+    let speed = speed(start, current)?;
+    let eta = (total - current) as f64 / speed;
+    Some(format!(
+        "ETA {}",
+        utils::format_elapsed(Duration::from_secs_f64(eta))
+    ))
+}
+
+/// The unit of the progress value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressUnit {
+    /// The value is bytes, shown in human-readable size, such as "1.2MiB".
+    Bytes,
+    /// The value is a plain count, such as objects or repositories.
+    Count,
+}
+
+impl ProgressUnit {
+    fn format(&self, value: u64) -> String {
+        match self {
+            Self::Bytes => utils::human_bytes(value),
+            Self::Count => format!("{value}"),
+        }
+    }
+}
+
+/// `Progress` renders a progress line in the terminal, in format:
+///
+/// * `{desc} {progress_bar} {current}/{total} - {speed}/s, ETA {eta}`
+///
+/// The line will adaptively change based on the current terminal width, the
+/// tail parts are omitted if there is no enough space.
+pub struct Progress {
     desc: String,
     done_desc: String,
     desc_size: usize,
 
+    unit: ProgressUnit,
+
     last_report: Instant,
 
-    current: usize,
-    total: usize,
+    current: u64,
+    total: u64,
 
     start: Instant,
 
+    quiet: bool,
     done: bool,
 }
 
-impl ProgressWrapper {
+impl Progress {
     const SPACE: &'static str = " ";
-    const SPACE_SIZE: usize = 1;
 
     const REPORT_INTERVAL: Duration = Duration::from_millis(200);
 
-    pub fn new(desc: String, done_desc: String, total: usize) -> ProgressWrapper {
+    pub fn new(desc: String, done_desc: String, total: u64, unit: ProgressUnit) -> Progress {
         let desc_size = console::measure_text_width(&desc);
-        let last_report = Instant::now();
+        let quiet = is_quiet();
 
-        let pw = ProgressWrapper {
+        let progress = Progress {
             desc,
             done_desc,
             desc_size,
-            last_report,
+            unit,
+            last_report: Instant::now(),
             current: 0,
             total,
             start: Instant::now(),
+            quiet,
             done: false,
         };
-        eprintln!("{}", pw.render());
-        pw
+        if !quiet {
+            eprintln!("{}", progress.render());
+        }
+        progress
     }
 
     fn render(&self) -> String {
@@ -58,70 +124,87 @@ impl ProgressWrapper {
         }
 
         let mut line = self.desc.clone();
-        if self.desc_size + Self::SPACE_SIZE > term_size || term::bar_size() == 0 {
+        if term::bar_size() == 0 {
             return line;
         }
-        line.push_str(Self::SPACE);
 
-        let bar = term::render_bar(self.current, self.total);
-        let bar_size = console::measure_text_width(&bar);
-        let line_size = console::measure_text_width(&line);
-        if line_size + bar_size > term_size {
-            return line;
+        let mut parts = Vec::with_capacity(3);
+        parts.push(term::render_bar(self.current as usize, self.total as usize));
+        parts.push(format!(
+            "{}/{}",
+            self.unit.format(self.current),
+            self.unit.format(self.total)
+        ));
+
+        if let Some(speed) = speed(self.start, self.current) {
+            let mut tail = format!("- {}/s", self.unit.format(speed as u64));
+            if let Some(eta) = render_eta(self.start, self.current, self.total) {
+                tail.push_str(", ");
+                tail.push_str(&eta);
+            }
+            parts.push(tail);
         }
-        line.push_str(&bar);
 
-        let line_size = console::measure_text_width(&line);
-        if line_size + Self::SPACE_SIZE > term_size {
-            return line;
+        for part in parts {
+            let line_size = console::measure_text_width(&line);
+            let part_size = console::measure_text_width(&part);
+            if line_size + Self::SPACE.len() + part_size > term_size {
+                return line;
+            }
+            line.push_str(Self::SPACE);
+            line.push_str(&part);
         }
-        line.push_str(Self::SPACE);
 
-        let info = utils::human_bytes(self.current as u64);
-        let info_size = console::measure_text_width(&info);
-        let line_size = console::measure_text_width(&line);
-        if line_size + info_size > term_size {
-            return line;
-        }
-        line.push_str(&info);
+        line
+    }
 
-        let line_size = console::measure_text_width(&line);
-        if line_size + Self::SPACE_SIZE > term_size {
-            return line;
-        }
-        let elapsed_seconds = self.start.elapsed().as_secs_f64();
-        if elapsed_seconds == 0.0 {
-            return line;
+    /// Add `size` to the current progress value.
+    pub fn inc(&mut self, size: u64) {
+        if self.done {
+            return;
         }
+        self.current += size;
 
-        line.push_str(Self::SPACE);
-
-        let speed = self.current as f64 / elapsed_seconds;
-        let speed = format!("- {}/s", utils::human_bytes(speed as u64));
-        let speed_size = console::measure_text_width(&speed);
-        let line_size = console::measure_text_width(&line);
-        if line_size + speed_size > term_size {
-            return line;
+        if self.current >= self.total {
+            self.finish();
+            return;
         }
-        line.push_str(&speed);
-
-        line
+        self.report();
     }
 
-    fn update_current(&mut self, size: usize) {
+    /// Set the current progress value and the total, this is useful when the
+    /// progress is reported by others, such as the output of a command.
+    pub fn set(&mut self, current: u64, total: u64) {
         if self.done {
             return;
         }
-        self.current += size;
+        self.total = total;
+        self.current = current;
 
         if self.current >= self.total {
-            self.done = true;
-            self.current = self.total;
-            term::cursor_up();
-            info!("{} {}", self.done_desc, style("done").green());
+            self.finish();
             return;
         }
+        self.report();
+    }
 
+    /// Mark the progress as done.
+    pub fn finish(&mut self) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        self.current = self.total;
+        if !self.quiet {
+            term::cursor_up();
+        }
+        info!("{} {}", self.done_desc, style("done").green());
+    }
+
+    fn report(&mut self) {
+        if self.quiet {
+            return;
+        }
         let now = Instant::now();
         let delta = now - self.last_report;
         if delta >= Self::REPORT_INTERVAL {
@@ -132,27 +215,233 @@ impl ProgressWrapper {
     }
 }
 
-impl Drop for ProgressWrapper {
+impl Drop for Progress {
     fn drop(&mut self) {
-        if self.done || self.current >= self.total {
+        if self.done {
+            return;
+        }
+        if self.current >= self.total {
+            // Nothing left, such as an empty total, this is not a failure.
+            self.finish();
             return;
         }
         // The progress didn't stop normally, mark it as failed.
-        term::cursor_up();
+        if !self.quiet {
+            term::cursor_up();
+        }
         info!("{} {}", self.done_desc, style("failed").red());
     }
 }
 
-struct ProgressWriter<W: Write> {
+/// The combined counter of the bytes and files processed by all the batch
+/// workers, such as the files scanned or the objects received by git.
+#[derive(Debug, Default)]
+pub struct Counter {
+    bytes: AtomicU64,
+    files: AtomicU64,
+}
+
+impl Counter {
+    pub fn add(&self, bytes: u64, files: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.files.fetch_add(files, Ordering::Relaxed);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.load(Ordering::Relaxed) == 0 && self.files.load(Ordering::Relaxed) == 0
+    }
+
+    /// Render the counter, in format `{files} files, {bytes} - {speed}/s`, the
+    /// speed is of the bytes.
+    pub fn render(&self, start: Instant) -> String {
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let files = self.files.load(Ordering::Relaxed);
+        let mut line = format!("{files} files, {}", utils::human_bytes(bytes));
+        if let Some(speed) = speed(start, bytes) {
+            line.push_str(&format!(" - {}/s", utils::human_bytes(speed as u64)));
+        }
+        line
+    }
+}
+
+/// The progress of the task running in a batch worker.
+#[derive(Debug, Default)]
+struct WorkerBar {
+    name: String,
+    /// The current phase, with its current and total value.
+    phase: Option<(String, u64, u64)>,
+    bytes: u64,
+    files: u64,
+}
+
+impl WorkerBar {
+    /// Return [`None`] if the task reports nothing, its name is already shown
+    /// in the tracing line.
+    fn render(&self) -> Option<String> {
+        if let Some((phase, current, total)) = self.phase.as_ref() {
+            return Some(format!(
+                "{}: {phase} {} {current}/{total}",
+                self.name,
+                term::render_bar(*current as usize, *total as usize)
+            ));
+        }
+        if self.bytes == 0 && self.files == 0 {
+            return None;
+        }
+        Some(format!(
+            "{}: {} files, {}",
+            self.name,
+            self.files,
+            utils::human_bytes(self.bytes)
+        ))
+    }
+}
+
+/// `MultiProgress` holds one bar per batch worker and the [`Counter`] combined
+/// from all of them. The tasks report to the bar of their worker through
+/// [`report`] and [`count`], and the batch tracker renders the bars under its
+/// tracing line, in format:
+///
+/// * `  {name}: {phase} {progress_bar} {current}/{total}`
+/// * `  {name}: {files} files, {bytes}`
+/// * `  Total: {files} files, {bytes} - {speed}/s`
+pub struct MultiProgress {
+    bars: Mutex<Vec<Option<WorkerBar>>>,
+    counter: Counter,
+    changed: AtomicBool,
+    start: Instant,
+}
+
+thread_local! {
+    /// The [`MultiProgress`] and the slot of the batch worker running in the
+    /// current thread, see [`MultiProgress::attach`].
+    static WORKER: RefCell<Option<(Arc<MultiProgress>, usize)>> = const { RefCell::new(None) };
+}
+
+impl MultiProgress {
+    const INDENT: &'static str = "  ";
+
+    pub fn new(workers: usize) -> Arc<MultiProgress> {
+        Arc::new(MultiProgress {
+            bars: Mutex::new((0..workers).map(|_| None).collect()),
+            counter: Counter::default(),
+            changed: AtomicBool::new(false),
+            start: Instant::now(),
+        })
+    }
+
+    /// Bind the current thread to the worker `slot`, so that the [`report`] and
+    /// [`count`] called in it go to the slot's bar.
+    pub fn attach(self: &Arc<Self>, slot: usize) {
+        WORKER.with(|worker| *worker.borrow_mut() = Some((Arc::clone(self), slot)));
+    }
+
+    /// Start a new bar for the task `name` in the worker `slot`.
+    pub fn start(&self, slot: usize, name: &str) {
+        self.update(slot, |bar| {
+            *bar = Some(WorkerBar {
+                name: name.to_string(),
+                ..Default::default()
+            })
+        });
+    }
+
+    /// Remove the bar of the worker `slot`, its task is done.
+    pub fn end(&self, slot: usize) {
+        self.update(slot, |bar| *bar = None);
+    }
+
+    fn update<F>(&self, slot: usize, f: F)
+    where
+        F: FnOnce(&mut Option<WorkerBar>),
+    {
+        let mut bars = self.bars.lock().unwrap();
+        if let Some(bar) = bars.get_mut(slot) {
+            f(bar);
+            self.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Return `true` if anything is reported since the last call, the caller
+    /// can skip rendering if nothing is changed.
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Render the bars of the workers which report their progress, and the
+    /// combined counter, one element per line. Each line is truncated to the
+    /// terminal width.
+    pub fn render(&self) -> Vec<String> {
+        let bars = self.bars.lock().unwrap();
+        let mut lines: Vec<_> = bars
+            .iter()
+            .filter_map(|bar| bar.as_ref().and_then(WorkerBar::render))
+            .collect();
+        drop(bars);
+        if !self.counter.is_empty() {
+            lines.push(format!("Total: {}", self.counter.render(self.start)));
+        }
+
+        let width = term::size();
+        lines
+            .into_iter()
+            .map(|line| {
+                let line = format!("{}{line}", Self::INDENT);
+                console::truncate_str(&line, width, "").into_owned()
+            })
+            .collect()
+    }
+}
+
+/// Report the `phase` progress of the current task to its worker bar, such as
+/// "Receiving objects". Return `false` if the current thread is not a batch
+/// worker, the caller should render the progress itself then.
+pub fn report(phase: &str, current: u64, total: u64) -> bool {
+    WORKER.with(|worker| match worker.borrow().as_ref() {
+        Some((multi, slot)) => {
+            multi.update(*slot, |bar| {
+                if let Some(bar) = bar.as_mut() {
+                    bar.phase = Some((phase.to_string(), current, total));
+                }
+            });
+            true
+        }
+        None => false,
+    })
+}
+
+/// Add the processed `bytes` and `files` to the worker bar of the current task
+/// and the combined counter. Do nothing if the current thread is not a batch
+/// worker.
+pub fn count(bytes: u64, files: u64) {
+    WORKER.with(|worker| {
+        if let Some((multi, slot)) = worker.borrow().as_ref() {
+            multi.counter.add(bytes, files);
+            multi.update(*slot, |bar| {
+                if let Some(bar) = bar.as_mut() {
+                    bar.bytes += bytes;
+                    bar.files += files;
+                }
+            });
+        }
+    })
+}
+
+/// Return `true` if the current thread is a batch worker, see [`MultiProgress`].
+pub fn in_worker() -> bool {
+    WORKER.with(|worker| worker.borrow().is_some())
+}
+
+pub struct ProgressWriter<W: Write> {
     upstream: W,
-    wrapper: ProgressWrapper,
+    progress: Progress,
 }
 
 impl<W: Write> ProgressWriter<W> {
-    pub fn new(desc: String, done_desc: String, total: usize, upstream: W) -> ProgressWriter<W> {
+    pub fn new(desc: String, done_desc: String, total: u64, upstream: W) -> ProgressWriter<W> {
         ProgressWriter {
             upstream,
-            wrapper: ProgressWrapper::new(desc, done_desc, total),
+            progress: Progress::new(desc, done_desc, total, ProgressUnit::Bytes),
         }
     }
 }
@@ -160,7 +449,7 @@ impl<W: Write> ProgressWriter<W> {
 impl<W: Write> Write for ProgressWriter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let size = self.upstream.write(buf)?;
-        self.wrapper.update_current(size);
+        self.progress.inc(size as u64);
 
         Ok(size)
     }
@@ -172,14 +461,14 @@ impl<W: Write> Write for ProgressWriter<W> {
 
 pub struct ProgressReader<R: Read> {
     upstream: R,
-    wrapper: ProgressWrapper,
+    progress: Progress,
 }
 
 impl<R: Read> ProgressReader<R> {
-    pub fn new(desc: String, done_desc: String, total: usize, upstream: R) -> ProgressReader<R> {
+    pub fn new(desc: String, done_desc: String, total: u64, upstream: R) -> ProgressReader<R> {
         ProgressReader {
             upstream,
-            wrapper: ProgressWrapper::new(desc, done_desc, total),
+            progress: Progress::new(desc, done_desc, total, ProgressUnit::Bytes),
         }
     }
 }
@@ -187,7 +476,7 @@ impl<R: Read> ProgressReader<R> {
 impl<R: Read> Read for ProgressReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let size = self.upstream.read(buf)?;
-        self.wrapper.update_current(size);
+        self.progress.inc(size as u64);
 
         Ok(size)
     }
@@ -221,8 +510,57 @@ pub fn download(name: &str, url: impl AsRef<str>, path: impl AsRef<str>) -> Resu
         .with_context(|| format!("Open file {}", path.display()))?;
     let desc = format!("Downloading {name}:");
 
-    let mut pw = ProgressWriter::new(desc, "Download".to_string(), total as usize, file);
+    let mut pw = ProgressWriter::new(desc, "Download".to_string(), total, file);
     resp.copy_to(&mut pw).context("download data")?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod progress_tests {
+    use std::thread;
+
+    use crate::progress::*;
+
+    #[test]
+    fn test_multi_progress() {
+        // Not in a batch worker, nothing is reported.
+        assert!(!report("Scanning", 1, 2));
+        count(10, 1);
+
+        let multi = MultiProgress::new(2);
+        multi.start(0, "idle");
+        let worker = Arc::clone(&multi);
+        thread::spawn(move || {
+            worker.attach(1);
+            worker.start(1, "github:fioncat/roxide");
+            assert!(in_worker());
+            count(2048, 2);
+            count(1024, 1);
+        })
+        .join()
+        .unwrap();
+        assert!(multi.take_changed());
+        assert!(!multi.take_changed());
+
+        let lines = multi.render();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "  github:fioncat/roxide: 3 files, 3KiB");
+        assert!(lines[1].starts_with("  Total: 3 files, 3KiB"));
+
+        let worker = Arc::clone(&multi);
+        thread::spawn(move || {
+            worker.attach(0);
+            assert!(report("Updating files", 5, 10));
+        })
+        .join()
+        .unwrap();
+        let lines = multi.render();
+        assert!(lines[0].starts_with("  idle: Updating files ["));
+        assert!(lines[0].ends_with(" 5/10"));
+
+        multi.end(0);
+        multi.end(1);
+        assert_eq!(multi.render().len(), 1);
+    }
+}
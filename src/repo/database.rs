@@ -2,7 +2,7 @@ use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use anyhow::{bail, Context, Result};
@@ -12,10 +12,12 @@ use serde::{Deserialize, Serialize};
 
 use crate::api::Provider;
 use crate::config::{Config, RemoteConfig};
+use crate::errors::ErrorKind;
 use crate::filelock::FileLock;
 use crate::repo::keywords::Keywords;
+use crate::repo::visits::Visits;
 use crate::repo::{NameLevel, Repo};
-use crate::{exec, info, term, utils};
+use crate::{audit, exec, info, term, timings, utils};
 
 pub fn get_path<S, R, O, N>(cfg: &Config, path: &Option<S>, remote: R, owner: O, name: N) -> PathBuf
 where
@@ -34,6 +36,25 @@ where
         .join(name.as_ref())
 }
 
+/// The reverse of [`get_path`], parse a path under the workspace to
+/// `(remote, owner, name)`. The owner can contain `/` (such as GitLab groups).
+/// Return [`None`] if the path is not a repository path under the workspace.
+pub fn parse_workspace_path(cfg: &Config, path: &Path) -> Option<(String, String, String)> {
+    let rel_path = path.strip_prefix(cfg.get_workspace_dir()).ok()?;
+    let parts: Vec<String> = rel_path
+        .components()
+        .map(|part| part.as_os_str().to_str().map(String::from))
+        .collect::<Option<_>>()?;
+    if parts.len() < 3 {
+        return None;
+    }
+
+    let remote = parts.first()?.clone();
+    let name = parts.last()?.clone();
+    let owner = parts[1..parts.len() - 1].join("/");
+    Some((remote, owner, name))
+}
+
 pub fn backup_replace(cfg: &Config, name: &str) -> Result<()> {
     let old_path = cfg.get_meta_dir().join("database");
 
@@ -70,7 +91,7 @@ impl DerefMut for RemoteBucket {
     }
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct OwnerBucket(HashMap<String, RepoBucket>);
 
 impl Deref for OwnerBucket {
@@ -152,10 +173,11 @@ impl Bucket {
         decoder.deserialize(data).context("decode repo data")
     }
 
-    /// Encode and write binary data to a file.
+    /// Encode and write binary data to a file. The file is replaced atomically,
+    /// so the readers without lock never see a partially written database.
     pub fn save(&self, path: &PathBuf) -> Result<()> {
         let data = self.encode()?;
-        utils::write_file(path, &data)
+        utils::write_file_atomic(path, &data)
     }
 
     /// Encode bucket to binary data.
@@ -179,10 +201,19 @@ pub struct Database<'a> {
     path: PathBuf,
 
     /// The Database can only be operated by one process at a time, so file lock
-    /// here are used here to provide protection.
-    lock: FileLock,
+    /// here are used here to provide protection. [`None`] means the database is
+    /// readonly, see [`Database::load_readonly`].
+    lock: Option<FileLock>,
 
     clean_labels: bool,
+
+    /// The repositories upserted or removed, reported to the audit history
+    /// when saving.
+    touched: Vec<String>,
+
+    /// The repositories visited (their `last_accessed` is advanced), recorded
+    /// to the visit history when saving.
+    visited: Vec<(String, u64)>,
 }
 
 impl Database<'_> {
@@ -191,7 +222,8 @@ impl Database<'_> {
     /// This function will attempt to acquire the file lock for the database file.
     /// If the database file does not exist, the load function will return an empty
     /// database, suitable for handling the initial condition.
-    pub fn load(cfg: &Config) -> Result<Database> {
+    pub fn load(cfg: &Config) -> Result<Database<'_>> {
+        let _span = timings::span("database");
         let lock = FileLock::acquire(cfg, "database")?;
         let path = cfg.get_meta_dir().join("database");
         let bucket = Bucket::read(&path)?;
@@ -200,12 +232,40 @@ impl Database<'_> {
             cfg,
             bucket,
             path,
-            lock,
+            lock: Some(lock),
             clean_labels: false,
+            touched: Vec::new(),
+            visited: Vec::new(),
         })
     }
 
-    pub fn get<R, O, N>(&self, remote: R, owner: O, name: N) -> Option<Repo>
+    /// Load the database without acquiring the file lock, so that it won't
+    /// contend with (or wait for) other roxide processes, such as the shell
+    /// prompt and completion. The database file is always replaced atomically,
+    /// so the readonly database is a consistent snapshot. It could not be
+    /// saved.
+    pub fn load_readonly(cfg: &Config) -> Result<Database<'_>> {
+        let _span = timings::span("database");
+        let path = cfg.get_meta_dir().join("database");
+        let bucket = Bucket::read(&path)?;
+
+        Ok(Database {
+            cfg,
+            bucket,
+            path,
+            lock: None,
+            clean_labels: false,
+            touched: Vec::new(),
+            visited: Vec::new(),
+        })
+    }
+
+    /// Return `true` if the database is loaded by [`Database::load_readonly`].
+    pub fn is_readonly(&self) -> bool {
+        self.lock.is_none()
+    }
+
+    pub fn get<R, O, N>(&self, remote: R, owner: O, name: N) -> Option<Repo<'_>>
     where
         R: AsRef<str>,
         O: AsRef<str>,
@@ -219,7 +279,7 @@ impl Database<'_> {
     }
 
     /// Similar to [`Database::get`], but returns error if the repository is not found.
-    pub fn must_get<R, O, N>(&self, remote: R, owner: O, name: N) -> Result<Repo>
+    pub fn must_get<R, O, N>(&self, remote: R, owner: O, name: N) -> Result<Repo<'_>>
     where
         R: AsRef<str>,
         O: AsRef<str>,
@@ -227,19 +287,19 @@ impl Database<'_> {
     {
         match self.get(remote.as_ref(), owner.as_ref(), name.as_ref()) {
             Some(repo) => Ok(repo),
-            None => bail!(
+            None => Err(ErrorKind::NotFound.error(format!(
                 "repo '{}:{}/{}' not found",
                 remote.as_ref(),
                 owner.as_ref(),
                 name.as_ref()
-            ),
+            ))),
         }
     }
 
     /// Locate a repository using a keyword. As long as the repository name contains
     /// the keyword, it is considered a successful match. The function prioritizes
     /// matches based on the repository's score.
-    pub fn get_fuzzy<R, K>(&self, remote: R, keyword: K) -> Option<Repo>
+    pub fn get_fuzzy<R, K>(&self, remote: R, keyword: K) -> Option<Repo<'_>>
     where
         R: AsRef<str>,
         K: AsRef<str>,
@@ -272,7 +332,7 @@ impl Database<'_> {
 
     /// Similar to [`Database::get_fuzzy`], but returns error if the repository
     /// is not found.
-    pub fn must_get_fuzzy<R, K>(&self, remote: R, keyword: K) -> Result<Repo>
+    pub fn must_get_fuzzy<R, K>(&self, remote: R, keyword: K) -> Result<Repo<'_>>
     where
         R: AsRef<str>,
         K: AsRef<str>,
@@ -281,16 +341,16 @@ impl Database<'_> {
             Some(repo) => Ok(repo),
             None => {
                 if remote.as_ref().is_empty() {
-                    bail!(
+                    return Err(ErrorKind::NotFound.error(format!(
                         "cannot find repo that contains keyword '{}'",
                         keyword.as_ref()
-                    );
+                    )));
                 }
-                bail!(
+                Err(ErrorKind::NotFound.error(format!(
                     "cannot find repo that contains keyword '{}' in remote '{}'",
                     keyword.as_ref(),
                     remote.as_ref()
-                );
+                )))
             }
         }
     }
@@ -305,7 +365,7 @@ impl Database<'_> {
     ///
     /// This design is intended to make the function more flexible, allowing it to
     /// achieve effects similar to `cd -` in Linux.
-    pub fn get_latest(&self, remote: impl AsRef<str>) -> Option<Repo> {
+    pub fn get_latest(&self, remote: impl AsRef<str>) -> Option<Repo<'_>> {
         let repos = self.scan(remote, "", |remote, owner, name, bucket| {
             let repo_path = get_path(self.cfg, &bucket.path, remote, owner, name);
             if repo_path.eq(self.cfg.get_current_dir()) {
@@ -327,7 +387,7 @@ impl Database<'_> {
     }
 
     /// Similar to `get_latest`, but returns error if the repository is not found.
-    pub fn must_get_latest<S>(&self, remote: S) -> Result<Repo>
+    pub fn must_get_latest<S>(&self, remote: S) -> Result<Repo<'_>>
     where
         S: AsRef<str>,
     {
@@ -343,11 +403,16 @@ impl Database<'_> {
     }
 
     /// Return the repository currently being accessed.
-    pub fn get_current(&self) -> Option<Repo> {
-        let current_dir = self.cfg.get_current_dir();
+    pub fn get_current(&self) -> Option<Repo<'_>> {
+        self.get_by_path(self.cfg.get_current_dir())
+    }
+
+    /// Return the repository that contains `path`, it can be a workspace
+    /// repository or an external one with custom path.
+    pub fn get_by_path(&self, path: &Path) -> Option<Repo<'_>> {
         let repos = self.scan("", "", |remote, owner, name, bucket| {
             let repo_path = get_path(self.cfg, &bucket.path, remote, owner, name);
-            if current_dir.starts_with(repo_path) {
+            if path.starts_with(repo_path) {
                 return None;
             }
             Some(false)
@@ -357,15 +422,15 @@ impl Database<'_> {
 
     /// Return the repository currently being accessed. If not within any
     /// repository, return an error.
-    pub fn must_get_current(&self) -> Result<Repo> {
+    pub fn must_get_current(&self) -> Result<Repo<'_>> {
         match self.get_current() {
             Some(repo) => Ok(repo),
-            None => bail!("you are not in a repo"),
+            None => Err(ErrorKind::NotFound.error("you are not in a repo")),
         }
     }
 
     /// List all repositories.
-    pub fn list_all(&self, labels: &Option<HashSet<String>>) -> Vec<Repo> {
+    pub fn list_all(&self, labels: &Option<HashSet<String>>) -> Vec<Repo<'_>> {
         let mut repos = self
             .scan("", "", |_remote, _owner, _name, bucket| {
                 self.filter_labels(bucket, labels)
@@ -380,7 +445,7 @@ impl Database<'_> {
         &self,
         remote: impl AsRef<str>,
         labels: &Option<HashSet<String>>,
-    ) -> Vec<Repo> {
+    ) -> Vec<Repo<'_>> {
         let mut repos = self
             .scan(remote, "", |_remote, _owner, _name, bucket| {
                 self.filter_labels(bucket, labels)
@@ -402,6 +467,7 @@ impl Database<'_> {
     }
 
     pub fn upsert(&mut self, repo: Repo) {
+        self.touched.push(repo.name_with_remote());
         let (remote, mut remote_bucket) = self
             .bucket
             .data
@@ -433,6 +499,10 @@ impl Database<'_> {
             self.clean_labels = true;
         }
 
+        if repo.last_accessed > repo_bucket.last_accessed {
+            self.visited
+                .push((repo.name_with_remote(), repo.last_accessed));
+        }
         repo_bucket.path = repo.path.map(|path| path.to_string());
         repo_bucket.last_accessed = repo.last_accessed;
         repo_bucket.accessed = repo.accessed;
@@ -443,6 +513,7 @@ impl Database<'_> {
     }
 
     pub fn remove(&mut self, repo: Repo) {
+        self.touched.push(repo.name_with_remote());
         if let Some((remote, mut remote_bucket)) =
             self.bucket.data.remove_entry(repo.remote.as_ref())
         {
@@ -470,7 +541,7 @@ impl Database<'_> {
         remote: R,
         owner: O,
         labels: &Option<HashSet<String>>,
-    ) -> Vec<Repo>
+    ) -> Vec<Repo<'_>>
     where
         R: AsRef<str>,
         O: AsRef<str>,
@@ -486,16 +557,32 @@ impl Database<'_> {
 
     /// Save the database changes to the disk file.
     pub fn save(mut self) -> Result<()> {
+        let _span = timings::span("database");
         self.do_clean_labels();
         let Database {
             bucket,
             clean_labels: _,
             path,
             lock,
-            cfg: _,
+            cfg,
+            touched,
+            visited,
         } = self;
+        if lock.is_none() {
+            bail!("the database is loaded as readonly, could not be saved");
+        }
 
         bucket.save(&path)?;
+        if !visited.is_empty() {
+            let mut visits = Visits::load(cfg)?;
+            for (name, time) in visited {
+                visits.record(name, time);
+            }
+            visits.save(cfg)?;
+        }
+        for name in touched {
+            audit::touch_repo(name);
+        }
         // Drop lock to release file lock after write done.
         drop(lock);
         Ok(())
@@ -513,6 +600,8 @@ impl Database<'_> {
             path: _,
             lock,
             cfg: _,
+            touched: _,
+            visited: _,
         } = self;
 
         drop(lock);
@@ -578,6 +667,13 @@ impl Database<'_> {
     #[inline]
     fn sort_repos(&self, repos: &mut [Repo]) {
         repos.sort_unstable_by(|a, b| {
+            // The pinned repositories come first, `None` is less than any `Some`.
+            let pin_a = a.pin_priority();
+            let pin_b = b.pin_priority();
+            if pin_a != pin_b {
+                return pin_b.cmp(&pin_a);
+            }
+
             let score_a = a.score(self.cfg);
             let score_b = b.score(self.cfg);
             if score_a != score_b {
@@ -595,7 +691,7 @@ impl Database<'_> {
     }
 
     #[inline]
-    fn scan<R, O, F>(&self, remote: R, owner: O, filter: F) -> Option<Vec<Repo>>
+    fn scan<R, O, F>(&self, remote: R, owner: O, filter: F) -> Option<Vec<Repo<'_>>>
     where
         R: AsRef<str>,
         O: AsRef<str>,
@@ -1010,7 +1106,7 @@ impl<'a, T: TerminalHelper, P: ProviderBuilder> Selector<'_, T, P> {
     pub fn must_one<'b>(&self, db: &'b Database) -> Result<Repo<'b>> {
         let (repo, exists) = self.one(db)?;
         if !exists {
-            bail!("could not find matched repo");
+            return Err(ErrorKind::NotFound.error("could not find matched repo"));
         }
         Ok(repo)
     }
@@ -1386,7 +1482,7 @@ impl<'a, T: TerminalHelper, P: ProviderBuilder> Selector<'_, T, P> {
             return Ok((repos.remove(0), true));
         }
 
-        repos.sort_unstable_by(|repo0, repo1| repo1.last_accessed.cmp(&repo0.last_accessed));
+        repos.sort_unstable_by_key(|repo| std::cmp::Reverse(repo.last_accessed));
         let mut repos = if repos.len() > n as usize {
             repos.into_iter().take(n as usize).collect()
         } else {
@@ -1460,12 +1556,12 @@ impl<'a, T: TerminalHelper, P: ProviderBuilder> Selector<'_, T, P> {
     {
         let repo = db.must_get_fuzzy(remote.as_ref(), keyword.as_ref())?;
 
-        if repo.name != keyword.as_ref() {
+        if repo.name != keyword.as_ref() && !db.is_readonly() {
             // If a fuzzy match hits a repository, record the fuzzy matching keywords in a
             // file for automatic keyword completion. If the fuzzy match word exactly matches
             // the repository name, no additional recording is needed, as the completion
             // logic will automatically include the repository name in the completion
-            // candidates.
+            // candidates. The readonly database never writes, including the keywords.
             let mut keywords = Keywords::load(db.cfg)?;
             keywords.upsert(remote.as_ref(), keyword.as_ref());
             keywords.save()?;
@@ -1654,7 +1750,7 @@ pub mod database_tests {
         }
     }
 
-    pub fn get_test_repos(cfg: &Config) -> Vec<Repo> {
+    pub fn get_test_repos(cfg: &Config) -> Vec<Repo<'_>> {
         vec![
             new_test_repo(cfg, "github", "fioncat", "csync", Some(vec!["sync", "pin"])),
             new_test_repo(cfg, "github", "fioncat", "fioncat", Some(vec!["sync"])),
@@ -1710,6 +1806,51 @@ pub mod database_tests {
         assert_eq!(repos, expect);
     }
 
+    #[test]
+    fn test_load_readonly() {
+        let cfg = config_tests::load_test_config("database/load_readonly");
+        let repos = get_test_repos(&cfg);
+        let mut expect = repos.clone();
+
+        let mut db = Database::load(&cfg).unwrap();
+        for repo in repos {
+            db.upsert(repo);
+        }
+        db.sort_repos(&mut expect);
+        db.save().unwrap();
+
+        // The readonly database does not contend with the lock holder.
+        let _db = Database::load(&cfg).unwrap();
+        let db = Database::load_readonly(&cfg).unwrap();
+        assert_eq!(db.list_all(&None), expect);
+        assert!(db.save().is_err());
+    }
+
+    #[test]
+    fn test_parse_workspace_path() {
+        let cfg = config_tests::load_test_config("database/parse_workspace_path");
+        let workspace = cfg.get_workspace_dir();
+
+        let cases = [
+            (
+                workspace.join("github/fioncat/roxide"),
+                Some(("github", "fioncat", "roxide")),
+            ),
+            (
+                workspace.join("gitlab/my-owner-01/sub/my-repo-01"),
+                Some(("gitlab", "my-owner-01/sub", "my-repo-01")),
+            ),
+            (workspace.join("github/fioncat"), None),
+            (PathBuf::from("/path/to/github/fioncat/roxide"), None),
+        ];
+        for (path, expect) in cases {
+            let expect = expect.map(|(remote, owner, name)| {
+                (remote.to_string(), owner.to_string(), name.to_string())
+            });
+            assert_eq!(parse_workspace_path(&cfg, &path), expect);
+        }
+    }
+
     #[test]
     fn test_get() {
         let cfg = config_tests::load_test_config("database/get");
@@ -1960,6 +2101,58 @@ pub mod database_tests {
         // The mark label should be removed
         assert_eq!(labels, hashset!["sync", "pin"]);
     }
+
+    #[test]
+    fn test_pin_sort() {
+        let cfg = config_tests::load_test_config("database/pin_sort");
+
+        let mut top = new_test_repo(&cfg, "github", "fioncat", "roxide", None);
+        top.set_pin_priority(Some(5));
+        assert_eq!(top.pin_priority(), Some(5));
+
+        let pinned = new_test_repo(
+            &cfg,
+            "github",
+            "fioncat",
+            "csync",
+            Some(vec!["sync", "pin"]),
+        );
+        assert_eq!(pinned.pin_priority(), Some(0));
+
+        let mut hot = new_test_repo(&cfg, "github", "fioncat", "dotfiles", None);
+        hot.accessed = 100;
+        hot.last_accessed = cfg.now();
+
+        let db = Database::load(&cfg).unwrap();
+        let mut repos = vec![hot.clone(), pinned.clone(), top.clone()];
+        db.sort_repos(&mut repos);
+        assert_eq!(repos, vec![top.clone(), pinned, hot]);
+
+        top.set_pin_priority(None);
+        assert_eq!(top.pin_priority(), None);
+        assert_eq!(top.labels, None);
+    }
+
+    #[test]
+    fn test_protected() {
+        let cfg = config_tests::load_test_config("database/protected");
+
+        let repo = new_test_repo(&cfg, "github", "fioncat", "roxide", None);
+        assert!(!repo.is_protected());
+
+        let repo = new_test_repo(&cfg, "github", "fioncat", "roxide", Some(vec!["protected"]));
+        assert!(repo.is_protected());
+
+        let mut repo = new_test_repo(&cfg, "github", "kubernetes", "kubernetes", None);
+        let mut remote_cfg = repo.remote_cfg.into_owned();
+        let owner_cfg = remote_cfg.owners.get_mut("kubernetes").unwrap();
+        owner_cfg.protect = vec![String::from("kube*")];
+        repo.remote_cfg = Cow::Owned(remote_cfg);
+        assert!(repo.is_protected());
+
+        repo.name = Cow::Borrowed("csync");
+        assert!(!repo.is_protected());
+    }
 }
 
 #[cfg(test)]
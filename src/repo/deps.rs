@@ -0,0 +1,252 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::Config;
+use crate::filelock::FileLock;
+use crate::repo::database::{self, Database};
+use crate::repo::Repo;
+use crate::utils;
+
+/// The dependencies between local repositories (for example, a service depends
+/// on a shared library), stored in `{metadir}/deps`.
+///
+/// Repositories are identified by their full name `{remote}:{owner}/{name}`, see
+/// [`Repo::name_with_remote`].
+pub struct Dependencies {
+    data: HashMap<String, BTreeSet<String>>,
+
+    path: PathBuf,
+
+    /// [`None`] means the dependencies are readonly, see
+    /// [`Dependencies::load_readonly`].
+    lock: Option<FileLock>,
+}
+
+impl Dependencies {
+    pub fn load(cfg: &Config) -> Result<Dependencies> {
+        let lock = FileLock::acquire(cfg, "deps")?;
+        Self::read(cfg, Some(lock))
+    }
+
+    /// Load the dependencies without acquiring the file lock, for the commands
+    /// only reading them (such as showing the graph), so that they won't
+    /// contend with other roxide processes. The file is always replaced
+    /// atomically, so this is a consistent snapshot. It could not be saved.
+    pub fn load_readonly(cfg: &Config) -> Result<Dependencies> {
+        Self::read(cfg, None)
+    }
+
+    fn read(cfg: &Config, lock: Option<FileLock>) -> Result<Dependencies> {
+        let path = cfg.get_meta_dir().join("deps");
+        let data = match fs::read(&path) {
+            Ok(data) => bincode::deserialize(&data).context("decode deps data")?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read deps file '{}'", path.display()))
+            }
+        };
+
+        Ok(Dependencies { data, path, lock })
+    }
+
+    /// Declare that `repo` depends on `dep`.
+    pub fn add(&mut self, repo: &Repo, dep: &Repo) -> Result<()> {
+        let name = repo.name_with_remote();
+        let dep_name = dep.name_with_remote();
+        if name == dep_name {
+            bail!("repo '{name}' could not depend on itself");
+        }
+        if self.closure(&dep_name).contains(&name) {
+            bail!("circular dependency, '{dep_name}' already depends on '{name}'");
+        }
+
+        self.data.entry(name).or_default().insert(dep_name);
+        Ok(())
+    }
+
+    /// Remove the dependency, return `false` if it does not exist.
+    pub fn remove(&mut self, repo: &Repo, dep_name: &str) -> bool {
+        let name = repo.name_with_remote();
+        let deps = match self.data.get_mut(&name) {
+            Some(deps) => deps,
+            None => return false,
+        };
+        let removed = deps.remove(dep_name);
+        if deps.is_empty() {
+            self.data.remove(&name);
+        }
+        removed
+    }
+
+    /// Rename the repo in the dependencies, both as a dependent and as a
+    /// dependency, used when the repo is moved.
+    pub fn rename(&mut self, name: &str, new_name: &str) {
+        if let Some(deps) = self.data.remove(name) {
+            self.data.insert(new_name.to_string(), deps);
+        }
+        for deps in self.data.values_mut() {
+            if deps.remove(name) {
+                deps.insert(new_name.to_string());
+            }
+        }
+    }
+
+    /// Return the direct dependencies of the repo.
+    pub fn get(&self, name: &str) -> Vec<String> {
+        match self.data.get(name) {
+            Some(deps) => deps.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Return all the dependencies (including the indirect ones) of the repo,
+    /// the nearer dependencies come first.
+    pub fn closure(&self, name: &str) -> Vec<String> {
+        let mut visited: HashSet<&str> = HashSet::new();
+        visited.insert(name);
+
+        let mut result = Vec::new();
+        let mut queue: VecDeque<&str> = VecDeque::new();
+        queue.push_back(name);
+        while let Some(current) = queue.pop_front() {
+            let deps = match self.data.get(current) {
+                Some(deps) => deps,
+                None => continue,
+            };
+            for dep in deps.iter() {
+                if visited.insert(dep.as_str()) {
+                    result.push(dep.clone());
+                    queue.push_back(dep.as_str());
+                }
+            }
+        }
+        result
+    }
+
+    /// Return all the dependency edges, sorted by repo name.
+    pub fn edges(&self) -> Vec<(&str, Vec<&str>)> {
+        let mut edges: Vec<_> = self
+            .data
+            .iter()
+            .map(|(name, deps)| (name.as_str(), deps.iter().map(|s| s.as_str()).collect()))
+            .collect();
+        edges.sort_unstable_by_key(|(name, _)| *name);
+        edges
+    }
+
+    /// Append the dependency closures of `repos` to it. The dependencies that
+    /// are not in the database will be ignored.
+    pub fn extend_repos<'a>(&self, db: &'a Database, mut repos: Vec<Repo<'a>>) -> Vec<Repo<'a>> {
+        let mut names: HashSet<String> = repos.iter().map(|repo| repo.name_with_remote()).collect();
+        let mut idx = 0;
+        while idx < repos.len() {
+            for dep in self.closure(&repos[idx].name_with_remote()) {
+                if !names.insert(dep.clone()) {
+                    continue;
+                }
+                let (remote, owner, name) = match parse_name(&dep) {
+                    Some(fields) => fields,
+                    None => continue,
+                };
+                if let Some(repo) = db.get(remote, owner, name) {
+                    repos.push(repo);
+                }
+            }
+            idx += 1;
+        }
+        repos
+    }
+
+    pub fn save(self) -> Result<()> {
+        if self.lock.is_none() {
+            bail!("the dependencies are loaded as readonly, could not be saved");
+        }
+        let data = bincode::serialize(&self.data).context("encode deps data")?;
+        utils::write_file_atomic(&self.path, &data)
+    }
+}
+
+/// Parse the full repo name `{remote}:{owner}/{name}` to `(remote, owner, name)`.
+pub fn parse_name(full_name: &str) -> Option<(String, String, String)> {
+    let (remote, query) = full_name.split_once(':')?;
+    let (owner, name) = database::parse_owner(query);
+    if remote.is_empty() || owner.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some((remote.to_string(), owner, name))
+}
+
+#[cfg(test)]
+mod deps_tests {
+    use crate::config::config_tests;
+    use crate::repo::database::database_tests;
+    use crate::repo::deps::*;
+
+    #[test]
+    fn test_closure() {
+        let cfg = config_tests::load_test_config("deps/closure");
+        let _ = fs::remove_file(cfg.get_meta_dir().join("deps"));
+        let repos = database_tests::get_test_repos(&cfg);
+        let find = |name: &str| repos.iter().find(|repo| repo.name == name).unwrap();
+
+        let mut deps = Dependencies::load(&cfg).unwrap();
+        deps.add(find("kubernetes"), find("kubelet")).unwrap();
+        deps.add(find("kubernetes"), find("kubectl")).unwrap();
+        deps.add(find("kubelet"), find("kube-proxy")).unwrap();
+        deps.add(find("kube-proxy"), find("csync")).unwrap();
+
+        assert!(deps.add(find("csync"), find("kubernetes")).is_err());
+        assert!(deps.add(find("csync"), find("csync")).is_err());
+
+        deps.save().unwrap();
+        let mut deps = Dependencies::load(&cfg).unwrap();
+
+        // The readonly load does not contend with the lock holder.
+        let readonly = Dependencies::load_readonly(&cfg).unwrap();
+        assert_eq!(
+            readonly.get("github:kubernetes/kubelet"),
+            vec!["github:kubernetes/kube-proxy"]
+        );
+        assert!(readonly.save().is_err());
+
+        assert_eq!(
+            deps.closure("github:kubernetes/kubernetes"),
+            vec![
+                "github:kubernetes/kubectl",
+                "github:kubernetes/kubelet",
+                "github:kubernetes/kube-proxy",
+                "github:fioncat/csync",
+            ]
+        );
+
+        assert!(deps.remove(find("kubelet"), "github:kubernetes/kube-proxy"));
+        assert!(!deps.remove(find("kubelet"), "github:kubernetes/kube-proxy"));
+        assert_eq!(
+            deps.closure("github:kubernetes/kubernetes"),
+            vec!["github:kubernetes/kubectl", "github:kubernetes/kubelet"]
+        );
+
+        deps.rename("github:kubernetes/kubectl", "github:kubernetes/kubectl2");
+        assert_eq!(
+            deps.get("github:kubernetes/kubernetes"),
+            vec!["github:kubernetes/kubectl2", "github:kubernetes/kubelet"]
+        );
+        deps.rename("github:kubernetes/kubernetes", "github:k8s/kubernetes");
+        assert!(deps.get("github:kubernetes/kubernetes").is_empty());
+        assert_eq!(deps.get("github:k8s/kubernetes").len(), 2);
+
+        assert_eq!(
+            parse_name("gitlab:my-owner-01/sub/my-repo-01"),
+            Some((
+                String::from("gitlab"),
+                String::from("my-owner-01/sub"),
+                String::from("my-repo-01")
+            ))
+        );
+        assert_eq!(parse_name("invalid"), None);
+    }
+}
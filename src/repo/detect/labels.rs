@@ -4,35 +4,36 @@ use std::fs;
 
 use anyhow::{Context, Result};
 
+use crate::config::local::LocalConfig;
 use crate::config::Config;
 use crate::repo::Repo;
 
-use super::{Language, Module};
+use super::{license, Language, Module};
 
 pub struct DetectLabels<'a> {
     languages: Vec<Language>,
-    language_labels: HashSet<&'static str>,
+    language_labels: HashSet<Cow<'static, str>>,
 
     modules: HashMap<&'static str, Module>,
 
-    builtin_labels: HashSet<&'static str>,
+    builtin_labels: HashSet<Cow<'static, str>>,
 
     cfg: &'a Config,
 }
 
 impl<'a> DetectLabels<'a> {
     pub fn new(cfg: &'a Config) -> Self {
-        let languages = super::builtin_languages();
+        let languages = super::languages(cfg);
         let modules = super::builtin_modules();
 
         let mut builtin_labels = HashSet::with_capacity(languages.len() + modules.len());
         let mut language_labels = HashSet::with_capacity(languages.len());
         for lang in languages.iter() {
-            builtin_labels.insert(lang.label);
-            language_labels.insert(lang.label);
+            builtin_labels.insert(lang.label.clone());
+            language_labels.insert(lang.label.clone());
         }
         for label in modules.keys() {
-            builtin_labels.insert(*label);
+            builtin_labels.insert(Cow::Borrowed(*label));
         }
 
         Self {
@@ -69,7 +70,15 @@ impl<'a> DetectLabels<'a> {
             root_files.insert(name);
         }
 
-        let groups = super::detect_languages(&self.cfg.detect_ignores, &path, &self.languages)?;
+        for id in license::detect_licenses(&path, &root_files)? {
+            labels.insert(Cow::Owned(format!("{}{id}", license::LICENSE_LABEL_PREFIX)));
+        }
+        if !license::has_readme(&root_files) {
+            labels.insert(Cow::Borrowed(license::NO_README_LABEL));
+        }
+
+        let ignores = LocalConfig::load(&path)?.detect_ignores(&self.cfg.detect_ignores)?;
+        let groups = super::detect_languages(&ignores, &path, &self.languages)?;
         let group = groups
             .into_iter()
             .max_by(|a, b| a.files.len().cmp(&b.files.len()));
@@ -125,7 +134,7 @@ impl<'a> DetectLabels<'a> {
 
         if !found_module {
             // No module, we use the language label as the detect target.
-            labels.insert(Cow::Borrowed(group.language.label));
+            labels.insert(group.language.label);
         }
 
         if labels.is_empty() {
@@ -149,7 +158,9 @@ impl<'a> DetectLabels<'a> {
     fn _clear<'b>(&self, labels: HashSet<Cow<'b, str>>) -> HashSet<Cow<'b, str>> {
         labels
             .into_iter()
-            .filter(|label| !self.builtin_labels.contains(label.as_ref()))
+            .filter(|label| {
+                !self.builtin_labels.contains(label.as_ref()) && !license::is_detect_label(label)
+            })
             .collect()
     }
 
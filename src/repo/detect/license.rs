@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::repo::Repo;
+
+/// The prefix of the license labels, followed by the SPDX id, such as
+/// `license:MIT`. A repository with multiple license files (such as the dual
+/// license `LICENSE-MIT` and `LICENSE-APACHE`) has multiple license labels.
+pub const LICENSE_LABEL_PREFIX: &str = "license:";
+
+/// The label for the repositories without README in root directory.
+pub const NO_README_LABEL: &str = "no-readme";
+
+/// The SPDX id for the license file that could not be recognized.
+const OTHER_LICENSE: &str = "Other";
+
+/// The well-known licenses and their key phrases (normalized: lowercase and
+/// single spaces), matched in order. The more specific ones go first, such as
+/// LGPL before GPL.
+const LICENSES: [(&str, &[&str]); 12] = [
+    (
+        "AGPL-3.0",
+        &["gnu affero general public license", "version 3"],
+    ),
+    (
+        "LGPL-3.0",
+        &["gnu lesser general public license", "version 3"],
+    ),
+    (
+        "LGPL-2.1",
+        &["gnu lesser general public license", "version 2.1"],
+    ),
+    ("GPL-3.0", &["gnu general public license", "version 3"]),
+    ("GPL-2.0", &["gnu general public license", "version 2"]),
+    ("Apache-2.0", &["apache license", "version 2.0"]),
+    ("MPL-2.0", &["mozilla public license", "2.0"]),
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["redistribution and use in source and binary forms"],
+    ),
+    (
+        "ISC",
+        &["permission to use, copy, modify, and/or distribute this software for any purpose"],
+    ),
+    (
+        "MIT",
+        &["permission is hereby granted, free of charge, to any person"],
+    ),
+    (
+        "Unlicense",
+        &["this is free and unencumbered software released into the public domain"],
+    ),
+];
+
+/// Detect the licenses (SPDX ids) from the license files in repository root,
+/// such as `LICENSE`, `LICENSE-MIT`, `COPYING`. The sorted ids are returned.
+pub fn detect_licenses(path: &Path, root_files: &HashSet<String>) -> Result<Vec<String>> {
+    let mut licenses = Vec::new();
+    for name in root_files.iter() {
+        let upper = name.to_uppercase();
+        if !["LICENSE", "LICENCE", "COPYING"]
+            .iter()
+            .any(|prefix| upper.starts_with(prefix))
+        {
+            continue;
+        }
+        let path = path.join(name);
+        let data =
+            fs::read(&path).with_context(|| format!("read license file '{}'", path.display()))?;
+        let license = match_license(&String::from_utf8_lossy(&data));
+        if !licenses.contains(&license) {
+            licenses.push(license);
+        }
+    }
+    licenses.sort_unstable();
+    Ok(licenses)
+}
+
+pub fn has_readme(root_files: &HashSet<String>) -> bool {
+    root_files
+        .iter()
+        .any(|name| name.to_uppercase().starts_with("README"))
+}
+
+/// Return the license ids in the labels of the repository.
+pub fn get_licenses<'a>(repo: &'a Repo) -> Vec<&'a str> {
+    let mut licenses: Vec<_> = match repo.labels.as_ref() {
+        Some(labels) => labels
+            .iter()
+            .filter_map(|label| label.strip_prefix(LICENSE_LABEL_PREFIX))
+            .collect(),
+        None => vec![],
+    };
+    licenses.sort_unstable();
+    licenses
+}
+
+#[inline]
+pub fn is_detect_label(label: &str) -> bool {
+    label.starts_with(LICENSE_LABEL_PREFIX) || label == NO_README_LABEL
+}
+
+fn match_license(text: &str) -> String {
+    // The license files generated by tools often have the SPDX id.
+    for line in text.lines().take(10) {
+        if let Some((_, id)) = line.split_once("SPDX-License-Identifier:") {
+            let id = id.trim();
+            if !id.is_empty() && !id.contains(char::is_whitespace) {
+                return id.to_string();
+            }
+        }
+    }
+
+    let text = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    for (id, phrases) in LICENSES.iter() {
+        if phrases.iter().all(|phrase| text.contains(phrase)) {
+            return id.to_string();
+        }
+    }
+    String::from(OTHER_LICENSE)
+}
+
+#[cfg(test)]
+mod license_tests {
+    use crate::repo::detect::license::*;
+
+    #[test]
+    fn test_match_license() {
+        let cases = [
+            (
+                "MIT License\n\nCopyright (c) 2023 fioncat\n\nPermission is hereby granted, free\nof charge, to any person obtaining a copy",
+                "MIT",
+            ),
+            (
+                "                                 Apache License\n                           Version 2.0, January 2004",
+                "Apache-2.0",
+            ),
+            (
+                "GNU LESSER GENERAL PUBLIC LICENSE\n Version 3, 29 June 2007",
+                "LGPL-3.0",
+            ),
+            (
+                "GNU GENERAL PUBLIC LICENSE\n Version 2, June 1991",
+                "GPL-2.0",
+            ),
+            (
+                "Redistribution and use in source and binary forms, with or without\nmodification. Neither the name of the copyright holder",
+                "BSD-3-Clause",
+            ),
+            ("// SPDX-License-Identifier: BSL-1.0\n", "BSL-1.0"),
+            ("All rights reserved.", "Other"),
+        ];
+        for (text, expect) in cases {
+            assert_eq!(match_license(text), expect);
+        }
+    }
+}
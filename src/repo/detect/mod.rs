@@ -1,134 +1,197 @@
 pub mod labels;
+pub mod license;
 pub mod stats;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use glob::Pattern as GlobPattern;
 
+use crate::config::Config;
 use crate::git;
 
 #[derive(Debug, Clone)]
 pub(super) struct Language {
-    name: &'static str,
-    label: &'static str,
+    name: Cow<'static, str>,
+    label: Cow<'static, str>,
 
-    extensions: Vec<&'static str>,
+    extensions: Vec<Cow<'static, str>>,
+
+    /// The interpreters in the shebang line (without version suffix, such as
+    /// `python3`), used to detect the scripts without extension.
+    interpreters: Vec<Cow<'static, str>>,
 
     // TODO: Support multi-line comments.
-    comments: Vec<&'static str>,
+    comments: Vec<Cow<'static, str>>,
+}
+
+/// Return the user-defined languages in config, followed by the builtin ones.
+pub(super) fn languages(cfg: &Config) -> Vec<Language> {
+    fn owned_all(items: &[String]) -> Vec<Cow<'static, str>> {
+        items.iter().map(|item| Cow::Owned(item.clone())).collect()
+    }
+
+    let mut languages = Vec::with_capacity(cfg.languages.len());
+    for lang in cfg.languages.iter() {
+        let mut comments = owned_all(&lang.line_comment);
+        if let Some((start, _)) = lang.block_comment.as_ref() {
+            comments.push(Cow::Owned(start.clone()));
+        }
+        languages.push(Language {
+            name: Cow::Owned(lang.name.clone()),
+            label: Cow::Owned(lang.get_label()),
+            extensions: owned_all(&lang.extensions),
+            interpreters: owned_all(&lang.interpreters),
+            comments,
+        });
+    }
+    languages.extend(builtin_languages());
+    languages
 }
 
-pub(super) fn builtin_languages() -> Vec<Language> {
+/// Convert the builtin strings to [`Language`] fields.
+fn cows(items: &[&'static str]) -> Vec<Cow<'static, str>> {
+    items.iter().map(|item| Cow::Borrowed(*item)).collect()
+}
+
+fn builtin_languages() -> Vec<Language> {
     vec![
         Language {
-            name: "C",
-            label: "c",
-            extensions: vec!["c", "h"],
-            comments: vec!["//", "/*"],
+            name: Cow::Borrowed("C"),
+            label: Cow::Borrowed("c"),
+            extensions: cows(&["c", "h"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//", "/*"]),
         },
         Language {
-            name: "C++",
-            label: "cpp",
-            extensions: vec!["cpp", "cc", "C", "hpp"],
-            comments: vec!["//", "/*"],
+            name: Cow::Borrowed("C++"),
+            label: Cow::Borrowed("cpp"),
+            extensions: cows(&["cpp", "cc", "C", "hpp"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//", "/*"]),
         },
         Language {
-            name: "C#",
-            label: "csharp",
-            extensions: vec!["cs"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("C#"),
+            label: Cow::Borrowed("csharp"),
+            extensions: cows(&["cs"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//"]),
         },
         Language {
-            name: "Rust",
-            label: "rust",
-            extensions: vec!["rs"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("Rust"),
+            label: Cow::Borrowed("rust"),
+            extensions: cows(&["rs"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//"]),
         },
         Language {
-            name: "Go",
-            label: "go",
-            extensions: vec!["go"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("Go"),
+            label: Cow::Borrowed("go"),
+            extensions: cows(&["go"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//"]),
         },
         Language {
-            name: "Java",
-            label: "java",
-            extensions: vec!["java"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("Java"),
+            label: Cow::Borrowed("java"),
+            extensions: cows(&["java"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//"]),
         },
         Language {
-            name: "JavaScript",
-            label: "js",
-            extensions: vec!["js"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("JavaScript"),
+            label: Cow::Borrowed("js"),
+            extensions: cows(&["js"]),
+            interpreters: cows(&["node", "nodejs"]),
+            comments: cows(&["//"]),
         },
         Language {
-            name: "TypeScript",
-            label: "ts",
-            extensions: vec!["ts"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("TypeScript"),
+            label: Cow::Borrowed("ts"),
+            extensions: cows(&["ts"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//"]),
         },
         Language {
-            name: "Lua",
-            label: "lua",
-            extensions: vec!["lua"],
-            comments: vec!["--"],
+            name: Cow::Borrowed("Lua"),
+            label: Cow::Borrowed("lua"),
+            extensions: cows(&["lua"]),
+            interpreters: cows(&["lua"]),
+            comments: cows(&["--"]),
         },
         Language {
-            name: "Python",
-            label: "python",
-            extensions: vec!["py"],
-            comments: vec!["#"],
+            name: Cow::Borrowed("Python"),
+            label: Cow::Borrowed("python"),
+            extensions: cows(&["py"]),
+            interpreters: cows(&["python"]),
+            comments: cows(&["#"]),
         },
         Language {
-            name: "HTML",
-            label: "html",
-            extensions: vec!["html"],
-            comments: vec!["<!--"],
+            name: Cow::Borrowed("Shell"),
+            label: Cow::Borrowed("shell"),
+            extensions: cows(&["sh", "bash", "zsh"]),
+            interpreters: cows(&["sh", "bash", "zsh", "dash", "ksh"]),
+            comments: cows(&["#"]),
         },
         Language {
-            name: "CSS",
-            label: "css",
-            extensions: vec!["css"],
-            comments: vec!["/*"],
+            name: Cow::Borrowed("HTML"),
+            label: Cow::Borrowed("html"),
+            extensions: cows(&["html"]),
+            interpreters: cows(&[]),
+            comments: cows(&["<!--"]),
         },
         Language {
-            name: "PHP",
-            label: "php",
-            extensions: vec!["php"],
-            comments: vec!["//", "#", "/*"],
+            name: Cow::Borrowed("CSS"),
+            label: Cow::Borrowed("css"),
+            extensions: cows(&["css"]),
+            interpreters: cows(&[]),
+            comments: cows(&["/*"]),
         },
         Language {
-            name: "R",
-            label: "r",
-            extensions: vec!["R"],
-            comments: vec!["#"],
+            name: Cow::Borrowed("PHP"),
+            label: Cow::Borrowed("php"),
+            extensions: cows(&["php"]),
+            interpreters: cows(&["php"]),
+            comments: cows(&["//", "#", "/*"]),
         },
         Language {
-            name: "Ruby",
-            label: "ruby",
-            extensions: vec!["rb"],
-            comments: vec!["#"],
+            name: Cow::Borrowed("R"),
+            label: Cow::Borrowed("r"),
+            extensions: cows(&["R"]),
+            interpreters: cows(&["Rscript"]),
+            comments: cows(&["#"]),
         },
         Language {
-            name: "Perl",
-            label: "perl",
-            extensions: vec!["pl"],
-            comments: vec!["="],
+            name: Cow::Borrowed("Ruby"),
+            label: Cow::Borrowed("ruby"),
+            extensions: cows(&["rb"]),
+            interpreters: cows(&["ruby"]),
+            comments: cows(&["#"]),
         },
         Language {
-            name: "Kotlin",
-            label: "kotlin",
-            extensions: vec!["kt"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("Perl"),
+            label: Cow::Borrowed("perl"),
+            extensions: cows(&["pl"]),
+            interpreters: cows(&["perl"]),
+            comments: cows(&["="]),
         },
         Language {
-            name: "Scala",
-            label: "scala",
-            extensions: vec!["scala"],
-            comments: vec!["//"],
+            name: Cow::Borrowed("Kotlin"),
+            label: Cow::Borrowed("kotlin"),
+            extensions: cows(&["kt"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//"]),
+        },
+        Language {
+            name: Cow::Borrowed("Scala"),
+            label: Cow::Borrowed("scala"),
+            extensions: cows(&["scala"]),
+            interpreters: cows(&[]),
+            comments: cows(&["//"]),
         },
     ]
 }
@@ -191,6 +254,31 @@ pub(super) fn builtin_modules() -> HashMap<&'static str, Module> {
     ]
 }
 
+/// Return the modules (such as `cargo`, `nodejs`) whose files or dirs are in
+/// the root of the repository, sorted. Unlike the labels, the languages are not
+/// detected, so this is cheap enough to run for many repositories.
+pub fn detect_root_modules(path: &Path) -> Vec<&'static str> {
+    let mut modules: Vec<_> = builtin_modules()
+        .into_iter()
+        .filter(|(_, module)| {
+            let has_file = module
+                .files
+                .iter()
+                .flatten()
+                .any(|file| path.join(file).is_file());
+            let has_dir = module
+                .dirs
+                .iter()
+                .flatten()
+                .any(|dir| path.join(dir).is_dir());
+            has_file || has_dir
+        })
+        .map(|(label, _)| label)
+        .collect();
+    modules.sort_unstable();
+    modules
+}
+
 pub(super) struct LanguageGroup {
     pub language: Language,
     pub files: Vec<String>,
@@ -205,33 +293,22 @@ pub(super) fn detect_languages(
     let mut groups_map: HashMap<&str, LanguageGroup> = HashMap::with_capacity(languages.len());
 
     for file in files {
-        let path = PathBuf::from(&file);
-        let extension = match path.extension() {
+        let file_path = PathBuf::from(&file);
+        let language = match file_path.extension() {
             Some(extension) => match extension.to_str() {
-                Some(extension) => extension,
+                Some(extension) => languages
+                    .iter()
+                    .find(|language| language.extensions.iter().any(|ext| ext == extension)),
                 None => continue,
             },
-            None => continue,
+            None => detect_script(&path.join(&file_path), languages),
         };
-
-        for language in languages {
-            let mut is_match = false;
-            for lang_extension in language.extensions.iter() {
-                if *lang_extension == extension {
-                    is_match = true;
-                    break;
-                }
-            }
-
-            if !is_match {
-                continue;
-            }
-
-            match groups_map.get_mut(language.label) {
+        if let Some(language) = language {
+            match groups_map.get_mut(language.label.as_ref()) {
                 Some(group) => group.files.push(file),
                 None => {
                     groups_map.insert(
-                        language.label,
+                        language.label.as_ref(),
                         LanguageGroup {
                             language: language.clone(),
                             files: vec![file],
@@ -239,11 +316,115 @@ pub(super) fn detect_languages(
                     );
                 }
             }
-            break;
         }
     }
 
     let mut groups: Vec<_> = groups_map.into_values().collect();
-    groups.sort_unstable_by(|a, b| a.language.label.cmp(b.language.label));
+    groups.sort_unstable_by(|a, b| a.language.label.cmp(&b.language.label));
     Ok(groups)
 }
+
+/// The content hints for the scripts without extension and shebang line, in
+/// the format `(prefix of the first line, language label)`.
+const CONTENT_HINTS: [(&str, &str); 4] = [
+    ("<?php", "php"),
+    ("<!DOCTYPE html", "html"),
+    ("<html", "html"),
+    ("package main", "go"),
+];
+
+/// Detect the language of a file without extension, by its shebang line (such
+/// as `#!/usr/bin/env python3`), or the [`CONTENT_HINTS`].
+fn detect_script<'a>(path: &Path, languages: &'a [Language]) -> Option<&'a Language> {
+    let file = File::open(path).ok()?;
+    let mut line = String::new();
+    // Limit the read size, the file may be a large binary.
+    BufReader::new(file.take(256)).read_line(&mut line).ok()?;
+    let line = line.trim();
+
+    if let Some(interpreter) = parse_shebang(line) {
+        let interpreter = interpreter.trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+        return languages
+            .iter()
+            .find(|language| language.interpreters.iter().any(|item| item == interpreter));
+    }
+
+    let (_, label) = CONTENT_HINTS
+        .iter()
+        .find(|(prefix, _)| line.starts_with(prefix))?;
+    languages.iter().find(|language| language.label == *label)
+}
+
+/// Return the interpreter name in the shebang line, the `env` (and its flags)
+/// is skipped.
+fn parse_shebang(line: &str) -> Option<&str> {
+    let line = line.strip_prefix("#!")?;
+    let mut fields = line.split_whitespace();
+    let mut program = fields.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = fields.find(|field| !field.starts_with('-') && !field.contains('='))?;
+    }
+    Some(program)
+}
+
+#[cfg(test)]
+mod detect_tests {
+    use crate::config::LanguageConfig;
+    use crate::repo::detect::*;
+    use crate::utils;
+
+    #[test]
+    fn test_detect_script() {
+        let path = PathBuf::from("_test/detect_script/bin");
+        let scripts = [
+            ("build", "#!/bin/bash\nset -e\n", Some("shell")),
+            ("run", "#!/usr/bin/env python3\nprint(1)\n", Some("python")),
+            ("serve", "#!/usr/bin/env -S node --harmony\n", Some("js")),
+            ("task", "#!/usr/local/bin/ruby2.7 -w\n", Some("ruby")),
+            ("index", "<?php echo 1;\n", Some("php")),
+            ("unknown", "#!/usr/bin/awk -f\n", None),
+            ("README", "Hello\n", None),
+        ];
+        let languages = builtin_languages();
+        for (name, content, expect) in scripts {
+            let file = path.join(name);
+            utils::write_file(&file, content.as_bytes()).unwrap();
+            let label = detect_script(&file, &languages).map(|language| language.label.as_ref());
+            assert_eq!(label, expect, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_languages() {
+        let mut cfg = Config::default();
+        cfg.languages.push(LanguageConfig {
+            name: String::from("Jsonnet"),
+            label: None,
+            extensions: vec![String::from("jsonnet"), String::from("libsonnet")],
+            interpreters: vec![],
+            line_comment: vec![String::from("//"), String::from("#")],
+            block_comment: Some((String::from("/*"), String::from("*/"))),
+        });
+        // Override the builtin one.
+        cfg.languages.push(LanguageConfig {
+            name: String::from("Perl"),
+            label: None,
+            extensions: vec![String::from("pl")],
+            interpreters: vec![String::from("perl")],
+            line_comment: vec![String::from("#")],
+            block_comment: None,
+        });
+
+        let languages = languages(&cfg);
+        assert_eq!(languages.len(), builtin_languages().len() + 2);
+        let jsonnet = &languages[0];
+        assert_eq!(jsonnet.label, "jsonnet");
+        assert_eq!(jsonnet.comments, vec!["//", "#", "/*"]);
+
+        let perl = languages
+            .iter()
+            .find(|language| language.extensions.iter().any(|ext| ext == "pl"))
+            .unwrap();
+        assert_eq!(perl.comments, vec!["#"]);
+    }
+}
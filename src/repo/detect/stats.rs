@@ -9,9 +9,11 @@ use chrono::{Local, NaiveDate};
 use glob::Pattern as GlobPattern;
 use serde::{Deserialize, Serialize};
 
+use crate::config::local::LocalConfig;
 use crate::config::Config;
 use crate::filelock::FileLock;
-use crate::{utils, warn};
+use crate::progress::{self, Progress, ProgressUnit};
+use crate::{interrupt, timings, utils, warn};
 
 use super::{Language, LanguageGroup};
 
@@ -38,19 +40,44 @@ pub struct LanguageStats {
 impl DetectStats {
     pub fn new(cfg: &Config) -> Self {
         Self {
-            languages: super::builtin_languages(),
+            languages: super::languages(cfg),
             ignores: cfg.detect_ignores.clone(),
         }
     }
 
     pub fn count(&self, path: &Path) -> Result<Vec<LanguageStats>> {
-        let groups = super::detect_languages(&self.ignores, path, &self.languages)?;
+        self.scan(path, false)
+    }
+
+    /// Same as [`DetectStats::count`], but render a [`Progress`] of the scanned
+    /// files. Don't use this in batch tasks, which have their own tracking.
+    pub fn count_progress(&self, path: &Path) -> Result<Vec<LanguageStats>> {
+        self.scan(path, true)
+    }
+
+    fn scan(&self, path: &Path, show_progress: bool) -> Result<Vec<LanguageStats>> {
+        let _span = timings::span("scan");
+        let ignores = LocalConfig::load(path)?.detect_ignores(&self.ignores)?;
+        let groups = super::detect_languages(&ignores, path, &self.languages)?;
         let mut result = Vec::with_capacity(groups.len());
 
+        let total: usize = groups.iter().map(|group| group.files.len()).sum();
+        let mut done = 0;
+        let mut progress = if show_progress {
+            Some(Progress::new(
+                String::from("Scanning"),
+                String::from("Scan"),
+                total as u64,
+                ProgressUnit::Count,
+            ))
+        } else {
+            None
+        };
+
         for group in groups {
             let LanguageGroup { language, files } = group;
             let mut stats = LanguageStats {
-                name: Cow::Borrowed(language.name),
+                name: language.name.clone(),
                 files: files.len(),
                 blank: 0,
                 comment: 0,
@@ -60,7 +87,15 @@ impl DetectStats {
             };
 
             for file in files {
+                interrupt::check()?;
                 self.count_file(path, file, &language, &mut stats)?;
+                done += 1;
+                match progress.as_mut() {
+                    Some(progress) => progress.inc(1),
+                    None => {
+                        progress::report("Scanning", done, total as u64);
+                    }
+                }
             }
 
             if stats.blank == 0 && stats.comment == 0 && stats.code == 0 {
@@ -104,7 +139,7 @@ impl DetectStats {
 
             let mut is_comment = false;
             for comment in language.comments.iter() {
-                if line.starts_with(*comment) {
+                if line.starts_with(comment.as_ref()) {
                     is_comment = true;
                     break;
                 }
@@ -407,7 +442,7 @@ impl LanguageStatsChange {
             change.percent = percent;
         }
 
-        changes.sort_unstable_by(|a, b| b.lines_abs.cmp(&a.lines_abs));
+        changes.sort_unstable_by_key(|change| std::cmp::Reverse(change.lines_abs));
         changes
     }
 }
@@ -0,0 +1,199 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::config::{Config, WorkflowEnv};
+use crate::exec::GitCmd;
+use crate::repo::Repo;
+use crate::{utils, workflow};
+
+/// The env file (such as `.envrc` or `.env`) generated for the repository, see
+/// [`crate::config::EnvFileConfig`].
+pub struct EnvFile {
+    /// The file name.
+    pub name: String,
+
+    vars: Vec<(String, String)>,
+}
+
+impl EnvFile {
+    const DEFAULT_NAME: &'static str = ".envrc";
+
+    /// Build the env file for the repository based on the remote and owner config,
+    /// return [`None`] if there is no env config.
+    pub fn build(cfg: &Config, repo: &Repo) -> Option<EnvFile> {
+        let remote_env = repo.remote_cfg.env.as_ref();
+        let owner_env = repo
+            .remote_cfg
+            .owners
+            .get(repo.owner.as_ref())
+            .and_then(|owner| owner.env.as_ref());
+        if remote_env.is_none() && owner_env.is_none() {
+            return None;
+        }
+
+        let name = owner_env
+            .and_then(|env| env.file.clone())
+            .or_else(|| remote_env.and_then(|env| env.file.clone()))
+            .unwrap_or_else(|| String::from(Self::DEFAULT_NAME));
+
+        // The owner's variables override remote's with the same name.
+        let mut env_cfg: Vec<WorkflowEnv> = Vec::new();
+        for env in remote_env
+            .into_iter()
+            .chain(owner_env)
+            .flat_map(|env| env.vars.iter())
+        {
+            match env_cfg.iter_mut().find(|item| item.name == env.name) {
+                Some(item) => *item = env.clone(),
+                None => env_cfg.push(env.clone()),
+            }
+        }
+
+        let path = repo.get_path(cfg);
+        let mut map = workflow::build_env(repo, &env_cfg, &path);
+        let vars = env_cfg
+            .into_iter()
+            .map(|env| {
+                let value = map.remove(&env.name).unwrap_or_default();
+                (env.name, value)
+            })
+            .collect();
+
+        Some(EnvFile { name, vars })
+    }
+
+    /// Render the file content. For `.envrc`, use `export NAME='value'`, for
+    /// others, use `NAME="value"`, the `$` is escaped so that the value is not
+    /// interpolated when the file is sourced or loaded.
+    pub fn render(&self) -> String {
+        let direnv = self.name.ends_with(".envrc");
+        let mut content = String::new();
+        for (name, value) in self.vars.iter() {
+            if direnv {
+                let value = value.replace('\'', r#"'\''"#);
+                content.push_str(&format!("export {name}='{value}'\n"));
+            } else {
+                let value = value
+                    .replace('\\', r"\\")
+                    .replace('"', "\\\"")
+                    .replace('$', r"\$");
+                content.push_str(&format!("{name}=\"{value}\"\n"));
+            }
+        }
+        content
+    }
+
+    /// Write the file into the repository directory `dir`. The variables may
+    /// contain secrets (such as the api token), so the file is only readable by
+    /// the owner, and is excluded from git by `.git/info/exclude`.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        utils::write_private_file(&dir.join(&self.name), self.render().as_bytes())?;
+        self.exclude(dir)
+    }
+
+    /// Append the file to the git exclude file if it is not there. Do nothing if
+    /// the directory is not a git repository.
+    fn exclude(&self, dir: &Path) -> Result<()> {
+        let dir_str = format!("{}", dir.display());
+        let exclude =
+            match GitCmd::with_path(&dir_str).read(&["rev-parse", "--git-path", "info/exclude"]) {
+                Ok(path) => dir.join(path),
+                Err(_) => return Ok(()),
+            };
+        let content = match fs::read_to_string(&exclude) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read file '{}'", exclude.display()))
+            }
+        };
+
+        let pattern = format!("/{}", self.name);
+        if content
+            .lines()
+            .any(|line| line.trim() == pattern || line.trim() == self.name)
+        {
+            return Ok(());
+        }
+        let mut content = content;
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&pattern);
+        content.push('\n');
+        utils::write_file(&exclude, content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod env_tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use crate::config::config_tests;
+    use crate::exec::Cmd;
+    use crate::repo::env::*;
+
+    #[test]
+    fn test_render() {
+        let vars = vec![
+            (String::from("REPO_NAME"), String::from("roxide")),
+            (String::from("QUOTE"), String::from(r#"it's "ok""#)),
+            (String::from("SECRET"), String::from("pa$$w0rd$HOME")),
+        ];
+        let envrc = EnvFile {
+            name: String::from(".envrc"),
+            vars: vars.clone(),
+        };
+        assert_eq!(
+            envrc.render(),
+            "export REPO_NAME='roxide'\nexport QUOTE='it'\\''s \"ok\"'\nexport SECRET='pa$$w0rd$HOME'\n"
+        );
+
+        let env = EnvFile {
+            name: String::from(".env"),
+            vars: vars.clone(),
+        };
+        assert_eq!(
+            env.render(),
+            "REPO_NAME=\"roxide\"\nQUOTE=\"it's \\\"ok\\\"\"\nSECRET=\"pa\\$\\$w0rd\\$HOME\"\n"
+        );
+
+        // Both files keep the values as they are when sourced by shell.
+        for file in [envrc, env] {
+            let script = format!(
+                "{}printf '%s\\n' \"$REPO_NAME\" \"$QUOTE\" \"$SECRET\"",
+                file.render()
+            );
+            let output = Cmd::with_args("sh", &["-c", script.as_str()])
+                .lines()
+                .unwrap();
+            let expect: Vec<_> = vars.iter().map(|(_, value)| value.clone()).collect();
+            assert_eq!(output, expect);
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let cfg = config_tests::load_test_config("env/write");
+        let dir = cfg.get_workspace_dir().join("repo");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let dir_str = format!("{}", dir.display());
+        Cmd::git(&["-C", &dir_str, "init", "-q"]).execute().unwrap();
+
+        let env = EnvFile {
+            name: String::from(".envrc"),
+            vars: vec![(String::from("TOKEN"), String::from("secret"))],
+        };
+        env.write(&dir).unwrap();
+        env.write(&dir).unwrap();
+
+        let meta = fs::metadata(dir.join(".envrc")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        let exclude = fs::read_to_string(dir.join(".git/info/exclude")).unwrap();
+        assert_eq!(exclude.lines().filter(|line| *line == "/.envrc").count(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::filelock::FileLock;
+use crate::utils;
+
+/// The jump table records the frequently used directories outside of the
+/// repositories (such as config dirs and notes), so that they can be jumped to
+/// alongside the repositories. The directories are reported by the shell hook,
+/// see `init`.
+///
+/// The table is stored in `{metadir}/jump`, apart from the repository
+/// database, so that its format can be changed without breaking the database.
+pub struct JumpTable<'a> {
+    cfg: &'a Config,
+
+    path: PathBuf,
+
+    /// The key is the absolute path of the directory.
+    dirs: HashMap<String, JumpEntry>,
+
+    _lock: FileLock,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JumpEntry {
+    pub accessed: u64,
+    pub last_accessed: u64,
+}
+
+/// A directory in the jump table.
+#[derive(Debug, Clone)]
+pub struct JumpDir {
+    pub path: String,
+    pub entry: JumpEntry,
+}
+
+impl JumpDir {
+    /// See [`crate::repo::Repo::score`].
+    pub fn score(&self, cfg: &Config) -> u64 {
+        utils::frecency(cfg.now(), self.entry.accessed, self.entry.last_accessed)
+    }
+}
+
+impl JumpTable<'_> {
+    const VERSION: u32 = 1;
+
+    const MAX_SIZE: u64 = 8 << 20;
+
+    /// Keep at most these directories, the ones with the lowest scores are
+    /// dropped when saving.
+    const MAX_DIRS: usize = 1000;
+
+    pub fn load(cfg: &Config) -> Result<JumpTable<'_>> {
+        let lock = FileLock::acquire(cfg, "jump")?;
+        let path = cfg.get_meta_dir().join("jump");
+        let dirs = Self::read(&path)?;
+        Ok(JumpTable {
+            cfg,
+            path,
+            dirs,
+            _lock: lock,
+        })
+    }
+
+    fn read(path: &Path) -> Result<HashMap<String, JumpEntry>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read jump table '{}'", path.display()))
+            }
+        };
+        let decoder = &mut bincode::options()
+            .with_fixint_encoding()
+            .with_limit(Self::MAX_SIZE);
+        let version_size = decoder.serialized_size(&Self::VERSION).unwrap() as usize;
+        if data.len() < version_size {
+            bail!("corrupted jump table");
+        }
+        let (version_data, data) = data.split_at(version_size);
+        let version: u32 = decoder
+            .deserialize(version_data)
+            .context("decode jump table version")?;
+        if version != Self::VERSION {
+            bail!("unsupported jump table version {version}");
+        }
+        decoder.deserialize(data).context("decode jump table")
+    }
+
+    /// Record a visit of the directory.
+    pub fn add(&mut self, path: &Path) {
+        let now = self.cfg.now();
+        let entry = self
+            .dirs
+            .entry(format!("{}", path.display()))
+            .or_insert(JumpEntry {
+                accessed: 0,
+                last_accessed: now,
+            });
+        entry.accessed += 1;
+        entry.last_accessed = now;
+    }
+
+    /// Remove the directory, return `false` if it is not in the table.
+    pub fn remove(&mut self, path: &Path) -> bool {
+        self.dirs
+            .remove(format!("{}", path.display()).as_str())
+            .is_some()
+    }
+
+    /// Remove the directories that no longer exist, return the count removed.
+    pub fn remove_missing(&mut self) -> usize {
+        let count = self.dirs.len();
+        self.dirs.retain(|path, _| Path::new(path).is_dir());
+        count - self.dirs.len()
+    }
+
+    /// List the directories, sorted by score, the highest first.
+    pub fn list(&self) -> Vec<JumpDir> {
+        let mut dirs: Vec<_> = self
+            .dirs
+            .iter()
+            .map(|(path, entry)| JumpDir {
+                path: path.clone(),
+                entry: entry.clone(),
+            })
+            .collect();
+        dirs.sort_unstable_by(|a, b| {
+            b.score(self.cfg)
+                .cmp(&a.score(self.cfg))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        dirs
+    }
+
+    pub fn save(mut self) -> Result<()> {
+        if self.dirs.len() > Self::MAX_DIRS {
+            let dirs = self.list();
+            for dir in dirs.into_iter().skip(Self::MAX_DIRS) {
+                self.dirs.remove(&dir.path);
+            }
+        }
+        let mut buffer = Vec::new();
+        bincode::serialize_into(&mut buffer, &Self::VERSION)
+            .context("encode jump table version")?;
+        bincode::serialize_into(&mut buffer, &self.dirs).context("encode jump table")?;
+        utils::write_file(&self.path, &buffer)
+    }
+}
+
+/// Return `true` if the `path` matches all the `keywords` in order, the same as
+/// zoxide: the matching is case-insensitive, and the last keyword must match the
+/// last component of the path.
+pub fn matches(path: &str, keywords: &[String]) -> bool {
+    let path = path.to_lowercase();
+    let last = match keywords.last() {
+        Some(last) => last.to_lowercase(),
+        None => return true,
+    };
+    let name = path.rsplit('/').next().unwrap_or_default();
+    if !name.contains(&last) {
+        return false;
+    }
+
+    let mut rest = path.as_str();
+    for keyword in keywords {
+        let keyword = keyword.to_lowercase();
+        match rest.find(&keyword) {
+            Some(idx) => rest = &rest[idx + keyword.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod jump_tests {
+    use crate::repo::jump::*;
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            ("/home/user/.config/nvim", vec!["nvim"], true),
+            ("/home/user/.config/nvim", vec!["conf", "nvim"], true),
+            ("/home/user/.config/nvim", vec!["Config"], false),
+            ("/home/user/.config/nvim", vec!["nvim", "conf"], false),
+            ("/home/user/notes/work", vec!["note", "WO"], true),
+            ("/home/user/notes/work", vec![], true),
+        ];
+        for (path, keywords, expect) in cases {
+            let keywords: Vec<String> = keywords.into_iter().map(String::from).collect();
+            assert_eq!(matches(path, &keywords), expect, "{path} {keywords:?}");
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::filelock::FileLock;
+use crate::repo::Repo;
+use crate::utils;
+
+/// A push mirror of a repository, the branches and tags of origin are pushed
+/// to it by `sync`, such as backing up a GitHub repository to a self-hosted
+/// GitLab.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushMirror {
+    /// The remote name of the mirror.
+    pub remote: String,
+    pub url: String,
+
+    /// The time of the last push, [`None`] means never pushed.
+    pub last_push: Option<u64>,
+    /// The error of the last push, [`None`] means it succeeded.
+    pub error: Option<String>,
+}
+
+/// The push mirrors of the local repositories, stored in `{metadir}/mirrors`.
+///
+/// Repositories are identified by their full name `{remote}:{owner}/{name}`, see
+/// [`Repo::name_with_remote`].
+pub struct Mirrors {
+    data: HashMap<String, Vec<PushMirror>>,
+
+    path: PathBuf,
+
+    _lock: FileLock,
+}
+
+impl Mirrors {
+    pub fn load(cfg: &Config) -> Result<Mirrors> {
+        let lock = FileLock::acquire(cfg, "mirrors")?;
+
+        let path = cfg.get_meta_dir().join("mirrors");
+        let data = match fs::read(&path) {
+            Ok(data) => bincode::deserialize(&data).context("decode mirrors data")?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read mirrors file '{}'", path.display()))
+            }
+        };
+
+        Ok(Mirrors {
+            data,
+            path,
+            _lock: lock,
+        })
+    }
+
+    /// Add a push mirror to the repo, the remote could not be the repo's own.
+    pub fn add(&mut self, repo: &Repo, remote: &str, url: String) -> Result<()> {
+        if repo.remote == remote {
+            bail!(
+                "could not mirror repo '{}' to its own remote",
+                repo.name_with_remote()
+            );
+        }
+        let mirrors = self.data.entry(repo.name_with_remote()).or_default();
+        if mirrors.iter().any(|mirror| mirror.remote == remote) {
+            bail!(
+                "repo '{}' already has a push mirror to remote '{remote}'",
+                repo.name_with_remote()
+            );
+        }
+        mirrors.push(PushMirror {
+            remote: remote.to_string(),
+            url,
+            last_push: None,
+            error: None,
+        });
+        Ok(())
+    }
+
+    /// Remove the push mirror, return `false` if it does not exist.
+    pub fn remove(&mut self, repo: &Repo, remote: &str) -> bool {
+        let name = repo.name_with_remote();
+        let mirrors = match self.data.get_mut(&name) {
+            Some(mirrors) => mirrors,
+            None => return false,
+        };
+        let count = mirrors.len();
+        mirrors.retain(|mirror| mirror.remote != remote);
+        let removed = mirrors.len() < count;
+        if mirrors.is_empty() {
+            self.data.remove(&name);
+        }
+        removed
+    }
+
+    /// Rename the repo of the push mirrors, used when the repo is moved.
+    pub fn rename(&mut self, name: &str, new_name: &str) {
+        if let Some(mirrors) = self.data.remove(name) {
+            self.data.insert(new_name.to_string(), mirrors);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> &[PushMirror] {
+        match self.data.get(name) {
+            Some(mirrors) => mirrors.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Record the result of pushing to the mirror.
+    pub fn update(&mut self, name: &str, remote: &str, now: u64, error: Option<String>) {
+        let mirrors = match self.data.get_mut(name) {
+            Some(mirrors) => mirrors,
+            None => return,
+        };
+        if let Some(mirror) = mirrors.iter_mut().find(|mirror| mirror.remote == remote) {
+            mirror.last_push = Some(now);
+            mirror.error = error;
+        }
+    }
+
+    pub fn save(self) -> Result<()> {
+        let data = bincode::serialize(&self.data).context("encode mirrors data")?;
+        utils::write_file(&self.path, &data)
+    }
+}
+
+#[cfg(test)]
+mod mirror_tests {
+    use crate::config::config_tests;
+    use crate::repo::database::database_tests;
+    use crate::repo::mirror::*;
+
+    #[test]
+    fn test_mirrors() {
+        let cfg = config_tests::load_test_config("mirrors");
+        let _ = fs::remove_file(cfg.get_meta_dir().join("mirrors"));
+        let repos = database_tests::get_test_repos(&cfg);
+        let repo = repos.iter().find(|repo| repo.name == "csync").unwrap();
+        let name = repo.name_with_remote();
+
+        let mut mirrors = Mirrors::load(&cfg).unwrap();
+        let url = String::from("git@gitlab.com:fioncat/csync.git");
+        mirrors.add(repo, "gitlab", url.clone()).unwrap();
+        assert!(mirrors.add(repo, "gitlab", url.clone()).is_err());
+        assert!(mirrors.add(repo, repo.remote.as_ref(), url).is_err());
+        mirrors.update(&name, "gitlab", 100, Some(String::from("failed")));
+        mirrors.save().unwrap();
+
+        let mut mirrors = Mirrors::load(&cfg).unwrap();
+        let expect = PushMirror {
+            remote: String::from("gitlab"),
+            url: String::from("git@gitlab.com:fioncat/csync.git"),
+            last_push: Some(100),
+            error: Some(String::from("failed")),
+        };
+        assert_eq!(mirrors.get(&name), &[expect]);
+
+        assert!(mirrors.remove(repo, "gitlab"));
+        assert!(!mirrors.remove(repo, "gitlab"));
+        assert!(mirrors.get(&name).is_empty());
+    }
+}
@@ -1,12 +1,20 @@
 pub mod database;
+pub mod deps;
 pub mod detect;
+pub mod env;
+pub mod jump;
 pub mod keywords;
+pub mod mirror;
 pub mod snapshot;
+pub mod usage;
+pub mod visits;
 
+use std::borrow::Cow;
 use std::collections::HashSet;
-use std::{borrow::Cow, path::PathBuf};
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use glob::Pattern as GlobPattern;
 
 use crate::api::ApiUpstream;
 use crate::config::{defaults, Config, RemoteConfig};
@@ -133,7 +141,11 @@ impl Repo<'_> {
 
     /// Use [`ApiUpstream`] to build a repository object.
     #[inline]
-    pub fn from_api_upstream(cfg: &Config, remote: impl AsRef<str>, upstream: ApiUpstream) -> Repo {
+    pub fn from_api_upstream(
+        cfg: &Config,
+        remote: impl AsRef<str>,
+        upstream: ApiUpstream,
+    ) -> Repo<'_> {
         Repo {
             remote: Cow::Owned(remote.as_ref().to_string()),
             owner: Cow::Owned(upstream.owner),
@@ -163,6 +175,15 @@ impl Repo<'_> {
         )
     }
 
+    /// Return `true` if the repository is located outside the workspace, that is,
+    /// it was attached with a custom path.
+    pub fn is_external(&self, cfg: &Config) -> bool {
+        match self.path.as_ref() {
+            Some(path) => !Path::new(path.as_ref()).starts_with(cfg.get_workspace_dir()),
+            None => false,
+        }
+    }
+
     /// `score` is used to sort and prioritize multiple repositories. In scenarios
     /// like fuzzy matching, repositories with higher scores are matched first.
     ///
@@ -174,16 +195,76 @@ impl Repo<'_> {
     ///
     /// * [Algorithm](https://github.com/ajeetdsouza/zoxide/wiki/Algorithm#frecency)
     pub fn score(&self, cfg: &Config) -> u64 {
-        let duration = cfg.now().saturating_sub(self.last_accessed);
-        if duration < utils::HOUR {
-            self.accessed * 16
-        } else if duration < utils::DAY {
-            self.accessed * 8
-        } else if duration < utils::WEEK {
-            self.accessed * 2
-        } else {
-            self.accessed
+        utils::frecency(cfg.now(), self.accessed, self.last_accessed)
+    }
+
+    /// Return the pin priority of this repository, [`None`] means the repository
+    /// is not pinned. A pinned repository has label `pin`, and optional label
+    /// `pin:{priority}` to set the priority (default is 0). Repositories with
+    /// higher priority will be listed first.
+    pub fn pin_priority(&self) -> Option<u32> {
+        let labels = self.labels.as_ref()?;
+        if !labels.contains("pin") {
+            return None;
+        }
+        let priority = labels
+            .iter()
+            .filter_map(|label| label.strip_prefix("pin:"))
+            .filter_map(|priority| priority.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+        Some(priority)
+    }
+
+    /// Return `true` if the repository is cloned as bare with worktrees, that
+    /// is, it has label `bare` (see `home --bare`). Its root has no work tree,
+    /// the per-branch operations should be run in the worktrees.
+    pub fn is_bare(&self) -> bool {
+        match self.labels.as_ref() {
+            Some(labels) => labels.contains("bare"),
+            None => false,
+        }
+    }
+
+    /// Return `true` if the repository is protected, that is, it has label
+    /// `protected`, or its name matches one of the owner's `protect` patterns.
+    /// Destructive operations on it should follow the config `protect_policy`.
+    pub fn is_protected(&self) -> bool {
+        if let Some(labels) = self.labels.as_ref() {
+            if labels.contains("protected") {
+                return true;
+            }
+        }
+        let owner_cfg = match self.remote_cfg.owners.get(self.owner.as_ref()) {
+            Some(owner_cfg) => owner_cfg,
+            None => return false,
+        };
+        owner_cfg.protect.iter().any(|pattern| {
+            pattern == self.name.as_ref()
+                || GlobPattern::new(pattern).is_ok_and(|pattern| pattern.matches(&self.name))
+        })
+    }
+
+    /// Set the pin priority, [`None`] means unpin the repository. See:
+    /// [`Repo::pin_priority`].
+    pub fn set_pin_priority(&mut self, priority: Option<u32>) {
+        if let Some(labels) = self.labels.as_mut() {
+            labels.retain(|label| label != "pin" && !label.starts_with("pin:"));
+            if labels.is_empty() {
+                self.labels = None;
+            }
         }
+        let priority = match priority {
+            Some(priority) => priority,
+            None => return,
+        };
+
+        let mut pin_labels = HashSet::with_capacity(2);
+        pin_labels.insert(String::from("pin"));
+        if priority > 0 {
+            pin_labels.insert(format!("pin:{priority}"));
+        }
+        self.append_labels(Some(pin_labels));
     }
 
     /// Build the string to display this repository. See: [`NameLevel`].
@@ -236,6 +317,38 @@ impl Repo<'_> {
         Self::get_clone_url(&self.owner, &self.name, self.remote_cfg.as_ref())
     }
 
+    /// Retrieve the SSH `git clone` URL for this repository, regardless of the
+    /// `ssh` config. Return [`None`] if the remote has no clone domain.
+    ///
+    /// Alias rules from the configuration will be applied here.
+    pub fn ssh_url(&self) -> Option<String> {
+        let domain = self.remote_cfg.clone.as_ref()?;
+        let (owner, name) = self.alias_names();
+        Some(format!("git@{domain}:{owner}/{name}.git"))
+    }
+
+    /// Retrieve the web URL for this repository. Return [`None`] if the remote
+    /// has no clone domain.
+    ///
+    /// Alias rules from the configuration will be applied here.
+    pub fn web_url(&self) -> Option<String> {
+        let domain = self.remote_cfg.clone.as_ref()?;
+        let (owner, name) = self.alias_names();
+        Some(format!("https://{domain}/{owner}/{name}"))
+    }
+
+    fn alias_names(&self) -> (&str, &str) {
+        let owner = self
+            .remote_cfg
+            .alias_owner(self.owner.as_ref())
+            .unwrap_or_else(|| self.owner.as_ref());
+        let name = self
+            .remote_cfg
+            .alias_repo(owner, self.name.as_ref())
+            .unwrap_or_else(|| self.name.as_ref());
+        (owner, name)
+    }
+
     /// Retrieve the `git clone` URL for the specified repository.
     ///
     /// Alias rules from the configuration will be applied here.
@@ -265,12 +378,7 @@ impl Repo<'_> {
         O: AsRef<str>,
         N: AsRef<str>,
     {
-        let mut ssh = remote_cfg.ssh;
-        if let Some(owner_cfg) = remote_cfg.owners.get(owner.as_ref()) {
-            if let Some(use_ssh) = owner_cfg.ssh {
-                ssh = use_ssh;
-            }
-        }
+        let ssh = remote_cfg.use_ssh(owner.as_ref());
 
         let domain = match remote_cfg.clone.as_ref() {
             Some(domain) => domain.as_str(),
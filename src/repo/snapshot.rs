@@ -1,13 +1,17 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::{fs, io};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::ser::PrettyFormatter;
 use serde_json::Serializer;
 
 use crate::config::Config;
+use crate::errors::ErrorKind;
+use crate::exec::GitCmd;
 use crate::repo::database::{Bucket, Database};
+use crate::repo::deps;
 use crate::{term, utils};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,12 +24,54 @@ pub struct Snapshot {
 
     pub bucket: Bucket,
 
+    /// The git revisions of the repositories, the key is the full name
+    /// `{remote}:{owner}/{name}`. The repositories that have not been cloned
+    /// are not included.
+    #[serde(default)]
+    pub revisions: BTreeMap<String, Revision>,
+
     #[serde(skip)]
     pub path: PathBuf,
 }
 
+/// The git revision of a repository when taking the snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    /// The current branch, [`None`] if the HEAD is detached.
+    pub branch: Option<String>,
+
+    /// The commit SHA of HEAD.
+    pub commit: String,
+}
+
+impl Revision {
+    /// Read the revision of the repository in `path`, return [`None`] if it is not
+    /// a git repository.
+    pub fn read(path: &str) -> Option<Revision> {
+        let git = GitCmd::with_path(path);
+        let commit = git.read(&["rev-parse", "HEAD"]).ok()?;
+        let branch = git
+            .read(&["branch", "--show-current"])
+            .ok()
+            .filter(|branch| !branch.is_empty());
+        Some(Revision { branch, commit })
+    }
+}
+
 impl Snapshot {
     pub fn take(cfg: &Config, db: Database, name: String) -> Snapshot {
+        let mut revisions = BTreeMap::new();
+        for repo in db.list_all(&None) {
+            let path = repo.get_path(cfg);
+            if !path.is_dir() {
+                continue;
+            }
+            let path = format!("{}", path.display());
+            if let Some(revision) = Revision::read(&path) {
+                revisions.insert(repo.name_with_remote(), revision);
+            }
+        }
+
         let path = cfg
             .get_meta_dir()
             .join("snapshot")
@@ -35,6 +81,7 @@ impl Snapshot {
             create_time: cfg.now(),
             version: Bucket::VERSION,
             bucket: db.close(),
+            revisions,
             path,
         }
     }
@@ -47,14 +94,19 @@ impl Snapshot {
         let data = match fs::read(&path) {
             Ok(data) => data,
             Err(err) if err.kind() == io::ErrorKind::NotFound => {
-                bail!("could not find snapshot '{}'", name)
+                return Err(
+                    ErrorKind::NotFound.error(format!("could not find snapshot '{}'", name))
+                );
             }
             Err(err) => {
                 return Err(err).with_context(|| format!("read snapshot path '{}'", path.display()))
             }
         };
 
-        serde_json::from_slice(&data).context("decode snapshot data")
+        let mut snapshot: Snapshot =
+            serde_json::from_slice(&data).context("decode snapshot data")?;
+        snapshot.path = path;
+        Ok(snapshot)
     }
 
     pub fn list(cfg: &Config) -> Result<Vec<String>> {
@@ -101,6 +153,46 @@ impl Snapshot {
         utils::write_file(&self.path, &data)
     }
 
+    /// Rename the repo `name` (`{remote}:{owner}/{name}`) in the snapshot, used
+    /// when the repo is moved. Return `false` if the repo is not in it.
+    pub fn rename_repo(&mut self, name: &str, new_name: &str) -> bool {
+        let mut renamed = false;
+        if let Some(revision) = self.revisions.remove(name) {
+            self.revisions.insert(new_name.to_string(), revision);
+            renamed = true;
+        }
+
+        let (remote, owner, name) = match deps::parse_name(name) {
+            Some(fields) => fields,
+            None => return renamed,
+        };
+        let (_, new_owner, new_name) = match deps::parse_name(new_name) {
+            Some(fields) => fields,
+            None => return renamed,
+        };
+        let remote_bucket = match self.bucket.data.get_mut(&remote) {
+            Some(remote_bucket) => remote_bucket,
+            None => return renamed,
+        };
+        let repo_bucket = match remote_bucket.get_mut(&owner) {
+            Some(owner_bucket) => match owner_bucket.remove(&name) {
+                Some(repo_bucket) => {
+                    if owner_bucket.is_empty() {
+                        remote_bucket.remove(&owner);
+                    }
+                    repo_bucket
+                }
+                None => return renamed,
+            },
+            None => return renamed,
+        };
+        remote_bucket
+            .entry(new_owner)
+            .or_default()
+            .insert(new_name, repo_bucket);
+        true
+    }
+
     pub fn restore(self, mut db: Database) -> Result<()> {
         db.set_bucket(self.bucket);
         db.save()
@@ -126,6 +218,7 @@ impl Snapshot {
         println!("Remote Count: {}", remote_count);
         println!("Owner Count:  {}", owner_count);
         println!("Repo Count:   {}", repo_count);
+        println!("Revisions:    {}", self.revisions.len());
 
         Ok(())
     }
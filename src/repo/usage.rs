@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use bincode::Options;
+use glob::Pattern as GlobPattern;
+use serde::{Deserialize, Serialize};
+
+use crate::config::local::LocalConfig;
+use crate::config::Config;
+use crate::repo::Repo;
+use crate::utils::{self, DirUsage, HardLink, UsageScan};
+use crate::{interrupt, progress, timings};
+
+/// The disk usage cache of a repository. For each directory, the usage of its
+/// direct entries is cached with its mtime, so that the unchanged directories
+/// can be skipped in the next scan.
+///
+/// The mtime of a directory only changes when its entries are added, removed
+/// or renamed, so the files modified in place are not detected. This is rare
+/// in git repositories, and the cache is dropped when the repo is synced. Use
+/// `--no-cache` to force a full scan.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageCache {
+    /// The ignore patterns used to build the cache, if they are changed, the
+    /// cache is dropped.
+    ignores: Vec<String>,
+
+    /// The key is the directory path relative to the repository root.
+    dirs: HashMap<String, DirCache>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct DirCache {
+    /// The mtime of the directory, in nanoseconds.
+    mtime: i128,
+
+    /// The usage of the direct entries (not directories) that have only one
+    /// link.
+    usage: DirUsage,
+
+    /// The direct entries that have more than one link, they are deduplicated
+    /// across directories when summing up.
+    links: Vec<HardLink>,
+
+    /// The names of the sub directories.
+    dirs: Vec<String>,
+}
+
+impl UsageCache {
+    const VERSION: u32 = 1;
+
+    const MAX_SIZE: u64 = 256 << 20;
+
+    fn read(path: &Path) -> Result<UsageCache> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read usage cache '{}'", path.display()))
+            }
+        };
+        let decoder = &mut bincode::options()
+            .with_fixint_encoding()
+            .with_limit(Self::MAX_SIZE);
+        let version_size = decoder.serialized_size(&Self::VERSION).unwrap() as usize;
+        if data.len() < version_size {
+            bail!("corrupted usage cache");
+        }
+        let (version_data, data) = data.split_at(version_size);
+        let version: u32 = decoder
+            .deserialize(version_data)
+            .context("decode usage cache version")?;
+        if version != Self::VERSION {
+            // The cache is outdated, rebuild it.
+            return Ok(Self::default());
+        }
+        decoder.deserialize(data).context("decode usage cache")
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let mut buffer = Vec::new();
+        bincode::serialize_into(&mut buffer, &Self::VERSION)
+            .context("encode usage cache version")?;
+        bincode::serialize_into(&mut buffer, self).context("encode usage cache")?;
+        utils::write_file(&path.to_path_buf(), &buffer)
+    }
+}
+
+/// Return the usage cache file path of the repository.
+pub fn cache_path(cfg: &Config, repo: &Repo) -> PathBuf {
+    cfg.get_meta_dir()
+        .join("usage")
+        .join(repo.remote.as_ref())
+        .join(repo.owner.as_ref())
+        .join(repo.name.as_ref())
+}
+
+/// Remove the usage cache of the repository, call this when the repository is
+/// changed by roxide, such as synced or removed.
+pub fn remove_cache(cfg: &Config, repo: &Repo) -> Result<()> {
+    let path = cache_path(cfg, repo);
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("remove usage cache '{}'", path.display())),
+    }
+}
+
+/// Move the usage cache of the repository to `new_repo`, used when the
+/// repository is moved.
+pub fn move_cache(cfg: &Config, repo: &Repo, new_repo: &Repo) -> Result<()> {
+    let path = cache_path(cfg, repo);
+    if !path.exists() {
+        return Ok(());
+    }
+    let new_path = cache_path(cfg, new_repo);
+    utils::ensure_dir(&new_path)?;
+    fs::rename(&path, &new_path).with_context(|| {
+        format!(
+            "move usage cache '{}' to '{}'",
+            path.display(),
+            new_path.display()
+        )
+    })
+}
+
+/// Drop the cached `dirs` (relative to the repository root), so that they will
+/// be scanned again, even if their mtime is not changed.
+pub fn invalidate_dirs(cache_path: &Path, dirs: &HashSet<String>) -> Result<()> {
+    let mut cache = UsageCache::read(cache_path)?;
+    let count = cache.dirs.len();
+    cache.dirs.retain(|dir, _| !dirs.contains(dir));
+    if cache.dirs.len() == count {
+        return Ok(());
+    }
+    cache.save(cache_path)
+}
+
+/// Return the disk usage of the repository in `path`, the same as
+/// [`crate::config::local::repo_usage`], but the unchanged directories are read from the
+/// `cache` file (if provided), and the cache is updated after scanning.
+///
+/// If `one_file_system` is `true`, the directories on other devices than the
+/// repository (such as the bind mounts and network mounts) are skipped.
+pub fn repo_usage(
+    path: &Path,
+    global_ignores: &[GlobPattern],
+    cache: Option<&Path>,
+    one_file_system: bool,
+) -> Result<UsageScan> {
+    let ignores = LocalConfig::load(path)?.size_ignores(global_ignores)?;
+    let cache_path = match cache {
+        Some(cache_path) => cache_path,
+        None => {
+            return utils::dir_usage_with(path.to_path_buf(), &ignores, one_file_system);
+        }
+    };
+    let _span = timings::span("scan");
+
+    let ignore_strs: Vec<_> = ignores.iter().map(|p| p.as_str().to_string()).collect();
+
+    let mut cache = UsageCache::read(cache_path)?;
+    if cache.ignores != ignore_strs {
+        cache = UsageCache::default();
+    }
+
+    let mut scanner = Scanner {
+        root: path,
+        ignores: &ignores,
+        one_file_system,
+        old: cache.dirs,
+        new: HashMap::new(),
+        links: HashSet::new(),
+        scan: UsageScan::default(),
+    };
+    scanner.scan(String::new())?;
+
+    let Scanner { mut scan, new, .. } = scanner;
+    scan.links.sort_unstable_by_key(|link| (link.dev, link.ino));
+    let cache = UsageCache {
+        ignores: ignore_strs,
+        dirs: new,
+    };
+    cache.save(cache_path)?;
+    Ok(scan)
+}
+
+struct Scanner<'a> {
+    root: &'a Path,
+    ignores: &'a [GlobPattern],
+    one_file_system: bool,
+
+    old: HashMap<String, DirCache>,
+    new: HashMap<String, DirCache>,
+
+    links: HashSet<(u64, u64)>,
+    scan: UsageScan,
+}
+
+impl Scanner<'_> {
+    fn scan(&mut self, root_rel: String) -> Result<()> {
+        let mut root_dev = None;
+        let mut stack = vec![root_rel];
+        while let Some(rel) = stack.pop() {
+            interrupt::check()?;
+            let dir = self.root.join(&rel);
+            let meta = match fs::metadata(&dir) {
+                Ok(meta) => meta,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("read metadata for '{}'", dir.display()))
+                }
+            };
+            let root_dev = *root_dev.get_or_insert(meta.dev());
+            if self.one_file_system && meta.dev() != root_dev {
+                // The mount point was counted as an inode by its parent.
+                self.scan.usage.inodes -= 1;
+                self.scan.mounts.push(dir);
+                continue;
+            }
+            let mtime = meta.mtime() as i128 * 1_000_000_000 + meta.mtime_nsec() as i128;
+
+            let dir_cache = match self.old.remove(&rel) {
+                Some(dir_cache) if dir_cache.mtime == mtime => dir_cache,
+                _ => self.read_dir(&rel, &dir, mtime)?,
+            };
+            progress::count(dir_cache.usage.size, dir_cache.usage.files);
+
+            let usage = &mut self.scan.usage;
+            usage.size += dir_cache.usage.size;
+            usage.disk_size += dir_cache.usage.disk_size;
+            usage.files += dir_cache.usage.files;
+            usage.inodes += dir_cache.usage.inodes + dir_cache.dirs.len() as u64;
+            for link in dir_cache.links.iter() {
+                if !self.links.insert((link.dev, link.ino)) {
+                    continue;
+                }
+                usage.inodes += 1;
+                if link.is_file {
+                    usage.size += link.size;
+                    usage.disk_size += link.disk_size;
+                    usage.files += 1;
+                }
+                self.scan.links.push(*link);
+            }
+            for name in dir_cache.dirs.iter() {
+                stack.push(Self::join(&rel, name));
+            }
+            self.new.insert(rel, dir_cache);
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, rel: &str, dir: &Path, mtime: i128) -> Result<DirCache> {
+        let mut dir_cache = DirCache {
+            mtime,
+            ..Default::default()
+        };
+        let dir_read =
+            fs::read_dir(dir).with_context(|| format!("read dir '{}'", dir.display()))?;
+        for entry in dir_read {
+            let entry = entry.with_context(|| format!("read sub dir for '{}'", dir.display()))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let sub_rel = Self::join(rel, &name);
+            if self.ignores.iter().any(|pattern| pattern.matches(&sub_rel)) {
+                continue;
+            }
+            let meta = entry
+                .metadata()
+                .with_context(|| format!("read metadata for '{}'", dir.join(&name).display()))?;
+            if meta.is_dir() {
+                dir_cache.dirs.push(name);
+                continue;
+            }
+            // The `st_blocks` is always in 512-byte units.
+            let disk_size = meta.blocks() * 512;
+            if meta.nlink() > 1 {
+                dir_cache.links.push(HardLink {
+                    dev: meta.dev(),
+                    ino: meta.ino(),
+                    size: meta.len(),
+                    disk_size,
+                    is_file: meta.is_file(),
+                });
+                continue;
+            }
+            dir_cache.usage.inodes += 1;
+            if meta.is_file() {
+                dir_cache.usage.size += meta.len();
+                dir_cache.usage.disk_size += disk_size;
+                dir_cache.usage.files += 1;
+            }
+        }
+        Ok(dir_cache)
+    }
+
+    fn join(rel: &str, name: &str) -> String {
+        if rel.is_empty() {
+            return name.to_string();
+        }
+        format!("{rel}/{name}")
+    }
+}
+
+#[cfg(test)]
+mod usage_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::config::local;
+    use crate::repo::usage::*;
+
+    #[test]
+    fn test_repo_usage() {
+        let root = PathBuf::from("_test/usage_cache");
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let path = root.join("repo");
+        let cache = root.join("cache");
+        utils::write_file(&path.join("src").join("main.rs"), b"fn main() {}").unwrap();
+        utils::write_file(&path.join("src").join("lib").join("mod.rs"), b"mod a;").unwrap();
+        utils::write_file(&path.join("target").join("bin"), b"0123456789").unwrap();
+        fs::hard_link(path.join("src/main.rs"), path.join("main.rs")).unwrap();
+
+        let ignores = vec![GlobPattern::new("target").unwrap()];
+        let expect = local::repo_usage(&path, &ignores).unwrap();
+        assert_eq!(
+            repo_usage(&path, &ignores, Some(&cache), false)
+                .unwrap()
+                .usage,
+            expect
+        );
+        assert!(cache.exists());
+        // Read from cache.
+        assert_eq!(
+            repo_usage(&path, &ignores, Some(&cache), false)
+                .unwrap()
+                .usage,
+            expect
+        );
+
+        // The new file changes the directory mtime.
+        utils::write_file(&path.join("src").join("lib").join("a.rs"), b"pub fn a() {}").unwrap();
+        let expect = local::repo_usage(&path, &ignores).unwrap();
+        assert_eq!(
+            repo_usage(&path, &ignores, Some(&cache), false)
+                .unwrap()
+                .usage,
+            expect
+        );
+
+        // The changed ignores drop the cache.
+        let expect = local::repo_usage(&path, &[]).unwrap();
+        assert_eq!(
+            repo_usage(&path, &[], Some(&cache), false).unwrap().usage,
+            expect
+        );
+
+        // Nothing is skipped in a single filesystem.
+        let expect = UsageScan {
+            usage: expect,
+            mounts: vec![],
+            links: repo_usage(&path, &[], None, false).unwrap().links,
+        };
+        assert_eq!(expect.links.len(), 1);
+        assert_eq!(repo_usage(&path, &[], Some(&cache), true).unwrap(), expect);
+        assert_eq!(repo_usage(&path, &[], None, true).unwrap(), expect);
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+use crate::utils;
+
+/// The visit history of the repositories, stored in `{metadir}/visits`. The
+/// database only records the total count and the last visit, this keeps the
+/// time of each visit, so that the visits in a time range can be counted.
+///
+/// The visits older than [`Visits::KEEP_DAYS`] are dropped when saving, and
+/// at most [`Visits::MAX_PER_REPO`] visits are kept for one repository.
+pub struct Visits {
+    /// The key is the repository name with remote, the value is the visit
+    /// timestamps in ascending order.
+    data: HashMap<String, Vec<u64>>,
+
+    path: PathBuf,
+}
+
+impl Visits {
+    const KEEP_DAYS: u64 = 90;
+
+    const MAX_PER_REPO: usize = 1024;
+
+    pub fn load(cfg: &Config) -> Result<Visits> {
+        let path = cfg.get_meta_dir().join("visits");
+        let data = match fs::read(&path) {
+            // The history is only used for statistics, drop it if corrupted.
+            Ok(data) => bincode::deserialize(&data).unwrap_or_default(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read visits file '{}'", path.display()))
+            }
+        };
+        Ok(Visits { data, path })
+    }
+
+    /// Record a visit of the repository `name` at `time`.
+    pub fn record(&mut self, name: String, time: u64) {
+        let times = self.data.entry(name).or_default();
+        let idx = times.partition_point(|t| *t <= time);
+        times.insert(idx, time);
+        if times.len() > Self::MAX_PER_REPO {
+            times.remove(0);
+        }
+    }
+
+    /// Return the number of visits of the repository `name` in `[start, end)`.
+    pub fn count(&self, name: &str, start: u64, end: u64) -> usize {
+        let times = match self.data.get(name) {
+            Some(times) => times,
+            None => return 0,
+        };
+        let start = times.partition_point(|t| *t < start);
+        let end = times.partition_point(|t| *t < end);
+        end.saturating_sub(start)
+    }
+
+    /// Save the history, the expired visits are dropped. The caller should hold
+    /// the database lock.
+    pub fn save(mut self, cfg: &Config) -> Result<()> {
+        let expire = cfg.now().saturating_sub(Self::KEEP_DAYS * utils::DAY);
+        self.data.retain(|_, times| {
+            times.retain(|t| *t >= expire);
+            !times.is_empty()
+        });
+        let data = bincode::serialize(&self.data).context("encode visits")?;
+        utils::write_file_atomic(&self.path, &data)
+    }
+}
+
+#[cfg(test)]
+mod visits_tests {
+    use std::borrow::Cow;
+
+    use crate::config::config_tests;
+    use crate::repo::database::Database;
+    use crate::repo::visits::*;
+    use crate::repo::Repo;
+
+    #[test]
+    fn test_visits() {
+        let cfg = config_tests::load_test_config("visits");
+        let _ = fs::remove_file(cfg.get_meta_dir().join("visits"));
+        let now = cfg.now();
+
+        let mut visits = Visits::load(&cfg).unwrap();
+        visits.record(String::from("github:fioncat/roxide"), now);
+        visits.record(String::from("github:fioncat/roxide"), now - utils::DAY);
+        visits.record(String::from("github:fioncat/roxide"), now - 10);
+        visits.record(String::from("github:fioncat/csync"), now - 100 * utils::DAY);
+        visits.save(&cfg).unwrap();
+
+        let visits = Visits::load(&cfg).unwrap();
+        let name = "github:fioncat/roxide";
+        assert_eq!(visits.count(name, now - 10, now + 1), 2);
+        assert_eq!(visits.count(name, now - utils::DAY, now - 10), 1);
+        assert_eq!(visits.count(name, 0, u64::MAX), 3);
+        assert_eq!(visits.count("github:fioncat/csync", 0, u64::MAX), 0);
+        assert_eq!(visits.count("github:fioncat/unknown", 0, u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_database_visits() {
+        let cfg = config_tests::load_test_config("database_visits");
+        let _ = fs::remove_file(cfg.get_meta_dir().join("database"));
+        let _ = fs::remove_file(cfg.get_meta_dir().join("visits"));
+        let now = cfg.now();
+
+        let mut repo = Repo::new(
+            &cfg,
+            Cow::Borrowed("github"),
+            Cow::Borrowed("fioncat"),
+            Cow::Borrowed("roxide"),
+            None,
+        )
+        .unwrap();
+        let mut db = Database::load(&cfg).unwrap();
+        repo.last_accessed = now - 10;
+        db.upsert(repo.clone());
+        db.save().unwrap();
+
+        // Not visited, such as only the labels are changed.
+        let mut db = Database::load(&cfg).unwrap();
+        db.upsert(repo.clone());
+        db.save().unwrap();
+
+        let mut db = Database::load(&cfg).unwrap();
+        repo.last_accessed = now;
+        db.upsert(repo);
+        db.save().unwrap();
+
+        let visits = Visits::load(&cfg).unwrap();
+        assert_eq!(visits.count("github:fioncat/roxide", 0, u64::MAX), 2);
+        assert_eq!(visits.count("github:fioncat/roxide", now, now + 1), 1);
+    }
+}
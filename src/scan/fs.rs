@@ -0,0 +1,314 @@
+use std::ffi::{CString, OsStr, OsString};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// An opened directory. The sub directories are opened and the entries are
+/// stated relative to its handle (`openat`, `statx`), rather than resolving the
+/// full path each time, which matters on network filesystems (such as NFS).
+pub struct Dir {
+    fd: OwnedFd,
+    path: PathBuf,
+}
+
+/// The type of an entry, read from the directory itself (`d_type`) without an
+/// extra syscall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Dir,
+    File,
+    Symlink,
+    Other,
+}
+
+pub struct Entry {
+    pub name: OsString,
+    pub kind: EntryKind,
+}
+
+/// The metadata fields used by the scans, the symlinks are not followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stat {
+    pub size: u64,
+    /// The number of 512-byte blocks allocated.
+    pub blocks: u64,
+    pub dev: u64,
+    pub ino: u64,
+    pub nlink: u64,
+}
+
+/// The entry passed to the handler of [`crate::utils::walk_dir`], the metadata
+/// is only read when [`WalkEntry::stat`] is called.
+pub struct WalkEntry<'a> {
+    dir: &'a Dir,
+    name: &'a OsStr,
+    kind: EntryKind,
+}
+
+impl WalkEntry<'_> {
+    pub fn new<'a>(dir: &'a Dir, entry: &'a Entry) -> WalkEntry<'a> {
+        WalkEntry {
+            dir,
+            name: &entry.name,
+            kind: entry.kind,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == EntryKind::File
+    }
+
+    pub fn stat(&self) -> io::Result<Stat> {
+        self.dir.stat(self.name)
+    }
+}
+
+impl Dir {
+    pub fn open(path: &Path) -> io::Result<Dir> {
+        Self::open_at(libc::AT_FDCWD, path.as_os_str(), path.to_path_buf())
+    }
+
+    /// Open the sub directory relative to this one.
+    pub fn open_sub(&self, name: &OsStr) -> io::Result<Dir> {
+        Self::open_at(self.fd.as_raw_fd(), name, self.path.join(name))
+    }
+
+    fn open_at(dirfd: libc::c_int, name: &OsStr, path: PathBuf) -> io::Result<Dir> {
+        let name = Self::c_name(name)?;
+        let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+        let fd = unsafe { libc::openat(dirfd, name.as_ptr(), flags) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        Ok(Dir { fd, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read all the entries, without `.` and `..`.
+    #[cfg(target_os = "linux")]
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        // The glibc `readdir` reads 32KiB each time, use a larger buffer to
+        // reduce the round trips for large directories.
+        const BUFFER_SIZE: usize = 64 << 10;
+        // Use u64 to keep the records 8-byte aligned.
+        let mut buf: Vec<u64> = vec![0; BUFFER_SIZE / 8];
+
+        let mut entries = Vec::new();
+        loop {
+            let n = unsafe {
+                libc::syscall(
+                    libc::SYS_getdents64,
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr(),
+                    BUFFER_SIZE,
+                )
+            };
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if n == 0 {
+                return Ok(entries);
+            }
+            let data = unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, n as usize) };
+
+            // The record of `linux_dirent64`: d_ino (8), d_off (8), d_reclen (2),
+            // d_type (1), and the NUL-terminated d_name.
+            let mut offset = 0;
+            while offset < data.len() {
+                let reclen = u16::from_ne_bytes([data[offset + 16], data[offset + 17]]) as usize;
+                let d_type = data[offset + 18];
+                let name = &data[offset + 19..offset + reclen];
+                let name = match name.iter().position(|b| *b == 0) {
+                    Some(end) => &name[..end],
+                    None => name,
+                };
+                offset += reclen;
+                if name == b"." || name == b".." {
+                    continue;
+                }
+
+                let name = OsStr::from_bytes(name);
+                let kind = match d_type {
+                    libc::DT_DIR => EntryKind::Dir,
+                    libc::DT_REG => EntryKind::File,
+                    libc::DT_LNK => EntryKind::Symlink,
+                    libc::DT_UNKNOWN => self.kind(name)?,
+                    _ => EntryKind::Other,
+                };
+                entries.push(Entry {
+                    name: name.to_os_string(),
+                    kind,
+                });
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let kind = if file_type.is_dir() {
+                EntryKind::Dir
+            } else if file_type.is_file() {
+                EntryKind::File
+            } else if file_type.is_symlink() {
+                EntryKind::Symlink
+            } else {
+                EntryKind::Other
+            };
+            entries.push(Entry {
+                name: entry.file_name(),
+                kind,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Stat the entry. Only the needed fields are requested, and the cached
+    /// attributes are accepted, so that the network filesystems can skip the
+    /// round trip to the server.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    pub fn stat(&self, name: &OsStr) -> io::Result<Stat> {
+        let c_name = Self::c_name(name)?;
+        let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+        let flags = libc::AT_SYMLINK_NOFOLLOW | libc::AT_STATX_DONT_SYNC;
+        let mask = libc::STATX_TYPE
+            | libc::STATX_NLINK
+            | libc::STATX_INO
+            | libc::STATX_SIZE
+            | libc::STATX_BLOCKS;
+        let ret =
+            unsafe { libc::statx(self.fd.as_raw_fd(), c_name.as_ptr(), flags, mask, &mut stx) };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            // The kernel before 4.11 has no statx.
+            if err.raw_os_error() == Some(libc::ENOSYS) {
+                return self.fstatat(name).map(|(stat, _)| stat);
+            }
+            return Err(err);
+        }
+        Ok(Stat {
+            size: stx.stx_size,
+            blocks: stx.stx_blocks,
+            dev: libc::makedev(stx.stx_dev_major, stx.stx_dev_minor),
+            ino: stx.stx_ino,
+            nlink: stx.stx_nlink as u64,
+        })
+    }
+
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    pub fn stat(&self, name: &OsStr) -> io::Result<Stat> {
+        self.fstatat(name).map(|(stat, _)| stat)
+    }
+
+    /// Get the type of the entry whose `d_type` is unknown (some filesystems
+    /// do not fill it).
+    #[cfg(target_os = "linux")]
+    fn kind(&self, name: &OsStr) -> io::Result<EntryKind> {
+        let (_, mode) = self.fstatat(name)?;
+        Ok(match mode & libc::S_IFMT {
+            libc::S_IFDIR => EntryKind::Dir,
+            libc::S_IFREG => EntryKind::File,
+            libc::S_IFLNK => EntryKind::Symlink,
+            _ => EntryKind::Other,
+        })
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn fstatat(&self, name: &OsStr) -> io::Result<(Stat, libc::mode_t)> {
+        let c_name = Self::c_name(name)?;
+        let mut st: libc::stat = unsafe { std::mem::zeroed() };
+        let ret = unsafe {
+            libc::fstatat(
+                self.fd.as_raw_fd(),
+                c_name.as_ptr(),
+                &mut st,
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let stat = Stat {
+            size: st.st_size as u64,
+            blocks: st.st_blocks as u64,
+            dev: st.st_dev as u64,
+            ino: st.st_ino as u64,
+            nlink: st.st_nlink as u64,
+        };
+        Ok((stat, st.st_mode))
+    }
+
+    fn c_name(name: &OsStr) -> io::Result<CString> {
+        CString::new(name.as_bytes()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path contains an interior nul byte",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod fs_tests {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    use crate::scan::fs::*;
+
+    #[test]
+    fn test_dir() {
+        let root = PathBuf::from("/tmp/test-roxide/scan_fs");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("file"), "hello").unwrap();
+        std::os::unix::fs::symlink("sub", root.join("link")).unwrap();
+
+        let dir = Dir::open(&root).unwrap();
+        let mut entries: Vec<_> = dir
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.name.into_string().unwrap(), entry.kind))
+            .collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(
+            entries,
+            vec![
+                (String::from("file"), EntryKind::File),
+                (String::from("link"), EntryKind::Symlink),
+                (String::from("sub"), EntryKind::Dir),
+            ]
+        );
+
+        let stat = dir.stat(OsStr::new("file")).unwrap();
+        let meta = fs::symlink_metadata(root.join("file")).unwrap();
+        assert_eq!(
+            stat,
+            Stat {
+                size: 5,
+                blocks: meta.blocks(),
+                dev: meta.dev(),
+                ino: meta.ino(),
+                nlink: 1,
+            }
+        );
+
+        let sub = dir.open_sub(OsStr::new("sub")).unwrap();
+        assert_eq!(sub.path(), root.join("sub"));
+        assert!(sub.entries().unwrap().is_empty());
+        // The symlinks are not followed.
+        assert!(dir.open_sub(OsStr::new("link")).is_err());
+    }
+}
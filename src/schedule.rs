@@ -0,0 +1,194 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, Timelike};
+
+/// A cron-like schedule, in the format of `minute hour day month weekday`, such
+/// as `0 3 * * *` (at 03:00 every day), `*/15 9-18 * * 1-5` (every 15 minutes
+/// during working hours). Each field is a list of `*`, `n`, `a-b`, with an
+/// optional step `/n`. The weekday is `0-7`, both 0 and 7 are Sunday.
+///
+/// The macros `@hourly`, `@daily` (`@midnight`), `@weekly`, `@monthly` and
+/// `@yearly` are supported as well.
+///
+/// Same as cron, when both the day and weekday are restricted (not `*`), the
+/// time matches if either of them matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    pub fn parse(s: &str) -> Result<Schedule> {
+        let s = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            s => s,
+        };
+        let fields: Vec<_> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            bail!("invalid schedule '{s}', expect 5 fields: minute hour day month weekday");
+        }
+
+        let mut weekdays = Self::parse_field(fields[4], 0, 7).context("parse weekday")?;
+        // Both 0 and 7 are Sunday.
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Schedule {
+            minutes: Self::parse_field(fields[0], 0, 59).context("parse minute")?,
+            hours: Self::parse_field(fields[1], 0, 23).context("parse hour")?,
+            days: Self::parse_field(fields[2], 1, 31).context("parse day")?,
+            months: Self::parse_field(fields[3], 1, 12).context("parse month")?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    /// Parse the field to a bitset, the bit `n` is set if `n` matches.
+    fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+        let mut bits = 0;
+        for item in field.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = match step.parse() {
+                        Ok(step) if step > 0 => step,
+                        _ => bail!("invalid step '{step}'"),
+                    };
+                    (range, step)
+                }
+                None => (item, 1),
+            };
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                (
+                    Self::parse_value(start, min, max)?,
+                    Self::parse_value(end, min, max)?,
+                )
+            } else {
+                let value = Self::parse_value(range, min, max)?;
+                // Such as `5/10`, means from 5 to the max, every 10.
+                let end = if step > 1 { max } else { value };
+                (value, end)
+            };
+            if start > end {
+                bail!("invalid range '{range}'");
+            }
+            for value in (start..=end).step_by(step as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(bits)
+    }
+
+    fn parse_value(s: &str, min: u32, max: u32) -> Result<u32> {
+        match s.parse() {
+            Ok(value) if (min..=max).contains(&value) => Ok(value),
+            _ => bail!("invalid value '{s}', should be in {min}-{max}"),
+        }
+    }
+
+    pub fn matches(&self, time: &DateTime<Local>) -> bool {
+        let has = |bits: u64, value: u32| bits & (1 << value) != 0;
+        if !has(self.minutes, time.minute())
+            || !has(self.hours, time.hour())
+            || !has(self.months, time.month())
+        {
+            return false;
+        }
+
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// Return the next matched time (in minutes) after the given time, [`None`]
+    /// if there is no match in the next year (such as `0 0 31 2 *`).
+    pub fn next_after(&self, time: &DateTime<Local>) -> Option<DateTime<Local>> {
+        const MAX_MINUTES: i64 = 366 * 24 * 60;
+        let mut next = time.with_second(0)?.with_nanosecond(0)?;
+        for _ in 0..MAX_MINUTES {
+            next += Duration::minutes(1);
+            if self.matches(&next) {
+                return Some(next);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod schedule_tests {
+    use chrono::TimeZone;
+
+    use crate::schedule::*;
+
+    fn time(s: &str) -> DateTime<Local> {
+        let time = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).unwrap()
+    }
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            // 2024-01-01 is Monday.
+            ("0 3 * * *", "2024-01-01 03:00", true),
+            ("0 3 * * *", "2024-01-01 03:01", false),
+            ("@daily", "2024-01-01 00:00", true),
+            ("*/15 9-18 * * 1-5", "2024-01-01 09:45", true),
+            ("*/15 9-18 * * 1-5", "2024-01-06 09:45", false),
+            ("0 0 * * 7", "2024-01-07 00:00", true),
+            ("0 0 * * 0", "2024-01-07 00:00", true),
+            ("5/20 * * * *", "2024-01-01 10:45", true),
+            ("5/20 * * * *", "2024-01-01 10:05", true),
+            ("5/20 * * * *", "2024-01-01 10:20", false),
+            // Either the day or the weekday matches.
+            ("0 0 15 * 1", "2024-01-15 00:00", true),
+            ("0 0 15 * 1", "2024-01-08 00:00", true),
+            ("0 0 15 * 1", "2024-01-09 00:00", false),
+            ("0,30 12 1 1,7 *", "2024-07-01 12:30", true),
+        ];
+        for (schedule, t, expect) in cases {
+            let schedule = Schedule::parse(schedule).unwrap();
+            assert_eq!(schedule.matches(&time(t)), expect, "{schedule:?} {t}");
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for s in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(Schedule::parse(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_next_after() {
+        let schedule = Schedule::parse("30 2 * * 0").unwrap();
+        assert_eq!(
+            schedule.next_after(&time("2024-01-01 10:00")),
+            Some(time("2024-01-07 02:30"))
+        );
+        let schedule = Schedule::parse("0 0 31 2 *").unwrap();
+        assert_eq!(schedule.next_after(&time("2024-01-01 10:00")), None);
+    }
+}
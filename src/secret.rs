@@ -3,6 +3,7 @@ use std::io;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::os::unix::fs::{FileExt, MetadataExt};
 use std::path::Path;
+use std::sync::Mutex;
 
 use aes_gcm::aead::rand_core::RngCore;
 use aes_gcm::aead::{Aead, Nonce, OsRng};
@@ -26,7 +27,8 @@ const HEADER_LENGTH: usize = SALT_LENGTH + NONCE_LENGTH;
 const SECRET_BEGIN_LINE: &str = "-----BEGIN ROXIDE SECRET-----";
 const SECRET_END_LINE: &str = "-----END ROXIDE SECRET-----";
 
-const PBKDF2_ROUNDS: u32 = 600_000;
+// The key derivation is slow by design, use fewer rounds in tests.
+const PBKDF2_ROUNDS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
 
 struct StdoutWrap {
     stdout: io::Stdout,
@@ -36,7 +38,7 @@ impl Write for StdoutWrap {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match String::from_utf8(buf.to_vec()) {
             Ok(s) => s,
-            Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "the plain data is not utf-8 encoded (maybe a binary file), please consider save data to file by using `-f` flag or pipe")),
+            Err(_) => return Err(io::Error::other("the plain data is not utf-8 encoded (maybe a binary file), please consider save data to file by using `-f` flag or pipe")),
         };
         self.stdout.write(buf)
     }
@@ -150,18 +152,7 @@ pub fn handle<P: AsRef<Path>>(
         }
     };
 
-    let mut head_buffer: [u8; SECRET_BEGIN_LINE.len()] = [0; SECRET_BEGIN_LINE.len()];
-    let read_count = src
-        .read_at(&mut head_buffer, 0)
-        .context("read head from src file")?;
-    let mut is_encrypt = true;
-    if read_count == SECRET_BEGIN_LINE.len() {
-        if let Ok(head) = String::from_utf8(head_buffer.to_vec()) {
-            if head == SECRET_BEGIN_LINE {
-                is_encrypt = false;
-            }
-        }
-    }
+    let is_encrypt = !has_secret_head(&src).context("read head from src file")?;
     let password = password
         .map(|s| Ok(s.to_string()))
         .unwrap_or(term::input_password(is_encrypt))?;
@@ -172,7 +163,7 @@ pub fn handle<P: AsRef<Path>>(
         Box::new(ProgressReader::new(
             "Processing".to_string(),
             "Process".to_string(),
-            src_meta.len() as usize,
+            src_meta.len(),
             src,
         ))
     } else {
@@ -186,6 +177,87 @@ pub fn handle<P: AsRef<Path>>(
     }
 }
 
+/// Return `true` if the file is encrypted by [`handle`], that is, it starts with
+/// the secret begin line.
+pub fn is_secret(path: &Path) -> Result<bool> {
+    let file = File::open(path).with_context(|| format!("open file '{}'", path.display()))?;
+    has_secret_head(&file).with_context(|| format!("read head of '{}'", path.display()))
+}
+
+fn has_secret_head(file: &File) -> io::Result<bool> {
+    let mut head_buffer: [u8; SECRET_BEGIN_LINE.len()] = [0; SECRET_BEGIN_LINE.len()];
+    let read_count = file.read_at(&mut head_buffer, 0)?;
+    Ok(read_count == SECRET_BEGIN_LINE.len() && head_buffer == SECRET_BEGIN_LINE.as_bytes())
+}
+
+/// The password inputted for [`read_secret`] (and the other in-memory secrets),
+/// reused for the later ones in the same process, so that the user only needs
+/// to input it once.
+static SECRET_PASSWORD: Mutex<Option<String>> = Mutex::new(None);
+
+/// Call `f` with the cached password, or input it if there is no one. The
+/// password is only cached when `f` succeeds, so that a wrong one is not kept.
+fn with_password<T, F>(confirm: bool, f: F) -> Result<T>
+where
+    F: FnOnce(&str) -> Result<T>,
+{
+    let mut password = SECRET_PASSWORD.lock().unwrap();
+    let input = match password.as_ref() {
+        Some(password) => password.clone(),
+        None => term::input_password(confirm)?,
+    };
+    let result = f(&input)?;
+    *password = Some(input);
+    Ok(result)
+}
+
+/// Set the cached password, so that tests won't prompt for it.
+#[cfg(test)]
+pub fn set_password(password: &str) {
+    *SECRET_PASSWORD.lock().unwrap() = Some(password.to_string());
+}
+
+/// Decrypt the secret file (encrypted by [`handle`]) into memory, the content
+/// must be utf-8 encoded. This is used to inject the secrets into workflows,
+/// the plain content is never written to disk.
+pub fn read_secret(path: &Path) -> Result<String> {
+    with_password(false, |password| {
+        let src =
+            File::open(path).with_context(|| format!("open secret file '{}'", path.display()))?;
+        let mut plain = Vec::new();
+        decrypt(src, &mut plain, password)
+            .with_context(|| format!("decrypt secret file '{}'", path.display()))?;
+        match String::from_utf8(plain) {
+            Ok(plain) => Ok(plain),
+            Err(_) => bail!("the secret file '{}' is not utf-8 encoded", path.display()),
+        }
+    })
+}
+
+/// Encrypt the data in memory, in the same format as [`handle`], with the
+/// password shared with [`read_secret`].
+pub fn encrypt_data(plain: &[u8]) -> Result<Vec<u8>> {
+    with_password(true, |password| {
+        let mut data = Vec::new();
+        encrypt(plain, &mut data, password)?;
+        Ok(data)
+    })
+}
+
+/// Decrypt the data encrypted by [`encrypt_data`].
+pub fn decrypt_data(data: &[u8]) -> Result<Vec<u8>> {
+    with_password(false, |password| {
+        let mut plain = Vec::new();
+        decrypt(data, &mut plain, password)?;
+        Ok(plain)
+    })
+}
+
+/// Return `true` if the data is encrypted, see [`is_secret`].
+pub fn is_secret_data(data: &[u8]) -> bool {
+    data.starts_with(SECRET_BEGIN_LINE.as_bytes())
+}
+
 /// See: [`handle`].
 fn encrypt<R, W, S>(plain: R, mut dest: W, password: S) -> Result<()>
 where
@@ -1,3 +1,8 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
 use console::style;
 use pad::PadStr;
 
@@ -17,19 +22,117 @@ pub enum TableCellColor {
 pub struct TableCell {
     pub text: String,
     pub color: Option<TableCellColor>,
+
+    /// The key used to sort rows by this cell, if not set, the text will be
+    /// used. This is useful when the text is formatted from a number, such
+    /// as size or time.
+    pub sort_key: Option<u64>,
 }
 
 impl TableCell {
     pub fn no_color(text: String) -> Self {
-        Self { text, color: None }
+        Self {
+            text,
+            color: None,
+            sort_key: None,
+        }
     }
 
     pub fn with_color(text: String, color: TableCellColor) -> Self {
         Self {
             text,
             color: Some(color),
+            sort_key: None,
         }
     }
+
+    pub fn with_sort_key(mut self, key: u64) -> Self {
+        self.sort_key = Some(key);
+        self
+    }
+
+    fn compare(&self, other: &TableCell) -> Ordering {
+        if let (Some(key), Some(other_key)) = (self.sort_key, other.sort_key) {
+            return key.cmp(&other_key);
+        }
+        if let (Ok(num), Ok(other_num)) = (self.text.parse::<i64>(), other.text.parse::<i64>()) {
+            return num.cmp(&other_num);
+        }
+        self.text.cmp(&other.text)
+    }
+}
+
+/// The format to output a table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum TableOutput {
+    /// Show a human-readable table in the terminal.
+    #[default]
+    Table,
+
+    /// Print the rows (without the foot) as CSV to stdout, so that they can
+    /// be imported into spreadsheets.
+    Csv,
+}
+
+/// The common flags for commands that show a table, to tailor the output
+/// without post-processing.
+#[derive(Args, Clone, Default)]
+pub struct TableArgs {
+    /// The comma-separated columns to show (case-insensitive), for example,
+    /// "name,access,size". Some columns are hidden by default and can only be
+    /// shown through this.
+    #[clap(long)]
+    pub columns: Option<String>,
+
+    /// Sort rows by the column, use "column:desc" to sort in descending order.
+    #[clap(long)]
+    pub sort: Option<String>,
+
+    /// The output format.
+    #[clap(long, value_enum, default_value_t = TableOutput::Table)]
+    pub output: TableOutput,
+}
+
+impl TableArgs {
+    /// Return the columns to show, `defaults` is used when `--columns` is not
+    /// provided. All the names are in lowercase.
+    pub fn columns(&self, defaults: &[&str]) -> Vec<String> {
+        match self.columns.as_ref() {
+            Some(columns) => columns
+                .split(',')
+                .map(|column| column.trim().to_lowercase())
+                .filter(|column| !column.is_empty())
+                .collect(),
+            None => defaults
+                .iter()
+                .map(|column| column.to_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Return `true` if the column will be shown or used to sort. Use this to
+    /// skip computing expensive hidden columns.
+    pub fn need_column(&self, defaults: &[&str], name: &str) -> bool {
+        let name = name.to_lowercase();
+        if self.columns(defaults).contains(&name) {
+            return true;
+        }
+        matches!(self.parse_sort(), Ok(Some((column, _))) if column == name)
+    }
+
+    fn parse_sort(&self) -> Result<Option<(String, bool)>> {
+        let sort = match self.sort.as_ref() {
+            Some(sort) => sort.trim(),
+            None => return Ok(None),
+        };
+        let (column, desc) = match sort.split_once(':') {
+            Some((column, "desc")) => (column, true),
+            Some((column, "asc")) => (column, false),
+            Some((_, order)) => bail!("invalid sort order '{order}', should be 'asc' or 'desc'"),
+            None => (sort, false),
+        };
+        Ok(Some((column.to_lowercase(), desc)))
+    }
 }
 
 impl Table {
@@ -63,6 +166,110 @@ impl Table {
         self.rows.push(row);
     }
 
+    /// Sort the rows and select the columns according to `args`, and then show
+    /// the table. The first row is treated as the titles, which are matched
+    /// against the column names case-insensitively.
+    pub fn show_with(mut self, args: &TableArgs, defaults: &[&str]) -> Result<()> {
+        self.tailor(args, defaults)?;
+        self.show_as(args.output);
+        Ok(())
+    }
+
+    pub fn show_as(self, output: TableOutput) {
+        match output {
+            TableOutput::Table => self.show(),
+            TableOutput::Csv => print!("{}", self.to_csv()),
+        }
+    }
+
+    /// Render the table as CSV (RFC 4180), the foot rows are omitted. The
+    /// surrounding whitespaces of the cells are only for display, so they are
+    /// trimmed.
+    pub fn to_csv(&self) -> String {
+        let end = if self.foot_index > 0 {
+            self.foot_index
+        } else {
+            self.rows.len()
+        };
+        let mut csv = String::new();
+        for row in self.rows[..end].iter() {
+            let fields: Vec<_> = row
+                .iter()
+                .map(|cell| Self::csv_escape(cell.text.trim()))
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+
+    fn csv_escape(text: &str) -> Cow<'_, str> {
+        if text.contains([',', '"', '\n', '\r']) {
+            Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    fn tailor(&mut self, args: &TableArgs, defaults: &[&str]) -> Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let titles: Vec<String> = self.rows[0]
+            .iter()
+            .map(|cell| cell.text.to_lowercase())
+            .collect();
+        let find_column = |name: &str| -> Result<usize> {
+            match titles.iter().position(|title| title == name) {
+                Some(idx) => Ok(idx),
+                None => bail!("unknown column '{name}', available: {}", titles.join(", ")),
+            }
+        };
+
+        if let Some((column, desc)) = args.parse_sort()? {
+            let idx = find_column(&column)?;
+            let end = if self.foot_index > 0 {
+                self.foot_index
+            } else {
+                self.rows.len()
+            };
+            self.rows[1..end].sort_by(|row1, row2| {
+                let order = row1[idx].compare(&row2[idx]);
+                if desc {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+        }
+
+        let columns = args.columns(defaults);
+        if columns.is_empty() {
+            bail!("no column to show");
+        }
+        let mut indexes = Vec::with_capacity(columns.len());
+        for column in columns.iter() {
+            indexes.push(find_column(column)?);
+        }
+
+        let rows = std::mem::take(&mut self.rows);
+        for row in rows {
+            let mut row: Vec<Option<TableCell>> = row.into_iter().map(Some).collect();
+            let row = indexes
+                .iter()
+                .map(|idx| {
+                    row[*idx]
+                        .take()
+                        .unwrap_or_else(|| TableCell::no_color(String::new()))
+                })
+                .collect();
+            self.rows.push(row);
+        }
+        self.ncol = indexes.len();
+        Ok(())
+    }
+
     pub fn show(self) {
         let mut pads = Vec::with_capacity(self.ncol);
         for coli in 0..self.ncol {
@@ -115,3 +322,101 @@ impl Table {
         eprintln!("{split}");
     }
 }
+
+#[cfg(test)]
+mod table_tests {
+    use crate::table::*;
+
+    fn build_table() -> Table {
+        let mut table = Table::with_capacity(5);
+        table.add(vec![
+            String::from("Name"),
+            String::from("Count"),
+            String::from("Size"),
+        ]);
+        for (name, count, size) in [("b", "10", 2048), ("a", "9", 10), ("c", "100", 4096)] {
+            table.add_color(vec![
+                TableCell::no_color(String::from(name)),
+                TableCell::no_color(String::from(count)),
+                TableCell::no_color(format!("{size}B")).with_sort_key(size),
+            ]);
+        }
+        table.foot();
+        table.add(vec![
+            String::from("SUM"),
+            String::from("119"),
+            String::new(),
+        ]);
+        table
+    }
+
+    fn table_texts(table: &Table) -> Vec<Vec<&str>> {
+        table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_tailor() {
+        let mut table = build_table();
+        let args = TableArgs {
+            columns: Some(String::from("size, NAME")),
+            sort: Some(String::from("count:desc")),
+            ..Default::default()
+        };
+        table.tailor(&args, &[]).unwrap();
+        assert_eq!(
+            table_texts(&table),
+            vec![
+                vec!["Size", "Name"],
+                vec!["4096B", "c"],
+                vec!["2048B", "b"],
+                vec!["10B", "a"],
+                vec!["", "SUM"],
+            ]
+        );
+
+        let mut table = build_table();
+        let args = TableArgs {
+            columns: None,
+            sort: Some(String::from("size")),
+            ..Default::default()
+        };
+        assert!(args.need_column(&["name"], "Size"));
+        assert!(!args.need_column(&["name"], "Count"));
+        table.tailor(&args, &["name"]).unwrap();
+        assert_eq!(
+            table_texts(&table),
+            vec![vec!["Name"], vec!["a"], vec!["b"], vec!["c"], vec!["SUM"]]
+        );
+
+        let args = TableArgs {
+            columns: Some(String::from("unknown")),
+            ..Default::default()
+        };
+        assert!(build_table().tailor(&args, &[]).is_err());
+
+        let args = TableArgs {
+            columns: None,
+            sort: Some(String::from("name:up")),
+            ..Default::default()
+        };
+        assert!(build_table().tailor(&args, &["name"]).is_err());
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut table = build_table();
+        table.rows[1][0].text = String::from("b, \"quoted\"  ");
+        table.rows[2][0].text = String::from("multi\nline");
+        assert_eq!(
+            table.to_csv(),
+            "Name,Count,Size\r\n\
+             \"b, \"\"quoted\"\"\",10,2048B\r\n\
+             \"multi\nline\",9,10B\r\n\
+             c,100,4096B\r\n"
+        );
+    }
+}
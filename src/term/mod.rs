@@ -1,5 +1,8 @@
+pub mod select;
+
 use std::env;
 use std::fs;
+use std::io::{self, Write};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
@@ -12,9 +15,10 @@ use serde::Serialize;
 use serde_json::ser::PrettyFormatter;
 use serde_json::Serializer;
 
-use crate::config::Config;
-use crate::errors::SilentExit;
+use crate::config::{Config, ProtectPolicy};
+use crate::errors::{ErrorKind, SilentExit};
 use crate::exec::Cmd;
+use crate::log::{self, Level};
 use crate::utils;
 
 /// The macro for [`must_confirm`].
@@ -104,6 +108,54 @@ macro_rules! error {
     };
 }
 
+/// The macro for [`show_debug`] at the `debug` level.
+///
+/// # Examples
+///
+/// ```
+/// debug!("Request url {}", url);
+/// ```
+#[macro_export]
+macro_rules! debug {
+    ($dst:expr $(,)?) => {
+        {
+            $crate::term::show_debug($crate::log::Level::Debug, $dst);
+        }
+    };
+    ($fmt:expr, $($arg:tt)*) => {
+        {
+            if $crate::log::enabled($crate::log::Level::Debug) {
+                let msg = format!($fmt, $($arg)*);
+                $crate::term::show_debug($crate::log::Level::Debug, msg.as_str());
+            }
+        }
+    };
+}
+
+/// The macro for [`show_debug`] at the `trace` level.
+///
+/// # Examples
+///
+/// ```
+/// trace!("Command output: {}", output);
+/// ```
+#[macro_export]
+macro_rules! trace {
+    ($dst:expr $(,)?) => {
+        {
+            $crate::term::show_debug($crate::log::Level::Trace, $dst);
+        }
+    };
+    ($fmt:expr, $($arg:tt)*) => {
+        {
+            if $crate::log::enabled($crate::log::Level::Trace) {
+                let msg = format!($fmt, $($arg)*);
+                $crate::term::show_debug($crate::log::Level::Trace, msg.as_str());
+            }
+        }
+    };
+}
+
 /// The macro for [`show_warn`].
 ///
 /// # Examples
@@ -129,21 +181,38 @@ macro_rules! warn {
 /// Display logs at the `exec` level.
 pub fn show_exec(msg: impl AsRef<str>) {
     eprintln!("{} {}", style("==>").cyan(), msg.as_ref());
+    log::record(Level::Info, msg.as_ref());
 }
 
 /// Display logs at the `info` level.
 pub fn show_info(msg: impl AsRef<str>) {
     eprintln!("{} {}", style("==>").green(), msg.as_ref());
+    log::record(Level::Info, msg.as_ref());
 }
 
 /// Display logs at the `error` level.
 pub fn show_error(msg: impl AsRef<str>) {
     eprintln!("{} {}", style("[ ERROR ]").red().bold(), msg.as_ref());
+    log::record(Level::Error, msg.as_ref());
 }
 
 /// Display logs at the `error` level.
 pub fn show_warn(msg: impl AsRef<str>) {
     eprintln!("{} {}", style("[ WARNING ]").yellow().bold(), msg.as_ref());
+    log::record(Level::Warn, msg.as_ref());
+}
+
+/// Display logs at the `debug` or `trace` level, they are only displayed with
+/// `-v` or `-vv`, but are always recorded to the log file if it is enabled.
+pub fn show_debug(level: Level, msg: impl AsRef<str>) {
+    if log::stderr_enabled(level) {
+        let prefix = match level {
+            Level::Trace => "[ TRACE ]",
+            _ => "[ DEBUG ]",
+        };
+        eprintln!("{} {}", style(prefix).dim(), msg.as_ref());
+    }
+    log::record(level, msg.as_ref());
 }
 
 /// Output the object in pretty JSON format in the terminal.
@@ -244,6 +313,27 @@ pub fn must_confirm(msg: impl AsRef<str>) -> Result<()> {
     Ok(())
 }
 
+/// Ask user to confirm a destructive operation on a protected repository, see
+/// [`ProtectPolicy`]. Unlike [`confirm`], this cannot be skipped by env
+/// `ROXIDE_NOCONFIRM`.
+pub fn must_confirm_protected(cfg: &Config, name: &str) -> Result<()> {
+    if let ProtectPolicy::Refuse = cfg.protect_policy {
+        return Err(
+            ErrorKind::UserAborted.error(format!("repo '{name}' is protected, refuse to continue"))
+        );
+    }
+    if cfg!(test) {
+        return Ok(());
+    }
+
+    let msg = format!("Repo '{name}' is protected, type its name to confirm");
+    let answer = input(msg, true, None)?;
+    if answer.trim() != name {
+        return Err(ErrorKind::UserAborted.error("the input does not match the repo name, abort"));
+    }
+    Ok(())
+}
+
 /// Ask user to confirm operation. Display multiple items.
 pub fn confirm_items(
     items: &[String],
@@ -368,6 +458,16 @@ pub fn input_password(confirm: bool) -> Result<String> {
     Ok(password)
 }
 
+/// Ask user to input a secret (such as token) in tty, the input is hidden.
+pub fn input_secret(msg: impl AsRef<str>) -> Result<String> {
+    let msg = format!("{} {}: ", style("::").bold().magenta(), msg.as_ref());
+    let secret = rpassword::prompt_password(msg).context("input secret from tty")?;
+    if secret.is_empty() {
+        bail!("input cannot be empty");
+    }
+    Ok(secret)
+}
+
 /// Ask user to edit content in editor.
 pub fn edit_content<S>(cfg: &Config, raw: S, name: S, require: bool) -> Result<String>
 where
@@ -423,6 +523,35 @@ pub fn edit_file(editor: impl AsRef<str>, path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// Show the content in pager, from env `$PAGER`, default is `less -R`.
+pub fn page(content: &[u8]) -> Result<()> {
+    let pager = match env::var("PAGER") {
+        Ok(pager) if !pager.is_empty() => pager,
+        _ => String::from("less -R"),
+    };
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", pager.as_str()]);
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+    cmd.stdin(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("launch pager `{pager}`"))?;
+    let mut stdin = child.stdin.take().unwrap();
+    // The pager might exit before reading all content (for example, user quits
+    // `less` early), the broken pipe error should be ignored.
+    if let Err(err) = stdin.write_all(content) {
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            return Err(err).context("write content to pager");
+        }
+    }
+    drop(stdin);
+
+    child.wait().context("wait pager")?;
+    Ok(())
+}
+
 /// Get default editor, from env `$EDITOR`.
 pub fn get_editor() -> Result<String> {
     let editor = env::var("EDITOR").context("get $EDITOR env")?;
@@ -0,0 +1,289 @@
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+
+use anyhow::{bail, Context, Result};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::IntoAlternateScreen;
+use termion::{clear, color, cursor, style};
+
+use crate::errors::SilentExit;
+
+/// The builtin fuzzy finder, used when `fzf` is not installed, so that roxide
+/// works out of the box on minimal machines. It reads keys from and draws to
+/// `/dev/tty` (in the alternate screen), so the stdout can still be captured,
+/// the same as `fzf`. Return the index of the selected item.
+///
+/// Keys: type to filter, `Up`/`Ctrl-P`/`Ctrl-K` and `Down`/`Ctrl-N`/`Ctrl-J` to
+/// move, `Ctrl-U` to clear the query, `Enter` to select, `Esc`/`Ctrl-C` to
+/// cancel.
+pub fn fuzzy_select<S>(keys: &[S]) -> Result<usize>
+where
+    S: AsRef<str>,
+{
+    let tty = termion::get_tty().context("open tty for fuzzy select")?;
+    let input = tty.try_clone().context("clone tty for fuzzy select")?;
+    let (width, height) = window_size(&tty);
+    let mut output = tty
+        .into_raw_mode()
+        .context("enable raw mode")?
+        .into_alternate_screen()
+        .context("enter alternate screen")?;
+
+    let mut selector = Selector::new(keys, width, height);
+    selector.render(&mut output)?;
+    for key in input.keys() {
+        match key.context("read key from tty")? {
+            Key::Char('\n') => {
+                return match selector.selected() {
+                    Some(idx) => Ok(idx),
+                    None => bail!("fuzzy select no match found"),
+                };
+            }
+            Key::Esc | Key::Ctrl('c') | Key::Ctrl('g') => bail!(SilentExit { code: 130 }),
+            Key::Up | Key::Ctrl('p') | Key::Ctrl('k') => selector.move_up(),
+            Key::Down | Key::Ctrl('n') | Key::Ctrl('j') => selector.move_down(),
+            Key::Backspace | Key::Ctrl('h') => {
+                selector.query.pop();
+                selector.filter();
+            }
+            Key::Ctrl('u') => {
+                selector.query.clear();
+                selector.filter();
+            }
+            Key::Char(ch) if !ch.is_control() => {
+                selector.query.push(ch);
+                selector.filter();
+            }
+            _ => continue,
+        }
+        selector.render(&mut output)?;
+    }
+    bail!("tty is closed during fuzzy select")
+}
+
+/// Return the score of matching `query` against `key` as a subsequence, the
+/// higher the better, [`None`] if not matched. The consecutive matches and the
+/// matches at word starts get bonus, the gaps get penalty. The matching is
+/// case-insensitive unless the query contains uppercase letters (smart case).
+pub fn fuzzy_score(key: &str, query: &str) -> Option<i64> {
+    let case_sensitive = query.chars().any(|ch| ch.is_uppercase());
+    let normalize = |ch: char| {
+        if case_sensitive {
+            ch
+        } else {
+            ch.to_ascii_lowercase()
+        }
+    };
+
+    let mut query_chars = query.chars().map(normalize).peekable();
+    let mut score: i64 = 0;
+    let mut last_match: Option<usize> = None;
+    let mut prev_char: Option<char> = None;
+    for (idx, ch) in key.chars().enumerate() {
+        let want = match query_chars.peek() {
+            Some(want) => *want,
+            None => break,
+        };
+        if normalize(ch) == want {
+            score += 16;
+            match last_match {
+                Some(last) if last + 1 == idx => score += 24,
+                Some(last) => score -= (idx - last - 1).min(8) as i64,
+                None => score -= idx.min(8) as i64,
+            }
+            let word_start = match prev_char {
+                Some(prev) => matches!(prev, '/' | '-' | '_' | '.' | ':' | ' '),
+                None => true,
+            };
+            if word_start {
+                score += 20;
+            }
+            last_match = Some(idx);
+            query_chars.next();
+        }
+        prev_char = Some(ch);
+    }
+    if query_chars.peek().is_some() {
+        return None;
+    }
+    Some(score)
+}
+
+struct Selector<'a, S> {
+    keys: &'a [S],
+
+    query: String,
+
+    /// The indexes of the matched keys, the best first.
+    matches: Vec<usize>,
+
+    /// The position in `matches` of the selected item.
+    cursor: usize,
+    /// The position in `matches` of the first item on the screen.
+    offset: usize,
+
+    width: usize,
+    height: usize,
+}
+
+impl<'a, S> Selector<'a, S>
+where
+    S: AsRef<str>,
+{
+    /// The lines for the query and the counter.
+    const HEADER_LINES: usize = 2;
+
+    fn new(keys: &'a [S], width: usize, height: usize) -> Self {
+        let mut selector = Selector {
+            keys,
+            query: String::new(),
+            matches: vec![],
+            cursor: 0,
+            offset: 0,
+            width,
+            height,
+        };
+        selector.filter();
+        selector
+    }
+
+    fn filter(&mut self) {
+        self.cursor = 0;
+        self.offset = 0;
+        if self.query.is_empty() {
+            self.matches = (0..self.keys.len()).collect();
+            return;
+        }
+
+        let mut scores: Vec<_> = self
+            .keys
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, key)| {
+                let key = key.as_ref();
+                fuzzy_score(key, &self.query).map(|score| (idx, key.len(), score))
+            })
+            .collect();
+        // For the same score, the shorter first, then keep the original order.
+        scores.sort_by(|(idx0, len0, score0), (idx1, len1, score1)| {
+            score1
+                .cmp(score0)
+                .then_with(|| len0.cmp(len1))
+                .then_with(|| idx0.cmp(idx1))
+        });
+        self.matches = scores.into_iter().map(|(idx, _, _)| idx).collect();
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.matches.get(self.cursor).copied()
+    }
+
+    fn page_size(&self) -> usize {
+        self.height.saturating_sub(Self::HEADER_LINES).max(1)
+    }
+
+    fn move_up(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        self.cursor -= 1;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        }
+    }
+
+    fn move_down(&mut self) {
+        if self.cursor + 1 >= self.matches.len() {
+            return;
+        }
+        self.cursor += 1;
+        let page_size = self.page_size();
+        if self.cursor >= self.offset + page_size {
+            self.offset = self.cursor + 1 - page_size;
+        }
+    }
+
+    fn render(&self, out: &mut impl Write) -> Result<()> {
+        write!(out, "{}{}", clear::All, cursor::Goto(1, 1))?;
+        write!(out, "> {}\r\n", self.query)?;
+        write!(
+            out,
+            "{}  {}/{}{}\r\n",
+            color::Fg(color::LightBlack),
+            self.matches.len(),
+            self.keys.len(),
+            color::Fg(color::Reset)
+        )?;
+
+        let max_width = self.width.saturating_sub(2);
+        let end = (self.offset + self.page_size()).min(self.matches.len());
+        for (pos, idx) in self.matches[self.offset..end].iter().enumerate() {
+            let key: String = self.keys[*idx].as_ref().chars().take(max_width).collect();
+            if self.offset + pos == self.cursor {
+                write!(
+                    out,
+                    "{}{}> {}{}{}",
+                    style::Bold,
+                    color::Fg(color::Cyan),
+                    key,
+                    color::Fg(color::Reset),
+                    style::Reset
+                )?;
+            } else {
+                write!(out, "  {key}")?;
+            }
+            if pos + 1 < end - self.offset {
+                write!(out, "\r\n")?;
+            }
+        }
+
+        let query_width = 3 + self.query.chars().count() as u16;
+        write!(out, "{}", cursor::Goto(query_width, 1))?;
+        out.flush().context("flush tty")
+    }
+}
+
+/// Return the (width, height) of the tty, the stdout may be redirected so it
+/// cannot be used here.
+fn window_size(tty: &File) -> (usize, usize) {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+    if ret != 0 || size.ws_col == 0 || size.ws_row == 0 {
+        return (80, 24);
+    }
+    (size.ws_col as usize, size.ws_row as usize)
+}
+
+#[cfg(test)]
+mod select_tests {
+    use crate::term::select::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("fioncat/roxide", ""), Some(0));
+        assert!(fuzzy_score("fioncat/roxide", "rox").is_some());
+        assert!(fuzzy_score("fioncat/roxide", "frx").is_some());
+        assert!(fuzzy_score("fioncat/roxide", "xor").is_none());
+        // Smart case.
+        assert!(fuzzy_score("fioncat/roxide", "ROX").is_none());
+        assert!(fuzzy_score("fioncat/Roxide", "Rox").is_some());
+
+        // The consecutive and word start matches are better.
+        let cases = [
+            ("rox", "fioncat/roxide", "fioncat/rust-open-x"),
+            ("rox", "roxide", "my-roxide"),
+            ("vim", "vim-config", "v-i-m"),
+        ];
+        for (query, better, worse) in cases {
+            let better_score = fuzzy_score(better, query).unwrap();
+            let worse_score = fuzzy_score(worse, query).unwrap();
+            assert!(
+                better_score > worse_score,
+                "{query}: {better} {better_score}, {worse} {worse_score}"
+            );
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use console::style;
+
+use crate::utils;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The measured phases, in the order of their first occurrence.
+static PHASES: Mutex<Vec<Phase>> = Mutex::new(Vec::new());
+
+struct Phase {
+    name: &'static str,
+    count: u32,
+    total: Duration,
+}
+
+/// Enable the timings measuring, see `--timings`. The spans created before
+/// this are not recorded.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Measure a phase, such as `git` and `api`, from now to the drop of the
+/// returned span. This does nothing if the timings is not enabled, so it is
+/// cheap to place spans in the hot paths.
+///
+/// The phases run concurrently (such as in batch tasks) are summed up, so their
+/// total can exceed the command's wall time.
+pub fn span(name: &'static str) -> Span {
+    let start = if is_enabled() {
+        Some(Instant::now())
+    } else {
+        None
+    };
+    Span { name, start }
+}
+
+pub struct Span {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            add(self.name, start.elapsed());
+        }
+    }
+}
+
+fn add(name: &'static str, elapsed: Duration) {
+    let mut phases = match PHASES.lock() {
+        Ok(phases) => phases,
+        Err(_) => return,
+    };
+    match phases.iter_mut().find(|phase| phase.name == name) {
+        Some(phase) => {
+            phase.count += 1;
+            phase.total += elapsed;
+        }
+        None => phases.push(Phase {
+            name,
+            count: 1,
+            total: elapsed,
+        }),
+    }
+}
+
+/// Print the breakdown of the phases to stderr, `total` is the wall time of the
+/// whole command. This does nothing if the timings is not enabled.
+pub fn show(total: Duration) {
+    if !is_enabled() {
+        return;
+    }
+    let phases = match PHASES.lock() {
+        Ok(phases) => phases,
+        Err(_) => return,
+    };
+    let name_width = phases
+        .iter()
+        .map(|phase| phase.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("total".len());
+
+    eprintln!();
+    eprintln!("{}", style("Timings:").bold());
+    for phase in phases.iter() {
+        eprintln!(
+            "  {:<name_width$}  {:>10}  {}",
+            phase.name,
+            utils::format_elapsed(phase.total),
+            style(format!("x{}", phase.count)).dim()
+        );
+    }
+    eprintln!(
+        "  {:<name_width$}  {:>10}",
+        style("total").bold(),
+        utils::format_elapsed(total)
+    );
+}
+
+#[cfg(test)]
+mod timings_tests {
+    use crate::timings::*;
+
+    #[test]
+    fn test_span() {
+        // Not enabled, nothing recorded.
+        drop(span("test_disabled"));
+
+        enable();
+        for _ in 0..3 {
+            let _span = span("test_enabled");
+        }
+
+        let phases = PHASES.lock().unwrap();
+        assert!(!phases.iter().any(|phase| phase.name == "test_disabled"));
+        let phase = phases
+            .iter()
+            .find(|phase| phase.name == "test_enabled")
+            .unwrap();
+        assert_eq!(phase.count, 3);
+    }
+}
@@ -1,16 +1,25 @@
 use std::collections::HashSet;
+use std::ffi::OsString;
 use std::io::{self, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::time::Duration;
 use std::{env, fs};
 
 use anyhow::{bail, Context, Result};
 use chrono::{Local, LocalResult, TimeZone};
 use console::{self, style};
+use glob::Pattern as GlobPattern;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
 use crate::info;
+use crate::interrupt;
+use crate::progress;
+use crate::scan::fs::{Dir, EntryKind, WalkEntry};
+use crate::timings;
 
 #[cfg(test)]
 #[macro_export]
@@ -109,6 +118,48 @@ pub fn write_file(path: &PathBuf, data: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Like [`write_file`], but write the content to a temporary file and then
+/// rename it to the path, so that the readers see either the old or the new
+/// content, never a partially written one.
+pub fn write_file_atomic(path: &PathBuf, data: &[u8]) -> Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".tmp.{}", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+    write_file(&tmp_path, data)?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("rename '{}' to '{}'", tmp_path.display(), path.display()))
+}
+
+/// Like [`write_file_atomic`], but the file is only readable and writable by
+/// the owner (0600), for the files containing secrets such as tokens. The
+/// temporary file is created with the mode, so the content is never exposed
+/// with the default permissions.
+pub fn write_private_file(path: &PathBuf, data: &[u8]) -> Result<()> {
+    ensure_dir(path)?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".tmp.{}", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+    // The stale temporary file may have other permissions, the mode only
+    // applies to the new created file.
+    match fs::remove_file(&tmp_path) {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err).with_context(|| format!("remove file '{}'", tmp_path.display()))
+        }
+    }
+
+    let mut opts = fs::OpenOptions::new();
+    opts.create_new(true).write(true).mode(0o600);
+    let mut file = opts
+        .open(&tmp_path)
+        .with_context(|| format!("open file '{}'", tmp_path.display()))?;
+    file.write_all(data)
+        .with_context(|| format!("write file '{}'", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("rename '{}' to '{}'", tmp_path.display(), path.display()))
+}
+
 /// See: [`shellexpand::full`].
 pub fn expandenv(s: impl AsRef<str>) -> Result<String> {
     let s = shellexpand::full(s.as_ref())
@@ -266,47 +317,281 @@ pub fn parse_duration_secs(s: impl AsRef<str>) -> Result<u64> {
     Ok(secs)
 }
 
+/// Set the cpu and IO priority of the current thread to idle. The threads
+/// spawned later inherit it.
+pub fn set_idle_priority() -> Result<()> {
+    // The lowest nice value, see `man 2 setpriority`.
+    let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error()).context("set cpu priority");
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // See `man 2 ioprio_set`, glibc does not provide a wrapper for it.
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error()).context("set IO priority");
+        }
+    }
+
+    Ok(())
+}
+
 /// Recursively walk all entries in a directory and call the provided `handle`
-/// function for each entry.
+/// function for each entry, return `false` to skip walking into the directory.
+///
+/// The sub directories are opened relative to their parents, and the metadata
+/// is only read when the handle requests it (see [`WalkEntry::stat`]), most
+/// walks only need the entry types, which are read without extra syscalls.
 pub fn walk_dir<F>(root: PathBuf, mut handle: F) -> Result<()>
 where
-    F: FnMut(&PathBuf, fs::Metadata) -> Result<bool>,
+    F: FnMut(&PathBuf, &WalkEntry) -> Result<bool>,
 {
-    let mut stack = vec![root];
-    while let Some(dir) = stack.pop() {
-        let dir_read = match fs::read_dir(&dir) {
-            Ok(dir_read) => dir_read,
-            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
-            Err(err) => return Err(err).with_context(|| format!("read dir '{}'", dir.display())),
+    let root = match Dir::open(&root) {
+        Ok(dir) => dir,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("read dir '{}'", root.display())),
+    };
+    // The pending sub dirs with their parents, the parents are kept open until
+    // all their sub dirs are walked.
+    let mut stack: Vec<(Rc<Dir>, OsString)> = Vec::new();
+    let mut next_dir = Some(Rc::new(root));
+    loop {
+        let dir = match next_dir.take() {
+            Some(dir) => dir,
+            None => match stack.pop() {
+                Some((parent, name)) => match parent.open_sub(&name) {
+                    Ok(dir) => Rc::new(dir),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                    Err(err) => {
+                        let path = parent.path().join(&name);
+                        return Err(err).with_context(|| format!("read dir '{}'", path.display()));
+                    }
+                },
+                None => return Ok(()),
+            },
         };
-        for entry in dir_read.into_iter() {
-            let entry = entry.with_context(|| format!("read sub dir for '{}'", dir.display()))?;
-            let sub = dir.join(entry.file_name());
-            let meta = entry
-                .metadata()
-                .with_context(|| format!("read metadata for '{}'", sub.display()))?;
-            let is_dir = meta.is_dir();
-            let next = handle(&sub, meta)?;
-            if next && is_dir {
-                stack.push(sub);
+
+        let entries = dir
+            .entries()
+            .with_context(|| format!("read sub dir for '{}'", dir.path().display()))?;
+        for entry in entries {
+            let sub = dir.path().join(&entry.name);
+            let next = handle(&sub, &WalkEntry::new(&dir, &entry))?;
+            if next && entry.kind == EntryKind::Dir {
+                stack.push((Rc::clone(&dir), entry.name));
             }
         }
     }
-    Ok(())
 }
 
 /// Recursively traverse the entire directory and return the size of the entire
 /// directory.
 pub fn dir_size(dir: PathBuf) -> Result<u64> {
-    let mut total_size: u64 = 0;
-    walk_dir(dir, |_path, meta| {
-        if meta.is_file() {
-            total_size += meta.len();
+    Ok(dir_usage(dir, &[])?.size)
+}
+
+/// The disk usage of a directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DirUsage {
+    /// The apparent size, that is, the sum of the file lengths.
+    pub size: u64,
+
+    /// The size of the allocated blocks, this is smaller than `size` for sparse
+    /// files, and larger for lots of small files.
+    pub disk_size: u64,
+
+    /// The number of files.
+    pub files: u64,
+
+    /// The number of distinct inodes, including directories.
+    pub inodes: u64,
+}
+
+impl DirUsage {
+    pub fn add(&mut self, other: &DirUsage) {
+        self.size += other.size;
+        self.disk_size += other.disk_size;
+        self.files += other.files;
+        self.inodes += other.inodes;
+    }
+}
+
+/// Recursively traverse the directory and return its disk usage, skip the
+/// entries whose path (relative to `dir`) matches one of the `ignores`
+/// patterns. The hard links are counted only once.
+pub fn dir_usage(dir: PathBuf, ignores: &[GlobPattern]) -> Result<DirUsage> {
+    Ok(dir_usage_with(dir, ignores, false)?.usage)
+}
+
+/// An entry with more than one hard link, it is counted only once in a scan.
+/// The scans report them, so that the links shared across scans (such as the
+/// git objects of the local clones) can be deduplicated, see [`UsageScan`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HardLink {
+    pub dev: u64,
+    pub ino: u64,
+    pub size: u64,
+    pub disk_size: u64,
+    pub is_file: bool,
+}
+
+/// The result of scanning disk usage, see [`dir_usage_with`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UsageScan {
+    pub usage: DirUsage,
+
+    /// The mount points skipped by `one_file_system`.
+    pub mounts: Vec<PathBuf>,
+
+    /// The hard links counted in `usage`.
+    pub links: Vec<HardLink>,
+}
+
+impl UsageScan {
+    /// Sum up the usages, the hard links shared by multiple scans are counted
+    /// only once.
+    pub fn dedup_total<'a, I>(scans: I) -> DirUsage
+    where
+        I: IntoIterator<Item = &'a UsageScan>,
+    {
+        let mut total = DirUsage::default();
+        let mut links: HashSet<(u64, u64)> = HashSet::new();
+        for scan in scans {
+            total.add(&scan.usage);
+            for link in scan.links.iter() {
+                if links.insert((link.dev, link.ino)) {
+                    continue;
+                }
+                total.inodes -= 1;
+                if link.is_file {
+                    total.size -= link.size;
+                    total.disk_size -= link.disk_size;
+                    total.files -= 1;
+                }
+            }
+        }
+        total
+    }
+}
+
+/// The same as [`dir_usage`], if `one_file_system` is `true`, the directories
+/// on other devices than `dir` (the mount points) are skipped, and returned
+/// with the usage.
+pub fn dir_usage_with(
+    dir: PathBuf,
+    ignores: &[GlobPattern],
+    one_file_system: bool,
+) -> Result<UsageScan> {
+    let _span = timings::span("scan");
+    let mut scan = UsageScan::default();
+    let root_dev = match fs::metadata(&dir) {
+        Ok(meta) => meta.dev(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(scan),
+        Err(err) => {
+            return Err(err).with_context(|| format!("read metadata for '{}'", dir.display()))
+        }
+    };
+    // The batch progress is reported in chunks, rather than for every file.
+    const COUNT_CHUNK: u64 = 256;
+    let mut counted = (0, 0);
+
+    let mut inodes: HashSet<(u64, u64)> = HashSet::new();
+    let root = dir.clone();
+    walk_dir(dir, |path, entry| {
+        interrupt::check()?;
+        if !ignores.is_empty() {
+            let rel = path.strip_prefix(&root).unwrap_or(path);
+            let rel = rel.to_string_lossy();
+            if ignores.iter().any(|pattern| pattern.matches(&rel)) {
+                return Ok(false);
+            }
+        }
+        let stat = entry
+            .stat()
+            .with_context(|| format!("read metadata for '{}'", path.display()))?;
+        if one_file_system && entry.is_dir() && stat.dev != root_dev {
+            scan.mounts.push(path.clone());
+            return Ok(false);
+        }
+        if !inodes.insert((stat.dev, stat.ino)) {
+            return Ok(true);
+        }
+        // The `st_blocks` is always in 512-byte units.
+        let disk_size = stat.blocks * 512;
+        if stat.nlink > 1 && !entry.is_dir() {
+            scan.links.push(HardLink {
+                dev: stat.dev,
+                ino: stat.ino,
+                size: stat.size,
+                disk_size,
+                is_file: entry.is_file(),
+            });
+        }
+        let usage = &mut scan.usage;
+        usage.inodes += 1;
+        if entry.is_file() {
+            usage.size += stat.size;
+            usage.disk_size += disk_size;
+            usage.files += 1;
+            if usage.files - counted.1 >= COUNT_CHUNK {
+                progress::count(usage.size - counted.0, usage.files - counted.1);
+                counted = (usage.size, usage.files);
+            }
         }
         Ok(true)
     })?;
+    let usage = &scan.usage;
+    progress::count(usage.size - counted.0, usage.files - counted.1);
+
+    scan.links.sort_unstable_by_key(|link| (link.dev, link.ino));
+    Ok(scan)
+}
+
+/// Parse a human-readable size to bytes, the unit can be `B`, `K`, `M`, `G`,
+/// `T` (case-insensitive, `KB`, `KiB` are also accepted), all in 1024-based.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(parse_bytes("512").unwrap(), 512)
+/// assert_eq!(parse_bytes("2KiB").unwrap(), 2048)
+/// assert_eq!(parse_bytes("1.5G").unwrap(), 1610612736)
+/// ```
+pub fn parse_bytes(s: impl AsRef<str>) -> Result<u64> {
+    const BYTES_REGEX: &str = r"^(\d+(?:\.\d+)?)\s*([kmgt]?)(?:i?b)?$";
+    let re = Regex::new(BYTES_REGEX).expect("parse bytes regex");
+    let lower = s.as_ref().trim().to_lowercase();
+    let caps = match re.captures(&lower) {
+        Some(caps) => caps,
+        None => bail!(
+            "invalid size '{}', the format should be <number>[B|K|M|G|T]",
+            style(s.as_ref()).yellow()
+        ),
+    };
 
-    Ok(total_size)
+    let number: f64 = caps[1]
+        .parse()
+        .with_context(|| format!("invalid size number '{}'", style(&caps[1]).yellow()))?;
+    let unit: u64 = match &caps[2] {
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => 1,
+    };
+    Ok((number * unit as f64) as u64)
 }
 
 /// Convert a size to a human-readable string, for example, "32KB".
@@ -360,11 +645,15 @@ pub fn remove_dir_recursively(path: PathBuf, display: bool) -> Result<()> {
     }
     fs::remove_dir_all(&path).context("remove directory")?;
 
-    let dir = path.parent();
-    if dir.is_none() {
-        return Ok(());
+    match path.parent() {
+        Some(dir) => remove_empty_dirs(dir, display),
+        None => Ok(()),
     }
-    let mut dir = dir.unwrap();
+}
+
+/// Remove the directory if it is empty, and then its empty parents.
+pub fn remove_empty_dirs(dir: &Path, display: bool) -> Result<()> {
+    let mut dir = dir;
     loop {
         match fs::read_dir(dir) {
             Ok(dir_read) => {
@@ -387,6 +676,16 @@ pub fn remove_dir_recursively(path: PathBuf, display: bool) -> Result<()> {
     }
 }
 
+/// Quote the value as a single shell word. The values only containing the safe
+/// characters are kept as they are, for readability.
+pub fn shell_quote(value: &str) -> String {
+    let is_safe = |ch: char| ch.is_ascii_alphanumeric() || "_-./:@%+=,".contains(ch);
+    if !value.is_empty() && value.chars().all(is_safe) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r#"'\''"#))
+}
+
 /// Parse labels from string to set.
 pub fn parse_labels_str(str: impl AsRef<str>) -> HashSet<String> {
     str.as_ref().split(',').map(|s| s.to_string()).collect()
@@ -399,6 +698,21 @@ pub fn parse_labels(labels: &Option<String>) -> Option<HashSet<String>> {
         .map(|s| s.split(',').map(|s| s.to_string()).collect())
 }
 
+/// Return the frecency score by the access count and the last access time, see
+/// [`crate::repo::Repo::score`].
+pub fn frecency(now: u64, accessed: u64, last_accessed: u64) -> u64 {
+    let duration = now.saturating_sub(last_accessed);
+    if duration < HOUR {
+        accessed * 16
+    } else if duration < DAY {
+        accessed * 8
+    } else if duration < WEEK {
+        accessed * 2
+    } else {
+        accessed
+    }
+}
+
 /// Get home dir, that is env $HOME. Not supported on Windows.
 pub fn get_home_dir() -> Result<PathBuf> {
     match env::var_os("HOME") {
@@ -469,6 +783,26 @@ mod utils_tests {
         }
     }
 
+    #[test]
+    fn test_parse_bytes() {
+        let cases = [
+            ("512", 512),
+            ("512B", 512),
+            ("2k", 2048),
+            ("2KiB", 2048),
+            ("10 MB", 10 << 20),
+            ("1.5G", 3 << 29),
+            ("1T", 1 << 40),
+        ];
+        for (str, expect) in cases {
+            assert_eq!(parse_bytes(str).unwrap(), expect, "{str}");
+        }
+
+        assert!(parse_bytes("").is_err());
+        assert!(parse_bytes("1X").is_err());
+        assert!(parse_bytes("MB").is_err());
+    }
+
     #[test]
     fn test_remove_dir_recursively() {
         const PATH: &str = "/tmp/test-roxide/sub01/sub02/sub03";
@@ -486,6 +820,26 @@ mod utils_tests {
     fn test_walk_dir() {
         let cfg = config_tests::load_test_config("utils/format_time");
         let path = cfg.get_current_dir().clone();
-        walk_dir(path, |_path, _meta| Ok(true)).unwrap();
+        walk_dir(path, |_path, _entry| Ok(true)).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_hard_links() {
+        let root = PathBuf::from("/tmp/test-roxide/dedup_hard_links");
+        let _ = fs::remove_dir_all(&root);
+        write_file(&root.join("a").join("shared"), b"0123456789").unwrap();
+        write_file(&root.join("a").join("own"), b"01234").unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::hard_link(root.join("a/shared"), root.join("b/shared")).unwrap();
+
+        let a = dir_usage_with(root.join("a"), &[], false).unwrap();
+        let b = dir_usage_with(root.join("b"), &[], false).unwrap();
+        assert_eq!(a.links.len(), 1);
+        assert_eq!(b.links, a.links);
+
+        let total = UsageScan::dedup_total([&a, &b]);
+        assert_eq!(total.size, 15);
+        assert_eq!(total.files, 2);
+        assert_eq!(total.inodes, a.usage.inodes);
     }
 }
@@ -1,11 +1,18 @@
 use std::borrow::Cow;
+use std::io::{Read, Write};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, path::PathBuf};
+use std::{fs, io};
 
 use anyhow::Result;
 use anyhow::{bail, Context};
 use console::style;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
 
 use crate::batch::Task;
 use crate::config::Config;
@@ -17,9 +24,10 @@ use crate::config::WorkflowFromRepo;
 use crate::config::WorkflowOS;
 use crate::config::WorkflowStep;
 use crate::exec::Cmd;
-use crate::info;
+use crate::filelock::FileLock;
 use crate::repo::Repo;
-use crate::{exec, utils};
+use crate::{errors, exec, secret, utils};
+use crate::{info, warn};
 
 struct StepContext<'a> {
     env_readonly: HashMap<&'a str, &'a str>,
@@ -31,6 +39,9 @@ struct StepContext<'a> {
 
     docker: &'a Docker,
 
+    vars: &'a HashMap<String, String>,
+    secrets: &'a [(String, String)],
+
     display: bool,
 
     op: StepOperation<'a>,
@@ -96,7 +107,7 @@ impl StepOperation<'_> {
 }
 
 enum StepResult {
-    Cmd(Cmd),
+    Cmd(Box<Cmd>),
 
     File,
     SetEnv,
@@ -126,24 +137,36 @@ impl StepContext<'_> {
         }
 
         match self.op {
-            StepOperation::Run(run) => Ok(StepResult::Cmd(Cmd::sh(run, self.display))),
+            StepOperation::Run(run) => {
+                let run = render_template(run, self.vars, 1);
+                Ok(StepResult::Cmd(Box::new(Cmd::sh(run, self.display))))
+            }
             StepOperation::Ssh(ssh, run) => {
-                let args = ["ssh", ssh, run];
-                Ok(StepResult::Cmd(Cmd::sh(args.join(" "), self.display)))
+                // The script is parsed by the local shell, and then by the
+                // remote shell, so the values are quoted twice.
+                let run = render_template(run, self.vars, 2);
+                let args = ["ssh", ssh, run.as_ref()];
+                Ok(StepResult::Cmd(Box::new(Cmd::sh(
+                    args.join(" "),
+                    self.display,
+                ))))
             }
             StepOperation::DockerRun(image, run) => {
-                Ok(StepResult::Cmd(self.build_docker_run(image, run)?))
+                let run = render_template(run, self.vars, 1);
+                Ok(StepResult::Cmd(Box::new(
+                    self.build_docker_run(image, &run)?,
+                )))
             }
             StepOperation::DockerPush(image) => {
                 let image = self.expandenv(image)?;
                 let args = vec!["push", image.as_ref()];
-                Ok(StepResult::Cmd(self.build_docker_cmd(&args)))
+                Ok(StepResult::Cmd(Box::new(self.build_docker_cmd(&args))))
             }
             StepOperation::DockerBuild(file, image) => {
                 let file = self.expandenv(file)?;
                 let image = self.expandenv(image)?;
                 let args = vec!["build", "-f", file.as_ref(), "-t", image.as_ref(), "."];
-                Ok(StepResult::Cmd(self.build_docker_cmd(&args)))
+                Ok(StepResult::Cmd(Box::new(self.build_docker_cmd(&args))))
             }
             StepOperation::SetEnv(key, value) => {
                 let value = self.expandenv(value)?;
@@ -152,7 +175,7 @@ impl StepContext<'_> {
             }
             StepOperation::File(content) => {
                 let path = self.path.join(&self.cfg.name);
-                let content = content.replace("\\t", "\t");
+                let content = render_template(content, self.vars, 0).replace("\\t", "\t");
                 utils::write_file(&path, content.as_bytes())?;
                 Ok(StepResult::File)
             }
@@ -208,13 +231,19 @@ impl StepContext<'_> {
         for (key, value) in self.env_mut.iter() {
             envs.push((key, value));
         }
-        envs.sort_unstable_by(|(key0, _), (key1, _)| key0.cmp(key1));
+        envs.sort_unstable_by_key(|(key0, _)| *key0);
 
         for (key, value) in envs {
             let env = format!("{key}={value}");
             args.push(Cow::Borrowed("-e"));
             args.push(Cow::Owned(env));
         }
+        // Only pass the names of the secrets, the values are taken from the
+        // env of docker command, to keep them out of the process arguments.
+        for (key, _) in self.secrets.iter() {
+            args.push(Cow::Borrowed("-e"));
+            args.push(Cow::Borrowed(key));
+        }
 
         args.push(Cow::Borrowed("--entrypoint"));
         args.push(Cow::Borrowed(&self.docker.shell));
@@ -263,6 +292,9 @@ impl StepContext<'_> {
         for (key, value) in self.env_mut.iter() {
             cmd.with_env(key, value);
         }
+        for (key, value) in self.secrets.iter() {
+            cmd.with_secret_env(key, value);
+        }
         cmd
     }
 
@@ -306,10 +338,84 @@ pub struct Workflow<C: AsRef<WorkflowConfig>> {
     display: bool,
 
     docker: Docker,
+
+    vars: HashMap<String, String>,
+    secrets: Vec<(String, String)>,
+
+    repo: String,
+    name: String,
+    now: u64,
+
+    history: Option<Arc<Mutex<Vec<WorkflowRecord>>>>,
 }
 
 impl<C: AsRef<WorkflowConfig>> Task<()> for Workflow<C> {
     fn run(&self) -> Result<()> {
+        let start = Instant::now();
+        let mut steps = Vec::with_capacity(self.cfg.as_ref().steps.len());
+        let result = self.run_steps(&mut steps);
+
+        if let Some(history) = self.history.as_ref() {
+            let record = WorkflowRecord {
+                id: 0,
+                repo: self.repo.clone(),
+                name: self.name.clone(),
+                time: self.now,
+                elapsed_ms: start.elapsed().as_millis() as u64,
+                error: result.as_ref().err().map(|err| format!("{err:#}")),
+                steps,
+            };
+            history.lock().unwrap().push(record);
+        }
+
+        result
+    }
+}
+
+impl<C: AsRef<WorkflowConfig>> Workflow<C> {
+    pub fn new(
+        cfg: &Config,
+        repo: &Repo,
+        name: impl ToString,
+        workflow: C,
+        display: bool,
+    ) -> Result<Workflow<C>> {
+        let path = repo.get_path(cfg);
+        let vars = build_vars(repo, workflow.as_ref(), &path);
+        let secrets = load_secrets(workflow.as_ref(), &path)?;
+        let env = build_env(repo, &workflow.as_ref().env, &path);
+        let step_env: Vec<_> = workflow
+            .as_ref()
+            .steps
+            .iter()
+            .map(|step_cfg| build_env(repo, &step_cfg.env, &path))
+            .collect();
+        let docker = cfg.docker.clone();
+
+        Ok(Workflow {
+            path,
+            cfg: workflow,
+            env,
+            step_env,
+            display,
+            docker,
+            vars,
+            secrets,
+            repo: repo.name_with_remote(),
+            name: name.to_string(),
+            now: cfg.now(),
+            history: None,
+        })
+    }
+
+    /// Record the workflow result to the history after running. See:
+    /// [`WorkflowHistory`].
+    pub fn with_history(mut self, history: Arc<Mutex<Vec<WorkflowRecord>>>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    fn run_steps(&self, records: &mut Vec<StepRecord>) -> Result<()> {
         let mut env_mut = HashMap::new();
 
         let mut ops = Vec::with_capacity(self.cfg.as_ref().steps.len());
@@ -341,15 +447,33 @@ impl<C: AsRef<WorkflowConfig>> Task<()> for Workflow<C> {
                 path: &self.path,
                 display: self.display,
                 docker: &self.docker,
+                vars: &self.vars,
+                secrets: &self.secrets,
                 op: ops.remove(0),
             };
-            let result = ctx.run()?;
-            let msg: Cow<str> = match result {
-                StepResult::Cmd(cmd) => self.run_cmd(&mut ctx, cmd)?,
-                StepResult::SetEnv => Cow::Borrowed("Set env done"),
-                StepResult::Skip(msg) => Cow::Owned(format!("Skip: {}", style(msg).bold())),
-                StepResult::File => Cow::Borrowed("Write to file done"),
+
+            let mut outcome = CmdOutcome::default();
+            let result = ctx.run().and_then(|result| match result {
+                StepResult::Cmd(cmd) => self.run_cmd(&mut ctx, *cmd, &mut outcome),
+                StepResult::SetEnv => Ok(Cow::Borrowed("Set env done")),
+                StepResult::Skip(msg) => Ok(Cow::Owned(format!("Skip: {}", style(msg).bold()))),
+                StepResult::File => Ok(Cow::Borrowed("Write to file done")),
+            });
+
+            let (message, error) = match result.as_ref() {
+                Ok(msg) => (console::strip_ansi_codes(msg).to_string(), None),
+                Err(err) => (String::new(), Some(self.mask_secrets(&format!("{err:#}")))),
             };
+            let log = self.mask_secrets(&String::from_utf8_lossy(&outcome.log));
+            records.push(StepRecord {
+                name: step_cfg.name.clone(),
+                message,
+                error,
+                log: StepRecord::compress_log(log.as_bytes()),
+                attempts: outcome.attempts,
+                timed_out: outcome.timed_out,
+            });
+            let msg = result?;
 
             if self.display {
                 if !msg.is_empty() {
@@ -363,58 +487,85 @@ impl<C: AsRef<WorkflowConfig>> Task<()> for Workflow<C> {
 
         Ok(())
     }
-}
 
-impl<C: AsRef<WorkflowConfig>> Workflow<C> {
-    pub fn new(cfg: &Config, repo: &Repo, workflow: C, display: bool) -> Workflow<C> {
-        let path = repo.get_path(cfg);
-        let env = build_env(repo, &workflow.as_ref().env, &path);
-        let step_env: Vec<_> = workflow
-            .as_ref()
-            .steps
-            .iter()
-            .map(|step_cfg| build_env(repo, &step_cfg.env, &path))
-            .collect();
-        let docker = cfg.docker.clone();
-
-        Workflow {
-            path,
-            cfg: workflow,
-            env,
-            step_env,
-            display,
-            docker,
-        }
-    }
-
-    fn run_cmd(&self, ctx: &mut StepContext, cmd: Cmd) -> Result<Cow<str>> {
+    fn run_cmd(
+        &self,
+        ctx: &mut StepContext,
+        cmd: Cmd,
+        outcome: &mut CmdOutcome,
+    ) -> Result<Cow<'_, str>> {
         let capture_output = ctx.cfg.capture_output.clone();
 
         let mut cmd = ctx.setup_cmd(cmd);
-        let result = (|| -> Result<Cow<str>> {
-            match capture_output {
-                Some(env_name) => {
-                    let output = cmd.read()?;
+        if let Some(timeout) = ctx.cfg.timeout_secs {
+            cmd.with_timeout(Duration::from_secs(timeout));
+        }
+
+        let mut attempt = 0;
+        let result = loop {
+            let result = self.execute_cmd(&mut cmd, capture_output.is_some(), &mut outcome.log);
+            outcome.attempts = attempt + 1;
+            outcome.timed_out = matches!(&result, Err(err) if errors::is_timeout(err));
+            let result = match capture_output.as_ref() {
+                Some(env_name) => result.map(|output| {
                     let msg = format!("Capture the command output to env '{}'", env_name.as_str());
-                    ctx.env_mut.insert(env_name, output);
-                    Ok(Cow::Owned(msg))
-                }
-                None => {
-                    cmd.execute()?;
-                    Ok(Cow::Borrowed(""))
-                }
+                    ctx.env_mut.insert(env_name.clone(), output);
+                    Cow::Owned(msg)
+                }),
+                None => result.map(|_| Cow::Borrowed("")),
+            };
+            if result.is_ok() || attempt >= ctx.cfg.retries {
+                break result;
             }
-        })();
 
-        if result.is_err() && ctx.cfg.allow_failure {
-            return Ok(Cow::Borrowed("Ignore step failure"));
+            attempt += 1;
+            if self.display {
+                warn!("Step failed, retry ({attempt}/{})", ctx.cfg.retries);
+            }
+        };
+
+        match result {
+            Err(err) if ctx.cfg.allow_failure => Ok(Cow::Owned(format!(
+                "Ignore step failure: {}",
+                style(format!("{err:#}")).yellow()
+            ))),
+            result => result,
+        }
+    }
+
+    /// Replace the secret values in the text saved to history.
+    fn mask_secrets(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (_, value) in self.secrets.iter() {
+            if !value.is_empty() {
+                text = text.replace(value, "******");
+            }
         }
+        text
+    }
 
-        result
+    /// Execute the command, return its stdout if `read` is true. The command
+    /// output is saved to `log` so that it can be reviewed in the history later,
+    /// when the workflow is displayed, the output is copied to the terminal as
+    /// well.
+    fn execute_cmd(&self, cmd: &mut Cmd, read: bool, log: &mut Vec<u8>) -> Result<String> {
+        if self.display {
+            cmd.with_tee(!read);
+        }
+
+        let result = cmd.execute_unchecked()?;
+        log.extend_from_slice(result.stdout.as_bytes());
+        log.extend_from_slice(result.stderr.as_bytes());
+        if read {
+            return result.read();
+        }
+        result.check()?;
+        Ok(String::new())
     }
 }
 
-fn build_env(repo: &Repo, env_cfg: &[WorkflowEnv], path: &Path) -> HashMap<String, String> {
+/// Build the env map, values with `from_repo` will be filled with the repo's info.
+pub fn build_env(repo: &Repo, env_cfg: &[WorkflowEnv], path: &Path) -> HashMap<String, String> {
     let mut map = HashMap::with_capacity(env_cfg.len());
     for env in env_cfg.iter() {
         let key = env.name.clone();
@@ -429,6 +580,10 @@ fn build_env(repo: &Repo, env_cfg: &[WorkflowEnv], path: &Path) -> HashMap<Strin
                     None => String::new(),
                 },
                 WorkflowFromRepo::Path => format!("{}", path.display()),
+                WorkflowFromRepo::Token => match repo.remote_cfg.token.as_ref() {
+                    Some(token) => utils::expandenv(token).unwrap_or_default(),
+                    None => String::new(),
+                },
             };
             let from_repo = if from_repo.is_empty() {
                 match env.value.as_ref() {
@@ -446,15 +601,422 @@ fn build_env(repo: &Repo, env_cfg: &[WorkflowEnv], path: &Path) -> HashMap<Strin
     map
 }
 
+/// Build the variables for the templates: the custom `vars` of the workflow,
+/// and the builtin `repo`, `owner`, `remote`, `path` and `branch` (which take
+/// precedence).
+fn build_vars(repo: &Repo, workflow: &WorkflowConfig, path: &Path) -> HashMap<String, String> {
+    let mut vars = workflow.vars.clone();
+    vars.insert(String::from("repo"), repo.name.to_string());
+    vars.insert(String::from("owner"), repo.owner.to_string());
+    vars.insert(String::from("remote"), repo.remote.to_string());
+    vars.insert(String::from("path"), format!("{}", path.display()));
+
+    // Only ask git for the branch when it is used, since this is called for
+    // every repo in batch.
+    let use_branch = workflow.steps.iter().any(|step| {
+        [step.run.as_ref(), step.file.as_ref()]
+            .into_iter()
+            .flatten()
+            .any(|text| uses_template_var(text, "branch"))
+    });
+    let branch = if use_branch && path.exists() {
+        let path = format!("{}", path.display());
+        Cmd::git(&["-C", path.as_str(), "branch", "--show-current"])
+            .read()
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    vars.insert(String::from("branch"), branch);
+    vars
+}
+
+/// Decrypt the secrets of the workflow, see: [`crate::secret::read_secret`].
+fn load_secrets(workflow: &WorkflowConfig, path: &Path) -> Result<Vec<(String, String)>> {
+    let mut secrets = Vec::with_capacity(workflow.secrets.len());
+    for secret_cfg in workflow.secrets.iter() {
+        let file = PathBuf::from(utils::expandenv(&secret_cfg.file)?);
+        let file = if file.is_absolute() {
+            file
+        } else {
+            path.join(file)
+        };
+        let value = secret::read_secret(&file)
+            .with_context(|| format!("load secret '{}'", secret_cfg.name))?;
+        let value = value.trim_end_matches(['\n', '\r']).to_string();
+        secrets.push((secret_cfg.name.clone(), value));
+    }
+    Ok(secrets)
+}
+
+/// Render the template, replace `{{ name }}` with the variable. The braces
+/// whose content is not a variable are kept as they are, so that the scripts
+/// using them for other purposes (such as `docker inspect -f '{{.Id}}'`) are
+/// not affected.
+///
+/// The values (such as the branch name) may contain shell syntax, for the
+/// scripts, they are quoted `quote_level` times (the number of shells parsing
+/// the script), so that they are always treated as plain words. The templates
+/// should not be quoted again in the scripts.
+pub fn render_template<'a>(
+    text: &'a str,
+    vars: &HashMap<String, String>,
+    quote_level: usize,
+) -> Cow<'a, str> {
+    replace_template(text, |name| {
+        let mut value = Cow::Borrowed(vars.get(name)?.as_str());
+        for _ in 0..quote_level {
+            value = Cow::Owned(utils::shell_quote(&value));
+        }
+        Some(value.into_owned())
+    })
+}
+
+/// Return `true` if the text uses the variable `name` as template, see
+/// [`render_template`].
+fn uses_template_var(text: &str, name: &str) -> bool {
+    let mut used = false;
+    replace_template(text, |key| {
+        used |= key == name;
+        None
+    });
+    used
+}
+
+/// Replace `{{ name }}` with the value returned by `replace`, keep it as it is
+/// if [`None`] is returned.
+fn replace_template<F>(text: &str, mut replace: F) -> Cow<'_, str>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    if !text.contains("{{") {
+        return Cow::Borrowed(text);
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after
+            .find("}}")
+            .and_then(|end| replace(after[..end].trim()).map(|value| (end, value)));
+        match value {
+            Some((end, value)) => {
+                result.push_str(&value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                result.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    Cow::Owned(result)
+}
+
 impl Workflow<Arc<WorkflowConfig>> {
-    pub fn load_for_batch(cfg: &Config, repo: &Repo, workflow: Arc<WorkflowConfig>) -> Self {
-        Workflow::new(cfg, repo, workflow, false)
+    pub fn load_for_batch(
+        cfg: &Config,
+        repo: &Repo,
+        name: impl ToString,
+        workflow: Arc<WorkflowConfig>,
+    ) -> Result<Self> {
+        Workflow::new(cfg, repo, name, workflow, false)
     }
 }
 
 impl<'a> Workflow<Cow<'a, WorkflowConfig>> {
     pub fn load(name: impl AsRef<str>, cfg: &'a Config, repo: &Repo) -> Result<Self> {
         let workflow = cfg.get_workflow(name.as_ref())?;
-        Ok(Workflow::new(cfg, repo, workflow, true))
+        Workflow::new(cfg, repo, name.as_ref(), workflow, true)
+    }
+}
+
+/// The output and outcome of running the step command, see [`StepRecord`].
+#[derive(Default)]
+struct CmdOutcome {
+    log: Vec<u8>,
+    attempts: u32,
+    timed_out: bool,
+}
+
+/// The result of a workflow run, saved in [`WorkflowHistory`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkflowRecord {
+    pub id: u64,
+
+    pub repo: String,
+    pub name: String,
+
+    pub time: u64,
+    pub elapsed_ms: u64,
+
+    /// The failure reason, [`None`] means the workflow succeeded.
+    pub error: Option<String>,
+
+    pub steps: Vec<StepRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub name: String,
+    pub message: String,
+    pub error: Option<String>,
+
+    /// The gzip compressed command output (stdout and stderr).
+    pub log: Vec<u8>,
+
+    /// The times the command was run, including the retries (see
+    /// `retries` of the step), 0 means the step has no command.
+    pub attempts: u32,
+    /// The command was killed for timeout in the last attempt (see
+    /// `timeout_secs` of the step).
+    pub timed_out: bool,
+}
+
+impl StepRecord {
+    /// The max size of the log to save, the exceeded part will be truncated (the
+    /// tail is reserved, since it is usually more valuable for debugging).
+    const LOG_LIMIT: usize = 64 * 1024;
+
+    fn compress_log(log: &[u8]) -> Vec<u8> {
+        if log.is_empty() {
+            return Vec::new();
+        }
+        let log = if log.len() > Self::LOG_LIMIT {
+            &log[log.len() - Self::LOG_LIMIT..]
+        } else {
+            log
+        };
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        match encoder.write_all(log) {
+            Ok(_) => encoder.finish().unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Decompress and return the log of this step.
+    pub fn get_log(&self) -> Result<String> {
+        if self.log.is_empty() {
+            return Ok(String::new());
+        }
+        let mut decoder = GzDecoder::new(self.log.as_slice());
+        let mut log = Vec::new();
+        decoder
+            .read_to_end(&mut log)
+            .with_context(|| format!("decompress log for step '{}'", self.name))?;
+        Ok(String::from_utf8_lossy(&log).into_owned())
+    }
+}
+
+/// The history of workflow runs, stored in `{metadir}/workflow_history`. Only the
+/// latest [`WorkflowHistory::LIMIT`] records will be kept.
+pub struct WorkflowHistory {
+    path: PathBuf,
+
+    records: Arc<Mutex<Vec<WorkflowRecord>>>,
+}
+
+impl WorkflowHistory {
+    const LIMIT: usize = 200;
+
+    pub fn new(cfg: &Config) -> WorkflowHistory {
+        WorkflowHistory {
+            path: cfg.get_meta_dir().join("workflow_history"),
+            records: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// The recorder to pass to [`Workflow::with_history`].
+    pub fn recorder(&self) -> Arc<Mutex<Vec<WorkflowRecord>>> {
+        Arc::clone(&self.records)
+    }
+
+    /// Append the new records to the history file, return the ids of the failed
+    /// ones.
+    pub fn save(&self, cfg: &Config) -> Result<Vec<u64>> {
+        let mut new_records = self.records.lock().unwrap();
+        if new_records.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let _lock = FileLock::acquire(cfg, "workflow_history")?;
+        let mut records = self.list()?;
+        let start_id = records.last().map(|record| record.id + 1).unwrap_or(1);
+        let mut failed = Vec::new();
+        for (id, mut record) in (start_id..).zip(new_records.drain(..)) {
+            record.id = id;
+            if record.error.is_some() {
+                failed.push(id);
+            }
+            records.push(record);
+        }
+        if records.len() > Self::LIMIT {
+            records.drain(..records.len() - Self::LIMIT);
+        }
+
+        let data = bincode::serialize(&records).context("encode workflow history")?;
+        utils::write_file(&self.path, &data)?;
+        Ok(failed)
+    }
+
+    /// Save the new records, and return the workflow `result`. The failure of
+    /// saving is only warned, so that it will not mask the workflow result.
+    pub fn finish<T>(&self, cfg: &Config, result: Result<T>) -> Result<T> {
+        match self.save(cfg) {
+            Ok(failed) => {
+                for id in failed {
+                    info!(
+                        "Use `roxide run -L {}` to view the logs of the failed workflow",
+                        id
+                    );
+                }
+            }
+            Err(err) => warn!("Save workflow history failed: {:#}", err),
+        }
+        result
+    }
+
+    /// List the saved records, in the order of running.
+    pub fn list(&self) -> Result<Vec<WorkflowRecord>> {
+        match fs::read(&self.path) {
+            Ok(data) => bincode::deserialize(&data).context("decode workflow history"),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err)
+                .with_context(|| format!("read workflow history file '{}'", self.path.display())),
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Result<WorkflowRecord> {
+        match self.list()?.into_iter().find(|record| record.id == id) {
+            Some(record) => Ok(record),
+            None => bail!("could not find workflow history {id}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod workflow_tests {
+    use crate::config::config_tests;
+    use crate::repo::database::database_tests;
+    use crate::workflow::*;
+
+    #[test]
+    fn test_history() {
+        let cfg = config_tests::load_test_config("workflow/history");
+        let history = WorkflowHistory::new(&cfg);
+        let _ = fs::remove_file(&history.path);
+
+        for i in 0..3 {
+            history.recorder().lock().unwrap().push(WorkflowRecord {
+                id: 0,
+                repo: String::from("github:fioncat/roxide"),
+                name: format!("test-{i}"),
+                time: cfg.now(),
+                elapsed_ms: 10,
+                error: (i == 1).then(|| String::from("exit 1")),
+                steps: vec![StepRecord {
+                    name: String::from("echo"),
+                    message: String::new(),
+                    error: None,
+                    log: StepRecord::compress_log(format!("hello {i}\n").as_bytes()),
+                    attempts: 1,
+                    timed_out: false,
+                }],
+            });
+        }
+        assert_eq!(history.save(&cfg).unwrap(), vec![2]);
+        assert!(history.save(&cfg).unwrap().is_empty());
+
+        let records = history.list().unwrap();
+        let ids: Vec<_> = records.iter().map(|record| record.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        let record = history.get(2).unwrap();
+        assert_eq!(record.name, "test-1");
+        assert_eq!(record.steps[0].get_log().unwrap(), "hello 1\n");
+    }
+
+    #[test]
+    fn test_step_outcome() {
+        let cfg = config_tests::load_test_config("workflow/step_outcome");
+        let repo = database_tests::get_test_repos(&cfg).remove(0);
+        fs::create_dir_all(repo.get_path(&cfg)).unwrap();
+        let workflow: WorkflowConfig = toml::from_str(
+            r#"
+[[steps]]
+name = "slow"
+run = "sleep 5"
+timeout_secs = 1
+retries = 1
+allow_failure = true
+
+[[steps]]
+name = "echo"
+run = "echo hello"
+"#,
+        )
+        .unwrap();
+
+        let history = WorkflowHistory::new(&cfg);
+        Workflow::new(&cfg, &repo, "test", Arc::new(workflow), false)
+            .unwrap()
+            .with_history(history.recorder())
+            .run()
+            .unwrap();
+
+        let records = history.recorder();
+        let records = records.lock().unwrap();
+        let steps = &records[0].steps;
+        assert_eq!((steps[0].attempts, steps[0].timed_out), (2, true));
+        assert!(
+            steps[0].message.contains("timed out"),
+            "{}",
+            steps[0].message
+        );
+        assert_eq!((steps[1].attempts, steps[1].timed_out), (1, false));
+        assert_eq!(steps[1].get_log().unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn test_render_template() {
+        let vars: HashMap<String, String> = [("repo", "roxide"), ("branch", "main")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let cases = [
+            ("cargo build", "cargo build"),
+            ("echo {{repo}}:{{ branch }}", "echo roxide:main"),
+            (
+                "docker inspect -f '{{.Id}}' {{ repo }}",
+                "docker inspect -f '{{.Id}}' roxide",
+            ),
+            ("{{ unknown }} {{repo", "{{ unknown }} {{repo"),
+        ];
+        for (text, expect) in cases {
+            assert_eq!(render_template(text, &vars, 1), expect);
+        }
+
+        assert!(uses_template_var("git push origin {{branch}}", "branch"));
+        assert!(uses_template_var("echo {{  branch }}", "branch"));
+        assert!(!uses_template_var("git branch -a # {{ repo }}", "branch"));
+
+        let vars: HashMap<String, String> = [("branch", "a$(touch x);`id`'b")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(
+            render_template("git push origin {{ branch }}", &vars, 0),
+            "git push origin a$(touch x);`id`'b"
+        );
+        let script = render_template("printf %s {{ branch }}", &vars, 1);
+        assert_eq!(Cmd::sh(script, false).read().unwrap(), "a$(touch x);`id`'b");
+        // Like ssh, join the args parsed by the local shell, and run them in
+        // another shell.
+        let script = render_template("printf %s {{ branch }}", &vars, 2);
+        let script = format!("printf '%s ' {script} | sh");
+        assert_eq!(Cmd::sh(script, false).read().unwrap(), "a$(touch x);`id`'b");
     }
 }